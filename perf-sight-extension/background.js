@@ -24,7 +24,14 @@ function formatCpu(cpuStr) {
 
 // WebSocket Connection
 let ws = null;
+// Last "data" message number sent (see the "seq" capability).
+let dataSeq = 0;
 const WS_URL = "ws://127.0.0.1:23333";
+// Must stay within the range PerfSight accepts (see WS_PROTOCOL_MIN/MAX in ws_server.rs).
+const PROTOCOL_VERSION = 1;
+// memory_mb: process memory is sent in MB.
+// seq: each "data" message carries a consecutive number so PerfSight can count lost messages.
+const CAPABILITIES = ["memory_mb", "console_log", "custom_metric", "seq"];
 
 function connectWebSocket() {
     if (ws && (ws.readyState === WebSocket.OPEN || ws.readyState === WebSocket.CONNECTING)) return;
@@ -32,11 +39,21 @@ function connectWebSocket() {
     console.log(`Connecting to PerfSight at ${WS_URL}...`);
     ws = new WebSocket(WS_URL);
 
-    ws.onopen = () => console.log("✅ Connected to PerfSight!");
+    ws.onopen = () => {
+        // The server drops connections that don't start with a hello.
+        ws.send(JSON.stringify({
+            type: "hello",
+            protocol_version: PROTOCOL_VERSION,
+            extension_version: chrome.runtime.getManifest().version,
+            capabilities: CAPABILITIES
+        }));
+        console.log("✅ Connected to PerfSight!");
+    };
     ws.onerror = (e) => {
         // console.log("WS Error (PerfSight might not be running)"); 
     };
-    ws.onclose = () => {
+    ws.onclose = (e) => {
+        if (e.reason) console.warn(`PerfSight closed the connection: ${e.reason}`);
         // console.log("WS Closed. Retrying in 2s...");
         setTimeout(connectWebSocket, 2000);
     };
@@ -54,6 +71,8 @@ chrome.runtime.onMessage.addListener((message, sender, sendResponse) => {
             // Try to resolve Tab ID to OS Process ID
             const tabId = sender.tab ? sender.tab.id : null;
             if (tabId) {
+                // Lets PerfSight attribute the log to the tab's renderer even when no PID resolves.
+                message.tab_id = tabId;
                 chrome.processes.getProcessIdForTab(tabId, (processId) => {
                     // processId is Chrome's internal ID
                     chrome.processes.getProcessInfo([processId], false, (infos) => {
@@ -115,8 +134,10 @@ function handleProcessUpdate(processes) {
 
     // Send to PerfSight
     if (ws && ws.readyState === WebSocket.OPEN) {
+        dataSeq += 1;
         const message = {
             type: "data", // Matches Python sidecar 'type'
+            seq: dataSeq,
             timestamp: Date.now(),
             metrics: metricsPayload
         };
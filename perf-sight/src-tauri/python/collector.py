@@ -4,13 +4,22 @@ import time
 import psutil
 import threading
 
+# Reported for the "version" action and saved with each report's data sources.
+COLLECTOR_VERSION = "4.7"
+
 # Global state
 config = {
     "running": False,
     "pids": [],
-    "interval": 1.0
+    "interval": 1.0,
+    # "total_capacity" | "per_core_sum"; None keeps the per-OS default below.
+    "cpu_normalization": None,
+    # "rss" | "task_manager" (private working set on Windows, PSS on Linux).
+    "memory_standard": "rss"
 }
 config_lock = threading.Lock()
+# Set by "set_interval" so a shorter interval applies without waiting out the current sleep.
+interval_changed = threading.Event()
 
 def get_cpu_count():
     try:
@@ -20,6 +29,59 @@ def get_cpu_count():
 
 CPU_COUNT = get_cpu_count()
 IS_DARWIN = sys.platform == "darwin"
+IS_WINDOWS = sys.platform == "win32"
+IS_LINUX = sys.platform.startswith("linux")
+
+def windows_private_working_set(pid):
+    """Task Manager's "Memory" column (private working set) in bytes, or None.
+    PrivateWorkingSetSize needs Windows 10 1809+; older systems report 0."""
+    import ctypes
+    from ctypes import wintypes
+
+    class PROCESS_MEMORY_COUNTERS_EX2(ctypes.Structure):
+        _fields_ = [
+            ("cb", wintypes.DWORD),
+            ("PageFaultCount", wintypes.DWORD),
+            ("PeakWorkingSetSize", ctypes.c_size_t),
+            ("WorkingSetSize", ctypes.c_size_t),
+            ("QuotaPeakPagedPoolUsage", ctypes.c_size_t),
+            ("QuotaPagedPoolUsage", ctypes.c_size_t),
+            ("QuotaPeakNonPagedPoolUsage", ctypes.c_size_t),
+            ("QuotaNonPagedPoolUsage", ctypes.c_size_t),
+            ("PagefileUsage", ctypes.c_size_t),
+            ("PeakPagefileUsage", ctypes.c_size_t),
+            ("PrivateUsage", ctypes.c_size_t),
+            ("PrivateWorkingSetSize", ctypes.c_size_t),
+            ("SharedCommitUsage", ctypes.c_ulonglong),
+        ]
+
+    PROCESS_QUERY_LIMITED_INFORMATION = 0x1000
+    kernel32 = ctypes.WinDLL("kernel32", use_last_error=True)
+    kernel32.OpenProcess.restype = wintypes.HANDLE
+    handle = kernel32.OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, False, pid)
+    if not handle:
+        return None
+    try:
+        counters = PROCESS_MEMORY_COUNTERS_EX2()
+        counters.cb = ctypes.sizeof(counters)
+        if not kernel32.K32GetProcessMemoryInfo(handle, ctypes.byref(counters), counters.cb):
+            return None
+        return counters.PrivateWorkingSetSize or None
+    finally:
+        kernel32.CloseHandle(handle)
+
+def task_manager_memory(proc):
+    """(bytes, basis) of what the OS task manager shows for proc, or None to keep the default."""
+    try:
+        if IS_WINDOWS:
+            private_ws = windows_private_working_set(proc.pid)
+            if private_ws is not None:
+                return private_ws, "private_working_set"
+        elif IS_LINUX:
+            return proc.memory_full_info().pss, "pss"
+    except (psutil.AccessDenied, AttributeError, OSError):
+        pass
+    return None
 
 def collect_metrics():
     """Main collection loop running in a separate thread."""
@@ -30,17 +92,28 @@ def collect_metrics():
     # CPU calculation cache: pid -> (last_time, last_total_cpu_time)
     cpu_state = {}
 
+    # Per-thread CPU times: pid -> (last_time, {thread_id: cpu_time})
+    thread_state = {}
+
+    # Sequence number of the last "data" message written.
+    data_seq = 0
+
     while True:
         with config_lock:
             running = config["running"]
             target_pids = list(config["pids"])
             interval = config["interval"]
+            per_core_sum = config["cpu_normalization"] == "per_core_sum" or (
+                config["cpu_normalization"] is None and IS_DARWIN
+            )
+            task_manager = config["memory_standard"] == "task_manager"
 
         if not running:
             # Clear caches to avoid stale data on resume
             if not target_pids:
                 process_cache.clear()
                 cpu_state.clear()
+                thread_state.clear()
             time.sleep(0.1)
             continue
 
@@ -52,6 +125,9 @@ def collect_metrics():
         for pid in list(cpu_state.keys()):
             if pid not in target_pid_set:
                 del cpu_state[pid]
+        for pid in list(thread_state.keys()):
+            if pid not in target_pid_set:
+                del thread_state[pid]
 
         timestamp = int(time.time() * 1000)
         now = time.time()
@@ -81,15 +157,18 @@ def collect_metrics():
                             # - Windows Task Manager: typically normalized to total CPU capacity (0-100%)
                             # - macOS Activity Monitor: typically sums per-core usage (can exceed 100%)
                             #
-                            # So:
+                            # So by default:
                             # - On macOS, do NOT divide by CPU_COUNT (match Activity Monitor).
                             # - On Windows/Linux, divide by CPU_COUNT (match Task Manager style).
+                            # The "cpu_normalization" start option overrides this.
                             cpu_raw = (delta_cpu / delta_time) * 100
-                            cpu = cpu_raw if IS_DARWIN else (cpu_raw / CPU_COUNT)
+                            cpu = cpu_raw if per_core_sum else (cpu_raw / CPU_COUNT)
                         else:
+                            cpu_raw = 0.0
                             cpu = 0.0
                     else:
                         # First run for this PID: cannot calc delta yet
+                        cpu_raw = 0.0
                         cpu = 0.0
                     
                     # Update state
@@ -97,16 +176,37 @@ def collect_metrics():
                     
                 except Exception as e:
                     # CPU access failed
+                    cpu_raw = 0.0
                     cpu = 0.0
                     # Remove from state to reset next time
                     if pid in cpu_state:
                         del cpu_state[pid]
 
+                # --- Busiest thread: percent of one core, never normalized ---
+                max_thread_cpu = None
+                try:
+                    threads = {t.id: t.user_time + t.system_time for t in proc.threads()}
+                    if pid in thread_state:
+                        last_time, last_threads = thread_state[pid]
+                        delta_time = now - last_time
+                        # Threads started since the last tick have no baseline yet.
+                        deltas = [max(0.0, total - last_threads[tid]) for tid, total in threads.items() if tid in last_threads]
+                        if delta_time > 0 and deltas:
+                            max_thread_cpu = max(deltas) / delta_time * 100
+                    thread_state[pid] = (now, threads)
+                except (psutil.AccessDenied, NotImplementedError):
+                    thread_state.pop(pid, None)
+
                 # --- Memory Logic ---
                 # User confirmed 'private' matches Chrome Task Manager best
                 mem_info = proc.memory_info()
                 # Windows: private (Commit Size), Linux/Mac: fallback to rss
                 private_mem = getattr(mem_info, 'private', mem_info.rss)
+                memory_basis = "private_commit" if hasattr(mem_info, 'private') else "rss"
+                # "task_manager" standard: what Task Manager / System Monitor shows instead.
+                tm = task_manager_memory(proc) if task_manager else None
+                if tm is not None:
+                    private_mem, memory_basis = tm
                 mem_mb = private_mem / 1024 / 1024
                 
                 # Log only if > 0 to reduce spam, or periodic? 
@@ -114,21 +214,29 @@ def collect_metrics():
                 
                 metrics[pid] = {
                     "cpu": round(cpu, 2),
+                    "cpu_raw": round(cpu_raw, 2),
                     "memory": round(mem_mb, 2),
+                    "memory_basis": memory_basis,
                 }
+                if max_thread_cpu is not None:
+                    metrics[pid]["max_thread_cpu"] = round(max_thread_cpu, 2)
                 has_data = True
                 
             except (psutil.NoSuchProcess, psutil.AccessDenied, psutil.ZombieProcess):
                 metrics[pid] = None
                 if pid in process_cache: del process_cache[pid]
                 if pid in cpu_state: del cpu_state[pid]
+                if pid in thread_state: del thread_state[pid]
             except Exception as e:
                 sys.stderr.write(f"PID={pid} Error: {e}\n")
                 metrics[pid] = None
 
         if has_data:
+            # Consecutive per message so the app can tell messages lost in transit.
+            data_seq += 1
             output = {
                 "type": "data",
+                "seq": data_seq,
                 "timestamp": timestamp,
                 "metrics": metrics
             }
@@ -137,8 +245,16 @@ def collect_metrics():
                 sys.stdout.flush()
             except Exception:
                 break
+        else:
+            # Nothing sampled this tick; tell the app the loop is still alive.
+            try:
+                sys.stdout.write(json.dumps({"type": "heartbeat", "timestamp": timestamp}) + "\n")
+                sys.stdout.flush()
+            except Exception:
+                break
 
-        time.sleep(interval)
+        interval_changed.wait(interval)
+        interval_changed.clear()
 
 def scan_chrome_processes():
     """Scan for all Chrome processes and categorize them."""
@@ -186,6 +302,34 @@ def scan_chrome_processes():
             
     return chrome_procs
 
+MIN_INTERVAL = 0.5
+
+def accept_pids(pids):
+    """PIDs that can be sampled; the rest are dropped."""
+    kept, dropped = [], []
+    for pid in pids:
+        try:
+            if psutil.pid_exists(int(pid)):
+                kept.append(int(pid))
+                continue
+        except (TypeError, ValueError):
+            pass
+        dropped.append(pid)
+    return kept, dropped
+
+def write_ack(cmd, dropped):
+    """Echo the configuration in effect after a start/update (must hold config_lock)."""
+    output = {
+        "type": "ack",
+        "action": cmd.get("action"),
+        "seq": cmd.get("seq"),
+        "pids": config["pids"],
+        "dropped_pids": dropped,
+        "interval": config["interval"],
+    }
+    sys.stdout.write(json.dumps(output) + "\n")
+    sys.stdout.flush()
+
 def read_stdin():
     """Read commands from stdin."""
     for line in sys.stdin:
@@ -204,13 +348,20 @@ def read_stdin():
                     sys.stdout.write(json.dumps(output) + "\n")
                     sys.stdout.flush()
                     
+                elif action == "version":
+                    sys.stdout.write(json.dumps({"type": "version", "version": COLLECTOR_VERSION}) + "\n")
+                    sys.stdout.flush()
+
                 elif action == "start":
-                    config["pids"] = cmd.get("pids", [])
+                    config["pids"], dropped = accept_pids(cmd.get("pids", []))
                     interval = cmd.get("interval", 1.0)
-                    config["interval"] = max(0.5, interval)
+                    config["interval"] = max(MIN_INTERVAL, interval)
+                    config["cpu_normalization"] = cmd.get("cpu_normalization")
+                    config["memory_standard"] = cmd.get("memory_standard") or "rss"
                     config["running"] = True
                     sys.stderr.write(f"Python: Started collection for pids: {config['pids']}\n")
                     sys.stderr.flush()
+                    write_ack(cmd, dropped)
                     
                 elif action == "stop":
                     config["running"] = False
@@ -218,10 +369,18 @@ def read_stdin():
                     sys.stderr.flush()
                     
                 elif action == "update":
+                    dropped = []
                     if "pids" in cmd:
-                        config["pids"] = cmd["pids"]
+                        config["pids"], dropped = accept_pids(cmd["pids"])
+                    if "interval" in cmd:
+                        config["interval"] = max(MIN_INTERVAL, cmd["interval"])
+                    write_ack(cmd, dropped)
+
+                elif action == "set_interval":
+                    # Adaptive sampling retunes the running loop; unlike "update" it is not acknowledged.
                     if "interval" in cmd:
-                        config["interval"] = max(0.5, cmd["interval"])
+                        config["interval"] = max(MIN_INTERVAL, cmd["interval"])
+                        interval_changed.set()
                         
                 elif action == "exit":
                     sys.exit(0)
@@ -234,7 +393,7 @@ def read_stdin():
 
 if __name__ == "__main__":
     if hasattr(sys, 'frozen'):
-        sys.stderr.write("DEBUG: Collector v4.0 (Manual Delta + Private Mem)\n")
+        sys.stderr.write(f"DEBUG: Collector v{COLLECTOR_VERSION} (Manual Delta + Private Mem)\n")
         sys.stderr.flush()
 
     t = threading.Thread(target=collect_metrics, daemon=True)
@@ -1,13 +1,115 @@
-use crate::models::BatchMetric;
-use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use crate::models::{BatchMetric, MemoryBasis, PerformanceBudget, ReportMeta, RunEvent, RunEventKind, SelfOverhead};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{json, Map, Value};
+
+/// Version of the analysis logic. Bump it whenever a change can alter the numbers of an existing
+/// report; cached analyses and sparklines of older versions are then recomputed by
+/// `reanalyze_reports`.
+pub const ANALYZER_VERSION: u32 = 1;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AnalysisReport {
     pub score: u8, // 0-100
+    /// `ANALYZER_VERSION` that produced this analysis; 0 for analyses cached before it existed.
+    #[serde(default)]
+    pub analyzer_version: u32,
     pub summary: MetricSummary,
     pub top_cpu: Vec<Contributor>,
     pub top_mem: Vec<Contributor>,
-    pub insights: Vec<String>,
+    pub insights: Vec<Insight>,
+    /// Deprecated: the messages of `insights`, for consumers that still expect strings.
+    #[serde(default)]
+    pub insights_text: Vec<String>,
+    /// Per-phase summaries when the run's markers define phases (see `phase_windows`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub phases: Vec<PhaseSummary>,
+    /// PerfSight's own usage when the run monitored itself; those PIDs are not in `summary`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overhead: Option<SelfOverhead>,
+    /// Samples per memory basis for each PID; samples without a recorded basis are not counted.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub memory_basis: BTreeMap<u32, BTreeMap<MemoryBasis, u64>>,
+}
+
+impl AnalysisReport {
+    fn push_insight(&mut self, insight: Insight) {
+        self.insights_text.push(insight.message.clone());
+        self.insights.push(insight);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InsightSeverity {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl InsightSeverity {
+    pub fn label(self) -> &'static str {
+        match self {
+            InsightSeverity::Info => "Info",
+            InsightSeverity::Warning => "Warning",
+            InsightSeverity::Critical => "Critical",
+        }
+    }
+}
+
+/// One finding of the analysis. `code` is stable across versions (e.g. "CPU_SPIKE"), `message`
+/// is the default English text and `params` holds the numbers it was built from.
+#[derive(Debug, Clone, Serialize)]
+pub struct Insight {
+    pub code: String,
+    pub severity: InsightSeverity,
+    pub message: String,
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    pub params: Map<String, Value>,
+}
+
+impl Insight {
+    fn new(code: &str, severity: InsightSeverity, params: Value, message: String) -> Self {
+        let params = match params {
+            Value::Object(map) => map,
+            _ => Map::new(),
+        };
+        Insight { code: code.to_string(), severity, message, params }
+    }
+}
+
+// Analyses saved before insights were structured hold plain strings; read them as LEGACY info.
+impl<'de> Deserialize<'de> for Insight {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Structured {
+            code: String,
+            severity: InsightSeverity,
+            message: String,
+            #[serde(default)]
+            params: Map<String, Value>,
+        }
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Stored {
+            Structured(Structured),
+            Text(String),
+        }
+        Ok(match Stored::deserialize(deserializer)? {
+            Stored::Structured(s) => Insight { code: s.code, severity: s.severity, message: s.message, params: s.params },
+            Stored::Text(message) => Insight::new("LEGACY", InsightSeverity::Info, Value::Null, message),
+        })
+    }
+}
+
+/// Summary of the samples inside one named phase. A phase entered more than once is pooled.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PhaseSummary {
+    pub name: String,
+    pub samples: usize,
+    /// Time covered by the phase's samples, summed over its windows.
+    pub duration_ms: i64,
+    pub summary: MetricSummary,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -35,15 +137,45 @@ pub struct MetricSummary {
     /// Fraction of samples where total memory exceeded 1024 MB.
     pub mem_high_ratio_1024mb: f32,
     pub mem_growth_rate: f64, // MB/s
+    /// Peak per-sample GPU process memory (sum of `gpu_memory_bytes`), when any sample has it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_gpu_memory_mb: Option<f64>,
+    /// Peak per-sample used JS heap across tabs, when any sample has it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_js_heap_mb: Option<f64>,
+    /// Used / total JS heap (0-1), over samples that report both.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avg_js_heap_utilization: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_js_heap_utilization: Option<f64>,
+    /// Trend of used JS heap (MB/sample), independent of OS memory growth.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub js_heap_growth_rate: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Contributor {
     pub pid: u32,
+    /// Resolved from report meta when the report is read (see `apply_aliases`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
     pub avg_cpu: f32,
     pub cpu_share: f32,
     pub avg_mem_mb: f64,
     pub mem_share: f64,
+    /// CPU/memory samples for this PID; its averages are over these, not over every batch.
+    #[serde(default)]
+    pub samples: u64,
+    /// Share of sampled batches (0-100) that carried this PID.
+    #[serde(default)]
+    pub coverage_pct: f32,
+}
+
+/// Label contributors with the report's current process aliases.
+pub fn apply_aliases(report: &mut AnalysisReport, aliases: &std::collections::HashMap<u32, String>) {
+    for c in report.top_cpu.iter_mut().chain(report.top_mem.iter_mut()) {
+        c.alias = aliases.get(&c.pid).cloned();
+    }
 }
 
 fn percentile_f32(sorted: &[f32], p: f32) -> f32 {
@@ -94,10 +226,185 @@ fn stddev_f64(values: &[f64], mean: f64) -> f64 {
     var.sqrt()
 }
 
+/// Points kept per series in a report sparkline.
+pub const SPARKLINE_POINTS: usize = 60;
+
+/// Down-sampled totals shown next to a report in the list (stored with the report, see `summary_json`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportSparkline {
+    pub cpu: Vec<f32>,
+    pub mem_mb: Vec<f64>,
+    pub score: u8,
+    /// `ANALYZER_VERSION` of `score` and the headline stats; 0 before it was recorded.
+    #[serde(default)]
+    pub analyzer_version: u32,
+    /// Headline stats kept alongside so folder aggregates don't need full metrics.
+    #[serde(default)]
+    pub p95_cpu: f32,
+    #[serde(default)]
+    pub max_mem_mb: f64,
+}
+
+// Min/max bucketing: each bucket contributes its min and max in time order, so short spikes
+// survive the down-sampling.
+fn downsample_min_max<T: PartialOrd + Copy>(values: &[T], points: usize) -> Vec<T> {
+    if values.len() <= points || points < 2 {
+        return values.to_vec();
+    }
+    let buckets = points / 2;
+    let mut out = Vec::with_capacity(buckets * 2);
+    for b in 0..buckets {
+        let start = b * values.len() / buckets;
+        let end = ((b + 1) * values.len() / buckets).max(start + 1);
+        let chunk = &values[start..end];
+        let (mut lo, mut hi) = (0, 0);
+        for (i, v) in chunk.iter().enumerate() {
+            if *v < chunk[lo] {
+                lo = i;
+            }
+            if *v > chunk[hi] {
+                hi = i;
+            }
+        }
+        let (first, second) = if lo <= hi { (lo, hi) } else { (hi, lo) };
+        out.push(chunk[first]);
+        out.push(chunk[second]);
+    }
+    out
+}
+
+/// Down-sampled CPU/memory totals of `metrics`, with the score and headline stats of `analysis`
+/// (computed from the same samples).
+pub fn build_sparkline(metrics: &[BatchMetric], analysis: &AnalysisReport) -> ReportSparkline {
+    let mut cpu = Vec::with_capacity(metrics.len());
+    let mut mem_mb = Vec::with_capacity(metrics.len());
+    for batch in metrics {
+        // Same totals and memory policy as `analyze`.
+        cpu.push(batch.metrics.values().map(|m| m.cpu_usage).sum::<f32>());
+        mem_mb.push(
+            batch
+                .metrics
+                .values()
+                .map(|m| m.memory_private.unwrap_or(m.memory_rss) as f64)
+                .sum::<f64>()
+                / 1024.0
+                / 1024.0,
+        );
+    }
+    ReportSparkline {
+        cpu: downsample_min_max(&cpu, SPARKLINE_POINTS),
+        mem_mb: downsample_min_max(&mem_mb, SPARKLINE_POINTS),
+        score: analysis.score,
+        analyzer_version: analysis.analyzer_version,
+        p95_cpu: analysis.summary.p95_cpu,
+        max_mem_mb: analysis.summary.max_mem_mb,
+    }
+}
+
+fn default_regression_lookback() -> usize {
+    5
+}
+
+fn default_regression_threshold() -> f64 {
+    15.0
+}
+
+/// Score regression check run when a report is saved; stored as `Settings::analysis`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalysisSettings {
+    /// Prior reports (same folder and scenario) whose median score is the baseline.
+    #[serde(default = "default_regression_lookback")]
+    pub regression_lookback: usize,
+    /// Score points below the baseline median that count as a regression.
+    #[serde(default = "default_regression_threshold")]
+    pub regression_threshold: f64,
+}
+
+impl Default for AnalysisSettings {
+    fn default() -> Self {
+        Self {
+            regression_lookback: default_regression_lookback(),
+            regression_threshold: default_regression_threshold(),
+        }
+    }
+}
+
+/// A new report's score against the median of the reports before it. Saved as
+/// `meta.score_regression` when `regressed`, and sent with the "report saved" webhook.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoreRegression {
+    pub score: u8,
+    pub baseline_median: f64,
+    /// `score - baseline_median`; negative is worse.
+    pub delta: f64,
+    pub threshold: f64,
+    pub regressed: bool,
+    /// The prior reports forming the baseline, newest first, with their scores and the analyzer
+    /// version of each score.
+    pub baseline_report_ids: Vec<i64>,
+    pub baseline_scores: Vec<u8>,
+    #[serde(default)]
+    pub baseline_analyzer_versions: Vec<u32>,
+    /// Analyzer version of `score`.
+    #[serde(default)]
+    pub analyzer_version: u32,
+}
+
+/// Compare `score` (from analyzer `version`) with the median of `prior` (id, score, analyzer
+/// version); None without prior reports.
+pub fn score_regression(score: u8, version: u32, prior: &[(i64, u8, u32)], threshold: f64) -> Option<ScoreRegression> {
+    if prior.is_empty() {
+        return None;
+    }
+    let mut sorted: Vec<f64> = prior.iter().map(|(_, s, _)| *s as f64).collect();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let mid = sorted.len() / 2;
+    let median = if sorted.len().is_multiple_of(2) { (sorted[mid - 1] + sorted[mid]) / 2.0 } else { sorted[mid] };
+    let delta = score as f64 - median;
+    Some(ScoreRegression {
+        score,
+        baseline_median: median,
+        delta,
+        threshold,
+        regressed: -delta > threshold,
+        baseline_report_ids: prior.iter().map(|(id, _, _)| *id).collect(),
+        baseline_scores: prior.iter().map(|(_, s, _)| *s).collect(),
+        baseline_analyzer_versions: prior.iter().map(|(_, _, v)| *v).collect(),
+        analyzer_version: version,
+    })
+}
+
+// Linear regression slope (y = kx + b) over sample index.
+// We assume equal time intervals for simplicity (1 sample = 1 unit time)
+// Ideally we should use actual timestamps, but sample index is good enough for trend detection if interval is constant.
+fn slope_per_sample(points: &[f64]) -> f64 {
+    let n = points.len() as f64;
+    if n <= 1.0 {
+        return 0.0;
+    }
+    let sum_x: f64 = (0..points.len()).map(|i| i as f64).sum();
+    let sum_y: f64 = points.iter().sum();
+    let sum_xy: f64 = points.iter().enumerate().map(|(i, &y)| i as f64 * y).sum();
+    let sum_xx: f64 = (0..points.len()).map(|i| (i * i) as f64).sum();
+    (n * sum_xy - sum_x * sum_y) / (n * sum_xx - sum_x * sum_x)
+}
+
+// PIDs sampled in fewer than this share (%) of batches get an insight.
+const LOW_COVERAGE_PCT: f32 = 90.0;
+// Used JS heap growing faster than this (MB/sample) gets an insight.
+const JS_HEAP_GROWTH_MB_PER_SAMPLE: f64 = 0.2;
+// Used / total JS heap above this gets an insight.
+const JS_HEAP_FULL_RATIO: f64 = 0.9;
+// A busiest thread at or above this CPU% (of one core) counts as saturated...
+const THREAD_SATURATED_CPU: f32 = 90.0;
+// ...and a PID saturated in at least this share of its thread samples gets an insight.
+const THREAD_SATURATED_SHARE: f64 = 0.5;
+
 pub fn analyze(metrics: &[BatchMetric]) -> AnalysisReport {
     if metrics.is_empty() {
         return AnalysisReport {
             score: 0,
+            analyzer_version: ANALYZER_VERSION,
             summary: MetricSummary {
                 avg_cpu: 0.0,
                 max_cpu: 0.0,
@@ -118,10 +425,19 @@ pub fn analyze(metrics: &[BatchMetric]) -> AnalysisReport {
                 mem_high_ratio_512mb: 0.0,
                 mem_high_ratio_1024mb: 0.0,
                 mem_growth_rate: 0.0,
+                max_gpu_memory_mb: None,
+                max_js_heap_mb: None,
+                avg_js_heap_utilization: None,
+                max_js_heap_utilization: None,
+                js_heap_growth_rate: None,
             },
             top_cpu: vec![],
             top_mem: vec![],
-            insights: vec!["No data collected".to_string()],
+            insights: vec![Insight::new("NO_DATA", InsightSeverity::Info, Value::Null, "No data collected".to_string())],
+            insights_text: vec!["No data collected".to_string()],
+            phases: Vec::new(),
+            overhead: None,
+            memory_basis: BTreeMap::new(),
         };
     }
 
@@ -129,16 +445,50 @@ pub fn analyze(metrics: &[BatchMetric]) -> AnalysisReport {
     let mut cpu_points = Vec::new();
     let mut mem_points = Vec::new();
     let mut cpu_sum_by_pid: std::collections::HashMap<u32, f32> = std::collections::HashMap::new();
+    let mut samples_by_pid: std::collections::HashMap<u32, u64> = std::collections::HashMap::new();
+    let mut sampled_batches: u64 = 0;
     let mut mem_sum_by_pid: std::collections::HashMap<u32, f64> = std::collections::HashMap::new();
+    let mut memory_basis: BTreeMap<u32, BTreeMap<MemoryBasis, u64>> = BTreeMap::new();
     let mut mem_total_sum: f64 = 0.0;
     let mut cpu_total_sum: f32 = 0.0;
+    let mut max_gpu_memory_mb: Option<f64> = None;
+    let mut heap_points: Vec<f64> = Vec::new();
+    let mut heap_utilization: Vec<f64> = Vec::new();
+    // PID -> (samples with max_thread_cpu, of which saturated)
+    let mut thread_samples_by_pid: std::collections::HashMap<u32, (u64, u64)> = std::collections::HashMap::new();
     
     for batch in metrics {
         let mut total_cpu = 0.0;
         let mut total_mem = 0.0;
-        for (pid, m) in &batch.metrics {
+        let gpu_mem = batch.metrics.values().filter_map(|m| m.gpu_memory_bytes).map(|b| b as f64).reduce(|a, b| a + b);
+        if let Some(bytes) = gpu_mem {
+            let mb = bytes / 1024.0 / 1024.0;
+            max_gpu_memory_mb = Some(max_gpu_memory_mb.map_or(mb, |m| m.max(mb)));
+        }
+        let heap_used = batch.metrics.values().filter_map(|m| m.js_heap_size).map(|b| b as f64).reduce(|a, b| a + b);
+        if let Some(used) = heap_used {
+            heap_points.push(used / 1024.0 / 1024.0);
+        }
+        let (used, total) = batch
+            .metrics
+            .values()
+            .filter_map(|m| Some((m.js_heap_size? as f64, m.js_heap_total_size? as f64)))
+            .fold((0.0, 0.0), |(u, t), (mu, mt)| (u + mu, t + mt));
+        if total > 0.0 {
+            heap_utilization.push(used / total);
+        }
+        if batch.metrics.values().any(|m| !m.is_custom_only()) {
+            sampled_batches += 1;
+        }
+        for (pid, m) in batch.metrics.iter().filter(|(_, m)| !m.is_custom_only()) {
+            *samples_by_pid.entry(*pid).or_insert(0) += 1;
             total_cpu += m.cpu_usage;
             *cpu_sum_by_pid.entry(*pid).or_insert(0.0) += m.cpu_usage;
+            if let Some(thread_cpu) = m.max_thread_cpu {
+                let t = thread_samples_by_pid.entry(*pid).or_insert((0, 0));
+                t.0 += 1;
+                t.1 += (thread_cpu >= THREAD_SATURATED_CPU) as u64;
+            }
 
             // Memory policy:
             // - Browser mode: prefer Chrome-aligned private memory if present.
@@ -147,6 +497,9 @@ pub fn analyze(metrics: &[BatchMetric]) -> AnalysisReport {
             let mem_bytes = m.memory_private.unwrap_or(m.memory_rss) as f64;
             total_mem += mem_bytes;
             *mem_sum_by_pid.entry(*pid).or_insert(0.0) += mem_bytes;
+            if let Some(basis) = m.memory_basis {
+                *memory_basis.entry(*pid).or_default().entry(basis).or_insert(0) += 1;
+            }
         }
         cpu_points.push(total_cpu);
         mem_points.push(total_mem / 1024.0 / 1024.0); // MB
@@ -181,20 +534,13 @@ pub fn analyze(metrics: &[BatchMetric]) -> AnalysisReport {
     let mem_high_ratio_512mb = mem_points.iter().filter(|v| **v > 512.0).count() as f32 / mem_points.len() as f32;
     let mem_high_ratio_1024mb = mem_points.iter().filter(|v| **v > 1024.0).count() as f32 / mem_points.len() as f32;
 
-    // 3. Memory Trend (Linear Regression: y = kx + b)
-    // We assume equal time intervals for simplicity (1 sample = 1 unit time)
-    // Ideally we should use actual timestamps, but sample index is good enough for trend detection if interval is constant.
-    let n = mem_points.len() as f64;
-    let sum_x: f64 = (0..mem_points.len()).map(|i| i as f64).sum();
-    let sum_y: f64 = mem_points.iter().sum();
-    let sum_xy: f64 = mem_points.iter().enumerate().map(|(i, &y)| i as f64 * y).sum();
-    let sum_xx: f64 = (0..mem_points.len()).map(|i| (i * i) as f64).sum();
-    
-    let slope = if n > 1.0 {
-        (n * sum_xy - sum_x * sum_y) / (n * sum_xx - sum_x * sum_x)
-    } else {
-        0.0
-    };
+    // 3. Memory Trend (OS memory, and JS heap separately)
+    let slope = slope_per_sample(&mem_points);
+    let max_js_heap_mb = heap_points.iter().copied().reduce(f64::max);
+    let js_heap_growth_rate = (!heap_points.is_empty()).then(|| slope_per_sample(&heap_points));
+    let avg_js_heap_utilization =
+        (!heap_utilization.is_empty()).then(|| heap_utilization.iter().sum::<f64>() / heap_utilization.len() as f64);
+    let max_js_heap_utilization = heap_utilization.iter().copied().reduce(f64::max);
 
     // 4. Scoring & Insights
     let mut score = 100.0;
@@ -203,57 +549,162 @@ pub fn analyze(metrics: &[BatchMetric]) -> AnalysisReport {
     // CPU Penalties
     if (avg_cpu as f64) > 30.0 { 
         score -= ((avg_cpu as f64) - 30.0) * 0.5; 
-        insights.push(format!("High average CPU usage: {:.1}%", avg_cpu));
+        insights.push(Insight::new(
+            "CPU_AVG_HIGH",
+            InsightSeverity::Warning,
+            json!({ "avg_cpu": avg_cpu }),
+            format!("High average CPU usage: {:.1}%", avg_cpu),
+        ));
     }
     if cpu_high_ratio_60 > 0.05 {
         score -= 5.0;
-        insights.push(format!(
-            "Sustained high CPU: {:.0}% of samples > 60%",
-            cpu_high_ratio_60 * 100.0
+        insights.push(Insight::new(
+            "CPU_SUSTAINED_HIGH",
+            InsightSeverity::Warning,
+            json!({ "cpu_high_ratio_60": cpu_high_ratio_60, "threshold_cpu": 60.0 }),
+            format!("Sustained high CPU: {:.0}% of samples > 60%", cpu_high_ratio_60 * 100.0),
         ));
     }
     if max_cpu > 80.0 {
         score -= 10.0;
-        insights.push(format!("CPU spike detected: {:.1}%", max_cpu));
+        insights.push(Insight::new(
+            "CPU_SPIKE",
+            InsightSeverity::Warning,
+            json!({ "max_cpu": max_cpu, "threshold_cpu": 80.0 }),
+            format!("CPU spike detected: {:.1}%", max_cpu),
+        ));
     }
 
     // Memory Penalties
     // slope is MB per sample. If sample interval is 1s, then MB/s.
     if slope > 0.5 { 
         score -= slope * 20.0; 
-        insights.push(format!("High Memory Growth detected (+{:.2} MB/sample)", slope));
+        insights.push(Insight::new(
+            "MEM_GROWTH_HIGH",
+            InsightSeverity::Critical,
+            json!({ "mem_growth_rate": slope }),
+            format!("High Memory Growth detected (+{:.2} MB/sample)", slope),
+        ));
     } else if slope > 0.1 {
         score -= 5.0;
-        insights.push("Slight memory growth trend detected".to_string());
+        insights.push(Insight::new(
+            "MEM_GROWTH_SLIGHT",
+            InsightSeverity::Info,
+            json!({ "mem_growth_rate": slope }),
+            "Slight memory growth trend detected".to_string(),
+        ));
     }
     if mem_high_ratio_1024mb > 0.05 {
         score -= 5.0;
-        insights.push(format!(
-            "High memory usage: {:.0}% of samples > 1 GB",
-            mem_high_ratio_1024mb * 100.0
+        insights.push(Insight::new(
+            "MEM_HIGH_USAGE",
+            InsightSeverity::Warning,
+            json!({ "mem_high_ratio_1024mb": mem_high_ratio_1024mb, "threshold_mb": 1024.0 }),
+            format!("High memory usage: {:.0}% of samples > 1 GB", mem_high_ratio_1024mb * 100.0),
+        ));
+    }
+
+    // JS heap: reported on its own, without changing the score (OS memory already does).
+    if let Some(rate) = js_heap_growth_rate.filter(|r| *r > JS_HEAP_GROWTH_MB_PER_SAMPLE) {
+        insights.push(Insight::new(
+            "JS_HEAP_GROWTH",
+            InsightSeverity::Warning,
+            json!({ "js_heap_growth_rate": rate }),
+            format!("JS heap growth detected (+{:.2} MB/sample)", rate),
+        ));
+    }
+    if let Some(peak) = max_js_heap_utilization.filter(|u| *u > JS_HEAP_FULL_RATIO) {
+        insights.push(Insight::new(
+            "JS_HEAP_NEARLY_FULL",
+            InsightSeverity::Warning,
+            json!({ "max_js_heap_utilization": peak }),
+            format!("JS heap nearly full: used reached {:.0}% of total", peak * 100.0),
+        ));
+    }
+
+    // One thread pinned to a core while the normalized total looks modest: a main-thread
+    // bottleneck that total CPU% hides on many-core machines. Reported, not scored.
+    let mut saturated: Vec<(u32, f64)> = thread_samples_by_pid
+        .iter()
+        .filter(|(_, (n, hot))| *n > 0 && *hot as f64 / *n as f64 >= THREAD_SATURATED_SHARE)
+        .map(|(pid, (n, hot))| (*pid, *hot as f64 / *n as f64))
+        .collect();
+    saturated.sort_by_key(|(pid, _)| *pid);
+    for (pid, share) in saturated {
+        let n = samples_by_pid.get(&pid).copied().unwrap_or(0).max(1);
+        let avg = cpu_sum_by_pid.get(&pid).copied().unwrap_or(0.0) / n as f32;
+        if avg >= THREAD_SATURATED_CPU {
+            continue; // already obvious from the total
+        }
+        insights.push(Insight::new(
+            "THREAD_SATURATED",
+            InsightSeverity::Warning,
+            json!({ "pid": pid, "saturated_share": share, "threshold_cpu": THREAD_SATURATED_CPU, "avg_cpu": avg }),
+            format!(
+                "Main-thread saturation in PID {}: one thread used >= {:.0}% of a core in {:.0}% of samples while its total CPU averaged {:.1}%",
+                pid,
+                THREAD_SATURATED_CPU,
+                share * 100.0,
+                avg
+            ),
         ));
     }
 
     if score < 0.0 { score = 0.0; }
 
-    // 5. Top contributors
+    // 5. Top contributors, each averaged over its own samples (PIDs fed over websocket can miss
+    // ticks; dividing by the batch count would deflate them).
     const TOP_N: usize = 5;
-    let sample_count = cpu_points.len().max(1) as f32;
-    let sample_count_f64 = mem_points.len().max(1) as f64;
-
-    let contributors: Vec<Contributor> = cpu_sum_by_pid
+    let mut contributors: Vec<Contributor> = cpu_sum_by_pid
         .iter()
         .map(|(pid, cpu_sum)| {
             let mem_sum = mem_sum_by_pid.get(pid).cloned().unwrap_or(0.0);
+            let samples = samples_by_pid.get(pid).copied().unwrap_or(0);
+            let n = samples.max(1);
             Contributor {
                 pid: *pid,
-                avg_cpu: *cpu_sum / sample_count,
+                alias: None,
+                avg_cpu: *cpu_sum / n as f32,
                 cpu_share: if cpu_total_sum > 0.0 { *cpu_sum / cpu_total_sum } else { 0.0 },
-                avg_mem_mb: (mem_sum / 1024.0 / 1024.0) / sample_count_f64,
+                avg_mem_mb: (mem_sum / 1024.0 / 1024.0) / n as f64,
                 mem_share: if mem_total_sum > 0.0 { mem_sum / mem_total_sum } else { 0.0 },
+                samples,
+                coverage_pct: (samples as f32 / sampled_batches.max(1) as f32 * 100.0).min(100.0),
             }
         })
         .collect();
+    contributors.sort_by_key(|c| c.pid);
+
+    // Coverage is reported, not scored: the PID's stats are just less trustworthy.
+    let low_coverage: Vec<&Contributor> = contributors.iter().filter(|c| c.coverage_pct < LOW_COVERAGE_PCT).collect();
+    if !low_coverage.is_empty() {
+        let listed: Vec<String> = low_coverage.iter().map(|c| format!("{} ({:.0}%)", c.pid, c.coverage_pct)).collect();
+        let coverage: Map<String, Value> =
+            low_coverage.iter().map(|c| (c.pid.to_string(), json!(c.coverage_pct))).collect();
+        insights.push(Insight::new(
+            "LOW_COVERAGE",
+            InsightSeverity::Info,
+            json!({ "coverage_pct_by_pid": coverage, "threshold_pct": LOW_COVERAGE_PCT }),
+            format!("Low sample coverage for PID {}: stats for these processes are unreliable", listed.join(", ")),
+        ));
+    }
+
+    // A series that switches basis mid-run (e.g. CDP private memory dropping out to footprint)
+    // steps by the gap between the two measures, which reads like a leak or a release.
+    let mixed: Vec<(&u32, &BTreeMap<MemoryBasis, u64>)> = memory_basis.iter().filter(|(_, b)| b.len() > 1).collect();
+    if !mixed.is_empty() {
+        let listed: Vec<String> = mixed
+            .iter()
+            .map(|(pid, b)| format!("{} ({})", pid, b.keys().map(|k| k.as_str()).collect::<Vec<_>>().join(", ")))
+            .collect();
+        let bases: Map<String, Value> = mixed.iter().map(|(pid, b)| (pid.to_string(), json!(b))).collect();
+        insights.push(Insight::new(
+            "MEMORY_BASIS_MIXED",
+            InsightSeverity::Warning,
+            json!({ "samples_by_basis_by_pid": bases }),
+            format!("Memory of PID {} was measured on more than one basis during the run; its memory series is not continuous", listed.join(", ")),
+        ));
+    }
 
     let mut top_cpu = contributors.clone();
     top_cpu.sort_by(|a, b| b.avg_cpu.partial_cmp(&a.avg_cpu).unwrap_or(std::cmp::Ordering::Equal));
@@ -265,6 +716,7 @@ pub fn analyze(metrics: &[BatchMetric]) -> AnalysisReport {
 
     AnalysisReport {
         score: score as u8,
+        analyzer_version: ANALYZER_VERSION,
         summary: MetricSummary {
             avg_cpu,
             max_cpu,
@@ -285,10 +737,651 @@ pub fn analyze(metrics: &[BatchMetric]) -> AnalysisReport {
             mem_high_ratio_512mb,
             mem_high_ratio_1024mb,
             mem_growth_rate: slope,
+            max_gpu_memory_mb,
+            max_js_heap_mb,
+            avg_js_heap_utilization,
+            max_js_heap_utilization,
+            js_heap_growth_rate,
         },
         top_cpu,
         top_mem,
+        insights_text: insights.iter().map(|i| i.message.clone()).collect(),
         insights,
+        phases: Vec::new(),
+        overhead: None,
+        memory_basis,
+    }
+}
+
+/// Memory bases recorded in `metrics`, for checking that two series measure the same thing.
+pub fn memory_bases(metrics: &[BatchMetric]) -> BTreeSet<MemoryBasis> {
+    metrics
+        .iter()
+        .flat_map(|b| b.metrics.values())
+        .filter(|m| !m.is_custom_only())
+        .filter_map(|m| m.memory_basis)
+        .collect()
+}
+
+// Samples this long after a machine_slept event are still skewed by the wake-up burst.
+const SLEEP_SETTLE_MS: i64 = 3000;
+
+/// A stretch of a run belonging to a named phase, in epoch milliseconds (end exclusive).
+#[derive(Debug, Clone)]
+pub struct PhaseWindow {
+    pub name: String,
+    pub start_ms: i64,
+    pub end_ms: i64,
+}
+
+/// Phases defined by `marker` events carrying `detail.phase`. Each one starts that phase (ending
+/// the open one), or ends it when `detail.boundary` is "end". A phase still open after the last
+/// marker runs to the end of the report.
+pub fn phase_windows(events: &[RunEvent]) -> Vec<PhaseWindow> {
+    let mut markers: Vec<(i64, &str, bool)> = events
+        .iter()
+        .filter(|e| e.kind == RunEventKind::Marker)
+        .filter_map(|e| {
+            let name = e.detail.get("phase")?.as_str()?.trim();
+            let ends = e.detail.get("boundary").and_then(|b| b.as_str()) == Some("end");
+            (!name.is_empty()).then_some((e.timestamp.timestamp_millis(), name, ends))
+        })
+        .collect();
+    markers.sort_by_key(|(at, _, _)| *at);
+
+    let mut windows = Vec::new();
+    let mut open: Option<(&str, i64)> = None;
+    for (at, name, ends) in markers {
+        match open.take() {
+            // An end marker for a phase that isn't open changes nothing.
+            Some((current, start)) if ends && current != name => open = Some((current, start)),
+            Some((current, start)) => windows.push(PhaseWindow { name: current.to_string(), start_ms: start, end_ms: at }),
+            None => {}
+        }
+        if !ends {
+            open = Some((name, at));
+        }
+    }
+    if let Some((name, start)) = open {
+        windows.push(PhaseWindow { name: name.to_string(), start_ms: start, end_ms: i64::MAX });
+    }
+    windows
+}
+
+/// The samples of one phase, pooled over all of its windows.
+pub struct PhaseSlice {
+    pub name: String,
+    pub metrics: Vec<BatchMetric>,
+    pub duration_ms: i64,
+}
+
+/// Split `metrics` by phase, in order of first appearance. Samples outside every phase are left out.
+pub fn split_by_phase(metrics: &[BatchMetric], windows: &[PhaseWindow]) -> Vec<PhaseSlice> {
+    let mut slices: Vec<PhaseSlice> = Vec::new();
+    for w in windows {
+        let inside: Vec<BatchMetric> = metrics
+            .iter()
+            .filter(|b| (w.start_ms..w.end_ms).contains(&b.timestamp.timestamp_millis()))
+            .cloned()
+            .collect();
+        let duration_ms = match (inside.first(), inside.last()) {
+            (Some(first), Some(last)) => (last.timestamp - first.timestamp).num_milliseconds(),
+            _ => 0,
+        };
+        match slices.iter_mut().find(|s| s.name == w.name) {
+            Some(slice) => {
+                slice.metrics.extend(inside);
+                slice.duration_ms += duration_ms;
+            }
+            None => slices.push(PhaseSlice { name: w.name.clone(), metrics: inside, duration_ms }),
+        }
+    }
+    slices
+}
+
+fn phase_summaries(metrics: &[BatchMetric], events: &[RunEvent]) -> Vec<PhaseSummary> {
+    split_by_phase(metrics, &phase_windows(events))
+        .into_iter()
+        .map(|slice| PhaseSummary {
+            samples: slice.metrics.len(),
+            duration_ms: slice.duration_ms,
+            summary: analyze(&slice.metrics).summary,
+            name: slice.name,
+        })
+        .collect()
+}
+
+/// `analyze`, minus samples inside a `machine_slept` or recovered `source_stalled` gap or its
+/// settle window, plus a summary per phase when phase markers are present. Other event kinds
+/// don't change the numbers.
+pub fn analyze_with_events(metrics: &[BatchMetric], events: &[RunEvent]) -> AnalysisReport {
+    let gap_window = |e: &RunEvent| {
+        // A recovery is recorded at the next progress tick; `resumed_at` is when data came back.
+        let resumed = e
+            .detail
+            .get("resumed_at")
+            .and_then(|v| v.as_str())
+            .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
+            .map_or(e.timestamp.timestamp_millis(), |t| t.timestamp_millis());
+        let gap = e.detail.get("gap_ms").and_then(|v| v.as_i64()).unwrap_or(0).max(0);
+        (resumed - gap, resumed + SLEEP_SETTLE_MS)
+    };
+    let sleeps = events.iter().filter(|e| e.kind == RunEventKind::MachineSlept).count();
+    let windows: Vec<(i64, i64)> = events
+        .iter()
+        .filter(|e| matches!(e.kind, RunEventKind::MachineSlept | RunEventKind::SourceRecovered))
+        .map(gap_window)
+        .collect();
+    if windows.is_empty() {
+        let mut report = analyze(metrics);
+        report.phases = phase_summaries(metrics, events);
+        return report;
+    }
+    let kept: Vec<BatchMetric> = metrics
+        .iter()
+        .filter(|b| {
+            let t = b.timestamp.timestamp_millis();
+            !windows.iter().any(|(from, to)| t >= *from && t <= *to)
+        })
+        .cloned()
+        .collect();
+    let excluded = metrics.len() - kept.len();
+    let mut report = analyze(&kept);
+    report.phases = phase_summaries(&kept, events);
+    if excluded > 0 {
+        report.push_insight(Insight::new(
+            "SLEEP_SAMPLES_EXCLUDED",
+            InsightSeverity::Info,
+            json!({ "excluded_samples": excluded, "sleep_gaps": sleeps, "stall_gaps": windows.len() - sleeps }),
+            match (sleeps, windows.len() - sleeps) {
+                (_, 0) => format!("Excluded {} sample(s) around {} machine sleep gap(s)", excluded, sleeps),
+                (0, stalls) => format!("Excluded {} sample(s) around {} data source stall(s)", excluded, stalls),
+                (sleeps, stalls) => format!(
+                    "Excluded {} sample(s) around {} machine sleep gap(s) and {} data source stall(s)",
+                    excluded, sleeps, stalls
+                ),
+            },
+        ));
+    }
+    report
+}
+
+// GPU process memory at or above this share of the adapter's memory gets an insight.
+const GPU_ADAPTER_WARN_RATIO: f64 = 0.8;
+// Share of a source's messages lost in transit above which the run gets an insight.
+const SAMPLE_LOSS_WARN_RATIO: f64 = 0.01;
+// Share of the run a monitored process may spend backgrounded (App Nap, occluded window) before
+// its CPU numbers get a warning.
+const BACKGROUND_WARN_FRACTION: f64 = 0.2;
+
+/// Average and peak of the per-batch CPU and memory totals of `pids`. None when no batch has a
+/// sample for them.
+pub fn self_overhead(metrics: &[BatchMetric], pids: &[u32]) -> Option<SelfOverhead> {
+    let mut overhead = SelfOverhead { pids: pids.to_vec(), ..Default::default() };
+    let (mut cpu_sum, mut mem_sum) = (0.0, 0.0);
+    for batch in metrics {
+        let points: Vec<_> = pids
+            .iter()
+            .filter_map(|pid| batch.metrics.get(pid))
+            .filter(|m| !m.is_custom_only())
+            .collect();
+        if points.is_empty() {
+            continue;
+        }
+        let cpu: f64 = points.iter().map(|m| m.cpu_usage as f64).sum();
+        let mem: f64 = points.iter().map(|m| m.memory_private.unwrap_or(m.memory_rss) as f64).sum::<f64>() / 1024.0 / 1024.0;
+        overhead.samples += 1;
+        overhead.max_cpu = overhead.max_cpu.max(cpu);
+        overhead.max_mem_mb = overhead.max_mem_mb.max(mem);
+        cpu_sum += cpu;
+        mem_sum += mem;
+    }
+    if overhead.samples == 0 {
+        return None;
+    }
+    overhead.avg_cpu = cpu_sum / overhead.samples as f64;
+    overhead.avg_mem_mb = mem_sum / overhead.samples as f64;
+    Some(overhead)
+}
+
+/// `analyze_with_events`, plus checks that need report meta (GPU memory against the adapter
+/// size recorded in `env.gpu.adapter_memory_bytes`). PerfSight's own PIDs (`collection.self_pids`)
+/// are left out of the totals and reported as `overhead`, unless nothing else was recorded.
+pub fn analyze_report(metrics: &[BatchMetric], meta: &ReportMeta) -> AnalysisReport {
+    let self_pids = meta.self_pids();
+    let others_recorded = metrics.iter().any(|b| b.metrics.keys().any(|pid| !self_pids.contains(pid)));
+    let mut report = if self_pids.is_empty() || !others_recorded {
+        analyze_with_events(metrics, &meta.events)
+    } else {
+        let scenario: Vec<BatchMetric> = metrics
+            .iter()
+            .filter_map(|b| {
+                let mut b = b.clone();
+                b.metrics.retain(|pid, _| !self_pids.contains(pid));
+                (!b.metrics.is_empty()).then_some(b)
+            })
+            .collect();
+        let mut report = analyze_with_events(&scenario, &meta.events);
+        report.overhead = self_overhead(metrics, &self_pids);
+        if let Some(o) = &report.overhead {
+            report.push_insight(Insight::new(
+                "SELF_OVERHEAD",
+                InsightSeverity::Info,
+                json!({ "pids": o.pids, "avg_cpu": o.avg_cpu, "avg_mem_mb": o.avg_mem_mb }),
+                format!(
+                    "PerfSight itself averaged {:.1}% CPU and {:.0} MB (excluded from the totals)",
+                    o.avg_cpu, o.avg_mem_mb
+                ),
+            ));
+        }
+        report
+    };
+    // Messages lost between a source and the app leave holes the percentiles don't see.
+    for source in meta.data_sources() {
+        let Some(seq) = source.sequence.filter(|s| s.loss_ratio() > SAMPLE_LOSS_WARN_RATIO) else { continue };
+        report.push_insight(Insight::new(
+            "SAMPLE_LOSS",
+            InsightSeverity::Warning,
+            json!({ "source": source.source, "expected": seq.expected, "received": seq.received, "lost": seq.lost }),
+            format!(
+                "{} of {} {} messages were lost in transit ({:.1}%); percentiles may be inaccurate",
+                seq.lost,
+                seq.expected,
+                source.source,
+                seq.loss_ratio() * 100.0
+            ),
+        ));
+    }
+    let mut backgrounded: Vec<(u32, f64)> = meta
+        .process_roles()
+        .into_iter()
+        .filter(|(_, r)| r.background_fraction >= BACKGROUND_WARN_FRACTION)
+        .map(|(pid, r)| (pid, r.background_fraction))
+        .collect();
+    if !backgrounded.is_empty() {
+        backgrounded.sort_by_key(|(pid, _)| *pid);
+        let worst = backgrounded.iter().map(|(_, f)| *f).fold(0.0, f64::max);
+        report.push_insight(Insight::new(
+            "PROCESS_BACKGROUNDED",
+            InsightSeverity::Warning,
+            json!({ "pids": backgrounded.iter().map(|(pid, _)| pid).collect::<Vec<_>>(), "max_background_fraction": worst }),
+            format!(
+                "{} monitored process(es) ran in the background for up to {:.0}% of the run (App Nap or an occluded window); CPU numbers may not match a foreground run",
+                backgrounded.len(),
+                worst * 100.0
+            ),
+        ));
+    }
+    let adapter_bytes = meta
+        .env
+        .as_ref()
+        .and_then(|e| e.extra.get("gpu"))
+        .and_then(|g| g.get("adapter_memory_bytes"))
+        .and_then(|v| v.as_u64())
+        .filter(|b| *b > 0);
+    if let (Some(peak_mb), Some(adapter_bytes)) = (report.summary.max_gpu_memory_mb, adapter_bytes) {
+        let adapter_mb = adapter_bytes as f64 / 1024.0 / 1024.0;
+        if peak_mb >= adapter_mb * GPU_ADAPTER_WARN_RATIO {
+            report.push_insight(Insight::new(
+                "GPU_MEMORY_NEAR_ADAPTER",
+                InsightSeverity::Warning,
+                json!({ "max_gpu_memory_mb": peak_mb, "adapter_memory_mb": adapter_mb }),
+                format!(
+                    "GPU process memory peaked at {:.0} MB, {:.0}% of the adapter's {:.0} MB",
+                    peak_mb,
+                    peak_mb / adapter_mb * 100.0,
+                    adapter_mb
+                ),
+            ));
+        }
+    }
+    report
+}
+
+/// Outcome of one performance budget against a report's analysis.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetResult {
+    pub metric: String,
+    /// "max" (observed must be <= threshold) or "min" (observed must be >= threshold).
+    pub comparison: String,
+    pub threshold: f64,
+    /// None when the metric name is unknown.
+    pub observed: Option<f64>,
+    pub passed: bool,
+    /// Analysis insight related to the metric, if any.
+    pub insight: Option<String>,
+}
+
+/// Look up a budgetable value by name: any `MetricSummary` field, or `score`.
+pub fn metric_value(report: &AnalysisReport, metric: &str) -> Option<f64> {
+    let s = &report.summary;
+    let v = match metric {
+        "score" => report.score as f64,
+        "avg_cpu" => s.avg_cpu as f64,
+        "max_cpu" => s.max_cpu as f64,
+        "p50_cpu" => s.p50_cpu as f64,
+        "p90_cpu" => s.p90_cpu as f64,
+        "p95_cpu" => s.p95_cpu as f64,
+        "p99_cpu" => s.p99_cpu as f64,
+        "cpu_stddev" => s.cpu_stddev as f64,
+        "cpu_high_ratio_30" => s.cpu_high_ratio_30 as f64,
+        "cpu_high_ratio_60" => s.cpu_high_ratio_60 as f64,
+        "avg_mem_mb" => s.avg_mem_mb,
+        "max_mem_mb" => s.max_mem_mb,
+        "p50_mem_mb" => s.p50_mem_mb,
+        "p90_mem_mb" => s.p90_mem_mb,
+        "p95_mem_mb" => s.p95_mem_mb,
+        "p99_mem_mb" => s.p99_mem_mb,
+        "mem_stddev_mb" => s.mem_stddev_mb,
+        "mem_high_ratio_512mb" => s.mem_high_ratio_512mb as f64,
+        "mem_high_ratio_1024mb" => s.mem_high_ratio_1024mb as f64,
+        "mem_growth_rate" => s.mem_growth_rate,
+        "max_gpu_memory_mb" => s.max_gpu_memory_mb?,
+        "max_js_heap_mb" => s.max_js_heap_mb?,
+        "avg_js_heap_utilization" => s.avg_js_heap_utilization?,
+        "max_js_heap_utilization" => s.max_js_heap_utilization?,
+        "js_heap_growth_rate" => s.js_heap_growth_rate?,
+        _ => return None,
+    };
+    Some(v)
+}
+
+fn related_insight(report: &AnalysisReport, metric: &str) -> Option<String> {
+    let prefixes: &[&str] = if metric.contains("cpu") {
+        &["CPU_", "THREAD_"]
+    } else if metric.contains("heap") {
+        &["JS_HEAP_"]
+    } else if metric.contains("mem") {
+        &["MEM_", "GPU_MEMORY_"]
+    } else {
+        return None;
+    };
+    report
+        .insights
+        .iter()
+        .find(|i| prefixes.iter().any(|p| i.code.starts_with(p)))
+        .map(|i| i.message.clone())
+}
+
+pub fn evaluate_budgets(report: &AnalysisReport, budgets: &[PerformanceBudget]) -> Vec<BudgetResult> {
+    let mut out = Vec::new();
+    for b in budgets {
+        let observed = metric_value(report, &b.metric);
+        let insight = related_insight(report, &b.metric);
+        let mut checks = Vec::new();
+        if let Some(max) = b.max {
+            checks.push(("max", max, observed.map(|v| v <= max).unwrap_or(false)));
+        }
+        if let Some(min) = b.min {
+            checks.push(("min", min, observed.map(|v| v >= min).unwrap_or(false)));
+        }
+        for (comparison, threshold, passed) in checks {
+            out.push(BudgetResult {
+                metric: b.metric.clone(),
+                comparison: comparison.to_string(),
+                threshold,
+                observed,
+                passed,
+                insight: insight.clone(),
+            });
+        }
     }
+    out
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone, Utc};
+
+    fn point(pid: u32, cpu: f32, mem_mb: f64, extra: Value) -> crate::models::MetricPoint {
+        let mut v = json!({
+            "timestamp": "2025-01-01T00:00:00Z",
+            "pid": pid,
+            "cpu_usage": cpu,
+            "cpu_os_usage": cpu,
+            "cpu_chrome_usage": null,
+            "memory_rss": (mem_mb * 1024.0 * 1024.0) as u64,
+            "memory_footprint": null,
+            "gpu_usage": null,
+            "js_heap_size": null,
+            "memory_private": null,
+            "custom_metrics": null,
+        });
+        v.as_object_mut().unwrap().extend(extra.as_object().cloned().unwrap_or_default());
+        serde_json::from_value(v).unwrap()
+    }
+
+    // `n` one-second batches; `points(i)` gives the samples of batch `i`.
+    fn series(n: usize, points: impl Fn(usize) -> Vec<crate::models::MetricPoint>) -> Vec<BatchMetric> {
+        let start = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        (0..n)
+            .map(|i| BatchMetric {
+                timestamp: start + Duration::seconds(i as i64),
+                metrics: points(i).into_iter().map(|p| (p.pid, p)).collect(),
+            })
+            .collect()
+    }
+
+    fn codes(report: &AnalysisReport) -> Vec<&str> {
+        report.insights.iter().map(|i| i.code.as_str()).collect()
+    }
+
+    fn insight<'a>(report: &'a AnalysisReport, code: &str) -> &'a Insight {
+        report.insights.iter().find(|i| i.code == code).unwrap_or_else(|| panic!("no {} in {:?}", code, codes(report)))
+    }
+
+    fn param(insight: &Insight, name: &str) -> f64 {
+        insight.params[name].as_f64().unwrap_or_else(|| panic!("{}.{} is not a number", insight.code, name))
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-6, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn no_samples_is_a_no_data_insight() {
+        let report = analyze(&[]);
+        assert_eq!(codes(&report), vec!["NO_DATA"]);
+        assert_eq!(report.insights[0].severity, InsightSeverity::Info);
+        assert!(report.insights[0].params.is_empty());
+        assert_eq!(report.insights_text, vec!["No data collected"]);
+    }
+
+    #[test]
+    fn quiet_run_has_no_insights() {
+        let report = analyze(&series(30, |_| vec![point(1, 5.0, 100.0, json!({}))]));
+        assert!(report.insights.is_empty(), "{:?}", codes(&report));
+        assert_eq!(report.score, 100);
+    }
+
+    #[test]
+    fn cpu_insights_carry_their_numbers() {
+        let report = analyze(&series(20, |_| vec![point(1, 40.0, 100.0, json!({}))]));
+        assert_eq!(codes(&report), vec!["CPU_AVG_HIGH"]);
+        let avg = insight(&report, "CPU_AVG_HIGH");
+        assert_eq!(avg.severity, InsightSeverity::Warning);
+        assert_close(param(avg, "avg_cpu"), 40.0);
+
+        let report = analyze(&series(20, |_| vec![point(1, 70.0, 100.0, json!({}))]));
+        assert_eq!(codes(&report), vec!["CPU_AVG_HIGH", "CPU_SUSTAINED_HIGH"]);
+        let sustained = insight(&report, "CPU_SUSTAINED_HIGH");
+        assert_eq!(sustained.severity, InsightSeverity::Warning);
+        assert_close(param(sustained, "cpu_high_ratio_60"), 1.0);
+        assert_close(param(sustained, "threshold_cpu"), 60.0);
+
+        // One spike in 40 samples: under the sustained share, over the spike threshold.
+        let report = analyze(&series(40, |i| vec![point(1, if i == 7 { 85.0 } else { 5.0 }, 100.0, json!({}))]));
+        assert_eq!(codes(&report), vec!["CPU_SPIKE"]);
+        let spike = insight(&report, "CPU_SPIKE");
+        assert_eq!(spike.severity, InsightSeverity::Warning);
+        assert_close(param(spike, "max_cpu"), 85.0);
+        assert_close(param(spike, "threshold_cpu"), 80.0);
+        assert_eq!(report.insights_text, vec![spike.message.clone()]);
+    }
+
+    #[test]
+    fn memory_insights_carry_their_numbers() {
+        let report = analyze(&series(30, |i| vec![point(1, 5.0, 100.0 + i as f64, json!({}))]));
+        assert_eq!(codes(&report), vec!["MEM_GROWTH_HIGH"]);
+        let growth = insight(&report, "MEM_GROWTH_HIGH");
+        assert_eq!(growth.severity, InsightSeverity::Critical);
+        assert!((param(growth, "mem_growth_rate") - 1.0).abs() < 1e-3);
+
+        let report = analyze(&series(30, |i| vec![point(1, 5.0, 100.0 + i as f64 * 0.25, json!({}))]));
+        assert_eq!(codes(&report), vec!["MEM_GROWTH_SLIGHT"]);
+        let slight = insight(&report, "MEM_GROWTH_SLIGHT");
+        assert_eq!(slight.severity, InsightSeverity::Info);
+        assert!((param(slight, "mem_growth_rate") - 0.25).abs() < 1e-3);
+
+        let report = analyze(&series(30, |_| vec![point(1, 5.0, 2048.0, json!({}))]));
+        assert_eq!(codes(&report), vec!["MEM_HIGH_USAGE"]);
+        let high = insight(&report, "MEM_HIGH_USAGE");
+        assert_eq!(high.severity, InsightSeverity::Warning);
+        assert_close(param(high, "mem_high_ratio_1024mb"), 1.0);
+        assert_close(param(high, "threshold_mb"), 1024.0);
+    }
+
+    #[test]
+    fn js_heap_insights_carry_their_numbers() {
+        const MB: u64 = 1024 * 1024;
+        let report = analyze(&series(30, |i| {
+            vec![point(1, 5.0, 100.0, json!({ "js_heap_size": (20 + i as u64) * MB, "js_heap_total_size": 200 * MB }))]
+        }));
+        assert_eq!(codes(&report), vec!["JS_HEAP_GROWTH"]);
+        let growth = insight(&report, "JS_HEAP_GROWTH");
+        assert_eq!(growth.severity, InsightSeverity::Warning);
+        assert!((param(growth, "js_heap_growth_rate") - 1.0).abs() < 1e-6);
+
+        let report = analyze(&series(30, |_| {
+            vec![point(1, 5.0, 100.0, json!({ "js_heap_size": 95 * MB, "js_heap_total_size": 100 * MB }))]
+        }));
+        assert_eq!(codes(&report), vec!["JS_HEAP_NEARLY_FULL"]);
+        let full = insight(&report, "JS_HEAP_NEARLY_FULL");
+        assert_eq!(full.severity, InsightSeverity::Warning);
+        assert_close(param(full, "max_js_heap_utilization"), 0.95);
+    }
+
+    #[test]
+    fn thread_saturation_is_reported_per_pid() {
+        let report = analyze(&series(20, |i| {
+            vec![
+                point(1, 20.0, 100.0, json!({ "max_thread_cpu": if i % 4 == 0 { 50.0 } else { 95.0 } })),
+                point(2, 10.0, 100.0, json!({ "max_thread_cpu": 40.0 })),
+            ]
+        }));
+        assert_eq!(codes(&report), vec!["THREAD_SATURATED"]);
+        let saturated = insight(&report, "THREAD_SATURATED");
+        assert_eq!(saturated.severity, InsightSeverity::Warning);
+        assert_eq!(saturated.params["pid"], json!(1));
+        assert_close(param(saturated, "saturated_share"), 0.75);
+        assert_close(param(saturated, "threshold_cpu"), 90.0);
+        assert_close(param(saturated, "avg_cpu"), 20.0);
+    }
+
+    #[test]
+    fn coverage_and_memory_basis_insights_list_their_pids() {
+        let report = analyze(&series(20, |i| {
+            let basis = if i < 15 { "rss" } else { "private_cdp" };
+            let mut points = vec![point(1, 5.0, 100.0, json!({ "memory_basis": basis }))];
+            if i % 2 == 0 {
+                points.push(point(2, 5.0, 100.0, json!({ "memory_basis": "rss" })));
+            }
+            points
+        }));
+        assert_eq!(codes(&report), vec!["LOW_COVERAGE", "MEMORY_BASIS_MIXED"]);
+        let coverage = insight(&report, "LOW_COVERAGE");
+        assert_eq!(coverage.severity, InsightSeverity::Info);
+        assert_eq!(coverage.params["coverage_pct_by_pid"], json!({ "2": 50.0 }));
+        assert_close(param(coverage, "threshold_pct"), 90.0);
+        let mixed = insight(&report, "MEMORY_BASIS_MIXED");
+        assert_eq!(mixed.severity, InsightSeverity::Warning);
+        assert_eq!(mixed.params["samples_by_basis_by_pid"], json!({ "1": { "rss": 15, "private_cdp": 5 } }));
+    }
+
+    #[test]
+    fn sleep_gaps_exclude_samples_with_an_insight() {
+        let metrics = series(60, |_| vec![point(1, 5.0, 100.0, json!({}))]);
+        let resumed = metrics[30].timestamp;
+        let events = vec![RunEvent {
+            timestamp: resumed,
+            kind: RunEventKind::MachineSlept,
+            pid: None,
+            detail: json!({ "gap_ms": 5000, "resumed_at": resumed.to_rfc3339() }),
+        }];
+        let report = analyze_with_events(&metrics, &events);
+        assert_eq!(codes(&report), vec!["SLEEP_SAMPLES_EXCLUDED"]);
+        let excluded = insight(&report, "SLEEP_SAMPLES_EXCLUDED");
+        assert_eq!(excluded.severity, InsightSeverity::Info);
+        // 5 s before the resume through the 3 s settle window, both ends included.
+        assert_eq!(excluded.params, json!({ "excluded_samples": 9, "sleep_gaps": 1, "stall_gaps": 0 }).as_object().unwrap().clone());
+        assert_eq!(report.insights_text, vec!["Excluded 9 sample(s) around 1 machine sleep gap(s)"]);
+    }
+
+    #[test]
+    fn meta_insights_carry_their_numbers() {
+        const MB: u64 = 1024 * 1024;
+        let metrics = series(10, |_| {
+            vec![
+                point(1, 5.0, 100.0, json!({ "gpu_memory_bytes": 900 * MB })),
+                point(9, 2.0, 50.0, json!({})),
+            ]
+        });
+        let meta = ReportMeta::from_json_str(
+            &json!({
+                "env": { "gpu": { "adapter_memory_bytes": 1024 * MB } },
+                "collection": {
+                    "self_pids": [9],
+                    "data_sources": [
+                        { "source": "sidecar", "backend": "psutil", "samples": 10, "sequence": { "expected": 100, "received": 95, "lost": 5 } },
+                        { "source": "websocket", "backend": "extension", "samples": 10, "sequence": { "expected": 100, "received": 100, "lost": 0 } },
+                    ],
+                    "process_roles": {
+                        "1": { "observed_ms": 10000, "background_ms": 5000, "background_fraction": 0.5, "transitions": 2 },
+                        "2": { "observed_ms": 10000, "background_ms": 1000, "background_fraction": 0.1, "transitions": 1 },
+                    },
+                },
+            })
+            .to_string(),
+        );
+        let report = analyze_report(&metrics, &meta);
+        assert_eq!(codes(&report), vec!["SELF_OVERHEAD", "SAMPLE_LOSS", "PROCESS_BACKGROUNDED", "GPU_MEMORY_NEAR_ADAPTER"]);
+        assert!(report.insights.iter().skip(1).all(|i| i.severity == InsightSeverity::Warning));
+
+        let overhead = insight(&report, "SELF_OVERHEAD");
+        assert_eq!(overhead.severity, InsightSeverity::Info);
+        assert_eq!(overhead.params["pids"], json!([9]));
+        assert_close(param(overhead, "avg_cpu"), 2.0);
+        assert_close(param(overhead, "avg_mem_mb"), 50.0);
+        let loss = insight(&report, "SAMPLE_LOSS");
+        assert_eq!(loss.params, json!({ "source": "sidecar", "expected": 100, "received": 95, "lost": 5 }).as_object().unwrap().clone());
+        let background = insight(&report, "PROCESS_BACKGROUNDED");
+        assert_eq!(background.params["pids"], json!([1]));
+        assert_close(param(background, "max_background_fraction"), 0.5);
+        let gpu = insight(&report, "GPU_MEMORY_NEAR_ADAPTER");
+        assert_close(param(gpu, "max_gpu_memory_mb"), 900.0);
+        assert_close(param(gpu, "adapter_memory_mb"), 1024.0);
+        assert_eq!(report.insights_text.len(), 4);
+    }
+
+    #[test]
+    fn stored_insights_load_structured_or_as_legacy_text() {
+        let structured: Insight = serde_json::from_value(json!({
+            "code": "CPU_SPIKE",
+            "severity": "warning",
+            "message": "CPU spike detected: 85.0%",
+            "params": { "max_cpu": 85.0 },
+        }))
+        .unwrap();
+        assert_eq!(structured.code, "CPU_SPIKE");
+        assert_eq!(structured.severity, InsightSeverity::Warning);
+        assert_close(param(&structured, "max_cpu"), 85.0);
+
+        let legacy: Insight = serde_json::from_value(json!("High Memory Growth detected (+1.00 MB/sample)")).unwrap();
+        assert_eq!(legacy.code, "LEGACY");
+        assert_eq!(legacy.severity, InsightSeverity::Info);
+        assert_eq!(legacy.message, "High Memory Growth detected (+1.00 MB/sample)");
+        assert!(legacy.params.is_empty());
+        assert_eq!(serde_json::to_value(&legacy).unwrap(), json!({ "code": "LEGACY", "severity": "info", "message": legacy.message }));
+    }
+}
@@ -0,0 +1,298 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+
+// Keys whose values identify the machine or the user; removed wherever they appear.
+const DROPPED_KEYS: &[&str] =
+    &["device_name", "hostname", "host_name", "username", "user_name", "cmdline", "command_line", "exe", "exe_path", "cwd"];
+// Keys holding a page or window title.
+const TITLE_KEYS: &[&str] = &["title", "window_title"];
+// Keys holding a URL.
+const URL_KEYS: &[&str] = &["url", "page_url"];
+
+/// What `Anonymizer` does beyond the fixed rules (titles, host and user names, command lines).
+/// Every field is optional in JSON.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnonymizeOptions {
+    /// Replace URLs with a salted hash instead of reducing them to their origin.
+    pub hash_urls: bool,
+    /// Replace every folder path segment with "folder-<n>".
+    pub mask_folders: bool,
+    /// Also write the original -> replacement map to a local file next to the export
+    /// (`<export>.anonymization.json`). It is never put inside the export.
+    pub write_map: bool,
+}
+
+/// Strips or replaces sensitive fields of exported datasets. One instance per export, so a title,
+/// URL or folder gets the same replacement in every report of a bundle.
+pub struct Anonymizer {
+    pub options: AnonymizeOptions,
+    salt: String,
+    // Original -> replacement per kind ("titles", "urls", "folders"), for `write_map`.
+    map: BTreeMap<&'static str, BTreeMap<String, String>>,
+}
+
+impl Anonymizer {
+    pub fn new(options: AnonymizeOptions) -> Self {
+        Self {
+            options,
+            salt: uuid::Uuid::new_v4().simple().to_string(),
+            map: BTreeMap::new(),
+        }
+    }
+
+    /// The replacements made so far, per kind.
+    pub fn map(&self) -> &BTreeMap<&'static str, BTreeMap<String, String>> {
+        &self.map
+    }
+
+    // The replacement of `original` within `kind`, made by `make` (given the kind's count so far)
+    // the first time it is seen.
+    fn replace(&mut self, kind: &'static str, original: &str, make: impl FnOnce(&Self, usize) -> String) -> String {
+        if let Some(r) = self.map.get(kind).and_then(|m| m.get(original)) {
+            return r.clone();
+        }
+        let n = self.map.get(kind).map_or(0, |m| m.len());
+        let r = make(self, n);
+        self.map.entry(kind).or_default().insert(original.to_string(), r.clone());
+        r
+    }
+
+    /// Anonymize a dataset (`ReportDataset` JSON): the report's meta and analysis and the
+    /// dataset header. The report's own title and the samples are kept.
+    pub fn dataset(&mut self, dataset: &mut Value) {
+        let Some(obj) = dataset.as_object_mut() else { return };
+        for (key, v) in obj.iter_mut() {
+            match key.as_str() {
+                "report" => {
+                    if let Some(report) = v.as_object_mut() {
+                        for field in ["meta", "analysis"] {
+                            if let Some(x) = report.get_mut(field) {
+                                self.value(x);
+                            }
+                        }
+                    }
+                }
+                "filter" | "schema_version" | "exported_at" => {}
+                _ => self.value(v),
+            }
+        }
+        // Aliases are often the tab title; replace those once every title has its name.
+        self.aliases(dataset);
+    }
+
+    fn value(&mut self, v: &mut Value) {
+        match v {
+            Value::Object(obj) => self.object(obj),
+            Value::Array(items) => items.iter_mut().for_each(|x| self.value(x)),
+            _ => {}
+        }
+    }
+
+    /// Replacement for a folder path: unchanged unless `mask_folders` is set.
+    pub fn folder(&mut self, path: &str) -> String {
+        if !self.options.mask_folders || path.is_empty() {
+            return path.to_string();
+        }
+        path.split('/')
+            .map(|seg| self.replace("folders", seg, |_, n| format!("folder-{}", n + 1)))
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    fn object(&mut self, obj: &mut Map<String, Value>) {
+        obj.retain(|k, _| !DROPPED_KEYS.contains(&k.as_str()));
+        // A tab_title_changed event carries the old and new title in its detail.
+        let title_event = obj.get("kind").and_then(Value::as_str) == Some("tab_title_changed");
+        for (key, v) in obj.iter_mut() {
+            match (key.as_str(), v) {
+                (k, Value::String(s)) if TITLE_KEYS.contains(&k) => *s = self.title(s),
+                (k, Value::String(s)) if URL_KEYS.contains(&k) => *s = self.url(s),
+                ("folder_path", Value::String(s)) => *s = self.folder(s),
+                ("detail", Value::Object(detail)) if title_event => {
+                    for k in ["from", "to"] {
+                        if let Some(Value::String(s)) = detail.get_mut(k) {
+                            *s = self.title(s);
+                        }
+                    }
+                }
+                (_, v) => self.value(v),
+            }
+        }
+    }
+
+    // "Tab <n>", the same for every occurrence of one title.
+    fn title(&mut self, title: &str) -> String {
+        if title.trim().is_empty() {
+            return String::new();
+        }
+        self.replace("titles", title, |_, n| format!("Tab {}", n + 1))
+    }
+
+    // The URL's origin, or a salted hash of the whole URL with `hash_urls` (or when it has none).
+    fn url(&mut self, url: &str) -> String {
+        if url.trim().is_empty() {
+            return String::new();
+        }
+        self.replace("urls", url, |this, _| {
+            let origin = url::Url::parse(url)
+                .ok()
+                .map(|u| u.origin())
+                .filter(|o| o.is_tuple())
+                .map(|o| o.ascii_serialization());
+            match origin {
+                Some(origin) if !this.options.hash_urls => origin,
+                _ => {
+                    let mut h = Sha256::new();
+                    h.update(this.salt.as_bytes());
+                    h.update(url.as_bytes());
+                    let hash: String = h.finalize().iter().take(8).map(|b| format!("{:02x}", b)).collect();
+                    format!("url-{}", hash)
+                }
+            }
+        })
+    }
+
+    // Aliases equal to a replaced title get the title's replacement; others were typed by the
+    // user and are kept.
+    fn aliases(&self, v: &mut Value) {
+        match v {
+            Value::Object(obj) => {
+                for (key, v) in obj.iter_mut() {
+                    match (key.as_str(), v) {
+                        ("alias", Value::String(s)) => {
+                            if let Some(r) = self.map.get("titles").and_then(|m| m.get(s.as_str())) {
+                                *s = r.clone();
+                            }
+                        }
+                        (_, v) => self.aliases(v),
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|x| self.aliases(x)),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // Every value a dataset may carry that identifies the user, the machine or what was open.
+    pub(crate) const SENSITIVE: &[&str] = &[
+        "alice-macbook.corp.example",
+        "alice",
+        "/Applications/Google Chrome.app",
+        "--user-data-dir",
+        "Q3 reorg plan - Google Docs",
+        "secret-doc-id",
+        "Inbox (3)",
+        "Launch plans",
+        "payroll",
+    ];
+
+    // A dataset with each sensitive value in a field the anonymizer is meant to handle.
+    pub(crate) fn sensitive_meta() -> Value {
+        json!({
+            "env": { "os": "macos", "device_name": "alice-macbook.corp.example", "hostname": "alice-macbook.corp.example" },
+            "collection": { "mode": "browser", "folder_path": "alice/payroll", "extra": { "username": "alice" } },
+            "process_snapshot": [{
+                "pid": 42,
+                "title": "Q3 reorg plan - Google Docs",
+                "url": "https://docs.example.com/d/secret-doc-id/edit",
+                "window_title": "Launch plans",
+                "cmdline": ["/Applications/Google Chrome.app/Contents/MacOS/Google Chrome", "--user-data-dir=/Users/alice"],
+                "exe": "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome",
+                "cwd": "/Users/alice",
+                "targets": [{ "id": "T1", "title": "Q3 reorg plan - Google Docs", "url": "https://docs.example.com/d/secret-doc-id/edit" }],
+            }],
+            "process_aliases": [{ "pid": 42, "alias": "Q3 reorg plan - Google Docs" }, { "pid": 43, "alias": "GPU" }],
+            "events": [{
+                "timestamp": "2025-01-01T00:00:05Z",
+                "kind": "tab_title_changed",
+                "pid": 42,
+                "detail": { "from": "Inbox (3)", "to": "Launch plans" },
+            }],
+        })
+    }
+
+    fn dataset() -> Value {
+        json!({
+            "schema_version": 3,
+            "exported_at": "2025-01-01T00:00:00Z",
+            "report": { "id": 1, "title": "Nightly", "metrics": [], "analysis": null, "meta": sensitive_meta() },
+            "meta_summary": { "device_name": "alice-macbook.corp.example", "folder_path": "alice/payroll" },
+        })
+    }
+
+    fn anonymized(options: AnonymizeOptions) -> (Value, Anonymizer) {
+        let mut anon = Anonymizer::new(options);
+        let mut v = dataset();
+        anon.dataset(&mut v);
+        (v, anon)
+    }
+
+    #[test]
+    fn no_sensitive_value_survives() {
+        let (v, _) = anonymized(AnonymizeOptions { mask_folders: true, ..Default::default() });
+        let out = serde_json::to_string(&v).unwrap();
+        for s in SENSITIVE {
+            assert!(!out.contains(s), "{:?} survived in {}", s, out);
+        }
+        for key in DROPPED_KEYS {
+            assert!(!out.contains(&format!("\"{}\"", key)), "{} survived", key);
+        }
+    }
+
+    #[test]
+    fn titles_urls_and_folders_get_stable_replacements() {
+        let (v, anon) = anonymized(AnonymizeOptions { mask_folders: true, ..Default::default() });
+        let meta = &v["report"]["meta"];
+        let snapshot = &meta["process_snapshot"][0];
+        let titles = &anon.map()["titles"];
+        assert_eq!(titles.len(), 3);
+        let (doc, launch, inbox) =
+            (&titles["Q3 reorg plan - Google Docs"], &titles["Launch plans"], &titles["Inbox (3)"]);
+        assert!(doc.starts_with("Tab ") && launch.starts_with("Tab ") && inbox.starts_with("Tab "));
+        assert!(doc != launch && launch != inbox && doc != inbox);
+        // One title gets the same name everywhere it appears.
+        assert_eq!(snapshot["title"], json!(doc));
+        assert_eq!(snapshot["targets"][0]["title"], json!(doc));
+        assert_eq!(snapshot["window_title"], json!(launch));
+        assert_eq!(meta["events"][0]["detail"], json!({ "from": inbox, "to": launch }));
+        assert_eq!(snapshot["url"], "https://docs.example.com");
+        assert_eq!(snapshot["pid"], 42);
+        // An alias copied from the title follows it; one the user typed is kept.
+        assert_eq!(meta["process_aliases"], json!([{ "pid": 42, "alias": doc }, { "pid": 43, "alias": "GPU" }]));
+        assert_eq!(meta["collection"]["folder_path"], "folder-1/folder-2");
+        assert_eq!(v["meta_summary"]["folder_path"], "folder-1/folder-2");
+        assert_eq!(meta["env"], json!({ "os": "macos" }));
+        // The report's own title, the header fields and the samples are left alone.
+        assert_eq!(v["report"]["title"], "Nightly");
+        assert_eq!(v["exported_at"], "2025-01-01T00:00:00Z");
+
+        assert_eq!(anon.map()["urls"]["https://docs.example.com/d/secret-doc-id/edit"], "https://docs.example.com");
+        assert_eq!(anon.map()["folders"]["payroll"], "folder-2");
+    }
+
+    #[test]
+    fn hashed_urls_keep_nothing_of_the_url_and_differ_per_export() {
+        let (v, _) = anonymized(AnonymizeOptions { hash_urls: true, ..Default::default() });
+        let url = v["report"]["meta"]["process_snapshot"][0]["url"].as_str().unwrap().to_string();
+        assert!(url.starts_with("url-") && url.len() == 4 + 16, "{}", url);
+        assert!(!url.contains("example"));
+        let (again, _) = anonymized(AnonymizeOptions { hash_urls: true, ..Default::default() });
+        assert_ne!(again["report"]["meta"]["process_snapshot"][0]["url"], json!(url), "salted per export");
+    }
+
+    #[test]
+    fn folders_are_kept_unless_masked() {
+        let (v, anon) = anonymized(AnonymizeOptions::default());
+        assert_eq!(v["report"]["meta"]["collection"]["folder_path"], "alice/payroll");
+        assert!(!anon.map().contains_key("folders"));
+    }
+}
@@ -0,0 +1,305 @@
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use base64::Engine;
+use crate::error::PerfSightError;
+
+pub const DEFAULT_MAX_ARTIFACT_MB: u64 = 512;
+
+// Base64 characters decoded per step; a multiple of 4 so chunks never split a quantum.
+const DECODE_CHUNK_CHARS: usize = 64 * 1024;
+
+pub fn mb_to_bytes(mb: u64) -> u64 {
+    mb.saturating_mul(1024 * 1024)
+}
+
+fn too_large(field: &str, limit_bytes: u64) -> PerfSightError {
+    PerfSightError::TooLarge { field: field.to_string(), limit_bytes }
+}
+
+// Accept:
+// - raw base64 "JVBERi0xLjc..."
+// - "data:application/pdf;base64,JVBERi0xLjc..."
+// - jsPDF output("datauristring") which often looks like:
+//   "data:application/pdf;filename=generated.pdf;base64,JVBERi0xLjc..."
+fn base64_payload(s: &str) -> &str {
+    let trimmed = s.trim();
+    match trimmed.find("base64,") {
+        Some(idx) => &trimmed[idx + "base64,".len()..],
+        None => trimmed,
+    }
+}
+
+/// Decoded size of a base64 string (data URL prefix and whitespace ignored), without decoding it.
+pub fn decoded_len(s: &str) -> u64 {
+    let mut chars = 0u64;
+    let mut padding = 0u64;
+    for b in base64_payload(s).bytes().filter(|b| !b.is_ascii_whitespace()) {
+        chars += 1;
+        if b == b'=' {
+            padding += 1;
+        }
+    }
+    (chars / 4 * 3 + (chars % 4).saturating_sub(1)).saturating_sub(padding)
+}
+
+/// Decode raw base64 or a data URL into `out` in fixed-size chunks, so the decoded bytes are never
+/// held in memory all at once. Fails before writing anything when the result would exceed
+/// `max_bytes`. Returns the number of bytes written.
+pub fn decode_base64_to_writer<W: Write>(
+    s: &str,
+    out: &mut W,
+    max_bytes: u64,
+    field: &str,
+) -> Result<u64, PerfSightError> {
+    if decoded_len(s) > max_bytes {
+        return Err(too_large(field, max_bytes));
+    }
+    let engine = &base64::engine::general_purpose::STANDARD;
+    let mut chunk: Vec<u8> = Vec::with_capacity(DECODE_CHUNK_CHARS);
+    let mut buf = vec![0u8; DECODE_CHUNK_CHARS / 4 * 3];
+    let mut written = 0u64;
+    let mut flush = |chunk: &[u8], written: &mut u64| -> Result<(), PerfSightError> {
+        let n = engine
+            .decode_slice(chunk, &mut buf)
+            .map_err(|e| PerfSightError::invalid_input(field, format!("base64 decode failed: {e}")))?;
+        out.write_all(&buf[..n])?;
+        *written += n as u64;
+        Ok(())
+    };
+    // Some encoders may insert newlines; skip whitespace.
+    for b in base64_payload(s).bytes().filter(|b| !b.is_ascii_whitespace()) {
+        chunk.push(b);
+        if chunk.len() == DECODE_CHUNK_CHARS {
+            flush(&chunk, &mut written)?;
+            chunk.clear();
+        }
+    }
+    if !chunk.is_empty() {
+        flush(&chunk, &mut written)?;
+    }
+    Ok(written)
+}
+
+/// Copy at most `max_bytes` from `reader`; anything longer is rejected rather than truncated.
+pub fn copy_limited<R: Read, W: Write>(
+    reader: R,
+    out: &mut W,
+    max_bytes: u64,
+    field: &str,
+) -> Result<u64, PerfSightError> {
+    let copied = std::io::copy(&mut reader.take(max_bytes.saturating_add(1)), out)?;
+    if copied > max_bytes {
+        return Err(too_large(field, max_bytes));
+    }
+    Ok(copied)
+}
+
+pub fn read_to_string_limited<R: Read>(reader: R, max_bytes: u64, field: &str) -> Result<String, PerfSightError> {
+    let mut bytes = Vec::new();
+    copy_limited(reader, &mut bytes, max_bytes, field)?;
+    String::from_utf8(bytes).map_err(|e| PerfSightError::invalid_input(field, e.to_string()))
+}
+
+/// Size check for a file on disk, before anything is read.
+pub fn check_file_size(path: &Path, max_bytes: u64, field: &str) -> Result<u64, PerfSightError> {
+    let len = std::fs::metadata(path).map_err(|e| path_io_error(path, e))?.len();
+    if len > max_bytes {
+        return Err(too_large(field, max_bytes));
+    }
+    Ok(len)
+}
+
+/// Size check for a payload that already arrived over IPC (e.g. a dataset JSON string).
+pub fn check_payload_size(payload: &str, max_bytes: u64, field: &str) -> Result<(), PerfSightError> {
+    if payload.len() as u64 > max_bytes {
+        return Err(too_large(field, max_bytes));
+    }
+    Ok(())
+}
+
+// Longest file stem kept, in bytes; leaves room for " (n)" and the extension on every platform.
+const MAX_FILENAME_STEM: usize = 120;
+
+const WINDOWS_RESERVED: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Turn a user-supplied name into a plain file name ending in `.ext`: path separators, reserved and
+/// control characters become `_`, Windows device names (CON, NUL, ...) get a `_` prefix, and the
+/// stem is capped at `MAX_FILENAME_STEM` bytes. Falls back to `fallback` when nothing is left.
+pub fn sanitize_export_filename(name: &str, ext: &str, fallback: &str) -> String {
+    let suffix = format!(".{}", ext.trim_start_matches('.'));
+    let cleaned: String = name
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | '<' | '>' | ':' | '"' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let split = cleaned.len().checked_sub(suffix.len()).filter(|i| cleaned.is_char_boundary(*i));
+    let mut stem = match split {
+        Some(i) if cleaned[i..].eq_ignore_ascii_case(&suffix) => cleaned[..i].to_string(),
+        _ => cleaned,
+    };
+    // Leading dots would hide the file (or spell ".."); Windows drops trailing dots and spaces.
+    stem = stem.trim_start_matches(['.', ' ']).trim_end_matches(['.', ' ']).to_string();
+    if stem.len() > MAX_FILENAME_STEM {
+        let mut cut = MAX_FILENAME_STEM;
+        while !stem.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        stem.truncate(cut);
+        stem = stem.trim_end_matches(['.', ' ']).to_string();
+    }
+    if stem.is_empty() {
+        return if fallback.is_empty() { format!("export{}", suffix) } else { sanitize_export_filename(fallback, ext, "") };
+    }
+    let device = stem.split('.').next().unwrap_or("").trim_end();
+    if WINDOWS_RESERVED.iter().any(|r| r.eq_ignore_ascii_case(device)) {
+        stem.insert(0, '_');
+    }
+    format!("{}{}", stem, suffix)
+}
+
+/// `dir/file_name`, or `dir/stem (2).ext`, `dir/stem (3).ext`, ... when that already exists and
+/// `overwrite` is off.
+pub fn unique_export_path(dir: &Path, file_name: &str, overwrite: bool) -> PathBuf {
+    let path = dir.join(file_name);
+    if overwrite || !path.exists() {
+        return path;
+    }
+    let (stem, ext) = match file_name.rfind('.') {
+        Some(idx) if idx > 0 => (&file_name[..idx], &file_name[idx..]),
+        _ => (file_name, ""),
+    };
+    (2u32..)
+        .map(|n| dir.join(format!("{} ({}){}", stem, n, ext)))
+        .find(|p| !p.exists())
+        .unwrap_or(path)
+}
+
+/// io error on `path` as a structured error: permission problems and missing paths get their own
+/// kinds so the UI can say which folder is at fault.
+pub fn path_io_error(path: &Path, e: std::io::Error) -> PerfSightError {
+    let shown = path.to_string_lossy().to_string();
+    match e.kind() {
+        std::io::ErrorKind::PermissionDenied => PerfSightError::PermissionDenied { path: shown },
+        std::io::ErrorKind::NotFound => PerfSightError::InvalidPath { path: shown, reason: "does not exist".to_string() },
+        _ => PerfSightError::Io(format!("{}: {}", shown, e)),
+    }
+}
+
+/// Paths go back to the frontend as strings; refuse to hand out a lossy one.
+pub fn path_string(path: &Path) -> Result<String, PerfSightError> {
+    path.to_str().map(str::to_string).ok_or_else(|| PerfSightError::InvalidPath {
+        path: path.to_string_lossy().to_string(),
+        reason: "not valid UTF-8".to_string(),
+    })
+}
+
+/// Check that `dir` is an existing, writable directory (creating it first when `create` is set).
+pub fn ensure_writable_dir(dir: &Path, create: bool) -> Result<(), PerfSightError> {
+    if !dir.exists() {
+        if !create {
+            return Err(PerfSightError::InvalidPath {
+                path: dir.to_string_lossy().to_string(),
+                reason: "does not exist".to_string(),
+            });
+        }
+        std::fs::create_dir_all(dir).map_err(|e| path_io_error(dir, e))?;
+    }
+    if !dir.is_dir() {
+        return Err(PerfSightError::InvalidPath {
+            path: dir.to_string_lossy().to_string(),
+            reason: "is not a directory".to_string(),
+        });
+    }
+    // Read-only mounts and ACLs only show up on an actual write.
+    let probe = dir.join(format!(".perfsight-write-test-{}", std::process::id()));
+    std::fs::File::create(&probe).map_err(|e| path_io_error(dir, e))?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(())
+}
+
+/// Path for a new export in `dir` (checked, and created with `create`): `requested` (or
+/// `default_name`) sanitized with `sanitize_export_filename`, so it can't leave `dir`, and made
+/// unique with `unique_export_path` unless `overwrite` is set.
+pub fn export_path_in(
+    dir: &Path,
+    create: bool,
+    requested: Option<&str>,
+    ext: &str,
+    default_name: &str,
+    overwrite: bool,
+) -> Result<PathBuf, PerfSightError> {
+    ensure_writable_dir(dir, create)?;
+    let name = sanitize_export_filename(requested.unwrap_or(""), ext, default_name);
+    Ok(unique_export_path(dir, &name, overwrite))
+}
+
+/// Write a whole export file, mapping failures with `path_io_error`.
+pub fn write_export_file(path: &Path, bytes: &[u8]) -> Result<(), PerfSightError> {
+    std::fs::write(path, bytes).map_err(|e| path_io_error(path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh directory under the system temp dir, removed on drop.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("perfsight-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn windows_device_names_get_a_prefix() {
+        assert_eq!(sanitize_export_filename("CON", "csv", "x"), "_CON.csv");
+        assert_eq!(sanitize_export_filename("nul.csv", "csv", "x"), "_nul.csv");
+        assert_eq!(sanitize_export_filename("Com1.backup", "json", "x"), "_Com1.backup.json");
+        assert_eq!(sanitize_export_filename("CONSOLE", "csv", "x"), "CONSOLE.csv");
+    }
+
+    #[test]
+    fn export_paths_stay_in_the_destination() {
+        let tmp = TempDir::new("export-paths");
+        let cases = [
+            ("../../etc/passwd", "_.._etc_passwd.csv"),
+            ("..", "report.csv"),
+            ("/tmp/absolute.csv", "_tmp_absolute.csv"),
+            ("C:\\Windows\\evil", "C__Windows_evil.csv"),
+            ("NUL", "_NUL.csv"),
+            ("", "report.csv"),
+        ];
+        for (requested, expected) in cases {
+            let path = export_path_in(&tmp.0, true, Some(requested), "csv", "report", true).unwrap();
+            assert_eq!(path.parent(), Some(tmp.0.as_path()), "requested {:?}", requested);
+            assert_eq!(path.file_name().unwrap(), expected, "requested {:?}", requested);
+        }
+    }
+
+    #[test]
+    fn existing_exports_are_kept() {
+        let tmp = TempDir::new("export-unique");
+        let first = export_path_in(&tmp.0, true, Some("run"), "md", "run", false).unwrap();
+        write_export_file(&first, b"first").unwrap();
+        let second = export_path_in(&tmp.0, true, Some("run"), "md", "run", false).unwrap();
+        assert_eq!(second, tmp.0.join("run (2).md"));
+        assert_eq!(export_path_in(&tmp.0, false, Some("run"), "md", "run", true).unwrap(), first);
+        assert!(export_path_in(&tmp.0.join("missing"), false, None, "md", "run", false).is_err());
+    }
+}
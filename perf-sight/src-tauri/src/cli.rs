@@ -0,0 +1,363 @@
+use std::path::PathBuf;
+use std::time::Duration;
+use chrono::Utc;
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager, State};
+use crate::analysis::{analyze_report, evaluate_budgets};
+use crate::artifacts::{export_path_in, write_export_file};
+use crate::commands::{
+    check_folder_budgets_with, resolve_export_dir, start_collection_with, stop_collection_and_save,
+    CollectionState,
+};
+use crate::database::{Database, ReportDetail};
+use crate::dataset::{DatasetHeaderV2, ReportDataset, DATASET_SCHEMA_VERSION};
+use crate::junit;
+use crate::markdown;
+use crate::csv_export::render_samples_csv;
+use crate::models::{CollectionConfig, PerformanceBudget, TestContext};
+use crate::timezone::DisplayZone;
+
+const USAGE: &str = "Usage: perf-sight --headless --pids <pid,pid..> --duration <seconds> \
+[--mode system|browser|simulate] [--interval-ms <ms>] [--folder <path>] [--scenario <name>] [--build-id <id>] \
+[--tags a,b] [--budget <metric><=|>=<value>].. [--export json,csv,junit,md] [--out-dir <dir>] \
+[--timezone <iana-name|local>] [--exit-code-on-budget-fail] [--allow-empty]\n\
+       perf-sight --headless --check-folder-budgets <path> [--scenario <name>] [--last-n <n>]";
+
+/// Exit codes: 0 ok, 1 budget failure (with --exit-code-on-budget-fail, or a failed
+/// --check-folder-budgets), 2 usage/runtime error, including a run that collected nothing
+/// (unless --allow-empty).
+pub const EXIT_BUDGET_FAILED: i32 = 1;
+pub const EXIT_ERROR: i32 = 2;
+
+#[derive(Debug, Clone)]
+pub struct HeadlessArgs {
+    pub mode: String,
+    pub pids: Vec<u32>,
+    pub interval_ms: u64,
+    pub duration_seconds: u64,
+    pub folder_path: Option<String>,
+    pub scenario: Option<String>,
+    pub build_id: Option<String>,
+    pub tags: Vec<String>,
+    pub budgets: Vec<PerformanceBudget>,
+    pub exports: Vec<String>,
+    pub out_dir: Option<PathBuf>,
+    /// Zone for CSV and Markdown timestamps.
+    pub timezone: DisplayZone,
+    pub exit_code_on_budget_fail: bool,
+    /// Treat a run that collected no samples as success instead of an error.
+    pub allow_empty: bool,
+    /// Check the budgets of the newest `last_n` reports in this folder instead of collecting.
+    pub check_folder_budgets: Option<String>,
+    pub last_n: usize,
+}
+
+fn split_list(s: &str) -> Vec<String> {
+    s.split(',')
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect()
+}
+
+// "p95_cpu<=40" / "score>=70"
+fn parse_budget(s: &str) -> Result<PerformanceBudget, String> {
+    let (metric, value, is_max) = if let Some((m, v)) = s.split_once("<=") {
+        (m, v, true)
+    } else if let Some((m, v)) = s.split_once(">=") {
+        (m, v, false)
+    } else {
+        return Err(format!("Invalid --budget '{}': expected <metric><=<value> or <metric>>=<value>", s));
+    };
+    let value: f64 = value
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid --budget '{}': threshold is not a number", s))?;
+    let metric = metric.trim().to_string();
+    if metric.is_empty() {
+        return Err(format!("Invalid --budget '{}': missing metric", s));
+    }
+    Ok(PerformanceBudget {
+        metric,
+        max: if is_max { Some(value) } else { None },
+        min: if is_max { None } else { Some(value) },
+    })
+}
+
+/// Parse process arguments. Returns Ok(None) when `--headless` is not present (normal UI launch).
+pub fn parse_headless_args(args: &[String]) -> Result<Option<HeadlessArgs>, String> {
+    if !args.iter().any(|a| a == "--headless") {
+        return Ok(None);
+    }
+
+    let mut out = HeadlessArgs {
+        mode: "system".to_string(),
+        pids: Vec::new(),
+        interval_ms: 1000,
+        duration_seconds: 0,
+        folder_path: None,
+        scenario: None,
+        build_id: None,
+        tags: Vec::new(),
+        budgets: Vec::new(),
+        exports: Vec::new(),
+        out_dir: None,
+        timezone: DisplayZone::Utc,
+        exit_code_on_budget_fail: false,
+        allow_empty: false,
+        check_folder_budgets: None,
+        last_n: 5,
+    };
+
+    let mut it = args.iter().skip(1);
+    while let Some(arg) = it.next() {
+        // Accept both "--flag value" and "--flag=value".
+        let (flag, inline) = match arg.split_once('=') {
+            Some((f, v)) if f.starts_with("--") => (f, Some(v.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = |name: &str| -> Result<String, String> {
+            inline
+                .clone()
+                .or_else(|| it.next().cloned())
+                .ok_or_else(|| format!("Missing value for {}", name))
+        };
+        match flag {
+            "--headless" => {}
+            "--exit-code-on-budget-fail" => out.exit_code_on_budget_fail = true,
+            "--allow-empty" => out.allow_empty = true,
+            "--mode" => {
+                let m = value(flag)?;
+                if !["system", "browser", "simulate"].contains(&m.as_str()) {
+                    return Err(format!("Invalid --mode '{}': expected system, browser or simulate", m));
+                }
+                out.mode = m;
+            }
+            "--pids" => {
+                for p in split_list(&value(flag)?) {
+                    out.pids.push(p.parse().map_err(|_| format!("Invalid pid '{}'", p))?);
+                }
+            }
+            "--interval-ms" => {
+                out.interval_ms = value(flag)?
+                    .parse()
+                    .ok()
+                    .filter(|v| *v > 0)
+                    .ok_or("--interval-ms must be a positive integer")?;
+            }
+            "--duration" => {
+                out.duration_seconds = value(flag)?
+                    .parse()
+                    .ok()
+                    .filter(|v| *v > 0)
+                    .ok_or("--duration must be a positive number of seconds")?;
+            }
+            "--folder" => out.folder_path = Some(value(flag)?),
+            "--scenario" => out.scenario = Some(value(flag)?),
+            "--build-id" => out.build_id = Some(value(flag)?),
+            "--tags" => out.tags = split_list(&value(flag)?),
+            "--budget" => out.budgets.push(parse_budget(&value(flag)?)?),
+            "--export" => {
+                for f in split_list(&value(flag)?) {
+                    let f = f.to_lowercase();
+                    if !["json", "csv", "junit", "md"].contains(&f.as_str()) {
+                        return Err(format!("Unsupported --export format '{}' (json, csv, junit, md)", f));
+                    }
+                    if !out.exports.contains(&f) {
+                        out.exports.push(f);
+                    }
+                }
+            }
+            "--out-dir" => out.out_dir = Some(PathBuf::from(value(flag)?)),
+            "--timezone" => {
+                let tz = value(flag)?;
+                out.timezone = DisplayZone::parse(Some(&tz))
+                    .map_err(|_| format!("Invalid --timezone '{}': expected an IANA name or local", tz))?;
+            }
+            "--check-folder-budgets" => out.check_folder_budgets = Some(value(flag)?),
+            "--last-n" => {
+                out.last_n = value(flag)?
+                    .parse()
+                    .ok()
+                    .filter(|v| *v > 0)
+                    .ok_or("--last-n must be a positive integer")?;
+            }
+            "--help" | "-h" => return Err(USAGE.to_string()),
+            other => return Err(format!("Unknown argument '{}'\n{}", other, USAGE)),
+        }
+    }
+
+    if out.check_folder_budgets.is_some() {
+        return Ok(Some(out));
+    }
+    if out.pids.is_empty() {
+        return Err(format!("--pids is required\n{}", USAGE));
+    }
+    if out.duration_seconds == 0 {
+        return Err(format!("--duration is required\n{}", USAGE));
+    }
+    Ok(Some(out))
+}
+
+fn write_exports(
+    app: &AppHandle,
+    args: &HeadlessArgs,
+    report: ReportDetail,
+    junit_xml: &str,
+    markdown: &str,
+) -> Result<Value, String> {
+    if args.exports.is_empty() {
+        return Ok(json!({}));
+    }
+    let dir = match &args.out_dir {
+        Some(d) => d.clone(),
+        None => resolve_export_dir(app)?,
+    };
+    let id = report.id;
+    let csv = if args.exports.iter().any(|f| f == "csv") { render_samples_csv(&report, args.timezone) } else { String::new() };
+    let dataset = ReportDataset {
+        schema_version: DATASET_SCHEMA_VERSION,
+        exported_at: Utc::now().to_rfc3339(),
+        filter: None,
+        header: DatasetHeaderV2::for_version(DATASET_SCHEMA_VERSION, &report.meta, Vec::new()),
+        report,
+    };
+
+    let mut paths = serde_json::Map::new();
+    for format in &args.exports {
+        let (filename, ext, content) = match format.as_str() {
+            "csv" => (format!("PerfSight_Report_{}_Samples", id), "csv", csv.clone()),
+            "junit" => (format!("PerfSight_Report_{}_JUnit", id), "xml", junit_xml.to_string()),
+            "md" => (format!("PerfSight_Report_{}_Summary", id), "md", markdown.to_string()),
+            _ => {
+                let json_str = serde_json::to_string_pretty(&dataset).map_err(|e| e.to_string())?;
+                (format!("PerfSight_Report_{}_Dataset", id), "json", json_str)
+            }
+        };
+        // Same naming rules as the UI exports; an earlier run's file is kept.
+        let path = export_path_in(&dir, true, Some(&filename), ext, &filename, false).map_err(|e| e.to_string())?;
+        write_export_file(&path, content.as_bytes()).map_err(|e| e.to_string())?;
+        paths.insert(format.clone(), Value::String(path.to_string_lossy().to_string()));
+    }
+    Ok(Value::Object(paths))
+}
+
+async fn run_inner(app: &AppHandle, args: &HeadlessArgs) -> Result<(Value, bool), String> {
+    let state: State<CollectionState> = app.state();
+    let db: State<Database> = app.state();
+
+    let config = CollectionConfig {
+        target_pids: args.pids.clone(),
+        interval_ms: args.interval_ms,
+        mode: args.mode.clone(),
+        folder_path: args.folder_path.clone(),
+        test_context: Some(TestContext {
+            scenario_name: args.scenario.clone(),
+            build_id: args.build_id.clone(),
+            tags: if args.tags.is_empty() { None } else { Some(args.tags.clone()) },
+            notes: None,
+            ..Default::default()
+        }),
+        process_aliases: None,
+        stop_after_seconds: Some(args.duration_seconds),
+        log_metric_configs: None,
+        metric_sink: None,
+        budgets: if args.budgets.is_empty() { None } else { Some(args.budgets.clone()) },
+        strict: true,
+        simulation: None,
+        target_selector: None,
+        phase_metric: None,
+        snapshot_cdp_process_info: false,
+        apply_role_templates: false,
+        stream_to_file: None,
+        include_self: false,
+        adaptive_sampling: None,
+        memory_standard: Default::default(),
+    };
+
+    let session_id = start_collection_with(app.clone(), state.inner(), db.inner(), config)
+        .await
+        .map_err(|e| e.to_string())?;
+    eprintln!("Headless collection running for {}s...", args.duration_seconds);
+    tokio::time::sleep(Duration::from_secs(args.duration_seconds)).await;
+
+    let stopped = stop_collection_and_save(app, state.inner(), db.inner(), &session_id)?;
+    let Some(report_id) = stopped.report_id else {
+        if args.allow_empty {
+            return Ok((json!({ "ok": true, "saved": false, "reasons": stopped.reasons }), true));
+        }
+        return Err(format!("No data collected: {}", stopped.reasons.join("; ")));
+    };
+    let mut report = db.get_report_detail(report_id).map_err(|e| e.to_string())?;
+    let analysis = report.analysis.take().unwrap_or_else(|| analyze_report(&report.metrics, &report.meta));
+    let results = evaluate_budgets(&analysis, &report.meta.budgets);
+    let passed = results.iter().all(|r| r.passed);
+    let junit_xml = junit::render_junit(&report.title, &report.created_at, &report.meta, &analysis, &results);
+    let markdown = markdown::render_markdown(&report, &analysis, Some(&results), args.timezone);
+    let score = analysis.score;
+    let title = report.title.clone();
+    report.analysis = Some(analysis);
+    let exports = write_exports(app, args, report, &junit_xml, &markdown)?;
+
+    Ok((
+        json!({
+            "ok": true,
+            "saved": true,
+            "report_id": report_id,
+            "title": title,
+            "score": score,
+            "budget": {
+                "passed": passed,
+                "results": results
+            },
+            "exports": exports
+        }),
+        passed,
+    ))
+}
+
+// --check-folder-budgets: no collection, just the verdicts of stored reports.
+fn check_folder(app: &AppHandle, args: &HeadlessArgs, folder: &str) -> i32 {
+    let db: State<Database> = app.state();
+    match check_folder_budgets_with(db.inner(), folder, args.scenario.as_deref(), args.last_n) {
+        Ok(check) => {
+            let passed = check.passed;
+            println!("{}", json!({ "ok": true, "check": check }));
+            if passed {
+                0
+            } else {
+                EXIT_BUDGET_FAILED
+            }
+        }
+        Err(e) => {
+            println!("{}", json!({ "ok": false, "error": e.to_string() }));
+            EXIT_ERROR
+        }
+    }
+}
+
+/// Run one collection without UI, print a JSON summary to stdout and return the process exit code.
+pub async fn run_headless(app: AppHandle, args: HeadlessArgs) -> i32 {
+    if let Some(folder) = &args.check_folder_budgets {
+        return check_folder(&app, &args, folder);
+    }
+    match run_inner(&app, &args).await {
+        Ok((summary, passed)) => {
+            println!("{}", summary);
+            if !passed && args.exit_code_on_budget_fail {
+                EXIT_BUDGET_FAILED
+            } else {
+                0
+            }
+        }
+        Err(e) => {
+            // Make sure a failed run doesn't leave the collector going.
+            let state: State<CollectionState> = app.state();
+            let db: State<Database> = app.state();
+            for session_id in state.session_ids() {
+                let _ = stop_collection_and_save(&app, state.inner(), db.inner(), &session_id);
+            }
+            println!("{}", json!({ "ok": false, "error": e }));
+            EXIT_ERROR
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::{HashMap, VecDeque};
+use serde::{Deserialize, Serialize};
+use crate::error::PerfSightError;
+use crate::models::{BatchMetric, MAX_INTERVAL_MS};
+
+/// Adaptive sampling policy. Every field is optional in JSON; unset fields use the defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdaptiveSampling {
+    /// Samples per PID that must stay within the deltas before the interval is raised.
+    pub window: usize,
+    /// Largest CPU% spread within the window that still counts as stable.
+    pub cpu_delta: f32,
+    /// Largest memory spread (MB) within the window that still counts as stable.
+    pub mem_delta_mb: f64,
+    /// Upper bound of the effective interval.
+    pub max_interval_ms: u64,
+}
+
+impl Default for AdaptiveSampling {
+    fn default() -> Self {
+        Self {
+            window: 10,
+            cpu_delta: 2.0,
+            mem_delta_mb: 10.0,
+            max_interval_ms: 10_000,
+        }
+    }
+}
+
+impl AdaptiveSampling {
+    /// Check the policy against the run's base interval.
+    pub fn validate(&self, base_interval_ms: u64) -> Result<(), PerfSightError> {
+        if self.window < 2 {
+            return Err(PerfSightError::invalid_input("adaptive_sampling.window", "must be at least 2"));
+        }
+        if self.cpu_delta < 0.0 || self.mem_delta_mb < 0.0 {
+            return Err(PerfSightError::invalid_input("adaptive_sampling", "deltas must be non-negative"));
+        }
+        if !(base_interval_ms..=MAX_INTERVAL_MS).contains(&self.max_interval_ms) {
+            return Err(PerfSightError::invalid_input(
+                "adaptive_sampling.max_interval_ms",
+                format!("must be between interval_ms ({}) and {}", base_interval_ms, MAX_INTERVAL_MS),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Effective interval of one run under an `AdaptiveSampling` policy. Fed every recorded batch:
+/// once every PID's last `window` samples stay within the deltas the interval doubles (capped at
+/// `max_interval_ms`), and a further full window is needed before the next step up. A sample
+/// that breaks a delta drops straight back to the base interval.
+pub struct AdaptiveInterval {
+    policy: AdaptiveSampling,
+    base_ms: u64,
+    current_ms: u64,
+    // Last `window` (cpu, memory MB) samples per PID.
+    recent: HashMap<u32, VecDeque<(f32, f64)>>,
+    // Batches observed since the interval last changed.
+    since_change: usize,
+    /// Base-interval ticks not sampled because the interval was raised.
+    pub skipped_ticks: u64,
+    /// Number of interval changes, up or down.
+    pub adjustments: u64,
+}
+
+impl AdaptiveInterval {
+    pub fn new(policy: AdaptiveSampling, base_ms: u64) -> Self {
+        Self {
+            policy,
+            base_ms: base_ms.max(1),
+            current_ms: base_ms.max(1),
+            recent: HashMap::new(),
+            since_change: 0,
+            skipped_ticks: 0,
+            adjustments: 0,
+        }
+    }
+
+    pub fn policy(&self) -> &AdaptiveSampling {
+        &self.policy
+    }
+
+    pub fn current_ms(&self) -> u64 {
+        self.current_ms
+    }
+
+    /// Record a batch taken at the current interval. Returns the new interval when it changed.
+    pub fn observe(&mut self, batch: &BatchMetric) -> Option<u64> {
+        self.skipped_ticks += self.current_ms / self.base_ms - 1;
+        let window = self.policy.window;
+        // PIDs that are gone no longer hold the interval down (or up).
+        self.recent.retain(|pid, _| batch.metrics.contains_key(pid));
+        let mut unstable = false;
+        for (pid, point) in batch.metrics.iter().filter(|(_, p)| !p.is_custom_only()) {
+            let samples = self.recent.entry(*pid).or_default();
+            samples.push_back((point.cpu_usage, point.memory_rss as f64 / (1024.0 * 1024.0)));
+            if samples.len() > window {
+                samples.pop_front();
+            }
+            let spread = |values: &mut dyn Iterator<Item = f64>| {
+                let (lo, hi) = values.fold((f64::MAX, f64::MIN), |(lo, hi), v| (lo.min(v), hi.max(v)));
+                hi - lo
+            };
+            let cpu_spread = spread(&mut samples.iter().map(|s| s.0 as f64));
+            let mem_spread = spread(&mut samples.iter().map(|s| s.1));
+            if cpu_spread > self.policy.cpu_delta as f64 || mem_spread > self.policy.mem_delta_mb {
+                unstable = true;
+            }
+        }
+
+        if unstable {
+            self.since_change = 0;
+            return self.set(self.base_ms);
+        }
+        self.since_change += 1;
+        let settled = !self.recent.is_empty() && self.recent.values().all(|s| s.len() >= window);
+        if settled && self.since_change >= window && self.current_ms < self.policy.max_interval_ms {
+            self.since_change = 0;
+            return self.set((self.current_ms * 2).min(self.policy.max_interval_ms));
+        }
+        None
+    }
+
+    fn set(&mut self, interval_ms: u64) -> Option<u64> {
+        if interval_ms == self.current_ms {
+            return None;
+        }
+        self.current_ms = interval_ms;
+        self.adjustments += 1;
+        Some(interval_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use serde_json::json;
+
+    fn policy() -> AdaptiveSampling {
+        AdaptiveSampling { window: 3, cpu_delta: 2.0, mem_delta_mb: 10.0, max_interval_ms: 8000 }
+    }
+
+    // One batch with a (pid, cpu %, memory MB) sample per process.
+    fn batch(samples: &[(u32, f32, f64)]) -> BatchMetric {
+        let metrics = samples
+            .iter()
+            .map(|&(pid, cpu, mem_mb)| {
+                let point = serde_json::from_value(json!({
+                    "timestamp": Utc::now(),
+                    "pid": pid,
+                    "cpu_usage": cpu,
+                    "cpu_os_usage": cpu,
+                    "cpu_chrome_usage": null,
+                    "memory_rss": (mem_mb * 1024.0 * 1024.0) as u64,
+                    "memory_footprint": null,
+                    "gpu_usage": null,
+                    "js_heap_size": null,
+                    "memory_private": null,
+                    "custom_metrics": null,
+                }))
+                .unwrap();
+                (pid, point)
+            })
+            .collect();
+        BatchMetric { timestamp: Utc::now(), metrics }
+    }
+
+    // What `observe` returned for each batch.
+    fn feed(adaptive: &mut AdaptiveInterval, batches: &[BatchMetric]) -> Vec<Option<u64>> {
+        batches.iter().map(|b| adaptive.observe(b)).collect()
+    }
+
+    fn steady(n: usize) -> Vec<BatchMetric> {
+        (0..n).map(|_| batch(&[(1, 10.0, 200.0)])).collect()
+    }
+
+    #[test]
+    fn stable_samples_double_the_interval_one_full_window_at_a_time() {
+        let mut adaptive = AdaptiveInterval::new(policy(), 1000);
+        let changes = feed(&mut adaptive, &steady(12));
+        assert_eq!(
+            changes,
+            vec![None, None, Some(2000), None, None, Some(4000), None, None, Some(8000), None, None, None]
+        );
+        assert_eq!(adaptive.current_ms(), 8000);
+        assert_eq!(adaptive.adjustments, 3);
+        // Batches 4-6 were taken at 2 s (1 skipped tick each), 7-9 at 4 s (3 each), 10-12 at 8 s (7 each).
+        assert_eq!(adaptive.skipped_ticks, 3 + 3 * 3 + 3 * 7);
+    }
+
+    #[test]
+    fn jitter_within_the_deltas_keeps_the_raised_interval() {
+        let mut adaptive = AdaptiveInterval::new(policy(), 1000);
+        feed(&mut adaptive, &steady(3));
+        assert_eq!(adaptive.current_ms(), 2000);
+        let jitter: Vec<_> = [(11.0, 205.0), (9.0, 195.0), (11.0, 204.0), (10.0, 200.0)]
+            .iter()
+            .map(|&(cpu, mem)| batch(&[(1, cpu, mem)]))
+            .collect();
+        // Spreads of exactly 2 % and 10 MB are still stable.
+        assert_eq!(feed(&mut adaptive, &jitter), vec![None, None, Some(4000), None]);
+    }
+
+    #[test]
+    fn a_sample_beyond_a_delta_drops_straight_to_base_and_must_settle_again() {
+        for spike in [(1, 13.0, 200.0), (1, 10.0, 215.0)] {
+            let mut adaptive = AdaptiveInterval::new(policy(), 1000);
+            feed(&mut adaptive, &steady(6));
+            assert_eq!(adaptive.current_ms(), 4000);
+
+            assert_eq!(adaptive.observe(&batch(&[spike])), Some(1000), "{:?}", spike);
+            // The spike stays in the window for two more batches, then a full stable window is
+            // needed before the interval goes up again: no flapping around the threshold.
+            let changes = feed(&mut adaptive, &steady(6));
+            assert_eq!(changes, vec![None, None, None, None, Some(2000), None], "{:?}", spike);
+            assert_eq!(adaptive.adjustments, 4);
+        }
+    }
+
+    #[test]
+    fn one_unstable_process_holds_every_process_at_base() {
+        let mut adaptive = AdaptiveInterval::new(policy(), 1000);
+        let busy: Vec<_> = (0..6).map(|i| batch(&[(1, 10.0, 200.0), (2, if i % 2 == 0 { 5.0 } else { 50.0 }, 100.0)])).collect();
+        assert!(feed(&mut adaptive, &busy).iter().all(Option::is_none));
+        assert_eq!(adaptive.current_ms(), 1000);
+
+        // Once the busy process exits it no longer holds the interval down.
+        assert_eq!(feed(&mut adaptive, &steady(3)), vec![None, None, Some(2000)]);
+    }
+
+    #[test]
+    fn a_new_process_must_fill_its_window_before_the_next_step_up() {
+        let mut adaptive = AdaptiveInterval::new(policy(), 1000);
+        // A full window has passed since the last step; PID 1 alone would step up now.
+        feed(&mut adaptive, &steady(5));
+        assert_eq!(adaptive.current_ms(), 2000);
+        let joined: Vec<_> = (0..4).map(|_| batch(&[(1, 10.0, 200.0), (2, 30.0, 100.0)])).collect();
+        // Not a drop (its first samples are stable), but no step up until PID 2 has 3 samples.
+        assert_eq!(feed(&mut adaptive, &joined), vec![None, None, Some(4000), None]);
+    }
+
+    #[test]
+    fn max_interval_at_base_never_adjusts() {
+        let mut adaptive = AdaptiveInterval::new(AdaptiveSampling { max_interval_ms: 1000, ..policy() }, 1000);
+        assert!(feed(&mut adaptive, &steady(10)).iter().all(Option::is_none));
+        assert_eq!((adaptive.adjustments, adaptive.skipped_ticks), (0, 0));
+    }
+}
@@ -3,7 +3,8 @@ use serde_json::json;
 use tungstenite::{client, Message};
 use url::Url;
 use std::net::TcpStream;
-use std::time::Duration;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Deserialize)]
 struct CdpVersionInfo {
@@ -11,13 +12,155 @@ struct CdpVersionInfo {
     pub ws_url: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct BrowserProcessInfo {
     pub cpu_time: f64,
     pub private_mem_bytes: Option<u64>,
+    /// GPU memory attributed to the process, on builds/platforms that report it.
+    pub gpu_mem_bytes: Option<u64>,
     pub proc_type: String,
 }
 
+/// One adapter from `SystemInfo.getInfo` (`result.gpu.devices[]`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GpuDevice {
+    pub vendor_id: u64,
+    pub device_id: u64,
+    pub vendor: String,
+    pub device: String,
+    pub driver_vendor: String,
+    pub driver_version: String,
+}
+
+/// Static GPU description recorded into report meta under `env.gpu`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GpuInfo {
+    /// Primary adapter's device string, kept as `name` for older readers.
+    pub name: Option<String>,
+    pub devices: Vec<GpuDevice>,
+    /// Dedicated video memory of the primary adapter, when the platform exposes it.
+    pub adapter_memory_bytes: Option<u64>,
+    pub model_name: Option<String>,
+}
+
+// System RAM for the plausibility check. `System::new()` loads nothing, so refresh memory first;
+// a zero total would make every size implausible.
+fn total_mem_bytes() -> u64 {
+    let mut sys = sysinfo::System::new();
+    sys.refresh_memory();
+    sys.total_memory().max(1)
+}
+
+// CDP memory sizes are documented in KB, but some builds return bytes. Pick the interpretation
+// that is plausible relative to system RAM. Returns the bytes, the unit picked and why.
+fn choose_mem_unit(raw: u64, total_mem_bytes: u64) -> (Option<u64>, &'static str, &'static str) {
+    let as_kib_bytes = raw.saturating_mul(1024);
+    let as_bytes = raw;
+    let plaus_kib = as_kib_bytes <= total_mem_bytes.saturating_mul(4);
+    let plaus_bytes = as_bytes <= total_mem_bytes.saturating_mul(4);
+    match (plaus_kib, plaus_bytes) {
+        (true, false) => (Some(as_kib_bytes), "KiB", "only KiB is within 4x system RAM"),
+        (false, true) => (Some(as_bytes), "bytes", "as KiB it exceeds 4x system RAM"),
+        (true, true) => (Some(as_kib_bytes), "KiB", "both within 4x system RAM; spec unit preferred"),
+        (false, false) => (None, "none", "exceeds 4x system RAM in either unit; dropped"),
+    }
+}
+
+/// How one raw CDP memory size was read, for `debug_get_cdp_process_info`.
+#[derive(Debug, Clone, Serialize)]
+pub struct MemUnitChoice {
+    pub field: String,
+    pub raw: u64,
+    /// "KiB", "bytes", or "none" when neither is plausible.
+    pub unit: &'static str,
+    pub bytes: Option<u64>,
+    pub reason: &'static str,
+}
+
+/// A `BrowserProcessInfo` with the unit decisions behind its memory fields.
+#[derive(Debug, Clone, Serialize)]
+pub struct InterpretedProcessInfo {
+    #[serde(flatten)]
+    pub info: BrowserProcessInfo,
+    pub memory_units: Vec<MemUnitChoice>,
+}
+
+/// Raw `SystemInfo.getProcessInfo` payload next to PerfSight's reading of it. Saved as
+/// `meta.env.cdp_process_info` when a browser run asks for it.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessInfoDebug {
+    pub captured_at: String,
+    /// System RAM the unit heuristics compare against.
+    pub total_mem_bytes: u64,
+    pub raw: serde_json::Value,
+    /// Keyed by OS process id.
+    pub interpreted: std::collections::BTreeMap<u32, InterpretedProcessInfo>,
+}
+
+// Read one `processInfo` entry. None for entries without an OS process id.
+fn interpret_process_info(info: &serde_json::Value, total_mem_bytes: u64) -> Option<(u32, InterpretedProcessInfo)> {
+    let id = info["id"].as_u64().unwrap_or(0) as u32;
+    if id == 0 {
+        return None;
+    }
+    let cpu_time = info["cpuTime"].as_f64().unwrap_or(0.0);
+    let proc_type_raw = info["type"].as_str().unwrap_or("other").to_string();
+    let proc_type_norm = proc_type_raw.to_lowercase();
+    let proc_type = match proc_type_norm.as_str() {
+        "gpu" => "GPU".to_string(),
+        "renderer" => "Renderer".to_string(),
+        "browser" => "Browser".to_string(),
+        "utility" => "Utility".to_string(),
+        _ => {
+            // Some builds return fully-qualified service names like
+            // "network.mojom.NetworkService" / "storage.mojom.StorageService".
+            if proc_type_norm.contains("network") || proc_type_norm.contains("storage") || proc_type_norm.contains("service") {
+                "Utility".to_string()
+            } else {
+                "Other".to_string()
+            }
+        }
+    };
+
+    let mut memory_units = Vec::new();
+    let mut read_mem = |keys: &[&str]| {
+        let (field, raw) = keys.iter().find_map(|k| info.get(*k).and_then(|m| m.as_u64()).map(|raw| (*k, raw)))?;
+        let (bytes, unit, reason) = choose_mem_unit(raw, total_mem_bytes);
+        memory_units.push(MemUnitChoice { field: field.to_string(), raw, unit, bytes, reason });
+        bytes
+    };
+    let private_mem_bytes = read_mem(&["privateMemorySize"]);
+    // Not part of the documented schema; present on some builds.
+    let gpu_mem_bytes = read_mem(&["gpuMemorySize", "gpuMemory"]);
+
+    Some((
+        id,
+        InterpretedProcessInfo {
+            info: BrowserProcessInfo {
+                cpu_time,
+                private_mem_bytes,
+                gpu_mem_bytes,
+                proc_type,
+            },
+            memory_units,
+        },
+    ))
+}
+
+// auxAttributes keys that carry the adapter's video memory on some platforms (values in bytes,
+// or MB when small).
+fn adapter_memory_bytes(aux: &serde_json::Value) -> Option<u64> {
+    let map = aux.as_object()?;
+    map.iter()
+        .filter(|(k, _)| {
+            let k = k.to_lowercase();
+            k.contains("videomemory") || k.contains("vram")
+        })
+        .filter_map(|(_, v)| v.as_u64().or_else(|| v.as_str().and_then(|s| s.trim().parse().ok())))
+        .find(|v| *v > 0)
+        .map(|v| if v < 1024 * 1024 { v * 1024 * 1024 } else { v })
+}
+
 #[derive(Debug, Deserialize, Clone, Serialize)]
 pub struct CdpTarget {
     pub id: String,
@@ -28,7 +171,7 @@ pub struct CdpTarget {
     pub ws_url: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Debug, Clone, Copy, Deserialize)]
 pub struct HeapUsage {
     #[serde(rename = "usedSize")]
     pub used_size: u64,
@@ -38,27 +181,35 @@ pub struct HeapUsage {
 
 pub struct CdpClient;
 
+/// Host and port of Chrome's remote debugging endpoint.
+pub const CDP_ENDPOINT: &str = "localhost:9222";
+
+// One HTTP client for the debugging endpoint, so the periodic /json/list polls reuse its
+// connection pool.
+fn http_client() -> Result<&'static reqwest::blocking::Client, String> {
+    static CLIENT: OnceLock<Result<reqwest::blocking::Client, String>> = OnceLock::new();
+    CLIENT
+        .get_or_init(|| {
+            reqwest::blocking::Client::builder()
+                .timeout(Duration::from_secs(2))
+                .build()
+                .map_err(|e| e.to_string())
+        })
+        .as_ref()
+        .map_err(|e| e.clone())
+}
+
 impl CdpClient {
     pub fn get_targets() -> Result<Vec<CdpTarget>, String> {
-        let url = "http://localhost:9222/json/list";
-        let client = reqwest::blocking::Client::builder()
-            .timeout(Duration::from_secs(2))
-            .build()
-            .map_err(|e| e.to_string())?;
-            
-        let resp = client.get(url).send().map_err(|e| e.to_string())?;
+        let url = format!("http://{}/json/list", CDP_ENDPOINT);
+        let resp = http_client()?.get(url).send().map_err(|e| e.to_string())?;
         let targets: Vec<CdpTarget> = resp.json().map_err(|e| e.to_string())?;
         Ok(targets)
     }
 
     fn get_browser_ws_url() -> Result<String, String> {
-        let url = "http://localhost:9222/json/version";
-        let client = reqwest::blocking::Client::builder()
-            .timeout(Duration::from_secs(2))
-            .build()
-            .map_err(|e| e.to_string())?;
-
-        let resp = client.get(url).send().map_err(|e| e.to_string())?;
+        let url = format!("http://{}/json/version", CDP_ENDPOINT);
+        let resp = http_client()?.get(url).send().map_err(|e| e.to_string())?;
         let version: CdpVersionInfo = resp.json().map_err(|e| e.to_string())?;
         version
             .ws_url
@@ -70,7 +221,7 @@ impl CdpClient {
     pub fn get_browser_process_info() -> Result<std::collections::HashMap<u32, BrowserProcessInfo>, String> {
         let ws_url = Self::get_browser_ws_url()?;
         let (mut socket, _) = Self::connect_ws(&ws_url).ok_or_else(|| "Failed to connect to browser websocket".to_string())?;
-        let total_mem_bytes = sysinfo::System::new().total_memory().max(1);
+        let total_mem_bytes = total_mem_bytes();
 
         let _ = socket.send(Message::Text(
             json!({ "id": 101, "method": "SystemInfo.getProcessInfo" }).to_string().into(),
@@ -83,55 +234,9 @@ impl CdpClient {
                     let mut out = std::collections::HashMap::new();
                     if let Some(infos) = v["result"]["processInfo"].as_array() {
                         for info in infos {
-                            let id = info["id"].as_u64().unwrap_or(0) as u32;
-                            if id == 0 {
-                                continue;
+                            if let Some((id, interpreted)) = interpret_process_info(info, total_mem_bytes) {
+                                out.insert(id, interpreted.info);
                             }
-                            let cpu_time = info["cpuTime"].as_f64().unwrap_or(0.0);
-                            let proc_type_raw = info["type"].as_str().unwrap_or("other").to_string();
-                            let proc_type_norm = proc_type_raw.to_lowercase();
-                            let proc_type = match proc_type_norm.as_str() {
-                                "gpu" => "GPU".to_string(),
-                                "renderer" => "Renderer".to_string(),
-                                "browser" => "Browser".to_string(),
-                                "utility" => "Utility".to_string(),
-                                _ => {
-                                    // Some builds return fully-qualified service names like
-                                    // "network.mojom.NetworkService" / "storage.mojom.StorageService".
-                                    if proc_type_norm.contains("network") || proc_type_norm.contains("storage") || proc_type_norm.contains("service") {
-                                        "Utility".to_string()
-                                    } else {
-                                        "Other".to_string()
-                                    }
-                                }
-                            };
-
-                            // CDP docs say privateMemorySize is in KB, but some builds appear to return bytes.
-                            // Choose the interpretation that yields a plausible value relative to system RAM.
-                            let private_mem_bytes = info
-                                .get("privateMemorySize")
-                                .and_then(|m| m.as_u64())
-                                .and_then(|raw| {
-                                    let as_kib_bytes = raw.saturating_mul(1024);
-                                    let as_bytes = raw;
-                                    let plaus_kib = as_kib_bytes <= total_mem_bytes.saturating_mul(4);
-                                    let plaus_bytes = as_bytes <= total_mem_bytes.saturating_mul(4);
-                                    match (plaus_kib, plaus_bytes) {
-                                        (true, false) => Some(as_kib_bytes),
-                                        (false, true) => Some(as_bytes),
-                                        (true, true) => Some(as_kib_bytes), // prefer spec unit
-                                        (false, false) => None,
-                                    }
-                                });
-
-                            out.insert(
-                                id,
-                                BrowserProcessInfo {
-                                    cpu_time,
-                                    private_mem_bytes,
-                                    proc_type,
-                                },
-                            );
                         }
                     }
                     return Ok(out);
@@ -142,6 +247,49 @@ impl CdpClient {
         Err("Timed out waiting for SystemInfo.getProcessInfo response".to_string())
     }
 
+    /// Static GPU description from `SystemInfo.getInfo` on the browser-level socket.
+    pub fn get_gpu_info() -> Result<GpuInfo, String> {
+        let ws_url = Self::get_browser_ws_url()?;
+        let (mut socket, _) = Self::connect_ws(&ws_url).ok_or_else(|| "Failed to connect to browser websocket".to_string())?;
+
+        let _ = socket.send(Message::Text(
+            json!({ "id": 301, "method": "SystemInfo.getInfo" }).to_string().into(),
+        ));
+
+        for _ in 0..10 {
+            if let Ok(Message::Text(text)) = socket.read() {
+                let v: serde_json::Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+                if v["id"] == 301 {
+                    let gpu = &v["result"]["gpu"];
+                    let text = |d: &serde_json::Value, k: &str| d[k].as_str().unwrap_or("").to_string();
+                    let devices: Vec<GpuDevice> = gpu["devices"]
+                        .as_array()
+                        .map(|arr| {
+                            arr.iter()
+                                .map(|d| GpuDevice {
+                                    vendor_id: d["vendorId"].as_u64().unwrap_or(0),
+                                    device_id: d["deviceId"].as_u64().unwrap_or(0),
+                                    vendor: text(d, "vendorString"),
+                                    device: text(d, "deviceString"),
+                                    driver_vendor: text(d, "driverVendor"),
+                                    driver_version: text(d, "driverVersion"),
+                                })
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                    return Ok(GpuInfo {
+                        name: devices.first().map(|d| d.device.clone()).filter(|n| !n.is_empty()),
+                        devices,
+                        adapter_memory_bytes: adapter_memory_bytes(&gpu["auxAttributes"]),
+                        model_name: v["result"]["modelName"].as_str().map(str::to_string).filter(|s| !s.is_empty()),
+                    });
+                }
+            }
+        }
+
+        Err("Timed out waiting for SystemInfo.getInfo response".to_string())
+    }
+
     /// Debug helper: return the raw `result.processInfo` array from CDP `SystemInfo.getProcessInfo`.
     /// This is useful to align fields/units with Chrome Task Manager across platforms/versions.
     pub fn get_browser_process_info_raw() -> Result<serde_json::Value, String> {
@@ -165,6 +313,23 @@ impl CdpClient {
         Err("Timed out waiting for SystemInfo.getProcessInfo response".to_string())
     }
 
+    /// Debug helper: one `SystemInfo.getProcessInfo` payload with the map
+    /// `get_browser_process_info` would build from it, including the memory unit picked per field.
+    pub fn get_process_info_debug() -> Result<ProcessInfoDebug, String> {
+        let raw = Self::get_browser_process_info_raw()?;
+        let total_mem_bytes = total_mem_bytes();
+        let interpreted = raw
+            .as_array()
+            .map(|infos| infos.iter().filter_map(|info| interpret_process_info(info, total_mem_bytes)).collect())
+            .unwrap_or_default();
+        Ok(ProcessInfoDebug {
+            captured_at: chrono::Utc::now().to_rfc3339(),
+            total_mem_bytes,
+            raw,
+            interpreted,
+        })
+    }
+
     // Helper to connect with timeout
     fn connect_ws(ws_url: &str) -> Option<(tungstenite::WebSocket<TcpStream>, tungstenite::handshake::client::Response)> {
         let url_obj = Url::parse(ws_url).ok()?;
@@ -238,27 +403,81 @@ impl CdpClient {
         None
     }
 
-    pub fn get_js_heap(ws_url: &str) -> Option<u64> {
-        let (mut socket, _) = Self::connect_ws(ws_url)?;
+}
 
-        let _ = socket.send(Message::Text(json!({
-            "id": 1, "method": "Runtime.enable"
-        }).to_string().into()));
-        let _ = socket.read(); 
+// How long a session call waits for its response while skipping interleaved events.
+const SESSION_CALL_TIMEOUT: Duration = Duration::from_millis(1500);
+
+/// A long-lived connection to one page target, reused across ticks.
+pub struct CdpSession {
+    ws_url: String,
+    socket: Option<tungstenite::WebSocket<TcpStream>>,
+    next_id: u64,
+    // Set when the page's execution contexts went away (navigation); Runtime is re-enabled
+    // before the next call.
+    runtime_enabled: bool,
+}
 
-        let _ = socket.send(Message::Text(json!({
-            "id": 2, "method": "Runtime.getHeapUsage"
-        }).to_string().into()));
+impl CdpSession {
+    pub fn new(ws_url: &str) -> Self {
+        Self { ws_url: ws_url.to_string(), socket: None, next_id: 1, runtime_enabled: false }
+    }
 
-        for _ in 0..5 {
-            if let Ok(Message::Text(text)) = socket.read() {
-                if let Ok(v) = serde_json::from_str::<serde_json::Value>(&text) {
-                    if v["id"] == 2 {
-                        if let Some(res) = v.get("result") {
-                            return res["usedSize"].as_u64();
-                        }
+    pub fn ws_url(&self) -> &str {
+        &self.ws_url
+    }
+
+    /// Used and total JS heap. Reconnects once when the socket dropped.
+    pub fn heap_usage(&mut self) -> Option<HeapUsage> {
+        for _ in 0..2 {
+            if !self.runtime_enabled && self.call("Runtime.enable").is_some() {
+                self.runtime_enabled = true;
+            }
+            if let Some(result) = self.call("Runtime.getHeapUsage") {
+                return serde_json::from_value(result).ok();
+            }
+            // Error response or dead socket: start over with a fresh connection.
+            self.socket = None;
+            self.runtime_enabled = false;
+        }
+        None
+    }
+
+    fn call(&mut self, method: &str) -> Option<serde_json::Value> {
+        if self.socket.is_none() {
+            self.socket = CdpClient::connect_ws(&self.ws_url).map(|(socket, _)| socket);
+        }
+        let socket = self.socket.as_mut()?;
+        let id = self.next_id;
+        self.next_id += 1;
+        if socket.send(Message::Text(json!({ "id": id, "method": method }).to_string().into())).is_err() {
+            self.socket = None;
+            return None;
+        }
+        let deadline = Instant::now() + SESSION_CALL_TIMEOUT;
+        while Instant::now() < deadline {
+            match socket.read() {
+                Ok(Message::Text(text)) => {
+                    let Ok(v) = serde_json::from_str::<serde_json::Value>(&text) else { continue };
+                    if v["id"] == id {
+                        return v.get("result").cloned();
+                    }
+                    if matches!(v["method"].as_str(), Some("Runtime.executionContextsCleared" | "Inspector.detached")) {
+                        self.runtime_enabled = false;
                     }
                 }
+                Ok(Message::Close(_)) => {
+                    self.socket = None;
+                    return None;
+                }
+                Ok(_) => {}
+                // Read timeouts are expected while waiting; anything else means the socket is gone.
+                Err(tungstenite::Error::Io(e))
+                    if matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut) => {}
+                Err(_) => {
+                    self.socket = None;
+                    return None;
+                }
             }
         }
         None
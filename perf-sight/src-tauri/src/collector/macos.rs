@@ -0,0 +1,160 @@
+use crate::collector::VIRTUAL_PID_BASE;
+use crate::models::{ProcessInfo, ProcessRole};
+use core_foundation::base::{CFType, TCFType};
+use core_foundation::bundle::CFBundle;
+use core_foundation::dictionary::{CFDictionary, CFDictionaryRef};
+use core_foundation::number::CFNumber;
+use core_foundation::string::CFString;
+use core_foundation::url::CFURL;
+use core_graphics::window::{
+    copy_window_info, kCGNullWindowID, kCGWindowLayer, kCGWindowListExcludeDesktopElements,
+    kCGWindowListOptionOnScreenOnly, kCGWindowName, kCGWindowOwnerPID,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// proc_pidpath's buffer size (PROC_PIDPATHINFO_MAXSIZE).
+const PID_PATH_MAX: usize = 4096;
+
+/// Fill in `bundle_id` and `window_title` for the scanned processes.
+pub fn enrich(mut list: Vec<ProcessInfo>) -> Vec<ProcessInfo> {
+    let titles = front_window_titles();
+    let mut bundles: HashMap<PathBuf, Option<String>> = HashMap::new();
+    for p in list.iter_mut() {
+        // Virtual PIDs (tabs without a resolvable renderer) have nothing to look up.
+        if p.pid >= VIRTUAL_PID_BASE {
+            continue;
+        }
+        if let Some(app) = executable_path(p.pid).as_deref().and_then(outermost_app) {
+            p.bundle_id = bundles.entry(app.clone()).or_insert_with(|| bundle_identifier(&app)).clone();
+        }
+        p.window_title = titles.get(&p.pid).cloned();
+    }
+    list
+}
+
+fn executable_path(pid: u32) -> Option<PathBuf> {
+    let mut buf = vec![0u8; PID_PATH_MAX];
+    let len = unsafe { libc::proc_pidpath(pid as i32, buf.as_mut_ptr().cast(), PID_PATH_MAX as u32) };
+    if len <= 0 {
+        return None;
+    }
+    buf.truncate(len as usize);
+    String::from_utf8(buf).ok().map(PathBuf::from)
+}
+
+// The enclosing app, not a nested helper: Chrome renderers live in
+// "Google Chrome.app/.../Google Chrome Helper (Renderer).app".
+fn outermost_app(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .filter(|p| p.extension().is_some_and(|e| e == "app"))
+        .last()
+        .map(Path::to_path_buf)
+}
+
+fn bundle_identifier(app: &Path) -> Option<String> {
+    let bundle = CFBundle::new(CFURL::from_path(app, true)?)?;
+    let info = bundle.info_dictionary();
+    let id = info.find(CFString::from_static_string("CFBundleIdentifier"))?;
+    id.downcast::<CFString>().map(|s| s.to_string())
+}
+
+// Title of each PID's frontmost named on-screen window. macOS only reports window names to apps
+// with the Screen Recording permission; without it the map is empty. In Chrome the windows
+// belong to the browser process, not the renderers.
+fn front_window_titles() -> HashMap<u32, String> {
+    let mut titles = HashMap::new();
+    let Some(windows) =
+        copy_window_info(kCGWindowListOptionOnScreenOnly | kCGWindowListExcludeDesktopElements, kCGNullWindowID)
+    else {
+        return titles;
+    };
+    let (pid_key, layer_key, name_key) = unsafe {
+        (
+            CFString::wrap_under_get_rule(kCGWindowOwnerPID),
+            CFString::wrap_under_get_rule(kCGWindowLayer),
+            CFString::wrap_under_get_rule(kCGWindowName),
+        )
+    };
+    // Front to back, so the first named window of a PID is its frontmost one.
+    for item in windows.get_all_values() {
+        let window: CFDictionary<CFString, CFType> =
+            unsafe { CFDictionary::wrap_under_get_rule(item as CFDictionaryRef) };
+        let number = |key: &CFString| window.find(key).and_then(|v| v.downcast::<CFNumber>()).and_then(|n| n.to_i64());
+        // Layer 0 holds normal app windows; menus and overlays sit above it.
+        if number(&layer_key) != Some(0) {
+            continue;
+        }
+        let Some(pid) = number(&pid_key) else { continue };
+        let Some(name) = window.find(&name_key).and_then(|v| v.downcast::<CFString>()).map(|s| s.to_string()) else {
+            continue;
+        };
+        if !name.trim().is_empty() {
+            titles.entry(pid as u32).or_insert(name);
+        }
+    }
+    titles
+}
+
+/// Foreground/background classification from the CPU time a process ran at each QoS class
+/// (proc_pid_rusage, which needs no task port). App Nap and background clamps move a process's
+/// CPU into the background and maintenance classes.
+#[derive(Default)]
+pub struct QosRoleTracker {
+    // PID -> (cumulative [background, other] CPU time, role of the last tick)
+    prev: HashMap<u32, ([u64; 2], Option<ProcessRole>)>,
+}
+
+impl QosRoleTracker {
+    /// Role over the time since the previous call: whichever group ran most of the CPU. A tick
+    /// without CPU keeps the last role; None on the first call and when the PID can't be read.
+    pub fn sample(&mut self, pid: u32) -> Option<ProcessRole> {
+        let Some(now) = qos_cpu_times(pid) else {
+            self.prev.remove(&pid);
+            return None;
+        };
+        let role = self.prev.get(&pid).and_then(|(prev, last)| {
+            let background = now[0].saturating_sub(prev[0]);
+            let other = now[1].saturating_sub(prev[1]);
+            match background + other {
+                0 => *last,
+                _ if background >= other => Some(ProcessRole::Background),
+                _ => Some(ProcessRole::Foreground),
+            }
+        });
+        self.prev.insert(pid, (now, role));
+        role
+    }
+}
+
+// Cumulative CPU time at background/maintenance QoS and at every other class, in Mach time units
+// (only their ratio is used).
+fn qos_cpu_times(pid: u32) -> Option<[u64; 2]> {
+    // From <sys/resource.h>; kept as literals to avoid libc API drift.
+    const RUSAGE_INFO_V3: libc::c_int = 3;
+    // struct rusage_info_v3 as u64 words (the 16-byte uuid takes the first two).
+    const WORDS: usize = 29;
+    const QOS_DEFAULT: usize = 20;
+    const QOS_MAINTENANCE: usize = 21;
+    const QOS_BACKGROUND: usize = 22;
+    const QOS_UTILITY: usize = 23;
+    const QOS_LEGACY: usize = 24;
+    const QOS_USER_INITIATED: usize = 25;
+    const QOS_USER_INTERACTIVE: usize = 26;
+    extern "C" {
+        fn proc_pid_rusage(pid: libc::c_int, flavor: libc::c_int, buffer: *mut libc::c_void) -> libc::c_int;
+    }
+
+    let mut info = [0u64; WORDS];
+    let rc = unsafe { proc_pid_rusage(pid as libc::c_int, RUSAGE_INFO_V3, info.as_mut_ptr().cast()) };
+    if rc != 0 {
+        return None;
+    }
+    let background = info[QOS_MAINTENANCE] + info[QOS_BACKGROUND];
+    let other = info[QOS_DEFAULT]
+        + info[QOS_UTILITY]
+        + info[QOS_LEGACY]
+        + info[QOS_USER_INITIATED]
+        + info[QOS_USER_INTERACTIVE];
+    Some([background, other])
+}
@@ -1,31 +1,20 @@
+pub mod adaptive;
 pub mod cdp;
-
-use crate::models::{MetricPoint, ProcessInfo}; 
-use self::cdp::{BrowserProcessInfo, CdpClient, CdpTarget};
+#[cfg(target_os = "macos")]
+mod macos;
+pub mod scan_cache;
+pub mod simulate;
+mod threads;
+
+use crate::models::{CpuNormalization, MemoryBasis, MemoryStandard, MetricPoint, ProcessInfo, ProcessRole, TabTarget};
+use self::cdp::{BrowserProcessInfo, CdpClient, CdpSession, CdpTarget};
+use self::simulate::{SimulatedCollector, SimulationConfig};
+use self::threads::ThreadCpuTracker;
 use chrono::Utc;
 use sysinfo::{Pid, System};
 use std::collections::HashMap;
-use std::time::Instant;
-
-fn os_cpu_pct_for_task_manager(raw_sysinfo_cpu_pct: f32) -> f32 {
-    // sysinfo's Process::cpu_usage() can exceed 100% on multi-core machines.
-    //
-    // For alignment:
-    // - Windows Task Manager usually shows 0-100% of total CPU capacity -> normalize by CPU count.
-    // - macOS Activity Monitor commonly shows per-core summed CPU% (can exceed 100%) -> do NOT normalize.
-    #[cfg(target_os = "windows")]
-    {
-        let cpu_count = std::thread::available_parallelism()
-            .map(|n| n.get())
-            .unwrap_or(1) as f32;
-        return raw_sysinfo_cpu_pct / cpu_count;
-    }
-
-    #[cfg(not(target_os = "windows"))]
-    {
-        raw_sysinfo_cpu_pct
-    }
-}
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 #[cfg(target_os = "macos")]
 fn macos_total_memory_bytes() -> Option<u64> {
@@ -97,8 +86,8 @@ fn macos_activity_monitor_memory_bytes(pid: u32) -> Option<u64> {
 
     // If phys_footprint looks wrong but resident is present, use resident as a safer fallback.
     if resident > 0 && resident < one_tb {
-        eprintln!(
-            "WARN: using resident_size instead of phys_footprint for pid {} (phys={} bytes, resident={} bytes, system_total={} bytes)",
+        crate::logging::log_warn!(
+            "using resident_size instead of phys_footprint for pid {} (phys={} bytes, resident={} bytes, system_total={} bytes)",
             pid, phys, resident, total_mem_bytes
         );
         return Some(resident);
@@ -107,24 +96,134 @@ fn macos_activity_monitor_memory_bytes(pid: u32) -> Option<u64> {
     None
 }
 
+#[cfg(target_os = "windows")]
+fn windows_private_working_set_bytes(pid: u32) -> Option<u64> {
+    use std::ffi::c_void;
+
+    // PROCESS_MEMORY_COUNTERS_EX2 from psapi.h. PrivateWorkingSetSize needs Windows 10 1809+;
+    // older systems leave it zero.
+    #[repr(C)]
+    #[allow(dead_code)]
+    struct ProcessMemoryCountersEx2 {
+        cb: u32,
+        page_fault_count: u32,
+        peak_working_set_size: usize,
+        working_set_size: usize,
+        quota_peak_paged_pool_usage: usize,
+        quota_paged_pool_usage: usize,
+        quota_peak_non_paged_pool_usage: usize,
+        quota_non_paged_pool_usage: usize,
+        pagefile_usage: usize,
+        peak_pagefile_usage: usize,
+        private_usage: usize,
+        private_working_set_size: usize,
+        shared_commit_usage: u64,
+    }
+    const PROCESS_QUERY_LIMITED_INFORMATION: u32 = 0x1000;
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn OpenProcess(access: u32, inherit: i32, pid: u32) -> *mut c_void;
+        fn CloseHandle(handle: *mut c_void) -> i32;
+        fn K32GetProcessMemoryInfo(process: *mut c_void, counters: *mut ProcessMemoryCountersEx2, cb: u32) -> i32;
+    }
+
+    let handle = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid) };
+    if handle.is_null() {
+        return None;
+    }
+    let mut counters: ProcessMemoryCountersEx2 = unsafe { std::mem::zeroed() };
+    let cb = std::mem::size_of::<ProcessMemoryCountersEx2>() as u32;
+    counters.cb = cb;
+    let ok = unsafe { K32GetProcessMemoryInfo(handle, &mut counters, cb) };
+    unsafe { CloseHandle(handle) };
+    (ok != 0 && counters.private_working_set_size > 0).then_some(counters.private_working_set_size as u64)
+}
+
+#[cfg(target_os = "linux")]
+fn linux_pss_bytes(pid: u32) -> Option<u64> {
+    // smaps_rollup (Linux 4.14+) sums smaps without listing every mapping. Only readable for
+    // processes we may ptrace, i.e. usually our own user's.
+    let rollup = std::fs::read_to_string(format!("/proc/{}/smaps_rollup", pid)).ok()?;
+    let kib: u64 = rollup
+        .lines()
+        .find_map(|l| l.strip_prefix("Pss:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kib * 1024)
+}
+
+/// The OS task manager's memory figure for `pid` and its basis: phys_footprint on macOS, the
+/// private working set on Windows, PSS on Linux. None where it can't be read (access denied,
+/// older OS, other platforms); callers keep RSS then.
+#[cfg(target_os = "macos")]
+pub fn task_manager_memory(pid: u32) -> Option<(u64, MemoryBasis)> {
+    macos_activity_monitor_memory_bytes(pid).map(|b| (b, MemoryBasis::PhysFootprint))
+}
+
+#[cfg(target_os = "windows")]
+pub fn task_manager_memory(pid: u32) -> Option<(u64, MemoryBasis)> {
+    windows_private_working_set_bytes(pid).map(|b| (b, MemoryBasis::PrivateWorkingSet))
+}
+
+#[cfg(target_os = "linux")]
+pub fn task_manager_memory(pid: u32) -> Option<(u64, MemoryBasis)> {
+    linux_pss_bytes(pid).map(|b| (b, MemoryBasis::Pss))
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+pub fn task_manager_memory(_pid: u32) -> Option<(u64, MemoryBasis)> {
+    None
+}
+
 pub trait ResourceCollector {
     fn update(&mut self); 
     fn scan_processes(&mut self, mode: &str) -> Vec<ProcessInfo>;
     fn collect_process(&self, pid: u32) -> Option<MetricPoint>;
+    /// Foreground/background scheduling of `pid` since the previous call, on platforms that
+    /// expose it (macOS). Called once per tick per PID.
+    fn process_role(&self, _pid: u32) -> Option<ProcessRole> {
+        None
+    }
+    /// Page targets per PID from the latest tab refresh (browser mode), once per refresh.
+    fn take_tab_targets(&mut self) -> Option<HashMap<u32, Vec<TabTarget>>> {
+        None
+    }
+    /// Called once before the first tick of a run, for figures that need a previous sample.
+    fn prewarm(&mut self) {}
 }
 
+// Source of `SystemInfo.getProcessInfo` results; tests swap in a fake browser.
+type ProcessInfoSource = fn() -> Result<HashMap<u32, BrowserProcessInfo>, String>;
+
 pub struct GeneralCollector {
     system: System,
-    // Cache: Virtual PID -> WebSocket URL
-    cdp_sessions: HashMap<u32, String>,
+    // PID (real or virtual) -> persistent page connection, reused every tick.
+    cdp_sessions: Mutex<HashMap<u32, CdpSession>>,
+    // Last time page targets were re-matched to PIDs (tabs opened mid-run).
+    tabs_refreshed_at: Option<Instant>,
+    // Page targets per PID seen by the last refresh, until `take_tab_targets`.
+    tab_targets: Option<HashMap<u32, Vec<TabTarget>>>,
     mode: String,
+    // How sysinfo's per-core summed CPU% is scaled into `cpu_os_usage`.
+    cpu_normalization: CpuNormalization,
+    // Memory figure of system-mode samples and picker rows.
+    memory_standard: MemoryStandard,
 
     // Browser Task Manager-aligned process info fetched from browser WS (/json/version).
     browser_procinfo: HashMap<u32, BrowserProcessInfo>,
+    process_info: ProcessInfoSource,
     // For CPU% calculation from cpuTime deltas.
     prev_cpu_time: HashMap<u32, (f64, Instant)>,
     // Computed CPU% from CDP cpuTime deltas (closest to Chrome Task Manager CPU column).
     browser_cpu_pct: HashMap<u32, f32>,
+    // Per-thread CPU times of the previous sample, for `max_thread_cpu`.
+    thread_cpu: Mutex<ThreadCpuTracker>,
+    // Per-QoS CPU times of the previous tick, for `process_role`.
+    #[cfg(target_os = "macos")]
+    qos_roles: Mutex<macos::QosRoleTracker>,
 }
 
 impl GeneralCollector {
@@ -133,56 +232,76 @@ impl GeneralCollector {
         sys.refresh_all();
         Self { 
             system: sys,
-            cdp_sessions: HashMap::new(),
+            cdp_sessions: Mutex::new(HashMap::new()),
+            tabs_refreshed_at: None,
+            tab_targets: None,
             mode,
+            cpu_normalization: CpuNormalization::platform_default(),
+            memory_standard: MemoryStandard::Rss,
             browser_procinfo: HashMap::new(),
+            process_info: CdpClient::get_browser_process_info,
             prev_cpu_time: HashMap::new(),
             browser_cpu_pct: HashMap::new(),
+            thread_cpu: Mutex::new(ThreadCpuTracker::default()),
+            #[cfg(target_os = "macos")]
+            qos_roles: Mutex::new(macos::QosRoleTracker::default()),
         }
     }
 }
 
-impl ResourceCollector for GeneralCollector {
-    fn update(&mut self) {
-        // sysinfo's CPU% (system and per-process) is computed from deltas between refreshes.
-        // On some platforms, `refresh_all()` doesn't reliably update per-process CPU usage
-        // unless processes/cpu are refreshed explicitly.
-        //
-        // Keep the same `System` instance and refresh at a reasonable interval (we use 1s).
-        self.system.refresh_cpu();
-        self.system.refresh_processes();
+// Row title for a renderer hosting several tabs: "3 tabs: Inbox | Calendar | Docs".
+fn tab_group_title(targets: &[TabTarget]) -> String {
+    let titles: Vec<&str> = targets
+        .iter()
+        .map(|t| if t.title.trim().is_empty() { t.url.as_str() } else { t.title.as_str() })
+        .collect();
+    format!("{} tabs: {}", targets.len(), titles.join(" | "))
+}
 
-        if self.mode == "browser" {
-            if let Ok(map) = CdpClient::get_browser_process_info() {
-                self.browser_procinfo = map;
+/// First PID handed to a browser tab whose renderer process can't be resolved; PIDs from here up
+/// are virtual and have no OS process behind them.
+pub const VIRTUAL_PID_BASE: u32 = 90000;
 
-                // Update CPU% cache based on cpuTime deltas.
-                let now = Instant::now();
-                let cpu_count = std::thread::available_parallelism()
-                    .map(|n| n.get())
-                    .unwrap_or(1) as f64;
-                let mut next_cpu = HashMap::new();
-                for (pid, info) in self.browser_procinfo.iter() {
-                    let cpu_time = info.cpu_time;
-                    if let Some((prev_time, prev_instant)) = self.prev_cpu_time.get(pid) {
-                        let dt = now.duration_since(*prev_instant).as_secs_f64();
-                        if dt > 0.0 {
-                            let dcpu = cpu_time - *prev_time;
-                            // cpuTime is CPU seconds; CPU% over wall time:
-                            // 100% == one fully utilized core; can exceed 100% with multi-threading.
-                            // Chrome Task Manager typically normalizes by total logical CPUs (percent of total CPU capacity).
-                            let pct = ((dcpu / dt) * 100.0 / cpu_count).max(0.0);
-                            next_cpu.insert(*pid, pct as f32);
-                        }
-                    }
-                    self.prev_cpu_time.insert(*pid, (cpu_time, now));
-                }
-                self.browser_cpu_pct = next_cpu;
+// Gap between the browser-mode pre-warm sample and the first tick.
+const BROWSER_PREWARM_DELAY: Duration = Duration::from_millis(500);
+
+// How often a browser-mode collector looks for tabs opened (or closed) since the last scan.
+const TAB_REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+impl GeneralCollector {
+    // Match page targets to renderer PIDs, keeping existing connections. Targets without a real
+    // PID keep whatever session `scan_processes` gave them.
+    fn refresh_tab_sessions(&mut self) {
+        let Ok(targets) = CdpClient::get_targets() else { return };
+        let pages: Vec<CdpTarget> = targets.into_iter().filter(|t| t.r#type == "page").collect();
+        let urls: Vec<&String> = pages.iter().filter_map(|t| t.ws_url.as_ref()).collect();
+        let sessions = self.cdp_sessions.get_mut().unwrap_or_else(|e| e.into_inner());
+        sessions.retain(|_, s| urls.iter().any(|u| *u == s.ws_url()));
+        for ws in urls {
+            if sessions.values().any(|s| s.ws_url() == ws) {
+                continue;
+            }
+            if let Some(pid) = CdpClient::get_pid(ws) {
+                sessions.insert(pid, CdpSession::new(ws));
             }
         }
+        // Current titles per PID, for renaming tabs mid-run.
+        let mut by_pid: HashMap<u32, Vec<TabTarget>> = HashMap::new();
+        for page in &pages {
+            let Some(ws) = &page.ws_url else { continue };
+            if let Some((pid, _)) = sessions.iter().find(|(_, s)| s.ws_url() == ws) {
+                by_pid.entry(*pid).or_default().push(TabTarget {
+                    id: page.id.clone(),
+                    title: page.title.clone(),
+                    url: page.url.clone(),
+                });
+            }
+        }
+        self.tab_targets = Some(by_pid);
     }
 
-    fn scan_processes(&mut self, mode: &str) -> Vec<ProcessInfo> {
+    // Browser-mode tabs (from CDP) or browser-like OS processes, before any platform enrichment.
+    fn scan_candidates(&mut self, mode: &str) -> Vec<ProcessInfo> {
         if mode == "browser" {
             // Preload browser process info so Browser-level processes (GPU/Browser/Utility) can be selectable.
             if let Ok(map) = CdpClient::get_browser_process_info() {
@@ -196,8 +315,9 @@ impl ResourceCollector for GeneralCollector {
                     .filter(|t| t.r#type == "page" && t.ws_url.is_some())
                     .collect();
                 
-                let mut results = Vec::new();
-                self.cdp_sessions.clear();
+                let mut results: Vec<ProcessInfo> = Vec::new();
+                let mut previous = std::mem::take(self.cdp_sessions.get_mut().unwrap_or_else(|e| e.into_inner()));
+                let mut sessions = HashMap::new();
                 let mut seen_pids: std::collections::HashSet<u32> = std::collections::HashSet::new();
 
                 for (i, target) in pages.iter().enumerate() {
@@ -212,22 +332,47 @@ impl ResourceCollector for GeneralCollector {
 
                     // Fallback to virtual PID
                     if pid == 0 {
-                        pid = 90000 + i as u32; 
+                        pid = VIRTUAL_PID_BASE + i as u32; 
+                    }
+
+                    let tab = TabTarget {
+                        id: target.id.clone(),
+                        title: target.title.clone(),
+                        url: target.url.clone(),
+                    };
+                    // Tabs sharing a renderer (same site, or site isolation off) become one row;
+                    // the PID's numbers cover all of them.
+                    if let Some(row) = results.iter_mut().find(|r| r.pid == pid) {
+                        row.targets.push(tab);
+                        row.title = Some(tab_group_title(&row.targets));
+                        continue;
                     }
                     
                     if let Some(ws) = &target.ws_url {
-                        self.cdp_sessions.insert(pid, ws.clone());
+                        // Keep an open connection when the tab is unchanged.
+                        let session = match previous.remove(&pid) {
+                            Some(session) if session.ws_url() == ws.as_str() => session,
+                            _ => CdpSession::new(ws),
+                        };
+                        sessions.insert(pid, session);
                     }
 
                     // Try to get OS info if PID is real
                     let mut memory = 0;
                     let mut cpu = 0.0;
-                    if pid < 90000 {
+                    if pid < VIRTUAL_PID_BASE {
                         if let Some(proc) = self.system.process(Pid::from(pid as usize)) {
                             // sysinfo (0.30+) returns memory in bytes.
                             memory = proc.memory();
                             cpu = proc.cpu_usage();
                         }
+                        // Prefer the Chrome Task Manager-aligned numbers when the browser reports them.
+                        if let Some(pct) = self.browser_cpu_pct.get(&pid) {
+                            cpu = *pct;
+                        }
+                        if let Some(private) = self.browser_procinfo.get(&pid).and_then(|info| info.private_mem_bytes) {
+                            memory = private;
+                        }
                     }
 
                     results.push(ProcessInfo {
@@ -240,12 +385,17 @@ impl ResourceCollector for GeneralCollector {
                         proc_type: "Renderer".to_string(),
                         title: Some(target.title.clone()),
                         url: Some(target.url.clone()),
+                        targets: vec![tab],
+                        bundle_id: None,
+                        window_title: None,
                     });
-                    if pid < 90000 {
+                    if pid < VIRTUAL_PID_BASE {
                         seen_pids.insert(pid);
                     }
                 }
 
+                *self.cdp_sessions.get_mut().unwrap_or_else(|e| e.into_inner()) = sessions;
+
                 // Add browser-level non-tab processes (GPU/Browser/Utility) so users can monitor them in Browser API mode.
                 for (pid, info) in self.browser_procinfo.iter() {
                     if seen_pids.contains(pid) {
@@ -263,6 +413,9 @@ impl ResourceCollector for GeneralCollector {
                         proc_type: info.proc_type.clone(),
                         title: Some(format!("{} Process", info.proc_type)),
                         url: None,
+                        targets: Vec::new(),
+                        bundle_id: None,
+                        window_title: None,
                     });
                 }
 
@@ -302,21 +455,103 @@ impl ResourceCollector for GeneralCollector {
                     p_type = "Utility".to_string();
                 }
 
+                // sysinfo returns memory in bytes. Same figure as the run's samples will have.
+                let memory_usage = match self.memory_standard {
+                    MemoryStandard::TaskManager => task_manager_memory(pid.as_u32()).map(|(b, _)| b),
+                    MemoryStandard::Rss => None,
+                };
                 results.push(ProcessInfo {
                     pid: pid.as_u32(),
                     alias: None,
                     name: name,
-                    // sysinfo returns memory in bytes.
-                    memory_usage: process.memory(),
-                    cpu_usage: os_cpu_pct_for_task_manager(process.cpu_usage()),
+                    memory_usage: memory_usage.unwrap_or_else(|| process.memory()),
+                    cpu_usage: self.cpu_normalization.apply(process.cpu_usage()),
                     proc_type: p_type,
                     title: title,
                     url: url,
+                    targets: Vec::new(),
+                    bundle_id: None,
+                    window_title: None,
                 });
             }
         }
         results
     }
+}
+
+impl ResourceCollector for GeneralCollector {
+    fn update(&mut self) {
+        // sysinfo's CPU% (system and per-process) is computed from deltas between refreshes.
+        // On some platforms, `refresh_all()` doesn't reliably update per-process CPU usage
+        // unless processes/cpu are refreshed explicitly.
+        //
+        // Keep the same `System` instance and refresh at a reasonable interval (we use 1s).
+        self.system.refresh_cpu();
+        self.system.refresh_processes();
+
+        if self.mode == "browser" {
+            if let Ok(map) = (self.process_info)() {
+                self.browser_procinfo = map;
+
+                // Update CPU% cache based on cpuTime deltas.
+                let now = Instant::now();
+                let cpu_count = std::thread::available_parallelism()
+                    .map(|n| n.get())
+                    .unwrap_or(1) as f64;
+                let mut next_cpu = HashMap::new();
+                for (pid, info) in self.browser_procinfo.iter() {
+                    let cpu_time = info.cpu_time;
+                    if let Some((prev_time, prev_instant)) = self.prev_cpu_time.get(pid) {
+                        let dt = now.duration_since(*prev_instant).as_secs_f64();
+                        if dt > 0.0 {
+                            let dcpu = cpu_time - *prev_time;
+                            // cpuTime is CPU seconds; CPU% over wall time:
+                            // 100% == one fully utilized core; can exceed 100% with multi-threading.
+                            // Chrome Task Manager typically normalizes by total logical CPUs (percent of total CPU capacity).
+                            let pct = ((dcpu / dt) * 100.0 / cpu_count).max(0.0);
+                            next_cpu.insert(*pid, pct as f32);
+                        }
+                    }
+                    self.prev_cpu_time.insert(*pid, (cpu_time, now));
+                }
+                self.browser_cpu_pct = next_cpu;
+            }
+
+            if self.tabs_refreshed_at.is_none_or(|t| t.elapsed() >= TAB_REFRESH_INTERVAL) {
+                self.tabs_refreshed_at = Some(Instant::now());
+                self.refresh_tab_sessions();
+            }
+        }
+    }
+
+    fn take_tab_targets(&mut self) -> Option<HashMap<u32, Vec<TabTarget>>> {
+        self.tab_targets.take()
+    }
+
+    // Chrome-aligned CPU is a delta between two getProcessInfo samples: take the first one now
+    // so the first tick already has it.
+    fn prewarm(&mut self) {
+        if self.mode == "browser" {
+            self.update();
+            std::thread::sleep(BROWSER_PREWARM_DELAY);
+        }
+    }
+
+    fn scan_processes(&mut self, mode: &str) -> Vec<ProcessInfo> {
+        let results = self.scan_candidates(mode);
+        // Bundle ids and window titles make identical renderer rows tell apart.
+        #[cfg(target_os = "macos")]
+        let results = macos::enrich(results);
+        results
+    }
+
+    #[cfg(target_os = "macos")]
+    fn process_role(&self, pid: u32) -> Option<ProcessRole> {
+        if pid >= VIRTUAL_PID_BASE {
+            return None;
+        }
+        self.qos_roles.lock().unwrap_or_else(|e| e.into_inner()).sample(pid)
+    }
 
     fn collect_process(&self, pid: u32) -> Option<MetricPoint> {
         let mut point = MetricPoint {
@@ -324,23 +559,30 @@ impl ResourceCollector for GeneralCollector {
             pid,
             cpu_usage: 0.0,
             cpu_os_usage: 0.0,
+            cpu_os_usage_raw: None,
             cpu_chrome_usage: None,
             memory_rss: 0,
             memory_footprint: None,
             gpu_usage: None,
             js_heap_size: None,
+            js_heap_total_size: None,
             memory_private: None,
+            gpu_memory_bytes: None,
+            max_thread_cpu: None,
             custom_metrics: None,
+            memory_basis: None,
         };
 
         // 1. Get Sysinfo Metrics (if PID is likely real)
-        // Virtual PIDs start at 90000
-        if pid < 90000 {
+        // Virtual PIDs start at VIRTUAL_PID_BASE
+        if pid < VIRTUAL_PID_BASE {
             let sys_pid = Pid::from(pid as usize);
             if let Some(process) = self.system.process(sys_pid) {
-                point.cpu_os_usage = os_cpu_pct_for_task_manager(process.cpu_usage());
+                point.cpu_os_usage_raw = Some(process.cpu_usage());
+                point.cpu_os_usage = self.cpu_normalization.apply(process.cpu_usage());
                 // Default primary CPU to OS unless overridden by Chrome-aligned value in browser mode.
                 point.cpu_usage = point.cpu_os_usage;
+                point.max_thread_cpu = self.thread_cpu.lock().unwrap_or_else(|e| e.into_inner()).sample(pid);
                 // sysinfo returns memory in bytes, but we add a defensive macOS sanity normalization
                 // to avoid regressions if a platform/build reports KiB unexpectedly.
                 let rss_raw = process.memory();
@@ -350,8 +592,8 @@ impl ResourceCollector for GeneralCollector {
                     if total > 0 && rss_raw > total.saturating_mul(4) {
                         let rss_kib_as_bytes = rss_raw / 1024;
                         if rss_kib_as_bytes <= total.saturating_mul(4) {
-                            eprintln!(
-                                "WARN: sysinfo process.memory() looks like KiB; normalizing to bytes for pid {} (raw={}, normalized={})",
+                            crate::logging::log_warn!(
+                                "sysinfo process.memory() looks like KiB; normalizing to bytes for pid {} (raw={}, normalized={})",
                                 pid, rss_raw, rss_kib_as_bytes
                             );
                             point.memory_rss = rss_kib_as_bytes;
@@ -366,18 +608,32 @@ impl ResourceCollector for GeneralCollector {
                 {
                     point.memory_rss = rss_raw;
                 }
+                point.memory_basis = Some(MemoryBasis::Rss);
+                if self.memory_standard == MemoryStandard::TaskManager && self.mode != "browser" {
+                    if let Some((bytes, basis)) = task_manager_memory(pid) {
+                        point.memory_private = Some(bytes);
+                        point.memory_basis = Some(basis);
+                        if basis == MemoryBasis::PhysFootprint {
+                            point.memory_footprint = Some(bytes);
+                        }
+                    }
+                }
             }
         }
 
         // macOS note:
         // We intentionally do NOT emit Activity Monitor "footprint" as the default System API memory
         // because it confuses users and doesn't match Activity Monitor's "Inspect Process -> Real Memory Size".
-        // For System API, we treat memory as RSS ("real memory") via sysinfo.
+        // For System API, we treat memory as RSS ("real memory") via sysinfo unless the run asks
+        // for MemoryStandard::TaskManager.
         // We only use rusage-based footprint as a best-effort fallback for Chrome-aligned browser metrics.
 
         // 2. Get CDP Metrics (if session exists)
-        if let Some(ws_url) = self.cdp_sessions.get(&pid) {
-            point.js_heap_size = CdpClient::get_js_heap(ws_url);
+        if let Some(session) = self.cdp_sessions.lock().unwrap_or_else(|e| e.into_inner()).get_mut(&pid) {
+            if let Some(heap) = session.heap_usage() {
+                point.js_heap_size = Some(heap.used_size);
+                point.js_heap_total_size = Some(heap.total_size);
+            }
         }
 
         // 3. Browser Task Manager-aligned CPU% + Memory footprint (if available)
@@ -391,17 +647,24 @@ impl ResourceCollector for GeneralCollector {
             }
             if let Some(info) = self.browser_procinfo.get(&pid) {
                 point.memory_private = info.private_mem_bytes;
+                if point.memory_private.is_some() {
+                    point.memory_basis = Some(MemoryBasis::PrivateCdp);
+                }
+                if info.proc_type == "GPU" {
+                    point.gpu_memory_bytes = info.gpu_mem_bytes;
+                }
             }
 
             // On macOS, Chrome Task Manager "Memory footprint" aligns better with phys_footprint
             // than RSS or CDP privateMemorySize (which may be absent depending on Chrome build).
             #[cfg(target_os = "macos")]
-            if pid < 90000 {
+            if pid < VIRTUAL_PID_BASE {
                 // Always capture footprint as a separate field so the frontend can choose it.
                 point.memory_footprint = macos_activity_monitor_memory_bytes(pid);
                 // And if CDP didn't provide private memory, fall back to footprint.
-                if point.memory_private.is_none() {
+                if point.memory_private.is_none() && point.memory_footprint.is_some() {
                     point.memory_private = point.memory_footprint;
+                    point.memory_basis = Some(MemoryBasis::PhysFootprint);
                 }
             }
         }
@@ -415,5 +678,86 @@ impl ResourceCollector for GeneralCollector {
 }
 
 pub fn create_collector(mode: &str) -> Box<dyn ResourceCollector + Send> {
-    Box::new(GeneralCollector::new(mode.to_string()))
+    create_collector_with(mode, None, CpuNormalization::platform_default(), MemoryStandard::Rss)
+}
+
+/// Like `create_collector`, with the series shape used by mode "simulate" (defaults when None),
+/// the OS CPU% normalization and the system-mode memory figure.
+pub fn create_collector_with(
+    mode: &str,
+    simulation: Option<&SimulationConfig>,
+    cpu_normalization: CpuNormalization,
+    memory_standard: MemoryStandard,
+) -> Box<dyn ResourceCollector + Send> {
+    if mode == "simulate" {
+        return Box::new(SimulatedCollector::new(simulation.cloned().unwrap_or_default()));
+    }
+    let mut collector = GeneralCollector::new(mode.to_string());
+    collector.cpu_normalization = cpu_normalization;
+    collector.memory_standard = memory_standard;
+    Box::new(collector)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    // A fake browser whose only process is the test process, burning 10 ms of CPU per call.
+    fn fake_process_info() -> Result<HashMap<u32, BrowserProcessInfo>, String> {
+        static CPU_MS: AtomicU64 = AtomicU64::new(0);
+        let cpu_time = CPU_MS.fetch_add(10, Ordering::SeqCst) as f64 / 1000.0;
+        let info = BrowserProcessInfo {
+            cpu_time,
+            private_mem_bytes: Some(64 * 1024 * 1024),
+            gpu_mem_bytes: None,
+            proc_type: "Renderer".to_string(),
+        };
+        Ok(HashMap::from([(std::process::id(), info)]))
+    }
+
+    fn browser_collector() -> GeneralCollector {
+        let mut collector = GeneralCollector::new("browser".to_string());
+        collector.process_info = fake_process_info;
+        // No tab refresh against a real debugging endpoint.
+        collector.tabs_refreshed_at = Some(Instant::now());
+        collector
+    }
+
+    fn chrome_cpu(collector: &GeneralCollector) -> Option<f32> {
+        collector.collect_process(std::process::id()).and_then(|p| p.cpu_chrome_usage)
+    }
+
+    #[test]
+    fn without_prewarm_the_first_tick_has_no_chrome_cpu() {
+        let mut collector = browser_collector();
+        collector.update();
+        assert_eq!(chrome_cpu(&collector), None);
+        std::thread::sleep(Duration::from_millis(20));
+        collector.update();
+        assert!(chrome_cpu(&collector).is_some());
+    }
+
+    #[test]
+    fn prewarm_gives_the_first_tick_chrome_cpu() {
+        let mut collector = browser_collector();
+        collector.prewarm();
+        collector.update();
+        let point = collector.collect_process(std::process::id()).unwrap();
+        let cpu = point.cpu_chrome_usage.expect("first tick after the pre-warm has a cpuTime delta");
+        assert!(cpu > 0.0);
+        assert_eq!(point.cpu_usage, cpu);
+        assert_eq!(point.memory_private, Some(64 * 1024 * 1024));
+        assert_eq!(point.memory_basis, Some(MemoryBasis::PrivateCdp));
+    }
+
+    #[test]
+    fn prewarm_is_a_no_op_outside_browser_mode() {
+        let mut collector = GeneralCollector::new("system".to_string());
+        collector.process_info = fake_process_info;
+        let started = Instant::now();
+        collector.prewarm();
+        assert!(started.elapsed() < BROWSER_PREWARM_DELAY);
+        assert!(collector.prev_cpu_time.is_empty());
+    }
 }
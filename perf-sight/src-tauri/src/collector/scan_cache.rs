@@ -0,0 +1,99 @@
+use super::cdp::CDP_ENDPOINT;
+use super::{create_collector_with, ResourceCollector};
+use crate::models::{CpuNormalization, MemoryStandard, ProcessInfo};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Scan results younger than this are served without touching the system.
+pub const SCAN_MAX_AGE: Duration = Duration::from_secs(3);
+
+// When each list was scanned, by cache key.
+type ScanResults = HashMap<String, (Instant, Vec<ProcessInfo>)>;
+// The shared collector with the mode, normalization and memory standard it was built for.
+type SharedCollector = Option<(String, CpuNormalization, MemoryStandard, Box<dyn ResourceCollector + Send>)>;
+
+/// Process lists shared by the picker and target selectors. One long-lived collector does the
+/// scanning, so repeated scans refresh its `System` incrementally instead of rebuilding it.
+#[derive(Clone, Default)]
+pub struct ProcessScanCache {
+    results: Arc<Mutex<ScanResults>>,
+    // Rebuilt only when the mode, the CPU normalization or the memory standard changes.
+    collector: Arc<Mutex<SharedCollector>>,
+    // Keys with a background refresh in flight.
+    refreshing: Arc<Mutex<HashSet<String>>>,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl ProcessScanCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cache key of a collector scan: browser scans are per CDP endpoint, system scans per
+    /// memory standard (their memory column differs).
+    pub fn key(mode: &str, memory_standard: MemoryStandard) -> String {
+        match (mode, memory_standard) {
+            ("browser", _) => format!("browser@{}", CDP_ENDPOINT),
+            (_, MemoryStandard::Rss) => mode.to_string(),
+            (_, standard) => format!("{}#{}", mode, standard.as_str()),
+        }
+    }
+
+    /// The last list stored under `key`, with its age.
+    pub fn cached(&self, key: &str) -> Option<(Duration, Vec<ProcessInfo>)> {
+        lock(&self.results).get(key).map(|(at, list)| (at.elapsed(), list.clone()))
+    }
+
+    pub fn store(&self, key: &str, list: &[ProcessInfo]) {
+        lock(&self.results).insert(key.to_string(), (Instant::now(), list.to_vec()));
+    }
+
+    /// Mark `key` as being refreshed. False when a refresh is already running.
+    pub fn begin_refresh(&self, key: &str) -> bool {
+        lock(&self.refreshing).insert(key.to_string())
+    }
+
+    pub fn end_refresh(&self, key: &str) {
+        lock(&self.refreshing).remove(key);
+    }
+
+    /// Scan `mode` with the shared collector, reusing a result younger than `max_age`.
+    /// Blocking; scans are serialized.
+    pub fn scan(
+        &self,
+        mode: &str,
+        cpu_normalization: CpuNormalization,
+        memory_standard: MemoryStandard,
+        max_age: Duration,
+    ) -> Vec<ProcessInfo> {
+        let key = Self::key(mode, memory_standard);
+        let mut collector = lock(&self.collector);
+        // Another caller may have scanned while we waited for the collector.
+        if let Some((age, list)) = self.cached(&key) {
+            if age < max_age {
+                return list;
+            }
+        }
+        let reusable = matches!(
+            collector.as_ref(),
+            Some((m, n, s, _)) if m == mode && *n == cpu_normalization && *s == memory_standard
+        );
+        let collector = match collector.as_mut() {
+            Some((_, _, _, c)) if reusable => {
+                c.update();
+                c
+            }
+            _ => {
+                let c = create_collector_with(mode, None, cpu_normalization, memory_standard);
+                &mut collector.insert((mode.to_string(), cpu_normalization, memory_standard, c)).3
+            }
+        };
+        let list = collector.scan_processes(mode);
+        self.store(&key, &list);
+        list
+    }
+}
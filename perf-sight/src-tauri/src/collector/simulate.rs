@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use crate::models::{BatchMetric, MemoryBasis, MetricPoint, ProcessInfo};
+use super::ResourceCollector;
+
+// Fake PIDs start here so they can't be mistaken for real processes in the UI.
+pub const SIMULATED_PID_BASE: u32 = 80000;
+
+/// Shape of the synthetic series. Every field is optional in JSON; unset fields use the defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimulationConfig {
+    /// Number of fake processes offered by `scan_processes`.
+    pub process_count: u32,
+    /// Steady CPU% per process.
+    pub baseline_cpu: f32,
+    /// Every `spike_every` samples the CPU jumps to `spike_cpu` (0 disables spikes).
+    pub spike_every: u64,
+    pub spike_cpu: f32,
+    /// Largest deviation of the CPU% from the baseline, as deterministic noise (0 disables it).
+    pub cpu_jitter: f32,
+    /// Starting RSS per process.
+    pub base_mem_mb: f64,
+    /// Linear growth of the first process's RSS, in MB per sample.
+    pub leak_mb_per_sample: f64,
+    /// Name of the sine-wave custom metric (empty disables it).
+    pub custom_metric: String,
+    pub sine_center: f64,
+    pub sine_amplitude: f64,
+    pub sine_period_samples: u64,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            process_count: 3,
+            baseline_cpu: 12.0,
+            spike_every: 20,
+            spike_cpu: 90.0,
+            cpu_jitter: 0.0,
+            base_mem_mb: 200.0,
+            leak_mb_per_sample: 1.0,
+            custom_metric: "sim_fps".to_string(),
+            sine_center: 60.0,
+            sine_amplitude: 10.0,
+            sine_period_samples: 30,
+        }
+    }
+}
+
+/// Deterministic synthetic collector for demos and tests: sample `n` of a PID depends only on
+/// the config, the PID and `n`.
+pub struct SimulatedCollector {
+    config: SimulationConfig,
+    // Sample index per PID, advanced by collect_process.
+    ticks: std::sync::Mutex<HashMap<u32, u64>>,
+}
+
+impl SimulatedCollector {
+    pub fn new(config: SimulationConfig) -> Self {
+        Self {
+            config,
+            ticks: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// `count` batches of every PID in `pids`, `interval_ms` apart from `start`: what a run of the
+    /// collector would have recorded, without waiting for it.
+    pub fn series(&self, pids: &[u32], count: u64, start: DateTime<Utc>, interval_ms: u64) -> Vec<BatchMetric> {
+        (0..count)
+            .map(|n| {
+                let timestamp = start + Duration::milliseconds((n * interval_ms) as i64);
+                let metrics = pids
+                    .iter()
+                    .map(|&pid| (pid, MetricPoint { timestamp, ..self.sample(pid, n) }))
+                    .collect();
+                BatchMetric { timestamp, metrics }
+            })
+            .collect()
+    }
+
+    fn sample(&self, pid: u32, n: u64) -> MetricPoint {
+        let c = &self.config;
+        let index = pid.saturating_sub(SIMULATED_PID_BASE) as u64;
+        // Small per-process offset so the series aren't identical.
+        let mut cpu = c.baseline_cpu + (index as f32) * 1.5;
+        if c.cpu_jitter > 0.0 {
+            // Cheap integer hash of (pid, n) mapped to -1..1.
+            let h = (pid as u64).wrapping_mul(2_654_435_761) ^ n.wrapping_mul(40_503).wrapping_add(0x9e37_79b9);
+            let unit = (h.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 40) as f32 / (1u64 << 24) as f32;
+            cpu = (cpu + c.cpu_jitter * (unit * 2.0 - 1.0)).max(0.0);
+        }
+        if c.spike_every > 0 && n > 0 && n.is_multiple_of(c.spike_every) {
+            cpu = c.spike_cpu;
+        }
+        let mut mem_mb = c.base_mem_mb + (index as f64) * 50.0;
+        if index == 0 {
+            mem_mb += c.leak_mb_per_sample * n as f64;
+        }
+        let custom_metrics = if c.custom_metric.trim().is_empty() {
+            None
+        } else {
+            let period = c.sine_period_samples.max(1) as f64;
+            let v = c.sine_center + c.sine_amplitude * (2.0 * std::f64::consts::PI * n as f64 / period).sin();
+            Some(HashMap::from([(c.custom_metric.clone(), v)]))
+        };
+        let rss = (mem_mb.max(0.0) * 1024.0 * 1024.0) as u64;
+        MetricPoint {
+            timestamp: Utc::now(),
+            pid,
+            cpu_usage: cpu,
+            cpu_os_usage: cpu,
+            cpu_os_usage_raw: None,
+            cpu_chrome_usage: None,
+            memory_rss: rss,
+            memory_footprint: None,
+            gpu_usage: None,
+            js_heap_size: None,
+            js_heap_total_size: None,
+            memory_private: None,
+            gpu_memory_bytes: None,
+            max_thread_cpu: None,
+            custom_metrics,
+            memory_basis: Some(MemoryBasis::Rss),
+        }
+    }
+}
+
+impl ResourceCollector for SimulatedCollector {
+    fn update(&mut self) {}
+
+    fn scan_processes(&mut self, _mode: &str) -> Vec<ProcessInfo> {
+        (0..self.config.process_count.max(1))
+            .map(|i| {
+                let pid = SIMULATED_PID_BASE + i;
+                let p = self.sample(pid, 0);
+                ProcessInfo {
+                    pid,
+                    alias: None,
+                    name: format!("simulated-{}", i + 1),
+                    memory_usage: p.memory_rss,
+                    cpu_usage: p.cpu_usage,
+                    proc_type: if i == 0 { "Browser" } else { "Renderer" }.to_string(),
+                    title: Some("Simulated process".to_string()),
+                    url: None,
+                    targets: Vec::new(),
+                    bundle_id: None,
+                    window_title: None,
+                }
+            })
+            .collect()
+    }
+
+    fn collect_process(&self, pid: u32) -> Option<MetricPoint> {
+        let n = {
+            let mut ticks = self.ticks.lock().unwrap_or_else(|e| e.into_inner());
+            let t = ticks.entry(pid).or_insert(0);
+            let n = *t;
+            *t += 1;
+            n
+        };
+        Some(self.sample(pid, n))
+    }
+}
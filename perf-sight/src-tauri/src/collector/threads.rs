@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// CPU% of each process's busiest thread (100 = one full core), from per-thread CPU time deltas
+/// between two samples. Platforms without per-thread times always yield None.
+#[derive(Default)]
+pub struct ThreadCpuTracker {
+    // PID -> (thread id -> CPU seconds, when they were read)
+    prev: HashMap<u32, (HashMap<u64, f64>, Instant)>,
+}
+
+impl ThreadCpuTracker {
+    /// None on the first sample of a PID, and when its threads can't be read.
+    pub fn sample(&mut self, pid: u32) -> Option<f32> {
+        let Some(times) = thread_cpu_seconds(pid) else {
+            self.prev.remove(&pid);
+            return None;
+        };
+        let now = Instant::now();
+        let busiest = self.prev.get(&pid).and_then(|(prev, at)| {
+            let dt = now.duration_since(*at).as_secs_f64();
+            if dt <= 0.0 {
+                return None;
+            }
+            // Threads that started since the last sample have no baseline yet.
+            times
+                .iter()
+                .filter_map(|(tid, secs)| prev.get(tid).map(|p| (secs - p).max(0.0)))
+                .reduce(f64::max)
+                .map(|delta| (delta / dt * 100.0) as f32)
+        });
+        self.prev.insert(pid, (times, now));
+        busiest
+    }
+}
+
+// Cumulative user + system CPU seconds per thread (from /proc/<pid>/task/<tid>/stat).
+#[cfg(target_os = "linux")]
+fn thread_cpu_seconds(pid: u32) -> Option<HashMap<u64, f64>> {
+    let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks <= 0 {
+        return None;
+    }
+    let mut out = HashMap::new();
+    for entry in std::fs::read_dir(format!("/proc/{}/task", pid)).ok()?.flatten() {
+        let Some(tid) = entry.file_name().to_str().and_then(|s| s.parse::<u64>().ok()) else { continue };
+        let Ok(stat) = std::fs::read_to_string(entry.path().join("stat")) else { continue };
+        // The command name may contain spaces; fields after it start at "state" (field 3), so
+        // utime and stime (fields 14 and 15) are at 11 and 12.
+        let Some((_, rest)) = stat.rsplit_once(')') else { continue };
+        let fields: Vec<&str> = rest.split_whitespace().collect();
+        let (Some(utime), Some(stime)) =
+            (fields.get(11).and_then(|v| v.parse::<u64>().ok()), fields.get(12).and_then(|v| v.parse::<u64>().ok()))
+        else {
+            continue;
+        };
+        out.insert(tid, (utime + stime) as f64 / ticks as f64);
+    }
+    (!out.is_empty()).then_some(out)
+}
+
+// Per-thread times from proc_pidinfo, which (unlike thread_info) needs no task port.
+#[cfg(target_os = "macos")]
+fn thread_cpu_seconds(pid: u32) -> Option<HashMap<u64, f64>> {
+    use std::mem::{size_of, MaybeUninit};
+    // From <sys/proc_info.h>; kept as literals to avoid libc API drift.
+    const PROC_PIDLISTTHREADS: libc::c_int = 6;
+    const PROC_PIDTHREADINFO: libc::c_int = 5;
+    const MAX_THREADS: usize = 4096;
+
+    let mut ids = vec![0u64; MAX_THREADS];
+    let bytes = unsafe {
+        libc::proc_pidinfo(
+            pid as libc::c_int,
+            PROC_PIDLISTTHREADS,
+            0,
+            ids.as_mut_ptr().cast(),
+            (ids.len() * size_of::<u64>()) as libc::c_int,
+        )
+    };
+    if bytes <= 0 {
+        return None;
+    }
+    ids.truncate(bytes as usize / size_of::<u64>());
+    let mut out = HashMap::new();
+    for id in ids {
+        let mut info = MaybeUninit::<libc::proc_threadinfo>::zeroed();
+        let size = size_of::<libc::proc_threadinfo>() as libc::c_int;
+        let rc = unsafe { libc::proc_pidinfo(pid as libc::c_int, PROC_PIDTHREADINFO, id, info.as_mut_ptr().cast(), size) };
+        if rc != size {
+            continue;
+        }
+        let info = unsafe { info.assume_init() };
+        out.insert(id, (info.pth_user_time + info.pth_system_time) as f64 / 1e9);
+    }
+    (!out.is_empty()).then_some(out)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn thread_cpu_seconds(_pid: u32) -> Option<HashMap<u64, f64>> {
+    None
+}
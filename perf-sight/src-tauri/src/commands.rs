@@ -1,404 +1,3389 @@
 
 use tauri::{AppHandle, Emitter, State, Manager};
-use std::sync::{Arc, Mutex};
-use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::collections::{HashMap, VecDeque};
 use tauri_plugin_shell::ShellExt;
 use tauri_plugin_shell::process::{CommandEvent, CommandChild};
-use crate::models::{CollectionConfig, ProcessInfo, BatchMetric, MetricPoint, ProcessAlias, LogMetricConfig};
-use crate::collector::create_collector;
+use crate::logging::{self, log_debug, log_error, log_info, log_warn, LogLevel, LogLine};
+use crate::models::{CollectionConfig, ProcessInfo, BatchMetric, MetricPoint, ProcessAlias, TabTarget, ProcessSelector, LogMetricConfig, MetricSinkConfig, PerformanceBudget, ReportMeta, AppMeta, EnvMeta, CollectionMeta, TestContext, CpuNormalization, DataSourceInfo, PidCoverage, MetricSelection, RunEvent, RunEventKind, EffectiveSampling, MemoryBasis, MemoryStandard, ProcessRole, ProcessRoleSummary, ProcessLifecycle, ProcessTermination, TestContextCut, TestContextLimits, LogParseFailures, LogIngestStats, FolderCollectionSettings, SequenceStats, AUTO_TITLE_PREFIX, MAX_INTERVAL_MS, SELF_PROC_TYPE, check_folder_path, check_metric_ids, compile_log_pattern, normalize_folder_path};
+use crate::collector::{create_collector_with, VIRTUAL_PID_BASE};
+use crate::lifecycle;
+use crate::collector::scan_cache::{ProcessScanCache, SCAN_MAX_AGE};
+use crate::collector::adaptive::AdaptiveInterval;
+use crate::collector::simulate::SimulationConfig;
+use crate::collector::cdp::{CdpClient, GpuInfo, ProcessInfoDebug};
+use crate::analysis::BudgetResult;
+use crate::metric_sink::{MetricSink, MetricSinkStats};
+use crate::stream_file::{StreamFile, StreamFileStats};
+use crate::otlp::{self, OtlpExportResult};
+use crate::junit;
+use crate::csv_export;
+use crate::markdown;
+use crate::timezone::{self, DisplayZone};
+use crate::settings::Settings;
+use crate::error::PerfSightError;
+use crate::csv_import::{self, CsvImportMapping, CsvImportResult};
+use crate::trace_import::{self, TraceSummary};
+use crate::scenario::{self, ScenarioState};
+use crate::webhook::{self, WebhookConfig};
+use crate::ws_server::{ExtensionStatus, IngestServerState, WsClientInfo, WS_PROTOCOL_MAX, WS_PROTOCOL_MIN};
 use crate::database::{
     Database,
+    FolderConflict,
+    ReanalyzeScope,
     ReportSummary,
     ReportDetail,
+    StoredReport,
+    for_each_batch,
     TagStat,
     FolderInfo,
     FolderStats,
     ComparisonSummary,
     ComparisonDetail,
     ComparisonFolderStats,
+    CollectionPreset,
+    FolderAggregate,
+    ComparisonFolderAggregate,
+    FolderRule,
+    MaintenanceReport,
+    DatabaseStats,
+    StorageBreakdown,
+    QueryResult,
 };
+use crate::folder_rules::{self, FolderRuleMatch};
+use crate::sample_data;
+use crate::artifacts;
+use crate::parallel;
+use crate::anonymize::{AnonymizeOptions, Anonymizer};
+use crate::dataset::{self, DatasetArtifact, DatasetCompatWarning, DatasetHeaderV2, ReportDataset};
 use chrono::{DateTime, Utc, TimeZone};
 use serde_json::json;
 use serde_json::Value;
-#[cfg(target_os = "macos")]
 use std::time::Duration;
-use base64::Engine;
 use tauri::path::BaseDirectory;
 use serde::{Deserialize, Serialize};
 use regex::Regex;
+use crate::roles::{self, RoleAssignment, RoleTemplate};
+use crate::analysis::ScoreRegression;
 use zip::write::FileOptions;
 use zip::ZipWriter;
 use std::io::Write;
 
+/// Everything that belongs to one collection run. It only exists while the run is active, so
+/// ingest paths can't buffer samples outside a run, and start/stop swap it in and out atomically.
+pub struct ActiveRun {
+    pub session_id: SessionId,
+    pub started_at: String,
+    pub target_pids: Vec<u32>,
+    pub mode: String,
+    pub interval_ms: u64,
+    pub app_version: String,
+    pub test_context: Option<Value>,
+    // Caps applied to `test_context` at start and what they cut (saved as
+    // `meta.collection.test_context_limits`).
+    pub test_context_limits: Option<(TestContextLimits, Vec<TestContextCut>)>,
+    pub process_snapshot: Vec<ProcessInfo>,
+    pub process_aliases: Vec<ProcessAlias>,
+    pub folder_path: Option<String>,
+    // PerfSight's own PIDs among target_pids (`CollectionConfig::include_self` or picked by hand).
+    pub self_pids: Vec<u32>,
+    // Config fields taken from folder defaults, with the folder each came from.
+    pub folder_defaults: serde_json::Map<String, Value>,
+    pub stop_after_seconds: Option<u64>,
+    // Compiled regexes for log metrics: (Config, Regex)
+    pub log_metrics: Vec<(LogMetricConfig, Regex)>,
+    // Captures per log metric name that did not parse as a number.
+    pub log_parse_failures: HashMap<String, LogParseFailures>,
+    // Log lines truncated or dropped before matching.
+    pub log_ingest: LogIngestStats,
+    pub budgets: Vec<PerformanceBudget>,
+    // Series shape of a "simulate" run (recorded in report meta).
+    pub simulation: Option<SimulationConfig>,
+    // How OS CPU% was scaled (saved as `meta.definitions.cpu_normalization`).
+    pub cpu_normalization: CpuNormalization,
+    // System API memory figure (saved as `meta.definitions.memory_standard`).
+    pub memory_standard: MemoryStandard,
+    // Rule-based selection of the run and how its PID set changed over time.
+    pub target_selector: Option<ProcessSelector>,
+    pub membership: Vec<MembershipChange>,
+    pub buffer: Vec<BatchMetric>,
+    // Latest buffered sample per PID (custom metrics merged in), for live exporters.
+    pub latest_samples: HashMap<u32, MetricPoint>,
+    // Optional line-protocol sink for the run.
+    pub metric_sink: Option<MetricSink>,
+    // Optional JSON Lines copy of the buffer (`CollectionConfig::stream_to_file`).
+    pub stream_file: Option<StreamFile>,
+    // Per-source health and drop counters.
+    pub ingest: IngestCounters,
+    // Timeline saved as `meta.events` (see append_run_event).
+    pub events: Vec<RunEvent>,
+    // Static GPU description (browser mode, via CDP), saved as `meta.env.gpu`.
+    pub gpu_info: Option<GpuInfo>,
+    // Raw CDP process info captured at start when requested, saved as `meta.env.cdp_process_info`.
+    pub cdp_process_info: Option<ProcessInfoDebug>,
+    // Extension connections whose data this run received.
+    pub ws_clients: Vec<WsClientInfo>,
+    // Set when a browser-mode run needs the extension for memory (saved as
+    // `meta.collection.extension_dependency`).
+    pub extension_dependency: Option<ExtensionDependency>,
+    // Last sidecar line before the current stall, while the sidecar is stalled.
+    pub stalled_since: Option<DateTime<Utc>>,
+    // Stopped because the app is quitting (saved as `meta.interrupted_by_exit`).
+    pub interrupted_by_exit: bool,
+    // CPU/memory samples per PID, saved as `meta.collection.coverage`.
+    pub coverage: HashMap<u32, PidCoverage>,
+    // Page target id (or extension tab id) -> renderer PID, used to attribute console-log
+    // metrics. Seeded from the browser-mode scan at start; saved as `meta.collection.tab_pids`.
+    pub tab_pids: HashMap<String, u32>,
+    // Custom metric whose zero/non-zero switches mark idle/active phases, and its last state.
+    pub phase_metric: Option<String>,
+    pub phase_active: Option<bool>,
+    // Aliases given by role templates at start, saved as `meta.collection.role_assignments`.
+    pub role_assignments: Vec<RoleAssignment>,
+    // Batches before this index are already in `run_chunks` (see spawn_chunk_writer), as are
+    // the chunks numbered below `chunk_seq`.
+    pub persisted_batches: usize,
+    pub chunk_seq: i64,
+    // Held while a chunk is written, so the stop path never assembles around a write in flight.
+    pub chunk_lock: Arc<Mutex<()>>,
+    // What the sidecar acknowledged for this run (sidecar modes only, see apply_sidecar_ack).
+    pub effective_sampling: Option<EffectiveSampling>,
+    // Effective interval under `CollectionConfig::adaptive_sampling` (native and sidecar modes).
+    pub adaptive: Option<AdaptiveInterval>,
+    // Current role, when it was last seen and time per role, per PID (macOS native runs).
+    pub process_roles: HashMap<u32, (ProcessRole, DateTime<Utc>, ProcessRoleSummary)>,
+    // Tab title per browser-mode PID: (current, most descriptive seen). Seeded from the snapshot.
+    pub tab_titles: HashMap<u32, (String, String)>,
+    // Target PIDs that went away mid-run: when it was noticed and, once resolved, why.
+    pub exits: HashMap<u32, (DateTime<Utc>, Option<ProcessTermination>)>,
+}
+
+impl ActiveRun {
+    /// Interval samples are currently recorded at: the adaptive one when the run has a policy.
+    fn sampling_interval_ms(&self) -> u64 {
+        self.adaptive.as_ref().map_or(self.interval_ms, |a| a.current_ms())
+    }
+
+    /// Feed a recorded batch to the adaptive policy. Returns the new interval when it changed.
+    fn adapt_interval(&mut self, batch: &BatchMetric) -> Option<u64> {
+        self.adaptive.as_mut().and_then(|a| a.observe(batch))
+    }
+
+    /// Update the per-PID "latest sample" cache from a batch that was just buffered.
+    /// Custom-metric-only points (no CPU/memory) are merged into the previous sample instead of replacing it.
+    fn record_latest(&mut self, batch: &BatchMetric) {
+        for (pid, point) in &batch.metrics {
+            let custom_only = point.is_custom_only();
+            match self.latest_samples.get_mut(pid) {
+                Some(prev) if custom_only => {
+                    let merged = prev.custom_metrics.get_or_insert_with(HashMap::new);
+                    if let Some(custom) = &point.custom_metrics {
+                        for (k, v) in custom {
+                            merged.insert(k.clone(), *v);
+                        }
+                    }
+                }
+                Some(prev) => {
+                    let mut next = point.clone();
+                    if let Some(prev_custom) = prev.custom_metrics.take() {
+                        let merged = next.custom_metrics.get_or_insert_with(HashMap::new);
+                        for (k, v) in prev_custom {
+                            merged.entry(k).or_insert(v);
+                        }
+                    }
+                    *prev = next;
+                }
+                None => {
+                    self.latest_samples.insert(*pid, point.clone());
+                }
+            }
+        }
+    }
+
+    // Forward newly buffered points to the run's metric sink, if one is configured.
+    fn forward_to_sink(&self, batch: &BatchMetric) {
+        if let Some(sink) = self.metric_sink.as_ref() {
+            sink.send_batch(batch);
+        }
+        if let Some(file) = self.stream_file.as_ref() {
+            file.send_batch(batch);
+        }
+    }
+
+    // Account the time since a PID's previous classification to the role it had then; a changed
+    // role is added to the timeline.
+    fn record_role(&mut self, pid: u32, role: ProcessRole, at: DateTime<Utc>) {
+        let Some((prev, last_at, summary)) = self.process_roles.get_mut(&pid) else {
+            self.process_roles.insert(pid, (role, at, ProcessRoleSummary::default()));
+            return;
+        };
+        let elapsed = (at - *last_at).num_milliseconds().max(0);
+        summary.observed_ms += elapsed;
+        if *prev == ProcessRole::Background {
+            summary.background_ms += elapsed;
+        }
+        *last_at = at;
+        if *prev != role {
+            summary.transitions += 1;
+            self.events.push(RunEvent {
+                timestamp: at,
+                kind: RunEventKind::ProcessRoleChanged,
+                pid: Some(pid),
+                detail: json!({ "from": prev, "to": role }),
+            });
+            *prev = role;
+        }
+    }
+
+    // Attach the resolved reason to an exit noticed at `at` and add it to the timeline.
+    fn record_exit(&mut self, pid: u32, at: DateTime<Utc>, termination: ProcessTermination) {
+        self.events.push(RunEvent {
+            timestamp: at,
+            kind: RunEventKind::ProcessExited,
+            pid: Some(pid),
+            detail: json!(termination),
+        });
+        self.exits.insert(pid, (at, Some(termination)));
+    }
+
+    // Follow the titles of the monitored tabs; a changed title is added to the timeline and the
+    // longest one seen is kept for the report.
+    fn observe_tab_titles(&mut self, tabs: &HashMap<u32, Vec<TabTarget>>, at: DateTime<Utc>) {
+        for pid in &self.target_pids {
+            let Some(title) = tabs.get(pid).map(|t| tab_title(t)).filter(|t| !t.is_empty()) else { continue };
+            let Some((current, best)) = self.tab_titles.get_mut(pid) else {
+                self.tab_titles.insert(*pid, (title.clone(), title));
+                continue;
+            };
+            if *current == title {
+                continue;
+            }
+            self.events.push(RunEvent {
+                timestamp: at,
+                kind: RunEventKind::TabTitleChanged,
+                pid: Some(*pid),
+                detail: json!({ "from": current, "to": title }),
+            });
+            if title.chars().count() > best.chars().count() {
+                *best = title.clone();
+            }
+            *current = title;
+        }
+    }
+
+    /// The run's aliases: the ones given at start (or by role templates), then the tab title of
+    /// every other browser-mode PID.
+    pub fn effective_aliases(&self) -> Vec<ProcessAlias> {
+        let mut aliases = self.process_aliases.clone();
+        let mut derived: Vec<ProcessAlias> = self
+            .tab_titles
+            .iter()
+            .filter(|(pid, _)| !self.process_aliases.iter().any(|a| a.pid == **pid && !a.alias.trim().is_empty()))
+            .map(|(pid, (_, best))| ProcessAlias { pid: *pid, alias: best.clone() })
+            .collect();
+        derived.sort_by_key(|a| a.pid);
+        aliases.extend(derived);
+        aliases
+    }
+
+    // Count a CPU/memory sample per PID of a newly buffered batch.
+    fn record_coverage<'a>(&mut self, at: DateTime<Utc>, points: impl Iterator<Item = (&'a u32, &'a MetricPoint)>) {
+        for (pid, _) in points.filter(|(_, p)| !p.is_custom_only()) {
+            PidCoverage::record(&mut self.coverage, *pid, at);
+        }
+    }
+
+    /// Add a phase marker when the phase metric switches between zero and non-zero.
+    fn record_phase_switch(&mut self, batch: &BatchMetric) {
+        let Some(name) = &self.phase_metric else { return };
+        let Some(value) = batch.metrics.values().find_map(|p| p.custom_metrics.as_ref()?.get(name).copied()) else {
+            return;
+        };
+        let active = value != 0.0;
+        if self.phase_active == Some(active) {
+            return;
+        }
+        self.phase_active = Some(active);
+        let phase = if active { "active" } else { "idle" };
+        self.events.push(RunEvent {
+            timestamp: batch.timestamp,
+            kind: RunEventKind::Marker,
+            pid: None,
+            detail: json!({ "label": format!("{} {}", name, phase), "phase": phase, "source": name }),
+        });
+    }
+
+    /// Append a batch to the run (latest-sample cache, sink and source health included).
+    fn buffer_batch(&mut self, batch: BatchMetric, source: DataSource) {
+        self.ingest.touch(source, batch.metrics.len());
+        self.record_latest(&batch);
+        self.record_coverage(batch.timestamp, batch.metrics.iter());
+        self.record_phase_switch(&batch);
+        self.forward_to_sink(&batch);
+        self.buffer.push(batch);
+    }
+}
+
+/// Identifies one of several concurrent runs (returned by `start_collection`).
+pub type SessionId = String;
+
 #[derive(Clone)]
 pub struct CollectionState {
     // Child process handle to write to stdin or kill
     pub child: Arc<Mutex<Option<CommandChild>>>,
-    pub is_running: Arc<Mutex<bool>>,
-    pub buffer: Arc<Mutex<Vec<BatchMetric>>>,
-    pub target_pids: Arc<Mutex<Vec<u32>>>,
-    pub mode: Arc<Mutex<String>>,
-    pub interval_ms: Arc<Mutex<u64>>,
-    pub started_at: Arc<Mutex<Option<String>>>,
-    pub process_snapshot: Arc<Mutex<Vec<ProcessInfo>>>,
-    pub process_aliases: Arc<Mutex<Vec<ProcessAlias>>>,
-    pub folder_path: Arc<Mutex<Option<String>>>,
-    pub app_version: Arc<Mutex<String>>,
-    pub test_context: Arc<Mutex<Option<Value>>>,
-    pub stop_after_seconds: Arc<Mutex<Option<u64>>>,
-    // Store compiled regexes for log metrics: (Config, Regex)
-    pub log_metrics: Arc<Mutex<Vec<(LogMetricConfig, Regex)>>>,
+    // Active runs by session. Each PID belongs to at most one session, which is how sidecar and
+    // extension samples are routed.
+    pub sessions: Arc<RwLock<HashMap<SessionId, Box<ActiveRun>>>>,
+    // Interval reported while idle (the settings default); a run carries its own.
+    pub default_interval_ms: Arc<Mutex<u64>>,
+    // Answer of the sidecar to the "version" action, once it has replied.
+    pub sidecar_version: Arc<Mutex<Option<String>>>,
+    // Recent process scans, shared by the picker and target selectors.
+    pub process_scans: ProcessScanCache,
+    // Sequence number of the last start command sent to the sidecar, and its latest ack.
+    pub sidecar_seq: Arc<AtomicU64>,
+    pub sidecar_ack: Arc<Mutex<Option<SidecarAck>>>,
+    // Last "data" or "heartbeat" line from the sidecar, for stall detection.
+    pub sidecar_last_seen: Arc<Mutex<Option<DateTime<Utc>>>>,
+    // Sidecar stdout lines that were not protocol JSON.
+    pub sidecar_protocol_errors: Arc<Mutex<SidecarProtocolErrors>>,
+}
+
+// Undecodable sidecar lines kept for `get_collection_status`, and how much of each.
+const PROTOCOL_ERROR_SAMPLES: usize = 20;
+const PROTOCOL_ERROR_LINE_CHARS: usize = 200;
+// Least time between two "collector-protocol-error" events while errors keep coming.
+const PROTOCOL_ERROR_EMIT_MS: i64 = 5000;
+// Only undecodable output for this long is treated like a silent sidecar (see `check_sidecar_stall`).
+const PROTOCOL_GARBAGE_STALL_MS: i64 = 10_000;
+
+/// Sidecar stdout lines that were not a JSON object with a "type", since the app started.
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct SidecarProtocolErrors {
+    pub count: u64,
+    /// The last few lines, oldest first, each cut to PROTOCOL_ERROR_LINE_CHARS characters.
+    pub recent: VecDeque<String>,
+    pub first_at: Option<String>,
+    pub last_at: Option<String>,
+    // Start of the current stretch of undecodable lines; a valid line ends it.
+    #[serde(skip)]
+    garbage_since: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    last_emit: Option<DateTime<Utc>>,
+}
+
+/// Payload of the "collector-protocol-error" event, sent when undecodable lines start and then
+/// at most every PROTOCOL_ERROR_EMIT_MS while they continue.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CollectorProtocolError {
+    /// Undecodable lines since the app started.
+    pub count: u64,
+    /// The line that triggered the event, truncated.
+    pub line: String,
+    /// Start of the current stretch of undecodable lines (RFC 3339).
+    pub since: String,
+}
+
+impl SidecarProtocolErrors {
+    // Record an undecodable line. Returns the event to emit, if one is due.
+    fn record(&mut self, line: &str, now: DateTime<Utc>) -> Option<CollectorProtocolError> {
+        let mut cut: String = line.chars().take(PROTOCOL_ERROR_LINE_CHARS).collect();
+        if cut.len() < line.len() {
+            cut.push('…');
+        }
+        if self.count == 0 {
+            log_warn!("sidecar sent an undecodable line: {}", cut);
+            self.first_at = Some(now.to_rfc3339());
+        }
+        self.count += 1;
+        self.last_at = Some(now.to_rfc3339());
+        if self.recent.len() == PROTOCOL_ERROR_SAMPLES {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(cut.clone());
+        let since = *self.garbage_since.get_or_insert(now);
+        let due = since == now || self.last_emit.is_none_or(|t| (now - t).num_milliseconds() >= PROTOCOL_ERROR_EMIT_MS);
+        if !due {
+            return None;
+        }
+        self.last_emit = Some(now);
+        Some(CollectorProtocolError { count: self.count, line: cut, since: since.to_rfc3339() })
+    }
+
+    // A valid line ends the current stretch.
+    fn valid_line(&mut self) {
+        self.garbage_since = None;
+    }
+
+    // How long the sidecar has sent nothing but undecodable lines.
+    fn garbage_ms(&self, now: DateTime<Utc>) -> i64 {
+        self.garbage_since.map_or(0, |t| (now - t).num_milliseconds())
+    }
+}
+
+/// The sidecar's reply to a start command: the PIDs and interval it actually applied.
+#[derive(Debug, Clone)]
+pub struct SidecarAck {
+    pub seq: u64,
+    pub pids: Vec<u32>,
+    pub dropped_pids: Vec<u32>,
+    pub interval_ms: u64,
 }
 
 impl CollectionState {
     pub fn new() -> Self {
         Self {
             child: Arc::new(Mutex::new(None)),
-            is_running: Arc::new(Mutex::new(false)),
-            buffer: Arc::new(Mutex::new(Vec::new())),
-            target_pids: Arc::new(Mutex::new(Vec::new())),
-            mode: Arc::new(Mutex::new("system".to_string())),
-            interval_ms: Arc::new(Mutex::new(1000)),
-            started_at: Arc::new(Mutex::new(None)),
-            process_snapshot: Arc::new(Mutex::new(Vec::new())),
-            process_aliases: Arc::new(Mutex::new(Vec::new())),
-            folder_path: Arc::new(Mutex::new(None)),
-            app_version: Arc::new(Mutex::new("unknown".to_string())),
-            test_context: Arc::new(Mutex::new(None)),
-            stop_after_seconds: Arc::new(Mutex::new(None)),
-            log_metrics: Arc::new(Mutex::new(Vec::new())),
+            sessions: Arc::new(RwLock::new(HashMap::new())),
+            default_interval_ms: Arc::new(Mutex::new(1000)),
+            sidecar_version: Arc::new(Mutex::new(None)),
+            process_scans: ProcessScanCache::new(),
+            sidecar_seq: Arc::new(AtomicU64::new(0)),
+            sidecar_ack: Arc::new(Mutex::new(None)),
+            sidecar_last_seen: Arc::new(Mutex::new(None)),
+            sidecar_protocol_errors: Arc::new(Mutex::new(SidecarProtocolErrors::default())),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        !safe_read(&self.sessions).is_empty()
+    }
+
+    /// Active session ids, oldest run first.
+    pub fn session_ids(&self) -> Vec<SessionId> {
+        let sessions = safe_read(&self.sessions);
+        let mut runs: Vec<(&String, &SessionId)> = sessions.values().map(|r| (&r.started_at, &r.session_id)).collect();
+        runs.sort();
+        runs.into_iter().map(|(_, id)| id.clone()).collect()
+    }
+
+    pub fn has_session(&self, session_id: &str) -> bool {
+        safe_read(&self.sessions).contains_key(session_id)
+    }
+
+    /// The session a run-scoped command applies to: `session_id` when given, otherwise the only
+    /// active session (None when idle). Ambiguous or unknown ids are an error.
+    pub fn resolve_session(&self, session_id: Option<&str>) -> Result<Option<SessionId>, PerfSightError> {
+        if let Some(id) = session_id.map(|s| s.trim()).filter(|s| !s.is_empty()) {
+            if !self.has_session(id) {
+                return Err(PerfSightError::invalid_input("session_id", format!("no active session '{}'", id)));
+            }
+            return Ok(Some(id.to_string()));
+        }
+        let mut ids = self.session_ids();
+        match ids.len() {
+            0 | 1 => Ok(ids.pop()),
+            n => Err(PerfSightError::invalid_input(
+                "session_id",
+                format!("{} sessions are active; pass one of: {}", n, ids.join(", ")),
+            )),
+        }
+    }
+
+    /// Read from one session; None when it isn't active.
+    pub fn read_session<R>(&self, session_id: &str, f: impl FnOnce(&ActiveRun) -> R) -> Option<R> {
+        safe_read(&self.sessions).get(session_id).map(|run| f(run))
+    }
+
+    /// Modify one session; None (and `f` is not called) when it isn't active. Background loops
+    /// use it with their own session id, so they never feed another run.
+    pub fn write_session<R>(&self, session_id: &str, f: impl FnOnce(&mut ActiveRun) -> R) -> Option<R> {
+        safe_write(&self.sessions).get_mut(session_id).map(|run| f(run))
+    }
+
+    /// Read from every active session.
+    pub fn read_each<R>(&self, f: impl FnMut(&ActiveRun) -> R) -> Vec<R> {
+        safe_read(&self.sessions).values().map(|run| &**run).map(f).collect()
+    }
+
+    /// Modify every active session under one lock.
+    pub fn write_each<R>(&self, mut f: impl FnMut(&mut ActiveRun) -> R) -> Vec<R> {
+        safe_write(&self.sessions).values_mut().map(|run| f(run)).collect()
+    }
+
+    /// The session recording `pid`, if any.
+    pub fn owner_of(&self, pid: u32) -> Option<SessionId> {
+        safe_read(&self.sessions)
+            .values()
+            .find(|run| run.target_pids.contains(&pid))
+            .map(|run| run.session_id.clone())
+    }
+
+    // A PID of `pids` already recorded by another session, with that session.
+    fn pid_conflict(&self, pids: &[u32]) -> Option<(u32, SessionId)> {
+        pids.iter().find_map(|pid| self.owner_of(*pid).map(|owner| (*pid, owner)))
+    }
+
+    // Register a new session. Fails (handing the run back) when one of its PIDs is already recorded.
+    pub(crate) fn begin(&self, run: ActiveRun) -> Result<(), Box<ActiveRun>> {
+        let mut sessions = safe_write(&self.sessions);
+        if sessions.values().any(|other| other.target_pids.iter().any(|p| run.target_pids.contains(p))) {
+            return Err(Box::new(run));
         }
+        sessions.insert(run.session_id.clone(), Box::new(run));
+        Ok(())
+    }
+
+    // Remove a session, returning the finished run.
+    fn finish(&self, session_id: &str) -> Option<Box<ActiveRun>> {
+        safe_write(&self.sessions).remove(session_id)
     }
 }
 
-#[derive(serde::Serialize)]
-pub struct CollectionStatus {
-    pub is_running: bool,
-    pub target_pids: Vec<u32>,
-    pub mode: String,
-    pub interval_ms: u64,
-    pub started_at: Option<String>,
-    pub test_context: Option<Value>,
-    pub process_aliases: Vec<ProcessAlias>,
-    pub folder_path: Option<String>,
-    pub stop_after_seconds: Option<u64>,
+/// One change to the monitored PID set of a selector-driven run (recorded in report meta).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MembershipChange {
+    pub at: String,
+    pub added: Vec<u32>,
+    pub removed: Vec<u32>,
 }
 
-#[tauri::command]
-pub fn get_collection_status(state: State<'_, CollectionState>) -> Result<CollectionStatus, String> {
-    Ok(CollectionStatus {
-        is_running: *safe_lock(&state.is_running),
-        target_pids: safe_lock(&state.target_pids).clone(),
-        mode: safe_lock(&state.mode).clone(),
-        interval_ms: *safe_lock(&state.interval_ms),
-        started_at: safe_lock(&state.started_at).clone(),
-        test_context: safe_lock(&state.test_context).clone(),
-        process_aliases: safe_lock(&state.process_aliases).clone(),
-        folder_path: safe_lock(&state.folder_path).clone(),
-        stop_after_seconds: *safe_lock(&state.stop_after_seconds),
-    })
+/// Where a sample came from, for per-source health in `CollectionProgress`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataSource {
+    Sidecar,
+    /// Extension WebSocket.
+    Websocket,
+    /// HTTP ingest (`POST /ingest/metrics`) from external rigs; feeds whichever session records
+    /// the PID, in any mode.
+    Http,
+    /// Rust-side collector loop (macOS system mode, simulate).
+    Native,
 }
 
-// Struct for arguments
-#[derive(serde::Deserialize)]
-pub struct ProcessListArgs {
-    mode: String,
+#[derive(Debug, Clone, Default)]
+pub struct IngestCounters {
+    pub last_sidecar: Option<DateTime<Utc>>,
+    pub last_websocket: Option<DateTime<Utc>>,
+    pub last_http: Option<DateTime<Utc>>,
+    pub last_native: Option<DateTime<Utc>>,
+    /// Per-PID points buffered from each source, saved as `collection.data_sources`.
+    pub sidecar_samples: u64,
+    pub websocket_samples: u64,
+    pub http_samples: u64,
+    pub native_samples: u64,
+    /// Points discarded because they were for an unmonitored PID or the wrong mode.
+    pub dropped_samples: u64,
+    /// Memory values replaced by the previous sample by the spike guard.
+    pub clamped_samples: u64,
+    /// Extension points ignored because the run was not in browser mode (also in `dropped_samples`).
+    pub websocket_ignored: u64,
+    /// Sidecar stdout lines that could not be decoded while the run was going.
+    pub sidecar_protocol_errors: u64,
+    /// `seq` accounting per stream: a sidecar process, an extension connection, HTTP ingest, the
+    /// native loop.
+    pub sequences: HashMap<String, (DataSource, SeqTracker)>,
 }
 
-// Helper to handle mutex poisoning gracefully
-pub fn safe_lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
-    match mutex.lock() {
-        Ok(guard) => guard,
-        Err(poisoned) => {
-            eprintln!("WARNING: Mutex was poisoned! Recovering...");
-            poisoned.into_inner()
-        }
-    }
+/// Follows the `seq` numbers of one stream. A number at or below the last one means the sender
+/// restarted, which starts a new span instead of counting as loss.
+#[derive(Debug, Clone, Default)]
+pub struct SeqTracker {
+    expected: u64,
+    received: u64,
+    last: Option<u64>,
 }
 
-// Websocket ingest (Chrome extension): only valid in Browser API mode.
-// This avoids mixing Chrome Task Manager memory (private/footprint) into System API runs.
-pub fn process_websocket_metric_payload(app: &AppHandle, data: Value, state: &CollectionState) {
-    if safe_lock(&state.mode).as_str() != "browser" {
-        return;
+impl SeqTracker {
+    fn observe(&mut self, seq: u64) {
+        self.expected += match self.last {
+            Some(last) if seq > last => seq - last,
+            _ => 1,
+        };
+        self.received += 1;
+        self.last = Some(seq);
     }
-    process_metric_payload(app, data, state);
 }
 
-fn decode_base64_maybe_data_url(s: &str) -> Result<Vec<u8>, String> {
-    // Accept:
-    // - raw base64 "JVBERi0xLjc..."
-    // - "data:application/pdf;base64,JVBERi0xLjc..."
-    // - jsPDF output("datauristring") which often looks like:
-    //   "data:application/pdf;filename=generated.pdf;base64,JVBERi0xLjc..."
-    let trimmed = s.trim();
-    let b64 = if let Some(idx) = trimmed.find("base64,") {
-        &trimmed[idx + "base64,".len()..]
-    } else {
-        trimmed
-    };
+impl IngestCounters {
+    fn observe_seq(&mut self, source: DataSource, stream: &str, seq: u64) {
+        self.sequences
+            .entry(stream.to_string())
+            .or_insert_with(|| (source, SeqTracker::default()))
+            .1
+            .observe(seq);
+    }
 
-    // Some encoders may insert newlines; remove whitespace.
-    let cleaned: String = b64.chars().filter(|c| !c.is_whitespace()).collect();
+    fn health(&self) -> SourceHealth {
+        SourceHealth {
+            sidecar: self.last_sidecar.map(|t| t.to_rfc3339()),
+            websocket: self.last_websocket.map(|t| t.to_rfc3339()),
+            http: self.last_http.map(|t| t.to_rfc3339()),
+            native: self.last_native.map(|t| t.to_rfc3339()),
+        }
+    }
 
-    base64::engine::general_purpose::STANDARD
-        .decode(cleaned)
-        .map_err(|e| format!("base64 decode failed: {e}"))
-}
+    fn sequences(&self) -> SourceSequences {
+        SourceSequences {
+            sidecar: self.sequence_stats(DataSource::Sidecar),
+            websocket: self.sequence_stats(DataSource::Websocket),
+            http: self.sequence_stats(DataSource::Http),
+            native: self.sequence_stats(DataSource::Native),
+        }
+    }
 
-#[tauri::command]
-pub async fn export_report_pdf(
-    app_handle: AppHandle,
-    report_id: i64,
-    filename: Option<String>,
-    pdf_base64: String
-) -> Result<String, String> {
-    let bytes = decode_base64_maybe_data_url(&pdf_base64)?;
-
-    let mut dir = app_handle
-        .path()
-        .resolve("", BaseDirectory::Download)
-        .ok();
-    if dir.is_none() {
-        dir = app_handle.path().app_local_data_dir().ok();
+    /// Totals over the streams of `source`; None when none of them carried `seq`.
+    pub fn sequence_stats(&self, source: DataSource) -> Option<SequenceStats> {
+        let mut trackers = self.sequences.values().filter(|(s, _)| *s == source).map(|(_, t)| t).peekable();
+        trackers.peek()?;
+        let (expected, received) = trackers.fold((0, 0), |(e, r), t| (e + t.expected, r + t.received));
+        Some(SequenceStats { expected, received, lost: expected.saturating_sub(received) })
     }
-    let dir = dir.ok_or("Failed to resolve output directory")?;
-    if !dir.exists() {
-        std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    fn touch(&mut self, source: DataSource, points: usize) {
+        let now = Some(Utc::now());
+        let points = points as u64;
+        match source {
+            DataSource::Sidecar => {
+                self.last_sidecar = now;
+                self.sidecar_samples += points;
+            }
+            DataSource::Websocket => {
+                self.last_websocket = now;
+                self.websocket_samples += points;
+            }
+            DataSource::Http => {
+                self.last_http = now;
+                self.http_samples += points;
+            }
+            DataSource::Native => {
+                self.last_native = now;
+                self.native_samples += points;
+            }
+        }
     }
+}
+
+/// Add an event to the timeline of the session recording `pid`, or of every session when `pid`
+/// is None; a no-op when nothing is running.
+pub fn append_run_event(state: &CollectionState, kind: RunEventKind, pid: Option<u32>, detail: Value) {
+    state.write_each(|run| {
+        if pid.is_none_or(|p| run.target_pids.contains(&p)) {
+            run.events.push(RunEvent::now(kind, pid, detail.clone()));
+        }
+    });
+}
+
+/// Add an event to one session's timeline.
+pub fn append_session_event(state: &CollectionState, session_id: &str, kind: RunEventKind, detail: Value) {
+    state.write_session(session_id, |run| run.events.push(RunEvent::now(kind, None, detail)));
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SourceHealth {
+    pub sidecar: Option<String>,
+    pub websocket: Option<String>,
+    pub http: Option<String>,
+    pub native: Option<String>,
+}
+
+/// Live `seq` accounting per source (see `SequenceStats`).
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct SourceSequences {
+    pub sidecar: Option<SequenceStats>,
+    pub websocket: Option<SequenceStats>,
+    pub http: Option<SequenceStats>,
+    pub native: Option<SequenceStats>,
+}
+
+/// Payload of the periodic "collection-progress" event (also part of `get_collection_status`).
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CollectionProgress {
+    pub session_id: Option<SessionId>,
+    pub is_running: bool,
+    pub elapsed_seconds: u64,
+    /// Buffered batches (one per timestamp).
+    pub sample_count: usize,
+    /// Buffered per-PID points across all batches.
+    pub point_count: usize,
+    /// Last time each source delivered data during this run (RFC 3339).
+    pub sources: SourceHealth,
+    pub sequences: SourceSequences,
+    pub dropped_samples: u64,
+    pub clamped_samples: u64,
+    /// Set when the run auto-stops (`stop_after_seconds`).
+    pub remaining_seconds: Option<u64>,
+    /// Last sidecar line before the current stall (RFC 3339), while it is stalled.
+    pub stalled_since: Option<String>,
+}
 
-    let name = filename
-        .and_then(|s| {
-            let t = s.trim().to_string();
-            if t.is_empty() { None } else { Some(t) }
+pub fn collection_progress(state: &CollectionState, session_id: Option<&str>) -> CollectionProgress {
+    session_id
+        .and_then(|id| state.read_session(id, |run| {
+            let elapsed_seconds = DateTime::parse_from_rfc3339(&run.started_at)
+                .ok()
+                .map(|t| (Utc::now() - t.with_timezone(&Utc)).num_seconds().max(0) as u64)
+                .unwrap_or(0);
+            let ingest = &run.ingest;
+            CollectionProgress {
+                session_id: Some(run.session_id.clone()),
+                is_running: true,
+                elapsed_seconds,
+                sample_count: run.buffer.len(),
+                point_count: run.buffer.iter().map(|b| b.metrics.len()).sum(),
+                sources: ingest.health(),
+                sequences: ingest.sequences(),
+                dropped_samples: ingest.dropped_samples,
+                clamped_samples: ingest.clamped_samples,
+                remaining_seconds: run.stop_after_seconds.map(|s| s.saturating_sub(elapsed_seconds)),
+                stalled_since: run.stalled_since.map(|t| t.to_rfc3339()),
+            }
+        }))
+        .unwrap_or(CollectionProgress {
+            session_id: None,
+            is_running: false,
+            elapsed_seconds: 0,
+            sample_count: 0,
+            point_count: 0,
+            sources: SourceHealth { sidecar: None, websocket: None, http: None, native: None },
+            sequences: SourceSequences::default(),
+            dropped_samples: 0,
+            clamped_samples: 0,
+            remaining_seconds: None,
+            stalled_since: None,
         })
-        .unwrap_or_else(|| format!("PerfSight_Report_{}.pdf", report_id));
-    let path = dir.join(name);
+}
+
+// Most a `get_live_buffer_tail` call returns, however long the window.
+const MAX_TAIL_SECONDS: u64 = 600;
+const MAX_TAIL_BATCHES: usize = 10_000;
 
-    std::fs::write(&path, bytes).map_err(|e| e.to_string())?;
-    Ok(path.to_string_lossy().to_string())
+/// Recent samples of a running session, for a window opened mid-run.
+#[derive(Debug, Clone, Serialize)]
+pub struct LiveBufferTail {
+    pub session_id: SessionId,
+    /// Window that was applied, after capping to `MAX_TAIL_SECONDS`.
+    pub seconds: u64,
+    /// Batches of the last `seconds` before the newest one, oldest first.
+    pub batches: Vec<BatchMetric>,
+    /// The window held more than `MAX_TAIL_BATCHES`; only the newest are returned.
+    pub truncated: bool,
+    /// Every marker of the run so far, including those before the window.
+    pub markers: Vec<RunEvent>,
+    pub sources: SourceHealth,
+    pub sequences: SourceSequences,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ReportDatasetV1 {
-    pub schema_version: u32,
-    pub exported_at: String,
-    pub report: ReportDetail,
+/// The last `seconds` of a session's samples (`session_id` may be omitted with one run) with its markers
+/// and source health. Only the window is copied, under a short read lock. The buffer keeps every
+/// batch of the run even after it is written to `run_chunks`, so the window never has to be
+/// read back from the database.
+#[tauri::command]
+pub fn get_live_buffer_tail(
+    state: State<'_, CollectionState>,
+    seconds: u64,
+    session_id: Option<String>,
+) -> Result<LiveBufferTail, PerfSightError> {
+    let session_id = state
+        .resolve_session(session_id.as_deref())?
+        .ok_or_else(|| PerfSightError::invalid_input("session_id", "no collection is running"))?;
+    let seconds = seconds.min(MAX_TAIL_SECONDS);
+    state
+        .read_session(&session_id, |run| {
+            let start = run.buffer.last().map_or(0, |newest| {
+                let from = newest.timestamp - chrono::Duration::seconds(seconds as i64);
+                run.buffer.partition_point(|b| b.timestamp < from)
+            });
+            let truncated = run.buffer.len() - start > MAX_TAIL_BATCHES;
+            let start = start.max(run.buffer.len().saturating_sub(MAX_TAIL_BATCHES));
+            LiveBufferTail {
+                session_id: run.session_id.clone(),
+                seconds,
+                batches: run.buffer[start..].to_vec(),
+                truncated,
+                markers: run.events.iter().filter(|e| e.kind == RunEventKind::Marker).cloned().collect(),
+                sources: run.ingest.health(),
+                sequences: run.ingest.sequences(),
+            }
+        })
+        .ok_or_else(|| PerfSightError::invalid_input("session_id", format!("session {} is not running", session_id)))
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ComparisonBundleV1 {
-    pub schema_version: u32,
-    pub bundle_type: String, // "comparison"
-    pub exported_at: String,
-    pub comparison_context: Value,
-    pub reports: Vec<ReportDetail>,
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+// A progress tick this much later than scheduled is recorded as a `machine_slept` event.
+const SLEEP_GAP_THRESHOLD: Duration = Duration::from_secs(5);
+
+// Emit "collection-progress" for one session until it stops.
+fn spawn_progress_ticker<R: tauri::Runtime>(app_handle: AppHandle<R>, state: CollectionState, session_id: SessionId) {
+    tauri::async_runtime::spawn(async move {
+        let mut last_tick = Utc::now();
+        loop {
+            tokio::time::sleep(PROGRESS_INTERVAL).await;
+            if !state.has_session(&session_id) {
+                break;
+            }
+            // The ticker can't run while the machine sleeps, so a late tick marks a gap in the data.
+            let now = Utc::now();
+            let late_ms = (now - last_tick).num_milliseconds() - PROGRESS_INTERVAL.as_millis() as i64;
+            if late_ms >= SLEEP_GAP_THRESHOLD.as_millis() as i64 {
+                state.write_session(&session_id, |run| {
+                    run.events.push(RunEvent {
+                        timestamp: now,
+                        kind: RunEventKind::MachineSlept,
+                        pid: None,
+                        detail: json!({ "gap_ms": late_ms, "resumed_at": now.to_rfc3339() }),
+                    })
+                });
+            }
+            last_tick = now;
+            check_sidecar_stall(&app_handle, &state, &session_id, now);
+            check_process_exits(&state, &session_id, now);
+            check_extension_attached(&app_handle, &state, &session_id, now);
+            let _ = app_handle.emit("collection-progress", &collection_progress(&state, Some(&session_id)));
+        }
+    });
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ExportBundleItemV1 {
-    pub report_id: i64,
-    /// Optional base64 PDF (raw base64 or data URL).
-    pub pdf_base64: Option<String>,
+/// A browser-mode run's need for the extension: Chrome's own process info has no private memory
+/// for `pids`, so only the extension's figures cover them.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtensionDependency {
+    pub pids: Vec<u32>,
+    /// Set when the run started without an active extension.
+    pub missing_since: Option<DateTime<Utc>>,
+    /// When extension data first arrived after that.
+    pub attached_at: Option<DateTime<Utc>>,
 }
 
-fn safe_slug(s: &str, max_len: usize) -> String {
-    let mut out = String::new();
-    for ch in s.chars() {
-        let c = match ch {
-            'a'..='z' | 'A'..='Z' | '0'..='9' => ch,
-            ' ' | '-' | '_' => '_',
-            _ => '_',
-        };
-        out.push(c);
-        if out.len() >= max_len {
-            break;
+// Real target PIDs (tabs without one are left out) whose memory Chrome's process info lacks;
+// all of them when the debugging port can't be reached. macOS falls back to phys_footprint.
+fn pids_needing_extension(pids: &[u32]) -> Vec<u32> {
+    if cfg!(target_os = "macos") {
+        return Vec::new();
+    }
+    let info = CdpClient::get_browser_process_info().map_err(|e| log_warn!("CDP process info unavailable: {}", e)).ok();
+    pids.iter()
+        .copied()
+        .filter(|pid| *pid < VIRTUAL_PID_BASE)
+        .filter(|pid| info.as_ref().and_then(|m| m.get(pid)).and_then(|i| i.private_mem_bytes).is_none())
+        .collect()
+}
+
+// Note when the extension a browser run was waiting for starts delivering, and tell the UI.
+fn check_extension_attached<R: tauri::Runtime>(app_handle: &AppHandle<R>, state: &CollectionState, session_id: &str, now: DateTime<Utc>) {
+    let attached = state.write_session(session_id, |run| {
+        let dep = run.extension_dependency.as_mut()?;
+        let since = dep.missing_since?;
+        if dep.attached_at.is_some() || run.ws_clients.is_empty() {
+            return None;
         }
+        dep.attached_at = Some(now);
+        Some((now - since).num_milliseconds())
+    });
+    if let Some(Some(waited_ms)) = attached {
+        log_info!("Extension attached to run {} after {} ms", session_id, waited_ms);
+        let _ = app_handle.emit(
+            "browser-data-source-attached",
+            &json!({ "session_id": session_id, "attached_at": now.to_rfc3339(), "waited_ms": waited_ms }),
+        );
     }
-    let trimmed = out.trim_matches('_').to_string();
-    if trimmed.is_empty() { "report".to_string() } else { trimmed }
 }
 
-fn compact_time_id(s: &str) -> String {
-    // Keep only digits + 'T' + 'Z' for a stable, filesystem-friendly identifier.
-    // Example: "2025-12-19T13:45:02.123Z" -> "20251219T134502123Z"
-    let mut out = String::new();
-    for ch in s.chars() {
-        if ch.is_ascii_digit() || ch == 'T' || ch == 'Z' {
-            out.push(ch);
+// Note target PIDs of a system or browser run that went away since the last tick. Each exit is
+// recorded once; its reason is resolved off the ticker (crash records can take a moment to
+// appear) and then added to the timeline and `meta.collection.process_lifecycle`.
+fn check_process_exits(state: &CollectionState, session_id: &str, now: DateTime<Utc>) {
+    let Some(pids) = state.read_session(session_id, |run| {
+        if !matches!(run.mode.as_str(), "system" | "browser") {
+            return Vec::new();
+        }
+        // Virtual PIDs (tabs without a real one) start at VIRTUAL_PID_BASE; our own exits are collector errors.
+        run.target_pids
+            .iter()
+            .copied()
+            .filter(|pid| *pid < VIRTUAL_PID_BASE && !run.exits.contains_key(pid) && !run.self_pids.contains(pid))
+            .collect::<Vec<u32>>()
+    }) else {
+        return;
+    };
+    if pids.is_empty() {
+        return;
+    }
+    let mut sys = sysinfo::System::new();
+    let gone: Vec<u32> = pids.into_iter().filter(|pid| !lifecycle::pid_alive(&mut sys, *pid)).collect();
+    if gone.is_empty() {
+        return;
+    }
+    state.write_session(session_id, |run| {
+        for pid in &gone {
+            run.exits.insert(*pid, (now, None));
+        }
+    });
+    let state = state.clone();
+    let session_id = session_id.to_string();
+    tauri::async_runtime::spawn_blocking(move || {
+        for pid in gone {
+            let termination = lifecycle::resolve_termination(pid, now);
+            state.write_session(&session_id, |run| run.record_exit(pid, now, termination));
+        }
+    });
+}
+
+// A sidecar-fed session counts as stalled after this many of its intervals without a sidecar line.
+const STALL_INTERVALS: i64 = 3;
+// Floor for short intervals, so scheduling jitter of the sidecar isn't reported as a stall.
+const STALL_MIN_MS: i64 = 2000;
+
+/// Payload of the "data-source-stalled" and "data-source-recovered" events.
+#[derive(Debug, Clone, Serialize)]
+pub struct DataSourceStall {
+    pub session_id: SessionId,
+    pub source: String,
+    /// Last line from the source before the stall (RFC 3339).
+    pub last_seen_at: String,
+    /// Silence so far (stalled) or in total (recovered).
+    pub gap_ms: i64,
+}
+
+// Count an undecodable sidecar line, globally and in every run the sidecar feeds, and emit
+// "collector-protocol-error" when one is due.
+fn record_protocol_error<R: tauri::Runtime>(app_handle: &AppHandle<R>, state: &CollectionState, line: &str) {
+    let event = safe_lock(&state.sidecar_protocol_errors).record(line.trim_end(), Utc::now());
+    state.write_each(|run| {
+        if mode_uses_sidecar(&run.mode) {
+            run.ingest.sidecar_protocol_errors += 1;
+        }
+    });
+    if let Some(event) = event {
+        let _ = app_handle.emit("collector-protocol-error", &event);
+    }
+}
+
+// One stdout line of the sidecar, received at `at`: samples, heartbeats, acks and the version
+// reply. Data and heartbeat lines count as signs of life for `check_sidecar_stall`.
+fn handle_sidecar_line<R: tauri::Runtime>(app_handle: &AppHandle<R>, state: &CollectionState, line: &str, at: DateTime<Utc>) {
+    if line.trim().is_empty() {
+        return;
+    }
+    let data = serde_json::from_str::<Value>(line).ok().filter(|d| d["type"].is_string());
+    let Some(data) = data else {
+        record_protocol_error(app_handle, state, line);
+        return;
+    };
+    safe_lock(&state.sidecar_protocol_errors).valid_line();
+    if data["type"] == "version" {
+        *safe_lock(&state.sidecar_version) = data["version"].as_str().map(str::to_string);
+        return;
+    }
+    if data["type"] == "ack" {
+        if let Some(ack) = parse_sidecar_ack(&data) {
+            apply_sidecar_ack(app_handle, state, ack);
+        }
+        return;
+    }
+    if data["type"] == "data" || data["type"] == "heartbeat" {
+        *safe_lock(&state.sidecar_last_seen) = Some(at);
+    }
+    if data["type"] == "heartbeat" {
+        return;
+    }
+    process_metric_payload(app_handle, data, state, DataSource::Sidecar, "sidecar");
+}
+
+// Mark a sidecar-fed session stalled once the sidecar has been silent (no data, no heartbeat) for
+// STALL_INTERVALS of its interval, or has sent only undecodable lines for PROTOCOL_GARBAGE_STALL_MS,
+// and recovered when it speaks again. Both go into the timeline, so the analysis can leave the
+// gap out (see `analysis::analyze_with_events`).
+fn check_sidecar_stall<R: tauri::Runtime>(app_handle: &AppHandle<R>, state: &CollectionState, session_id: &str, now: DateTime<Utc>) {
+    let last_seen = *safe_lock(&state.sidecar_last_seen);
+    let garbage_ms = safe_lock(&state.sidecar_protocol_errors).garbage_ms(now);
+    let change = state
+        .write_session(session_id, |run| {
+            if !mode_uses_sidecar(&run.mode) {
+                return None;
+            }
+            let started = DateTime::parse_from_rfc3339(&run.started_at).map_or(now, |t| t.with_timezone(&Utc));
+            let seen = last_seen.map_or(started, |t| t.max(started));
+            match run.stalled_since {
+                None => {
+                    let threshold_ms = (run.sampling_interval_ms() as i64 * STALL_INTERVALS).max(STALL_MIN_MS);
+                    let gap_ms = (now - seen).num_milliseconds();
+                    if gap_ms < threshold_ms && garbage_ms < PROTOCOL_GARBAGE_STALL_MS {
+                        return None;
+                    }
+                    run.stalled_since = Some(seen);
+                    run.events.push(RunEvent::now(
+                        RunEventKind::SourceStalled,
+                        None,
+                        json!({ "source": "sidecar", "last_seen_at": seen.to_rfc3339(), "threshold_ms": threshold_ms }),
+                    ));
+                    Some(("data-source-stalled", seen, gap_ms))
+                }
+                Some(since) if seen > since => {
+                    run.stalled_since = None;
+                    let gap_ms = (seen - since).num_milliseconds();
+                    run.events.push(RunEvent::now(
+                        RunEventKind::SourceRecovered,
+                        None,
+                        json!({ "source": "sidecar", "resumed_at": seen.to_rfc3339(), "gap_ms": gap_ms }),
+                    ));
+                    Some(("data-source-recovered", since, gap_ms))
+                }
+                Some(_) => None,
+            }
+        })
+        .flatten();
+    if let Some((event, since, gap_ms)) = change {
+        let payload = DataSourceStall {
+            session_id: session_id.to_string(),
+            source: "sidecar".to_string(),
+            last_seen_at: since.to_rfc3339(),
+            gap_ms,
+        };
+        let _ = app_handle.emit(event, &payload);
+    }
+}
+
+// How often a running session's finished samples are written to `run_chunks`.
+const CHUNK_WRITE_INTERVAL: Duration = Duration::from_secs(5);
+
+// Write each session's samples to the database while it runs, so stopping only has to join the
+// chunks and a crash loses at most one interval.
+fn spawn_chunk_writer<R: tauri::Runtime>(app_handle: AppHandle<R>, state: CollectionState, session_id: SessionId) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(CHUNK_WRITE_INTERVAL).await;
+            let (app_handle, state, id) = (app_handle.clone(), state.clone(), session_id.clone());
+            let alive = tauri::async_runtime::spawn_blocking(move || {
+                let db: State<Database> = app_handle.state();
+                write_pending_chunk(&state, db.inner(), &id)
+            })
+            .await
+            .unwrap_or(false);
+            if !alive {
+                break;
+            }
+        }
+    });
+}
+
+// Write the batches buffered since the last chunk. The newest batch stays behind: samples with
+// the same timestamp are still merged into it. Returns false once the session has stopped.
+fn write_pending_chunk(state: &CollectionState, db: &Database, session_id: &str) -> bool {
+    let Some(chunk_lock) = state.read_session(session_id, |run| run.chunk_lock.clone()) else {
+        return false;
+    };
+    let _guard = safe_lock(&chunk_lock);
+    let Some((seq, end, batches, started_at, mode, title)) = state.read_session(session_id, |run| {
+        let end = run.buffer.len().saturating_sub(1);
+        let start = run.persisted_batches.min(end);
+        let title = run
+            .test_context
+            .as_ref()
+            .and_then(|v| v.get("scenario_name"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .to_string();
+        (run.chunk_seq, end, run.buffer[start..end].to_vec(), run.started_at.clone(), run.mode.clone(), title)
+    }) else {
+        return false;
+    };
+    if batches.is_empty() {
+        return true;
+    }
+    let written = serde_json::to_string(&batches)
+        .map_err(|e| e.to_string())
+        .and_then(|json| db.append_run_chunk(session_id, seq, &started_at, &mode, &title, &json).map_err(|e| e.to_string()));
+    match written {
+        Ok(_) => state
+            .write_session(session_id, |run| {
+                run.persisted_batches = end;
+                run.chunk_seq = seq + 1;
+            })
+            .is_some(),
+        // Not fatal: the samples are still buffered and go out with the next chunk or at stop.
+        Err(e) => {
+            log_error!("Failed to write samples of session {}: {}", session_id, e);
+            true
+        }
+    }
+}
+
+// metrics_json of a stopping run: the chunks written during the run plus the unwritten tail,
+// joined without re-serializing the chunks. Same text `serde_json::to_string(buffer)` produces.
+fn assemble_metrics_json(db: &Database, run: &ActiveRun, buffer: &[BatchMetric]) -> Result<String, String> {
+    let _guard = safe_lock(&run.chunk_lock);
+    let mut parts = db.run_chunks(&run.session_id, run.chunk_seq).map_err(|e| e.to_string())?;
+    let tail = &buffer[run.persisted_batches.min(buffer.len())..];
+    if !tail.is_empty() {
+        parts.push(serde_json::to_string(tail).map_err(|e| e.to_string())?);
+    }
+    let mut json = String::with_capacity(parts.iter().map(|p| p.len()).sum::<usize>() + 2);
+    json.push('[');
+    for part in parts.iter().filter(|p| p.len() > 2) {
+        if json.len() > 1 {
+            json.push(',');
+        }
+        json.push_str(&part[1..part.len() - 1]);
+    }
+    json.push(']');
+    Ok(json)
+}
+
+/// Startup hook: save runs whose samples were written to `run_chunks` but never made it into a
+/// report (the app quit or crashed mid-run, or the final save failed). No collection can be
+/// running yet.
+pub fn recover_unsaved_runs(db: &Database) {
+    let runs = match db.unsaved_runs() {
+        Ok(runs) => runs,
+        Err(e) => {
+            log_error!("Failed to look for unsaved runs: {}", e);
+            return;
+        }
+    };
+    for (session_id, started_at, mode, title) in runs {
+        let chunks = match db.run_chunks(&session_id, i64::MAX) {
+            Ok(chunks) => chunks,
+            Err(e) => {
+                log_error!("Failed to read unsaved run {}: {}", session_id, e);
+                continue;
+            }
+        };
+        let mut buffer: Vec<BatchMetric> = Vec::new();
+        for chunk in &chunks {
+            match serde_json::from_str::<Vec<BatchMetric>>(chunk) {
+                Ok(batches) => buffer.extend(batches),
+                Err(e) => log_warn!("Skipping unreadable chunk of run {}: {}", session_id, e),
+            }
+        }
+        if !buffer.is_empty() {
+            let ended_at = buffer.last().map(|b| b.timestamp.to_rfc3339());
+            let duration_seconds = match (buffer.first(), buffer.last()) {
+                (Some(first), Some(last)) => Some((last.timestamp - first.timestamp).num_seconds().max(0) as u64),
+                _ => None,
+            };
+            let mut meta = ReportMeta {
+                schema_version: Some(1),
+                collection: Some(CollectionMeta {
+                    mode: Some(mode),
+                    started_at: Some(started_at.clone()),
+                    ended_at,
+                    duration_seconds,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            meta.extra.insert(
+                "recovered".to_string(),
+                json!({ "session_id": session_id, "recovered_at": Utc::now().to_rfc3339() }),
+            );
+            let title = if title.trim().is_empty() {
+                format!("{}{} (recovered)", AUTO_TITLE_PREFIX, started_at)
+            } else {
+                format!("{} (recovered)", title.trim())
+            };
+            match db.save_report(&title, &buffer, &meta) {
+                Ok(id) => log_info!("Recovered unsaved run {} as report {}", session_id, id),
+                Err(e) => {
+                    log_error!("Failed to recover run {}: {}", session_id, e);
+                    continue;
+                }
+            }
+        }
+        if let Err(e) = db.delete_run_chunks(&session_id) {
+            log_warn!("Failed to clear chunks of run {}: {}", session_id, e);
+        }
+    }
+}
+
+/// Settings key of the `SampleDataRecord`.
+pub const SETTING_SAMPLE_DATA: &str = "sample_data";
+
+/// Result of the first-launch sample data check, stored so it runs once per database.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SampleDataRecord {
+    pub checked_at: String,
+    /// Empty when the database already held reports.
+    pub report_ids: Vec<i64>,
+    pub comparison_id: Option<i64>,
+}
+
+/// On the first launch of an empty database, save the sample reports (`sample_data`) and a
+/// comparison of them. They go through `save_report` and `create_comparison_checked` like real
+/// ones, so a failure here also points at the storage layer. A database that already holds
+/// reports is only marked as checked.
+pub fn seed_sample_data(db: &Database) {
+    if matches!(db.get_setting_as::<SampleDataRecord>(SETTING_SAMPLE_DATA), Ok(Some(_))) {
+        return;
+    }
+    let mut record = SampleDataRecord { checked_at: Utc::now().to_rfc3339(), report_ids: Vec::new(), comparison_id: None };
+    match db.report_count() {
+        Ok(0) => {}
+        Ok(_) => {
+            let _ = db.set_setting_as(SETTING_SAMPLE_DATA, &record);
+            return;
+        }
+        Err(e) => {
+            log_error!("Failed to count reports for sample data: {}", e);
+            return;
+        }
+    }
+    if let Err(e) = db.ensure_folder_path(sample_data::SAMPLE_FOLDER) {
+        log_error!("Failed to create folder {}: {}", sample_data::SAMPLE_FOLDER, e);
+        return;
+    }
+    for report in sample_data::reports(Utc::now()) {
+        match db.save_report(&report.title, &report.metrics, &report.meta) {
+            Ok(id) => record.report_ids.push(id),
+            Err(e) => {
+                log_error!("Failed to save sample report '{}': {}", report.title, e);
+                return;
+            }
+        }
+    }
+    let selection = sample_data::comparison_selection(&record.report_ids);
+    let args = CreateComparisonArgs {
+        title: Some("Sample: baseline vs candidate".to_string()),
+        report_ids: record.report_ids.clone(),
+        folder_path: Some(sample_data::SAMPLE_FOLDER.to_string()),
+        baseline_report_id: record.report_ids.first().copied(),
+        cpu_selections_by_id: Some(selection.clone()),
+        mem_selections_by_id: Some(selection),
+        metric_selections: None,
+        meta: Some(json!({ "sample": true, "tags": [sample_data::SAMPLE_TAG] })),
+        strict: true,
+    };
+    match create_comparison_checked_with(db, args) {
+        Ok(created) => record.comparison_id = Some(created.id),
+        Err(e) => {
+            log_error!("Failed to create the sample comparison: {}", e);
+            return;
+        }
+    }
+    log_info!("Saved sample data: reports {:?}, comparison {:?}", record.report_ids, record.comparison_id);
+    let _ = db.set_setting_as(SETTING_SAMPLE_DATA, &record);
+}
+
+/// What `remove_sample_data` deleted.
+#[derive(Debug, Clone, Serialize)]
+pub struct SampleDataRemoval {
+    pub reports: usize,
+    pub comparisons: usize,
+}
+
+/// Delete every report and comparison flagged `sample` in meta, and the sample folder once it is
+/// empty. Locked sample reports fail the call with `locked` unless `force`.
+#[tauri::command]
+pub fn remove_sample_data(db: State<'_, Database>, force: Option<bool>) -> Result<SampleDataRemoval, PerfSightError> {
+    let (report_ids, comparison_ids) = db.sample_data_ids()?;
+    ensure_unlocked(&db, &report_ids, force)?;
+    let comparisons = db.delete_comparisons(&comparison_ids)?;
+    let reports = db.delete_reports(&report_ids)?;
+    let folder = db.get_folder_stats(sample_data::SAMPLE_FOLDER)?;
+    if folder.report_count == 0 && folder.child_folder_count == 0 {
+        db.delete_folder(sample_data::SAMPLE_FOLDER, None)?;
+    }
+    Ok(SampleDataRemoval { reports, comparisons })
+}
+
+#[derive(serde::Serialize)]
+pub struct CollectionStatus {
+    pub session_id: Option<SessionId>,
+    /// Every active session, oldest first.
+    pub sessions: Vec<SessionId>,
+    pub is_running: bool,
+    pub target_pids: Vec<u32>,
+    pub mode: String,
+    pub interval_ms: u64,
+    pub started_at: Option<String>,
+    pub test_context: Option<Value>,
+    pub process_aliases: Vec<ProcessAlias>,
+    pub folder_path: Option<String>,
+    pub stop_after_seconds: Option<u64>,
+    pub metric_sink: Option<MetricSinkStats>,
+    pub stream_file: Option<StreamFileStats>,
+    /// Log metric captures that were not numbers, by metric name.
+    pub log_metric_parse_failures: HashMap<String, LogParseFailures>,
+    pub progress: CollectionProgress,
+    /// Extension connections, running or not, so a browser-mode start can be held back until
+    /// one is active.
+    pub browser_extension: ExtensionStatus,
+    /// The session's need for the extension, when it is a browser run that has one.
+    pub extension_dependency: Option<ExtensionDependency>,
+    /// Undecodable sidecar lines since the app started (this session's count is in
+    /// `sidecar_protocol_errors_in_run`).
+    pub sidecar_protocol_errors: SidecarProtocolErrors,
+    pub sidecar_protocol_errors_in_run: u64,
+}
+
+/// Status of `session_id`; without one, of the only session (the newest when several are active).
+#[tauri::command]
+pub fn get_collection_status(
+    state: State<'_, CollectionState>,
+    server: State<'_, IngestServerState>,
+    session_id: Option<String>,
+) -> Result<CollectionStatus, PerfSightError> {
+    let browser_extension = server.extension_status();
+    let sidecar_protocol_errors = safe_lock(&state.sidecar_protocol_errors).clone();
+    let sessions = state.session_ids();
+    let session_id = match session_id {
+        Some(id) => state.resolve_session(Some(&id))?,
+        None => sessions.last().cloned(),
+    };
+    let progress = collection_progress(state.inner(), session_id.as_deref());
+    let status = session_id.as_deref().and_then(|id| state.read_session(id, |run| CollectionStatus {
+        session_id: Some(run.session_id.clone()),
+        sessions: sessions.clone(),
+        is_running: true,
+        target_pids: run.target_pids.clone(),
+        mode: run.mode.clone(),
+        interval_ms: run.interval_ms,
+        started_at: Some(run.started_at.clone()),
+        test_context: run.test_context.clone(),
+        process_aliases: run.effective_aliases(),
+        folder_path: run.folder_path.clone(),
+        stop_after_seconds: run.stop_after_seconds,
+        metric_sink: run.metric_sink.as_ref().map(|s| s.stats()),
+        stream_file: run.stream_file.as_ref().map(|f| f.stats()),
+        log_metric_parse_failures: run.log_parse_failures.clone(),
+        progress: progress.clone(),
+        browser_extension: browser_extension.clone(),
+        extension_dependency: run.extension_dependency.clone(),
+        sidecar_protocol_errors: sidecar_protocol_errors.clone(),
+        sidecar_protocol_errors_in_run: run.ingest.sidecar_protocol_errors,
+    }));
+    Ok(status.unwrap_or_else(|| CollectionStatus {
+        session_id: None,
+        sessions: Vec::new(),
+        is_running: false,
+        target_pids: Vec::new(),
+        mode: "system".to_string(),
+        interval_ms: *safe_lock(&state.default_interval_ms),
+        started_at: None,
+        test_context: None,
+        process_aliases: Vec::new(),
+        folder_path: None,
+        stop_after_seconds: None,
+        metric_sink: None,
+        stream_file: None,
+        log_metric_parse_failures: HashMap::new(),
+        progress,
+        browser_extension,
+        extension_dependency: None,
+        sidecar_protocol_errors,
+        sidecar_protocol_errors_in_run: 0,
+    }))
+}
+
+#[derive(serde::Serialize)]
+pub struct WsServerStatus {
+    pub ws_port: Option<u16>,
+    pub http_port: Option<u16>,
+    /// Token HTTP ingest clients must send as `X-PerfSight-Token` (or `Authorization: Bearer`).
+    pub session_token: String,
+    pub prometheus_enabled: bool,
+    /// Extension protocol versions accepted in the hello handshake.
+    pub ws_protocol_min: u32,
+    pub ws_protocol_max: u32,
+    /// Connected extensions with their negotiated protocol version.
+    pub ws_clients: Vec<WsClientInfo>,
+}
+
+#[tauri::command]
+pub fn get_ws_server_status(server: State<'_, IngestServerState>) -> Result<WsServerStatus, PerfSightError> {
+    Ok(WsServerStatus {
+        ws_port: *safe_lock(&server.ws_port),
+        http_port: *safe_lock(&server.http_port),
+        session_token: server.session_token.clone(),
+        prometheus_enabled: *safe_lock(&server.prometheus_enabled),
+        ws_protocol_min: WS_PROTOCOL_MIN,
+        ws_protocol_max: WS_PROTOCOL_MAX,
+        ws_clients: safe_lock(&server.ws_clients).clone(),
+    })
+}
+
+pub const SETTING_PROMETHEUS_ENABLED: &str = "prometheus_enabled";
+pub const SETTING_METRIC_SINK: &str = "metric_sink";
+
+/// Enable/disable the Prometheus `/metrics` endpoint on the local HTTP listener (persisted).
+#[tauri::command]
+pub fn set_prometheus_enabled(
+    db: State<'_, Database>,
+    server: State<'_, IngestServerState>,
+    enabled: bool,
+) -> Result<bool, PerfSightError> {
+    db.set_setting(SETTING_PROMETHEUS_ENABLED, &Value::Bool(enabled))?;
+    *safe_lock(&server.prometheus_enabled) = enabled;
+    Ok(enabled)
+}
+
+#[tauri::command]
+pub fn get_metric_sink_config(db: State<'_, Database>) -> Result<Option<MetricSinkConfig>, PerfSightError> {
+    db.get_setting_as(SETTING_METRIC_SINK).map_err(PerfSightError::from)
+}
+
+/// Save (or clear, with `null`) the default metric sink used when a run doesn't specify one.
+#[tauri::command]
+pub fn set_metric_sink_config(
+    db: State<'_, Database>,
+    config: Option<MetricSinkConfig>,
+) -> Result<(), PerfSightError> {
+    db.set_setting_as(SETTING_METRIC_SINK, &config)?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_webhook_config(db: State<'_, Database>) -> Result<WebhookConfig, PerfSightError> {
+    Ok(webhook::load_config(db.inner()))
+}
+
+#[tauri::command]
+pub fn set_webhook_config(db: State<'_, Database>, config: WebhookConfig) -> Result<(), PerfSightError> {
+    if config.enabled {
+        let url = url::Url::parse(config.url.trim())
+            .map_err(|e| PerfSightError::invalid_input("url", e.to_string()))?;
+        if url.scheme() != "http" && url.scheme() != "https" {
+            return Err(PerfSightError::invalid_input("url", "must be http(s)"));
+        }
+    }
+    db.set_setting_as(webhook::SETTING_WEBHOOK, &config)?;
+    Ok(())
+}
+
+pub const SETTING_LAST_MAINTENANCE: &str = "last_maintenance";
+const AUTO_MAINTENANCE_INTERVAL_DAYS: i64 = 30;
+
+/// Integrity check, orphan cleanup and VACUUM. Refused during a collection; the last result is
+/// kept under the `last_maintenance` setting.
+#[tauri::command]
+pub async fn maintain_database(
+    app_handle: AppHandle,
+    state: State<'_, CollectionState>,
+) -> Result<MaintenanceReport, PerfSightError> {
+    if state.is_running() {
+        return Err(PerfSightError::invalid_input("collection", "stop the running collection first"));
+    }
+    run_blocking(&app_handle, |_, db| {
+        let report = db.maintain()?;
+        db.set_setting_as(SETTING_LAST_MAINTENANCE, &report)?;
+        Ok(report)
+    })
+    .await
+}
+
+#[tauri::command]
+pub fn get_last_maintenance(db: State<'_, Database>) -> Result<Option<MaintenanceReport>, PerfSightError> {
+    Ok(db.get_setting_as(SETTING_LAST_MAINTENANCE)?)
+}
+
+/// Database file size, free pages and how much of it reports and unsaved runs take.
+#[tauri::command]
+pub async fn get_database_stats(app_handle: AppHandle) -> Result<DatabaseStats, PerfSightError> {
+    run_blocking(&app_handle, |_, db| Ok(db.database_stats()?)).await
+}
+
+// Lines `get_recent_logs` returns when no limit is given.
+const DEFAULT_RECENT_LOGS: usize = 200;
+
+/// The newest diagnostic log lines at `level` (default "info") or more severe, oldest first.
+/// `limit` defaults to 200 and is capped at `logging::MAX_RECENT_LINES`.
+#[tauri::command]
+pub async fn get_recent_logs(level: Option<LogLevel>, limit: Option<usize>) -> Result<Vec<LogLine>, PerfSightError> {
+    let limit = limit.unwrap_or(DEFAULT_RECENT_LOGS);
+    if limit == 0 {
+        return Err(PerfSightError::invalid_input("limit", "must be at least 1"));
+    }
+    tauri::async_runtime::spawn_blocking(move || logging::recent(level.unwrap_or_default(), limit))
+        .await
+        .map_err(|e| PerfSightError::Internal(format!("Background task failed: {}", e)))
+}
+
+/// Zip the log files (`logs/`) with diagnostics.json (app, OS and database versions, database
+/// stats, settings without the webhook and metric sink, which may hold credentials) for a bug
+/// report. Returns the written path.
+#[tauri::command]
+pub async fn export_diagnostics_zip(
+    app_handle: AppHandle,
+    filename: Option<String>,
+    overwrite: Option<bool>,
+    destination: Option<ExportDestination>,
+) -> Result<String, PerfSightError> {
+    run_blocking(&app_handle, move |app_handle, db| {
+        export_diagnostics_zip_blocking(app_handle, db, filename, overwrite, destination)
+    })
+    .await
+}
+
+fn export_diagnostics_zip_blocking(
+    app_handle: &AppHandle,
+    db: &Database,
+    filename: Option<String>,
+    overwrite: Option<bool>,
+    destination: Option<ExportDestination>,
+) -> Result<String, PerfSightError> {
+    let default_name = format!("PerfSight_Diagnostics_{}", Utc::now().format("%Y%m%d_%H%M%S"));
+    let path = resolve_export_path(app_handle, destination.as_ref(), filename, &default_name, "zip", overwrite)?;
+
+    let mut settings = serde_json::to_value(Settings::load(db))?;
+    if let Some(obj) = settings.as_object_mut() {
+        obj.remove("webhook");
+        obj.remove("metric_sink");
+    }
+    let state: State<CollectionState> = app_handle.state();
+    let diagnostics = json!({
+        "generated_at": Utc::now().to_rfc3339(),
+        "app_version": app_handle.package_info().version.to_string(),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "os_version": sysinfo::System::os_version(),
+        "os_long_version": sysinfo::System::long_os_version(),
+        "kernel_version": sysinfo::System::kernel_version(),
+        "schema_version": crate::migrations::SCHEMA_VERSION,
+        "database": db.database_stats()?,
+        "sidecar_version": safe_lock(&state.sidecar_version).clone(),
+        "sidecar_protocol_errors": safe_lock(&state.sidecar_protocol_errors).clone(),
+        "active_sessions": state.session_ids(),
+        "log_level": logging::level(),
+        "settings": settings,
+    });
+
+    let file = std::fs::File::create(&path).map_err(|e| artifacts::path_io_error(&path, e))?;
+    let mut zip = ZipWriter::new(file);
+    let opts = FileOptions::<()>::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .unix_permissions(0o644);
+    zip.start_file("diagnostics.json", opts).map_err(|e| e.to_string())?;
+    zip.write_all(serde_json::to_string_pretty(&diagnostics)?.as_bytes())?;
+    for log in logging::log_files() {
+        let Some(name) = log.file_name().and_then(|n| n.to_str()) else { continue };
+        // The current file may be written to meanwhile; a line cut at the end is fine here.
+        let bytes = std::fs::read(&log).map_err(|e| artifacts::path_io_error(&log, e))?;
+        zip.start_file(format!("logs/{}", name), opts).map_err(|e| e.to_string())?;
+        zip.write_all(&bytes)?;
+    }
+    zip.finish().map_err(|e| e.to_string())?;
+    log_info!("Diagnostics exported to {}", path.display());
+    artifacts::path_string(&path)
+}
+
+// Most reports `get_storage_breakdown` lists as the largest.
+const MAX_LARGEST_REPORTS: usize = 200;
+
+/// Stored size per folder and per month, and the `largest` (default 20) biggest reports.
+#[tauri::command]
+pub async fn get_storage_breakdown(app_handle: AppHandle, largest: Option<usize>) -> Result<StorageBreakdown, PerfSightError> {
+    let largest = largest.unwrap_or(20).min(MAX_LARGEST_REPORTS);
+    run_blocking(&app_handle, move |_, db| Ok(db.storage_breakdown(largest)?)).await
+}
+
+// Most rows and longest run time of one `execute_readonly_query` call.
+const MAX_QUERY_ROWS: usize = 10_000;
+const QUERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Unstable: run one ad-hoc SELECT (or WITH ... SELECT) over the database, on a separate
+/// read-only connection, returning column names and up to `limit` rows (at most 10000) as JSON
+/// values. Stopped after 10 s. The table layout is internal and may change in any release.
+/// Every query is logged.
+#[tauri::command]
+pub async fn execute_readonly_query(app_handle: AppHandle, sql: String, limit: usize) -> Result<QueryResult, PerfSightError> {
+    let sql = check_readonly_sql(&sql)?;
+    if limit == 0 {
+        return Err(PerfSightError::invalid_input("limit", "must be greater than 0"));
+    }
+    let limit = limit.min(MAX_QUERY_ROWS);
+    log_info!("Read-only query (limit {}): {}", limit, sql);
+    run_blocking(&app_handle, move |_, db| {
+        let result = db.readonly_query(&sql, limit, QUERY_TIMEOUT);
+        match &result {
+            Ok(r) => log_info!("Read-only query returned {} row(s) in {} ms", r.rows.len(), r.elapsed_ms),
+            Err(e) => log_warn!("Read-only query failed: {}", e),
+        }
+        result
+    })
+    .await
+}
+
+// The statement without leading comments and a trailing ";", when it is a SELECT. Further
+// statements fail to prepare and the connection refuses writes anyway; this rejects PRAGMA,
+// ATTACH and the like up front.
+fn check_readonly_sql(sql: &str) -> Result<String, PerfSightError> {
+    let mut rest = sql.trim();
+    loop {
+        if let Some(after) = rest.strip_prefix("--") {
+            rest = after.split_once('\n').map_or("", |(_, r)| r).trim_start();
+        } else if let Some(after) = rest.strip_prefix("/*") {
+            rest = after.split_once("*/").map_or("", |(_, r)| r).trim_start();
+        } else {
+            break;
+        }
+    }
+    let stmt = rest.trim_end().trim_end_matches(';').trim_end();
+    let keyword: String = stmt.chars().take_while(|c| c.is_ascii_alphabetic()).collect();
+    if !keyword.eq_ignore_ascii_case("select") && !keyword.eq_ignore_ascii_case("with") {
+        return Err(PerfSightError::invalid_input("sql", "only a single SELECT statement is allowed"));
+    }
+    Ok(stmt.to_string())
+}
+
+/// Startup hook for `Settings::auto_maintenance`. VACUUM can take a while on a large database, so
+/// it runs off the setup thread; it is skipped when a collection has started in the meantime.
+pub fn spawn_auto_maintenance_if_due(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<Database>();
+        if !Settings::load(db.inner()).auto_maintenance || !auto_maintenance_due(db.inner()) {
+            return;
+        }
+        if app.state::<CollectionState>().is_running() {
+            log_info!("Skipping database maintenance: a collection is running");
+            return;
+        }
+        run_auto_maintenance(db.inner());
+        let _ = app.emit("database-maintenance-finished", ());
+    });
+}
+
+fn auto_maintenance_due(db: &Database) -> bool {
+    let last: Option<MaintenanceReport> = db.get_setting_as(SETTING_LAST_MAINTENANCE).ok().flatten();
+    last.and_then(|r| DateTime::parse_from_rfc3339(&r.ran_at).ok())
+        .is_none_or(|t| Utc::now().signed_duration_since(t) >= chrono::Duration::days(AUTO_MAINTENANCE_INTERVAL_DAYS))
+}
+
+fn run_auto_maintenance(db: &Database) {
+    match db.maintain() {
+        Ok(report) => {
+            log_info!(
+                "Database maintenance: {} -> {} bytes, {} fixes",
+                report.size_before_bytes,
+                report.size_after_bytes,
+                report.fixes.len()
+            );
+            let _ = db.set_setting_as(SETTING_LAST_MAINTENANCE, &report);
+        }
+        Err(e) => log_error!("Database maintenance failed: {}", e),
+    }
+}
+
+#[tauri::command]
+pub fn get_app_settings(db: State<'_, Database>) -> Result<Settings, PerfSightError> {
+    Ok(Settings::load(db.inner()))
+}
+
+/// Validate and persist settings. Listener ports take effect on the next launch;
+/// everything else applies immediately.
+#[tauri::command]
+pub fn update_app_settings(
+    db: State<'_, Database>,
+    state: State<'_, CollectionState>,
+    server: State<'_, IngestServerState>,
+    settings: Settings,
+) -> Result<Settings, PerfSightError> {
+    settings.validate()?;
+    settings.save(db.inner())?;
+    *safe_lock(&server.prometheus_enabled) = settings.prometheus_enabled;
+    *safe_lock(&state.default_interval_ms) = settings.default_interval_ms;
+    logging::set_level(settings.log_level);
+    Ok(settings)
+}
+
+// Struct for arguments
+#[derive(serde::Deserialize)]
+pub struct ProcessListArgs {
+    mode: String,
+    /// Scan now instead of answering from the cache.
+    #[serde(default)]
+    refresh: Option<bool>,
+    /// Append PerfSight's own processes (proc_type "PerfSight") to the list.
+    #[serde(default)]
+    include_self: Option<bool>,
+    /// Memory figure of system-mode rows; pass the run's `memory_standard` so the picker shows
+    /// what the run will record. Default "rss".
+    #[serde(default)]
+    memory_standard: Option<MemoryStandard>,
+}
+
+/// Payload of the "process-list-updated" event.
+#[derive(Serialize)]
+pub struct ProcessListUpdated {
+    pub mode: String,
+    pub processes: Vec<ProcessInfo>,
+}
+
+// Cache key of the sidecar's Chrome process scan (browser mode in the picker).
+const SIDECAR_CHROME_SCAN_KEY: &str = "sidecar:chrome";
+
+// Helper to handle mutex poisoning gracefully
+pub fn safe_lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => {
+            log_warn!("Mutex was poisoned! Recovering...");
+            poisoned.into_inner()
+        }
+    }
+}
+
+// Same as `safe_lock`, for the run state lock.
+pub fn safe_read<T>(lock: &RwLock<T>) -> std::sync::RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(|poisoned| {
+        log_warn!("RwLock was poisoned! Recovering...");
+        poisoned.into_inner()
+    })
+}
+
+pub fn safe_write<T>(lock: &RwLock<T>) -> std::sync::RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(|poisoned| {
+        log_warn!("RwLock was poisoned! Recovering...");
+        poisoned.into_inner()
+    })
+}
+
+// Websocket ingest (Chrome extension): only valid in Browser API mode.
+// This avoids mixing Chrome Task Manager memory (private/footprint) into System API runs.
+// `stream` names the connection (or "http") whose `seq` numbers the payload continues.
+pub fn process_websocket_metric_payload<R: tauri::Runtime>(app: &AppHandle<R>, data: Value, state: &CollectionState, stream: &str) -> usize {
+    if !state.read_each(|run| run.mode == "browser").contains(&true) {
+        let ignored = data["metrics"].as_object().map(|m| m.len() as u64).unwrap_or(0);
+        state.write_each(|run| {
+            run.ingest.dropped_samples += ignored;
+            run.ingest.websocket_ignored += ignored;
+        });
+        return 0;
+    }
+    process_metric_payload(app, data, state, DataSource::Websocket, stream)
+}
+
+// Write the PDF to `path`; a partially written file is removed on failure.
+fn write_pdf_file(path: &std::path::Path, write: impl FnOnce(&mut std::fs::File) -> Result<u64, PerfSightError>) -> Result<(), PerfSightError> {
+    let mut file = std::fs::File::create(path).map_err(|e| artifacts::path_io_error(path, e))?;
+    let result = write(&mut file).and_then(|_| file.flush().map_err(PerfSightError::from));
+    if result.is_err() {
+        drop(file);
+        let _ = std::fs::remove_file(path);
+    }
+    result
+}
+
+
+/// Save a PDF rendered by the frontend. `pdf_base64` is raw base64 or a data URL; it is decoded
+/// in chunks straight into the file and rejected above `max_artifact_mb`.
+#[tauri::command]
+pub async fn export_report_pdf(
+    app_handle: AppHandle,
+    report_id: i64,
+    filename: Option<String>,
+    pdf_base64: String,
+    overwrite: Option<bool>,
+    destination: Option<ExportDestination>,
+) -> Result<String, PerfSightError> {
+    run_blocking(&app_handle, move |app_handle, db| {
+        export_report_pdf_blocking(app_handle, db, report_id, filename, pdf_base64, overwrite, destination)
+    })
+    .await
+}
+
+fn export_report_pdf_blocking(
+    app_handle: &AppHandle,
+    db: &Database,
+    report_id: i64,
+    filename: Option<String>,
+    pdf_base64: String,
+    overwrite: Option<bool>,
+    destination: Option<ExportDestination>,
+) -> Result<String, PerfSightError> {
+    let max_bytes = Settings::load(db).max_artifact_bytes();
+    let default_name = format!("PerfSight_Report_{}", report_id);
+    let path = resolve_export_path(app_handle, destination.as_ref(), filename, &default_name, "pdf", overwrite)?;
+    write_pdf_file(&path, |file| artifacts::decode_base64_to_writer(&pdf_base64, file, max_bytes, "pdf_base64"))?;
+    artifacts::path_string(&path)
+}
+
+/// Like `export_report_pdf`, but copies a PDF the frontend already wrote to `src_path` instead
+/// of passing the bytes over IPC. The source file is left in place.
+#[tauri::command]
+pub async fn export_report_pdf_from_path(
+    app_handle: AppHandle,
+    report_id: i64,
+    filename: Option<String>,
+    src_path: String,
+    overwrite: Option<bool>,
+    destination: Option<ExportDestination>,
+) -> Result<String, PerfSightError> {
+    run_blocking(&app_handle, move |app_handle, db| {
+        export_report_pdf_from_path_blocking(app_handle, db, report_id, filename, src_path, overwrite, destination)
+    })
+    .await
+}
+
+fn export_report_pdf_from_path_blocking(
+    app_handle: &AppHandle,
+    db: &Database,
+    report_id: i64,
+    filename: Option<String>,
+    src_path: String,
+    overwrite: Option<bool>,
+    destination: Option<ExportDestination>,
+) -> Result<String, PerfSightError> {
+    let max_bytes = Settings::load(db).max_artifact_bytes();
+    let src = std::path::PathBuf::from(src_path.trim());
+    artifacts::check_file_size(&src, max_bytes, "src_path")?;
+    let default_name = format!("PerfSight_Report_{}", report_id);
+    let path = resolve_export_path(app_handle, destination.as_ref(), filename, &default_name, "pdf", overwrite)?;
+    if path == src {
+        return Err(PerfSightError::invalid_input("src_path", "is the same as the output file"));
+    }
+    let input = std::fs::File::open(&src)?;
+    write_pdf_file(&path, |file| artifacts::copy_limited(input, file, max_bytes, "src_path"))?;
+    artifacts::path_string(&path)
+}
+
+/// Push a saved report to an OTLP/HTTP collector as gauge data points (original sample timestamps).
+/// `endpoint` may be the collector base URL or the full `/v1/metrics` URL. The report is loaded
+/// and converted on the blocking pool; only the push runs on the async runtime.
+#[tauri::command]
+pub async fn export_report_otlp(
+    app_handle: AppHandle,
+    id: i64,
+    endpoint: String,
+    headers: Option<HashMap<String, String>>,
+) -> Result<OtlpExportResult, PerfSightError> {
+    let requests = run_blocking(&app_handle, move |_, db| {
+        let report = db.get_report_detail(id)?;
+        Ok(otlp::build_requests(report.id, &report.title, &report.metrics, &report.meta))
+    })
+    .await?;
+    Ok(otlp::push_requests(&endpoint, &headers.unwrap_or_default(), requests).await?)
+}
+
+/// Write the report's budget verdicts as JUnit XML (for CI test report rendering) to `path`, a
+/// full file path like `destination.dest_path`; without either it goes to the export dir.
+#[tauri::command]
+pub async fn export_report_junit(
+    app_handle: AppHandle,
+    id: i64,
+    path: Option<String>,
+    overwrite: Option<bool>,
+    destination: Option<ExportDestination>,
+) -> Result<String, PerfSightError> {
+    run_blocking(&app_handle, move |app_handle, db| {
+        export_report_junit_blocking(app_handle, db, id, path, overwrite, destination)
+    })
+    .await
+}
+
+fn export_report_junit_blocking<R: tauri::Runtime>(
+    app_handle: &AppHandle<R>,
+    db: &Database,
+    id: i64,
+    path: Option<String>,
+    overwrite: Option<bool>,
+    destination: Option<ExportDestination>,
+) -> Result<String, PerfSightError> {
+    let report = db.get_report_detail(id)?;
+    let analysis = report
+        .analysis
+        .unwrap_or_else(|| crate::analysis::analyze_report(&report.metrics, &report.meta));
+    let results = crate::analysis::evaluate_budgets(&analysis, &report.meta.budgets);
+    let xml = junit::render_junit(&report.title, &report.created_at, &report.meta, &analysis, &results);
+
+    let default_name = format!("PerfSight_Report_{}_JUnit", id);
+    let path = resolve_export_path(app_handle, with_dest_path(destination, path).as_ref(), None, &default_name, "xml", overwrite)?;
+    artifacts::write_export_file(&path, xml.as_bytes())?;
+    artifacts::path_string(&path)
+}
+
+/// Markdown summary of a report (returned as a string for the clipboard).
+/// The budget verdict table is included by default when the report has budgets.
+#[tauri::command]
+pub async fn export_report_markdown(
+    app_handle: AppHandle,
+    id: i64,
+    include_budgets: Option<bool>,
+    timezone: Option<String>,
+) -> Result<String, PerfSightError> {
+    let zone = DisplayZone::parse(timezone.as_deref())?;
+    run_blocking(&app_handle, move |_, db| render_report_markdown(db, id, include_budgets, zone)).await
+}
+
+fn render_report_markdown(db: &Database, id: i64, include_budgets: Option<bool>, zone: DisplayZone) -> Result<String, PerfSightError> {
+    let mut report = db.get_report_detail(id)?;
+    let analysis = report
+        .analysis
+        .take()
+        .unwrap_or_else(|| crate::analysis::analyze_report(&report.metrics, &report.meta));
+    let results = if include_budgets.unwrap_or(true) {
+        Some(crate::analysis::evaluate_budgets(&analysis, &report.meta.budgets))
+    } else {
+        None
+    };
+    Ok(markdown::render_markdown(&report, &analysis, results.as_deref(), zone))
+}
+
+/// Budget verdict of one report in a `check_folder_budgets` table.
+#[derive(Debug, Serialize)]
+pub struct ReportBudgetVerdict {
+    pub report_id: i64,
+    pub created_at: String,
+    pub title: String,
+    /// None when the report has no budgets.
+    pub passed: Option<bool>,
+    pub results: Vec<BudgetResult>,
+    /// First failing budget, in budget order.
+    pub first_failure: Option<BudgetResult>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FolderBudgetCheck {
+    pub folder_path: String,
+    pub scenario_name: Option<String>,
+    pub last_n: usize,
+    /// Newest first; fewer than `last_n` when the folder has fewer matching reports.
+    pub reports: Vec<ReportBudgetVerdict>,
+    /// At least one report matched, and every matched report has budgets and passed them.
+    pub passed: bool,
+    /// The newest failing report and its first failing budget.
+    pub first_failing_report_id: Option<i64>,
+    pub first_failure: Option<BudgetResult>,
+}
+
+/// Evaluate the budgets of the newest `last_n` reports in a folder (optionally one scenario only).
+/// Uses the cached verdicts and analysis; samples are only read for reports never evaluated.
+pub fn check_folder_budgets_with(
+    db: &Database,
+    folder_path: &str,
+    scenario_name: Option<&str>,
+    last_n: usize,
+) -> Result<FolderBudgetCheck, PerfSightError> {
+    if last_n == 0 {
+        return Err(PerfSightError::invalid_input("last_n", "must be at least 1"));
+    }
+    let scenario_name = scenario_name.map(|s| s.trim()).filter(|s| !s.is_empty());
+    let mut reports = Vec::new();
+    for (report_id, created_at, title, _) in db.latest_reports_in_folder(folder_path, scenario_name, last_n)? {
+        let results = db.budget_results(report_id)?;
+        let first_failure = results.iter().find(|r| !r.passed).cloned();
+        reports.push(ReportBudgetVerdict {
+            report_id,
+            created_at,
+            title,
+            passed: (!results.is_empty()).then(|| first_failure.is_none()),
+            results,
+            first_failure,
+        });
+    }
+    let passed = !reports.is_empty() && reports.iter().all(|r| r.passed == Some(true));
+    let first_failing = reports.iter().find(|r| r.first_failure.is_some());
+    Ok(FolderBudgetCheck {
+        folder_path: normalize_folder_path(folder_path),
+        scenario_name: scenario_name.map(|s| s.to_string()),
+        last_n,
+        first_failing_report_id: first_failing.map(|r| r.report_id),
+        first_failure: first_failing.and_then(|r| r.first_failure.clone()),
+        reports,
+        passed,
+    })
+}
+
+/// "Do the last N runs of scenario X in this folder all pass their budgets?"
+#[tauri::command]
+pub fn check_folder_budgets(
+    db: State<'_, Database>,
+    folder_path: String,
+    scenario_name: Option<String>,
+    last_n: usize,
+) -> Result<FolderBudgetCheck, PerfSightError> {
+    check_folder_budgets_with(db.inner(), &folder_path, scenario_name.as_deref(), last_n)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ComparisonBundleV1 {
+    pub schema_version: u32,
+    pub bundle_type: String, // "comparison"
+    pub exported_at: String,
+    pub comparison_context: Value,
+    pub reports: Vec<ReportDetail>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportBundleItemV1 {
+    pub report_id: i64,
+    /// Optional base64 PDF (raw base64 or data URL).
+    pub pdf_base64: Option<String>,
+    /// Optional PDF already written to disk; used instead of `pdf_base64` when set.
+    #[serde(default)]
+    pub pdf_path: Option<String>,
+}
+
+fn safe_slug(s: &str, max_len: usize) -> String {
+    let mut out = String::new();
+    for ch in s.chars() {
+        let c = match ch {
+            'a'..='z' | 'A'..='Z' | '0'..='9' => ch,
+            ' ' | '-' | '_' => '_',
+            _ => '_',
+        };
+        out.push(c);
+        if out.len() >= max_len {
+            break;
+        }
+    }
+    let trimmed = out.trim_matches('_').to_string();
+    if trimmed.is_empty() { "report".to_string() } else { trimmed }
+}
+
+fn compact_time_id(s: &str) -> String {
+    // Keep only digits + 'T' + 'Z' for a stable, filesystem-friendly identifier.
+    // Example: "2025-12-19T13:45:02.123Z" -> "20251219T134502123Z"
+    let mut out = String::new();
+    for ch in s.chars() {
+        if ch.is_ascii_digit() || ch == 'T' || ch == 'Z' {
+            out.push(ch);
+        }
+    }
+    if out.is_empty() { "unknown_time".to_string() } else { out }
+}
+
+/// Run database/file work on the blocking pool so large reports stall neither the IPC handler nor
+/// the async runtime. The connection mutex is only taken inside `f`, never across an await.
+async fn run_blocking<R: tauri::Runtime, T, F>(app_handle: &AppHandle<R>, f: F) -> Result<T, PerfSightError>
+where
+    T: Send + 'static,
+    F: FnOnce(&AppHandle<R>, &Database) -> Result<T, PerfSightError> + Send + 'static,
+{
+    let app = app_handle.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db: State<Database> = app.state();
+        f(&app, db.inner())
+    })
+    .await
+    .map_err(|e| PerfSightError::Internal(format!("Background task failed: {}", e)))?
+}
+
+/// Downloads folder (falling back to the app data dir), created if missing.
+pub fn resolve_export_dir<R: tauri::Runtime>(app_handle: &AppHandle<R>) -> Result<std::path::PathBuf, String> {
+    let mut dir = app_handle.path().resolve("", BaseDirectory::Download).ok();
+    if dir.is_none() {
+        dir = app_handle.path().app_local_data_dir().ok();
+    }
+    let dir = dir.ok_or("Failed to resolve output directory")?;
+    if !dir.exists() {
+        std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    }
+    Ok(dir)
+}
+
+/// Optional user-chosen output location for file exports (e.g. from a save dialog).
+/// Without one, exports go to `resolve_export_dir`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ExportDestination {
+    /// Directory to write into; the file name still comes from `filename` or the default.
+    #[serde(default)]
+    pub dest_dir: Option<String>,
+    /// Full output path; takes precedence over `dest_dir` and `filename`.
+    #[serde(default)]
+    pub dest_path: Option<String>,
+    /// Create the missing directory instead of failing.
+    #[serde(default)]
+    pub create: bool,
+}
+
+/// `destination` with `path` (a full file path, when given) as its `dest_path`.
+fn with_dest_path(destination: Option<ExportDestination>, path: Option<String>) -> Option<ExportDestination> {
+    match path {
+        Some(path) => Some(ExportDestination { dest_path: Some(path), ..destination.unwrap_or_default() }),
+        None => destination,
+    }
+}
+
+/// Where an export lands: the requested name (or `default_name`) sanitized with
+/// `artifacts::sanitize_export_filename`, in the destination (default: the export dir). An
+/// existing file is kept and the new one gets a " (n)" suffix unless `overwrite` is set.
+pub fn resolve_export_path<R: tauri::Runtime>(
+    app_handle: &AppHandle<R>,
+    destination: Option<&ExportDestination>,
+    filename: Option<String>,
+    default_name: &str,
+    ext: &str,
+    overwrite: Option<bool>,
+) -> Result<std::path::PathBuf, PerfSightError> {
+    let trimmed = |s: &Option<String>| s.as_deref().map(str::trim).filter(|s| !s.is_empty()).map(std::path::PathBuf::from);
+    let dest_path = destination.and_then(|d| trimmed(&d.dest_path));
+    let dest_dir = destination.and_then(|d| trimmed(&d.dest_dir));
+    let create = destination.is_some_and(|d| d.create);
+    let (dir, requested) = match (dest_path, dest_dir) {
+        (Some(p), _) => {
+            let name = p.file_name().map(|n| n.to_string_lossy().to_string());
+            let dir = p.parent().filter(|d| !d.as_os_str().is_empty()).map(|d| d.to_path_buf());
+            let dir = dir.ok_or_else(|| PerfSightError::InvalidPath {
+                path: p.to_string_lossy().to_string(),
+                reason: "must include a directory".to_string(),
+            })?;
+            (dir, name)
+        }
+        (None, Some(d)) => (d, filename),
+        (None, None) => (resolve_export_dir(app_handle)?, filename),
+    };
+    artifacts::export_path_in(&dir, create, requested.as_deref(), ext, default_name, overwrite.unwrap_or(false))
+}
+
+/// Export a report as a dataset JSON. `filter` keeps only some PIDs, metrics and/or a time range;
+/// the applied filter is written into the dataset header. `target_version` picks the dataset
+/// layout (default: the latest, see `dataset::DATASET_SCHEMA_VERSION`). With `anonymize`, titles,
+/// URLs, host and user names and command lines are replaced or dropped (see `Anonymizer`).
+#[tauri::command]
+pub async fn export_report_dataset(
+    app_handle: AppHandle,
+    report_id: i64,
+    overwrite: Option<bool>,
+    destination: Option<ExportDestination>,
+    filter: Option<MetricSelection>,
+    target_version: Option<u32>,
+    anonymize: Option<AnonymizeOptions>,
+) -> Result<String, PerfSightError> {
+    run_blocking(&app_handle, move |app_handle, db| {
+        export_report_dataset_blocking(app_handle, db, report_id, overwrite, destination, filter, target_version, anonymize)
+    })
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
+fn export_report_dataset_blocking<R: tauri::Runtime>(
+    app_handle: &AppHandle<R>,
+    db: &Database,
+    report_id: i64,
+    overwrite: Option<bool>,
+    destination: Option<ExportDestination>,
+    filter: Option<MetricSelection>,
+    target_version: Option<u32>,
+    anonymize: Option<AnonymizeOptions>,
+) -> Result<String, PerfSightError> {
+    let version = dataset::target_version(target_version)?;
+    let filter = filter.filter(|f| !f.is_empty());
+    if let Some(f) = &filter {
+        f.validate()?;
+    }
+    let mut report = db.get_report_detail(report_id)?;
+    if let Some(f) = &filter {
+        report.metrics = f.apply(&report.metrics);
+        if report.metrics.is_empty() {
+            return Err(PerfSightError::invalid_input("filter", "no samples match the filter"));
+        }
+        report
+            .meta
+            .events
+            .retain(|e| f.in_range(e.timestamp) && e.pid.is_none_or(|pid| f.pids.is_empty() || f.pids.contains(&pid)));
+        let mut analysis = crate::analysis::analyze_report(&report.metrics, &report.meta);
+        crate::analysis::apply_aliases(&mut analysis, &report.meta.aliases());
+        report.analysis = Some(analysis);
+    }
+    let dataset = ReportDataset {
+        schema_version: version,
+        exported_at: Utc::now().to_rfc3339(),
+        filter,
+        header: DatasetHeaderV2::for_version(version, &report.meta, Vec::new()),
+        report,
+    };
+    let mut anonymizer = anonymize.map(Anonymizer::new);
+    let json_str = match anonymizer.as_mut() {
+        Some(anon) => {
+            let mut v = serde_json::to_value(&dataset)?;
+            anon.dataset(&mut v);
+            serde_json::to_string_pretty(&v)?
+        }
+        None => serde_json::to_string_pretty(&dataset)?,
+    };
+
+    let default_name = format!("PerfSight_Report_{}_Dataset", report_id);
+    let path = resolve_export_path(app_handle, destination.as_ref(), None, &default_name, "json", overwrite)?;
+    artifacts::write_export_file(&path, json_str.as_bytes())?;
+    if let Some(anon) = &anonymizer {
+        write_anonymization_map(&path, anon)?;
+    }
+    artifacts::path_string(&path)
+}
+
+// With `write_map`, save what the anonymizer replaced as `<export file>.anonymization.json`, for
+// the owner only: it is never part of the export itself.
+fn write_anonymization_map(export_path: &std::path::Path, anon: &Anonymizer) -> Result<(), PerfSightError> {
+    if !anon.options.write_map {
+        return Ok(());
+    }
+    let mut name = export_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".anonymization.json");
+    artifacts::write_export_file(&export_path.with_file_name(name), serde_json::to_string_pretty(anon.map())?.as_bytes())
+}
+
+// Same JSON as `ReportDataset` (without a filter), serialized straight from the stored row.
+#[derive(Serialize)]
+struct StoredDataset<'a> {
+    schema_version: u32,
+    exported_at: String,
+    report: StoredDatasetReport<'a>,
+    #[serde(flatten)]
+    header: DatasetHeaderV2,
+}
+
+#[derive(Serialize)]
+struct StoredDatasetReport<'a> {
+    id: i64,
+    created_at: &'a str,
+    title: &'a str,
+    metrics: StoredMetrics<'a>,
+    analysis: Option<&'a crate::analysis::AnalysisReport>,
+    meta: &'a ReportMeta,
+}
+
+// Re-serializes a stored `metrics_json` array one batch at a time (see `for_each_batch`), PIDs in
+// ascending order so exporting the same report always writes the same bytes.
+struct StoredMetrics<'a>(&'a str);
+
+#[derive(Serialize)]
+struct SortedBatch<'a> {
+    timestamp: &'a DateTime<Utc>,
+    metrics: std::collections::BTreeMap<&'a u32, &'a MetricPoint>,
+}
+
+impl Serialize for StoredMetrics<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::{Error, SerializeSeq};
+        let mut seq = serializer.serialize_seq(None)?;
+        let mut failed = None;
+        for_each_batch(self.0, |batch: BatchMetric| match seq
+            .serialize_element(&SortedBatch { timestamp: &batch.timestamp, metrics: batch.metrics.iter().collect() })
+        {
+            Ok(()) => true,
+            Err(e) => {
+                failed = Some(e);
+                false
+            }
+        })
+        .map_err(S::Error::custom)?;
+        if let Some(e) = failed {
+            return Err(e);
+        }
+        seq.end()
+    }
+}
+
+// Counts what passes through to the zip entry, for the progress events.
+struct CountingWriter<'a, W: Write> {
+    inner: &'a mut W,
+    bytes: u64,
+}
+
+impl<W: Write> Write for CountingWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.bytes += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// What `write_prepared_dataset` wrote for one report.
+struct ZippedDataset {
+    folder: String,
+    created_id: String,
+    title: String,
+    created_at: String,
+    folder_path: String,
+    bytes: u64,
+}
+
+/// One report's dataset, built off the zip writer so several can be prepared at once (see
+/// `parallel::for_each_ordered`) and then written in order.
+struct PreparedDataset {
+    id: i64,
+    folder: String,
+    created_id: String,
+    title: String,
+    created_at: String,
+    folder_path: String,
+    body: PreparedBody,
+}
+
+enum PreparedBody {
+    Json(Vec<u8>),
+    // Kept as a value for the anonymizer, which must see the reports in export order so every
+    // export of the same reports gets the same replacements.
+    Value(Value),
+}
+
+// Build one report's dataset. The samples are re-serialized from the stored JSON batch by batch,
+// so no parsed copy of the report is kept; only a report without a cached analysis is parsed
+// whole, once, to compute it. With `has_pdf` the dataset lists `report_<id>_<created>.pdf`, which
+// the caller writes next to it. With `for_anonymizer` it is built as a value to be rewritten.
+// Every dataset of one export carries the same `exported_at`.
+fn prepare_report_dataset(
+    db: &Database,
+    id: i64,
+    has_pdf: bool,
+    for_anonymizer: bool,
+    exported_at: &str,
+) -> Result<PreparedDataset, PerfSightError> {
+    let report = db.stored_report(id)?;
+    let mut analysis = match report.analysis {
+        Some(cached) => cached,
+        None => {
+            let metrics: Vec<BatchMetric> = serde_json::from_str(&report.metrics_json).unwrap_or_default();
+            let analysis = crate::analysis::analyze_report(&metrics, &report.meta);
+            if let Err(e) = db.cache_analysis(id, &analysis) {
+                log_warn!("Failed to cache analysis of report {}: {}", id, e);
+            }
+            analysis
+        }
+    };
+    crate::analysis::apply_aliases(&mut analysis, &report.meta.aliases());
+
+    let created_id = compact_time_id(&report.created_at);
+    let artifacts = if has_pdf {
+        vec![DatasetArtifact { kind: "pdf".to_string(), path: format!("report_{}_{}.pdf", id, created_id) }]
+    } else {
+        Vec::new()
+    };
+    let dataset = StoredDataset {
+        schema_version: dataset::DATASET_SCHEMA_VERSION,
+        exported_at: exported_at.to_string(),
+        header: DatasetHeaderV2::for_version(dataset::DATASET_SCHEMA_VERSION, &report.meta, artifacts),
+        report: StoredDatasetReport {
+            id,
+            created_at: &report.created_at,
+            title: &report.title,
+            metrics: StoredMetrics(&report.metrics_json),
+            analysis: Some(&analysis),
+            meta: &report.meta,
+        },
+    };
+    let body = if for_anonymizer {
+        PreparedBody::Value(serde_json::to_value(&dataset)?)
+    } else {
+        PreparedBody::Json(serde_json::to_vec(&dataset)?)
+    };
+    Ok(PreparedDataset {
+        id,
+        folder: format!("{}_{}_{}", created_id, id, safe_slug(&report.title, 60)),
+        created_id,
+        folder_path: report.meta.folder_path(),
+        title: report.title,
+        created_at: report.created_at,
+        body,
+    })
+}
+
+// Write a prepared report as `<created>_<id>_<slug>/dataset_<id>_<created>.json`, anonymized
+// first when an anonymizer is given (the dataset must then have been prepared for one).
+fn write_prepared_dataset<W: Write + std::io::Seek>(
+    zip: &mut ZipWriter<W>,
+    opts: FileOptions<()>,
+    prepared: PreparedDataset,
+    anonymizer: Option<&mut Anonymizer>,
+) -> Result<ZippedDataset, PerfSightError> {
+    let PreparedDataset { id, folder, created_id, title, created_at, mut folder_path, body } = prepared;
+    zip.start_file(format!("{}/dataset_{}_{}.json", folder, id, created_id), opts)
+        .map_err(|e| e.to_string())?;
+    let mut counter = CountingWriter { inner: zip, bytes: 0 };
+    match (body, anonymizer) {
+        (PreparedBody::Value(mut v), Some(anon)) => {
+            anon.dataset(&mut v);
+            let mut out = std::io::BufWriter::new(&mut counter);
+            serde_json::to_writer(&mut out, &v)?;
+            out.flush()?;
+            folder_path = anon.folder(&folder_path);
+        }
+        (PreparedBody::Value(v), None) => serde_json::to_writer(&mut counter, &v)?,
+        (PreparedBody::Json(bytes), _) => counter.write_all(&bytes)?,
+    }
+    Ok(ZippedDataset { folder, created_id, title, created_at, folder_path, bytes: counter.bytes })
+}
+
+// Bundles that carry comparisons.json; older bundles (no such entry) are read as version 1.
+const BUNDLE_SCHEMA_VERSION: u32 = 2;
+
+/// A saved comparison inside a bundle. Report ids change on import, so members are also recorded
+/// by content hash (see `Database::report_content_hashes`).
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundledComparison {
+    pub comparison: ComparisonDetail,
+    /// Hash per member, in `comparison.report_ids` order.
+    pub report_hashes: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundleComparisonsV2 {
+    pub schema_version: u32,
+    pub comparisons: Vec<BundledComparison>,
+}
+
+// Write comparisons.json with every saved comparison that uses at least one of `report_ids`.
+// Returns how many were written.
+fn zip_bundle_comparisons<W: Write + std::io::Seek>(
+    zip: &mut ZipWriter<W>,
+    opts: FileOptions<()>,
+    db: &Database,
+    report_ids: &[i64],
+    mut anonymizer: Option<&mut Anonymizer>,
+) -> Result<usize, PerfSightError> {
+    let mut comparisons = Vec::new();
+    for summary in db.get_all_comparisons(None)? {
+        let mut cmp = db.get_comparison_detail(summary.id)?;
+        if !cmp.report_ids.iter().any(|id| report_ids.contains(id)) {
+            continue;
+        }
+        let hashes = db.report_content_hashes(&cmp.report_ids)?;
+        // A member deleted since the comparison was saved can't be resolved anywhere.
+        let Some(report_hashes) = cmp.report_ids.iter().map(|id| hashes.get(id).cloned()).collect::<Option<Vec<_>>>()
+        else {
+            continue;
+        };
+        // Masked only once exported, so skipped comparisons don't shift the folder numbering.
+        if let Some(anon) = anonymizer.as_deref_mut() {
+            cmp.folder_path = anon.folder(&cmp.folder_path);
+        }
+        comparisons.push(BundledComparison { comparison: cmp, report_hashes });
+    }
+    if comparisons.is_empty() {
+        return Ok(0);
+    }
+    let count = comparisons.len();
+    zip.start_file("comparisons.json", opts).map_err(|e| e.to_string())?;
+    zip.write_all(
+        serde_json::to_string_pretty(&BundleComparisonsV2 { schema_version: BUNDLE_SCHEMA_VERSION, comparisons })?
+            .as_bytes(),
+    )?;
+    Ok(count)
+}
+
+// `{ "<old id>": [pid..] }` -> `{ "<new id>": [pid..] }`; entries for unmapped ids are dropped.
+fn remap_selections(selections: &Value, ids: &HashMap<i64, i64>) -> Value {
+    let Some(obj) = selections.as_object() else { return json!({}) };
+    Value::Object(
+        obj.iter()
+            .filter_map(|(k, pids)| {
+                let new_id = ids.get(&k.parse::<i64>().ok()?)?;
+                Some((new_id.to_string(), pids.clone()))
+            })
+            .collect(),
+    )
+}
+
+/// Recreate bundled comparisons against the local report ids. A comparison is skipped (with a
+/// warning) when any member report is missing, or when the same title and members already exist.
+/// Returns the created comparison ids and the warnings.
+fn restore_bundle_comparisons(db: &Database, comparisons_json: &str) -> Result<(Vec<i64>, Vec<String>), PerfSightError> {
+    let bundle: BundleComparisonsV2 = serde_json::from_str(comparisons_json)
+        .map_err(|e| PerfSightError::invalid_input("comparisons.json", e.to_string()))?;
+    if bundle.schema_version > BUNDLE_SCHEMA_VERSION {
+        return Err(PerfSightError::invalid_input(
+            "schema_version",
+            format!("unsupported bundle schema_version {}", bundle.schema_version),
+        ));
+    }
+    let mut existing: Vec<(String, Vec<i64>)> = Vec::new();
+    for summary in db.get_all_comparisons(None)? {
+        let mut ids = db.get_comparison_detail(summary.id)?.report_ids;
+        ids.sort_unstable();
+        existing.push((summary.title, ids));
+    }
+
+    let mut created = Vec::new();
+    let mut warnings = Vec::new();
+    for item in bundle.comparisons {
+        let cmp = &item.comparison;
+        if item.report_hashes.len() != cmp.report_ids.len() {
+            warnings.push(format!("Skipped comparison '{}': member list is inconsistent", cmp.title));
+            continue;
+        }
+        let mut id_map: HashMap<i64, i64> = HashMap::new();
+        let mut missing = 0;
+        for (old_id, hash) in cmp.report_ids.iter().zip(&item.report_hashes) {
+            match db.report_id_by_content_hash(hash)? {
+                Some(new_id) => {
+                    id_map.insert(*old_id, new_id);
+                }
+                None => missing += 1,
+            }
+        }
+        if missing > 0 {
+            warnings.push(format!(
+                "Skipped comparison '{}': {} of {} reports are missing",
+                cmp.title,
+                missing,
+                cmp.report_ids.len()
+            ));
+            continue;
+        }
+        let report_ids: Vec<i64> = cmp.report_ids.iter().map(|id| id_map[id]).collect();
+        let mut sorted = report_ids.clone();
+        sorted.sort_unstable();
+        if existing.iter().any(|(title, ids)| title == &cmp.title && ids == &sorted) {
+            warnings.push(format!("Skipped comparison '{}': already present", cmp.title));
+            continue;
+        }
+        if !cmp.folder_path.is_empty() {
+            db.ensure_comparison_folder_path(&cmp.folder_path)?;
+        }
+        let id = db.create_comparison(
+            &cmp.title,
+            &report_ids,
+            &cmp.folder_path,
+            cmp.baseline_report_id.and_then(|b| id_map.get(&b).copied()),
+            &remap_selections(&cmp.cpu_selections_by_id, &id_map),
+            &remap_selections(&cmp.mem_selections_by_id, &id_map),
+            Some(&cmp.metric_selections),
+            &cmp.meta,
+        )?;
+        existing.push((cmp.title.clone(), sorted));
+        created.push(id);
+    }
+    Ok((created, warnings))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BundleProgress {
+    /// "export" | "import"
+    pub phase: &'static str,
+    pub done: usize,
+    pub total: usize,
+    pub report_id: i64,
+}
+
+/// Export every report in a folder (optionally with sub-folders) into one zip: the same per-report
+/// layout and manifest.json as `export_reports_bundle_zip`, plus folders.json describing the folder
+/// tree and, unless `include_comparisons` is false, comparisons.json with the saved comparisons that
+/// use those reports. Emits "folder-bundle-progress" after each report. `anonymize` works as in
+/// `export_report_dataset`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn export_folder_bundle_zip(
+    app_handle: AppHandle,
+    folder_path: String,
+    include_nested: bool,
+    filename: Option<String>,
+    overwrite: Option<bool>,
+    destination: Option<ExportDestination>,
+    include_comparisons: Option<bool>,
+    anonymize: Option<AnonymizeOptions>,
+) -> Result<String, PerfSightError> {
+    run_blocking(&app_handle, move |app_handle, db| {
+        export_folder_bundle_zip_blocking(
+            app_handle,
+            db,
+            folder_path,
+            include_nested,
+            filename,
+            overwrite,
+            destination,
+            include_comparisons.unwrap_or(true),
+            anonymize,
+        )
+    })
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
+fn export_folder_bundle_zip_blocking(
+    app_handle: &AppHandle,
+    db: &Database,
+    folder_path: String,
+    include_nested: bool,
+    filename: Option<String>,
+    overwrite: Option<bool>,
+    destination: Option<ExportDestination>,
+    include_comparisons: bool,
+    anonymize: Option<AnonymizeOptions>,
+) -> Result<String, PerfSightError> {
+    let root = normalize_folder_path(&folder_path);
+    let ids = db.report_ids_in_folder(&root, include_nested)?;
+    if ids.is_empty() {
+        return Err(PerfSightError::invalid_input("folder_path", "folder contains no reports"));
+    }
+
+    let folder_name = if root.is_empty() { "All".to_string() } else { safe_slug(&root, 60) };
+    let default_name = format!("PerfSight_Folder_{}_{}", folder_name, Utc::now().format("%Y%m%d_%H%M%S"));
+    let path = resolve_export_path(app_handle, destination.as_ref(), filename, &default_name, "zip", overwrite)?;
+
+    let file = std::fs::File::create(&path).map_err(|e| artifacts::path_io_error(&path, e))?;
+    let mut zip = ZipWriter::new(file);
+    let opts = FileOptions::<()>::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .unix_permissions(0o644);
+
+    let mut anonymizer = anonymize.map(Anonymizer::new);
+    let anonymizing = anonymizer.is_some();
+    let mut manifest: Vec<Value> = Vec::new();
+    let total = ids.len();
+    let mut failure = None;
+    let exported_at = Utc::now().to_rfc3339();
+    // Datasets are built in parallel and written here in report order.
+    parallel::for_each_ordered(
+        &ids,
+        Settings::load(db).report_workers(),
+        |id| prepare_report_dataset(db, *id, false, anonymizing, &exported_at),
+        |i, prepared| {
+            let zipped = match prepared.and_then(|p| write_prepared_dataset(&mut zip, opts, p, anonymizer.as_mut())) {
+                Ok(z) => z,
+                Err(e) => {
+                    failure = Some(e);
+                    return false;
+                }
+            };
+            manifest.push(json!({
+                "report_id": ids[i],
+                "title": zipped.title,
+                "created_at": zipped.created_at,
+                "has_pdf": false,
+                "folder_path": zipped.folder_path,
+                "entry": zipped.folder,
+            }));
+            let _ = app_handle.emit(
+                "folder-bundle-progress",
+                BundleProgress { phase: "export", done: i + 1, total, report_id: ids[i] },
+            );
+            true
+        },
+    );
+    if let Some(e) = failure {
+        return Err(e);
+    }
+
+    // Explicit folders count even when empty, so the import can recreate the whole tree.
+    let in_scope = |p: &str| {
+        if include_nested {
+            root.is_empty() || p == root || p.starts_with(&format!("{}/", root))
+        } else {
+            p == root
+        }
+    };
+    let mut folders: Vec<String> = db
+        .list_folder_paths()?
+        .into_iter()
+        .map(|f| f.path)
+        .filter(|p| !p.is_empty() && in_scope(p))
+        .collect();
+    if include_comparisons {
+        zip_bundle_comparisons(&mut zip, opts, db, &ids, anonymizer.as_mut())?;
+    }
+    let root = match anonymizer.as_mut() {
+        Some(anon) => {
+            folders = folders.iter().map(|f| anon.folder(f)).collect();
+            anon.folder(&root)
+        }
+        None => root,
+    };
+    zip.start_file("folders.json", opts).map_err(|e| e.to_string())?;
+    zip.write_all(
+        serde_json::to_string_pretty(&json!({
+            "schema_version": BUNDLE_SCHEMA_VERSION,
+            "root": root,
+            "include_nested": include_nested,
+            "folders": folders,
+        }))?
+        .as_bytes(),
+    )?;
+
+    zip.start_file("manifest.json", opts).map_err(|e| e.to_string())?;
+    zip.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+    zip.finish().map_err(|e| e.to_string())?;
+    if let Some(anon) = &anonymizer {
+        write_anonymization_map(&path, anon)?;
+    }
+    artifacts::path_string(&path)
+}
+
+#[derive(Debug, Serialize)]
+pub struct FolderBundleImportResult {
+    pub imported_ids: Vec<i64>,
+    pub folders_created: Vec<String>,
+    /// One entry per manifest item, in order.
+    pub items: Vec<ImportItemResult>,
+    /// Comparisons recreated from comparisons.json.
+    pub comparisons_created: Vec<i64>,
+    /// Comparisons that were skipped, and why.
+    pub warnings: Vec<String>,
+}
+
+/// Import a zip written by `export_folder_bundle_zip` or `export_reports_bundle_zip`: recreates the
+/// recorded folders (folder bundles only) and puts each report back into its folder, then restores
+/// bundled comparisons. `target_parent` optionally nests everything under another folder.
+#[tauri::command]
+pub async fn import_folder_bundle(
+    app_handle: AppHandle,
+    zip_path: String,
+    target_parent: Option<String>,
+    allow_duplicates: Option<bool>,
+) -> Result<FolderBundleImportResult, PerfSightError> {
+    run_blocking(&app_handle, move |app_handle, db| {
+        import_folder_bundle_blocking(app_handle, db, zip_path, target_parent, allow_duplicates)
+    })
+    .await
+}
+
+fn import_folder_bundle_blocking<R: tauri::Runtime>(
+    app_handle: &AppHandle<R>,
+    db: &Database,
+    zip_path: String,
+    target_parent: Option<String>,
+    allow_duplicates: Option<bool>,
+) -> Result<FolderBundleImportResult, PerfSightError> {
+    let max_bytes = Settings::load(db).max_artifact_bytes();
+    let zip_path = std::path::PathBuf::from(zip_path.trim());
+    artifacts::check_file_size(&zip_path, max_bytes, "zip_path")?;
+    let file = std::fs::File::open(&zip_path)?;
+    let mut archive = zip::ZipArchive::new(file)
+        .map_err(|e| PerfSightError::invalid_input("zip_path", e.to_string()))?;
+    // Entries are limited on their decompressed size too, so a small zip can't expand unbounded.
+    let mut read_entry = |name: &str| -> Result<Option<String>, PerfSightError> {
+        let entry = match archive.by_name(name) {
+            Ok(e) => e,
+            Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+            Err(e) => return Err(PerfSightError::invalid_input("zip_path", e.to_string())),
+        };
+        artifacts::read_to_string_limited(entry, max_bytes, name).map(Some)
+    };
+
+    // Reports bundles have no folders.json; their reports keep the folder stored in their meta.
+    let folders_v: Value = match read_entry("folders.json")? {
+        Some(folders_json) => serde_json::from_str(&folders_json)
+            .map_err(|e| PerfSightError::invalid_input("folders.json", e.to_string()))?,
+        None => json!({}),
+    };
+    let manifest_json = read_entry("manifest.json")?
+        .ok_or_else(|| PerfSightError::invalid_input("zip_path", "missing manifest.json"))?;
+    let comparisons_json = read_entry("comparisons.json")?;
+    let manifest: Vec<Value> = serde_json::from_str(&manifest_json)
+        .map_err(|e| PerfSightError::invalid_input("manifest.json", e.to_string()))?;
+
+    let parent = normalize_folder_path(target_parent.as_deref().unwrap_or(""));
+    let place = |p: &str| -> String {
+        let p = normalize_folder_path(p);
+        match (parent.is_empty(), p.is_empty()) {
+            (true, _) => p,
+            (false, true) => parent.clone(),
+            (false, false) => format!("{}/{}", parent, p),
+        }
+    };
+
+    let mut folders_created = Vec::new();
+    let recorded = folders_v.get("folders").and_then(|f| f.as_array()).cloned().unwrap_or_default();
+    for f in recorded.iter().filter_map(|f| f.as_str()).map(place).chain(std::iter::once(parent.clone())) {
+        if !f.is_empty() && !folders_created.contains(&f) {
+            db.ensure_folder_path(&f)?;
+            folders_created.push(f);
+        }
+    }
+
+    let total = manifest.len();
+    let mut imported_ids = Vec::new();
+    let mut items = Vec::new();
+    for (i, item) in manifest.iter().enumerate() {
+        let title = item.get("title").and_then(|t| t.as_str()).unwrap_or("").to_string();
+        let mut load = || -> Result<dataset::ParsedDataset, PerfSightError> {
+            let (Some(entry), Some(old_id)) =
+                (item.get("entry").and_then(|e| e.as_str()), item.get("report_id").and_then(|x| x.as_i64()))
+            else {
+                return Err(PerfSightError::invalid_input("manifest.json", format!("entry {} has no dataset reference", i)));
+            };
+            let created_id = item
+                .get("created_at")
+                .and_then(|c| c.as_str())
+                .map(compact_time_id)
+                .unwrap_or_default();
+            let dataset_json = read_entry(&format!("{}/dataset_{}_{}.json", entry, old_id, created_id))?
+                .ok_or_else(|| PerfSightError::invalid_input("zip_path", format!("missing dataset for report {}", old_id)))?;
+            let dataset: Value = serde_json::from_str(&dataset_json)
+                .map_err(|e| PerfSightError::invalid_input("dataset", e.to_string()))?;
+            let mut parsed = dataset::read_dataset(dataset)?;
+            let recorded_folder = item
+                .get("folder_path")
+                .and_then(|f| f.as_str())
+                .map(|f| f.to_string())
+                .unwrap_or_else(|| parsed.report.meta.folder_path());
+            parsed.report.meta.set_folder_path(&place(&recorded_folder));
+            Ok(parsed)
+        };
+        // One bad entry doesn't stop the rest of the bundle.
+        let result = match load() {
+            Ok(parsed) => ImportItemResult {
+                compat_warning: parsed.warning,
+                ..import_report_deduped(db, &parsed.report, allow_duplicates.unwrap_or(false))
+            },
+            Err(e) => ImportItemResult {
+                title,
+                status: ImportStatus::Failed,
+                report_id: None,
+                error: Some(e.to_string()),
+                compat_warning: None,
+            },
+        };
+        if result.status == ImportStatus::Imported {
+            imported_ids.extend(result.report_id);
+        }
+        let _ = app_handle.emit(
+            "folder-bundle-progress",
+            BundleProgress { phase: "import", done: i + 1, total, report_id: result.report_id.unwrap_or(0) },
+        );
+        items.push(result);
+    }
+
+    let (comparisons_created, warnings) = match comparisons_json {
+        Some(json) => restore_bundle_comparisons(db, &json)?,
+        None => (Vec::new(), Vec::new()),
+    };
+    for w in &warnings {
+        log_warn!("import_folder_bundle: {}", w);
+    }
+
+    Ok(FolderBundleImportResult { imported_ids, folders_created, items, comparisons_created, warnings })
+}
+
+/// Cancel flag of the running `export_reports_bundle_zip`, checked between reports.
+#[derive(Clone, Default)]
+pub struct BundleExports {
+    cancel: Arc<AtomicBool>,
+}
+
+impl BundleExports {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Payload of the "bundle-export-progress" event, sent after each report is written.
+#[derive(Debug, Clone, Serialize)]
+pub struct BundleExportProgress {
+    /// 1-based position of the report just written.
+    pub index: usize,
+    pub total: usize,
+    pub report_id: i64,
+    /// Uncompressed bytes of dataset and PDF entries written so far.
+    pub bytes_written: u64,
+}
+
+/// Stop the running `export_reports_bundle_zip` after its current report; it then fails with
+/// kind "cancelled" and removes the partial zip.
+#[tauri::command]
+pub fn cancel_bundle_export(exports: State<'_, BundleExports>) {
+    exports.cancel.store(true, Ordering::SeqCst);
+}
+
+/// Export reports (with optional PDFs) into one zip. Unless `include_comparisons` is false, saved
+/// comparisons that use any of the reports go into comparisons.json. Emits
+/// "bundle-export-progress" after each report; see `cancel_bundle_export`. `anonymize` works as in
+/// `export_report_dataset`; PDFs can't be anonymized, so items with one are refused then, and
+/// collection presets are left out.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn export_reports_bundle_zip(
+    app_handle: AppHandle,
+    exports: State<'_, BundleExports>,
+    items: Vec<ExportBundleItemV1>,
+    filename: Option<String>,
+    overwrite: Option<bool>,
+    destination: Option<ExportDestination>,
+    include_comparisons: Option<bool>,
+    anonymize: Option<AnonymizeOptions>,
+) -> Result<String, PerfSightError> {
+    let cancel = exports.cancel.clone();
+    cancel.store(false, Ordering::SeqCst);
+    run_blocking(&app_handle, move |app_handle, db| {
+        export_reports_bundle_zip_blocking(
+            app_handle,
+            db,
+            &cancel,
+            items,
+            filename,
+            overwrite,
+            destination,
+            include_comparisons.unwrap_or(true),
+            anonymize,
+        )
+    })
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
+fn export_reports_bundle_zip_blocking(
+    app_handle: &AppHandle,
+    db: &Database,
+    cancel: &AtomicBool,
+    items: Vec<ExportBundleItemV1>,
+    filename: Option<String>,
+    overwrite: Option<bool>,
+    destination: Option<ExportDestination>,
+    include_comparisons: bool,
+    anonymize: Option<AnonymizeOptions>,
+) -> Result<String, PerfSightError> {
+    if items.is_empty() {
+        return Err(PerfSightError::invalid_input("ids", "no reports selected"));
+    }
+    if anonymize.is_some() && items.iter().any(|i| i.pdf_path.is_some() || i.pdf_base64.is_some()) {
+        return Err(PerfSightError::invalid_input("anonymize", "PDFs can't be anonymized; export without them"));
+    }
+    let mut anonymizer = anonymize.map(Anonymizer::new);
+    let max_bytes = Settings::load(db).max_artifact_bytes();
+
+    let default_name = format!("PerfSight_Reports_Export_{}", Utc::now().format("%Y%m%d_%H%M%S"));
+    let path = resolve_export_path(app_handle, destination.as_ref(), filename, &default_name, "zip", overwrite)?;
+
+    let file = std::fs::File::create(&path).map_err(|e| artifacts::path_io_error(&path, e))?;
+    let written = write_reports_bundle(
+        db,
+        cancel,
+        file,
+        items,
+        max_bytes,
+        include_comparisons,
+        anonymizer.as_mut(),
+        &Utc::now().to_rfc3339(),
+        |progress| {
+            let _ = app_handle.emit("bundle-export-progress", progress);
+        },
+    );
+    if written.is_err() {
+        let _ = std::fs::remove_file(&path);
+    }
+    written?;
+    if let Some(anon) = &anonymizer {
+        write_anonymization_map(&path, anon)?;
+    }
+    artifacts::path_string(&path)
+}
+
+// Write the bundle zip of `items` to `file`, calling `progress` after each report.
+#[allow(clippy::too_many_arguments)]
+fn write_reports_bundle<W: Write + std::io::Seek>(
+    db: &Database,
+    cancel: &AtomicBool,
+    file: W,
+    items: Vec<ExportBundleItemV1>,
+    max_bytes: u64,
+    include_comparisons: bool,
+    mut anonymizer: Option<&mut Anonymizer>,
+    exported_at: &str,
+    mut progress: impl FnMut(BundleExportProgress),
+) -> Result<(), PerfSightError> {
+    let mut zip = ZipWriter::new(file);
+    let opts = FileOptions::<()>::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .unix_permissions(0o644);
+
+    let mut manifest: Vec<Value> = Vec::new();
+    let report_ids: Vec<i64> = items.iter().map(|i| i.report_id).collect();
+    let total = items.len();
+    let mut bytes_written = 0u64;
+    let anonymizing = anonymizer.is_some();
+    let has_pdf = |item: &ExportBundleItemV1| item.pdf_path.is_some() || item.pdf_base64.is_some();
+
+    // Datasets are built in parallel; entries are written here, in item order, so the zip
+    // matches a sequential export.
+    let mut failure = None;
+    parallel::for_each_ordered(
+        &items,
+        Settings::load(db).report_workers(),
+        |item| {
+            if cancel.load(Ordering::SeqCst) {
+                return Err(PerfSightError::Cancelled);
+            }
+            prepare_report_dataset(db, item.report_id, has_pdf(item), anonymizing, exported_at)
+        },
+        |i, prepared| {
+            let item = &items[i];
+            let written = prepared.and_then(|p| {
+                if cancel.load(Ordering::SeqCst) {
+                    return Err(PerfSightError::Cancelled);
+                }
+                write_bundle_item(&mut zip, opts, item, p, max_bytes, anonymizer.as_deref_mut())
+            });
+            let (entry, bytes) = match written {
+                Ok(x) => x,
+                Err(e) => {
+                    failure = Some(e);
+                    return false;
+                }
+            };
+            bytes_written += bytes;
+            manifest.push(entry);
+            progress(BundleExportProgress { index: i + 1, total, report_id: item.report_id, bytes_written });
+            true
+        },
+    );
+    if let Some(e) = failure {
+        return Err(e);
+    }
+
+    if include_comparisons {
+        zip_bundle_comparisons(&mut zip, opts, db, &report_ids, anonymizer.as_deref_mut())?;
+    }
+
+    // Presets travel with exports so a machine migration keeps them (see import_collection_presets).
+    // An anonymized bundle is for sharing, not migrating, so they stay home.
+    let presets = if anonymizer.is_some() { Vec::new() } else { db.list_collection_presets()? };
+    if !presets.is_empty() {
+        zip.start_file("presets.json", opts).map_err(|e| e.to_string())?;
+        zip.write_all(serde_json::to_string_pretty(&presets)?.as_bytes())?;
+    }
+
+    zip.start_file("manifest.json", opts).map_err(|e| e.to_string())?;
+    zip.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+
+    zip.finish().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// Write one bundle item: its dataset and, when it has one, its PDF. Returns the manifest entry and
+// the uncompressed bytes written.
+fn write_bundle_item<W: Write + std::io::Seek>(
+    zip: &mut ZipWriter<W>,
+    opts: FileOptions<()>,
+    item: &ExportBundleItemV1,
+    prepared: PreparedDataset,
+    max_bytes: u64,
+    anonymizer: Option<&mut Anonymizer>,
+) -> Result<(Value, u64), PerfSightError> {
+    let has_pdf = item.pdf_path.is_some() || item.pdf_base64.is_some();
+    let zipped = write_prepared_dataset(zip, opts, prepared, anonymizer)?;
+    let mut bytes = zipped.bytes;
+    if has_pdf {
+        let pdf_path = format!("{}/report_{}_{}.pdf", zipped.folder, item.report_id, zipped.created_id);
+        zip.start_file(pdf_path, opts).map_err(|e| e.to_string())?;
+        let mut counter = CountingWriter { inner: zip, bytes: 0 };
+        if let Some(src) = item.pdf_path.as_deref() {
+            let src = std::path::Path::new(src.trim());
+            artifacts::check_file_size(src, max_bytes, "pdf_path")?;
+            artifacts::copy_limited(std::fs::File::open(src)?, &mut counter, max_bytes, "pdf_path")?;
+        } else if let Some(pdf_b64_raw) = item.pdf_base64.as_deref() {
+            artifacts::decode_base64_to_writer(pdf_b64_raw, &mut counter, max_bytes, "pdf_base64")?;
+        }
+        bytes += counter.bytes;
+    }
+    let entry = json!({
+        "report_id": item.report_id,
+        "title": zipped.title,
+        "created_at": zipped.created_at,
+        "has_pdf": has_pdf,
+        "entry": zipped.folder,
+    });
+    Ok((entry, bytes))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStatus {
+    Imported,
+    SkippedDuplicate,
+    Failed,
+}
+
+/// Outcome of importing one report. `report_id` is the new report, or the existing one for a
+/// skipped duplicate.
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportItemResult {
+    pub title: String,
+    pub status: ImportStatus,
+    pub report_id: Option<i64>,
+    pub error: Option<String>,
+    /// Set when the dataset came from a newer build and some of its fields were not imported.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compat_warning: Option<DatasetCompatWarning>,
+}
+
+// Import one report unless an identical one (see `content_hash`) already exists.
+fn import_report_deduped(db: &Database, report: &ReportDetail, allow_duplicates: bool) -> ImportItemResult {
+    let result = (|| -> Result<(ImportStatus, i64), PerfSightError> {
+        if !allow_duplicates {
+            if let Some(existing) =
+                db.find_duplicate_report(&report.created_at, &report.title, &report.metrics, &report.meta)?
+            {
+                return Ok((ImportStatus::SkippedDuplicate, existing));
+            }
         }
+        let id = db.import_report(&report.created_at, &report.title, &report.metrics, &report.meta)?;
+        Ok((ImportStatus::Imported, id))
+    })();
+    match result {
+        Ok((status, id)) => ImportItemResult {
+            title: report.title.clone(),
+            status,
+            report_id: Some(id),
+            error: None,
+            compat_warning: None,
+        },
+        Err(e) => ImportItemResult {
+            title: report.title.clone(),
+            status: ImportStatus::Failed,
+            report_id: None,
+            error: Some(e.to_string()),
+            compat_warning: None,
+        },
     }
-    if out.is_empty() { "unknown_time".to_string() } else { out }
 }
 
+/// Import a single dataset. An identical report is not imported again unless `allow_duplicates`.
 #[tauri::command]
-pub fn export_report_dataset(
+pub async fn import_report_dataset(
     app_handle: AppHandle,
-    db: State<'_, Database>,
-    report_id: i64
-) -> Result<String, String> {
-    let report = db.get_report_detail(report_id).map_err(|e| e.to_string())?;
-    let dataset = ReportDatasetV1 {
-        schema_version: 1,
-        exported_at: Utc::now().to_rfc3339(),
-        report,
-    };
-    let json_str = serde_json::to_string_pretty(&dataset).map_err(|e| e.to_string())?;
+    dataset_json: String,
+    allow_duplicates: Option<bool>,
+) -> Result<ImportItemResult, PerfSightError> {
+    run_blocking(&app_handle, move |_, db| {
+        import_report_dataset_blocking(db, dataset_json, allow_duplicates)
+    })
+    .await
+}
 
-    let mut dir = app_handle.path().resolve("", BaseDirectory::Download).ok();
-    if dir.is_none() {
-        dir = app_handle.path().app_local_data_dir().ok();
-    }
-    let dir = dir.ok_or("Failed to resolve output directory")?;
-    if !dir.exists() {
-        std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+fn import_report_dataset_blocking(
+    db: &Database,
+    dataset_json: String,
+    allow_duplicates: Option<bool>,
+) -> Result<ImportItemResult, PerfSightError> {
+    artifacts::check_payload_size(&dataset_json, Settings::load(db).max_artifact_bytes(), "dataset_json")?;
+    // Accept either pretty json or wrapped dataset.
+    let v: Value = serde_json::from_str(&dataset_json)
+        .map_err(|e| PerfSightError::invalid_input("dataset_json", e.to_string()))?;
+    let parsed = dataset::read_dataset(v)?;
+
+    // Preserve original created_at/title/metrics/meta. (analysis will be recomputed on read)
+    let item = import_report_deduped(db, &parsed.report, allow_duplicates.unwrap_or(false));
+    match (item.status, &item.error) {
+        (ImportStatus::Failed, Some(e)) => Err(PerfSightError::Internal(e.clone())),
+        _ => Ok(ImportItemResult { compat_warning: parsed.warning, ..item }),
     }
-    let filename = format!("PerfSight_Report_{}_Dataset.json", report_id);
-    let path = dir.join(filename);
-    std::fs::write(&path, json_str.as_bytes()).map_err(|e| e.to_string())?;
-    Ok(path.to_string_lossy().to_string())
 }
 
+/// Import an externally captured CSV time series (psrecord, atop, ...) as a report.
 #[tauri::command]
-pub fn export_reports_bundle_zip(
+pub async fn import_csv_report(
     app_handle: AppHandle,
-    db: State<'_, Database>,
-    items: Vec<ExportBundleItemV1>,
-    filename: Option<String>,
-) -> Result<String, String> {
-    if items.is_empty() {
-        return Err("No reports selected".to_string());
-    }
+    path: String,
+    mapping: CsvImportMapping,
+) -> Result<CsvImportResult, PerfSightError> {
+    run_blocking(&app_handle, move |_, db| {
+        import_csv_report_blocking(db, path, mapping)
+    })
+    .await
+}
 
-    let mut dir = app_handle.path().resolve("", BaseDirectory::Download).ok();
-    if dir.is_none() {
-        dir = app_handle.path().app_local_data_dir().ok();
-    }
-    let dir = dir.ok_or("Failed to resolve output directory")?;
-    if !dir.exists() {
-        std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+fn import_csv_report_blocking(
+    db: &Database,
+    path: String,
+    mapping: CsvImportMapping,
+) -> Result<CsvImportResult, PerfSightError> {
+    let path = std::path::PathBuf::from(path.trim());
+    artifacts::check_file_size(&path, Settings::load(db).max_artifact_bytes(), "path")?;
+    let content = std::fs::read_to_string(&path)?;
+    let parsed = csv_import::parse_csv(&content, &mapping)?;
+    if parsed.metrics.is_empty() {
+        return Err(PerfSightError::invalid_input(
+            "content",
+            format!("no valid rows found ({} skipped)", parsed.skipped_rows),
+        ));
     }
 
-    let name = filename
-        .and_then(|s| {
-            let t = s.trim().to_string();
-            if t.is_empty() { None } else { Some(t) }
-        })
-        .unwrap_or_else(|| format!("PerfSight_Reports_Export_{}.zip", Utc::now().format("%Y%m%d_%H%M%S")));
-    let path = dir.join(name);
-
-    let file = std::fs::File::create(&path).map_err(|e| e.to_string())?;
-    let mut zip = ZipWriter::new(file);
-    let opts = FileOptions::<()>::default()
-        .compression_method(zip::CompressionMethod::Deflated)
-        .unix_permissions(0o644);
-
-    let mut manifest: Vec<Value> = Vec::new();
-
-    for item in items {
-        let report = db.get_report_detail(item.report_id).map_err(|e| e.to_string())?;
-        let title = report.title.clone();
-        let created_at = report.created_at.clone();
-        let created_id = compact_time_id(&created_at);
-        let dataset = ReportDatasetV1 {
-            schema_version: 1,
-            exported_at: Utc::now().to_rfc3339(),
-            report,
-        };
-        let json_str = serde_json::to_string_pretty(&dataset).map_err(|e| e.to_string())?;
-
-        let folder = format!(
-            "{}_{}_{}",
-            created_id,
-            item.report_id,
-            safe_slug(&title, 60)
-        );
-
-        let dataset_path = format!("{}/dataset_{}_{}.json", folder, item.report_id, created_id);
-        zip.start_file(dataset_path, opts).map_err(|e| e.to_string())?;
-        zip.write_all(json_str.as_bytes()).map_err(|e| e.to_string())?;
+    let source_file = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let title = mapping
+        .title
+        .as_deref()
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| format!("Imported - {}", source_file));
+    let meta = csv_import::build_meta(&source_file, &mapping, &parsed.metrics, parsed.skipped_rows);
+    let report_id = db
+        .import_report(&Utc::now().to_rfc3339(), &title, &parsed.metrics, &meta)?;
+    Ok(CsvImportResult {
+        report_id,
+        imported_rows: parsed.imported_rows,
+        skipped_rows: parsed.skipped_rows,
+    })
+}
 
-        let has_pdf = item.pdf_base64.as_ref().is_some();
-        if let Some(pdf_b64_raw) = item.pdf_base64 {
-            let bytes = decode_base64_maybe_data_url(&pdf_b64_raw)?;
-            let pdf_path = format!("{}/report_{}_{}.pdf", folder, item.report_id, created_id);
-            zip.start_file(pdf_path, opts).map_err(|e| e.to_string())?;
-            zip.write_all(&bytes).map_err(|e| e.to_string())?;
+/// Turn a run's stream file (`CollectionConfig::stream_to_file`) into a report, e.g. after a crash.
+#[tauri::command]
+pub async fn import_jsonl_report(
+    app_handle: AppHandle,
+    path: String,
+    title: Option<String>,
+    folder_path: Option<String>,
+) -> Result<CsvImportResult, PerfSightError> {
+    run_blocking(&app_handle, move |_, db| {
+        let path = std::path::PathBuf::from(path.trim());
+        artifacts::check_file_size(&path, Settings::load(db).max_artifact_bytes(), "path")?;
+        let content = std::fs::read_to_string(&path)?;
+        let parsed = csv_import::parse_jsonl(&content);
+        if parsed.metrics.is_empty() {
+            return Err(PerfSightError::invalid_input(
+                "content",
+                format!("no valid lines found ({} skipped)", parsed.skipped_rows),
+            ));
         }
+        let source_file = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let title = title
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("Imported - {}", source_file));
+        let meta = csv_import::build_jsonl_meta(&source_file, folder_path.as_deref(), &parsed.metrics, parsed.skipped_rows);
+        let report_id = db.import_report(&Utc::now().to_rfc3339(), &title, &parsed.metrics, &meta)?;
+        Ok(CsvImportResult {
+            report_id,
+            imported_rows: parsed.imported_rows,
+            skipped_rows: parsed.skipped_rows,
+        })
+    })
+    .await
+}
 
-        manifest.push(json!({
-            "report_id": item.report_id,
-            "title": title,
-            "created_at": created_at,
-            "has_pdf": has_pdf,
-        }));
-    }
+/// Attach a Chrome DevTools Performance trace to an existing report: derived main-thread totals
+/// (long tasks, scripting, layout, paint) restricted to the report window go into
+/// `meta.extra.trace_summary`; with `add_points` they are also added as per-second
+/// `trace:*` custom metrics. Re-importing replaces the previous summary and points.
+#[tauri::command]
+pub async fn import_trace_summary(
+    app_handle: AppHandle,
+    report_id: i64,
+    trace_path: String,
+    pid: Option<u32>,
+    add_points: Option<bool>,
+    trace_started_at: Option<String>,
+    force: Option<bool>,
+) -> Result<TraceSummary, PerfSightError> {
+    run_blocking(&app_handle, move |_, db| {
+        import_trace_summary_blocking(db, report_id, trace_path, pid, add_points, trace_started_at, force)
+    })
+    .await
+}
 
-    zip.start_file("manifest.json", opts).map_err(|e| e.to_string())?;
-    zip.write_all(serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?.as_bytes())
-        .map_err(|e| e.to_string())?;
+fn import_trace_summary_blocking(
+    db: &Database,
+    report_id: i64,
+    trace_path: String,
+    pid: Option<u32>,
+    add_points: Option<bool>,
+    trace_started_at: Option<String>,
+    force: Option<bool>,
+) -> Result<TraceSummary, PerfSightError> {
+    ensure_unlocked(db, &[report_id], force)?;
+    let started_at = trace_started_at
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            DateTime::parse_from_rfc3339(s)
+                .map(|d| d.with_timezone(&Utc))
+                .map_err(|e| PerfSightError::invalid_input("trace_started_at", e.to_string()))
+        })
+        .transpose()?;
+    let path = std::path::PathBuf::from(trace_path.trim());
+    artifacts::check_file_size(&path, Settings::load(db).max_artifact_bytes(), "trace_path")?;
+    let file = std::fs::File::open(&path).map_err(|e| artifacts::path_io_error(&path, e))?;
+    let trace = trace_import::parse_trace(std::io::BufReader::new(file), started_at)?;
 
-    zip.finish().map_err(|e| e.to_string())?;
-    Ok(path.to_string_lossy().to_string())
-}
+    let report = db.get_report_detail(report_id)?;
+    let mut metrics = report.metrics;
+    trace_import::strip_trace_metrics(&mut metrics);
+    let (Some(from), Some(to)) = (metrics.first().map(|b| b.timestamp), metrics.last().map(|b| b.timestamp)) else {
+        return Err(PerfSightError::invalid_input("report_id", "report has no samples"));
+    };
+    let source_file = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let mut summary = trace.summarize(&source_file, from, to);
+    if trace.buckets.iter().all(|(t, _)| *t < from || *t > to) {
+        return Err(PerfSightError::invalid_input(
+            "trace_started_at",
+            "trace does not overlap the report window; check the trace start time",
+        ));
+    }
 
-#[tauri::command]
-pub fn import_report_dataset(
-    db: State<'_, Database>,
-    dataset_json: String
-) -> Result<i64, String> {
-    // Accept either pretty json or wrapped dataset.
-    let v: Value = serde_json::from_str(&dataset_json).map_err(|e| format!("Invalid JSON: {e}"))?;
-    let schema_version = v.get("schema_version").and_then(|x| x.as_u64()).unwrap_or(0);
-    if schema_version != 1 {
-        return Err(format!("Unsupported dataset schema_version: {}", schema_version));
+    if add_points.unwrap_or(false) {
+        let report_pids: std::collections::BTreeSet<u32> =
+            metrics.iter().flat_map(|b| b.metrics.keys().copied()).collect();
+        let pid = match pid {
+            Some(p) if report_pids.contains(&p) => p,
+            Some(p) => return Err(PerfSightError::invalid_input("pid", format!("pid {} is not in this report", p))),
+            // The traced renderer when it was also sampled, otherwise the report's first process.
+            None => trace
+                .threads
+                .iter()
+                .map(|(p, _)| *p as u32)
+                .find(|p| report_pids.contains(p))
+                .or_else(|| report_pids.iter().next().copied())
+                .unwrap_or(0),
+        };
+        let points = trace.custom_batches(pid, from, to);
+        summary.points_added = points.len();
+        metrics.extend(points);
+        metrics.sort_by_key(|b| b.timestamp);
     }
-    let report_v = v.get("report").ok_or("Missing report field")?;
-    let report: ReportDetail = serde_json::from_value(report_v.clone()).map_err(|e| e.to_string())?;
 
-    // Preserve original created_at/title/metrics/meta. (analysis will be recomputed on read)
-    let new_id = db
-        .import_report(&report.created_at, &report.title, &report.metrics, &report.meta)
-        .map_err(|e| e.to_string())?;
-    Ok(new_id)
+    let mut meta = report.meta;
+    meta.extra.insert("trace_summary".to_string(), serde_json::to_value(&summary)?);
+    db.update_report_samples(report_id, &metrics, &meta)?;
+    Ok(summary)
 }
 
 /// Import a comparison bundle (multiple reports + context)
 /// Returns mapping from old IDs to new IDs and the comparison context
 #[tauri::command]
-pub fn import_comparison_bundle(
-    db: State<'_, Database>,
-    bundle_json: String
-) -> Result<Value, String> {
-    let v: Value = serde_json::from_str(&bundle_json).map_err(|e| format!("Invalid JSON: {e}"))?;
+pub async fn import_comparison_bundle(
+    app_handle: AppHandle,
+    bundle_json: String,
+    allow_duplicates: Option<bool>,
+) -> Result<Value, PerfSightError> {
+    run_blocking(&app_handle, move |_, db| {
+        import_comparison_bundle_blocking(db, bundle_json, allow_duplicates)
+    })
+    .await
+}
+
+fn import_comparison_bundle_blocking(
+    db: &Database,
+    bundle_json: String,
+    allow_duplicates: Option<bool>,
+) -> Result<Value, PerfSightError> {
+    artifacts::check_payload_size(&bundle_json, Settings::load(db).max_artifact_bytes(), "bundle_json")?;
+    let v: Value = serde_json::from_str(&bundle_json)
+        .map_err(|e| PerfSightError::invalid_input("bundle_json", e.to_string()))?;
     let schema_version = v.get("schema_version").and_then(|x| x.as_u64()).unwrap_or(0);
     if schema_version != 1 {
-        return Err(format!("Unsupported bundle schema_version: {}", schema_version));
+        return Err(PerfSightError::invalid_input(
+            "schema_version",
+            format!("unsupported bundle schema_version {}", schema_version),
+        ));
     }
     let bundle_type = v.get("bundle_type").and_then(|x| x.as_str()).unwrap_or("");
     if bundle_type != "comparison" {
-        return Err(format!("Expected bundle_type 'comparison', got '{}'", bundle_type));
+        return Err(PerfSightError::invalid_input(
+            "bundle_type",
+            format!("expected 'comparison', got '{}'", bundle_type),
+        ));
     }
-    let reports_v = v.get("reports").ok_or("Missing reports array")?;
-    let reports_arr = reports_v.as_array().ok_or("reports is not an array")?;
+    let reports_arr = v
+        .get("reports")
+        .and_then(|r| r.as_array())
+        .ok_or_else(|| PerfSightError::invalid_input("reports", "missing or not an array"))?;
     if reports_arr.len() < 2 {
-        return Err("Bundle must contain at least 2 reports".to_string());
+        return Err(PerfSightError::invalid_input("reports", "bundle must contain at least 2 reports"));
     }
 
     let mut id_mapping: std::collections::HashMap<i64, i64> = std::collections::HashMap::new();
+    // Report ids used by the comparison: new ones, or existing ones for skipped duplicates.
     let mut imported_ids: Vec<i64> = Vec::new();
+    let mut items: Vec<ImportItemResult> = Vec::new();
 
     for report_v in reports_arr {
-        let report: ReportDetail = serde_json::from_value(report_v.clone()).map_err(|e| e.to_string())?;
+        let report: ReportDetail = serde_json::from_value(report_v.clone())
+            .map_err(|e| PerfSightError::invalid_input("reports", e.to_string()))?;
         let original_id = report_v.get("id").and_then(|x| x.as_i64()).unwrap_or(0);
         
-        // Import the report
-        let new_id = db
-            .import_report(&report.created_at, &report.title, &report.metrics, &report.meta)
-            .map_err(|e| e.to_string())?;
+        // Import the report (a comparison needs every report, so a failure aborts)
+        let item = import_report_deduped(db, &report, allow_duplicates.unwrap_or(false));
+        let new_id = match (item.status, item.report_id) {
+            (ImportStatus::Failed, _) | (_, None) => {
+                return Err(PerfSightError::Internal(item.error.unwrap_or_default()));
+            }
+            (_, Some(id)) => id,
+        };
         
         id_mapping.insert(original_id, new_id);
         imported_ids.push(new_id);
+        items.push(item);
     }
 
     // Map comparison context IDs
@@ -426,11 +3411,22 @@ pub fn import_comparison_bundle(
 
     let cpu_selections = map_selections(comparison_context.get("cpu_selections_by_id"));
     let mem_selections = map_selections(comparison_context.get("mem_selections_by_id"));
+    // Older bundles have no metric selection; an unusable one falls back to the default as well.
+    let metric_selections = comparison_context
+        .get("metric_selections")
+        .and_then(|v| serde_json::from_value::<Vec<String>>(v.clone()).ok())
+        .and_then(|m| check_metric_selections(Some(m)).ok().flatten());
 
     // Create a Comparison record from imported reports + mapped context.
     let title = v.get("title").and_then(|x| x.as_str()).unwrap_or("Imported Comparison");
     let folder_path = v.get("folder_path").and_then(|x| x.as_str()).unwrap_or("");
-    let meta = v.get("meta").cloned().unwrap_or_else(|| serde_json::json!({}));
+    let mut meta = v.get("meta").cloned().unwrap_or_else(|| serde_json::json!({}));
+    // Older bundles only carry tags in the comparison context.
+    if let Some(tags) = comparison_context.get("tags").filter(|t| !t.is_null()) {
+        if meta.is_object() && meta.get("tags").is_none() {
+            meta["tags"] = tags.clone();
+        }
+    }
     let comparison_id = db
         .create_comparison(
             title,
@@ -439,12 +3435,13 @@ pub fn import_comparison_bundle(
             baseline_new_id,
             &cpu_selections,
             &mem_selections,
+            metric_selections.as_deref(),
             &meta,
-        )
-        .map_err(|e| e.to_string())?;
+        )?;
 
     Ok(serde_json::json!({
         "imported_ids": imported_ids,
+        "items": items,
         "id_mapping": id_mapping,
         "comparison_id": comparison_id,
         "comparison": {
@@ -468,16 +3465,404 @@ pub struct CreateComparisonArgs {
     pub baseline_report_id: Option<i64>,
     pub cpu_selections_by_id: Option<Value>,
     pub mem_selections_by_id: Option<Value>,
+    /// Metrics to diff ("cpu", "memory", "custom:<name>", ...); omitted means cpu and memory.
+    #[serde(default)]
+    pub metric_selections: Option<Vec<String>>,
     pub meta: Option<Value>,
+    /// `create_comparison_checked` only: fail on compatibility warnings instead of returning them.
+    #[serde(default)]
+    pub strict: bool,
+}
+
+// Trimmed, de-duplicated comparison metric selection; it must name at least one known metric.
+fn check_metric_selections(metrics: Option<Vec<String>>) -> Result<Option<Vec<String>>, PerfSightError> {
+    let Some(metrics) = metrics else { return Ok(None) };
+    let mut out: Vec<String> = Vec::new();
+    for m in metrics.iter().map(|m| m.trim()).filter(|m| !m.is_empty()) {
+        if !out.iter().any(|o| o == m) {
+            out.push(m.to_string());
+        }
+    }
+    if out.is_empty() {
+        return Err(PerfSightError::invalid_input("metric_selections", "select at least one metric"));
+    }
+    check_metric_ids("metric_selections", &out)?;
+    Ok(Some(out))
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreateComparisonResult {
+    pub id: i64,
+    pub warnings: Vec<String>,
+}
+
+// PIDs chosen for one report in a `{report_id: [pid..]}` selection map.
+fn selected_pids(selections: Option<&Value>, report_id: i64) -> Vec<u32> {
+    selections
+        .and_then(|v| v.get(report_id.to_string()))
+        .and_then(|v| v.as_array())
+        .map(|a| a.iter().filter_map(|p| p.as_u64()).map(|p| p as u32).collect())
+        .unwrap_or_default()
+}
+
+/// One alias lined up across the reports of a comparison (stored in comparison meta under
+/// `process_mapping`).
+#[derive(Debug, Clone, Serialize)]
+pub struct AliasMapping {
+    pub alias: String,
+    /// Report id -> PIDs carrying the alias there (empty when the report has none).
+    pub pids: std::collections::BTreeMap<i64, Vec<u32>>,
+}
+
+// Align a `{report_id: [pid..]}` selection across reports by alias: a process selected under an
+// alias in any report selects the process with the same alias (case-insensitive) in every
+// report. Unaliased PIDs stay selected only where they were chosen. Returns the resolved
+// selections, the mapping and one warning per alias missing from a report; None when no
+// selected PID has an alias.
+fn map_selections_by_alias(
+    reports: &[(i64, ReportMeta)],
+    selections: Option<&Value>,
+    label: &str,
+) -> Option<(Value, Vec<AliasMapping>, Vec<String>)> {
+    let aliases: Vec<(i64, HashMap<u32, String>)> = reports.iter().map(|(id, m)| (*id, m.aliases())).collect();
+    let mut wanted: Vec<String> = Vec::new();
+    for (id, by_pid) in &aliases {
+        for pid in selected_pids(selections, *id) {
+            if let Some(alias) = by_pid.get(&pid) {
+                if !wanted.iter().any(|w| w.eq_ignore_ascii_case(alias)) {
+                    wanted.push(alias.clone());
+                }
+            }
+        }
+    }
+    if wanted.is_empty() {
+        return None;
+    }
+
+    let mut resolved = serde_json::Map::new();
+    let mut mapping: Vec<AliasMapping> =
+        wanted.iter().map(|a| AliasMapping { alias: a.clone(), pids: Default::default() }).collect();
+    let mut warnings = Vec::new();
+    for (id, by_pid) in &aliases {
+        let mut pids = selected_pids(selections, *id);
+        for m in mapping.iter_mut() {
+            let mut matched: Vec<u32> =
+                by_pid.iter().filter(|(_, a)| a.eq_ignore_ascii_case(&m.alias)).map(|(pid, _)| *pid).collect();
+            matched.sort_unstable();
+            if matched.is_empty() {
+                warnings.push(format!("Report {}: no process aliased '{}' for the {} selection", id, m.alias, label));
+            }
+            for pid in &matched {
+                if !pids.contains(pid) {
+                    pids.push(*pid);
+                }
+            }
+            m.pids.insert(*id, matched);
+        }
+        resolved.insert(id.to_string(), json!(pids));
+    }
+    Some((Value::Object(resolved), mapping, warnings))
+}
+
+/// Reasons the reports may not be meaningfully comparable (empty = compatible).
+fn comparison_warnings(reports: &[(i64, ReportMeta)], cpu: Option<&Value>, mem: Option<&Value>) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    let mut modes: Vec<&str> = reports.iter().filter_map(|(_, m)| m.mode()).collect();
+    modes.sort_unstable();
+    modes.dedup();
+    if modes.len() > 1 {
+        warnings.push(format!("Reports were collected in different modes ({})", modes.join(", ")));
+    }
+
+    let intervals: Vec<u64> = reports
+        .iter()
+        .filter_map(|(_, m)| m.collection.as_ref().and_then(|c| c.interval_ms))
+        .filter(|i| *i > 0)
+        .collect();
+    if let (Some(min), Some(max)) = (intervals.iter().min(), intervals.iter().max()) {
+        if *max > min * 2 {
+            warnings.push(format!("Sampling intervals differ by more than 2x ({}ms vs {}ms)", min, max));
+        }
+    }
+
+    let durations: Vec<u64> = reports.iter().filter_map(|(_, m)| m.duration_seconds()).filter(|d| *d > 0).collect();
+    if let (Some(min), Some(max)) = (durations.iter().min(), durations.iter().max()) {
+        if (*max - *min) * 2 > *max {
+            warnings.push(format!("Durations differ by more than 50% ({}s vs {}s)", min, max));
+        }
+    }
+
+    // OS CPU% is only comparable when every system-mode report scaled it the same way.
+    let mut normalizations: Vec<&str> = reports
+        .iter()
+        .filter(|(_, m)| m.mode().is_none_or(|mode| mode == "system"))
+        .filter_map(|(_, m)| m.cpu_normalization())
+        .map(|n| n.as_str())
+        .collect();
+    normalizations.sort_unstable();
+    normalizations.dedup();
+    if normalizations.len() > 1 {
+        warnings.push(format!(
+            "Reports used different CPU normalization ({}); OS CPU% values are not directly comparable",
+            normalizations.join(", ")
+        ));
+    }
+
+    for (id, meta) in reports {
+        let collected = meta.collection.as_ref().map(|c| c.target_pids.clone()).unwrap_or_default();
+        if collected.is_empty() {
+            continue;
+        }
+        for (label, selections) in [("CPU", cpu), ("memory", mem)] {
+            let chosen = selected_pids(selections, *id);
+            if !chosen.is_empty() && !chosen.iter().any(|p| collected.contains(p)) {
+                warnings.push(format!("Report {}: {} selection has no PIDs that were collected", id, label));
+            }
+        }
+    }
+    warnings
+}
+
+/// Like `create_comparison`, but checks that the reports exist and are comparable. Compatibility
+/// warnings are returned and stored in the comparison meta (`compatibility_warnings`); with
+/// `strict` they are an error instead. Selections of aliased processes are carried over to the
+/// same alias in every report (`process_mapping`); aliases a report lacks are reported as
+/// warnings but never fail a strict check.
+#[tauri::command]
+pub async fn create_comparison_checked(
+    app_handle: AppHandle,
+    args: CreateComparisonArgs,
+) -> Result<CreateComparisonResult, PerfSightError> {
+    run_blocking(&app_handle, move |_, db| create_comparison_checked_with(db, args)).await
+}
+
+fn create_comparison_checked_with(db: &Database, args: CreateComparisonArgs) -> Result<CreateComparisonResult, PerfSightError> {
+    let mut ids: Vec<i64> = Vec::new();
+    for id in &args.report_ids {
+        if !ids.contains(id) {
+            ids.push(*id);
+        }
+    }
+    if ids.len() < 2 {
+        return Err(PerfSightError::invalid_input("report_ids", "comparison requires at least 2 distinct reports"));
+    }
+    if let Some(baseline) = args.baseline_report_id {
+        if !ids.contains(&baseline) {
+            return Err(PerfSightError::invalid_input("baseline_report_id", "must be one of report_ids"));
+        }
+    }
+    let metric_selections = check_metric_selections(args.metric_selections)?;
+    let mut reports = Vec::with_capacity(ids.len());
+    for id in &ids {
+        let meta = db.get_report_meta(*id).map_err(|e| match PerfSightError::from(e) {
+            PerfSightError::NotFound => PerfSightError::invalid_input("report_ids", format!("report {} does not exist", id)),
+            other => other,
+        })?;
+        reports.push((*id, meta));
+    }
+
+    let mut warnings = comparison_warnings(&reports, args.cpu_selections_by_id.as_ref(), args.mem_selections_by_id.as_ref());
+    if args.strict && !warnings.is_empty() {
+        return Err(PerfSightError::invalid_input("report_ids", warnings.join("; ")));
+    }
+
+    let mut meta = args.meta.unwrap_or_else(|| json!({}));
+    if !meta.is_object() {
+        meta = json!({});
+    }
+    meta["compatibility_warnings"] = json!(warnings);
+
+    // PIDs differ between runs; line selections up by alias where the processes have one.
+    let mut selections = [args.cpu_selections_by_id, args.mem_selections_by_id];
+    let mut process_mapping = serde_json::Map::new();
+    let mut mapping_warnings = Vec::new();
+    for (key, label, sel) in [("cpu", "CPU", 0), ("mem", "memory", 1)] {
+        if let Some((resolved, mapping, w)) = map_selections_by_alias(&reports, selections[sel].as_ref(), label) {
+            selections[sel] = Some(resolved);
+            process_mapping.insert(key.to_string(), json!(mapping));
+            mapping_warnings.extend(w);
+        }
+    }
+    if !process_mapping.is_empty() {
+        meta["process_mapping"] = Value::Object(process_mapping);
+        meta["mapping_warnings"] = json!(mapping_warnings);
+        warnings.extend(mapping_warnings);
+    }
+    let [cpu_selections, mem_selections] = selections;
+    let id = db.create_comparison(
+        &args.title.unwrap_or_else(|| format!("Comparison ({})", ids.len())),
+        &ids,
+        &args.folder_path.unwrap_or_default(),
+        args.baseline_report_id,
+        &cpu_selections.unwrap_or(Value::Object(serde_json::Map::new())),
+        &mem_selections.unwrap_or(Value::Object(serde_json::Map::new())),
+        metric_selections.as_deref(),
+        &meta,
+    )?;
+    Ok(CreateComparisonResult { id, warnings })
+}
+
+/// How `create_comparison_auto` picked the baseline; stored as comparison meta `baseline_selection`.
+#[derive(Debug, Clone, Serialize)]
+pub struct BaselineSelection {
+    pub report_id: i64,
+    /// "folder_pin" or "earliest".
+    pub reason: &'static str,
+    /// Report folder whose pin was consulted.
+    pub folder_path: Option<String>,
+    pub detail: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AutoComparisonResult {
+    pub id: i64,
+    pub warnings: Vec<String>,
+    pub baseline: BaselineSelection,
+}
+
+/// Create a checked comparison without choosing a baseline: the pinned baseline of `folder_path`
+/// when it is among the reports, otherwise the earliest report. `folder_path` defaults to the
+/// folder all reports share; the comparison is saved under it.
+#[tauri::command]
+pub fn create_comparison_auto(
+    db: State<'_, Database>,
+    report_ids: Vec<i64>,
+    folder_path: Option<String>,
+    title: Option<String>,
+) -> Result<AutoComparisonResult, PerfSightError> {
+    let mut ids: Vec<i64> = Vec::new();
+    for id in &report_ids {
+        if !ids.contains(id) {
+            ids.push(*id);
+        }
+    }
+    if ids.len() < 2 {
+        return Err(PerfSightError::invalid_input("report_ids", "comparison requires at least 2 distinct reports"));
+    }
+    let placements = db.report_placements(&ids).map_err(|e| match PerfSightError::from(e) {
+        PerfSightError::NotFound => PerfSightError::invalid_input("report_ids", "a report does not exist"),
+        other => other,
+    })?;
+    let folder = match folder_path {
+        Some(p) => Some(normalize_folder_path(&p)),
+        None => {
+            let first = normalize_folder_path(&placements[0].1);
+            placements.iter().all(|(_, fp)| normalize_folder_path(fp) == first).then_some(first)
+        }
+    };
+
+    let pinned = match &folder {
+        Some(p) => db.get_folder_baseline(p)?,
+        None => None,
+    };
+    let baseline = match pinned {
+        Some(pin) if ids.contains(&pin) => BaselineSelection {
+            report_id: pin,
+            reason: "folder_pin",
+            folder_path: folder.clone(),
+            detail: format!("Report {} is the pinned baseline of the folder", pin),
+        },
+        _ => {
+            // RFC 3339 timestamps in one zone sort lexicographically; ties go to the lower id.
+            let (report_id, created_at) = ids
+                .iter()
+                .zip(&placements)
+                .map(|(id, (created_at, _))| (*id, created_at))
+                .min_by(|a, b| a.1.cmp(b.1).then(a.0.cmp(&b.0)))
+                .map(|(id, c)| (id, c.clone()))
+                .unwrap_or_default();
+            let why = match (pinned, &folder) {
+                (Some(pin), _) => format!("pinned baseline {} is not among the reports", pin),
+                (None, Some(_)) => "folder has no pinned baseline".to_string(),
+                (None, None) => "reports are in different folders".to_string(),
+            };
+            BaselineSelection {
+                report_id,
+                reason: "earliest",
+                folder_path: folder.clone(),
+                detail: format!("Earliest report (created {}); {}", created_at, why),
+            }
+        }
+    };
+
+    let result = create_comparison_checked_with(
+        &db,
+        CreateComparisonArgs {
+            title,
+            report_ids: ids,
+            folder_path: folder,
+            baseline_report_id: Some(baseline.report_id),
+            cpu_selections_by_id: None,
+            mem_selections_by_id: None,
+            metric_selections: None,
+            meta: Some(json!({ "baseline_selection": baseline })),
+            strict: false,
+        },
+    )?;
+    Ok(AutoComparisonResult { id: result.id, warnings: result.warnings, baseline })
+}
+
+/// Pin a report as the baseline of a report folder, or clear the pin (`report_id` omitted).
+/// `create_comparison_auto` prefers the pinned report; folder aggregates report it.
+#[tauri::command]
+pub fn set_folder_baseline(
+    db: State<'_, Database>,
+    folder_path: String,
+    report_id: Option<i64>,
+) -> Result<(), PerfSightError> {
+    if let Some(id) = report_id {
+        db.get_report_meta(id).map_err(|e| match PerfSightError::from(e) {
+            PerfSightError::NotFound => PerfSightError::invalid_input("report_id", format!("report {} does not exist", id)),
+            other => other,
+        })?;
+    }
+    db.set_folder_baseline(&folder_path, report_id)?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_folder_baseline(db: State<'_, Database>, folder_path: String) -> Result<Option<i64>, PerfSightError> {
+    db.get_folder_baseline(&folder_path).map_err(PerfSightError::from)
+}
+
+/// Set the collection defaults of a report folder. Runs filed into it (or a sub-folder without
+/// its own value) take each field their config leaves unset; an empty object clears them.
+#[tauri::command]
+pub fn set_folder_settings(
+    db: State<'_, Database>,
+    folder_path: String,
+    settings: FolderCollectionSettings,
+) -> Result<(), PerfSightError> {
+    if normalize_folder_path(&folder_path).is_empty() {
+        return Err(PerfSightError::invalid_input("folder_path", "the root folder has no settings; use the app settings"));
+    }
+    settings.validate()?;
+    db.set_folder_settings(&folder_path, &settings)?;
+    Ok(())
+}
+
+/// The defaults set on the folder itself, or None.
+#[tauri::command]
+pub fn get_folder_settings(
+    db: State<'_, Database>,
+    folder_path: String,
+) -> Result<Option<FolderCollectionSettings>, PerfSightError> {
+    db.get_folder_settings(&folder_path).map_err(PerfSightError::from)
+}
+
+#[tauri::command]
+pub fn delete_folder_settings(db: State<'_, Database>, folder_path: String) -> Result<usize, PerfSightError> {
+    db.set_folder_settings(&folder_path, &FolderCollectionSettings::default()).map_err(PerfSightError::from)
 }
 
 #[tauri::command]
 pub fn create_comparison(
     db: State<'_, Database>,
     args: CreateComparisonArgs,
-) -> Result<i64, String> {
+) -> Result<i64, PerfSightError> {
     if args.report_ids.len() < 2 {
-        return Err("Comparison requires at least 2 reports".to_string());
+        return Err(PerfSightError::invalid_input("report_ids", "comparison requires at least 2 reports"));
     }
     let title = args
         .title
@@ -489,6 +3874,7 @@ pub fn create_comparison(
     let mem = args
         .mem_selections_by_id
         .unwrap_or(Value::Object(serde_json::Map::new()));
+    let metric_selections = check_metric_selections(args.metric_selections)?;
     let meta = args.meta.unwrap_or_else(|| serde_json::json!({}));
     db.create_comparison(
         &title,
@@ -497,32 +3883,33 @@ pub fn create_comparison(
         args.baseline_report_id,
         &cpu,
         &mem,
+        metric_selections.as_deref(),
         &meta,
     )
-    .map_err(|e| e.to_string())
+    .map_err(PerfSightError::from)
 }
 
 #[tauri::command]
-pub fn get_comparisons(db: State<'_, Database>) -> Result<Vec<ComparisonSummary>, String> {
-    db.get_all_comparisons().map_err(|e| e.to_string())
+pub fn get_comparisons(db: State<'_, Database>, tag: Option<String>) -> Result<Vec<ComparisonSummary>, PerfSightError> {
+    db.get_all_comparisons(tag.as_deref()).map_err(PerfSightError::from)
 }
 
 #[tauri::command]
 pub fn get_comparison_detail(
     db: State<'_, Database>,
     id: i64,
-) -> Result<ComparisonDetail, String> {
-    db.get_comparison_detail(id).map_err(|e| e.to_string())
+) -> Result<ComparisonDetail, PerfSightError> {
+    db.get_comparison_detail(id).map_err(PerfSightError::from)
 }
 
 #[tauri::command]
-pub fn delete_comparison(db: State<'_, Database>, id: i64) -> Result<usize, String> {
-    db.delete_comparison(id).map_err(|e| e.to_string())
+pub fn delete_comparison(db: State<'_, Database>, id: i64) -> Result<usize, PerfSightError> {
+    db.delete_comparison(id).map_err(PerfSightError::from)
 }
 
 #[tauri::command]
-pub fn delete_comparisons(db: State<'_, Database>, ids: Vec<i64>) -> Result<usize, String> {
-    db.delete_comparisons(&ids).map_err(|e| e.to_string())
+pub fn delete_comparisons(db: State<'_, Database>, ids: Vec<i64>) -> Result<usize, PerfSightError> {
+    db.delete_comparisons(&ids).map_err(PerfSightError::from)
 }
 
 #[tauri::command]
@@ -530,8 +3917,8 @@ pub fn update_comparison_title(
     db: State<'_, Database>,
     id: i64,
     title: String,
-) -> Result<usize, String> {
-    db.update_comparison_title(id, &title).map_err(|e| e.to_string())
+) -> Result<usize, PerfSightError> {
+    db.update_comparison_title(id, &title).map_err(PerfSightError::from)
 }
 
 #[derive(Debug, Deserialize)]
@@ -541,20 +3928,25 @@ pub struct UpdateComparisonConfigArgs {
     pub baseline_report_id: Option<i64>,
     pub cpu_selections_by_id: Value,
     pub mem_selections_by_id: Value,
+    /// Omitted keeps the saved metric selection.
+    #[serde(default)]
+    pub metric_selections: Option<Vec<String>>,
 }
 
 #[tauri::command]
 pub fn update_comparison_config(
     db: State<'_, Database>,
     args: UpdateComparisonConfigArgs,
-) -> Result<usize, String> {
+) -> Result<usize, PerfSightError> {
+    let metric_selections = check_metric_selections(args.metric_selections)?;
     db.update_comparison_config(
         args.id,
         args.baseline_report_id,
         &args.cpu_selections_by_id,
         &args.mem_selections_by_id,
+        metric_selections.as_deref(),
     )
-    .map_err(|e| e.to_string())
+    .map_err(PerfSightError::from)
 }
 
 #[tauri::command]
@@ -562,9 +3954,10 @@ pub fn update_comparison_folder_path(
     db: State<'_, Database>,
     id: i64,
     folder_path: String,
-) -> Result<usize, String> {
+) -> Result<usize, PerfSightError> {
+    check_folder_path("folder_path", &folder_path)?;
     db.update_comparison_folder_path(id, &folder_path)
-        .map_err(|e| e.to_string())
+        .map_err(PerfSightError::from)
 }
 
 #[tauri::command]
@@ -572,14 +3965,15 @@ pub fn update_comparisons_folder_path(
     db: State<'_, Database>,
     ids: Vec<i64>,
     folder_path: String,
-) -> Result<usize, String> {
+) -> Result<usize, PerfSightError> {
+    check_folder_path("folder_path", &folder_path)?;
     db.update_comparisons_folder_path(&ids, &folder_path)
-        .map_err(|e| e.to_string())
+        .map_err(PerfSightError::from)
 }
 
 #[tauri::command]
-pub fn list_comparison_folder_paths(db: State<'_, Database>) -> Result<Vec<FolderInfo>, String> {
-    db.list_comparison_folder_paths().map_err(|e| e.to_string())
+pub fn list_comparison_folder_paths(db: State<'_, Database>) -> Result<Vec<FolderInfo>, PerfSightError> {
+    db.list_comparison_folder_paths().map_err(PerfSightError::from)
 }
 
 #[tauri::command]
@@ -587,27 +3981,38 @@ pub fn create_comparison_folder(
     db: State<'_, Database>,
     parent_path: String,
     name: String,
-) -> Result<String, String> {
+) -> Result<String, PerfSightError> {
+    check_folder_path("name", &format!("{}/{}", parent_path, name))?;
     db.create_comparison_folder(&parent_path, &name)
-        .map_err(|e| e.to_string())
+        .map_err(PerfSightError::from)
 }
 
 #[tauri::command]
 pub fn get_comparison_folder_stats(
     db: State<'_, Database>,
     path: String,
-) -> Result<ComparisonFolderStats, String> {
-    db.get_comparison_folder_stats(&path).map_err(|e| e.to_string())
+) -> Result<ComparisonFolderStats, PerfSightError> {
+    db.get_comparison_folder_stats(&path).map_err(PerfSightError::from)
+}
+
+#[tauri::command]
+pub fn get_comparison_folder_aggregate(
+    db: State<'_, Database>,
+    path: String,
+) -> Result<ComparisonFolderAggregate, PerfSightError> {
+    db.get_comparison_folder_aggregate(&path).map_err(PerfSightError::from)
 }
 
+/// Rename a comparison folder; see `rename_folder` for `on_conflict`.
 #[tauri::command]
 pub fn rename_comparison_folder(
     db: State<'_, Database>,
     path: String,
     new_name: String,
-) -> Result<String, String> {
-    db.rename_comparison_folder(&path, &new_name)
-        .map_err(|e| e.to_string())
+    on_conflict: Option<FolderConflict>,
+) -> Result<String, PerfSightError> {
+    check_folder_path("new_name", &new_name)?;
+    db.rename_comparison_folder(&path, &new_name, on_conflict.unwrap_or_default())
 }
 
 #[tauri::command]
@@ -615,10 +4020,8 @@ pub fn delete_comparison_folder(
     db: State<'_, Database>,
     path: String,
     strategy: Option<String>,
-) -> Result<Value, String> {
-    let (moved_comparisons, moved_folders) = db
-        .delete_comparison_folder(&path, strategy.as_deref())
-        .map_err(|e| e.to_string())?;
+) -> Result<Value, PerfSightError> {
+    let (moved_comparisons, moved_folders) = db.delete_comparison_folder(&path, strategy.as_deref())?;
     Ok(serde_json::json!({
         "moved_comparisons": moved_comparisons,
         "moved_folders": moved_folders
@@ -626,27 +4029,43 @@ pub fn delete_comparison_folder(
 }
 
 #[tauri::command]
-pub fn export_comparison_bundle_json(
+pub async fn export_comparison_bundle_json(
     app_handle: AppHandle,
-    db: State<'_, Database>,
     comparison_id: i64,
     filename: Option<String>,
-) -> Result<String, String> {
-    let cmp = db.get_comparison_detail(comparison_id).map_err(|e| e.to_string())?;
+    overwrite: Option<bool>,
+    destination: Option<ExportDestination>,
+) -> Result<String, PerfSightError> {
+    run_blocking(&app_handle, move |app_handle, db| {
+        export_comparison_bundle_json_blocking(app_handle, db, comparison_id, filename, overwrite, destination)
+    })
+    .await
+}
+
+fn export_comparison_bundle_json_blocking<R: tauri::Runtime>(
+    app_handle: &AppHandle<R>,
+    db: &Database,
+    comparison_id: i64,
+    filename: Option<String>,
+    overwrite: Option<bool>,
+    destination: Option<ExportDestination>,
+) -> Result<String, PerfSightError> {
+    let cmp = db.get_comparison_detail(comparison_id)?;
     if cmp.report_ids.len() < 2 {
-        return Err("Comparison must contain at least 2 reports".to_string());
+        return Err(PerfSightError::invalid_input("id", "comparison must contain at least 2 reports"));
     }
 
     // Load reports in full (ReportDetail)
     let mut reports: Vec<ReportDetail> = Vec::new();
     for rid in &cmp.report_ids {
-        reports.push(db.get_report_detail(*rid).map_err(|e| e.to_string())?);
+        reports.push(db.get_report_detail(*rid)?);
     }
 
     let comparison_context = serde_json::json!({
         "baseline_report_id": cmp.baseline_report_id,
         "cpu_selections_by_id": cmp.cpu_selections_by_id,
         "mem_selections_by_id": cmp.mem_selections_by_id,
+        "metric_selections": cmp.metric_selections,
         "title": cmp.title,
         "folder_path": cmp.folder_path,
         "tags": cmp.tags,
@@ -663,26 +4082,156 @@ pub fn export_comparison_bundle_json(
         "meta": cmp.meta
     });
 
-    let json_str = serde_json::to_string_pretty(&bundle).map_err(|e| e.to_string())?;
+    let json_str = serde_json::to_string_pretty(&bundle)?;
+
+    let default_name = format!("PerfSight_Comparison_{}_Bundle", comparison_id);
+    let path = resolve_export_path(app_handle, destination.as_ref(), filename, &default_name, "json", overwrite)?;
+    artifacts::write_export_file(&path, json_str.as_bytes())?;
+    artifacts::path_string(&path)
+}
+
+/// Export a report's samples as CSV, one row per PID per sample, labelled with the report's
+/// current aliases. Timestamps are written in `timezone` (see `DisplayZone::parse`).
+#[tauri::command]
+pub async fn export_report_csv(
+    app_handle: AppHandle,
+    id: i64,
+    filename: Option<String>,
+    overwrite: Option<bool>,
+    destination: Option<ExportDestination>,
+    timezone: Option<String>,
+) -> Result<String, PerfSightError> {
+    let zone = DisplayZone::parse(timezone.as_deref())?;
+    run_blocking(&app_handle, move |app_handle, db| {
+        export_report_csv_blocking(app_handle, db, id, filename, overwrite, destination, zone)
+    })
+    .await
+}
+
+fn export_report_csv_blocking<R: tauri::Runtime>(
+    app_handle: &AppHandle<R>,
+    db: &Database,
+    id: i64,
+    filename: Option<String>,
+    overwrite: Option<bool>,
+    destination: Option<ExportDestination>,
+    zone: DisplayZone,
+) -> Result<String, PerfSightError> {
+    let report = db.get_report_detail(id)?;
+    let csv = csv_export::render_samples_csv(&report, zone);
+
+    let default_name = format!("PerfSight_Report_{}_Samples", id);
+    let path = resolve_export_path(app_handle, destination.as_ref(), filename, &default_name, "csv", overwrite)?;
+    artifacts::write_export_file(&path, csv.as_bytes())?;
+    artifacts::path_string(&path)
+}
+
+/// Export the comparison matrix (metrics x reports, with percent deltas vs baseline) as CSV to
+/// `path`, a full file path like `destination.dest_path`; without either it goes to the export dir.
+#[tauri::command]
+pub async fn export_comparison_csv(
+    app_handle: AppHandle,
+    comparison_id: i64,
+    path: Option<String>,
+    overwrite: Option<bool>,
+    destination: Option<ExportDestination>,
+) -> Result<String, PerfSightError> {
+    run_blocking(&app_handle, move |app_handle, db| {
+        export_comparison_csv_blocking(app_handle, db, comparison_id, path, overwrite, destination)
+    })
+    .await
+}
+
+fn export_comparison_csv_blocking<R: tauri::Runtime>(
+    app_handle: &AppHandle<R>,
+    db: &Database,
+    comparison_id: i64,
+    path: Option<String>,
+    overwrite: Option<bool>,
+    destination: Option<ExportDestination>,
+) -> Result<String, PerfSightError> {
+    let cmp = db.get_comparison_detail(comparison_id)?;
+    if cmp.report_ids.len() < 2 {
+        return Err(PerfSightError::invalid_input("id", "comparison must contain at least 2 reports"));
+    }
+    let mut reports: Vec<ReportDetail> = Vec::new();
+    for rid in &cmp.report_ids {
+        reports.push(db.get_report_detail(*rid)?);
+    }
+    let csv = csv_export::render_comparison_csv(&cmp, &reports);
+
+    let default_name = format!("PerfSight_Comparison_{}_Summary", comparison_id);
+    let path = resolve_export_path(app_handle, with_dest_path(destination, path).as_ref(), None, &default_name, "csv", overwrite)?;
+    artifacts::write_export_file(&path, csv.as_bytes())?;
+    artifacts::path_string(&path)
+}
+
+/// Export the comparison's runs resampled onto one relative time axis (seconds since each run's
+/// first sample, every `resample_interval_ms`) as a wide CSV for plotting them on top of each other.
+#[tauri::command]
+pub async fn export_comparison_overlay_csv(
+    app_handle: AppHandle,
+    comparison_id: i64,
+    resample_interval_ms: u64,
+    filename: Option<String>,
+    overwrite: Option<bool>,
+    destination: Option<ExportDestination>,
+) -> Result<String, PerfSightError> {
+    run_blocking(&app_handle, move |app_handle, db| {
+        export_comparison_overlay_csv_blocking(
+            app_handle,
+            db,
+            comparison_id,
+            resample_interval_ms,
+            filename,
+            overwrite,
+            destination,
+        )
+    })
+    .await
+}
 
-    let mut dir = app_handle.path().resolve("", BaseDirectory::Download).ok();
-    if dir.is_none() {
-        dir = app_handle.path().app_local_data_dir().ok();
+fn export_comparison_overlay_csv_blocking<R: tauri::Runtime>(
+    app_handle: &AppHandle<R>,
+    db: &Database,
+    comparison_id: i64,
+    resample_interval_ms: u64,
+    filename: Option<String>,
+    overwrite: Option<bool>,
+    destination: Option<ExportDestination>,
+) -> Result<String, PerfSightError> {
+    if !(10..=MAX_INTERVAL_MS).contains(&resample_interval_ms) {
+        return Err(PerfSightError::invalid_input(
+            "resample_interval_ms",
+            format!("must be between 10 and {}", MAX_INTERVAL_MS),
+        ));
     }
-    let dir = dir.ok_or("Failed to resolve output directory")?;
-    if !dir.exists() {
-        std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let cmp = db.get_comparison_detail(comparison_id)?;
+    if cmp.report_ids.len() < 2 {
+        return Err(PerfSightError::invalid_input("id", "comparison must contain at least 2 reports"));
+    }
+    let mut reports: Vec<ReportDetail> = Vec::new();
+    for rid in &cmp.report_ids {
+        reports.push(db.get_report_detail(*rid)?);
+    }
+    let longest_ms = reports
+        .iter()
+        .filter_map(|r| Some((r.metrics.last()?.timestamp - r.metrics.first()?.timestamp).num_milliseconds()))
+        .max()
+        .unwrap_or(0)
+        .max(0) as u64;
+    if longest_ms / resample_interval_ms >= csv_export::MAX_OVERLAY_ROWS as u64 {
+        return Err(PerfSightError::invalid_input(
+            "resample_interval_ms",
+            format!("too fine for a {} s run (over {} rows)", longest_ms / 1000, csv_export::MAX_OVERLAY_ROWS),
+        ));
     }
+    let csv = csv_export::render_comparison_overlay_csv(&cmp, &reports, resample_interval_ms);
 
-    let name = filename
-        .and_then(|s| {
-            let t = s.trim().to_string();
-            if t.is_empty() { None } else { Some(t) }
-        })
-        .unwrap_or_else(|| format!("PerfSight_Comparison_{}_Bundle.json", comparison_id));
-    let path = dir.join(name);
-    std::fs::write(&path, json_str.as_bytes()).map_err(|e| e.to_string())?;
-    Ok(path.to_string_lossy().to_string())
+    let default_name = format!("PerfSight_Comparison_{}_Overlay", comparison_id);
+    let path = resolve_export_path(app_handle, destination.as_ref(), filename, &default_name, "csv", overwrite)?;
+    artifacts::write_export_file(&path, csv.as_bytes())?;
+    artifacts::path_string(&path)
 }
 
 #[derive(Debug, Deserialize)]
@@ -697,21 +4246,20 @@ pub struct UpdateComparisonReportsArgs {
 pub fn update_comparison_reports(
     db: State<'_, Database>,
     args: UpdateComparisonReportsArgs,
-) -> Result<usize, String> {
+) -> Result<usize, PerfSightError> {
     if args.report_ids.len() < 2 {
-        return Err("Comparison requires at least 2 reports".to_string());
+        return Err(PerfSightError::invalid_input("report_ids", "comparison requires at least 2 reports"));
     }
     let mut ids = args.report_ids.clone();
     ids.sort();
     ids.dedup();
-    db.update_comparison_report_ids(args.id, &ids)
-        .map_err(|e| e.to_string())?;
+    db.update_comparison_report_ids(args.id, &ids)?;
 
     // Optionally update baseline (e.g., if user picks a new baseline for the new report set)
     if let Some(baseline_id) = args.baseline_report_id {
         let cpu = serde_json::json!({});
         let mem = serde_json::json!({});
-        let _ = db.update_comparison_config(args.id, Some(baseline_id), &cpu, &mem);
+        let _ = db.update_comparison_config(args.id, Some(baseline_id), &cpu, &mem, None);
     }
     Ok(ids.len())
 }
@@ -727,22 +4275,20 @@ pub struct UpdateComparisonMetaArgs {
 pub fn update_comparison_meta(
     db: State<'_, Database>,
     args: UpdateComparisonMetaArgs,
-) -> Result<usize, String> {
+) -> Result<usize, PerfSightError> {
     db.update_comparison_meta_patch(args.id, &args.meta)
-        .map_err(|e| e.to_string())
+        .map_err(PerfSightError::from)
 }
 
 // Helper to push a custom metric derived from logs
-pub fn push_custom_metric(
-    app: &AppHandle,
+pub fn push_custom_metric<R: tauri::Runtime>(
+    app: &AppHandle<R>,
     state: &CollectionState,
     pid: u32,
     timestamp: DateTime<Utc>,
     name: String,
     value: f64
 ) {
-    // Emit for live preview regardless of run state
-    
     let mut custom = HashMap::new();
     custom.insert(name, value);
     
@@ -751,37 +4297,71 @@ pub fn push_custom_metric(
         pid,
         cpu_usage: 0.0,
         cpu_os_usage: 0.0,
+        cpu_os_usage_raw: None,
         cpu_chrome_usage: None,
         memory_rss: 0,
         memory_footprint: None,
         gpu_usage: None,
         js_heap_size: None,
+        js_heap_total_size: None,
         memory_private: None,
+        gpu_memory_bytes: None,
+        max_thread_cpu: None,
         custom_metrics: Some(custom),
+        memory_basis: None,
     };
     
     let mut metrics = HashMap::new();
     metrics.insert(pid, point);
     let batch = BatchMetric { timestamp, metrics };
-    
-    let _ = app.emit("new-metric-batch", &batch);
-    
-    // Only save if running
-    if *safe_lock(&state.is_running) {
-        safe_lock(&state.buffer).push(batch);
+
+    // Saved by the session recording the PID; a lone session also keeps metrics for other PIDs.
+    let session_id = state.owner_of(pid).or_else(|| state.resolve_session(None).ok().flatten());
+
+    // Emit for live preview regardless of run state
+    let _ = app.emit("new-metric-batch", &SessionBatch { session_id: session_id.as_deref(), batch: &batch });
+
+    if let Some(id) = &session_id {
+        state.write_session(id, |run| run.buffer_batch(batch, DataSource::Websocket));
     }
 }
 
-// Helper to process metric payload from Sidecar or WebSocket
-pub fn process_metric_payload(
-    app: &AppHandle,
+/// Payload of the "new-metric-batch" event: the batch plus the session it was recorded for
+/// (None for live-only points outside any run).
+#[derive(Serialize)]
+pub struct SessionBatch<'a> {
+    pub session_id: Option<&'a str>,
+    #[serde(flatten)]
+    pub batch: &'a BatchMetric,
+}
+
+// Helper to process metric payload from Sidecar or WebSocket.
+/// A payload's `timestamp` (ms since the epoch), refused when chrono can't represent it.
+pub fn payload_timestamp(ts_ms: i64) -> Result<DateTime<Utc>, PerfSightError> {
+    Utc.timestamp_millis_opt(ts_ms)
+        .single()
+        .ok_or_else(|| PerfSightError::invalid_input("timestamp", format!("{} ms is out of range", ts_ms)))
+}
+
+// Returns the number of per-PID points accepted from the payload.
+pub fn process_metric_payload<R: tauri::Runtime>(
+    app: &AppHandle<R>,
     data: Value,
-    state: &CollectionState
-) {
+    state: &CollectionState,
+    source: DataSource,
+    stream: &str,
+) -> usize {
     if data["type"] == "data" {
         let ts_ms = data["timestamp"].as_i64().unwrap_or(0);
-        let timestamp = Utc.timestamp_millis_opt(ts_ms).unwrap();
-        let target_pids = safe_lock(&state.target_pids);
+        let timestamp = match payload_timestamp(ts_ms) {
+            Ok(t) => t,
+            Err(e) => {
+                log_warn!("Dropping {} payload: {}", stream, e);
+                let dropped = data["metrics"].as_object().map_or(0, |m| m.len() as u64);
+                state.write_each(|run| run.ingest.dropped_samples += dropped);
+                return 0;
+            }
+        };
 
         // Get total memory (bytes) for sanity checks.
         // sysinfo has had unit differences across versions (KiB vs bytes) and may return 0 until refreshed.
@@ -796,22 +4376,91 @@ pub fn process_metric_payload(
         } else {
             total_mem_raw as f64
         };
-        
+
+        let (outcomes, retune) = record_metric_payload(state, &data, source, stream, timestamp, total_mem_bytes);
+        if retune {
+            if let Err(e) = retune_sidecar(state) {
+                log_warn!("Failed to retune sidecar interval: {}", e);
+            }
+        }
+
+        // Emit for live preview (the merged batch when it joined an existing timestamp)
+        let mut accepted = 0;
+        for (n, recorded) in outcomes {
+            accepted += n;
+            if let Some((session_id, batch)) = recorded {
+                let _ = app.emit("new-metric-batch", &SessionBatch { session_id: Some(&session_id), batch: &batch });
+            }
+        }
+        return accepted;
+    }
+    0
+}
+
+// Points accepted per session, with the batch recorded (merged into an existing timestamp when it
+// joined one), and whether the sidecar interval needs retuning.
+type PayloadOutcomes = (Vec<(usize, Option<(SessionId, BatchMetric)>)>, bool);
+
+// Route a "data" payload to the sessions recording its PIDs.
+fn record_metric_payload(
+    state: &CollectionState,
+    data: &Value,
+    source: DataSource,
+    stream: &str,
+    timestamp: DateTime<Utc>,
+    total_mem_bytes: f64,
+) -> PayloadOutcomes {
+    let seq = data["seq"].as_u64();
+    // Filtering, spike clamping and buffering happen under one lock on the sessions, so a stop
+    // in between can't lose or misplace the batch. Each session takes the PIDs it records.
+    let owned: Vec<u32> = state.read_each(|run| run.target_pids.clone()).concat();
+    let mut retune = false;
+    let outcomes = state.write_each(|run| {
+        // Extension samples only feed browser-mode sessions.
+        if source == DataSource::Websocket && run.mode != "browser" {
+            return (0, None);
+        }
+        // Counted before the per-session tick skip below: that drop is deliberate, not loss.
+        if let Some(seq) = seq {
+            run.ingest.observe_seq(source, stream, seq);
+        }
+        // The shared sidecar samples at the shortest session interval; slower sessions (and
+        // sessions slowed down by adaptive sampling) skip ticks.
+        if source == DataSource::Sidecar
+            && run.buffer.last().is_some_and(|last| {
+                (timestamp - last.timestamp).num_milliseconds() < run.sampling_interval_ms() as i64 * 9 / 10
+            })
+        {
+            return (0, None);
+        }
         let mut metrics = HashMap::new();
+        let mut dropped = 0u64;
+        let mut clamped = 0u64;
         if let Some(obj) = data["metrics"].as_object() {
             for (pid_str, val) in obj {
                 if !val.is_null() {
                     let pid = pid_str.parse::<u32>().unwrap_or(0);
-                    
-                    // Strict filtering: Only record requested PIDs
-                    if !target_pids.contains(&pid) {
+
+                    // Strict filtering: Only record requested PIDs (other sessions take theirs)
+                    if !run.target_pids.contains(&pid) {
+                        if !owned.contains(&pid) {
+                            dropped += 1;
+                        }
                         continue;
                     }
 
                     let cpu = val["cpu"].as_f64().unwrap_or(0.0) as f32;
+                    // Only the sidecar reports the pre-normalization value.
+                    let cpu_raw = val["cpu_raw"].as_f64().map(|v| v as f32);
+                    let max_thread_cpu = val["max_thread_cpu"].as_f64().map(|v| v as f32);
+                    // The sidecar (and HTTP rigs) may say which figure they sent; older sidecars don't.
+                    let memory_basis = match source {
+                        DataSource::Websocket => Some(MemoryBasis::ExtensionPrivate),
+                        _ => serde_json::from_value(val["memory_basis"].clone()).ok(),
+                    };
                     let mem_raw = val["memory"].as_f64().unwrap_or(0.0);
 
-                    // Websocket payloads (from perf-sight-extension) should send memory in MB.
+                    // Websocket payloads (from perf-sight-extension) and HTTP ingest send memory in MB.
                     // Guard against occasional unit flips (bytes vs MB) and glitch spikes.
                     let mem_bytes_from_mb = mem_raw * 1024.0 * 1024.0;
                     let treated_as_bytes = total_mem_bytes > 0.0
@@ -827,69 +4476,56 @@ pub fn process_metric_payload(
 
                     // Spike clamp: if this PID's memory suddenly jumps to an implausible value
                     // compared to the previous sample, treat it as a glitch and keep previous.
-                    {
-                        let buffer = safe_lock(&state.buffer);
-                        if let Some(last) = buffer.last() {
-                            if let Some(prev) = last.metrics.get(&pid) {
-                                let prev_bytes = prev.memory_rss as f64;
-                                let delta = mem_bytes - prev_bytes;
-
-                                // Typical Chrome processes shouldn't jump by hundreds of MB to multiple GB in 1 tick.
-                                // We clamp when the jump is both:
-                                // - multiplicatively large, and
-                                // - absolutely large (to avoid clamping legitimate small changes).
-                                //
-                                // Also clamp if it exceeds near-total system memory (definitely wrong).
-                                let clamp =
-                                    (prev_bytes > 0.0
-                                        && mem_bytes > prev_bytes * 6.0
-                                        && delta > 512.0 * 1024.0 * 1024.0) // > 512MB jump
-                                    || (prev_bytes > 0.0
-                                        && delta > 2.0 * 1024.0 * 1024.0 * 1024.0) // > 2GB jump
-                                    || (total_mem_bytes > 0.0 && mem_bytes > total_mem_bytes * 0.90);
-
-                                if pid == 78937 {
-                                    eprintln!(
-                                        "DEBUG pid=78937 mem websocket ts_ms={} raw_memory_field={} treated_as_bytes={} prev={}MB current={}MB delta={}MB total_mem={}GB clamp={}",
-                                        ts_ms,
-                                        mem_raw,
-                                        treated_as_bytes,
-                                        (prev_bytes / 1024.0 / 1024.0).round(),
-                                        (mem_bytes / 1024.0 / 1024.0).round(),
-                                        (delta / 1024.0 / 1024.0).round(),
-                                        if total_mem_bytes > 0.0 {
-                                            (total_mem_bytes / 1024.0 / 1024.0 / 1024.0).round()
-                                        } else {
-                                            -1.0
-                                        },
-                                        clamp
-                                    );
-                                }
+                    if let Some(prev) = run.buffer.last().and_then(|last| last.metrics.get(&pid)) {
+                        let prev_bytes = prev.memory_rss as f64;
+                        let delta = mem_bytes - prev_bytes;
 
-                                if clamp {
-                                    eprintln!(
-                                        "WARN: dropping suspicious websocket memory spike pid={} prev={}MB current={}MB raw_memory_field={} total_mem={}GB",
-                                        pid,
-                                        (prev_bytes / 1024.0 / 1024.0).round(),
-                                        (mem_bytes / 1024.0 / 1024.0).round(),
-                                        mem_raw,
-                                        if total_mem_bytes > 0.0 {
-                                            (total_mem_bytes / 1024.0 / 1024.0 / 1024.0).round()
-                                        } else {
-                                            -1.0
-                                        }
-                                    );
-                                    mem_bytes = prev_bytes;
+                        // Typical Chrome processes shouldn't jump by hundreds of MB to multiple GB in 1 tick.
+                        // We clamp when the jump is both:
+                        // - multiplicatively large, and
+                        // - absolutely large (to avoid clamping legitimate small changes).
+                        //
+                        // Also clamp if it exceeds near-total system memory (definitely wrong).
+                        let clamp =
+                            (prev_bytes > 0.0
+                                && mem_bytes > prev_bytes * 6.0
+                                && delta > 512.0 * 1024.0 * 1024.0) // > 512MB jump
+                            || (prev_bytes > 0.0
+                                && delta > 2.0 * 1024.0 * 1024.0 * 1024.0) // > 2GB jump
+                            || (total_mem_bytes > 0.0 && mem_bytes > total_mem_bytes * 0.90);
+
+                        if clamp {
+                            log_warn!(
+                                "dropping suspicious websocket memory spike pid={} prev={}MB current={}MB raw_memory_field={} total_mem={}GB",
+                                pid,
+                                (prev_bytes / 1024.0 / 1024.0).round(),
+                                (mem_bytes / 1024.0 / 1024.0).round(),
+                                mem_raw,
+                                if total_mem_bytes > 0.0 {
+                                    (total_mem_bytes / 1024.0 / 1024.0 / 1024.0).round()
+                                } else {
+                                    -1.0
                                 }
-                            }
+                            );
+                            run.events.push(RunEvent::now(
+                                RunEventKind::SampleClamped,
+                                Some(pid),
+                                json!({
+                                    "prev_mb": (prev_bytes / 1024.0 / 1024.0).round(),
+                                    "current_mb": (mem_bytes / 1024.0 / 1024.0).round()
+                                }),
+                            ));
+                            mem_bytes = prev_bytes;
+                            clamped += 1;
                         }
                     }
-                    
+
                     metrics.insert(pid, MetricPoint {
                         timestamp,
                         pid,
                         cpu_usage: cpu,
                         cpu_os_usage: cpu,
+                        cpu_os_usage_raw: cpu_raw,
                         cpu_chrome_usage: None,
                         // Websocket provides Chrome "private memory" (Task Manager memory footprint), not RSS.
                         // Populate memory_private so the frontend can label/choose it correctly.
@@ -897,51 +4533,161 @@ pub fn process_metric_payload(
                         memory_footprint: None,
                         gpu_usage: None,
                         js_heap_size: None,
+                        js_heap_total_size: None,
                         memory_private: Some(mem_bytes.max(0.0) as u64),
+                        gpu_memory_bytes: None,
+                        max_thread_cpu,
                         custom_metrics: None,
+                        memory_basis,
                     });
                 }
             }
         }
-        
-        if !metrics.is_empty() {
-            let is_running = *safe_lock(&state.is_running);
-            let batch = BatchMetric { timestamp, metrics };
-
-            if is_running {
-                // Merge logic for recording
-                let mut buffer = safe_lock(&state.buffer);
-                if let Some(last) = buffer.last_mut() {
-                    if last.timestamp == timestamp {
-                        for (pid, mp) in batch.metrics.clone() {
-                            last.metrics.insert(pid, mp);
-                        }
-                        let merged = last.clone();
-                        drop(buffer);
-                        let _ = app.emit("new-metric-batch", &merged);
-                        return;
-                    }
+
+        run.ingest.dropped_samples += dropped;
+        run.ingest.clamped_samples += clamped;
+        let accepted = metrics.len();
+        if metrics.is_empty() {
+            return (0, None);
+        }
+        let batch = BatchMetric { timestamp, metrics };
+
+        // Merge logic for recording
+        if let Some(last) = run.buffer.last_mut() {
+            if last.timestamp == timestamp {
+                // Only PIDs the batch didn't already have a sample for add to their coverage.
+                let added: Vec<(u32, MetricPoint)> = batch
+                    .metrics
+                    .iter()
+                    .filter(|(pid, _)| last.metrics.get(pid).is_none_or(|p| p.is_custom_only()))
+                    .map(|(pid, mp)| (*pid, mp.clone()))
+                    .collect();
+                for (pid, mp) in batch.metrics.clone() {
+                    last.metrics.insert(pid, mp);
                 }
-                buffer.push(batch.clone());
+                let merged = last.clone();
+                run.ingest.touch(source, batch.metrics.len());
+                run.record_latest(&merged);
+                run.record_coverage(timestamp, added.iter().map(|(pid, mp)| (pid, mp)));
+                run.forward_to_sink(&batch);
+                return (accepted, Some((run.session_id.clone(), merged)));
             }
-            
-            // Emit for live preview (if not merged above)
-            let _ = app.emit("new-metric-batch", &batch);
         }
-    }
+        if source == DataSource::Sidecar && run.adapt_interval(&batch).is_some() {
+            retune = true;
+        }
+        run.buffer_batch(batch.clone(), source);
+        (accepted, Some((run.session_id.clone(), batch)))
+    });
+    (outcomes, retune)
 }
 
+/// Processes for the picker. Answers from the cache when there is one (kicking off a background
+/// refresh, announced as "process-list-updated", once it is older than a few seconds); scans
+/// right away when there is none or `refresh` is set.
 #[tauri::command]
 pub async fn get_process_list(
     app_handle: AppHandle,
+    state: State<'_, CollectionState>,
     args: Option<ProcessListArgs>
-) -> Result<Vec<ProcessInfo>, String> {
-    let mode = args.map(|a| a.mode).unwrap_or("system".to_string());
+) -> Result<Vec<ProcessInfo>, PerfSightError> {
+    let (mode, refresh, include_self, memory_standard) = args
+        .map(|a| {
+            (a.mode, a.refresh.unwrap_or(false), a.include_self.unwrap_or(false), a.memory_standard.unwrap_or_default())
+        })
+        .unwrap_or_else(|| ("system".to_string(), false, false, MemoryStandard::Rss));
+    let mut list = process_list(&app_handle, &state, mode, refresh, memory_standard).await?;
+    if include_self {
+        let own = self_pids(&state);
+        list.retain(|p| !own.contains(&p.pid));
+        list.extend(self_process_list(&own));
+    }
+    Ok(list)
+}
+
+// PerfSight's own PIDs: the app, plus the collector sidecar while it runs.
+fn self_pids(state: &CollectionState) -> Vec<u32> {
+    let mut pids = vec![std::process::id()];
+    if let Some(child) = safe_lock(&state.child).as_ref() {
+        pids.push(child.pid());
+    }
+    pids
+}
+
+// Picker entries for `pids` (from `self_pids`), with their current memory.
+fn self_process_list(pids: &[u32]) -> Vec<ProcessInfo> {
+    let mut sys = sysinfo::System::new();
+    pids.iter()
+        .enumerate()
+        .map(|(i, pid)| {
+            let sys_pid = sysinfo::Pid::from_u32(*pid);
+            sys.refresh_process(sys_pid);
+            let process = sys.process(sys_pid);
+            ProcessInfo {
+                pid: *pid,
+                alias: None,
+                name: process
+                    .map(|p| p.name().to_string())
+                    .unwrap_or_else(|| if i == 0 { "PerfSight" } else { "collector" }.to_string()),
+                memory_usage: process.map(|p| p.memory()).unwrap_or(0),
+                cpu_usage: 0.0,
+                proc_type: SELF_PROC_TYPE.to_string(),
+                title: Some(if i == 0 { "PerfSight app" } else { "PerfSight collector sidecar" }.to_string()),
+                url: None,
+                targets: Vec::new(),
+                bundle_id: None,
+                window_title: None,
+            }
+        })
+        .collect()
+}
+
+async fn process_list(
+    app_handle: &AppHandle,
+    state: &CollectionState,
+    mode: String,
+    refresh: bool,
+    memory_standard: MemoryStandard,
+) -> Result<Vec<ProcessInfo>, PerfSightError> {
+    let cache = state.process_scans.clone();
+    let key = if mode == "browser" {
+        SIDECAR_CHROME_SCAN_KEY.to_string()
+    } else {
+        ProcessScanCache::key(&mode, memory_standard)
+    };
+
+    if !refresh {
+        if let Some((age, list)) = cache.cached(&key) {
+            if age >= SCAN_MAX_AGE && cache.begin_refresh(&key) {
+                let app_handle = app_handle.clone();
+                tauri::async_runtime::spawn(async move {
+                    let scanned = scan_process_list(&app_handle, &mode, memory_standard).await;
+                    cache.end_refresh(&key);
+                    match scanned {
+                        Ok(processes) => {
+                            let _ = app_handle.emit("process-list-updated", &ProcessListUpdated { mode, processes });
+                        }
+                        Err(e) => log_warn!("Background process scan failed: {}", e),
+                    }
+                });
+            }
+            return Ok(list);
+        }
+    }
+    scan_process_list(app_handle, &mode, memory_standard).await
+}
 
+// Scan for the picker and store the result in the cache.
+async fn scan_process_list(
+    app_handle: &AppHandle,
+    mode: &str,
+    memory_standard: MemoryStandard,
+) -> Result<Vec<ProcessInfo>, PerfSightError> {
+    let cache = app_handle.state::<CollectionState>().process_scans.clone();
     if mode == "browser" {
-        println!("Scanning Chrome processes via Sidecar...");
-        let sidecar = app_handle.shell().sidecar("collector").map_err(|e| e.to_string())?;
-        let (mut rx, mut child) = sidecar.spawn().map_err(|e| e.to_string())?;
+        log_debug!("Scanning Chrome processes via Sidecar...");
+        let sidecar = app_handle.shell().sidecar("collector").map_err(|_| PerfSightError::SidecarMissing)?;
+        let (mut rx, mut child) = sidecar.spawn().map_err(|_| PerfSightError::SidecarMissing)?;
         
         let cmd = json!({ "action": "scan_chrome" }).to_string() + "\n";
         child.write(cmd.as_bytes()).map_err(|e| e.to_string())?;
@@ -965,9 +4711,13 @@ pub async fn get_process_list(
                                         proc_type: p["proc_type"].as_str().unwrap_or("Unknown").to_string(),
                                         title: None,
                                         url: None,
+                                        targets: Vec::new(),
+                                        bundle_id: None,
+                                        window_title: None,
                                     });
                                 }
                             }
+                            cache.store(SIDECAR_CHROME_SCAN_KEY, &processes);
                             return Ok(processes);
                         }
                     }
@@ -975,14 +4725,15 @@ pub async fn get_process_list(
                 _ => {}
             }
         }
-        return Err("Sidecar closed without returning list".to_string());
+        return Err("Sidecar closed without returning list".into());
     }
 
-    // System mode: Use existing Rust collector
-    let res = tokio::task::spawn_blocking(move || {
-        let mut collector = create_collector(&mode);
-        collector.scan_processes(&mode)
-    }).await.map_err(|e| e.to_string())?;
+    // System mode: the shared collector, refreshed in place
+    let cpu_normalization = Settings::load(app_handle.state::<Database>().inner()).cpu_normalization();
+    let mode = mode.to_string();
+    let res = tokio::task::spawn_blocking(move || cache.scan(&mode, cpu_normalization, memory_standard, Duration::ZERO))
+        .await
+        .map_err(|e| e.to_string())?;
     
     Ok(res)
 }
@@ -991,55 +4742,549 @@ pub async fn get_process_list(
 pub async fn start_collection(
     app_handle: AppHandle,
     state: State<'_, CollectionState>,
+    db: State<'_, Database>,
     config: CollectionConfig
-) -> Result<String, String> {
-    println!("Starting collection...");
-    
-    // Save target PIDs for filtering
-    *safe_lock(&state.target_pids) = config.target_pids.clone();
-    *safe_lock(&state.mode) = config.mode.clone();
-    *safe_lock(&state.interval_ms) = config.interval_ms;
-    *safe_lock(&state.started_at) = Some(Utc::now().to_rfc3339());
-    *safe_lock(&state.app_version) = app_handle.package_info().version.to_string();
-    *safe_lock(&state.test_context) = config
-        .test_context
-        .as_ref()
-        .map(|tc| serde_json::to_value(tc).unwrap_or_else(|_| json!({})));
-    *safe_lock(&state.process_aliases) = config.process_aliases.clone().unwrap_or_default();
-    *safe_lock(&state.folder_path) = config
-        .folder_path
-        .as_ref()
-        .map(|s| s.trim().to_string())
-        .filter(|s| !s.is_empty());
-    *safe_lock(&state.stop_after_seconds) = config.stop_after_seconds;
-
-    // Compile regexes for log metrics
-    if let Some(configs) = config.log_metric_configs {
-        let mut compiled = Vec::new();
-        for cfg in configs {
-            match Regex::new(&cfg.pattern) {
-                Ok(re) => compiled.push((cfg, re)),
-                Err(e) => eprintln!("Invalid regex pattern '{}': {}", cfg.pattern, e),
-            }
-        }
-        *safe_lock(&state.log_metrics) = compiled;
+) -> Result<String, PerfSightError> {
+    start_collection_with(app_handle, state.inner(), db.inner(), config).await
+}
+
+/// Save (or overwrite) a named preset. `config_json` is a CollectionConfig; `target_pids` is
+/// dropped because PIDs only make sense for the current session.
+#[tauri::command]
+pub fn save_collection_preset(
+    db: State<'_, Database>,
+    name: String,
+    config_json: Value,
+) -> Result<CollectionPreset, PerfSightError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(PerfSightError::invalid_input("name", "cannot be empty"));
+    }
+    let Value::Object(mut config) = config_json else {
+        return Err(PerfSightError::invalid_input("config_json", "must be an object"));
+    };
+    config.remove("target_pids");
+    let config = Value::Object(config);
+    serde_json::from_value::<CollectionConfig>(config.clone())
+        .map_err(|e| PerfSightError::invalid_input("config_json", e.to_string()))?;
+    db.save_collection_preset(name, &config)?;
+    Ok(db.get_collection_preset(name)?)
+}
+
+#[tauri::command]
+pub fn list_collection_presets(db: State<'_, Database>) -> Result<Vec<CollectionPreset>, PerfSightError> {
+    db.list_collection_presets().map_err(PerfSightError::from)
+}
+
+#[tauri::command]
+pub fn delete_collection_preset(db: State<'_, Database>, name: String) -> Result<usize, PerfSightError> {
+    db.delete_collection_preset(name.trim()).map_err(PerfSightError::from)
+}
+
+/// Restore presets from a `presets.json` (as written into report bundles). Existing presets with
+/// the same name are overwritten. Returns the number imported.
+#[tauri::command]
+pub fn import_collection_presets(db: State<'_, Database>, presets_json: String) -> Result<usize, PerfSightError> {
+    let presets: Vec<CollectionPreset> = serde_json::from_str(&presets_json)
+        .map_err(|e| PerfSightError::invalid_input("presets_json", e.to_string()))?;
+    for p in &presets {
+        if p.name.trim().is_empty() {
+            return Err(PerfSightError::invalid_input("presets_json", "preset name cannot be empty"));
+        }
+        db.save_collection_preset(p.name.trim(), &p.config)?;
+    }
+    Ok(presets.len())
+}
+
+/// Start a run from a saved preset with the given PIDs. Preset aliases only carry over for PIDs
+/// in `pid_overrides`; the merged config goes through the same validation as a manual start.
+#[tauri::command]
+pub async fn start_collection_from_preset(
+    app_handle: AppHandle,
+    state: State<'_, CollectionState>,
+    db: State<'_, Database>,
+    name: String,
+    pid_overrides: Vec<u32>,
+) -> Result<String, PerfSightError> {
+    let preset = db.get_collection_preset(name.trim())?;
+    let mut config: CollectionConfig = serde_json::from_value(preset.config)
+        .map_err(|e| PerfSightError::invalid_input("config_json", e.to_string()))?;
+    config.target_pids = pid_overrides;
+    if let Some(aliases) = config.process_aliases.as_mut() {
+        aliases.retain(|a| config.target_pids.contains(&a.pid));
+    }
+    start_collection_with(app_handle, state.inner(), db.inner(), config).await
+}
+
+/// Run a scripted sequence of steps (start, sleep, add_marker, shell, stop_and_save,
+/// assert_budget) in the background. Progress arrives as "scenario-progress" events and the
+/// result as "scenario-finished". Returns the scenario id.
+#[tauri::command]
+pub fn run_scenario_script(app_handle: AppHandle, script: Value) -> Result<String, PerfSightError> {
+    let script = scenario::ScenarioScript::parse(script)?;
+    scenario::spawn_scenario(app_handle, script)
+}
+
+/// Abort the running scenario script. Partial data is saved and flagged incomplete.
+/// Returns the aborted scenario id, or None when no script is running.
+#[tauri::command]
+pub fn abort_scenario_script(scenario_state: State<'_, ScenarioState>) -> Option<String> {
+    scenario_state.abort()
+}
+
+// Title of the tabs behind one PID, without unread counters like "(3) " so a ticking counter
+// does not count as a rename.
+fn tab_title(targets: &[TabTarget]) -> String {
+    let strip = |title: &str| -> String {
+        let t = title.trim();
+        match t.strip_prefix('(').and_then(|rest| rest.split_once(") ")) {
+            Some((count, rest)) if !count.is_empty() && count.chars().all(|c| c.is_ascii_digit() || c == '+') => {
+                rest.trim().to_string()
+            }
+            _ => t.to_string(),
+        }
+    };
+    targets.iter().map(|t| strip(&t.title)).filter(|t| !t.is_empty()).collect::<Vec<_>>().join(" | ")
+}
+
+/// Poll a Rust-side collector on a blocking thread until the run stops.
+fn spawn_native_collection<R: tauri::Runtime>(
+    app_handle: AppHandle<R>,
+    state: CollectionState,
+    config: &CollectionConfig,
+    simulation: Option<SimulationConfig>,
+    cpu_normalization: CpuNormalization,
+    session_id: SessionId,
+) {
+    let mode = config.mode.clone();
+    let memory_standard = config.memory_standard;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut collector = create_collector_with(&mode, simulation.as_ref(), cpu_normalization, memory_standard);
+        collector.prewarm();
+        let mut seq = 0u64;
+        loop {
+            collector.update();
+            if let Some(tabs) = collector.take_tab_targets() {
+                let at = Utc::now();
+                state.write_session(&session_id, |run| run.observe_tab_titles(&tabs, at));
+            }
+
+            // Re-read every tick: a target selector can change the set mid-run.
+            let Some((pids, mut interval_ms)) =
+                state.read_session(&session_id, |run| (run.target_pids.clone(), run.sampling_interval_ms()))
+            else {
+                break;
+            };
+            let mut metrics = HashMap::new();
+            let mut roles = Vec::new();
+            for pid in &pids {
+                if let Some(m) = collector.collect_process(*pid) {
+                    metrics.insert(*pid, m);
+                }
+                if let Some(role) = collector.process_role(*pid) {
+                    roles.push((*pid, role));
+                }
+            }
+            if !roles.is_empty() {
+                let at = Utc::now();
+                state.write_session(&session_id, |run| {
+                    for (pid, role) in roles {
+                        run.record_role(pid, role, at);
+                    }
+                });
+            }
+
+            if !metrics.is_empty() {
+                let batch = BatchMetric { timestamp: Utc::now(), metrics };
+                let _ = app_handle.emit("new-metric-batch", &SessionBatch { session_id: Some(&session_id), batch: &batch });
+                seq += 1;
+                if let Some(Some(next)) = state.write_session(&session_id, |run| {
+                    if seq == 1 && mode == "browser" {
+                        run.events.push(RunEvent::now(
+                            RunEventKind::Warmup,
+                            None,
+                            json!({ "batch_timestamp": batch.timestamp.to_rfc3339() }),
+                        ));
+                    }
+                    run.ingest.observe_seq(DataSource::Native, "native", seq);
+                    let next = run.adapt_interval(&batch);
+                    run.buffer_batch(batch, DataSource::Native);
+                    next
+                }) {
+                    interval_ms = next;
+                }
+            }
+
+            std::thread::sleep(Duration::from_millis(interval_ms));
+        }
+    });
+}
+
+async fn scan_processes_blocking(
+    state: &CollectionState,
+    mode: String,
+    simulation: Option<SimulationConfig>,
+    cpu_normalization: CpuNormalization,
+    memory_standard: MemoryStandard,
+) -> Vec<ProcessInfo> {
+    let cache = state.process_scans.clone();
+    tokio::task::spawn_blocking(move || {
+        // Simulated process lists are cheap and depend on the run's simulation config.
+        if mode == "simulate" {
+            let mut collector = create_collector_with(&mode, simulation.as_ref(), cpu_normalization, memory_standard);
+            return collector.scan_processes(&mode);
+        }
+        cache.scan(&mode, cpu_normalization, memory_standard, SCAN_MAX_AGE)
+    })
+    .await
+    .unwrap_or_default()
+}
+
+// Whether the sidecar streams samples for this mode (see the branches in start_collection_with).
+fn mode_uses_sidecar(mode: &str) -> bool {
+    mode != "browser" && mode != "simulate" && !cfg!(target_os = "macos")
+}
+
+// Point the shared sidecar at every session it feeds: the union of their PIDs at the shortest
+// interval (slower sessions skip ticks on ingest). Stops it when no such session is left.
+fn sync_sidecar(state: &CollectionState) -> Result<Option<u64>, String> {
+    let mut runs: Vec<(String, Vec<u32>, u64, CpuNormalization, MemoryStandard)> = state
+        .read_each(|run| {
+            mode_uses_sidecar(&run.mode).then(|| {
+                (run.started_at.clone(), run.target_pids.clone(), run.interval_ms, run.cpu_normalization, run.memory_standard)
+            })
+        })
+        .into_iter()
+        .flatten()
+        .collect();
+    runs.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut seq = None;
+    let cmd = match runs.last() {
+        None => json!({ "action": "stop" }),
+        // The sidecar has one normalization and memory standard; the newest session's settings win.
+        Some((_, _, _, cpu_normalization, memory_standard)) => {
+            let next = state.sidecar_seq.fetch_add(1, Ordering::SeqCst) + 1;
+            seq = Some(next);
+            json!({
+                "action": "start",
+                "seq": next,
+                "pids": runs.iter().flat_map(|r| r.1.iter().copied()).collect::<Vec<u32>>(),
+                "interval": runs.iter().map(|r| r.2).min().unwrap_or(1000) as f64 / 1000.0,
+                "cpu_normalization": cpu_normalization,
+                "memory_standard": memory_standard
+            })
+        }
+    };
+    let mut child = safe_lock(&state.child);
+    let Some(child) = child.as_mut() else { return Ok(None) };
+    let cmd_str = cmd.to_string() + "\n";
+    log_debug!("Sending command to sidecar: {}", cmd_str);
+    child.write(cmd_str.as_bytes()).map_err(|e| e.to_string())?;
+    Ok(seq)
+}
+
+// Move the running sidecar to the shortest interval its sessions currently sample at, after
+// adaptive sampling changed one. Unlike a start command this keeps the sidecar's CPU baselines.
+fn retune_sidecar(state: &CollectionState) -> Result<(), String> {
+    let Some(interval_ms) = state
+        .read_each(|run| mode_uses_sidecar(&run.mode).then(|| run.sampling_interval_ms()))
+        .into_iter()
+        .flatten()
+        .min()
+    else {
+        return Ok(());
+    };
+    let cmd = json!({ "action": "set_interval", "interval": interval_ms as f64 / 1000.0 });
+    let mut child = safe_lock(&state.child);
+    let Some(child) = child.as_mut() else { return Ok(()) };
+    child.write((cmd.to_string() + "\n").as_bytes()).map_err(|e| e.to_string())
+}
+
+// How long start_collection waits for the sidecar to acknowledge its start command. Covers a
+// cold sidecar spawn, which unpacks the bundled interpreter first.
+const SIDECAR_ACK_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn parse_sidecar_ack(data: &Value) -> Option<SidecarAck> {
+    let pids = |key: &str| -> Vec<u32> {
+        data[key].as_array().map(|a| a.iter().filter_map(|v| v.as_u64()).map(|v| v as u32).collect()).unwrap_or_default()
+    };
+    Some(SidecarAck {
+        seq: data["seq"].as_u64()?,
+        pids: pids("pids"),
+        dropped_pids: pids("dropped_pids"),
+        interval_ms: (data["interval"].as_f64()? * 1000.0).round() as u64,
+    })
+}
+
+/// Record what the sidecar applied on each sidecar-fed run. A run whose effective interval or
+/// PID set differs from its request gets a `SamplingAdjusted` event and a `sampling-adjusted`
+/// warning, once per change.
+fn apply_sidecar_ack<R: tauri::Runtime>(app_handle: &AppHandle<R>, state: &CollectionState, ack: SidecarAck) {
+    let mut warnings = Vec::new();
+    state.write_each(|run| {
+        if !mode_uses_sidecar(&run.mode) {
+            return;
+        }
+        let effective = EffectiveSampling {
+            interval_ms: ack.interval_ms,
+            pids: run.target_pids.iter().copied().filter(|p| ack.pids.contains(p)).collect(),
+            dropped_pids: run.target_pids.iter().copied().filter(|p| ack.dropped_pids.contains(p)).collect(),
+        };
+        if run.effective_sampling.as_ref() == Some(&effective) {
+            return;
+        }
+        if effective.interval_ms != run.interval_ms || effective.pids.len() != run.target_pids.len() {
+            let detail = json!({
+                "requested_interval_ms": run.interval_ms,
+                "effective_interval_ms": effective.interval_ms,
+                "requested_pids": run.target_pids,
+                "effective_pids": effective.pids,
+                "dropped_pids": effective.dropped_pids,
+            });
+            run.events.push(RunEvent::now(RunEventKind::SamplingAdjusted, None, detail.clone()));
+            warnings.push(json!({ "session_id": run.session_id, "detail": detail }));
+        }
+        run.effective_sampling = Some(effective);
+    });
+    *safe_lock(&state.sidecar_ack) = Some(ack);
+    for warning in warnings {
+        let _ = app_handle.emit("sampling-adjusted", warning);
+    }
+}
+
+// Wait until the sidecar acknowledged start command `seq` (or a later one).
+async fn await_sidecar_ack(state: &CollectionState, seq: u64) -> bool {
+    let deadline = std::time::Instant::now() + SIDECAR_ACK_TIMEOUT;
+    loop {
+        if safe_lock(&state.sidecar_ack).as_ref().is_some_and(|a| a.seq >= seq) {
+            return true;
+        }
+        if std::time::Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(Duration::from_millis(25)).await;
+    }
+}
+
+// Re-resolve the run's target selector periodically, updating target_pids (and the sidecar's PID list)
+// and recording each membership change. Explicit selector PIDs are never removed.
+fn spawn_selector_refresh(state: CollectionState, selector: ProcessSelector, every_seconds: u64, session_id: SessionId) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(Duration::from_secs(every_seconds)).await;
+            let Some((mode, simulation, cpu_normalization, memory_standard)) = state.read_session(&session_id, |run| {
+                (run.mode.clone(), run.simulation.clone(), run.cpu_normalization, run.memory_standard)
+            }) else {
+                break;
+            };
+            let matched = selector
+                .resolve(scan_processes_blocking(&state, mode.clone(), simulation, cpu_normalization, memory_standard).await);
+
+            // Processes another session records stay with it.
+            let mut next: Vec<u32> = selector.pids.clone();
+            for p in &matched {
+                if !next.contains(&p.pid) && state.owner_of(p.pid).is_none_or(|owner| owner == session_id) {
+                    next.push(p.pid);
+                }
+            }
+            // Diff and apply in one step so concurrent ingest sees either the old or the new set.
+            let changed = state.write_session(&session_id, |run| {
+                let current = &run.target_pids;
+                let added: Vec<u32> = next.iter().copied().filter(|p| !current.contains(p)).collect();
+                let removed: Vec<u32> = current.iter().copied().filter(|p| !next.contains(p)).collect();
+                if added.is_empty() && removed.is_empty() {
+                    return false;
+                }
+                run.target_pids = next.clone();
+                for p in matched.into_iter().filter(|p| added.contains(&p.pid)) {
+                    if !run.process_snapshot.iter().any(|s| s.pid == p.pid) {
+                        run.process_snapshot.push(p);
+                    }
+                }
+                run.events.push(RunEvent::now(
+                    RunEventKind::MembershipChanged,
+                    None,
+                    json!({ "added": added, "removed": removed }),
+                ));
+                run.membership.push(MembershipChange {
+                    at: Utc::now().to_rfc3339(),
+                    added,
+                    removed,
+                });
+                true
+            });
+            match changed {
+                None => break,
+                Some(false) => continue,
+                Some(true) => {}
+            }
+
+            if mode_uses_sidecar(&mode) {
+                let _ = sync_sidecar(&state);
+            }
+        }
+    });
+}
+
+/// Expand a selection spec into the matching processes for `mode` (defaults to "system").
+#[tauri::command]
+pub async fn resolve_process_selection(
+    db: State<'_, Database>,
+    state: State<'_, CollectionState>,
+    selector: ProcessSelector,
+    mode: Option<String>,
+) -> Result<Vec<ProcessInfo>, PerfSightError> {
+    if selector.is_empty() {
+        return Err(PerfSightError::invalid_input("selector", "set pids, proc_types or name_contains"));
+    }
+    let mode = mode.unwrap_or_else(|| "system".to_string());
+    let cpu_normalization = Settings::load(db.inner()).cpu_normalization();
+    Ok(selector.resolve(scan_processes_blocking(state.inner(), mode, None, cpu_normalization, MemoryStandard::Rss).await))
+}
+
+/// Aliases `apply_role_templates` would give, without starting a run. `templates` previews
+/// unsaved edits (default: the saved ones); `pids` limits it to the processes about to be selected.
+#[tauri::command]
+pub async fn preview_role_assignment(
+    db: State<'_, Database>,
+    state: State<'_, CollectionState>,
+    mode: Option<String>,
+    pids: Option<Vec<u32>>,
+    templates: Option<Vec<RoleTemplate>>,
+) -> Result<Vec<RoleAssignment>, PerfSightError> {
+    let settings = Settings::load(db.inner());
+    let templates = templates.unwrap_or(settings.role_templates.clone());
+    roles::validate_templates(&templates)?;
+    let mode = mode.unwrap_or_else(|| "system".to_string());
+    let mut processes =
+        scan_processes_blocking(state.inner(), mode, None, settings.cpu_normalization(), MemoryStandard::Rss).await;
+    if let Some(pids) = pids {
+        processes.retain(|p| pids.contains(&p.pid));
+    }
+    Ok(roles::assign(&templates, &processes))
+}
+
+// Append the PIDs `target` doesn't have yet, in order.
+fn add_target_pids(target: &mut Vec<u32>, pids: impl IntoIterator<Item = u32>) {
+    for pid in pids {
+        if !target.contains(&pid) {
+            target.push(pid);
+        }
+    }
+}
+
+/// Start a run from a config as a new session, returning its id. Runs can overlap as long as
+/// they record different PIDs. Shared by the `start_collection` command and headless mode.
+pub async fn start_collection_with<R: tauri::Runtime>(
+    app_handle: AppHandle<R>,
+    state: &CollectionState,
+    db: &Database,
+    mut config: CollectionConfig
+) -> Result<SessionId, PerfSightError> {
+    log_info!("Starting collection...");
+    let simulation = if config.mode == "simulate" {
+        Some(config.simulation.clone().unwrap_or_default())
     } else {
-        safe_lock(&state.log_metrics).clear();
+        None
+    };
+    let cpu_normalization = Settings::load(db).cpu_normalization();
+    // Expand a rule-based selection into concrete PIDs before validation.
+    let selector = config.target_selector.clone().filter(|s| !s.is_empty());
+    if let Some(sel) = &selector {
+        let matched = sel.resolve(
+            scan_processes_blocking(state, config.mode.clone(), simulation.clone(), cpu_normalization, config.memory_standard)
+                .await,
+        );
+        // Explicit PIDs usually match the rules too; each PID is recorded once.
+        add_target_pids(&mut config.target_pids, sel.pids.iter().copied());
+        // Matches already recorded by another session stay with it.
+        add_target_pids(
+            &mut config.target_pids,
+            matched.iter().map(|p| p.pid).filter(|pid| state.owner_of(*pid).is_none()),
+        );
+    }
+    // Self-monitoring: the app now; the sidecar is added once it runs (see below).
+    if config.include_self && !config.target_pids.contains(&std::process::id()) {
+        config.target_pids.push(std::process::id());
+    }
+    // Folder defaults fill what the caller left unset, nearest folder first.
+    let mut folder_defaults = serde_json::Map::new();
+    if let Some(folder) = config.folder_path.clone() {
+        for (path, settings) in db.folder_settings_chain(&folder)? {
+            for field in config.apply_folder_defaults(&settings) {
+                folder_defaults.insert(field.to_string(), json!(path));
+            }
+        }
+    }
+    if config.interval_ms == 0 {
+        config.interval_ms = Settings::load(db).default_interval_ms;
+    }
+    let mut warnings = config.validate()?;
+    let test_context_limits = match config.test_context.as_mut() {
+        Some(tc) => {
+            let limits = Settings::load(db).test_context_limits;
+            let cuts = tc.sanitize(&limits)?;
+            for c in &cuts {
+                warnings.push(match c.field.as_str() {
+                    "tags" => format!("test_context.tags: kept {} of {} tags", c.kept, c.original),
+                    field => format!("test_context.{} cut from {} to {} characters", field, c.original, c.kept),
+                });
+            }
+            Some((limits, cuts))
+        }
+        None => None,
+    };
+    for w in &warnings {
+        log_warn!("start_collection: {}", w);
+    }
+    if let Some((pid, owner)) = state.pid_conflict(&config.target_pids) {
+        return Err(PerfSightError::invalid_input(
+            "target_pids",
+            format!("pid {} is already being recorded by session {}", pid, owner),
+        ));
     }
+    let own_pids: Vec<u32> = self_pids(state).into_iter().filter(|p| config.target_pids.contains(p)).collect();
 
-    // Capture a process snapshot for the selected PIDs (best effort).
-    let snapshot = tokio::task::spawn_blocking({
+    let membership = match &selector {
+        Some(_) => vec![MembershipChange {
+            at: Utc::now().to_rfc3339(),
+            added: config.target_pids.clone(),
+            removed: Vec::new(),
+        }],
+        None => Vec::new(),
+    };
+
+    // Compile regexes for log metrics (patterns were checked by validate()).
+    let log_metrics = config
+        .log_metric_configs
+        .take()
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|cfg| compile_log_pattern(&cfg.pattern).ok().map(|re| (cfg, re)))
+        .collect();
+
+    // Capture a process snapshot for the selected PIDs (best effort), and which page target
+    // lives in which renderer.
+    let (snapshot, tab_pids) = tokio::task::spawn_blocking({
         let mode = config.mode.clone();
         let pids = config.target_pids.clone();
         let aliases = config.process_aliases.clone().unwrap_or_default();
+        let simulation = simulation.clone();
+        let memory_standard = config.memory_standard;
         move || {
             let alias_map: std::collections::HashMap<u32, String> = aliases
                 .into_iter()
                 .map(|a| (a.pid, a.alias))
                 .collect();
-            let mut collector = create_collector(&mode);
+            let mut collector = create_collector_with(&mode, simulation.as_ref(), cpu_normalization, memory_standard);
             let list = collector.scan_processes(&mode);
-            list.into_iter()
+            let tab_pids: HashMap<String, u32> = list
+                .iter()
+                .flat_map(|p| p.targets.iter().map(move |t| (t.id.clone(), p.pid)))
+                .collect();
+            let snapshot = list
+                .into_iter()
                 .filter(|p| pids.contains(&p.pid))
                 .map(|mut p| {
                     if let Some(a) = alias_map.get(&p.pid) {
@@ -1050,130 +5295,489 @@ pub async fn start_collection(
                     }
                     p
                 })
-                .collect::<Vec<ProcessInfo>>()
+                .collect::<Vec<ProcessInfo>>();
+            (snapshot, tab_pids)
         }
     })
     .await
     .ok()
     .unwrap_or_default();
-    *safe_lock(&state.process_snapshot) = snapshot;
-
-    *safe_lock(&state.is_running) = true;
-    safe_lock(&state.buffer).clear();
 
-    // macOS System API: use native Rust collector for accurate CPU + RSS ("Real Memory Size").
-    // This avoids psutil RSS/normalization mismatches.
-    #[cfg(target_os = "macos")]
-    if config.mode != "browser" {
-        let app_handle_clone = app_handle.clone();
-        let state_clone = state.inner().clone();
-        let mode = config.mode.clone();
-        let interval_ms = config.interval_ms;
+    // Role templates name the selected processes the caller left unnamed.
+    let mut snapshot = snapshot;
+    for p in snapshot.iter_mut().filter(|p| own_pids.contains(&p.pid)) {
+        p.proc_type = SELF_PROC_TYPE.to_string();
+    }
+    let mut process_aliases = config.process_aliases.clone().unwrap_or_default();
+    let role_assignments = if config.apply_role_templates {
+        let unnamed: Vec<ProcessInfo> = snapshot.iter().filter(|p| p.alias.is_none()).cloned().collect();
+        let assigned = roles::assign(&Settings::load(db).role_templates, &unnamed);
+        for a in &assigned {
+            if let Some(p) = snapshot.iter_mut().find(|p| p.pid == a.pid) {
+                p.alias = Some(a.alias.clone());
+            }
+            process_aliases.retain(|pa| pa.pid != a.pid);
+            process_aliases.push(ProcessAlias { pid: a.pid, alias: a.alias.clone() });
+        }
+        assigned
+    } else {
+        Vec::new()
+    };
+
+    let tab_titles: HashMap<u32, (String, String)> = snapshot
+        .iter()
+        .filter(|p| config.mode == "browser" && !p.targets.is_empty())
+        .map(|p| (p.pid, tab_title(&p.targets)))
+        .filter(|(_, t)| !t.is_empty())
+        .map(|(pid, t)| (pid, (t.clone(), t)))
+        .collect();
+
+    // Browser runs whose memory only the extension can supply need one that is sending: strict
+    // starts fail without it, others start and report when it attaches.
+    let extension_dependency = if config.mode == "browser" {
         let pids = config.target_pids.clone();
+        let pids = tokio::task::spawn_blocking(move || pids_needing_extension(&pids)).await.unwrap_or_default();
+        let missing = app_handle.state::<IngestServerState>().active_extensions().is_empty();
+        match pids.is_empty() {
+            true => None,
+            false if missing && config.strict => return Err(PerfSightError::ExtensionNotConnected { pids }),
+            false => Some(ExtensionDependency { pids, missing_since: missing.then(Utc::now), attached_at: None }),
+        }
+    } else {
+        None
+    };
+
+    // GPU adapter description for browser runs (best effort; needs the debugging port).
+    let gpu_info = if config.mode == "browser" {
+        tokio::task::spawn_blocking(|| CdpClient::get_gpu_info().map_err(|e| log_warn!("GPU info unavailable: {}", e)).ok())
+            .await
+            .ok()
+            .flatten()
+    } else {
+        None
+    };
+    let cdp_process_info = if config.mode == "browser" && config.snapshot_cdp_process_info {
+        tokio::task::spawn_blocking(|| {
+            CdpClient::get_process_info_debug().map_err(|e| log_warn!("CDP process info unavailable: {}", e)).ok()
+        })
+        .await
+        .ok()
+        .flatten()
+    } else {
+        None
+    };
 
-        tauri::async_runtime::spawn_blocking(move || {
-            let mut collector = create_collector(&mode);
-            while *safe_lock(&state_clone.is_running) {
-                collector.update();
+    // Optional live metric sink (config wins over the saved setting).
+    let sink_config: Option<MetricSinkConfig> = config
+        .metric_sink
+        .clone()
+        .or_else(|| db.get_setting_as(SETTING_METRIC_SINK).ok().flatten());
+    let metric_sink = match sink_config.filter(|c| !c.url.trim().is_empty()) {
+        Some(sink_config) => {
+            let tags: HashMap<u32, (String, String)> = snapshot
+                .iter()
+                .map(|p| (p.pid, (p.alias.clone().unwrap_or_default(), p.proc_type.clone())))
+                .collect();
+            Some(MetricSink::start(sink_config, &config.mode, tags)?)
+        }
+        None => None,
+    };
 
-                let mut metrics = HashMap::new();
-                for pid in &pids {
-                    if let Some(m) = collector.collect_process(*pid) {
-                        metrics.insert(*pid, m);
+    let session_id: SessionId = uuid::Uuid::new_v4().simple().to_string();
+    let stream_file = match config.stream_to_file.as_deref() {
+        Some(target) => {
+            let path = if target.eq_ignore_ascii_case("auto") {
+                let dir = app_handle.path().app_local_data_dir().map_err(|e| e.to_string())?.join("streams");
+                dir.join(format!("{}-{}.jsonl", Utc::now().format("%Y%m%d-%H%M%S"), session_id))
+            } else {
+                std::path::PathBuf::from(target)
+            };
+            match StreamFile::start(&path) {
+                Ok(file) => Some(file),
+                Err(e) => {
+                    if let Some(sink) = metric_sink {
+                        sink.shutdown();
                     }
+                    return Err(PerfSightError::invalid_input("stream_to_file", e));
                 }
+            }
+        }
+        None => None,
+    };
+    let run = ActiveRun {
+        folder_defaults,
+        session_id: session_id.clone(),
+        started_at: Utc::now().to_rfc3339(),
+        target_pids: config.target_pids.clone(),
+        mode: config.mode.clone(),
+        interval_ms: config.interval_ms,
+        app_version: app_handle.package_info().version.to_string(),
+        test_context: config
+            .test_context
+            .as_ref()
+            .map(|tc| serde_json::to_value(tc).unwrap_or_else(|_| json!({}))),
+        test_context_limits,
+        process_snapshot: snapshot,
+        process_aliases,
+        folder_path: config.folder_path.clone(),
+        self_pids: own_pids,
+        stop_after_seconds: config.stop_after_seconds,
+        log_metrics,
+        log_parse_failures: HashMap::new(),
+        log_ingest: LogIngestStats::default(),
+        budgets: config.budgets.clone().unwrap_or_default(),
+        simulation: simulation.clone(),
+        cpu_normalization,
+        memory_standard: config.memory_standard,
+        target_selector: selector.clone(),
+        membership,
+        buffer: Vec::new(),
+        latest_samples: HashMap::new(),
+        metric_sink,
+        stream_file,
+        ingest: IngestCounters::default(),
+        events: vec![RunEvent::now(
+            RunEventKind::Started,
+            None,
+            json!({ "mode": config.mode, "target_pids": config.target_pids, "interval_ms": config.interval_ms }),
+        )],
+        gpu_info,
+        cdp_process_info,
+        ws_clients: Vec::new(),
+        extension_dependency: extension_dependency.clone(),
+        stalled_since: None,
+        interrupted_by_exit: false,
+        coverage: HashMap::new(),
+        tab_pids,
+        phase_metric: config.phase_metric.clone().filter(|m| !m.trim().is_empty()),
+        phase_active: None,
+        role_assignments,
+        persisted_batches: 0,
+        chunk_seq: 0,
+        chunk_lock: Arc::new(Mutex::new(())),
+        effective_sampling: None,
+        adaptive: config
+            .adaptive_sampling
+            .clone()
+            .filter(|_| config.mode != "browser")
+            .map(|policy| AdaptiveInterval::new(policy, config.interval_ms)),
+        process_roles: HashMap::new(),
+        exits: HashMap::new(),
+        tab_titles,
+    };
+    // Another start may have won the race while we were scanning processes.
+    if let Err(run) = state.begin(run) {
+        if let Some(sink) = run.metric_sink {
+            sink.shutdown();
+        }
+        if let Some(file) = run.stream_file {
+            file.shutdown();
+        }
+        return Err(PerfSightError::invalid_input("target_pids", "a PID is already being recorded by another session"));
+    }
 
-                if !metrics.is_empty() {
-                    let batch = BatchMetric { timestamp: Utc::now(), metrics };
-                    let _ = app_handle_clone.emit("new-metric-batch", &batch);
-                    safe_lock(&state_clone.buffer).push(batch);
-                }
+    if let Some(dep) = extension_dependency.filter(|d| d.missing_since.is_some()) {
+        log_warn!("start_collection: browser extension not connected; no memory for pids {:?}", dep.pids);
+        let _ = app_handle.emit(
+            "browser-data-source-missing",
+            &json!({ "session_id": session_id, "pids": dep.pids }),
+        );
+    }
+    spawn_progress_ticker(app_handle.clone(), state.clone(), session_id.clone());
+    spawn_chunk_writer(app_handle.clone(), state.clone(), session_id.clone());
+    if let Some(sel) = selector {
+        if let Some(every) = sel.refresh_interval() {
+            spawn_selector_refresh(state.clone(), sel, every, session_id.clone());
+        }
+    }
 
-                std::thread::sleep(Duration::from_millis(interval_ms));
-            }
-        });
+    // Simulated runs never touch the sidecar; they use the native loop on every platform.
+    if config.mode == "simulate" {
+        spawn_native_collection(app_handle.clone(), state.clone(), &config, simulation, cpu_normalization, session_id.clone());
+        return Ok(session_id);
+    }
 
-        return Ok("Started".to_string());
+    // macOS System API: use native Rust collector for accurate CPU + RSS ("Real Memory Size").
+    // This avoids psutil RSS/normalization mismatches.
+    #[cfg(target_os = "macos")]
+    if config.mode != "browser" {
+        spawn_native_collection(app_handle.clone(), state.clone(), &config, simulation, cpu_normalization, session_id.clone());
+        return Ok(session_id);
     }
     
-    let mut child_guard = safe_lock(&state.child);
+    // 1. Ensure sidecar is running (the guard must not live across the ack wait below)
+    {
+        let mut child_guard = safe_lock(&state.child);
     
-    // 1. Ensure sidecar is running
-    if child_guard.is_none() {
-        println!("Spawning collector sidecar...");
-        let sidecar = app_handle.shell().sidecar("collector").map_err(|e| e.to_string())?;
-        let (mut rx, child) = sidecar.spawn().map_err(|e| e.to_string())?;
+        if child_guard.is_none() {
+            log_info!("Spawning collector sidecar...");
+            let sidecar = app_handle.shell().sidecar("collector").map_err(|e| e.to_string())?;
+            let (mut rx, mut child) = sidecar.spawn().map_err(|e| e.to_string())?;
+            // Recorded with each report's data sources; older collectors just ignore the action.
+            if let Err(e) = child.write(b"{\"action\":\"version\"}\n") {
+                log_warn!("Failed to ask the sidecar for its version: {}", e);
+            }
         
-        *child_guard = Some(child);
+            *child_guard = Some(child);
         
-        // Spawn listener task (Reads Stdout)
-        let app_handle_clone = app_handle.clone();
-        let state_clone = state.inner().clone();
+            // Spawn listener task (Reads Stdout)
+            let app_handle_clone = app_handle.clone();
+            let state_clone = state.clone();
         
-        tauri::async_runtime::spawn(async move {
-            println!("Sidecar listener thread started.");
-            while let Some(event) = rx.recv().await {
-                match event {
-                    CommandEvent::Stdout(line_bytes) => {
-                        let line = String::from_utf8_lossy(&line_bytes);
-                        // println!("Sidecar Output: {}", line); // Debug
-                        
-                        if let Ok(data) = serde_json::from_str::<Value>(&line) {
-                            process_metric_payload(&app_handle_clone, data, &state_clone);
+            tauri::async_runtime::spawn(async move {
+                log_debug!("Sidecar listener thread started.");
+                while let Some(event) = rx.recv().await {
+                    match event {
+                        CommandEvent::Stdout(line_bytes) => {
+                            let line = String::from_utf8_lossy(&line_bytes);
+                            handle_sidecar_line(&app_handle_clone, &state_clone, &line, Utc::now());
                         }
+                        CommandEvent::Stderr(line_bytes) => {
+                            let line = String::from_utf8_lossy(&line_bytes);
+                            log_info!("Sidecar Log: {}", line);
+                        }
+                        CommandEvent::Error(message) => {
+                            append_run_event(&state_clone, RunEventKind::CollectorError, None, json!({ "message": message }));
+                        }
+                        CommandEvent::Terminated(payload) => {
+                            append_run_event(
+                                &state_clone,
+                                RunEventKind::CollectorError,
+                                None,
+                                json!({ "message": "collector sidecar exited", "code": payload.code, "signal": payload.signal }),
+                            );
+                        }
+                        _ => {}
                     }
-                    CommandEvent::Stderr(line_bytes) => {
-                        let line = String::from_utf8_lossy(&line_bytes);
-                        eprintln!("Sidecar Log: {}", line);
-                    }
-                    _ => {}
                 }
-            }
-            println!("Sidecar listener exited.");
+                log_info!("Sidecar listener exited.");
+            });
+        }
+
+    }
+
+    // A self-monitoring run records the sidecar too (unless another session already does).
+    let sidecar_pid = safe_lock(&state.child).as_ref().map(|c| c.pid());
+    if let Some(pid) = sidecar_pid.filter(|pid| config.include_self && config.mode != "browser" && state.owner_of(*pid).is_none()) {
+        state.write_session(&session_id, |run| {
+            run.target_pids.push(pid);
+            run.self_pids.push(pid);
         });
     }
 
-    // 2. Send Start Command to Sidecar
+    // 2. Send Start Command to Sidecar (covering every sidecar-fed session)
     // Only start sidecar collection if we are NOT in browser mode (or if we want hybrid, but currently sidecar reports 0 for chrome)
     if config.mode != "browser" {
-        if let Some(child) = child_guard.as_mut() {
-            let cmd = json!({
-                "action": "start",
-                "pids": config.target_pids,
-                "interval": config.interval_ms as f64 / 1000.0
-            });
-            let cmd_str = cmd.to_string() + "\n";
-            println!("Sending command to sidecar: {}", cmd_str);
-            child.write(cmd_str.as_bytes()).map_err(|e| e.to_string())?;
+        if let Some(seq) = sync_sidecar(state)? {
+            if !await_sidecar_ack(state, seq).await {
+                // Nothing was sampled; drop the run instead of recording an empty one.
+                if let Some(run) = state.finish(&session_id) {
+                    if let Some(sink) = run.metric_sink {
+                        sink.shutdown();
+                    }
+                    if let Some(file) = run.stream_file {
+                        file.shutdown();
+                    }
+                }
+                let _ = app_handle.state::<Database>().delete_run_chunks(&session_id);
+                let _ = sync_sidecar(state);
+                return Err(PerfSightError::SidecarNotResponding {
+                    timeout_ms: SIDECAR_ACK_TIMEOUT.as_millis() as u64,
+                });
+            }
         }
     } else {
-        println!("Browser mode: Skipping Sidecar collection (relying on Extension).");
+        log_info!("Browser mode: Skipping Sidecar collection (relying on Extension).");
+    }
+
+    Ok(session_id)
+}
+
+/// App exit hook: save every running session (flagged `interrupted_by_exit`), then stop and kill
+/// the sidecar and close extension connections, so no child is orphaned and no buffer is lost.
+pub fn shutdown_on_exit<R: tauri::Runtime>(app_handle: &AppHandle<R>) {
+    let state: State<CollectionState> = app_handle.state();
+    let db: State<Database> = app_handle.state();
+    for session_id in state.session_ids() {
+        state.write_session(&session_id, |run| run.interrupted_by_exit = true);
+        match stop_collection_and_save(app_handle, state.inner(), db.inner(), &session_id) {
+            Ok(result) => log_info!("Saved session {} on exit (report {:?})", session_id, result.report_id),
+            // The chunks already written stay behind and are recovered at the next start.
+            Err(e) => log_error!("Failed to save session {} on exit: {}", session_id, e),
+        }
+    }
+    if let Some(mut child) = safe_lock(&state.child).take() {
+        let _ = child.write(b"{\"action\":\"stop\"}\n");
+        if let Err(e) = child.kill() {
+            log_warn!("Failed to kill the collector sidecar: {}", e);
+        }
     }
+    crate::ws_server::close_connections(app_handle);
+}
+
+/// Outcome of stopping a run. When nothing was saved, `reasons` says why (from the ingest counters).
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct StopResult {
+    pub saved: bool,
+    pub report_id: Option<i64>,
+    /// Buffered batches at stop time.
+    pub samples: usize,
+    pub reasons: Vec<String>,
+}
 
-    Ok("Started".to_string())
+impl StopResult {
+    fn empty(reasons: Vec<String>) -> Self {
+        StopResult { saved: false, report_id: None, samples: 0, reasons }
+    }
 }
 
+/// Stop `session_id` (the only active session when omitted) and save its report.
 #[tauri::command]
-pub async fn stop_collection(
-    state: State<'_, CollectionState>,
-    db: State<'_, Database>
-) -> Result<String, String> {
-    println!("Stopping collection...");
-    
-    // 1. Send Stop Command
-    let mut child_guard = safe_lock(&state.child);
-    if let Some(child) = child_guard.as_mut() {
-        let cmd = json!({ "action": "stop" }).to_string() + "\n";
-        let _ = child.write(cmd.as_bytes());
+pub async fn stop_collection(app_handle: AppHandle, session_id: Option<String>) -> Result<StopResult, PerfSightError> {
+    let Some(session_id) = app_handle.state::<CollectionState>().resolve_session(session_id.as_deref())? else {
+        return Ok(StopResult::empty(vec!["no active run".to_string()]));
+    };
+    // Saving parses and analyzes the whole buffer; keep it off the async runtime.
+    run_blocking(&app_handle, move |app_handle, db| {
+        let state: State<CollectionState> = app_handle.state();
+        Ok(stop_collection_and_save(app_handle, state.inner(), db, &session_id)?)
+    })
+    .await
+}
+
+// Why a run ended without samples, most specific first.
+fn empty_run_reasons(run: &ActiveRun) -> Vec<String> {
+    let ingest = &run.ingest;
+    let mut reasons = Vec::new();
+    if run.target_pids.is_empty() {
+        reasons.push("no target PIDs were selected".to_string());
     }
-    
-    *safe_lock(&state.is_running) = false;
+    if ingest.websocket_ignored > 0 {
+        reasons.push(format!("websocket connected but mode was {} ({} points ignored)", run.mode, ingest.websocket_ignored));
+    }
+    if run.mode == "browser" && run.ws_clients.is_empty() && ingest.websocket_samples == 0 {
+        reasons.push("browser extension never connected".to_string());
+    }
+    if mode_uses_sidecar(&run.mode) {
+        match &run.effective_sampling {
+            Some(e) if e.pids.is_empty() && !run.target_pids.is_empty() => {
+                reasons.push(format!("sidecar dropped every target PID (not running): {:?}", e.dropped_pids))
+            }
+            _ if ingest.sidecar_samples == 0 && ingest.sidecar_protocol_errors > 0 => reasons.push(format!(
+                "sidecar output could not be decoded ({} lines)",
+                ingest.sidecar_protocol_errors
+            )),
+            _ if ingest.sidecar_samples == 0 => reasons.push("sidecar never produced data".to_string()),
+            _ => {}
+        }
+    } else if run.mode != "browser" && ingest.native_samples == 0 {
+        reasons.push("native collector never produced data".to_string());
+    }
+    let foreign = ingest.dropped_samples.saturating_sub(ingest.websocket_ignored);
+    if foreign > 0 {
+        reasons.push(format!("no samples matched target_pids ({} points for other PIDs dropped)", foreign));
+    }
+    if reasons.is_empty() {
+        reasons.push("no samples were received".to_string());
+    }
+    reasons
+}
+
+// Sources that delivered samples to the run, with what produced them.
+fn run_data_sources<R: tauri::Runtime>(app_handle: &AppHandle<R>, state: &CollectionState, run: &ActiveRun) -> Vec<DataSourceInfo> {
+    let ingest = &run.ingest;
+    let mut sources = Vec::new();
+    if ingest.sidecar_samples > 0 || ingest.sidecar_protocol_errors > 0 {
+        sources.push(DataSourceInfo {
+            source: "sidecar".to_string(),
+            backend: "psutil".to_string(),
+            samples: ingest.sidecar_samples,
+            last_sample_at: ingest.last_sidecar.map(|t| t.to_rfc3339()),
+            version: safe_lock(&state.sidecar_version).clone(),
+            sequence: ingest.sequence_stats(DataSource::Sidecar),
+            protocol_errors: (ingest.sidecar_protocol_errors > 0).then_some(ingest.sidecar_protocol_errors),
+            ..Default::default()
+        });
+    }
+    if ingest.websocket_samples > 0 {
+        let server: State<IngestServerState> = app_handle.state();
+        let mut extension_versions: Vec<String> =
+            run.ws_clients.iter().filter_map(|c| c.extension_version.clone()).collect();
+        extension_versions.sort();
+        extension_versions.dedup();
+        sources.push(DataSourceInfo {
+            source: "websocket".to_string(),
+            backend: "extension".to_string(),
+            samples: ingest.websocket_samples,
+            last_sample_at: ingest.last_websocket.map(|t| t.to_rfc3339()),
+            ws_port: *safe_lock(&server.ws_port),
+            extension_versions,
+            sequence: ingest.sequence_stats(DataSource::Websocket),
+            ..Default::default()
+        });
+    }
+    if ingest.http_samples > 0 {
+        let server: State<IngestServerState> = app_handle.state();
+        sources.push(DataSourceInfo {
+            source: "http".to_string(),
+            backend: "http".to_string(),
+            samples: ingest.http_samples,
+            last_sample_at: ingest.last_http.map(|t| t.to_rfc3339()),
+            http_port: *safe_lock(&server.http_port),
+            sequence: ingest.sequence_stats(DataSource::Http),
+            ..Default::default()
+        });
+    }
+    if ingest.native_samples > 0 {
+        sources.push(DataSourceInfo {
+            source: "native".to_string(),
+            backend: if run.simulation.is_some() { "simulate" } else { "sysinfo" }.to_string(),
+            samples: ingest.native_samples,
+            last_sample_at: ingest.last_native.map(|t| t.to_rfc3339()),
+            sequence: ingest.sequence_stats(DataSource::Native),
+            ..Default::default()
+        });
+    }
+    sources
+}
+
+/// Stop one session and persist its buffer as its own report. When nothing was collected no
+/// report is saved, the result carries the reasons and "collection-empty" is emitted. Shared by
+/// the `stop_collection` command, scenarios and headless mode.
+pub fn stop_collection_and_save<R: tauri::Runtime>(
+    app_handle: &AppHandle<R>,
+    state: &CollectionState,
+    db: &Database,
+    session_id: &str,
+) -> Result<StopResult, String> {
+    log_info!("Stopping collection {}...", session_id);
+
+    // Detach the run in one step; anything ingested after this point is dropped.
+    let Some(mut run) = state.finish(session_id) else {
+        log_info!("Stopped (No active run).");
+        return Ok(StopResult::empty(vec!["no active run".to_string()]));
+    };
+
+    // 1. Narrow the sidecar to the remaining sessions (stops it when none is left)
+    let _ = sync_sidecar(state);
+
+    // Drain the metric sink before building meta so points_dropped is final.
+    let metric_sink_stats = run.metric_sink.take().map(|s| s.shutdown());
+    let stream_file_stats = run.stream_file.take().map(|f| f.shutdown());
+    let stop_detail = if run.interrupted_by_exit {
+        json!({ "samples": run.buffer.len(), "reason": "app_exit" })
+    } else {
+        json!({ "samples": run.buffer.len() })
+    };
+    run.events.push(RunEvent::now(RunEventKind::Stopped, None, stop_detail));
     
     // 2. Save Report
-    let mut buffer = safe_lock(&state.buffer);
+    let samples = run.buffer.len();
+    let buffer = std::mem::take(&mut run.buffer);
     if !buffer.is_empty() {
-        let default_title = format!("Test Run - {}", Utc::now().format("%Y-%m-%d %H:%M:%S"));
-        let title = match safe_lock(&state.test_context)
+        let default_title = format!("{}{}", AUTO_TITLE_PREFIX, Utc::now().format("%Y-%m-%d %H:%M:%S"));
+        let title = match run
+            .test_context
             .as_ref()
             .and_then(|v| v.get("scenario_name"))
             .and_then(|v| v.as_str())
@@ -1182,19 +5786,38 @@ pub async fn stop_collection(
             Some(s) if !s.is_empty() => s.to_string(),
             _ => default_title,
         };
-        println!("Buffer size: {}. Writing to DB...", buffer.len());
+        log_debug!("Buffer size: {}. Writing to DB...", buffer.len());
 
         // Build metadata for AI-friendly analysis.
         let ended_at = Utc::now().to_rfc3339();
-        let started_at = safe_lock(&state.started_at).clone();
-        let mode = safe_lock(&state.mode).clone();
-        let interval_ms = *safe_lock(&state.interval_ms);
-        let target_pids = safe_lock(&state.target_pids).clone();
-        let process_snapshot = safe_lock(&state.process_snapshot).clone();
-        let app_version = safe_lock(&state.app_version).clone();
-        let test_context = safe_lock(&state.test_context).clone();
-        let stop_after_seconds = *safe_lock(&state.stop_after_seconds);
-        let folder_path = safe_lock(&state.folder_path).clone();
+        let started_at = Some(run.started_at.clone());
+        let mode = run.mode.clone();
+        let interval_ms = run.interval_ms;
+        // Include PIDs a target selector dropped mid-run; their samples are still in the buffer.
+        let mut target_pids = run.target_pids.clone();
+        for change in run.membership.iter() {
+            for pid in &change.added {
+                if !target_pids.contains(pid) {
+                    target_pids.push(*pid);
+                }
+            }
+        }
+        // Tabs renamed mid-run keep their most descriptive title; their derived aliases join the
+        // explicit ones (which win).
+        run.process_aliases = run.effective_aliases();
+        let mut process_snapshot = std::mem::take(&mut run.process_snapshot);
+        for p in process_snapshot.iter_mut() {
+            if let Some((_, best)) = run.tab_titles.get(&p.pid) {
+                p.title = Some(best.clone());
+            }
+            if let Some(a) = run.process_aliases.iter().find(|a| a.pid == p.pid && !a.alias.trim().is_empty()) {
+                p.alias = Some(a.alias.trim().to_string());
+            }
+        }
+        let app_version = run.app_version.clone();
+        let test_context = run.test_context.take();
+        let stop_after_seconds = run.stop_after_seconds;
+        let folder_path = run.folder_path.clone();
 
         let cpu_count = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
         let mut sys = sysinfo::System::new_all();
@@ -1215,118 +5838,818 @@ pub async fn stop_collection(
             } else { 0 }
         } else { 0 };
 
-        let meta = json!({
-            "schema_version": 1,
-            "app": { "version": app_version },
-            "versions": {
+        let mut extra = serde_json::Map::new();
+        extra.insert(
+            "versions".to_string(),
+            json!({
                 "os_version": os_version,
                 "os_long_version": long_os_version
-            },
-            "definitions": {
+            }),
+        );
+        extra.insert(
+            "definitions".to_string(),
+            json!({
                 "units": {
                     "cpu": "percent",
                     "memory": "bytes"
                 },
+                "cpu_normalization": run.cpu_normalization,
+                "memory_standard": run.memory_standard,
                 "system": {
-                    "cpu": "OS process CPU% (sysinfo). On Windows normalized to 0-100 total capacity; on macOS/Linux may exceed 100 for multi-core.",
-                    "memory": "RSS / Real Memory Size (resident set size) in bytes"
+                    "cpu": match run.cpu_normalization {
+                        CpuNormalization::TotalCapacity => "OS process CPU% normalized to 0-100 of total capacity (Task Manager style); cpu_os_usage_raw keeps the per-core sum.",
+                        CpuNormalization::PerCoreSum => "OS process CPU% summed per core; may exceed 100 on multi-core machines.",
+                    },
+                    "memory": match run.memory_standard {
+                        MemoryStandard::Rss => "RSS / Real Memory Size (resident set size) in bytes",
+                        MemoryStandard::TaskManager => "OS task manager memory in bytes (macOS phys_footprint, Windows private working set, Linux PSS); RSS where unavailable, see each sample's memory_basis",
+                    }
                 },
                 "browser": {
                     "cpu": "Chrome Task Manager-aligned CPU% when cpuch_* is present; otherwise falls back to OS CPU%",
                     "memory": "Chrome private/footprint memory in bytes when pmem_* is present; otherwise falls back to RSS"
                 }
+            }),
+        );
+        // Every metric the log configs can produce, including each named capture's.
+        let log_metric_defs: serde_json::Map<String, serde_json::Value> = run
+            .log_metrics
+            .iter()
+            .flat_map(|(cfg, _)| {
+                cfg.targets().into_iter().map(move |t| {
+                    (
+                        t.name.to_string(),
+                        json!({ "unit": t.unit, "source": "log", "config": cfg.name, "group": t.group }),
+                    )
+                })
+            })
+            .collect();
+        if !log_metric_defs.is_empty() {
+            if let Some(defs) = extra.get_mut("definitions").and_then(|d| d.as_object_mut()) {
+                defs.insert("custom_metrics".to_string(), serde_json::Value::Object(log_metric_defs));
+            }
+        }
+        if run.interrupted_by_exit {
+            extra.insert("interrupted_by_exit".to_string(), json!(true));
+        }
+        let mut env_extra = serde_json::Map::new();
+        let gpu = run.gpu_info.take().map(|g| json!(g)).unwrap_or_else(|| json!({ "name": null }));
+        env_extra.insert("gpu".to_string(), gpu);
+        if let Some(info) = run.cdp_process_info.take() {
+            env_extra.insert("cdp_process_info".to_string(), json!(info));
+        }
+        let mut collection_extra = serde_json::Map::new();
+        if !run.folder_defaults.is_empty() {
+            collection_extra.insert("folder_defaults".to_string(), Value::Object(run.folder_defaults.clone()));
+        }
+        if !run.self_pids.is_empty() {
+            collection_extra.insert("self_pids".to_string(), json!(run.self_pids));
+            if let Some(overhead) = crate::analysis::self_overhead(&buffer, &run.self_pids) {
+                extra.insert("overhead".to_string(), json!(overhead));
+            }
+        }
+        if let Some(stats) = &stream_file_stats {
+            collection_extra.insert("stream_file".to_string(), json!(stats));
+        }
+        if let Some(stats) = &metric_sink_stats {
+            collection_extra.insert("metric_sink".to_string(), json!(stats));
+        }
+        if !run.ws_clients.is_empty() {
+            collection_extra.insert("websocket_clients".to_string(), json!(run.ws_clients));
+        }
+        // Share of sampled batches that carried each PID (websocket-fed PIDs can miss ticks).
+        let sampled_batches = buffer.iter().filter(|b| b.metrics.values().any(|m| !m.is_custom_only())).count().max(1);
+        let coverage: serde_json::Map<String, Value> = run
+            .coverage
+            .iter()
+            .map(|(pid, c)| {
+                let pct = (c.samples as f64 / sampled_batches as f64 * 1000.0).round() / 10.0;
+                (pid.to_string(), json!({
+                    "samples": c.samples,
+                    "first_sample_at": c.first_sample_at.to_rfc3339(),
+                    "last_sample_at": c.last_sample_at.to_rfc3339(),
+                    "coverage_pct": pct.min(100.0)
+                }))
+            })
+            .collect();
+        if !coverage.is_empty() {
+            collection_extra.insert("coverage".to_string(), Value::Object(coverage));
+        }
+        if !run.tab_pids.is_empty() {
+            collection_extra.insert("tab_pids".to_string(), json!(run.tab_pids));
+        }
+        if !run.role_assignments.is_empty() {
+            collection_extra.insert("role_assignments".to_string(), json!(run.role_assignments));
+        }
+        if !run.log_parse_failures.is_empty() {
+            collection_extra.insert("log_metric_parse_failures".to_string(), json!(run.log_parse_failures));
+        }
+        if run.log_ingest.truncated_lines > 0 || run.log_ingest.dropped_lines > 0 {
+            collection_extra.insert("log_ingest".to_string(), json!(run.log_ingest));
+        }
+        if !run.process_roles.is_empty() {
+            let roles: HashMap<u32, ProcessRoleSummary> = run
+                .process_roles
+                .iter()
+                .map(|(pid, (_, _, summary))| {
+                    let mut summary = summary.clone();
+                    if summary.observed_ms > 0 {
+                        summary.background_fraction = summary.background_ms as f64 / summary.observed_ms as f64;
+                    }
+                    (*pid, summary)
+                })
+                .collect();
+            collection_extra.insert("process_roles".to_string(), json!(roles));
+        }
+        if !run.exits.is_empty() {
+            // Exits still being resolved when the run stopped are saved as Unknown.
+            let lifecycle: HashMap<u32, ProcessLifecycle> = run
+                .exits
+                .iter()
+                .map(|(pid, (exited_at, termination))| {
+                    let entry = ProcessLifecycle {
+                        last_sample_at: run.coverage.get(pid).map(|c| c.last_sample_at),
+                        exited_at: *exited_at,
+                        termination: termination.clone().unwrap_or_else(ProcessTermination::unknown),
+                    };
+                    (*pid, entry)
+                })
+                .collect();
+            collection_extra.insert("process_lifecycle".to_string(), json!(lifecycle));
+        }
+        if let Some(dep) = &run.extension_dependency {
+            collection_extra.insert("extension_dependency".to_string(), json!(dep));
+        }
+        if let Some((limits, cuts)) = &run.test_context_limits {
+            collection_extra.insert("test_context_limits".to_string(), json!({
+                "limits": limits,
+                "truncated": cuts,
+            }));
+        }
+        if let Some(adaptive) = &run.adaptive {
+            collection_extra.insert("adaptive_sampling".to_string(), json!({
+                "policy": adaptive.policy(),
+                "skipped_ticks": adaptive.skipped_ticks,
+                "adjustments": adaptive.adjustments,
+                "final_interval_ms": adaptive.current_ms(),
+            }));
+        }
+        if let Some(effective) = &run.effective_sampling {
+            collection_extra.insert("effective_interval_ms".to_string(), json!(effective.interval_ms));
+            collection_extra.insert("effective_pids".to_string(), json!(effective.pids));
+            if !effective.dropped_pids.is_empty() {
+                collection_extra.insert("effective_dropped_pids".to_string(), json!(effective.dropped_pids));
+            }
+        }
+        let data_sources = run_data_sources(app_handle, state, &run);
+        if !data_sources.is_empty() {
+            collection_extra.insert("data_sources".to_string(), json!(data_sources));
+        }
+        if let Some(selector) = &run.target_selector {
+            collection_extra.insert("target_selector".to_string(), json!(selector));
+            collection_extra.insert("membership".to_string(), json!(run.membership));
+        }
+        // Synthetic data must never be mistaken for a real measurement.
+        if let Some(sim) = &run.simulation {
+            collection_extra.insert("simulated".to_string(), json!(true));
+            collection_extra.insert("simulation".to_string(), json!(sim));
+        }
+
+        let mut meta = ReportMeta {
+            schema_version: Some(1),
+            app: Some(AppMeta { version: Some(app_version), extra: Default::default() }),
+            env: Some(EnvMeta {
+                os: Some(std::env::consts::OS.to_string()),
+                arch: Some(std::env::consts::ARCH.to_string()),
+                device_name,
+                cpu_logical_cores: Some(cpu_count as u64),
+                cpu_physical_cores: cpu_physical_cores.map(|n| n as u64),
+                cpu_brand,
+                cpu_vendor,
+                cpu_frequency_mhz,
+                total_memory_bytes: Some(total_mem_bytes),
+                timezone: timezone::local_iana_name(),
+                utc_offset: Some(timezone::local_utc_offset()),
+                extra: env_extra,
+            }),
+            collection: Some(CollectionMeta {
+                metric_standard: Some(
+                    match mode.as_str() {
+                        "browser" => "chrome",
+                        "simulate" => "simulated",
+                        _ => "os",
+                    }
+                    .to_string(),
+                ),
+                mode: Some(mode),
+                interval_ms: Some(interval_ms),
+                target_pids,
+                folder_path,
+                started_at,
+                ended_at: Some(ended_at),
+                duration_seconds: Some(duration_seconds),
+                stop_after_seconds,
+                test_context: None,
+                extra: collection_extra,
+            }),
+            test_context: test_context.and_then(|tc| serde_json::from_value(tc).ok()),
+            process_aliases: std::mem::take(&mut run.process_aliases),
+            process_snapshot: process_snapshot
+                .iter()
+                .filter_map(|p| serde_json::to_value(p).ok())
+                .collect(),
+            folder_path: None,
+            budgets: std::mem::take(&mut run.budgets),
+            events: std::mem::take(&mut run.events),
+            extra,
+            source: None,
+        };
+
+        // No explicit folder: let the first matching auto-foldering rule pick one.
+        if meta.folder_path().is_empty() {
+            let rules = db.list_folder_rules().unwrap_or_default();
+            if let Some(m) = folder_rules::evaluate(&rules, &meta, Utc::now()) {
+                log_info!("Folder rule {} filed report under {}", m.rule_id, m.folder_path);
+                if let Err(e) = db.ensure_folder_path(&m.folder_path) {
+                    log_warn!("Failed to create folder {}: {}", m.folder_path, e);
+                }
+                meta.set_folder_path(&m.folder_path);
+                meta.extra.insert("folder_rule_id".to_string(), json!(m.rule_id));
+            }
+        }
+
+        // On failure the written chunks stay behind and are recovered at the next start.
+        let metrics_json = assemble_metrics_json(db, &run, &buffer)?;
+        let report_id = db.save_report_json(&title, &buffer, &metrics_json, &meta).map_err(|e| e.to_string())?;
+        if let Err(e) = db.delete_run_chunks(session_id) {
+            log_warn!("Failed to clear written samples of session {}: {}", session_id, e);
+        }
+        log_info!("Report saved successfully.");
+
+        let regression = check_score_regression(app_handle, db, report_id, &meta);
+
+        // Optional "report saved" webhook (delivered in the background).
+        let webhook_config = webhook::load_config(db);
+        if webhook_config.enabled && !webhook_config.url.trim().is_empty() {
+            let analysis = crate::analysis::analyze_report(&buffer, &meta);
+            let payload = webhook::build_payload(report_id, &title, &meta, &analysis, regression.as_ref());
+            webhook::notify_report_saved(app_handle.clone(), webhook_config, report_id, payload);
+        }
+        return Ok(StopResult { saved: true, report_id: Some(report_id), samples, reasons: Vec::new() });
+    }
+
+    let reasons = empty_run_reasons(&run);
+    log_info!("Stopped (No Data): {}", reasons.join("; "));
+    let _ = app_handle.emit("collection-empty", json!({ "session_id": session_id, "reasons": reasons }));
+    Ok(StopResult { saved: false, report_id: None, samples, reasons })
+}
+
+/// Payload of the "score-regression" event.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScoreRegressionEvent {
+    pub report_id: i64,
+    pub folder_path: String,
+    pub scenario_name: Option<String>,
+    #[serde(flatten)]
+    pub regression: ScoreRegression,
+}
+
+// Compare a just-saved report's cached score with the recent reports of its folder and scenario
+// (`Settings::analysis`). A regression is noted in the report meta and emitted as
+// "score-regression". Returns the verdict when there was a baseline to compare with.
+fn check_score_regression<R: tauri::Runtime>(app_handle: &AppHandle<R>, db: &Database, report_id: i64, meta: &ReportMeta) -> Option<ScoreRegression> {
+    let settings = Settings::load(db).analysis;
+    let folder_path = meta.folder_path();
+    let (score, version) = db.cached_score(report_id).ok().flatten()?;
+    // Older reports of the folder may still lack a cached score; they count from the next save on.
+    spawn_sparkline_backfill(app_handle);
+    let prior = db
+        .recent_scores_in_folder(&folder_path, meta.scenario_name(), report_id, settings.regression_lookback)
+        .map_err(|e| log_warn!("Score history unavailable: {}", e))
+        .ok()?;
+    let verdict = crate::analysis::score_regression(score, version, &prior, settings.regression_threshold)?;
+    if verdict.regressed {
+        log_info!("Report {} scored {} against a median of {}", report_id, score, verdict.baseline_median);
+        if let Err(e) = db.update_report_meta_patch(report_id, &json!({ "score_regression": verdict })) {
+            log_warn!("Failed to note score regression on report {}: {}", report_id, e);
+        }
+        let _ = app_handle.emit(
+            "score-regression",
+            &ScoreRegressionEvent {
+                report_id,
+                folder_path,
+                scenario_name: meta.scenario_name().map(str::to_string),
+                regression: verdict.clone(),
             },
-            "env": {
-                "os": std::env::consts::OS,
-                "arch": std::env::consts::ARCH,
-                "device_name": device_name,
-                "cpu_logical_cores": cpu_count,
-                "cpu_physical_cores": cpu_physical_cores,
-                "cpu_brand": cpu_brand,
-                "cpu_vendor": cpu_vendor,
-                "cpu_frequency_mhz": cpu_frequency_mhz,
-                "total_memory_bytes": total_mem_bytes,
-                "gpu": { "name": null }
-            },
-            "collection": {
-                "mode": mode,
-                "metric_standard": if mode == "browser" { "chrome" } else { "os" },
-                "interval_ms": interval_ms,
-                "target_pids": target_pids,
-                "folder_path": folder_path,
-                "started_at": started_at,
-                "ended_at": ended_at,
-                "duration_seconds": duration_seconds,
-                "stop_after_seconds": stop_after_seconds
-            },
-            "test_context": test_context,
-            "process_aliases": safe_lock(&state.process_aliases).clone(),
-            "process_snapshot": process_snapshot
-        });
+        );
+    }
+    Some(verdict)
+}
+
+// Compute missing sparklines off the command threads; "report-sparklines-updated" tells the
+// listing to reload once some were filled.
+fn spawn_sparkline_backfill<R: tauri::Runtime>(app_handle: &AppHandle<R>) {
+    let app = app_handle.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = app.state::<Database>();
+        match db.backfill_sparklines_once(Settings::load(db.inner()).report_workers()) {
+            Some(Ok(filled)) if filled > 0 => {
+                let _ = app.emit("report-sparklines-updated", json!({ "filled": filled }));
+            }
+            Some(Err(e)) => log_warn!("Failed to backfill report sparklines: {}", e),
+            _ => {}
+        }
+    });
+}
+
+/// `timezone` (IANA name or "local") re-renders `created_at` with that zone's offset; default UTC.
+/// Reports without a sparkline yet are returned as is and backfilled in the background.
+#[tauri::command]
+pub async fn get_reports(app_handle: AppHandle, timezone: Option<String>) -> Result<Vec<ReportSummary>, PerfSightError> {
+    let zone = DisplayZone::parse(timezone.as_deref())?;
+    run_blocking(&app_handle, move |app, db| {
+        let mut reports = db.get_all_reports()?;
+        if reports.iter().any(|r| r.sparkline.is_none()) {
+            spawn_sparkline_backfill(app);
+        }
+        if zone != DisplayZone::Utc {
+            for r in reports.iter_mut() {
+                r.created_at = zone.rfc3339_str(&r.created_at);
+            }
+        }
+        Ok(reports)
+    })
+    .await
+}
+
+#[tauri::command]
+pub fn get_known_tags(db: State<'_, Database>) -> Result<Vec<TagStat>, PerfSightError> {
+    db.get_known_tags().map_err(PerfSightError::from)
+}
+
+#[tauri::command]
+pub fn get_known_comparison_tags(db: State<'_, Database>) -> Result<Vec<TagStat>, PerfSightError> {
+    db.get_known_comparison_tags().map_err(PerfSightError::from)
+}
+
+/// Replace a comparison's tags. Returns the normalized list that was stored.
+#[tauri::command]
+pub fn update_comparison_tags(db: State<'_, Database>, id: i64, tags: Vec<String>) -> Result<Vec<String>, PerfSightError> {
+    db.update_comparison_tags(id, &tags).map_err(PerfSightError::from)
+}
+
+#[tauri::command]
+pub async fn get_report_detail(app_handle: AppHandle, id: i64) -> Result<ReportDetail, PerfSightError> {
+    run_blocking(&app_handle, move |_, db| db.get_report_detail(id).map_err(PerfSightError::from)).await
+}
+
+// Analysis of the samples of `pids` only (all PIDs when empty), with the report's aliases.
+fn analyze_pid_subset(report: &ReportDetail, pids: &[u32]) -> crate::analysis::AnalysisReport {
+    let metrics = if pids.is_empty() {
+        report.metrics.clone()
+    } else {
+        MetricSelection { pids: pids.to_vec(), ..Default::default() }.apply(&report.metrics)
+    };
+    let mut analysis = crate::analysis::analyze_report(&metrics, &report.meta);
+    crate::analysis::apply_aliases(&mut analysis, &report.meta.aliases());
+    analysis
+}
+
+/// Analyze a report as if only `pids` had been recorded. An empty list analyzes every PID.
+#[tauri::command]
+pub async fn analyze_report_with_selection(
+    app_handle: AppHandle,
+    report_id: i64,
+    pids: Vec<u32>,
+) -> Result<crate::analysis::AnalysisReport, PerfSightError> {
+    run_blocking(&app_handle, move |_, db| {
+        let report = db.get_report_detail(report_id)?;
+        Ok(analyze_pid_subset(&report, &pids))
+    })
+    .await
+}
+
+/// Which PIDs `get_report_detail_for_comparison` analyzed.
+#[derive(Debug, Serialize)]
+pub struct AppliedSelection {
+    pub comparison_id: i64,
+    /// The comparison's CPU and memory selections for this report, as stored.
+    pub cpu_pids: Vec<u32>,
+    pub mem_pids: Vec<u32>,
+    /// Union of both selections; this is what the analysis covers.
+    pub pids: Vec<u32>,
+    /// True when the comparison selected nothing for this report, so every PID was analyzed.
+    pub all_pids: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ComparisonReportDetail {
+    #[serde(flatten)]
+    pub report: ReportDetail,
+    pub selection: AppliedSelection,
+}
+
+/// A report of a comparison, with its analysis restricted to the PIDs the comparison selected for
+/// it, so the numbers match the comparison view.
+#[tauri::command]
+pub async fn get_report_detail_for_comparison(
+    app_handle: AppHandle,
+    comparison_id: i64,
+    report_id: i64,
+) -> Result<ComparisonReportDetail, PerfSightError> {
+    run_blocking(&app_handle, move |_, db| {
+        let cmp = db.get_comparison_detail(comparison_id)?;
+        if !cmp.report_ids.contains(&report_id) {
+            return Err(PerfSightError::invalid_input(
+                "report_id",
+                format!("report {} is not part of comparison {}", report_id, comparison_id),
+            ));
+        }
+        let cpu_pids = selected_pids(Some(&cmp.cpu_selections_by_id), report_id);
+        let mem_pids = selected_pids(Some(&cmp.mem_selections_by_id), report_id);
+        let pids: Vec<u32> = cpu_pids
+            .iter()
+            .chain(&mem_pids)
+            .copied()
+            .collect::<std::collections::BTreeSet<_>>()
+            .into_iter()
+            .collect();
+
+        let mut report = db.get_report_detail(report_id)?;
+        report.analysis = Some(analyze_pid_subset(&report, &pids));
+        Ok(ComparisonReportDetail {
+            report,
+            selection: AppliedSelection { comparison_id, all_pids: pids.is_empty(), cpu_pids, mem_pids, pids },
+        })
+    })
+    .await
+}
+
+const DEFAULT_STREAM_CHUNK_BATCHES: usize = 2000;
+
+/// Abort flags of the running `get_report_detail_streamed` calls, by stream id.
+#[derive(Clone, Default)]
+pub struct ReportStreams {
+    active: Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>,
+}
+
+impl ReportStreams {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Payload of the "report-metrics-chunk" event.
+#[derive(Serialize)]
+pub struct ReportMetricsChunk<'a> {
+    pub stream_id: &'a str,
+    pub report_id: i64,
+    pub chunk_index: usize,
+    pub total_chunks: usize,
+    pub metrics: &'a [BatchMetric],
+}
+
+/// Payload of the "report-detail-streamed" event, sent once after the last chunk.
+#[derive(Serialize)]
+pub struct StreamedReportDetail {
+    pub stream_id: String,
+    pub report_id: i64,
+    /// "completed" | "cancelled" | "failed"
+    pub status: String,
+    pub created_at: Option<String>,
+    pub title: Option<String>,
+    pub meta: Option<ReportMeta>,
+    pub analysis: Option<crate::analysis::AnalysisReport>,
+    pub error: Option<String>,
+}
+
+/// Send a report's samples as "report-metrics-chunk" events of `chunk_size` batches (default
+/// 2000), then meta and analysis as "report-detail-streamed". Returns the stream id at once;
+/// pass it to `abort_report_stream` to stop early.
+#[tauri::command]
+pub fn get_report_detail_streamed(
+    app_handle: AppHandle,
+    streams: State<'_, ReportStreams>,
+    id: i64,
+    chunk_size: Option<usize>,
+) -> Result<String, PerfSightError> {
+    let chunk_size = chunk_size.unwrap_or(DEFAULT_STREAM_CHUNK_BATCHES);
+    if chunk_size == 0 {
+        return Err(PerfSightError::invalid_input("chunk_size", "must be greater than 0"));
+    }
+    let stream_id = format!("report-{}-{}", id, uuid::Uuid::new_v4().simple());
+    let abort = Arc::new(AtomicBool::new(false));
+    safe_lock(&streams.active).insert(stream_id.clone(), abort.clone());
+
+    let streams = streams.inner().clone();
+    let sid = stream_id.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db: State<Database> = app_handle.state();
+        let mut done = StreamedReportDetail {
+            stream_id: sid.clone(),
+            report_id: id,
+            status: "completed".to_string(),
+            created_at: None,
+            title: None,
+            meta: None,
+            analysis: None,
+            error: None,
+        };
+        match stream_report(&app_handle, db.inner(), &sid, id, chunk_size, &abort) {
+            Ok(Some(report)) => {
+                done.created_at = Some(report.created_at);
+                done.title = Some(report.title);
+                done.analysis = report.analysis;
+                done.meta = Some(report.meta);
+            }
+            Ok(None) => done.status = "cancelled".to_string(),
+            Err(e) => {
+                done.status = "failed".to_string();
+                done.error = Some(e.to_string());
+            }
+        }
+        safe_lock(&streams.active).remove(&sid);
+        let _ = app_handle.emit("report-detail-streamed", &done);
+    });
+    Ok(stream_id)
+}
+
+// Emit the chunks of one report. The samples are parsed batch by batch from the stored JSON;
+// they are only kept whole when the analysis isn't cached yet. None when aborted.
+fn stream_report(
+    app_handle: &AppHandle,
+    db: &Database,
+    stream_id: &str,
+    id: i64,
+    chunk_size: usize,
+    abort: &AtomicBool,
+) -> Result<Option<StoredReport>, PerfSightError> {
+    let mut report = db.stored_report(id)?;
+    let invalid = |e: serde_json::Error| PerfSightError::Internal(format!("Report {} samples are unreadable: {}", id, e));
+    let mut total_batches = 0usize;
+    for_each_batch(&report.metrics_json, |_: serde::de::IgnoredAny| {
+        total_batches += 1;
+        true
+    })
+    .map_err(invalid)?;
+    let total_chunks = total_batches.div_ceil(chunk_size);
+
+    let mut all: Option<Vec<BatchMetric>> = report.analysis.is_none().then(Vec::new);
+    let mut chunk: Vec<BatchMetric> = Vec::with_capacity(chunk_size.min(total_batches));
+    let mut chunk_index = 0;
+    let mut send = |chunk: &mut Vec<BatchMetric>| {
+        let _ = app_handle.emit(
+            "report-metrics-chunk",
+            &ReportMetricsChunk { stream_id, report_id: id, chunk_index, total_chunks, metrics: chunk },
+        );
+        chunk_index += 1;
+        if let Some(all) = all.as_mut() {
+            all.append(chunk);
+        }
+        chunk.clear();
+    };
+    for_each_batch(&report.metrics_json, |batch: BatchMetric| {
+        if abort.load(Ordering::SeqCst) {
+            return false;
+        }
+        chunk.push(batch);
+        if chunk.len() == chunk_size {
+            send(&mut chunk);
+        }
+        true
+    })
+    .map_err(invalid)?;
+    if abort.load(Ordering::SeqCst) {
+        return Ok(None);
+    }
+    if !chunk.is_empty() {
+        send(&mut chunk);
+    }
+    report.metrics_json = String::new();
+
+    let mut analysis = match (report.analysis.take(), all) {
+        (Some(cached), _) => cached,
+        (None, metrics) => {
+            let analysis = crate::analysis::analyze_report(&metrics.unwrap_or_default(), &report.meta);
+            if let Err(e) = db.cache_analysis(id, &analysis) {
+                log_warn!("Failed to cache analysis of report {}: {}", id, e);
+            }
+            analysis
+        }
+    };
+    crate::analysis::apply_aliases(&mut analysis, &report.meta.aliases());
+    report.analysis = Some(analysis);
+    Ok(Some(report))
+}
+
+/// Stop a `get_report_detail_streamed` call. Returns false when it already finished.
+#[tauri::command]
+pub fn abort_report_stream(streams: State<'_, ReportStreams>, stream_id: String) -> bool {
+    match safe_lock(&streams.active).get(&stream_id) {
+        Some(abort) => {
+            abort.store(true, Ordering::SeqCst);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Cancel flags of the running `reanalyze_reports` jobs, by job id.
+#[derive(Clone, Default)]
+pub struct ReanalyzeJobs {
+    active: Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>,
+}
 
-        db.save_report(&title, &buffer, &meta).map_err(|e| e.to_string())?;
-        buffer.clear();
-        println!("Report saved successfully.");
-
-        // Reset run state after saving.
-        safe_lock(&state.target_pids).clear();
-        *safe_lock(&state.mode) = "system".to_string();
-        *safe_lock(&state.interval_ms) = 1000;
-        *safe_lock(&state.started_at) = None;
-        safe_lock(&state.process_snapshot).clear();
-        safe_lock(&state.process_aliases).clear();
-        *safe_lock(&state.folder_path) = None;
-        *safe_lock(&state.test_context) = None;
-        safe_lock(&state.log_metrics).clear();
-        return Ok("Stopped and Saved Report".to_string());
+impl ReanalyzeJobs {
+    pub fn new() -> Self {
+        Self::default()
     }
-    
-    println!("Stopped (No Data).");
-    // Reset run state even if no data.
-    safe_lock(&state.target_pids).clear();
-    *safe_lock(&state.mode) = "system".to_string();
-    *safe_lock(&state.interval_ms) = 1000;
-    *safe_lock(&state.started_at) = None;
-    safe_lock(&state.process_snapshot).clear();
-    safe_lock(&state.process_aliases).clear();
-    *safe_lock(&state.folder_path) = None;
-    *safe_lock(&state.test_context) = None;
-    safe_lock(&state.log_metrics).clear();
-    Ok("Stopped (No Data)".to_string())
 }
 
+/// Payload of the "reanalyze-progress" event, sent after each report.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReanalyzeProgress {
+    pub job_id: String,
+    /// 1-based position of the report just handled.
+    pub index: usize,
+    pub total: usize,
+    pub report_id: i64,
+    /// New score; None when the report failed.
+    pub score: Option<u8>,
+}
+
+/// Payload of the "reanalyze-finished" event.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReanalyzeFinished {
+    pub job_id: String,
+    /// "completed" | "cancelled" | "failed" (the reports in scope could not be listed)
+    pub status: String,
+    pub analyzer_version: u32,
+    /// Reports that were stale (or in scope, with `force`).
+    pub total: usize,
+    pub recomputed: Vec<i64>,
+    /// (report id, error) of reports that could not be recomputed.
+    pub failed: Vec<(i64, String)>,
+    pub error: Option<String>,
+}
+
+/// Recompute the cached analysis and sparkline of the reports in `scope` that an older analyzer
+/// produced (all of them with `force`), so scores and trends compare like with like. Runs in the
+/// background: emits "reanalyze-progress" per report and "reanalyze-finished" at the end, and
+/// returns the job id at once; pass it to `cancel_reanalyze_reports` to stop. Reports are
+/// computed `max_parallel_reports` at a time.
 #[tauri::command]
-pub fn get_reports(db: State<'_, Database>) -> Result<Vec<ReportSummary>, String> {
-    db.get_all_reports().map_err(|e| e.to_string())
+pub fn reanalyze_reports(
+    app_handle: AppHandle,
+    jobs: State<'_, ReanalyzeJobs>,
+    scope: ReanalyzeScope,
+    force: Option<bool>,
+) -> Result<String, PerfSightError> {
+    if let ReanalyzeScope::Ids { ids } = &scope {
+        if ids.is_empty() {
+            return Err(PerfSightError::invalid_input("scope.ids", "select at least one report"));
+        }
+    }
+    let job_id = format!("reanalyze-{}", uuid::Uuid::new_v4().simple());
+    let cancel = Arc::new(AtomicBool::new(false));
+    safe_lock(&jobs.active).insert(job_id.clone(), cancel.clone());
+
+    let jobs = jobs.inner().clone();
+    let id = job_id.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db: State<Database> = app_handle.state();
+        let mut done = ReanalyzeFinished {
+            job_id: id.clone(),
+            status: "completed".to_string(),
+            analyzer_version: crate::analysis::ANALYZER_VERSION,
+            total: 0,
+            recomputed: Vec::new(),
+            failed: Vec::new(),
+            error: None,
+        };
+        match db.reports_to_reanalyze(&scope, force.unwrap_or(false)) {
+            Ok(ids) => {
+                done.total = ids.len();
+                // Reports are computed in parallel and stored here, one at a time.
+                parallel::for_each_ordered(
+                    &ids,
+                    Settings::load(db.inner()).report_workers(),
+                    |report_id| db.reanalysis(*report_id),
+                    |i, result| {
+                        if cancel.load(Ordering::SeqCst) {
+                            done.status = "cancelled".to_string();
+                            return false;
+                        }
+                        let report_id = ids[i];
+                        let score = match result.and_then(|r| db.store_reanalysis(&r).map(|_| r.score)) {
+                            Ok(score) => {
+                                done.recomputed.push(report_id);
+                                Some(score)
+                            }
+                            Err(e) => {
+                                done.failed.push((report_id, e.to_string()));
+                                None
+                            }
+                        };
+                        let _ = app_handle.emit(
+                            "reanalyze-progress",
+                            &ReanalyzeProgress { job_id: id.clone(), index: i + 1, total: done.total, report_id, score },
+                        );
+                        true
+                    },
+                );
+            }
+            Err(e) => {
+                done.status = "failed".to_string();
+                done.error = Some(e.to_string());
+            }
+        }
+        safe_lock(&jobs.active).remove(&id);
+        let _ = app_handle.emit("reanalyze-finished", &done);
+    });
+    Ok(job_id)
 }
 
+/// Stop a `reanalyze_reports` job; reports already stored keep their new analysis, ones still
+/// being computed are discarded. Returns false when it already finished.
 #[tauri::command]
-pub fn get_known_tags(db: State<'_, Database>) -> Result<Vec<TagStat>, String> {
-    db.get_known_tags().map_err(|e| e.to_string())
+pub fn cancel_reanalyze_reports(jobs: State<'_, ReanalyzeJobs>, job_id: String) -> bool {
+    match safe_lock(&jobs.active).get(&job_id) {
+        Some(cancel) => {
+            cancel.store(true, Ordering::SeqCst);
+            true
+        }
+        None => false,
+    }
+}
+
+// Locked reports only change when the caller passes `force`.
+fn ensure_unlocked(db: &Database, ids: &[i64], force: Option<bool>) -> Result<(), PerfSightError> {
+    if force.unwrap_or(false) {
+        return Ok(());
+    }
+    let locked = db.locked_report_ids(ids)?;
+    if locked.is_empty() {
+        Ok(())
+    } else {
+        Err(PerfSightError::Locked { report_ids: locked })
+    }
+}
+
+// Same check for every report a folder operation would move (the root itself is never moved).
+fn ensure_folder_unlocked(db: &Database, path: &str, force: Option<bool>) -> Result<(), PerfSightError> {
+    if force.unwrap_or(false) || normalize_folder_path(path).is_empty() {
+        return Ok(());
+    }
+    ensure_unlocked(db, &db.report_ids_in_folder(path, true)?, force)
 }
 
+/// Mark a report as a read-only baseline (or release it).
 #[tauri::command]
-pub fn get_report_detail(db: State<'_, Database>, id: i64) -> Result<ReportDetail, String> {
-    db.get_report_detail(id).map_err(|e| e.to_string())
+pub fn set_report_locked(db: State<'_, Database>, id: i64, locked: bool) -> Result<usize, PerfSightError> {
+    match db.set_report_locked(id, locked)? {
+        0 => Err(PerfSightError::NotFound),
+        n => Ok(n),
+    }
 }
 
 #[tauri::command]
-pub fn delete_report(db: State<'_, Database>, id: i64) -> Result<usize, String> {
-    db.delete_report(id).map_err(|e| e.to_string())
+pub fn delete_report(db: State<'_, Database>, id: i64, force: Option<bool>) -> Result<usize, PerfSightError> {
+    ensure_unlocked(&db, &[id], force)?;
+    db.delete_report(id).map_err(PerfSightError::from)
 }
 
 #[tauri::command]
-pub fn delete_reports(db: State<'_, Database>, ids: Vec<i64>) -> Result<usize, String> {
-    db.delete_reports(&ids).map_err(|e| e.to_string())
+pub fn delete_reports(db: State<'_, Database>, ids: Vec<i64>, force: Option<bool>) -> Result<usize, PerfSightError> {
+    ensure_unlocked(&db, &ids, force)?;
+    db.delete_reports(&ids).map_err(PerfSightError::from)
 }
 
 #[tauri::command]
-pub fn update_report_title(db: State<'_, Database>, id: i64, title: String) -> Result<usize, String> {
+pub fn update_report_title(
+    db: State<'_, Database>,
+    id: i64,
+    title: String,
+    force: Option<bool>,
+) -> Result<usize, PerfSightError> {
     let t = title.trim().to_string();
     if t.is_empty() {
-        return Err("Title cannot be empty".to_string());
+        return Err(PerfSightError::invalid_input("title", "cannot be empty"));
     }
-    db.update_report_title(id, &t).map_err(|e| e.to_string())
+    ensure_unlocked(&db, &[id], force)?;
+    db.update_report_title(id, &t).map_err(PerfSightError::from)
+}
+
+/// Rename processes of a saved report. Analysis and exports pick the new aliases up on next read.
+#[tauri::command]
+pub fn update_report_aliases(
+    db: State<'_, Database>,
+    id: i64,
+    aliases: Vec<ProcessAlias>,
+    force: Option<bool>,
+) -> Result<usize, PerfSightError> {
+    ensure_unlocked(&db, &[id], force)?;
+    db.update_report_aliases(id, &aliases)
 }
 
 #[tauri::command]
@@ -1334,16 +6657,12 @@ pub fn update_report_folder_path(
     db: State<'_, Database>,
     id: i64,
     folder_path: String,
-) -> Result<usize, String> {
-    let raw = folder_path.trim().to_string();
-    let normalized = raw
-        .split('/')
-        .map(|p| p.trim())
-        .filter(|p| !p.is_empty() && *p != ".")
-        .collect::<Vec<_>>()
-        .join("/");
-    db.update_report_folder_path(id, &normalized)
-        .map_err(|e| e.to_string())
+    force: Option<bool>,
+) -> Result<usize, PerfSightError> {
+    ensure_unlocked(&db, &[id], force)?;
+    check_folder_path("folder_path", &folder_path)?;
+    db.update_report_folder_path(id, &normalize_folder_path(&folder_path))
+        .map_err(PerfSightError::from)
 }
 
 #[tauri::command]
@@ -1351,36 +6670,89 @@ pub fn update_reports_folder_path(
     db: State<'_, Database>,
     ids: Vec<i64>,
     folder_path: String,
-) -> Result<usize, String> {
-    let raw = folder_path.trim().to_string();
-    let normalized = raw
-        .split('/')
-        .map(|p| p.trim())
-        .filter(|p| !p.is_empty() && *p != ".")
-        .collect::<Vec<_>>()
-        .join("/");
-    db.update_reports_folder_path(&ids, &normalized)
-        .map_err(|e| e.to_string())
+    force: Option<bool>,
+) -> Result<usize, PerfSightError> {
+    ensure_unlocked(&db, &ids, force)?;
+    check_folder_path("folder_path", &folder_path)?;
+    db.update_reports_folder_path(&ids, &normalize_folder_path(&folder_path))
+        .map_err(PerfSightError::from)
+}
+
+#[tauri::command]
+pub fn list_folder_paths(db: State<'_, Database>) -> Result<Vec<FolderInfo>, PerfSightError> {
+    db.list_folder_paths().map_err(PerfSightError::from)
+}
+
+#[tauri::command]
+pub fn create_folder(db: State<'_, Database>, parent_path: String, name: String) -> Result<String, PerfSightError> {
+    check_folder_path("name", &format!("{}/{}", parent_path, name))?;
+    db.create_folder(&parent_path, &name).map_err(PerfSightError::from)
+}
+
+#[tauri::command]
+pub fn get_folder_stats(db: State<'_, Database>, path: String) -> Result<FolderStats, PerfSightError> {
+    db.get_folder_stats(&path).map_err(PerfSightError::from)
+}
+
+#[tauri::command]
+pub fn list_folder_rules(db: State<'_, Database>) -> Result<Vec<FolderRule>, PerfSightError> {
+    db.list_folder_rules().map_err(PerfSightError::from)
+}
+
+/// Create (id 0) or update a folder rule. Returns the rule id.
+#[tauri::command]
+pub fn save_folder_rule(db: State<'_, Database>, rule: FolderRule) -> Result<i64, PerfSightError> {
+    folder_rules::validate_rule(&rule)?;
+    db.save_folder_rule(&rule).map_err(PerfSightError::from)
 }
 
 #[tauri::command]
-pub fn list_folder_paths(db: State<'_, Database>) -> Result<Vec<FolderInfo>, String> {
-    db.list_folder_paths().map_err(|e| e.to_string())
+pub fn delete_folder_rule(db: State<'_, Database>, id: i64) -> Result<usize, PerfSightError> {
+    db.delete_folder_rule(id).map_err(PerfSightError::from)
 }
 
 #[tauri::command]
-pub fn create_folder(db: State<'_, Database>, parent_path: String, name: String) -> Result<String, String> {
-    db.create_folder(&parent_path, &name).map_err(|e| e.to_string())
+pub fn reorder_folder_rules(db: State<'_, Database>, ids: Vec<i64>) -> Result<(), PerfSightError> {
+    db.reorder_folder_rules(&ids).map_err(PerfSightError::from)
+}
+
+/// Preview where a run with this test context would be filed. None = stays in the root folder.
+#[tauri::command]
+pub fn dry_run_folder_rules(
+    db: State<'_, Database>,
+    test_context: TestContext,
+    mode: Option<String>,
+) -> Result<Option<FolderRuleMatch>, PerfSightError> {
+    let meta = ReportMeta {
+        test_context: Some(test_context),
+        collection: Some(CollectionMeta { mode, ..Default::default() }),
+        ..Default::default()
+    };
+    Ok(folder_rules::evaluate(&db.list_folder_rules()?, &meta, Utc::now()))
 }
 
 #[tauri::command]
-pub fn get_folder_stats(db: State<'_, Database>, path: String) -> Result<FolderStats, String> {
-    db.get_folder_stats(&path).map_err(|e| e.to_string())
+pub async fn get_folder_aggregate(app_handle: AppHandle, path: String) -> Result<FolderAggregate, PerfSightError> {
+    run_blocking(&app_handle, move |_, db| {
+        let workers = Settings::load(db).report_workers();
+        db.get_folder_aggregate(&path, workers).map_err(PerfSightError::from)
+    })
+    .await
 }
 
+/// Rename a report folder (its leaf). When a sibling with the new name already holds reports or
+/// sub-folders, fails with `folder_exists` unless `on_conflict` is "merge".
 #[tauri::command]
-pub fn rename_folder(db: State<'_, Database>, path: String, new_name: String) -> Result<String, String> {
-    db.rename_folder(&path, &new_name).map_err(|e| e.to_string())
+pub fn rename_folder(
+    db: State<'_, Database>,
+    path: String,
+    new_name: String,
+    force: Option<bool>,
+    on_conflict: Option<FolderConflict>,
+) -> Result<String, PerfSightError> {
+    ensure_folder_unlocked(&db, &path, force)?;
+    check_folder_path("new_name", &new_name)?;
+    db.rename_folder(&path, &new_name, on_conflict.unwrap_or_default())
 }
 
 #[tauri::command]
@@ -1388,12 +6760,27 @@ pub fn delete_folder(
     db: State<'_, Database>,
     path: String,
     strategy: Option<String>,
-) -> Result<(usize, usize), String> {
-    db.delete_folder(&path, strategy.as_deref()).map_err(|e| e.to_string())
+    force: Option<bool>,
+) -> Result<(usize, usize), PerfSightError> {
+    // Without a strategy a non-empty folder is refused anyway, so nothing can move.
+    if strategy.as_deref().is_some_and(|s| !s.is_empty()) {
+        ensure_folder_unlocked(&db, &path, force)?;
+    }
+    db.delete_folder(&path, strategy.as_deref())
+}
+
+/// Raw CDP `SystemInfo.getProcessInfo` next to the interpreted per-PID map, with the memory unit
+/// chosen for each field and why. Needs Chrome's remote debugging port.
+#[tauri::command]
+pub async fn debug_get_cdp_process_info() -> Result<ProcessInfoDebug, PerfSightError> {
+    tokio::task::spawn_blocking(CdpClient::get_process_info_debug)
+        .await
+        .map_err(|e| PerfSightError::from(e.to_string()))?
+        .map_err(PerfSightError::from)
 }
 
 #[tauri::command]
-pub fn debug_get_macos_rusage(pid: u32) -> Result<Value, String> {
+pub fn debug_get_macos_rusage(pid: u32) -> Result<Value, PerfSightError> {
     #[cfg(target_os = "macos")]
     {
         // sysctl hw.memsize
@@ -1419,7 +6806,7 @@ pub fn debug_get_macos_rusage(pid: u32) -> Result<Value, String> {
         let mut buf: libc::rusage_info_t = info.as_mut_ptr().cast::<libc::c_void>();
         let rc = unsafe { libc::proc_pid_rusage(pid as libc::c_int, RUSAGE_INFO_V4, &mut buf) };
         if rc != 0 {
-            return Err(format!("proc_pid_rusage failed rc={}", rc));
+            return Err(format!("proc_pid_rusage failed rc={}", rc).into());
         }
         let info = unsafe { info.assume_init() };
 
@@ -1436,8 +6823,1036 @@ pub fn debug_get_macos_rusage(pid: u32) -> Result<Value, String> {
     #[cfg(not(target_os = "macos"))]
     {
         let _ = pid;
-        Err("debug_get_macos_rusage is only available on macOS".to_string())
+        Err("debug_get_macos_rusage is only available on macOS".into())
     }
 }
 
 
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use crate::collector::simulate::SIMULATED_PID_BASE;
+    use crate::models::MIN_INTERVAL_MS;
+
+    // Counts the heap bytes live on each thread, and their peak, for the memory tests. Frees
+    // are charged to the freeing thread, which is close enough for single-threaded work.
+    struct ThreadHeap;
+
+    thread_local! {
+        static HEAP_LIVE: std::cell::Cell<i64> = const { std::cell::Cell::new(0) };
+        static HEAP_PEAK: std::cell::Cell<i64> = const { std::cell::Cell::new(0) };
+    }
+
+    fn track_heap(delta: i64) {
+        let _ = HEAP_LIVE.try_with(|live| {
+            live.set(live.get() + delta);
+            let _ = HEAP_PEAK.try_with(|peak| peak.set(peak.get().max(live.get())));
+        });
+    }
+
+    // Peak heap growth of this thread while `f` runs.
+    fn peak_heap_growth(f: impl FnOnce()) -> u64 {
+        let base = HEAP_LIVE.with(|l| l.get());
+        HEAP_PEAK.with(|p| p.set(base));
+        f();
+        (HEAP_PEAK.with(|p| p.get()) - base).max(0) as u64
+    }
+
+    unsafe impl std::alloc::GlobalAlloc for ThreadHeap {
+        unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+            let p = std::alloc::System.alloc(layout);
+            if !p.is_null() {
+                track_heap(layout.size() as i64);
+            }
+            p
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+            std::alloc::System.dealloc(ptr, layout);
+            track_heap(-(layout.size() as i64));
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: std::alloc::Layout, new_size: usize) -> *mut u8 {
+            let p = std::alloc::System.realloc(ptr, layout, new_size);
+            if !p.is_null() {
+                track_heap(new_size as i64 - layout.size() as i64);
+            }
+            p
+        }
+    }
+
+    #[global_allocator]
+    static HEAP: ThreadHeap = ThreadHeap;
+
+    pub(crate) fn test_run(session_id: &str, target_pids: Vec<u32>) -> ActiveRun {
+        ActiveRun {
+            session_id: session_id.to_string(),
+            started_at: Utc::now().to_rfc3339(),
+            target_pids,
+            mode: "system".to_string(),
+            interval_ms: 1000,
+            app_version: "test".to_string(),
+            test_context: None,
+            test_context_limits: None,
+            process_snapshot: Vec::new(),
+            process_aliases: Vec::new(),
+            folder_path: None,
+            self_pids: Vec::new(),
+            folder_defaults: serde_json::Map::new(),
+            stop_after_seconds: None,
+            log_metrics: Vec::new(),
+            log_parse_failures: HashMap::new(),
+            log_ingest: LogIngestStats::default(),
+            budgets: Vec::new(),
+            simulation: None,
+            cpu_normalization: CpuNormalization::TotalCapacity,
+            memory_standard: MemoryStandard::default(),
+            target_selector: None,
+            membership: Vec::new(),
+            buffer: Vec::new(),
+            latest_samples: HashMap::new(),
+            metric_sink: None,
+            stream_file: None,
+            ingest: IngestCounters::default(),
+            events: Vec::new(),
+            gpu_info: None,
+            cdp_process_info: None,
+            ws_clients: Vec::new(),
+            extension_dependency: None,
+            stalled_since: None,
+            interrupted_by_exit: false,
+            coverage: HashMap::new(),
+            tab_pids: HashMap::new(),
+            phase_metric: None,
+            phase_active: None,
+            role_assignments: Vec::new(),
+            persisted_batches: 0,
+            chunk_seq: 0,
+            chunk_lock: Arc::new(Mutex::new(())),
+            effective_sampling: None,
+            adaptive: None,
+            process_roles: HashMap::new(),
+            tab_titles: HashMap::new(),
+            exits: HashMap::new(),
+        }
+    }
+
+    fn payload(pids: impl Iterator<Item = u32>) -> Value {
+        let metrics: serde_json::Map<String, Value> =
+            pids.map(|pid| (pid.to_string(), json!({ "cpu": 1.0, "memory": 10.0 }))).collect();
+        json!({ "type": "data", "metrics": metrics })
+    }
+
+    // Sessions start and stop while several threads ingest: every accepted point ends up in
+    // exactly one stopped run, and only in the run recording its PID.
+    #[test]
+    fn start_stop_and_ingest_hammered_concurrently() {
+        const INGEST_THREADS: i64 = 4;
+        const PAYLOADS_PER_THREAD: i64 = 2000;
+        let state = Arc::new(CollectionState::new());
+        let done = Arc::new(AtomicBool::new(false));
+        let accepted = Arc::new(AtomicUsize::new(0));
+        let base = Utc::now();
+
+        let ingesters: Vec<_> = (0..INGEST_THREADS)
+            .map(|t| {
+                let state = state.clone();
+                let accepted = accepted.clone();
+                std::thread::spawn(move || {
+                    for i in 0..PAYLOADS_PER_THREAD {
+                        // Unique timestamps, so no batch merges into another thread's.
+                        let timestamp = base + chrono::Duration::milliseconds(i * INGEST_THREADS + t);
+                        let (outcomes, _) =
+                            record_metric_payload(&state, &payload(1..=8), DataSource::Native, "test", timestamp, 0.0);
+                        accepted.fetch_add(outcomes.iter().map(|(n, _)| n).sum::<usize>(), Ordering::SeqCst);
+                    }
+                })
+            })
+            .collect();
+
+        let cycler = {
+            let state = state.clone();
+            let done = done.clone();
+            std::thread::spawn(move || {
+                let mut finished = Vec::new();
+                let mut round = 0;
+                while !done.load(Ordering::SeqCst) {
+                    let a = format!("a-{}", round);
+                    let b = format!("b-{}", round);
+                    assert!(state.begin(test_run(&a, vec![1, 2, 3, 4])).is_ok());
+                    assert!(state.begin(test_run(&b, vec![5, 6, 7, 8])).is_ok());
+                    std::thread::yield_now();
+                    finished.extend(state.finish(&a));
+                    finished.extend(state.finish(&b));
+                    round += 1;
+                }
+                finished
+            })
+        };
+
+        for t in ingesters {
+            t.join().expect("ingest thread panicked");
+        }
+        done.store(true, Ordering::SeqCst);
+        let mut finished = cycler.join().expect("start/stop thread panicked");
+        for id in state.session_ids() {
+            finished.extend(state.finish(&id));
+        }
+        assert!(!state.is_running());
+
+        let mut recorded = 0;
+        for run in &finished {
+            for batch in &run.buffer {
+                for pid in batch.metrics.keys() {
+                    assert!(run.target_pids.contains(pid), "pid {} buffered by {}", pid, run.session_id);
+                }
+                recorded += batch.metrics.len();
+            }
+        }
+        assert_eq!(recorded, accepted.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn selector_pids_are_added_once() {
+        let mut target = vec![7, 3];
+        add_target_pids(&mut target, [3, 9, 9]);
+        add_target_pids(&mut target, [9, 7, 11, 3]);
+        assert_eq!(target, vec![7, 3, 9, 11]);
+    }
+
+    // Samples written in chunks during the run and joined at stop read back as the same text the
+    // single insert at stop used to write.
+    #[test]
+    fn chunked_metrics_json_matches_serializing_the_buffer() {
+        let db = Database::new(":memory:").unwrap();
+        let state = CollectionState::new();
+        assert!(state.begin(test_run("chunked", vec![1, 2, 3])).is_ok());
+        let base = Utc::now();
+        for i in 0..500i64 {
+            let metrics: serde_json::Map<String, Value> = (1..=3u32)
+                .map(|pid| {
+                    let x = (i * pid as i64) as f64;
+                    (pid.to_string(), json!({ "cpu": x / 7.0, "memory": 100.0 + x.sqrt(), "gpu": x * 1e-9 }))
+                })
+                .collect();
+            let payload = json!({ "type": "data", "metrics": metrics });
+            let timestamp = base + chrono::Duration::milliseconds(i * 250);
+            record_metric_payload(&state, &payload, DataSource::Native, "test", timestamp, 16.0 * 1024.0 * 1024.0 * 1024.0);
+            if i % 37 == 0 {
+                assert!(write_pending_chunk(&state, &db, "chunked"));
+            }
+        }
+        let run = state.finish("chunked").expect("session was running");
+        assert!(run.chunk_seq > 1 && run.persisted_batches < run.buffer.len());
+
+        let chunked = assemble_metrics_json(&db, &run, &run.buffer).unwrap();
+        assert_eq!(chunked, serde_json::to_string(&run.buffer).unwrap());
+    }
+
+    fn bundle_entries(db: &Database, workers: usize) -> Vec<(String, Vec<u8>)> {
+        let mut settings = Settings::load(db);
+        settings.max_parallel_reports = Some(workers);
+        settings.save(db).unwrap();
+        let items: Vec<ExportBundleItemV1> = db
+            .get_all_reports()
+            .unwrap()
+            .iter()
+            .map(|r| ExportBundleItemV1 { report_id: r.id, pdf_base64: None, pdf_path: None })
+            .collect();
+        let mut progress = Vec::new();
+        let mut zip = std::io::Cursor::new(Vec::new());
+        write_reports_bundle(
+            db,
+            &AtomicBool::new(false),
+            &mut zip,
+            items,
+            u64::MAX,
+            true,
+            None,
+            "2025-01-01T00:00:00+00:00",
+            |p| progress.push(p.report_id),
+        )
+        .unwrap();
+        assert_eq!(progress.len(), 8);
+
+        let mut archive = zip::ZipArchive::new(zip).unwrap();
+        (0..archive.len())
+            .map(|i| {
+                let mut entry = archive.by_index(i).unwrap();
+                let mut bytes = Vec::new();
+                std::io::Read::read_to_end(&mut entry, &mut bytes).unwrap();
+                (entry.name().to_string(), bytes)
+            })
+            .collect()
+    }
+
+    // Parallel dataset building must not change the bundle: same entries, same order, same bytes.
+    #[test]
+    fn bundle_zip_content_does_not_depend_on_the_worker_count() {
+        let db = Database::new(":memory:").unwrap();
+        for _ in 0..4 {
+            for sample in crate::sample_data::reports(Utc::now()) {
+                db.save_report(&sample.title, &sample.metrics, &sample.meta).unwrap();
+            }
+        }
+        // The first export computes and caches each analysis; compare exports reading the cache.
+        bundle_entries(&db, 1);
+        let sequential = bundle_entries(&db, 1);
+        assert!(sequential.iter().filter(|(name, _)| name.contains("/dataset_")).count() == 8);
+        for workers in [2, 4, 8] {
+            assert!(bundle_entries(&db, workers) == sequential, "{} workers", workers);
+        }
+    }
+
+    // Quitting mid-run saves the buffered samples as a report flagged interrupted_by_exit.
+    #[test]
+    fn exit_during_a_run_saves_its_buffer() {
+        let app = tauri::test::mock_app();
+        app.manage(Database::new(":memory:").unwrap());
+        app.manage(CollectionState::new());
+        app.manage(IngestServerState::new());
+        let state = app.state::<CollectionState>();
+        assert!(state.begin(test_run("quitting", vec![1, 2])).is_ok());
+        let base = Utc::now();
+        for i in 0..20 {
+            let timestamp = base + chrono::Duration::seconds(i);
+            record_metric_payload(state.inner(), &payload(1..=2), DataSource::Native, "test", timestamp, 0.0);
+        }
+
+        shutdown_on_exit(app.handle());
+
+        assert!(!state.is_running());
+        let db = app.state::<Database>();
+        let reports = db.get_all_reports().unwrap();
+        assert_eq!(reports.len(), 1);
+        let detail = db.get_report_detail(reports[0].id).unwrap();
+        assert_eq!(detail.metrics.len(), 20);
+        assert_eq!(detail.meta.extra.get("interrupted_by_exit"), Some(&json!(true)));
+        let stopped = detail.meta.events.iter().find(|e| e.kind == RunEventKind::Stopped).expect("stop event");
+        assert_eq!(stopped.detail["reason"], "app_exit");
+        assert!(db.unsaved_runs().unwrap().is_empty());
+    }
+
+    // A rename replaces the scenario fallback of an auto-titled run, in the list, the detail and
+    // a dataset exported and imported into another database.
+    #[test]
+    fn renamed_report_keeps_its_title_through_listing_and_dataset_round_trip() {
+        let db = Database::new(":memory:").unwrap();
+        let sample = crate::sample_data::reports(Utc::now()).remove(0);
+        let auto_title = format!("{}2025-01-01 10:00:00", AUTO_TITLE_PREFIX);
+        let id = db.save_report(&auto_title, &sample.metrics, &sample.meta).unwrap();
+        assert_eq!(db.get_all_reports().unwrap()[0].title, "Sample: dashboard idle");
+
+        assert_eq!(db.update_report_title(id, "Nightly regression").unwrap(), 1);
+        assert_eq!(db.get_all_reports().unwrap()[0].title, "Nightly regression");
+        assert_eq!(db.get_report_detail(id).unwrap().title, "Nightly regression");
+
+        let prepared = prepare_report_dataset(&db, id, false, false, "2025-01-01T00:00:00+00:00").unwrap();
+        assert_eq!(prepared.title, "Nightly regression");
+        let PreparedBody::Json(json) = prepared.body else { panic!("dataset built as a value") };
+        let other = Database::new(":memory:").unwrap();
+        let imported = import_report_dataset_blocking(&other, String::from_utf8(json).unwrap(), None).unwrap();
+        assert_eq!(imported.status, ImportStatus::Imported);
+        let reports = other.get_all_reports().unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].title, "Nightly regression");
+        assert_eq!(other.get_report_detail(reports[0].id).unwrap().title, "Nightly regression");
+    }
+
+    // A simulated run goes through the real start, collection loop, stop and save; the saved
+    // report's analysis finds the injected spike and leak.
+    #[test]
+    fn simulated_run_is_saved_with_its_spike_and_leak_detected() {
+        let app = tauri::test::mock_app();
+        app.manage(Database::new(":memory:").unwrap());
+        app.manage(CollectionState::new());
+        app.manage(IngestServerState::new());
+        let state = app.state::<CollectionState>();
+        let db = app.state::<Database>();
+        let config: CollectionConfig = serde_json::from_value(json!({
+            "target_pids": [SIMULATED_PID_BASE, SIMULATED_PID_BASE + 1],
+            "interval_ms": MIN_INTERVAL_MS,
+            "mode": "simulate",
+            "simulation": { "spike_every": 10, "spike_cpu": 95.0, "leak_mb_per_sample": 2.0 },
+        }))
+        .unwrap();
+
+        let session_id =
+            tauri::async_runtime::block_on(start_collection_with(app.handle().clone(), state.inner(), db.inner(), config))
+                .unwrap();
+        let deadline = std::time::Instant::now() + Duration::from_secs(30);
+        while state.read_session(&session_id, |run| run.buffer.len()).unwrap() < 25 {
+            assert!(std::time::Instant::now() < deadline, "simulated run stalled");
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        let stopped = stop_collection_and_save(app.handle(), state.inner(), db.inner(), &session_id).unwrap();
+
+        assert!(stopped.saved);
+        let detail = db.get_report_detail(stopped.report_id.unwrap()).unwrap();
+        assert!(detail.metrics.len() >= 25);
+        let collection = detail.meta.collection.as_ref().unwrap();
+        assert_eq!(collection.mode.as_deref(), Some("simulate"));
+        assert_eq!(collection.extra.get("simulated"), Some(&json!(true)));
+        let analysis = detail.analysis.unwrap();
+        let codes: Vec<&str> = analysis.insights.iter().map(|i| i.code.as_str()).collect();
+        assert!(codes.contains(&"CPU_SPIKE"), "{:?}", codes);
+        assert!(codes.contains(&"MEM_GROWTH_HIGH"), "{:?}", codes);
+        assert!(analysis.summary.mem_growth_rate > 1.5, "{}", analysis.summary.mem_growth_rate);
+    }
+
+    // Export reports 1 and 2 with their comparisons; comparison B also uses report 3, which is
+    // not in the bundle.
+    fn bundle_with_partial_comparison(src: &Database, path: &std::path::Path) -> (i64, i64, i64) {
+        let now = Utc::now();
+        let mut ids = Vec::new();
+        for sample in crate::sample_data::reports(now).into_iter().chain(crate::sample_data::reports(now - chrono::Duration::days(3)).into_iter().take(1)) {
+            ids.push(src.save_report(&sample.title, &sample.metrics, &sample.meta).unwrap());
+        }
+        let (r1, r2, r3) = (ids[0], ids[1], ids[2]);
+        let pid = crate::collector::simulate::SIMULATED_PID_BASE;
+        src.create_comparison(
+            "A",
+            &[r1, r2],
+            "Cmp",
+            Some(r1),
+            &json!({ r1.to_string(): [pid], r2.to_string(): [pid] }),
+            &json!({}),
+            None,
+            &json!({ "tags": "perf, nightly" }),
+        )
+        .unwrap();
+        src.create_comparison("B", &[r1, r3], "", None, &json!({}), &json!({}), None, &json!({})).unwrap();
+
+        let items = [r1, r2].iter().map(|&report_id| ExportBundleItemV1 { report_id, pdf_base64: None, pdf_path: None }).collect();
+        let mut zip = std::io::Cursor::new(Vec::new());
+        write_reports_bundle(src, &AtomicBool::new(false), &mut zip, items, u64::MAX, true, None, "2025-01-01T00:00:00+00:00", |_| {})
+            .unwrap();
+        std::fs::write(path, zip.into_inner()).unwrap();
+        (r1, r2, r3)
+    }
+
+    fn import_bundle(db: Database, path: &std::path::Path) -> (FolderBundleImportResult, Database) {
+        let app = tauri::test::mock_app();
+        let result = import_folder_bundle_blocking(app.handle(), &db, path.display().to_string(), None, None).unwrap();
+        (result, db)
+    }
+
+    #[test]
+    fn bundled_comparisons_round_trip_with_partial_members() {
+        let path = std::env::temp_dir().join(format!("perfsight-bundle-comparisons-{}.zip", std::process::id()));
+        let src = Database::new(":memory:").unwrap();
+        let (_, _, r3) = bundle_with_partial_comparison(&src, &path);
+
+        // Only A's members are in the bundle: A is recreated against the new ids, B is skipped.
+        let (result, dst) = import_bundle(Database::new(":memory:").unwrap(), &path);
+        assert_eq!(result.imported_ids.len(), 2);
+        assert_eq!(result.comparisons_created.len(), 1);
+        assert_eq!(result.warnings, vec!["Skipped comparison 'B': 1 of 2 reports are missing".to_string()]);
+        let a = dst.get_comparison_detail(result.comparisons_created[0]).unwrap();
+        assert_eq!(a.title, "A");
+        assert_eq!(a.folder_path, "Cmp");
+        assert_eq!(a.tags, vec!["perf", "nightly"]);
+        assert_eq!(a.report_ids, result.imported_ids);
+        assert_eq!(a.baseline_report_id, Some(result.imported_ids[0]));
+        let pid = crate::collector::simulate::SIMULATED_PID_BASE;
+        let selections: Value =
+            result.imported_ids.iter().map(|id| (id.to_string(), json!([pid]))).collect::<serde_json::Map<_, _>>().into();
+        assert_eq!(a.cpu_selections_by_id, selections);
+        assert_eq!(dst.get_known_comparison_tags().unwrap().len(), 2);
+
+        // With report 3 already present, B resolves to it too; a second import adds nothing.
+        let dst = Database::new(":memory:").unwrap();
+        let prepared = prepare_report_dataset(&src, r3, false, false, "2025-01-01T00:00:00+00:00").unwrap();
+        let PreparedBody::Json(json) = prepared.body else { panic!("dataset built as a value") };
+        let existing = import_report_dataset_blocking(&dst, String::from_utf8(json).unwrap(), None).unwrap().report_id.unwrap();
+        let (result, dst) = import_bundle(dst, &path);
+        assert_eq!(result.comparisons_created.len(), 2);
+        assert!(result.warnings.is_empty(), "{:?}", result.warnings);
+        let b = dst.get_all_comparisons(None).unwrap().into_iter().find(|c| c.title == "B").unwrap();
+        let b = dst.get_comparison_detail(b.id).unwrap();
+        assert_eq!(b.report_ids, vec![result.imported_ids[0], existing]);
+
+        let (again, dst) = import_bundle(dst, &path);
+        assert!(again.imported_ids.is_empty());
+        assert!(again.comparisons_created.is_empty());
+        assert_eq!(again.warnings.len(), 2);
+        assert!(again.warnings.iter().all(|w| w.ends_with("already present")), "{:?}", again.warnings);
+        assert_eq!(dst.get_all_comparisons(None).unwrap().len(), 2);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    // Synthetic report of at least `min_bytes` of metrics JSON: ten PIDs per batch.
+    fn large_metrics(min_bytes: usize) -> (Vec<BatchMetric>, String) {
+        let start = Utc::now();
+        let batch = |i: usize| {
+            let timestamp = start + chrono::Duration::milliseconds(i as i64 * 100);
+            let metrics = (0..10u32)
+                .map(|pid| {
+                    let point: MetricPoint = serde_json::from_value(json!({
+                        "timestamp": timestamp,
+                        "pid": 1000 + pid,
+                        "cpu_usage": (i % 97) as f32 * 0.5 + pid as f32,
+                        "cpu_os_usage": (i % 89) as f32 * 0.5,
+                        "cpu_chrome_usage": null,
+                        "memory_rss": 200_000_000u64 + (i as u64 * 4096) + pid as u64,
+                        "memory_footprint": null,
+                        "gpu_usage": null,
+                        "js_heap_size": 50_000_000u64 + i as u64,
+                        "memory_private": 150_000_000u64 + i as u64,
+                        "custom_metrics": null,
+                    }))
+                    .unwrap();
+                    (1000 + pid, point)
+                })
+                .collect();
+            BatchMetric { timestamp, metrics }
+        };
+        let per_batch = serde_json::to_string(&batch(0)).unwrap().len();
+        let metrics: Vec<BatchMetric> = (0..min_bytes / per_batch + 1).map(batch).collect();
+        let json = serde_json::to_string(&metrics).unwrap();
+        (metrics, json)
+    }
+
+    // Bundling streams each report: peak memory follows the largest report (its stored JSON
+    // and its dataset), not the number of reports or the bundle size.
+    #[test]
+    fn bundle_export_memory_does_not_grow_with_the_report_count() {
+        const REPORT_BYTES: usize = 50 * 1024 * 1024;
+        let db = Database::new(":memory:").unwrap();
+        let mut settings = Settings::load(&db);
+        settings.max_parallel_reports = Some(1);
+        settings.save(&db).unwrap();
+        let (metrics, metrics_json) = large_metrics(REPORT_BYTES);
+        let meta = ReportMeta::default();
+        let analysis = crate::analysis::analyze_report(&metrics, &meta);
+        drop(metrics);
+        let ids: Vec<i64> = (0..3)
+            .map(|i| {
+                let id = db.save_report_json(&format!("Large {}", i), &[], &metrics_json, &meta).unwrap();
+                db.cache_analysis(id, &analysis).unwrap();
+                id
+            })
+            .collect();
+        drop(metrics_json);
+
+        let path = std::env::temp_dir().join(format!("perfsight-large-bundle-{}.zip", std::process::id()));
+        let export = |ids: &[i64]| {
+            let items = ids.iter().map(|&report_id| ExportBundleItemV1 { report_id, pdf_base64: None, pdf_path: None }).collect();
+            let mut progress = Vec::new();
+            let peak = peak_heap_growth(|| {
+                let file = std::fs::File::create(&path).unwrap();
+                write_reports_bundle(&db, &AtomicBool::new(false), file, items, u64::MAX, true, None, "2025-01-01T00:00:00+00:00", |p| {
+                    progress.push(p.bytes_written)
+                })
+                .unwrap();
+            });
+            assert_eq!(progress.len(), ids.len());
+            assert!(progress[ids.len() - 1] as usize > ids.len() * REPORT_BYTES);
+            peak
+        };
+        let one = export(&ids[..1]);
+        let three = export(&ids);
+        let _ = std::fs::remove_file(&path);
+
+        assert!(one < 3 * REPORT_BYTES as u64, "one report peaked at {} MB", one >> 20);
+        assert!(three < one + one / 5, "three reports peaked at {} MB, one at {} MB", three >> 20, one >> 20);
+    }
+
+    // The sidecar's stdout pauses mid-run: once it has been silent for the stall threshold the
+    // run is marked stalled, the next data line recovers it, and the analysis leaves the gap out.
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn paused_sidecar_stream_is_detected_as_a_stall_and_recovers() {
+        use tauri::Listener;
+        let app = tauri::test::mock_app();
+        app.manage(CollectionState::new());
+        let state = app.state::<CollectionState>();
+        let base = Utc.timestamp_millis_opt(Utc::now().timestamp_millis() / 1000 * 1000).unwrap();
+        let mut run = test_run("paused", vec![1, 2]);
+        run.started_at = base.to_rfc3339();
+        assert!(state.begin(run).is_ok());
+        let emitted = Arc::new(Mutex::new(Vec::new()));
+        for event in ["data-source-stalled", "data-source-recovered"] {
+            let emitted = emitted.clone();
+            app.listen_any(event, move |e| emitted.lock().unwrap().push((event, e.payload().to_string())));
+        }
+        let feed = |at: DateTime<Utc>| {
+            let mut line = payload(1..=2);
+            line["timestamp"] = json!(at.timestamp_millis());
+            handle_sidecar_line(app.handle(), state.inner(), &line.to_string(), at);
+        };
+        let stall_events = || {
+            state
+                .read_session("paused", |run| {
+                    run.events
+                        .iter()
+                        .filter(|e| matches!(e.kind, RunEventKind::SourceStalled | RunEventKind::SourceRecovered))
+                        .cloned()
+                        .collect::<Vec<_>>()
+                })
+                .unwrap()
+        };
+
+        for i in 0..5 {
+            feed(base + chrono::Duration::seconds(i));
+            check_sidecar_stall(app.handle(), state.inner(), "paused", base + chrono::Duration::seconds(i));
+        }
+        // Paused: a heartbeat-free stretch just under the threshold (3 intervals) is still fine.
+        check_sidecar_stall(app.handle(), state.inner(), "paused", base + chrono::Duration::milliseconds(6900));
+        assert!(stall_events().is_empty());
+        check_sidecar_stall(app.handle(), state.inner(), "paused", base + chrono::Duration::seconds(10));
+        let events = stall_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, RunEventKind::SourceStalled);
+        assert_eq!(events[0].detail["last_seen_at"], json!((base + chrono::Duration::seconds(4)).to_rfc3339()));
+        assert_eq!(events[0].detail["threshold_ms"], json!(3000));
+        // Still silent: no second stall.
+        check_sidecar_stall(app.handle(), state.inner(), "paused", base + chrono::Duration::seconds(20));
+        assert_eq!(stall_events().len(), 1);
+
+        // The stream resumes.
+        for i in 30..40 {
+            feed(base + chrono::Duration::seconds(i));
+            check_sidecar_stall(app.handle(), state.inner(), "paused", base + chrono::Duration::seconds(i));
+        }
+        let events = stall_events();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].kind, RunEventKind::SourceRecovered);
+        assert_eq!(events[1].detail["resumed_at"], json!((base + chrono::Duration::seconds(30)).to_rfc3339()));
+        assert_eq!(events[1].detail["gap_ms"], json!(26_000));
+        assert_eq!(state.read_session("paused", |run| run.stalled_since).unwrap(), None);
+        let emitted = emitted.lock().unwrap().clone();
+        assert_eq!(emitted.iter().map(|(e, _)| *e).collect::<Vec<_>>(), vec!["data-source-stalled", "data-source-recovered"]);
+        assert!(emitted[1].1.contains("\"gap_ms\":26000"), "{}", emitted[1].1);
+
+        let (metrics, events) = state.read_session("paused", |run| (run.buffer.clone(), run.events.clone())).unwrap();
+        assert_eq!(metrics.len(), 15);
+        let report = crate::analysis::analyze_with_events(&metrics, &events);
+        let excluded = report.insights.iter().find(|i| i.code == "SLEEP_SAMPLES_EXCLUDED").expect("gap excluded");
+        assert_eq!(excluded.params["stall_gaps"], json!(1));
+        assert_eq!(excluded.params["sleep_gaps"], json!(0));
+    }
+
+    // Neither an anonymized dataset export nor an anonymized bundle carries any of the
+    // sensitive values; the local map next to the dataset does.
+    #[test]
+    fn anonymized_exports_leave_no_sensitive_field() {
+        use crate::anonymize::tests::{sensitive_meta, SENSITIVE};
+        let app = tauri::test::mock_app();
+        let db = Database::new(":memory:").unwrap();
+        let sample = crate::sample_data::reports(Utc::now()).remove(0);
+        let meta: ReportMeta = serde_json::from_value(sensitive_meta()).unwrap();
+        let id = db.save_report("Nightly", &sample.metrics, &meta).unwrap();
+        let assert_clean = |what: &str, text: &str| {
+            for s in SENSITIVE {
+                assert!(!text.contains(s), "{:?} survived in {}", s, what);
+            }
+        };
+        let options = AnonymizeOptions { mask_folders: true, write_map: true, ..Default::default() };
+
+        let dir = std::env::temp_dir().join(format!("perfsight-anon-{}", uuid::Uuid::new_v4().simple()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let destination = ExportDestination { dest_path: Some(dir.join("shared.json").to_string_lossy().to_string()), ..Default::default() };
+        let path = export_report_dataset_blocking(app.handle(), &db, id, None, Some(destination), None, None, Some(options.clone())).unwrap();
+        let exported = std::fs::read_to_string(&path).unwrap();
+        assert_clean("the dataset", &exported);
+        let dataset: Value = serde_json::from_str(&exported).unwrap();
+        assert_eq!(dataset["report"]["title"], "Nightly");
+        assert_eq!(dataset["report"]["metrics"].as_array().unwrap().len(), sample.metrics.len());
+        // Without the option the same export does carry them.
+        let plain = prepare_report_dataset(&db, id, false, false, "2025-01-01T00:00:00+00:00").unwrap();
+        let PreparedBody::Json(plain) = plain.body else { panic!("dataset built as a value") };
+        let plain = String::from_utf8(plain).unwrap();
+        assert!(plain.contains("alice-macbook.corp.example") && plain.contains("secret-doc-id") && plain.contains("--user-data-dir"));
+        let map = std::fs::read_to_string(dir.join("shared.json.anonymization.json")).unwrap();
+        assert!(map.contains("Q3 reorg plan - Google Docs") && map.contains("payroll"), "{}", map);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let mut anonymizer = Anonymizer::new(options);
+        let mut zip = std::io::Cursor::new(Vec::new());
+        let items = vec![ExportBundleItemV1 { report_id: id, pdf_base64: None, pdf_path: None }];
+        write_reports_bundle(&db, &AtomicBool::new(false), &mut zip, items, u64::MAX, true, Some(&mut anonymizer), "2025-01-01T00:00:00+00:00", |_| {})
+            .unwrap();
+        let mut archive = zip::ZipArchive::new(zip).unwrap();
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i).unwrap();
+            let name = entry.name().to_string();
+            assert_clean("an entry name", &name);
+            let mut text = String::new();
+            std::io::Read::read_to_string(&mut entry, &mut text).unwrap();
+            assert_clean(&name, &text);
+        }
+    }
+
+    // Loading a 100 MB report runs on the blocking pool and holds the connection only while
+    // reading the row: a status poll and another database command finish while it is still
+    // parsing.
+    #[test]
+    fn loading_a_large_report_blocks_neither_status_nor_other_commands() {
+        let app = tauri::test::mock_app();
+        app.manage(Database::new(":memory:").unwrap());
+        app.manage(CollectionState::new());
+        app.manage(IngestServerState::new());
+        let (metrics, _) = large_metrics(100 * 1024 * 1024);
+        let id = app.state::<Database>().save_report("Large", &metrics, &ReportMeta::default()).unwrap();
+        let batches = metrics.len();
+        drop(metrics);
+
+        tauri::async_runtime::block_on(async {
+            let loaded = Arc::new(AtomicBool::new(false));
+            let (started_tx, started_rx) = tokio::sync::oneshot::channel();
+            let slow = {
+                let (app_handle, loaded) = (app.handle().clone(), loaded.clone());
+                tauri::async_runtime::spawn(async move {
+                    run_blocking(&app_handle, move |_, db| {
+                        let _ = started_tx.send(());
+                        let detail = db.get_report_detail(id)?;
+                        loaded.store(true, Ordering::SeqCst);
+                        Ok(detail.metrics.len())
+                    })
+                    .await
+                })
+            };
+            started_rx.await.unwrap();
+
+            let status = get_collection_status(app.state(), app.state(), None).unwrap();
+            assert!(!status.is_running);
+            let listed = run_blocking(app.handle(), |_, db| Ok(db.get_all_reports()?.len())).await.unwrap();
+            assert_eq!(listed, 1);
+            assert!(!loaded.load(Ordering::SeqCst), "the other commands waited for the 100 MB load");
+
+            assert_eq!(slow.await.unwrap().unwrap(), batches);
+        });
+    }
+
+    // A line where only some named groups capture pushes just those metrics, all at the line's
+    // timestamp and PID; a group whose text isn't a number counts as a parse failure.
+    #[test]
+    fn partially_matching_log_lines_push_only_the_captured_metrics() {
+        let app = tauri::test::mock_app();
+        app.manage(CollectionState::new());
+        let state = app.state::<CollectionState>();
+        let cfg: LogMetricConfig = serde_json::from_value(json!({
+            "name": "stats",
+            "pattern": r"stats:(?: fps=(?P<fps>\S+))?(?: bitrate=(?P<bitrate>\d+))?(?: rtt=(?P<rtt>\d+))?",
+            "unit": null,
+            "target_pid": null,
+            "captures": { "fps": "FPS", "bitrate": { "name": "Bitrate", "unit": "kbps" }, "rtt": { "name": "RTT", "unit": "ms" } },
+        }))
+        .unwrap();
+        let re = crate::models::compile_log_pattern(&cfg.pattern).unwrap();
+        let mut run = test_run("logs", vec![7]);
+        run.log_metrics = vec![(cfg, re)];
+        assert!(state.begin(run).is_ok());
+
+        let base = Utc::now().timestamp_millis() / 1000 * 1000;
+        let lines = [
+            "stats: fps=29 bitrate=1800 rtt=45",
+            "stats: fps=30 rtt=50",
+            "stats: bitrate=1700",
+            "stats:",
+            "unrelated line",
+            "stats: fps=n/a rtt=40",
+        ];
+        let pushed: Vec<usize> = lines
+            .iter()
+            .enumerate()
+            .map(|(i, content)| {
+                let payload = json!({ "type": "console_log", "data": { "content": content, "pid": 7, "timestamp": base + i as i64 * 1000 } });
+                crate::ws_server::process_console_log_payload(app.handle(), state.inner(), &payload)
+            })
+            .collect();
+        assert_eq!(pushed, vec![3, 2, 1, 0, 0, 1]);
+
+        // Each value is its own point; group them by the line (second) they came from.
+        let mut captured: std::collections::BTreeMap<i64, Vec<(String, f64)>> = Default::default();
+        state.read_session("logs", |run| {
+            for b in &run.buffer {
+                let values = captured.entry((b.timestamp.timestamp_millis() - base) / 1000).or_default();
+                values.extend(b.metrics[&7].custom_metrics.clone().unwrap_or_default());
+                values.sort_by(|a, b| a.0.cmp(&b.0));
+            }
+        });
+        let captured: Vec<_> = captured.into_iter().collect();
+        let metrics = |pairs: &[(&str, f64)]| pairs.iter().map(|(n, v)| (n.to_string(), *v)).collect::<Vec<_>>();
+        assert_eq!(
+            captured,
+            vec![
+                (0, metrics(&[("Bitrate", 1800.0), ("FPS", 29.0), ("RTT", 45.0)])),
+                (1, metrics(&[("FPS", 30.0), ("RTT", 50.0)])),
+                (2, metrics(&[("Bitrate", 1700.0)])),
+                (5, metrics(&[("RTT", 40.0)])),
+            ]
+        );
+        let failures = state.read_session("logs", |run| run.log_parse_failures.clone()).unwrap();
+        assert_eq!(failures.len(), 1);
+        assert_eq!((failures["FPS"].count, failures["FPS"].last_text.as_str()), (1, "n/a"));
+    }
+
+    // A pattern that backtracking engines take exponential time on, against lines well past the
+    // length cap: matching each line stays cheap, and metric payloads arriving while the log
+    // worker is busy are ingested without waiting for it.
+    #[test]
+    fn slow_log_patterns_do_not_delay_metric_ingestion() {
+        let app = tauri::test::mock_app();
+        app.manage(CollectionState::new());
+        app.manage(crate::ws_server::IngestServerState::new());
+        let state = app.state::<CollectionState>();
+        let cfg: LogMetricConfig = serde_json::from_value(json!({
+            "name": "Frames",
+            "pattern": r"^(?:a|aa)+(?:a*)*(\d+)$",
+            "unit": null,
+            "target_pid": null,
+        }))
+        .unwrap();
+        let re = crate::models::compile_log_pattern(&cfg.pattern).unwrap();
+        let mut run = test_run("slow-logs", vec![7]);
+        run.mode = "browser".to_string();
+        run.log_metrics = vec![(cfg, re)];
+        assert!(state.begin(run).is_ok());
+
+        // Nested quantifiers that would blow the compiled size limit are refused up front.
+        assert!(crate::models::compile_log_pattern(r"(\w{1000}){1000}").is_err());
+
+        // Matching is linear in the (truncated) line, whatever the pattern.
+        let base = Utc::now().timestamp_millis() / 1000 * 1000;
+        let long_line = json!({ "type": "console_log", "data": { "content": "a".repeat(1 << 20), "pid": 7, "timestamp": base } });
+        let started = std::time::Instant::now();
+        assert_eq!(crate::ws_server::process_console_log_payload(app.handle(), state.inner(), &long_line), 0);
+        assert!(started.elapsed() < std::time::Duration::from_secs(1), "one line took {:?}", started.elapsed());
+        assert_eq!(state.read_session("slow-logs", |run| run.log_ingest.truncated_lines), Some(1));
+
+        let server = app.state::<crate::ws_server::IngestServerState>();
+        *safe_lock(&server.log_queue) = Some(crate::ws_server::spawn_log_worker(app.handle().clone()));
+        let mut slowest = std::time::Duration::ZERO;
+        for i in 1..=20i64 {
+            let ts = base + i * 1000;
+            let line = json!({ "type": "console_log", "data": { "content": "a".repeat(1 << 20), "pid": 7, "timestamp": ts } });
+            let metrics = json!({ "type": "data", "timestamp": ts, "metrics": { "7": { "cpu": 5.0, "memory": 100.0 } } });
+            let started = std::time::Instant::now();
+            for _ in 0..5 {
+                crate::ws_server::queue_console_log(app.handle(), line.clone());
+            }
+            assert_eq!(process_websocket_metric_payload(app.handle(), metrics, state.inner(), "test"), 1);
+            slowest = slowest.max(started.elapsed());
+        }
+        assert!(slowest < std::time::Duration::from_millis(250), "metric ingest took {:?}", slowest);
+        assert_eq!(state.read_session("slow-logs", |run| run.buffer.len()), Some(20));
+
+        // Every queued line is matched (truncated) or counted as dropped; none are lost silently.
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(60);
+        loop {
+            let ingest = state.read_session("slow-logs", |run| run.log_ingest.clone()).unwrap();
+            if ingest.truncated_lines + ingest.dropped_lines == 101 {
+                break;
+            }
+            assert!(std::time::Instant::now() < deadline, "log worker stuck at {:?}", ingest);
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+    }
+
+    // Each launch opens the same file; only the first, on an empty database, seeds samples.
+    #[test]
+    fn sample_data_is_seeded_once_on_an_empty_database() {
+        let dir = std::env::temp_dir().join(format!("perfsight-seed-{}", uuid::Uuid::new_v4().simple()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("perfsight.db");
+        let launch = || {
+            let db = Database::new(path.to_str().unwrap()).unwrap();
+            seed_sample_data(&db);
+            db
+        };
+
+        let db = launch();
+        let record = db.get_setting_as::<SampleDataRecord>(SETTING_SAMPLE_DATA).unwrap().unwrap();
+        assert_eq!(record.report_ids.len(), 2);
+        assert!(record.comparison_id.is_some());
+        assert_eq!(db.report_count().unwrap(), 2);
+        assert_eq!(db.get_all_comparisons(None).unwrap().len(), 1);
+        drop(db);
+
+        let db = launch();
+        assert_eq!(db.report_count().unwrap(), 2);
+        assert_eq!(db.get_all_comparisons(None).unwrap().len(), 1);
+        let again = db.get_setting_as::<SampleDataRecord>(SETTING_SAMPLE_DATA).unwrap().unwrap();
+        assert_eq!((again.checked_at, again.report_ids), (record.checked_at, record.report_ids));
+
+        // Removing the samples doesn't bring them back on the next launch.
+        let (reports, comparisons) = db.sample_data_ids().unwrap();
+        db.delete_comparisons(&comparisons).unwrap();
+        db.delete_reports(&reports).unwrap();
+        drop(db);
+        let db = launch();
+        assert_eq!(db.report_count().unwrap(), 0);
+        assert!(db.get_all_comparisons(None).unwrap().is_empty());
+        drop(db);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // A database that already holds reports is only marked as checked.
+        let db = Database::new(":memory:").unwrap();
+        let sample = crate::sample_data::reports(Utc::now()).remove(0);
+        db.save_report("Mine", &sample.metrics, &ReportMeta::default()).unwrap();
+        seed_sample_data(&db);
+        let record = db.get_setting_as::<SampleDataRecord>(SETTING_SAMPLE_DATA).unwrap().unwrap();
+        assert!(record.report_ids.is_empty() && record.comparison_id.is_none());
+        assert_eq!(db.report_count().unwrap(), 1);
+        assert!(db.sample_data_ids().unwrap().0.is_empty());
+    }
+
+    #[test]
+    fn comparison_csv_is_written_to_the_requested_path() {
+        let app = tauri::test::mock_app();
+        let db = Database::new(":memory:").unwrap();
+        seed_sample_data(&db);
+        let comparison_id = db.get_setting_as::<SampleDataRecord>(SETTING_SAMPLE_DATA).unwrap().unwrap().comparison_id.unwrap();
+        let dir = std::env::temp_dir().join(format!("perfsight-cmp-csv-{}", uuid::Uuid::new_v4().simple()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("matrix.csv");
+        let written = export_comparison_csv_blocking(app.handle(), &db, comparison_id, Some(path.to_string_lossy().to_string()), None, None).unwrap();
+        assert_eq!(std::path::PathBuf::from(&written), path);
+        let csv = std::fs::read_to_string(&path).unwrap();
+        assert!(csv.starts_with("metric,"), "{}", csv);
+
+        // The path wins over a destination directory; an existing file gets a suffix unless overwritten.
+        let other = ExportDestination { dest_dir: Some(std::env::temp_dir().to_string_lossy().to_string()), ..Default::default() };
+        let again = export_comparison_csv_blocking(app.handle(), &db, comparison_id, Some(path.to_string_lossy().to_string()), None, Some(other)).unwrap();
+        assert_eq!(std::path::PathBuf::from(&again), dir.join("matrix (2).csv"));
+        let replaced = export_comparison_csv_blocking(app.handle(), &db, comparison_id, Some(path.to_string_lossy().to_string()), Some(true), None).unwrap();
+        assert_eq!(std::path::PathBuf::from(&replaced), path);
+
+        // A bare file name has no directory to write into.
+        let err = export_comparison_csv_blocking(app.handle(), &db, comparison_id, Some("matrix.csv".to_string()), None, None).unwrap_err();
+        assert!(matches!(err, PerfSightError::InvalidPath { .. }), "{:?}", err);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // Aliases edited after the run show up in every exporter, read from meta at export time.
+    #[test]
+    fn edited_aliases_reach_the_csv_and_comparison_exports() {
+        let app = tauri::test::mock_app();
+        let db = Database::new(":memory:").unwrap();
+        seed_sample_data(&db);
+        let record = db.get_setting_as::<SampleDataRecord>(SETTING_SAMPLE_DATA).unwrap().unwrap();
+        let (ids, comparison_id) = (record.report_ids, record.comparison_id.unwrap());
+        let browser = SIMULATED_PID_BASE;
+        db.update_report_aliases(ids[0], &[ProcessAlias { pid: browser, alias: "Main browser".to_string() }]).unwrap();
+
+        let dir = std::env::temp_dir().join(format!("perfsight-aliases-{}", uuid::Uuid::new_v4().simple()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let to = |name: &str| Some(ExportDestination { dest_path: Some(dir.join(name).to_string_lossy().to_string()), ..Default::default() });
+        let read = |path: String| std::fs::read_to_string(path).unwrap();
+
+        let samples = read(export_report_csv_blocking(app.handle(), &db, ids[0], None, None, to("samples.csv"), DisplayZone::Utc).unwrap());
+        let rows: Vec<Vec<&str>> = samples.lines().skip(1).map(|l| l.split(',').collect()).collect();
+        assert!(rows.iter().filter(|r| r[1] == browser.to_string()).all(|r| r[2] == "Main browser"));
+        // The other processes' aliases were replaced by the edit.
+        assert!(rows.iter().filter(|r| r[1] != browser.to_string()).all(|r| r[2].is_empty()));
+
+        let matrix = read(export_comparison_csv_blocking(app.handle(), &db, comparison_id, None, None, to("matrix.csv")).unwrap());
+        assert!(matrix.contains(&format!("Main browser ({})", browser)), "{}", matrix);
+        assert!(matrix.contains(&format!("Browser ({})", browser)), "report 2 keeps its alias: {}", matrix);
+
+        let overlay = read(export_comparison_overlay_csv_blocking(app.handle(), &db, comparison_id, 1000, None, None, to("overlay.csv")).unwrap());
+        let header = overlay.lines().next().unwrap();
+        assert!(header.contains("Sample: baseline build/Main browser/cpu"), "{}", header);
+        assert!(header.contains("Sample: candidate build/Browser/cpu"), "{}", header);
+
+        let bundle: Value = serde_json::from_str(&read(
+            export_comparison_bundle_json_blocking(app.handle(), &db, comparison_id, None, None, to("bundle.json")).unwrap(),
+        ))
+        .unwrap();
+        let top_cpu = bundle["reports"][0]["analysis"]["top_cpu"].as_array().unwrap();
+        assert!(top_cpu.iter().any(|c| c["pid"] == browser && c["alias"] == "Main browser"), "{:?}", top_cpu);
+
+        // A report that only carries aliases in its process snapshot is labelled from there.
+        let mut sample = crate::sample_data::reports(Utc::now()).remove(1);
+        sample.meta.process_aliases.clear();
+        for p in sample.meta.process_snapshot.iter_mut() {
+            if p["pid"] == browser {
+                p["alias"] = json!("Legacy browser");
+            }
+        }
+        let legacy = db.save_report("Legacy", &sample.metrics, &sample.meta).unwrap();
+        let samples = read(export_report_csv_blocking(app.handle(), &db, legacy, None, None, to("legacy.csv"), DisplayZone::Utc).unwrap());
+        assert!(samples.lines().skip(1).filter(|l| l.split(',').nth(1) == Some(&browser.to_string())).all(|l| l.contains(",Legacy browser,")));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn junit_export_goes_through_the_export_path_rules() {
+        let app = tauri::test::mock_app();
+        let db = Database::new(":memory:").unwrap();
+        seed_sample_data(&db);
+        let id = db.get_setting_as::<SampleDataRecord>(SETTING_SAMPLE_DATA).unwrap().unwrap().report_ids[0];
+        let dir = std::env::temp_dir().join(format!("perfsight-junit-{}", uuid::Uuid::new_v4().simple()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| Some(dir.join(name).to_string_lossy().to_string());
+
+        let written = export_report_junit_blocking(app.handle(), &db, id, path("ci.xml"), None, None).unwrap();
+        assert_eq!(std::path::PathBuf::from(&written), dir.join("ci.xml"));
+        assert!(std::fs::read_to_string(&written).unwrap().contains("<testsuite"));
+        // Collisions get a suffix unless overwritten; names are sanitized.
+        let again = export_report_junit_blocking(app.handle(), &db, id, path("ci.xml"), None, None).unwrap();
+        assert_eq!(std::path::PathBuf::from(&again), dir.join("ci (2).xml"));
+        let replaced = export_report_junit_blocking(app.handle(), &db, id, path("ci.xml"), Some(true), None).unwrap();
+        assert_eq!(std::path::PathBuf::from(&replaced), dir.join("ci.xml"));
+        let sanitized = export_report_junit_blocking(app.handle(), &db, id, path("a:b?.xml"), None, None).unwrap();
+        assert_eq!(std::path::Path::new(&sanitized).parent(), Some(dir.as_path()));
+        assert!(!sanitized.contains('?'), "{}", sanitized);
+
+        // A missing directory is a structured error unless the destination asks to create it.
+        let missing = path("nested/ci.xml");
+        let err = export_report_junit_blocking(app.handle(), &db, id, missing.clone(), None, None).unwrap_err();
+        assert!(matches!(err, PerfSightError::InvalidPath { .. }), "{:?}", err);
+        let create = ExportDestination { create: true, ..Default::default() };
+        let created = export_report_junit_blocking(app.handle(), &db, id, missing, None, Some(create)).unwrap();
+        assert_eq!(std::path::PathBuf::from(&created), dir.join("nested").join("ci.xml"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // Only the folders of exported comparisons enter the anonymization map and its numbering.
+    #[test]
+    fn skipped_comparisons_do_not_shift_masked_folder_numbers() {
+        let db = Database::new(":memory:").unwrap();
+        let now = Utc::now();
+        let ids: Vec<i64> = crate::sample_data::reports(now)
+            .into_iter()
+            .chain(crate::sample_data::reports(now - chrono::Duration::days(7)))
+            .map(|r| db.save_report(&r.title, &r.metrics, &r.meta).unwrap())
+            .collect();
+        let compare = |report_ids: Vec<i64>, folder: &str| {
+            let args = CreateComparisonArgs {
+                title: Some(folder.to_string()),
+                report_ids,
+                folder_path: Some(folder.to_string()),
+                baseline_report_id: None,
+                cpu_selections_by_id: None,
+                mem_selections_by_id: None,
+                metric_selections: None,
+                meta: None,
+                strict: false,
+            };
+            create_comparison_checked_with(&db, args).unwrap();
+        };
+        // Both orders, so the skipped one is met before the exported one whatever the listing order.
+        compare(vec![ids[2], ids[3]], "Hidden A");
+        compare(vec![ids[0], ids[1]], "Shared");
+        compare(vec![ids[3], ids[2]], "Hidden B");
+
+        let mut anonymizer = Anonymizer::new(AnonymizeOptions { mask_folders: true, ..Default::default() });
+        let mut zip = ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let count = zip_bundle_comparisons(&mut zip, FileOptions::<()>::default(), &db, &ids[..2], Some(&mut anonymizer)).unwrap();
+        assert_eq!(count, 1);
+        let folders = &anonymizer.map()["folders"];
+        assert_eq!(folders.keys().collect::<Vec<_>>(), vec!["Shared"]);
+        assert_eq!(folders["Shared"], "folder-1");
+
+        let mut archive = zip::ZipArchive::new(zip.finish().unwrap()).unwrap();
+        let mut text = String::new();
+        std::io::Read::read_to_string(&mut archive.by_name("comparisons.json").unwrap(), &mut text).unwrap();
+        let bundled: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(bundled["comparisons"][0]["comparison"]["folder_path"], "folder-1");
+    }
+}
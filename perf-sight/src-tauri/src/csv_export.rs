@@ -0,0 +1,628 @@
+use std::collections::{BTreeSet, HashMap};
+use serde_json::Value;
+use crate::analysis::{analyze, ANALYZER_VERSION, memory_bases, phase_windows, split_by_phase, MetricSummary};
+use crate::database::{ComparisonDetail, ReportDetail};
+use crate::models::{BatchMetric, MemoryBasis, MetricSelection};
+use crate::timezone::DisplayZone;
+
+pub fn csv_field(s: &str) -> String {
+    if s.contains(',') || s.contains('"') || s.contains('\n') {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Long-format samples table: one row per (timestamp, pid), custom metrics as extra columns.
+/// Timestamps are RFC3339 with `zone`'s offset so they line up with local logs.
+pub fn render_samples_csv(report: &ReportDetail, zone: DisplayZone) -> String {
+    let aliases = report.meta.aliases();
+    let mut custom_names: Vec<String> = report
+        .metrics
+        .iter()
+        .flat_map(|b| b.metrics.values())
+        .filter_map(|p| p.custom_metrics.as_ref())
+        .flat_map(|m| m.keys().cloned())
+        .collect();
+    custom_names.sort();
+    custom_names.dedup();
+
+    let mut out = String::from("timestamp,pid,alias,cpu_percent,memory_rss_bytes,memory_private_bytes,memory_basis");
+    for name in &custom_names {
+        out.push(',');
+        out.push_str(&csv_field(name));
+    }
+    out.push('\n');
+    for batch in &report.metrics {
+        let mut pids: Vec<_> = batch.metrics.keys().copied().collect();
+        pids.sort();
+        for pid in pids {
+            let p = &batch.metrics[&pid];
+            out.push_str(&format!(
+                "{},{},{},{},{},{},{}",
+                zone.rfc3339(p.timestamp),
+                pid,
+                csv_field(aliases.get(&pid).map(|s| s.as_str()).unwrap_or("")),
+                p.cpu_usage,
+                p.memory_rss,
+                p.memory_private.map(|v| v.to_string()).unwrap_or_default(),
+                p.memory_basis.map(MemoryBasis::as_str).unwrap_or_default()
+            ));
+            for name in &custom_names {
+                out.push(',');
+                if let Some(v) = p.custom_metrics.as_ref().and_then(|m| m.get(name)) {
+                    out.push_str(&v.to_string());
+                }
+            }
+            out.push('\n');
+        }
+    }
+    out
+}
+
+
+// Selected PIDs for one report from a comparison's `{ "<report_id>": [pid..] }` map; None = all PIDs.
+fn selected_pids(selections: &Value, report_id: i64) -> Option<Vec<u32>> {
+    let pids: Vec<u32> = selections
+        .get(report_id.to_string())
+        .and_then(|v| v.as_array())?
+        .iter()
+        .filter_map(|p| p.as_u64().map(|n| n as u32))
+        .collect();
+    if pids.is_empty() { None } else { Some(pids) }
+}
+
+fn restrict_to_pids(metrics: &[BatchMetric], pids: Option<&[u32]>) -> Vec<BatchMetric> {
+    let Some(pids) = pids else { return metrics.to_vec() };
+    metrics
+        .iter()
+        .filter_map(|b| {
+            let m: HashMap<_, _> = b
+                .metrics
+                .iter()
+                .filter(|(pid, _)| pids.contains(pid))
+                .map(|(pid, p)| (*pid, p.clone()))
+                .collect();
+            if m.is_empty() { None } else { Some(BatchMetric { timestamp: b.timestamp, metrics: m }) }
+        })
+        .collect()
+}
+
+fn custom_metric_means(metrics: &[BatchMetric]) -> HashMap<String, f64> {
+    let mut sums: HashMap<String, (f64, usize)> = HashMap::new();
+    for p in metrics.iter().flat_map(|b| b.metrics.values()) {
+        if let Some(custom) = &p.custom_metrics {
+            for (name, v) in custom {
+                if v.is_finite() {
+                    let e = sums.entry(name.clone()).or_insert((0.0, 0));
+                    e.0 += v;
+                    e.1 += 1;
+                }
+            }
+        }
+    }
+    sums.into_iter().map(|(k, (sum, n))| (k, sum / n as f64)).collect()
+}
+
+fn pid_labels(pids: Option<&[u32]>, aliases: &HashMap<u32, String>) -> String {
+    match pids {
+        None => "all".to_string(),
+        Some(pids) => pids
+            .iter()
+            .map(|pid| match aliases.get(pid) {
+                Some(a) => format!("{} ({})", a, pid),
+                None => pid.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("; "),
+    }
+}
+
+fn fmt_value(v: f64) -> String {
+    format!("{:.3}", v)
+}
+
+const SUMMARY_ROWS: [&str; 6] = [
+    "avg_cpu_percent",
+    "p95_cpu_percent",
+    "max_cpu_percent",
+    "avg_mem_mb",
+    "max_mem_mb",
+    "mem_growth_rate_mb_per_sample",
+];
+
+// Metric family ("cpu" / "memory") of each SUMMARY_ROWS entry.
+const SUMMARY_FAMILIES: [&str; 6] = ["cpu", "cpu", "cpu", "memory", "memory", "memory"];
+
+fn summary_values(cpu: &MetricSummary, mem: &MetricSummary) -> [f64; 6] {
+    [
+        cpu.avg_cpu as f64,
+        cpu.p95_cpu as f64,
+        cpu.max_cpu as f64,
+        mem.avg_mem_mb,
+        mem.max_mem_mb,
+        mem.mem_growth_rate,
+    ]
+}
+
+// Summary of one phase of `metrics` (empty when the report never entered it).
+fn phase_summary(metrics: &[BatchMetric], report: &ReportDetail, phase: &str) -> MetricSummary {
+    let slice = split_by_phase(metrics, &phase_windows(&report.meta.events))
+        .into_iter()
+        .find(|s| s.name == phase)
+        .map(|s| s.metrics)
+        .unwrap_or_default();
+    analyze(&slice).summary
+}
+
+/// Comparison matrix: metrics as rows, one value column per report, then a percent-delta-vs-baseline
+/// column per non-baseline report. CPU rows use the stored CPU PID selection, memory and custom
+/// metric rows the memory one.
+/// When every report defines phases, the summary rows are given per shared phase
+/// (`phase:<name>:<metric>`) instead of over whole runs. Only the comparison's `metric_selections`
+/// are listed.
+pub fn render_comparison_csv(cmp: &ComparisonDetail, reports: &[ReportDetail]) -> String {
+    let baseline_id = cmp
+        .baseline_report_id
+        .filter(|id| reports.iter().any(|r| r.id == *id))
+        .or_else(|| reports.first().map(|r| r.id));
+
+    let mut shared_phases: Vec<String> = Vec::new();
+    for w in reports.first().map(|r| phase_windows(&r.meta.events)).unwrap_or_default() {
+        if !shared_phases.contains(&w.name) {
+            shared_phases.push(w.name);
+        }
+    }
+    shared_phases.retain(|name| reports.iter().all(|r| phase_windows(&r.meta.events).iter().any(|w| &w.name == name)));
+    let segments: Vec<Option<String>> = if shared_phases.is_empty() {
+        vec![None]
+    } else {
+        shared_phases.into_iter().map(Some).collect()
+    };
+
+    let mut rows: Vec<(String, Vec<Option<f64>>)> = segments
+        .iter()
+        .flat_map(|segment| {
+            SUMMARY_ROWS.iter().map(move |metric| match segment {
+                Some(phase) => (format!("phase:{}:{}", phase, metric), Vec::new()),
+                None => (metric.to_string(), Vec::new()),
+            })
+        })
+        .collect();
+    let mut cpu_pid_labels = Vec::new();
+    let mut mem_pid_labels = Vec::new();
+    let mut mem_basis_labels = Vec::new();
+    let mut customs: Vec<HashMap<String, f64>> = Vec::new();
+
+    for r in reports {
+        let aliases = r.meta.aliases();
+        let cpu_pids = selected_pids(&cmp.cpu_selections_by_id, r.id);
+        let mem_pids = selected_pids(&cmp.mem_selections_by_id, r.id);
+        let cpu_metrics = restrict_to_pids(&r.metrics, cpu_pids.as_deref());
+        let mem_metrics = restrict_to_pids(&r.metrics, mem_pids.as_deref());
+        let values: Vec<f64> = segments
+            .iter()
+            .flat_map(|segment| match segment {
+                Some(phase) => {
+                    summary_values(&phase_summary(&cpu_metrics, r, phase), &phase_summary(&mem_metrics, r, phase))
+                }
+                None => summary_values(&analyze(&cpu_metrics).summary, &analyze(&mem_metrics).summary),
+            })
+            .collect();
+        for (row, v) in rows.iter_mut().zip(values) {
+            row.1.push(Some(v));
+        }
+        cpu_pid_labels.push(pid_labels(cpu_pids.as_deref(), &aliases));
+        mem_pid_labels.push(pid_labels(mem_pids.as_deref(), &aliases));
+        let bases: Vec<&str> = memory_bases(&mem_metrics).into_iter().map(MemoryBasis::as_str).collect();
+        mem_basis_labels.push(if bases.is_empty() { "unknown".to_string() } else { bases.join("; ") });
+        customs.push(custom_metric_means(&mem_metrics));
+    }
+
+    // Only the comparison's selected metrics are listed.
+    let wanted = MetricSelection { metrics: cmp.metric_selections.clone(), ..Default::default() };
+    let mut rows: Vec<(String, Vec<Option<f64>>)> = rows
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| wanted.wants(SUMMARY_FAMILIES[i % SUMMARY_ROWS.len()]))
+        .map(|(_, row)| row)
+        .collect();
+
+    // Custom metrics present in every report.
+    let mut shared: BTreeSet<String> = customs.first().map(|c| c.keys().cloned().collect()).unwrap_or_default();
+    for c in customs.iter().skip(1) {
+        shared.retain(|k| c.contains_key(k));
+    }
+    shared.retain(|k| wanted.wants_custom(k));
+    for name in shared {
+        let values = customs.iter().map(|c| c.get(&name).copied()).collect();
+        rows.push((format!("custom:{}", name), values));
+    }
+
+    let baseline_idx = reports.iter().position(|r| Some(r.id) == baseline_id).unwrap_or(0);
+    let label = |r: &ReportDetail| format!("{} (#{})", r.title, r.id);
+
+    let mut out = String::from("metric");
+    for r in reports {
+        out.push(',');
+        let mut l = label(r);
+        if Some(r.id) == baseline_id {
+            l.push_str(" [baseline]");
+        }
+        out.push_str(&csv_field(&l));
+    }
+    for (i, r) in reports.iter().enumerate() {
+        if i != baseline_idx {
+            out.push(',');
+            out.push_str(&csv_field(&format!("delta_pct {}", label(r))));
+        }
+    }
+    out.push('\n');
+
+    let delta_blanks = reports.len().saturating_sub(1);
+    // Every number below is computed now, so all columns share the current analyzer.
+    out.push_str("analyzer_version");
+    out.push_str(&format!(",{}", ANALYZER_VERSION).repeat(reports.len()));
+    out.push_str(&",".repeat(delta_blanks));
+    out.push('\n');
+    // Memory rows only compare like with like when every report shares one mem_basis.
+    for (name, labels) in [("cpu_pids", &cpu_pid_labels), ("mem_pids", &mem_pid_labels), ("mem_basis", &mem_basis_labels)] {
+        if !wanted.wants(if name == "cpu_pids" { "cpu" } else { "memory" }) {
+            continue;
+        }
+        out.push_str(name);
+        for l in labels.iter() {
+            out.push(',');
+            out.push_str(&csv_field(l));
+        }
+        out.push_str(&",".repeat(delta_blanks));
+        out.push('\n');
+    }
+
+    for (name, values) in &rows {
+        out.push_str(&csv_field(name));
+        for v in values {
+            out.push(',');
+            if let Some(v) = v {
+                out.push_str(&fmt_value(*v));
+            }
+        }
+        let base = values.get(baseline_idx).copied().flatten();
+        for (i, v) in values.iter().enumerate() {
+            if i == baseline_idx {
+                continue;
+            }
+            out.push(',');
+            if let (Some(b), Some(v)) = (base, v) {
+                if b != 0.0 {
+                    out.push_str(&format!("{:.2}", (v - b) / b * 100.0));
+                }
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+// One resampled column of the overlay: its header and (seconds since run start, value) samples.
+struct OverlaySeries {
+    header: String,
+    samples: Vec<(f64, f64)>,
+}
+
+// Linear interpolation of time-sorted `samples` at `t`; None outside their range.
+fn interpolate(samples: &[(f64, f64)], t: f64) -> Option<f64> {
+    let (first, last) = (samples.first()?, samples.last()?);
+    if t < first.0 || t > last.0 {
+        return None;
+    }
+    let i = samples.partition_point(|(st, _)| *st < t);
+    let hi = samples[i];
+    if hi.0 == t || i == 0 {
+        return Some(hi.1);
+    }
+    let lo = samples[i - 1];
+    Some(lo.1 + (hi.1 - lo.1) * (t - lo.0) / (hi.0 - lo.0))
+}
+
+/// Most rows `render_comparison_overlay_csv` writes; a finer interval over a long run is refused.
+pub const MAX_OVERLAY_ROWS: usize = 1_000_000;
+
+/// Time-aligned overlay of a comparison's runs: one row per `interval_ms` step of seconds since
+/// each run's first sample, one column per selected series ("<report title>/<alias or pid>/<metric>",
+/// metrics "cpu", "memory_mb" and "custom:<name>"). Values are linearly interpolated between
+/// samples and blank outside a run's range, so runs of different lengths share one axis. CPU
+/// columns use the comparison's CPU PID selection, memory and custom columns the memory one;
+/// only its `metric_selections` are included.
+pub fn render_comparison_overlay_csv(cmp: &ComparisonDetail, reports: &[ReportDetail], interval_ms: u64) -> String {
+    let wanted = MetricSelection { metrics: cmp.metric_selections.clone(), ..Default::default() };
+    let mut series: Vec<OverlaySeries> = Vec::new();
+    for r in reports {
+        let Some(start) = r.metrics.first().map(|b| b.timestamp) else { continue };
+        // Same-titled reports get their id so the columns stay apart.
+        let title = if reports.iter().filter(|o| o.title == r.title).count() > 1 {
+            format!("{} (#{})", r.title, r.id)
+        } else {
+            r.title.clone()
+        };
+        let aliases = r.meta.aliases();
+        let label = |pid: u32| match aliases.get(&pid) {
+            Some(a) if aliases.values().filter(|o| *o == a).count() == 1 => a.clone(),
+            Some(a) => format!("{} ({})", a, pid),
+            None => pid.to_string(),
+        };
+        let all_pids: BTreeSet<u32> = r.metrics.iter().flat_map(|b| b.metrics.keys().copied()).collect();
+        let pick = |selections: &Value| match selected_pids(selections, r.id) {
+            Some(pids) => pids.into_iter().filter(|p| all_pids.contains(p)).collect::<BTreeSet<u32>>(),
+            None => all_pids.clone(),
+        };
+        let (cpu_pids, mem_pids) = (pick(&cmp.cpu_selections_by_id), pick(&cmp.mem_selections_by_id));
+        let custom_names: BTreeSet<String> = r
+            .metrics
+            .iter()
+            .flat_map(|b| b.metrics.values())
+            .filter_map(|p| p.custom_metrics.as_ref())
+            .flat_map(|m| m.keys().cloned())
+            .filter(|k| wanted.wants_custom(k))
+            .collect();
+
+        let column = |pid: u32, metric: &str, value: &dyn Fn(&crate::models::MetricPoint) -> Option<f64>| OverlaySeries {
+            header: format!("{}/{}/{}", title, label(pid), metric),
+            samples: r
+                .metrics
+                .iter()
+                .filter_map(|b| {
+                    let v = b.metrics.get(&pid).filter(|p| !p.is_custom_only() || metric.starts_with("custom:"))?;
+                    let t = (b.timestamp - start).num_milliseconds() as f64 / 1000.0;
+                    value(v).filter(|v| v.is_finite()).map(|v| (t, v))
+                })
+                .collect(),
+        };
+        for pid in &all_pids {
+            if wanted.wants("cpu") && cpu_pids.contains(pid) {
+                series.push(column(*pid, "cpu", &|p| Some(p.cpu_usage as f64)));
+            }
+            if wanted.wants("memory") && mem_pids.contains(pid) {
+                series.push(column(*pid, "memory_mb", &|p| {
+                    Some(p.memory_private.unwrap_or(p.memory_rss) as f64 / 1024.0 / 1024.0)
+                }));
+            }
+            if mem_pids.contains(pid) {
+                for name in &custom_names {
+                    let s = column(*pid, &format!("custom:{}", name), &|p| {
+                        p.custom_metrics.as_ref().and_then(|m| m.get(name)).copied()
+                    });
+                    if !s.samples.is_empty() {
+                        series.push(s);
+                    }
+                }
+            }
+        }
+    }
+    for s in &mut series {
+        // Batches are in time order already; keep the later of two samples at one instant.
+        s.samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+        s.samples.dedup_by(|later, earlier| {
+            let same = later.0 == earlier.0;
+            if same {
+                earlier.1 = later.1;
+            }
+            same
+        });
+    }
+
+    let step = interval_ms.max(1) as f64 / 1000.0;
+    let end = series.iter().filter_map(|s| s.samples.last()).map(|(t, _)| *t).fold(0.0, f64::max);
+    let rows = ((end / step).floor() as usize + 1).min(MAX_OVERLAY_ROWS);
+
+    let mut out = String::from("t_seconds");
+    for s in &series {
+        out.push(',');
+        out.push_str(&csv_field(&s.header));
+    }
+    out.push('\n');
+    for i in 0..rows {
+        let t = i as f64 * step;
+        out.push_str(&fmt_value(t));
+        for s in &series {
+            out.push(',');
+            if let Some(v) = interpolate(&s.samples, t) {
+                out.push_str(&fmt_value(v));
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone, Utc};
+    use serde_json::json;
+
+    // A sample of `pid` taken `ms` after the run started at `start_secs` (seconds since 2025-01-01).
+    fn sample(start_secs: i64, ms: i64, pid: u32, cpu: f64, mem_mb: f64, fps: Option<f64>) -> (chrono::DateTime<Utc>, crate::models::MetricPoint) {
+        let at = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap() + Duration::seconds(start_secs) + Duration::milliseconds(ms);
+        let point = serde_json::from_value(json!({
+            "timestamp": at,
+            "pid": pid,
+            "cpu_usage": cpu,
+            "cpu_os_usage": cpu,
+            "cpu_chrome_usage": null,
+            "memory_rss": (mem_mb * 1024.0 * 1024.0) as u64,
+            "memory_footprint": null,
+            "gpu_usage": null,
+            "js_heap_size": null,
+            "memory_private": null,
+            "custom_metrics": fps.map(|v| json!({ "fps": v })),
+        }))
+        .unwrap();
+        (at, point)
+    }
+
+    fn report(id: i64, title: &str, samples: Vec<(chrono::DateTime<Utc>, crate::models::MetricPoint)>) -> ReportDetail {
+        let mut metrics: Vec<BatchMetric> = Vec::new();
+        for (at, point) in samples {
+            match metrics.iter_mut().find(|b| b.timestamp == at) {
+                Some(b) => {
+                    b.metrics.insert(point.pid, point);
+                }
+                None => metrics.push(BatchMetric { timestamp: at, metrics: HashMap::from([(point.pid, point)]) }),
+            }
+        }
+        serde_json::from_value(json!({
+            "id": id,
+            "created_at": "2025-01-01T00:00:00Z",
+            "title": title,
+            "metrics": metrics,
+            "analysis": null,
+            "meta": { "process_aliases": [{ "pid": 1, "alias": "Browser" }] },
+        }))
+        .unwrap()
+    }
+
+    fn comparison(report_ids: &[i64], cpu: Value, mem: Value, metrics: &[&str]) -> ComparisonDetail {
+        serde_json::from_value(json!({
+            "id": 1,
+            "created_at": "2025-01-01T00:00:00Z",
+            "title": "Overlay",
+            "report_ids": report_ids,
+            "baseline_report_id": report_ids[0],
+            "cpu_selections_by_id": cpu,
+            "mem_selections_by_id": mem,
+            "metric_selections": metrics,
+        }))
+        .unwrap()
+    }
+
+    // Header and rows of a CSV, fields split on commas (the tests avoid quoted fields).
+    fn parse(csv: &str) -> (Vec<String>, Vec<Vec<String>>) {
+        let mut lines = csv.lines().map(|l| l.split(',').map(str::to_string).collect::<Vec<_>>());
+        let header = lines.next().unwrap();
+        (header, lines.collect())
+    }
+
+    fn column(csv: &str, name: &str) -> Vec<String> {
+        let (header, rows) = parse(csv);
+        let i = header.iter().position(|h| h == name).unwrap_or_else(|| panic!("no column {} in {:?}", name, header));
+        rows.iter().map(|r| r[i].clone()).collect()
+    }
+
+    #[test]
+    fn interpolation_is_linear_between_samples_and_blank_outside() {
+        let samples = [(0.0, 0.0), (2.0, 10.0), (3.0, 40.0)];
+        assert_eq!(interpolate(&samples, 0.0), Some(0.0));
+        assert_eq!(interpolate(&samples, 1.0), Some(5.0));
+        assert_eq!(interpolate(&samples, 2.0), Some(10.0));
+        assert_eq!(interpolate(&samples, 2.5), Some(25.0));
+        assert_eq!(interpolate(&samples, 3.0), Some(40.0));
+        assert_eq!(interpolate(&samples, -0.5), None);
+        assert_eq!(interpolate(&samples, 3.5), None);
+        assert_eq!(interpolate(&[(1.0, 7.0)], 1.0), Some(7.0));
+        assert_eq!(interpolate(&[(1.0, 7.0)], 0.0), None);
+        assert_eq!(interpolate(&[], 0.0), None);
+    }
+
+    // Runs recorded hours apart and of different lengths share one relative axis; the shorter
+    // one is blank after its last sample.
+    #[test]
+    fn runs_of_different_lengths_are_aligned_on_seconds_since_start() {
+        let long = report(1, "Long", (0..5).map(|i| sample(0, i * 1000, 1, i as f64 * 10.0, 100.0 + i as f64, None)).collect());
+        let short = report(2, "Short", vec![sample(7200, 0, 1, 0.0, 50.0, None), sample(7200, 1500, 1, 30.0, 80.0, None)]);
+        let cmp = comparison(&[1, 2], json!({}), json!({}), &["cpu", "memory"]);
+        let csv = render_comparison_overlay_csv(&cmp, &[long, short], 500);
+
+        let (header, rows) = parse(&csv);
+        assert_eq!(header, vec!["t_seconds", "Long/Browser/cpu", "Long/Browser/memory_mb", "Short/Browser/cpu", "Short/Browser/memory_mb"]);
+        assert_eq!(rows.len(), 9, "0 to 4 s every 0.5 s");
+        assert_eq!(column(&csv, "t_seconds"), vec!["0.000", "0.500", "1.000", "1.500", "2.000", "2.500", "3.000", "3.500", "4.000"]);
+        assert_eq!(
+            column(&csv, "Long/Browser/cpu"),
+            vec!["0.000", "5.000", "10.000", "15.000", "20.000", "25.000", "30.000", "35.000", "40.000"]
+        );
+        assert_eq!(column(&csv, "Short/Browser/cpu"), vec!["0.000", "10.000", "20.000", "30.000", "", "", "", "", ""]);
+        assert_eq!(column(&csv, "Short/Browser/memory_mb"), vec!["50.000", "60.000", "70.000", "80.000", "", "", "", "", ""]);
+    }
+
+    // Samples off the grid are interpolated onto it, not snapped to the nearest one.
+    #[test]
+    fn off_grid_samples_are_interpolated_onto_the_grid() {
+        let a = report(1, "A", vec![sample(0, 0, 1, 0.0, 10.0, None), sample(0, 700, 1, 7.0, 10.0, None), sample(0, 2300, 1, 23.0, 10.0, None)]);
+        let b = report(2, "B", vec![sample(0, 0, 1, 1.0, 10.0, None), sample(0, 2000, 1, 1.0, 10.0, None)]);
+        let cmp = comparison(&[1, 2], json!({}), json!({}), &["cpu"]);
+        let csv = render_comparison_overlay_csv(&cmp, &[a, b], 1000);
+        assert_eq!(column(&csv, "A/Browser/cpu"), vec!["0.000", "10.000", "20.000"]);
+        assert_eq!(column(&csv, "B/Browser/cpu"), vec!["1.000", "1.000", "1.000"]);
+        assert_eq!(parse(&csv).0.len(), 3, "memory not selected");
+    }
+
+    #[test]
+    fn selections_aliases_and_same_titles_pick_and_name_the_columns() {
+        let samples = |start| {
+            (0..3)
+                .flat_map(|i| [sample(start, i * 1000, 1, 5.0, 100.0, Some(60.0)), sample(start, i * 1000, 2, 9.0, 200.0, None)])
+                .collect()
+        };
+        let (a, b) = (report(1, "Run", samples(0)), report(2, "Run", samples(600)));
+        // Report 1: CPU of PID 2 only, memory of both; report 2: everything.
+        let cmp = comparison(&[1, 2], json!({ "1": [2] }), json!({}), &["cpu", "memory", "custom:fps"]);
+        let csv = render_comparison_overlay_csv(&cmp, &[a, b], 1000);
+        assert_eq!(
+            parse(&csv).0,
+            vec![
+                "t_seconds",
+                "Run (#1)/Browser/memory_mb",
+                "Run (#1)/Browser/custom:fps",
+                "Run (#1)/2/cpu",
+                "Run (#1)/2/memory_mb",
+                "Run (#2)/Browser/cpu",
+                "Run (#2)/Browser/memory_mb",
+                "Run (#2)/Browser/custom:fps",
+                "Run (#2)/2/cpu",
+                "Run (#2)/2/memory_mb",
+            ]
+        );
+        assert_eq!(column(&csv, "Run (#2)/Browser/custom:fps"), vec!["60.000"; 3]);
+    }
+
+    // A run spanning the fall-back hour: the repeated 02:xx wall times keep their offsets apart.
+    #[test]
+    fn samples_csv_renders_each_row_with_its_dst_offset() {
+        // Starts 2024-10-27T00:00:00Z, 02:00 CEST in Berlin.
+        let start_secs = (Utc.with_ymd_and_hms(2024, 10, 27, 0, 0, 0).unwrap() - Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap()).num_seconds();
+        let r = report(1, "Overnight", (0..4).map(|i| sample(start_secs, i * 30 * 60 * 1000, 1, 1.0, 10.0, None)).collect());
+        let zone = crate::timezone::DisplayZone::parse(Some("Europe/Berlin")).unwrap();
+        assert_eq!(
+            column(&render_samples_csv(&r, zone), "timestamp"),
+            vec!["2024-10-27T02:00:00+02:00", "2024-10-27T02:30:00+02:00", "2024-10-27T02:00:00+01:00", "2024-10-27T02:30:00+01:00"]
+        );
+        assert_eq!(
+            column(&render_samples_csv(&r, crate::timezone::DisplayZone::Utc), "timestamp"),
+            vec!["2024-10-27T00:00:00+00:00", "2024-10-27T00:30:00+00:00", "2024-10-27T01:00:00+00:00", "2024-10-27T01:30:00+00:00"]
+        );
+    }
+
+    // Custom metrics are averaged over the memory selection, like the overlay's custom columns.
+    #[test]
+    fn comparison_custom_metrics_follow_the_memory_selection() {
+        let samples = |start| {
+            (0..3)
+                .flat_map(|i| [sample(start, i * 1000, 1, 5.0, 100.0, Some(60.0)), sample(start, i * 1000, 2, 9.0, 200.0, Some(20.0))])
+                .collect::<Vec<_>>()
+        };
+        let custom_row = |csv: &str| {
+            let (_, rows) = parse(csv);
+            let row = rows.into_iter().find(|r| r[0] == "custom:fps").expect("custom:fps row");
+            row[1..3].iter().map(|v| v.parse::<f64>().unwrap()).collect::<Vec<_>>()
+        };
+        let reports = [report(1, "A", samples(0)), report(2, "B", samples(600))];
+
+        let all = comparison(&[1, 2], json!({}), json!({}), &["cpu", "memory", "custom:fps"]);
+        assert_eq!(custom_row(&render_comparison_csv(&all, &reports)), vec![40.0, 40.0]);
+
+        // Report 1 keeps the browser, report 2 the other process; the CPU selection doesn't matter.
+        let picked = comparison(&[1, 2], json!({ "1": [2], "2": [1] }), json!({ "1": [1], "2": [2] }), &["cpu", "memory", "custom:fps"]);
+        assert_eq!(custom_row(&render_comparison_csv(&picked, &reports)), vec![60.0, 20.0]);
+    }
+}
@@ -0,0 +1,360 @@
+use std::collections::{BTreeMap, HashMap};
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use crate::models::{BatchMetric, MetricPoint, ReportMeta};
+
+/// Describes how columns of an external CSV map onto PerfSight metrics. Columns are referenced by header name.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CsvImportMapping {
+    pub timestamp_column: String,
+    /// "rfc3339" (default; also accepts "YYYY-MM-DD HH:MM:SS[.fff]" as UTC), "unix_s", "unix_ms",
+    /// or "elapsed_s" (seconds since `start_time`).
+    pub timestamp_format: Option<String>,
+    /// Base time for "elapsed_s" (RFC3339). Defaults to the import time.
+    pub start_time: Option<String>,
+    pub pid_column: Option<String>,
+    /// Used when there is no pid column (single-process tools like psrecord).
+    pub pid_constant: Option<u32>,
+    pub cpu_column: Option<String>,
+    pub memory_column: Option<String>,
+    /// "bytes" (default), "kb"/"kib", "mb"/"mib", "gb"/"gib". Decimal and binary prefixes are both treated as 1024-based.
+    pub memory_unit: Option<String>,
+    #[serde(default)]
+    pub custom_columns: Vec<CsvCustomColumn>,
+    /// Single-character delimiter (default ',').
+    pub delimiter: Option<String>,
+    pub title: Option<String>,
+    pub folder_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CsvCustomColumn {
+    pub column: String,
+    /// Metric name in PerfSight (defaults to the column name).
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CsvImportResult {
+    pub report_id: i64,
+    pub imported_rows: usize,
+    pub skipped_rows: usize,
+}
+
+pub struct ParsedCsv {
+    pub metrics: Vec<BatchMetric>,
+    pub imported_rows: usize,
+    pub skipped_rows: usize,
+}
+
+fn memory_multiplier(unit: Option<&str>) -> Result<f64, String> {
+    match unit.map(|u| u.trim().to_lowercase()).as_deref() {
+        None | Some("") | Some("b") | Some("bytes") => Ok(1.0),
+        Some("kb") | Some("kib") => Ok(1024.0),
+        Some("mb") | Some("mib") => Ok(1024.0 * 1024.0),
+        Some("gb") | Some("gib") => Ok(1024.0 * 1024.0 * 1024.0),
+        Some(other) => Err(format!("Unsupported memory_unit '{}'", other)),
+    }
+}
+
+// Whole milliseconds of `ms`, None when it is not finite or doesn't fit an i64 (a plain `as`
+// cast would saturate instead).
+fn whole_millis(ms: f64) -> Option<i64> {
+    let ms = ms.round();
+    (ms.is_finite() && ms >= i64::MIN as f64 && ms < i64::MAX as f64).then_some(ms as i64)
+}
+
+// None (the row is skipped) for unparseable values and ones outside chrono's range.
+fn parse_timestamp(raw: &str, format: &str, base: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let raw = raw.trim();
+    match format {
+        "unix_s" => Utc.timestamp_millis_opt(whole_millis(raw.parse::<f64>().ok()? * 1000.0)?).single(),
+        "unix_ms" => Utc.timestamp_millis_opt(whole_millis(raw.parse::<f64>().ok()?)?).single(),
+        "elapsed_s" => {
+            let ms = whole_millis(raw.parse::<f64>().ok()? * 1000.0)?;
+            base.checked_add_signed(chrono::Duration::try_milliseconds(ms)?)
+        }
+        _ => DateTime::parse_from_rfc3339(raw)
+            .map(|d| d.with_timezone(&Utc))
+            .ok()
+            .or_else(|| {
+                NaiveDateTime::parse_from_str(raw, "%Y-%m-%d %H:%M:%S%.f")
+                    .ok()
+                    .map(|n| Utc.from_utc_datetime(&n))
+            }),
+    }
+}
+
+fn column_index(headers: &csv::StringRecord, name: &str) -> Result<usize, String> {
+    headers
+        .iter()
+        .position(|h| h.trim() == name.trim())
+        .ok_or_else(|| format!("Column '{}' not found in CSV header", name))
+}
+
+fn parse_number(record: &csv::StringRecord, idx: usize) -> Option<f64> {
+    record.get(idx)?.trim().parse::<f64>().ok().filter(|v| v.is_finite())
+}
+
+/// Parse CSV text into batches (rows sharing a timestamp are merged). Rows with an unparseable
+/// timestamp/pid, without any mapped value, or repeating an earlier row's timestamp and pid
+/// (the first one wins) are skipped and counted.
+pub fn parse_csv(content: &str, mapping: &CsvImportMapping) -> Result<ParsedCsv, String> {
+    let delimiter = match mapping.delimiter.as_deref() {
+        None | Some("") => b',',
+        Some("\\t") | Some("tab") => b'\t',
+        Some(d) if d.len() == 1 => d.as_bytes()[0],
+        Some(d) => return Err(format!("Invalid delimiter '{}'", d)),
+    };
+    let mem_mult = memory_multiplier(mapping.memory_unit.as_deref())?;
+    let ts_format = mapping.timestamp_format.as_deref().unwrap_or("rfc3339").to_lowercase();
+    let base = match mapping.start_time.as_deref() {
+        Some(s) => DateTime::parse_from_rfc3339(s.trim())
+            .map(|d| d.with_timezone(&Utc))
+            .map_err(|e| format!("Invalid start_time: {e}"))?,
+        None => Utc::now(),
+    };
+    if mapping.pid_column.is_none() && mapping.pid_constant.is_none() {
+        return Err("Mapping needs pid_column or pid_constant".to_string());
+    }
+    if mapping.cpu_column.is_none() && mapping.memory_column.is_none() && mapping.custom_columns.is_empty() {
+        return Err("Mapping needs at least one of cpu_column, memory_column, custom_columns".to_string());
+    }
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(content.as_bytes());
+    let headers = reader.headers().map_err(|e| format!("Failed to read CSV header: {e}"))?.clone();
+
+    let ts_idx = column_index(&headers, &mapping.timestamp_column)?;
+    let pid_idx = mapping.pid_column.as_deref().map(|c| column_index(&headers, c)).transpose()?;
+    let cpu_idx = mapping.cpu_column.as_deref().map(|c| column_index(&headers, c)).transpose()?;
+    let mem_idx = mapping.memory_column.as_deref().map(|c| column_index(&headers, c)).transpose()?;
+    let custom_idx = mapping
+        .custom_columns
+        .iter()
+        .map(|c| {
+            let name = c.name.clone().filter(|n| !n.trim().is_empty()).unwrap_or_else(|| c.column.clone());
+            column_index(&headers, &c.column).map(|i| (i, name))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut batches: BTreeMap<DateTime<Utc>, HashMap<u32, MetricPoint>> = BTreeMap::new();
+    let mut imported_rows = 0;
+    let mut skipped_rows = 0;
+
+    for record in reader.records() {
+        let Ok(record) = record else {
+            skipped_rows += 1;
+            continue;
+        };
+        let Some(timestamp) = record.get(ts_idx).and_then(|raw| parse_timestamp(raw, &ts_format, base)) else {
+            skipped_rows += 1;
+            continue;
+        };
+        let pid = match pid_idx {
+            Some(i) => record.get(i).and_then(|s| s.trim().parse::<u32>().ok()),
+            None => mapping.pid_constant,
+        };
+        let Some(pid) = pid else {
+            skipped_rows += 1;
+            continue;
+        };
+
+        let cpu = cpu_idx.and_then(|i| parse_number(&record, i));
+        let mem = mem_idx.and_then(|i| parse_number(&record, i)).map(|v| (v * mem_mult).max(0.0) as u64);
+        let mut custom = HashMap::new();
+        for (i, name) in &custom_idx {
+            if let Some(v) = parse_number(&record, *i) {
+                custom.insert(name.clone(), v);
+            }
+        }
+        if cpu.is_none() && mem.is_none() && custom.is_empty() {
+            skipped_rows += 1;
+            continue;
+        }
+
+        let batch = batches.entry(timestamp).or_default();
+        if batch.contains_key(&pid) {
+            skipped_rows += 1;
+            continue;
+        }
+        let cpu = cpu.unwrap_or(0.0) as f32;
+        batch.insert(pid, MetricPoint {
+            timestamp,
+            pid,
+            cpu_usage: cpu,
+            cpu_os_usage: cpu,
+            cpu_os_usage_raw: None,
+            cpu_chrome_usage: None,
+            memory_rss: mem.unwrap_or(0),
+            memory_footprint: None,
+            gpu_usage: None,
+            js_heap_size: None,
+            js_heap_total_size: None,
+            memory_private: None,
+            gpu_memory_bytes: None,
+            max_thread_cpu: None,
+            custom_metrics: if custom.is_empty() { None } else { Some(custom) },
+            memory_basis: None,
+        });
+        imported_rows += 1;
+    }
+
+    let metrics = batches
+        .into_iter()
+        .map(|(timestamp, metrics)| BatchMetric { timestamp, metrics })
+        .collect();
+    Ok(ParsedCsv { metrics, imported_rows, skipped_rows })
+}
+
+/// Minimal meta for an imported run so it shows up alongside collected reports.
+pub fn build_meta(
+    source_file: &str,
+    mapping: &CsvImportMapping,
+    metrics: &[BatchMetric],
+    skipped_rows: usize,
+) -> ReportMeta {
+    imported_meta(
+        mapping.folder_path.as_deref(),
+        metrics,
+        json!({
+            "source": "csv",
+            "source_file": source_file,
+            "declared_units": {
+                "timestamp": mapping.timestamp_format.clone().unwrap_or_else(|| "rfc3339".to_string()),
+                "memory": mapping.memory_unit.clone().unwrap_or_else(|| "bytes".to_string())
+            },
+            "mapping": mapping,
+            "skipped_rows": skipped_rows,
+            "imported_at": Utc::now().to_rfc3339()
+        }),
+    )
+}
+
+/// Parse a stream file written during a run (`CollectionConfig::stream_to_file`): one
+/// `BatchMetric` per line. Unparseable lines, such as one cut short by a crash, are skipped.
+pub fn parse_jsonl(content: &str) -> ParsedCsv {
+    let mut batches: BTreeMap<DateTime<Utc>, HashMap<u32, MetricPoint>> = BTreeMap::new();
+    let mut imported_rows = 0;
+    let mut skipped_rows = 0;
+    for line in content.lines().filter(|l| !l.trim().is_empty()) {
+        match serde_json::from_str::<BatchMetric>(line) {
+            Ok(batch) => {
+                imported_rows += 1;
+                batches.entry(batch.timestamp).or_default().extend(batch.metrics);
+            }
+            Err(_) => skipped_rows += 1,
+        }
+    }
+    let metrics = batches
+        .into_iter()
+        .map(|(timestamp, metrics)| BatchMetric { timestamp, metrics })
+        .collect();
+    ParsedCsv { metrics, imported_rows, skipped_rows }
+}
+
+/// Meta for a report imported from a stream file.
+pub fn build_jsonl_meta(
+    source_file: &str,
+    folder_path: Option<&str>,
+    metrics: &[BatchMetric],
+    skipped_rows: usize,
+) -> ReportMeta {
+    imported_meta(
+        folder_path,
+        metrics,
+        json!({
+            "source": "jsonl",
+            "source_file": source_file,
+            "skipped_rows": skipped_rows,
+            "imported_at": Utc::now().to_rfc3339()
+        }),
+    )
+}
+
+fn imported_meta(folder_path: Option<&str>, metrics: &[BatchMetric], import: serde_json::Value) -> ReportMeta {
+    let started_at = metrics.first().map(|b| b.timestamp.to_rfc3339());
+    let ended_at = metrics.last().map(|b| b.timestamp.to_rfc3339());
+    let duration_seconds = match (metrics.first(), metrics.last()) {
+        (Some(a), Some(b)) => (b.timestamp - a.timestamp).num_seconds().max(0),
+        _ => 0,
+    };
+    let mut pids: Vec<u32> = metrics.iter().flat_map(|b| b.metrics.keys().copied()).collect();
+    pids.sort();
+    pids.dedup();
+    let folder_path = folder_path.map(|s| s.trim()).filter(|s| !s.is_empty());
+
+    ReportMeta::from_value(json!({
+        "schema_version": 1,
+        "definitions": {
+            "units": {
+                "cpu": "percent",
+                "memory": "bytes"
+            }
+        },
+        "collection": {
+            "mode": "imported",
+            "metric_standard": "os",
+            "target_pids": pids,
+            "folder_path": folder_path,
+            "started_at": started_at,
+            "ended_at": ended_at,
+            "duration_seconds": duration_seconds
+        },
+        "import": import,
+        "test_context": null,
+        "process_aliases": [],
+        "process_snapshot": []
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn huge_elapsed_seconds_are_skipped_not_panicking() {
+        let base = Utc.timestamp_millis_opt(1_700_000_000_000).unwrap();
+        assert_eq!(parse_timestamp("1.5", "elapsed_s", base), Some(base + chrono::Duration::milliseconds(1500)));
+        for raw in ["1e300", "-1e300", "9223372036854775", "inf", "NaN"] {
+            assert_eq!(parse_timestamp(raw, "elapsed_s", base), None, "{}", raw);
+            assert_eq!(parse_timestamp(raw, "unix_s", base), None, "{}", raw);
+            assert_eq!(parse_timestamp(raw, "unix_ms", base), None, "{}", raw);
+        }
+    }
+
+    #[test]
+    fn rows_with_overflowing_timestamps_are_counted_as_skipped() {
+        let mapping: CsvImportMapping = serde_json::from_value(serde_json::json!({
+            "timestamp_column": "t",
+            "timestamp_format": "elapsed_s",
+            "start_time": "2024-01-01T00:00:00Z",
+            "pid_constant": 1,
+            "cpu_column": "cpu",
+        }))
+        .unwrap();
+        let parsed = parse_csv("t,cpu\n0,1\n1e300,2\n2,3\n", &mapping).unwrap();
+        assert_eq!(parsed.imported_rows, 2);
+        assert_eq!(parsed.skipped_rows, 1);
+    }
+
+    #[test]
+    fn duplicate_timestamp_and_pid_rows_keep_the_first_and_count_as_skipped() {
+        let mapping: CsvImportMapping = serde_json::from_value(serde_json::json!({
+            "timestamp_column": "t",
+            "timestamp_format": "elapsed_s",
+            "start_time": "2024-01-01T00:00:00Z",
+            "pid_column": "pid",
+            "cpu_column": "cpu",
+        }))
+        .unwrap();
+        let parsed = parse_csv("t,pid,cpu\n0,1,10\n0,2,20\n0,1,99\n1,1,30\n", &mapping).unwrap();
+        assert_eq!(parsed.imported_rows, 3);
+        assert_eq!(parsed.skipped_rows, 1);
+        assert_eq!(parsed.metrics[0].metrics[&1].cpu_usage, 10.0);
+    }
+}
@@ -1,18 +1,69 @@
-use rusqlite::{params, Connection, Result};
+use rusqlite::{params, Connection, OpenFlags, Result};
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
 use serde::{Serialize, Deserialize};
-use crate::models::BatchMetric;
-use crate::analysis::{self, AnalysisReport};
+use crate::logging::log_warn;
+use crate::models::{lenient, BatchMetric, FolderCollectionSettings, ProcessAlias, ReportMeta};
+use crate::analysis::{self, AnalysisReport, BudgetResult, ReportSparkline};
+use crate::error::PerfSightError;
+use crate::migrations;
+use crate::parallel;
 use serde_json::Value;
 
+/// SHA-256 (hex) identifying a report's content. Samples are fed in a canonical order (PIDs and
+/// custom metric names sorted) so the hash doesn't depend on HashMap iteration order.
+fn content_hash(created_at: &str, title: &str, metrics: &[BatchMetric]) -> String {
+    use sha2::{Digest, Sha256};
+    let mut h = Sha256::new();
+    h.update(created_at.trim().as_bytes());
+    h.update([0]);
+    h.update(title.trim().as_bytes());
+    h.update([0]);
+    for batch in metrics {
+        h.update(batch.timestamp.to_rfc3339().as_bytes());
+        let mut pids: Vec<&u32> = batch.metrics.keys().collect();
+        pids.sort_unstable();
+        for pid in pids {
+            let m = &batch.metrics[pid];
+            h.update(pid.to_le_bytes());
+            h.update(m.cpu_usage.to_bits().to_le_bytes());
+            h.update(m.cpu_os_usage.to_bits().to_le_bytes());
+            h.update(m.cpu_chrome_usage.map(f32::to_bits).unwrap_or(u32::MAX).to_le_bytes());
+            h.update(m.memory_rss.to_le_bytes());
+            for v in [m.memory_footprint, m.js_heap_size, m.memory_private] {
+                h.update(v.unwrap_or(u64::MAX).to_le_bytes());
+            }
+            h.update(m.gpu_usage.map(f32::to_bits).unwrap_or(u32::MAX).to_le_bytes());
+            if let Some(custom) = &m.custom_metrics {
+                let mut names: Vec<&String> = custom.keys().collect();
+                names.sort();
+                for name in names {
+                    h.update(name.as_bytes());
+                    h.update(custom[name].to_bits().to_le_bytes());
+                }
+            }
+        }
+        h.update([0xff]);
+    }
+    h.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
 pub struct Database {
     conn: Mutex<Connection>,
+    // File the connection was opened on, for the separate read-only connections.
+    path: String,
+    // Set while a sparkline backfill runs (see `backfill_sparklines_once`).
+    sparkline_backfill: AtomicBool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FolderInfo {
     /// Folder path like "Release/Scenario". Root is "".
     pub path: String,
+    /// Collection defaults are set on this folder (see `set_folder_settings`).
+    #[serde(default)]
+    pub has_settings: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -23,6 +74,36 @@ pub struct FolderStats {
     pub child_folder_count: u64,
 }
 
+/// What a folder rename does when the destination already holds items or sub-folders. An
+/// existing but empty destination is simply taken over.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FolderConflict {
+    /// Refuse with `PerfSightError::FolderExists`.
+    #[default]
+    Fail,
+    /// Move everything in; each moved item records the merge in its meta (`folder_merges`).
+    Merge,
+}
+
+/// Reports covered by `reanalyze_reports`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ReanalyzeScope {
+    All,
+    /// The folder and its subfolders ("" = unfiled reports only).
+    Folder { path: String },
+    Ids { ids: Vec<i64> },
+}
+
+/// A report's recomputed analysis, from `Database::reanalysis`.
+pub struct Reanalysis {
+    pub id: i64,
+    pub score: u8,
+    analysis_json: String,
+    summary_json: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ComparisonFolderStats {
     /// Folder path like "Release/Scenario". Root is "".
@@ -31,6 +112,48 @@ pub struct ComparisonFolderStats {
     pub child_folder_count: u64,
 }
 
+/// Roll-up of every report under a folder (prefix semantics, like `FolderStats`).
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FolderAggregate {
+    pub path: String,
+    pub report_count: u64,
+    pub avg_score: Option<f64>,
+    pub min_score: Option<u8>,
+    pub max_score: Option<u8>,
+    pub avg_p95_cpu: Option<f64>,
+    pub avg_max_mem_mb: Option<f64>,
+    /// Analyzer versions behind the scores and stats above, ascending. More than one means the
+    /// folder mixes old and new analyses (see `reanalyze_reports`).
+    #[serde(default)]
+    pub analyzer_versions: Vec<u32>,
+    pub earliest_created_at: Option<String>,
+    pub latest_created_at: Option<String>,
+    pub top_tags: Vec<TagStat>,
+    /// Report pinned with `set_folder_baseline` for this folder.
+    #[serde(default)]
+    pub baseline_report_id: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ComparisonFolderAggregate {
+    pub path: String,
+    pub comparison_count: u64,
+    /// Sum of report references across the comparisons (a report can be counted more than once).
+    pub report_ref_count: u64,
+    pub earliest_created_at: Option<String>,
+    pub latest_created_at: Option<String>,
+    pub top_tags: Vec<TagStat>,
+}
+
+// Tag index tables and the column holding the owner id (reports, then comparisons).
+const TAG_TABLES: [(&str, &str); 2] = [("report_tags", "report_id"), ("comparison_tags", "comparison_id")];
+
+// One tag index row: owner id, tag_lower, tag.
+type TagRow = (i64, String, String);
+
+// Number of tags returned by the folder aggregates.
+const AGGREGATE_TOP_TAGS: usize = 10;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ReportSummary {
     pub id: i64,
@@ -41,6 +164,137 @@ pub struct ReportSummary {
     pub folder_path: String,
     #[serde(default)]
     pub tags: Vec<String>,
+    /// Down-sampled CPU/memory totals + score (from `summary_json`).
+    #[serde(default)]
+    pub sparkline: Option<ReportSparkline>,
+    /// Read-only baseline: edits, moves and deletes need `force`.
+    #[serde(default)]
+    pub locked: bool,
+    /// Stored size of the samples and meta (see `ReportStorage`).
+    #[serde(default)]
+    pub size_bytes: u64,
+}
+
+/// Storage accounting of one report (`reports.storage_json`). Rows saved before it existed only
+/// carry the byte sizes, backfilled in SQL.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReportStorage {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub samples: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pids: Option<u64>,
+    pub metrics_bytes: u64,
+    pub meta_bytes: u64,
+    /// Stored size of the samples once they are compressed; they aren't yet.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compressed_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_seconds: Option<u64>,
+}
+
+/// Reports grouped by folder or by month, with their stored size.
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageBucket {
+    /// Folder path ("" = root) or "YYYY-MM".
+    pub key: String,
+    pub reports: u64,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportStorageEntry {
+    pub id: i64,
+    pub created_at: String,
+    pub title: String,
+    pub folder_path: String,
+    pub size_bytes: u64,
+    pub storage: Option<ReportStorage>,
+}
+
+/// Where the database's space goes (`Database::storage_breakdown`).
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageBreakdown {
+    pub stats: DatabaseStats,
+    /// Largest first; a folder's own reports only, not its sub-folders.
+    pub by_folder: Vec<StorageBucket>,
+    /// Oldest month first, by `created_at`.
+    pub by_month: Vec<StorageBucket>,
+    /// The biggest reports, largest first.
+    pub largest: Vec<ReportStorageEntry>,
+}
+
+/// Overall database size (`Database::database_stats`).
+#[derive(Debug, Clone, Serialize)]
+pub struct DatabaseStats {
+    /// File size (page_count * page_size).
+    pub db_bytes: u64,
+    /// Free pages a VACUUM would return.
+    pub free_bytes: u64,
+    pub reports: u64,
+    pub comparisons: u64,
+    /// Samples and meta of all reports.
+    pub reports_bytes: u64,
+    /// Samples of running or unsaved runs (`run_chunks`).
+    pub run_chunks_bytes: u64,
+}
+
+/// Answer of `Database::readonly_query`.
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    /// One JSON value per column; blobs are given as `{"blob_bytes": n}`.
+    pub rows: Vec<Vec<Value>>,
+    /// More rows matched than the limit.
+    pub truncated: bool,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionPreset {
+    pub name: String,
+    /// CollectionConfig fields except `target_pids`.
+    pub config: Value,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Auto-foldering rule applied when a run is saved without a folder (see `folder_rules`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FolderRule {
+    /// 0 when creating a new rule.
+    #[serde(default)]
+    pub id: i64,
+    /// Evaluation order (ascending); first match wins.
+    #[serde(default)]
+    pub position: i64,
+    /// Tag the report must carry (case-insensitive).
+    #[serde(default)]
+    pub match_tag: Option<String>,
+    /// Regex the scenario name must match.
+    #[serde(default)]
+    pub match_scenario: Option<String>,
+    /// Target folder, e.g. "CI/Nightly/{build_id}".
+    pub folder_template: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+/// Outcome of `Database::maintain`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceReport {
+    pub ran_at: String,
+    /// `["ok"]`, or the problems listed by `PRAGMA integrity_check`.
+    pub integrity: Vec<String>,
+    pub size_before_bytes: u64,
+    pub size_after_bytes: u64,
+    /// One line per repair made.
+    pub fixes: Vec<String>,
+    /// False when the integrity check failed (nothing is modified then).
+    pub vacuumed: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -56,7 +310,55 @@ pub struct ReportDetail {
     pub title: String,
     pub metrics: Vec<BatchMetric>,
     pub analysis: Option<AnalysisReport>,
-    pub meta: Value,
+    #[serde(default, deserialize_with = "lenient")]
+    pub meta: ReportMeta,
+}
+
+/// A report as stored, for readers that parse the samples incrementally (see `for_each_batch`).
+pub struct StoredReport {
+    pub id: i64,
+    pub created_at: String,
+    pub title: String,
+    pub meta: ReportMeta,
+    pub metrics_json: String,
+    /// Cached analysis, when it has been computed since the report last changed.
+    pub analysis: Option<AnalysisReport>,
+}
+
+/// Visit the elements of a stored JSON array (e.g. `metrics_json`) one at a time instead of
+/// building the whole Vec. `f` returns false to stop early; the rest is then left unparsed.
+pub fn for_each_batch<T, F>(json: &str, f: F) -> serde_json::Result<()>
+where
+    T: serde::de::DeserializeOwned,
+    F: FnMut(T) -> bool,
+{
+    struct ArrayVisitor<T, F> {
+        f: F,
+        stopped: bool,
+        _element: std::marker::PhantomData<T>,
+    }
+    impl<'de, T: serde::de::DeserializeOwned, F: FnMut(T) -> bool> serde::de::Visitor<'de> for &mut ArrayVisitor<T, F> {
+        type Value = ();
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str("an array")
+        }
+        fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<(), A::Error> {
+            while let Some(item) = seq.next_element::<T>()? {
+                if !(self.f)(item) {
+                    self.stopped = true;
+                    // Ending with elements left would be reported as trailing data; bail out instead.
+                    return Err(serde::de::Error::custom("stopped"));
+                }
+            }
+            Ok(())
+        }
+    }
+    let mut visitor = ArrayVisitor { f, stopped: false, _element: std::marker::PhantomData };
+    let mut de = serde_json::Deserializer::from_str(json);
+    match serde::Deserializer::deserialize_seq(&mut de, &mut visitor) {
+        Err(_) if visitor.stopped => Ok(()),
+        result => result,
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -88,37 +390,38 @@ pub struct ComparisonDetail {
     /// Map<report_id, [pid...]>
     #[serde(default)]
     pub mem_selections_by_id: Value,
+    /// Metrics to diff ("cpu", "memory", "custom:<name>", ...). Comparisons saved before the
+    /// selection existed read as `DEFAULT_COMPARISON_METRICS`.
+    #[serde(default = "default_comparison_metrics")]
+    pub metric_selections: Vec<String>,
     #[serde(default)]
     pub meta: Value,
 }
 
+/// Metrics a comparison without a saved `metric_selections` diffs.
+pub const DEFAULT_COMPARISON_METRICS: &[&str] = &["cpu", "memory"];
+
+pub fn default_comparison_metrics() -> Vec<String> {
+    DEFAULT_COMPARISON_METRICS.iter().map(|m| m.to_string()).collect()
+}
+
 impl Database {
     fn normalize_folder_path(raw: &str) -> String {
-        let s = raw.trim();
-        if s.is_empty() {
-            return "".to_string();
-        }
-        s.split('/')
-            .map(|p| p.trim())
-            .filter(|p| !p.is_empty() && *p != ".")
-            .collect::<Vec<_>>()
-            .join("/")
+        crate::models::normalize_folder_path(raw)
     }
 
-    fn set_meta_folder_path(meta: &mut Value, folder_path: &str) {
-        let fp = folder_path.to_string();
-        if !meta.is_object() {
-            *meta = serde_json::json!({});
-        }
-        // meta.folder_path (compat)
-        meta["folder_path"] = Value::String(fp.clone());
-        // meta.collection.folder_path (canonical)
-        if meta.get("collection").is_none()
-            || !meta.get("collection").unwrap().is_object()
-        {
-            meta["collection"] = serde_json::json!({});
+    // LIKE pattern matching everything below folder `prefix`. `%`, `_` and `\` in folder names are
+    // escaped, so every query using it needs `ESCAPE '\'`.
+    fn like_children(prefix: &str) -> String {
+        let mut pattern = String::with_capacity(prefix.len() + 2);
+        for c in prefix.chars() {
+            if matches!(c, '%' | '_' | '\\') {
+                pattern.push('\\');
+            }
+            pattern.push(c);
         }
-        meta["collection"]["folder_path"] = Value::String(fp);
+        pattern.push_str("/%");
+        pattern
     }
 
     fn set_comparison_meta_folder_path(meta: &mut Value, folder_path: &str) {
@@ -132,14 +435,13 @@ impl Database {
 
     fn get_folder_stats_conn(conn: &Connection, path: &str) -> Result<FolderStats> {
         let p = Self::normalize_folder_path(path);
-        let like_prefix = if p.is_empty() { "".to_string() } else { format!("{}/", p) };
 
         let report_count: u64 = if p.is_empty() {
             conn.query_row("SELECT COUNT(1) FROM reports WHERE folder_path = ''", [], |row| row.get(0))?
         } else {
             conn.query_row(
-                "SELECT COUNT(1) FROM reports WHERE folder_path = ?1 OR folder_path LIKE ?2",
-                params![p, format!("{}%", like_prefix)],
+                "SELECT COUNT(1) FROM reports WHERE folder_path = ?1 OR folder_path LIKE ?2 ESCAPE '\\'",
+                params![p, Self::like_children(&p)],
                 |row| row.get(0),
             )?
         };
@@ -148,8 +450,8 @@ impl Database {
             conn.query_row("SELECT COUNT(1) FROM folders WHERE path != ''", [], |row| row.get(0))?
         } else {
             conn.query_row(
-                "SELECT COUNT(1) FROM folders WHERE path LIKE ?1 AND path != ?2",
-                params![format!("{}%", like_prefix), p],
+                "SELECT COUNT(1) FROM folders WHERE path LIKE ?1 ESCAPE '\\' AND path != ?2",
+                params![Self::like_children(&p), p],
                 |row| row.get(0),
             )?
         };
@@ -163,14 +465,13 @@ impl Database {
 
     fn get_comparison_folder_stats_conn(conn: &Connection, path: &str) -> Result<ComparisonFolderStats> {
         let p = Self::normalize_folder_path(path);
-        let like_prefix = if p.is_empty() { "".to_string() } else { format!("{}/", p) };
 
         let comparison_count: u64 = if p.is_empty() {
             conn.query_row("SELECT COUNT(1) FROM comparisons WHERE folder_path = ''", [], |row| row.get(0))?
         } else {
             conn.query_row(
-                "SELECT COUNT(1) FROM comparisons WHERE folder_path = ?1 OR folder_path LIKE ?2",
-                params![p, format!("{}%", like_prefix)],
+                "SELECT COUNT(1) FROM comparisons WHERE folder_path = ?1 OR folder_path LIKE ?2 ESCAPE '\\'",
+                params![p, Self::like_children(&p)],
                 |row| row.get(0),
             )?
         };
@@ -179,8 +480,8 @@ impl Database {
             conn.query_row("SELECT COUNT(1) FROM comparison_folders WHERE path != ''", [], |row| row.get(0))?
         } else {
             conn.query_row(
-                "SELECT COUNT(1) FROM comparison_folders WHERE path LIKE ?1 AND path != ?2",
-                params![format!("{}%", like_prefix), p],
+                "SELECT COUNT(1) FROM comparison_folders WHERE path LIKE ?1 ESCAPE '\\' AND path != ?2",
+                params![Self::like_children(&p), p],
                 |row| row.get(0),
             )?
         };
@@ -192,40 +493,8 @@ impl Database {
         })
     }
 
-    fn extract_folder_path_from_meta(meta: &Value) -> String {
-        // Canonical location: meta.collection.folder_path
-        // Back-compat / alternative: meta.folder_path
-        let raw = meta
-            .get("collection")
-            .and_then(|c| c.get("folder_path"))
-            .and_then(|v| v.as_str())
-            .or_else(|| meta.get("folder_path").and_then(|v| v.as_str()))
-            .unwrap_or("")
-            .trim()
-            .to_string();
-
-        // Normalize: trim slashes, collapse repeated slashes, avoid "." segments.
-        let parts = raw
-            .split('/')
-            .map(|p| p.trim())
-            .filter(|p| !p.is_empty() && *p != ".")
-            .collect::<Vec<_>>();
-        parts.join("/")
-    }
-
     fn extract_folder_path_from_comparison_meta(meta: &Value) -> String {
-        let raw = meta
-            .get("folder_path")
-            .and_then(|v| v.as_str())
-            .unwrap_or("")
-            .trim()
-            .to_string();
-        let parts = raw
-            .split('/')
-            .map(|p| p.trim())
-            .filter(|p| !p.is_empty() && *p != ".")
-            .collect::<Vec<_>>();
-        parts.join("/")
+        Self::normalize_folder_path(meta.get("folder_path").and_then(|v| v.as_str()).unwrap_or(""))
     }
 
     fn extract_tags_from_comparison_meta(meta: &Value) -> Vec<String> {
@@ -278,251 +547,602 @@ impl Database {
         out
     }
 
-    fn extract_tags_from_meta(meta: &Value) -> Vec<String> {
-        // We support multiple historical shapes for backward compatibility:
-        // - meta.test_context.tags (current)
-        // - meta.collection.test_context.tags (older UI checks this)
-        // - tags as an array OR as a comma-separated string
-        fn read_tags(v: &Value) -> Vec<String> {
-            let mut out: Vec<String> = Vec::new();
-            match v {
-                Value::Array(arr) => {
-                    for t in arr {
-                        if let Some(s) = t.as_str() {
-                            out.push(s.to_string());
-                        }
-                    }
-                }
-                Value::String(s) => {
-                    out.extend(
-                        s.split(',')
-                            .map(|x| x.trim())
-                            .filter(|x| !x.is_empty())
-                            .map(|x| x.to_string()),
-                    );
-                }
-                _ => {}
-            }
-            out
-        }
 
-        let mut raw: Vec<String> = Vec::new();
-        if let Some(v) = meta.get("test_context").and_then(|t| t.get("tags")) {
-            raw.extend(read_tags(v));
-        }
-        if let Some(v) = meta
-            .get("collection")
-            .and_then(|c| c.get("test_context"))
-            .and_then(|t| t.get("tags"))
-        {
-            raw.extend(read_tags(v));
-        }
+    /// Open (or create) the database at `path` and apply pending schema migrations. Fails for a
+    /// database written by a newer build (see `migrations::migrate`).
+    pub fn new(path: &str) -> Result<Self> {
+        let mut conn = Connection::open(path)?;
+        migrations::migrate(&mut conn)?;
 
-        // Normalize + dedupe (case-insensitive), keep first-seen casing.
-        let mut seen = std::collections::HashSet::<String>::new();
-        let mut out: Vec<String> = Vec::new();
-        for t in raw {
-            let trimmed = t.trim();
-            if trimmed.is_empty() {
-                continue;
-            }
-            let key = trimmed.to_lowercase();
-            if seen.insert(key) {
-                out.push(trimmed.to_string());
+        Ok(Self {
+            conn: Mutex::new(conn),
+            path: path.to_string(),
+            sparkline_backfill: AtomicBool::new(false),
+        })
+    }
+
+    pub fn get_setting(&self, key: &str) -> Result<Option<Value>> {
+        let conn = self.conn.lock().unwrap();
+        let raw: Option<String> = match conn.query_row(
+            "SELECT value_json FROM settings WHERE key = ?1",
+            params![key],
+            |row| row.get(0),
+        ) {
+            Ok(v) => Some(v),
+            Err(rusqlite::Error::QueryReturnedNoRows) => None,
+            Err(e) => return Err(e),
+        };
+        Ok(raw.and_then(|s| serde_json::from_str(&s).ok()))
+    }
+
+    pub fn set_setting(&self, key: &str, value: &Value) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        let value_json = serde_json::to_string(value).unwrap_or_else(|_| "null".to_string());
+        conn.execute(
+            "INSERT INTO settings (key, value_json) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value_json = excluded.value_json",
+            params![key, value_json],
+        )
+    }
+
+    /// Typed read; values that don't deserialize into `T` are treated as unset.
+    pub fn get_setting_as<T: serde::de::DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        Ok(self.get_setting(key)?.and_then(|v| serde_json::from_value(v).ok()))
+    }
+
+    pub fn set_setting_as<T: Serialize>(&self, key: &str, value: &T) -> Result<usize> {
+        let v = serde_json::to_value(value).unwrap_or(Value::Null);
+        self.set_setting(key, &v)
+    }
+
+    pub fn get_all_settings(&self) -> Result<Vec<(String, Value)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT key, value_json FROM settings ORDER BY key")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?;
+        let mut out = Vec::new();
+        for row in rows {
+            let (key, raw) = row?;
+            if let Ok(v) = serde_json::from_str(&raw) {
+                out.push((key, v));
             }
         }
-        out
+        Ok(out)
     }
 
-    pub fn new(path: &str) -> Result<Self> {
-        let conn = Connection::open(path)?;
-        
-        // Init tables
-        // Storing metrics as a huge JSON blob for simplicity in Phase 1
+    /// Insert or replace a preset by name (keeps the original created_at).
+    pub fn save_collection_preset(&self, name: &str, config: &Value) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        let now = chrono::Utc::now().to_rfc3339();
+        let config_json = serde_json::to_string(config).unwrap_or_else(|_| "{}".to_string());
         conn.execute(
-            "CREATE TABLE IF NOT EXISTS reports (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                created_at TEXT NOT NULL,
-                title TEXT NOT NULL,
-                folder_path TEXT NOT NULL DEFAULT '',
-                metrics_json TEXT NOT NULL,
-                meta_json TEXT NOT NULL DEFAULT '{}'
-            )",
-            [],
-        )?;
+            "INSERT INTO collection_presets (name, config_json, created_at, updated_at) VALUES (?1, ?2, ?3, ?3)
+             ON CONFLICT(name) DO UPDATE SET config_json = excluded.config_json, updated_at = excluded.updated_at",
+            params![name, config_json, now],
+        )
+    }
 
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS folders (
-                path TEXT PRIMARY KEY,
-                created_at TEXT NOT NULL
-            )",
-            [],
+    pub fn list_collection_presets(&self) -> Result<Vec<CollectionPreset>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT name, config_json, created_at, updated_at FROM collection_presets ORDER BY name COLLATE NOCASE",
         )?;
+        let rows = stmt.query_map([], |row| {
+            let config_str: String = row.get(1)?;
+            Ok(CollectionPreset {
+                name: row.get(0)?,
+                config: serde_json::from_str(&config_str).unwrap_or_else(|_| serde_json::json!({})),
+                created_at: row.get(2)?,
+                updated_at: row.get(3)?,
+            })
+        })?;
+        rows.collect()
+    }
 
-        // Comparisons (separate artifact from reports)
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS comparisons (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                created_at TEXT NOT NULL,
-                title TEXT NOT NULL,
-                folder_path TEXT NOT NULL DEFAULT '',
-                report_ids_json TEXT NOT NULL,
-                baseline_report_id INTEGER,
-                cpu_selections_json TEXT NOT NULL DEFAULT '{}',
-                mem_selections_json TEXT NOT NULL DEFAULT '{}',
-                meta_json TEXT NOT NULL DEFAULT '{}'
-            )",
-            [],
+    pub fn list_folder_rules(&self) -> Result<Vec<FolderRule>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, position, match_tag, match_scenario, folder_template, enabled FROM folder_rules ORDER BY position, id",
         )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(FolderRule {
+                id: row.get(0)?,
+                position: row.get(1)?,
+                match_tag: row.get(2)?,
+                match_scenario: row.get(3)?,
+                folder_template: row.get(4)?,
+                enabled: row.get::<_, i64>(5)? != 0,
+            })
+        })?;
+        rows.collect()
+    }
 
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS comparison_folders (
-                path TEXT PRIMARY KEY,
-                created_at TEXT NOT NULL
-            )",
-            [],
+    /// Insert (id 0, appended last) or update a rule. Returns the rule id.
+    pub fn save_folder_rule(&self, rule: &FolderRule) -> Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        let tag = rule.match_tag.as_deref().map(str::trim).filter(|s| !s.is_empty());
+        let scenario = rule.match_scenario.as_deref().map(str::trim).filter(|s| !s.is_empty());
+        let template = rule.folder_template.trim();
+        if rule.id == 0 {
+            let position: i64 =
+                conn.query_row("SELECT COALESCE(MAX(position), -1) + 1 FROM folder_rules", [], |row| row.get(0))?;
+            conn.execute(
+                "INSERT INTO folder_rules (position, match_tag, match_scenario, folder_template, enabled, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![position, tag, scenario, template, rule.enabled, chrono::Utc::now().to_rfc3339()],
+            )?;
+            return Ok(conn.last_insert_rowid());
+        }
+        let updated = conn.execute(
+            "UPDATE folder_rules SET match_tag = ?1, match_scenario = ?2, folder_template = ?3, enabled = ?4 WHERE id = ?5",
+            params![tag, scenario, template, rule.enabled, rule.id],
         )?;
+        if updated == 0 {
+            return Err(rusqlite::Error::QueryReturnedNoRows);
+        }
+        Ok(rule.id)
+    }
 
-        // Backward-compatible migration for existing DBs: add meta_json if missing.
-        {
-            let mut stmt = conn.prepare("PRAGMA table_info(reports)")?;
-            let mut rows = stmt.query([])?;
-            let mut has_meta = false;
-            let mut has_folder = false;
-            while let Some(row) = rows.next()? {
-                let name: String = row.get(1)?;
-                if name == "meta_json" {
-                    has_meta = true;
-                }
-                if name == "folder_path" {
-                    has_folder = true;
-                }
-            }
-            if !has_meta {
-                conn.execute(
-                    "ALTER TABLE reports ADD COLUMN meta_json TEXT NOT NULL DEFAULT '{}'",
-                    [],
-                )?;
-            }
-            if !has_folder {
-                conn.execute(
-                    "ALTER TABLE reports ADD COLUMN folder_path TEXT NOT NULL DEFAULT ''",
-                    [],
-                )?;
-            }
+    pub fn delete_folder_rule(&self, id: i64) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM folder_rules WHERE id = ?1", params![id])
+    }
+
+    /// Set evaluation order from a list of rule ids (rules not listed keep their relative order after them).
+    pub fn reorder_folder_rules(&self, ids: &[i64]) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let offset = ids.len() as i64;
+        tx.execute("UPDATE folder_rules SET position = position + ?1", params![offset])?;
+        for (i, id) in ids.iter().enumerate() {
+            tx.execute("UPDATE folder_rules SET position = ?1 WHERE id = ?2", params![i as i64, id])?;
         }
+        tx.commit()
+    }
 
-        // Backward-compatible migration for existing DBs: ensure comparisons columns exist.
-        {
-            let mut stmt = conn.prepare("PRAGMA table_info(comparisons)")?;
-            let mut rows = stmt.query([])?;
-            let mut cols: std::collections::HashSet<String> = std::collections::HashSet::new();
-            while let Some(row) = rows.next()? {
-                let name: String = row.get(1)?;
-                cols.insert(name);
-            }
-            if !cols.contains("folder_path") {
-                conn.execute(
-                    "ALTER TABLE comparisons ADD COLUMN folder_path TEXT NOT NULL DEFAULT ''",
-                    [],
-                )?;
-            }
-            if !cols.contains("report_ids_json") {
-                conn.execute(
-                    "ALTER TABLE comparisons ADD COLUMN report_ids_json TEXT NOT NULL DEFAULT '[]'",
-                    [],
-                )?;
-            }
-            if !cols.contains("baseline_report_id") {
-                conn.execute(
-                    "ALTER TABLE comparisons ADD COLUMN baseline_report_id INTEGER",
-                    [],
-                )?;
-            }
-            if !cols.contains("cpu_selections_json") {
-                conn.execute(
-                    "ALTER TABLE comparisons ADD COLUMN cpu_selections_json TEXT NOT NULL DEFAULT '{}'",
-                    [],
-                )?;
-            }
-            if !cols.contains("mem_selections_json") {
-                conn.execute(
-                    "ALTER TABLE comparisons ADD COLUMN mem_selections_json TEXT NOT NULL DEFAULT '{}'",
-                    [],
-                )?;
-            }
-            if !cols.contains("meta_json") {
-                conn.execute(
-                    "ALTER TABLE comparisons ADD COLUMN meta_json TEXT NOT NULL DEFAULT '{}'",
-                    [],
-                )?;
-            }
+    /// Create the folder row for `path` and each of its parents.
+    pub fn ensure_folder_path(&self, path: &str) -> Result<String> {
+        let mut parent = String::new();
+        for part in Self::normalize_folder_path(path).split('/').filter(|p| !p.is_empty()) {
+            parent = self.create_folder(&parent, part)?;
         }
+        Ok(parent)
+    }
 
-        Ok(Self {
-            conn: Mutex::new(conn),
-        })
+    pub fn get_collection_preset(&self, name: &str) -> Result<CollectionPreset> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT name, config_json, created_at, updated_at FROM collection_presets WHERE name = ?1",
+            params![name],
+            |row| {
+                let config_str: String = row.get(1)?;
+                Ok(CollectionPreset {
+                    name: row.get(0)?,
+                    config: serde_json::from_str(&config_str).unwrap_or_else(|_| serde_json::json!({})),
+                    created_at: row.get(2)?,
+                    updated_at: row.get(3)?,
+                })
+            },
+        )
     }
 
-    pub fn save_report(&self, title: &str, metrics: &Vec<BatchMetric>, meta: &Value) -> Result<i64> {
+    pub fn delete_collection_preset(&self, name: &str) -> Result<usize> {
         let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM collection_presets WHERE name = ?1", params![name])
+    }
+
+    pub fn save_report(&self, title: &str, metrics: &Vec<BatchMetric>, meta: &ReportMeta) -> Result<i64> {
         let metrics_json = serde_json::to_string(metrics).unwrap(); // TODO: Handle error better
-        let meta_json = serde_json::to_string(meta).unwrap_or_else(|_| "{}".to_string());
-        let folder_path = Self::extract_folder_path_from_meta(meta);
+        self.save_report_json(title, metrics, &metrics_json, meta)
+    }
+
+    /// `save_report` with `metrics` already serialized (see `append_run_chunk`).
+    pub fn save_report_json(&self, title: &str, metrics: &[BatchMetric], metrics_json: &str, meta: &ReportMeta) -> Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        let meta_json = meta.to_json_string();
+        let folder_path = meta.folder_path();
         let created_at = chrono::Utc::now().to_rfc3339();
+        let summary_json = Self::sparkline_json(metrics, meta);
+        let hash = content_hash(&created_at, &meta.display_title(title), metrics);
+        let (size_bytes, storage_json) = Self::storage_json(metrics, metrics_json, &meta_json, meta);
 
         conn.execute(
-            "INSERT INTO reports (created_at, title, folder_path, metrics_json, meta_json) VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![created_at, title, folder_path, metrics_json, meta_json],
+            "INSERT INTO reports (created_at, title, folder_path, metrics_json, meta_json, summary_json, content_hash, size_bytes, storage_json)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![created_at, title, folder_path, metrics_json, meta_json, summary_json, hash, size_bytes, storage_json],
         )?;
-
-        Ok(conn.last_insert_rowid())
+        let id = conn.last_insert_rowid();
+        Self::index_tags(&conn, TAG_TABLES[0], id, &meta.tags())?;
+        Ok(id)
     }
 
-    /// Import a report from an external dataset package (preserve created_at/title/metrics/meta).
-    pub fn import_report(&self, created_at: &str, title: &str, metrics: &Vec<BatchMetric>, meta: &Value) -> Result<i64> {
+    /// Write one chunk of a running session's samples (`batches_json` is a JSON array).
+    pub fn append_run_chunk(
+        &self,
+        session_id: &str,
+        seq: i64,
+        started_at: &str,
+        mode: &str,
+        title: &str,
+        batches_json: &str,
+    ) -> Result<usize> {
         let conn = self.conn.lock().unwrap();
-        let metrics_json = serde_json::to_string(metrics).unwrap();
-        let meta_json = serde_json::to_string(meta).unwrap_or_else(|_| "{}".to_string());
-        let folder_path = Self::extract_folder_path_from_meta(meta);
-
         conn.execute(
-            "INSERT INTO reports (created_at, title, folder_path, metrics_json, meta_json) VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![created_at, title, folder_path, metrics_json, meta_json],
-        )?;
+            "INSERT OR REPLACE INTO run_chunks (session_id, seq, started_at, mode, title, batches_json) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![session_id, seq, started_at, mode, title, batches_json],
+        )
+    }
 
-        Ok(conn.last_insert_rowid())
+    /// Chunks of `session_id` with `seq < before_seq`, in order.
+    pub fn run_chunks(&self, session_id: &str, before_seq: i64) -> Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt =
+            conn.prepare("SELECT batches_json FROM run_chunks WHERE session_id = ?1 AND seq < ?2 ORDER BY seq")?;
+        let rows = stmt.query_map(params![session_id, before_seq], |row| row.get(0))?;
+        rows.collect()
     }
 
-    pub fn get_all_reports(&self) -> Result<Vec<ReportSummary>> {
+    pub fn delete_run_chunks(&self, session_id: &str) -> Result<usize> {
         let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare("SELECT id, created_at, title, folder_path, meta_json FROM reports ORDER BY id DESC")?;
-        
-        let report_iter = stmt.query_map([], |row| {
-            let meta_str: String = row.get(4).unwrap_or_else(|_| "{}".to_string());
-            let meta: Value = serde_json::from_str(&meta_str).unwrap_or_else(|_| serde_json::json!({}));
-            let duration_seconds = meta
-                .get("collection")
-                .and_then(|c| c.get("duration_seconds"))
-                .and_then(|d| d.as_u64())
-                .unwrap_or(0);
-            let title_from_meta = meta
-                .get("test_context")
-                .and_then(|t| t.get("scenario_name"))
-                .and_then(|s| s.as_str())
-                .map(|s| s.trim().to_string())
-                .filter(|s| !s.is_empty());
-            let tags = Self::extract_tags_from_meta(&meta);
+        conn.execute("DELETE FROM run_chunks WHERE session_id = ?1", params![session_id])
+    }
+
+    /// Sessions with chunks left behind: (session_id, started_at, mode, title).
+    pub fn unsaved_runs(&self) -> Result<Vec<(String, String, String, String)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT session_id, MIN(started_at), MIN(mode), MIN(title) FROM run_chunks GROUP BY session_id ORDER BY MIN(started_at)",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?;
+        rows.collect()
+    }
+
+    /// Import a report from an external dataset package (preserve created_at/title/metrics/meta).
+    pub fn import_report(&self, created_at: &str, title: &str, metrics: &Vec<BatchMetric>, meta: &ReportMeta) -> Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        let metrics_json = serde_json::to_string(metrics).unwrap();
+        let meta_json = meta.to_json_string();
+        let folder_path = meta.folder_path();
+        let summary_json = Self::sparkline_json(metrics, meta);
+        let hash = content_hash(created_at, &meta.display_title(title), metrics);
+        let (size_bytes, storage_json) = Self::storage_json(metrics, &metrics_json, &meta_json, meta);
+
+        conn.execute(
+            "INSERT INTO reports (created_at, title, folder_path, metrics_json, meta_json, summary_json, content_hash, size_bytes, storage_json)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![created_at, title, folder_path, metrics_json, meta_json, summary_json, hash, size_bytes, storage_json],
+        )?;
+        let id = conn.last_insert_rowid();
+        Self::index_tags(&conn, TAG_TABLES[0], id, &meta.tags())?;
+        Ok(id)
+    }
+
+    // Sparkline with the score and headline stats of `analysis::analyze_report`, the analysis
+    // report details show.
+    fn sparkline_json(metrics: &[BatchMetric], meta: &ReportMeta) -> String {
+        Self::sparkline_json_from(metrics, &analysis::analyze_report(metrics, meta))
+    }
+
+    fn sparkline_json_from(metrics: &[BatchMetric], analysis: &AnalysisReport) -> String {
+        serde_json::to_string(&analysis::build_sparkline(metrics, analysis)).unwrap_or_else(|_| "null".to_string())
+    }
+
+    // Stored size and `ReportStorage` JSON of a report about to be written.
+    fn storage_json(metrics: &[BatchMetric], metrics_json: &str, meta_json: &str, meta: &ReportMeta) -> (i64, String) {
+        let pids: std::collections::HashSet<u32> = metrics.iter().flat_map(|b| b.metrics.keys().copied()).collect();
+        let storage = ReportStorage {
+            samples: Some(metrics.len() as u64),
+            pids: Some(pids.len() as u64),
+            metrics_bytes: metrics_json.len() as u64,
+            meta_bytes: meta_json.len() as u64,
+            compressed_bytes: None,
+            duration_seconds: meta.duration_seconds(),
+        };
+        let size = (storage.metrics_bytes + storage.meta_bytes) as i64;
+        (size, serde_json::to_string(&storage).unwrap_or_else(|_| "null".to_string()))
+    }
+
+    // Sizes for reports saved before storage accounting, measured in SQL so no sample is loaded.
+    fn backfill_storage(conn: &Connection) -> Result<usize> {
+        conn.execute(
+            "UPDATE reports SET
+                size_bytes = octet_length(metrics_json) + octet_length(meta_json),
+                storage_json = json_object('metrics_bytes', octet_length(metrics_json), 'meta_bytes', octet_length(meta_json))
+             WHERE size_bytes IS NULL OR storage_json IS NULL",
+            [],
+        )
+    }
+
+    fn sum_i64(conn: &Connection, sql: &str) -> Result<u64> {
+        Ok(conn.query_row(sql, [], |row| row.get::<_, Option<i64>>(0))?.unwrap_or(0).max(0) as u64)
+    }
+
+    fn database_stats_conn(conn: &Connection) -> Result<DatabaseStats> {
+        let page_size = Self::sum_i64(conn, "PRAGMA page_size")?;
+        Ok(DatabaseStats {
+            db_bytes: Self::db_size_bytes(conn)?,
+            free_bytes: Self::sum_i64(conn, "PRAGMA freelist_count")? * page_size,
+            reports: Self::sum_i64(conn, "SELECT COUNT(*) FROM reports")?,
+            comparisons: Self::sum_i64(conn, "SELECT COUNT(*) FROM comparisons")?,
+            reports_bytes: Self::sum_i64(conn, "SELECT SUM(size_bytes) FROM reports")?,
+            run_chunks_bytes: Self::sum_i64(conn, "SELECT SUM(octet_length(batches_json)) FROM run_chunks")?,
+        })
+    }
+
+    pub fn database_stats(&self) -> Result<DatabaseStats> {
+        let conn = self.conn.lock().unwrap();
+        Self::backfill_storage(&conn)?;
+        Self::database_stats_conn(&conn)
+    }
+
+    /// Run one read-only statement on its own `SQLITE_OPEN_READONLY` connection, so the shared
+    /// connection is never held. Returns at most `limit` rows; the statement is interrupted once
+    /// it runs longer than `timeout`.
+    pub fn readonly_query(
+        &self,
+        sql: &str,
+        limit: usize,
+        timeout: std::time::Duration,
+    ) -> std::result::Result<QueryResult, PerfSightError> {
+        let conn = Connection::open_with_flags(
+            &self.path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+        conn.busy_timeout(timeout)?;
+        let started = std::time::Instant::now();
+        let interrupt = conn.get_interrupt_handle();
+        let (done, finished) = std::sync::mpsc::channel::<()>();
+        let watchdog = std::thread::spawn(move || {
+            if finished.recv_timeout(timeout) == Err(std::sync::mpsc::RecvTimeoutError::Timeout) {
+                interrupt.interrupt();
+            }
+        });
+        let result = (|| -> std::result::Result<QueryResult, PerfSightError> {
+            let mut stmt = conn.prepare(sql)?;
+            if !stmt.readonly() {
+                return Err(PerfSightError::invalid_input("sql", "statement would modify the database"));
+            }
+            let columns: Vec<String> = stmt.column_names().into_iter().map(str::to_string).collect();
+            let mut rows = stmt.query([])?;
+            let mut out = Vec::new();
+            let mut truncated = false;
+            while let Some(row) = rows.next()? {
+                if out.len() == limit {
+                    truncated = true;
+                    break;
+                }
+                let values = (0..columns.len())
+                    .map(|i| {
+                        Ok(match row.get_ref(i)? {
+                            rusqlite::types::ValueRef::Null => Value::Null,
+                            rusqlite::types::ValueRef::Integer(v) => Value::from(v),
+                            rusqlite::types::ValueRef::Real(v) => Value::from(v),
+                            rusqlite::types::ValueRef::Text(t) => Value::from(String::from_utf8_lossy(t).into_owned()),
+                            rusqlite::types::ValueRef::Blob(b) => serde_json::json!({ "blob_bytes": b.len() }),
+                        })
+                    })
+                    .collect::<Result<Vec<Value>>>()?;
+                out.push(values);
+            }
+            Ok(QueryResult { columns, rows: out, truncated, elapsed_ms: started.elapsed().as_millis() as u64 })
+        })();
+        let _ = done.send(());
+        let _ = watchdog.join();
+        result.map_err(|e| match e {
+            PerfSightError::Database(msg) if started.elapsed() >= timeout => {
+                PerfSightError::invalid_input("sql", format!("exceeded the {} s time limit ({})", timeout.as_secs(), msg))
+            }
+            other => other,
+        })
+    }
+
+    /// Stored size per folder and per month, plus the `largest` biggest reports.
+    pub fn storage_breakdown(&self, largest: usize) -> Result<StorageBreakdown> {
+        let conn = self.conn.lock().unwrap();
+        Self::backfill_storage(&conn)?;
+        let buckets = |sql: &str| -> Result<Vec<StorageBucket>> {
+            let mut stmt = conn.prepare(sql)?;
+            let rows = stmt.query_map([], |row| {
+                Ok(StorageBucket {
+                    key: row.get(0)?,
+                    reports: row.get::<_, i64>(1)?.max(0) as u64,
+                    bytes: row.get::<_, Option<i64>>(2)?.unwrap_or(0).max(0) as u64,
+                })
+            })?;
+            rows.collect()
+        };
+        let by_folder = buckets(
+            "SELECT folder_path, COUNT(*), SUM(size_bytes) FROM reports GROUP BY folder_path ORDER BY SUM(size_bytes) DESC",
+        )?;
+        let by_month = buckets(
+            "SELECT substr(created_at, 1, 7), COUNT(*), SUM(size_bytes) FROM reports GROUP BY 1 ORDER BY 1",
+        )?;
+        let largest = {
+            let mut stmt = conn.prepare(
+                "SELECT id, created_at, title, folder_path, size_bytes, storage_json FROM reports
+                 ORDER BY size_bytes DESC LIMIT ?1",
+            )?;
+            let rows = stmt.query_map(params![largest as i64], |row| {
+                Ok(ReportStorageEntry {
+                    id: row.get(0)?,
+                    created_at: row.get(1)?,
+                    title: row.get(2)?,
+                    folder_path: row.get(3)?,
+                    size_bytes: row.get::<_, Option<i64>>(4)?.unwrap_or(0).max(0) as u64,
+                    storage: row.get::<_, Option<String>>(5)?.and_then(|s| serde_json::from_str(&s).ok()),
+                })
+            })?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+        Ok(StorageBreakdown { stats: Self::database_stats_conn(&conn)?, by_folder, by_month, largest })
+    }
+
+    /// Compute sparklines for reports saved before summary_json existed, unless another backfill is
+    /// already running (then None). Meant for a background thread; see `backfill_sparklines_parallel`.
+    pub fn backfill_sparklines_once(&self, workers: usize) -> Option<Result<usize>> {
+        if self.sparkline_backfill.swap(true, Ordering::SeqCst) {
+            return None;
+        }
+        let filled = self.backfill_sparklines_parallel(workers);
+        self.sparkline_backfill.store(false, Ordering::SeqCst);
+        Some(filled)
+    }
+
+    // Sparklines for reports saved before summary_json existed, or before it held the headline
+    // stats (which would read as 0), on up to `workers` threads: the samples are read and parsed
+    // outside the connection lock, which is only taken per row read and per update, so listings
+    // never wait for the whole backfill.
+    fn backfill_sparklines_parallel(&self, workers: usize) -> Result<usize> {
+        let missing: Vec<i64> = {
+            let conn = self.conn.lock().unwrap();
+            let mut stmt = conn.prepare(
+                "SELECT id FROM reports WHERE summary_json IS NULL OR CASE WHEN json_valid(summary_json) \
+                 THEN json_type(summary_json, '$.p95_cpu') IS NULL OR json_type(summary_json, '$.max_mem_mb') IS NULL \
+                 ELSE 1 END",
+            )?;
+            let ids = stmt.query_map([], |row| row.get(0))?;
+            ids.collect::<Result<_>>()?
+        };
+        let mut filled = 0;
+        let mut failure = None;
+        parallel::for_each_ordered(
+            &missing,
+            workers,
+            |id| -> Result<String> {
+                let (metrics_str, meta_str): (String, String) = self.conn.lock().unwrap().query_row(
+                    "SELECT metrics_json, meta_json FROM reports WHERE id = ?1",
+                    [id],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )?;
+                let metrics: Vec<BatchMetric> = serde_json::from_str(&metrics_str).unwrap_or_default();
+                Ok(Self::sparkline_json(&metrics, &ReportMeta::from_json_str(&meta_str)))
+            },
+            |i, summary| {
+                let stored = summary.and_then(|s| {
+                    self.conn.lock().unwrap().execute(
+                        "UPDATE reports SET summary_json = ?1 WHERE id = ?2",
+                        params![s, missing[i]],
+                    )
+                });
+                match stored {
+                    Ok(_) => {
+                        filled += 1;
+                        true
+                    }
+                    Err(e) => {
+                        failure = Some(e);
+                        false
+                    }
+                }
+            },
+        );
+        failure.map_or(Ok(filled), Err)
+    }
+
+    fn backfill_content_hashes(conn: &Connection) -> Result<()> {
+        let missing: Vec<(i64, String, String, String, String)> = {
+            let mut stmt = conn.prepare(
+                "SELECT id, created_at, title, metrics_json, meta_json FROM reports WHERE content_hash IS NULL",
+            )?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)))?;
+            rows.collect::<Result<_>>()?
+        };
+        for (id, created_at, title, metrics_str, meta_str) in missing {
+            let metrics: Vec<BatchMetric> = serde_json::from_str(&metrics_str).unwrap_or_default();
+            let title = ReportMeta::from_json_str(&meta_str).display_title(&title);
+            conn.execute(
+                "UPDATE reports SET content_hash = ?1 WHERE id = ?2",
+                params![content_hash(&created_at, &title, &metrics), id],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Id of an existing report with the same created_at, title and samples, if any.
+    pub fn find_duplicate_report(
+        &self,
+        created_at: &str,
+        title: &str,
+        metrics: &[BatchMetric],
+        meta: &ReportMeta,
+    ) -> Result<Option<i64>> {
+        let conn = self.conn.lock().unwrap();
+        Self::backfill_content_hashes(&conn)?;
+        let hash = content_hash(created_at, &meta.display_title(title), metrics);
+        match conn.query_row(
+            "SELECT id FROM reports WHERE content_hash = ?1 ORDER BY id LIMIT 1",
+            params![hash],
+            |row| row.get(0),
+        ) {
+            Ok(id) => Ok(Some(id)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Content hash per id (computed first where missing); unknown ids are left out.
+    pub fn report_content_hashes(&self, ids: &[i64]) -> Result<std::collections::HashMap<i64, String>> {
+        let conn = self.conn.lock().unwrap();
+        Self::backfill_content_hashes(&conn)?;
+        let mut stmt = conn.prepare("SELECT content_hash FROM reports WHERE id = ?1")?;
+        let mut out = std::collections::HashMap::new();
+        for id in ids {
+            match stmt.query_row(params![id], |row| row.get::<_, String>(0)) {
+                Ok(hash) => {
+                    out.insert(*id, hash);
+                }
+                Err(rusqlite::Error::QueryReturnedNoRows) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(out)
+    }
+
+    /// Newest report with this content hash (the just-imported copy when duplicates exist).
+    pub fn report_id_by_content_hash(&self, hash: &str) -> Result<Option<i64>> {
+        let conn = self.conn.lock().unwrap();
+        Self::backfill_content_hashes(&conn)?;
+        match conn.query_row(
+            "SELECT id FROM reports WHERE content_hash = ?1 ORDER BY id DESC LIMIT 1",
+            params![hash],
+            |row| row.get(0),
+        ) {
+            Ok(id) => Ok(Some(id)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Every report, newest first. Reports saved before summary_json existed come without a
+    /// sparkline until `backfill_sparklines_once` has run.
+    pub fn get_all_reports(&self) -> Result<Vec<ReportSummary>> {
+        let conn = self.conn.lock().unwrap();
+        if let Err(e) = Self::backfill_storage(&conn) {
+            log_warn!("Failed to backfill report sizes: {}", e);
+        }
+        let mut stmt = conn.prepare(
+            "SELECT id, created_at, title, folder_path, meta_json, summary_json, locked, size_bytes FROM reports ORDER BY id DESC",
+        )?;
+        
+        let report_iter = stmt.query_map([], |row| {
+            let meta_str: String = row.get(4).unwrap_or_else(|_| "{}".to_string());
+            let meta = ReportMeta::from_json_str(&meta_str);
+            let duration_seconds = meta.duration_seconds().unwrap_or(0);
+            let tags = meta.tags();
             let title_db: String = row.get(2)?;
             let folder_db: String = row.get(3).unwrap_or_else(|_| "".to_string());
-            let folder_from_meta = Self::extract_folder_path_from_meta(&meta);
+            let folder_from_meta = meta.folder_path();
             Ok(ReportSummary {
                 id: row.get(0)?,
                 created_at: row.get(1)?,
-                title: title_from_meta.unwrap_or(title_db),
+                title: meta.display_title(&title_db),
                 duration_seconds,
                 folder_path: if !folder_from_meta.is_empty() { folder_from_meta } else { folder_db },
                 tags,
+                sparkline: row
+                    .get::<_, Option<String>>(5)?
+                    .and_then(|s| serde_json::from_str(&s).ok()),
+                locked: row.get(6)?,
+                size_bytes: row.get::<_, Option<i64>>(7)?.unwrap_or(0).max(0) as u64,
             })
         })?;
 
@@ -533,6 +1153,26 @@ impl Database {
         Ok(reports)
     }
 
+    /// Ids of reports in `path` (oldest first). With `include_nested`, sub-folders count too; at the
+    /// root that means every report.
+    pub fn report_ids_in_folder(&self, path: &str, include_nested: bool) -> Result<Vec<i64>> {
+        let conn = self.conn.lock().unwrap();
+        let p = Self::normalize_folder_path(path);
+        let (sql, args): (&str, Vec<String>) = if include_nested && p.is_empty() {
+            ("SELECT id FROM reports ORDER BY id", vec![])
+        } else if include_nested {
+            (
+                "SELECT id FROM reports WHERE folder_path = ?1 OR folder_path LIKE ?2 ESCAPE '\\' ORDER BY id",
+                vec![p.clone(), Self::like_children(&p)],
+            )
+        } else {
+            ("SELECT id FROM reports WHERE folder_path = ?1 ORDER BY id", vec![p])
+        };
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(args.iter()), |row| row.get(0))?;
+        rows.collect()
+    }
+
     pub fn list_folder_paths(&self) -> Result<Vec<FolderInfo>> {
         let conn = self.conn.lock().unwrap();
         let mut out: std::collections::HashSet<String> = std::collections::HashSet::new();
@@ -564,9 +1204,16 @@ impl Database {
             }
         }
 
+        let with_settings: std::collections::HashSet<String> = {
+            let mut stmt = conn.prepare("SELECT path FROM folder_settings")?;
+            let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+            rows.collect::<Result<_>>()?
+        };
         let mut v = out.into_iter().collect::<Vec<_>>();
         v.sort();
-        Ok(v.into_iter().map(|path| FolderInfo { path }).collect())
+        Ok(v.into_iter()
+            .map(|path| FolderInfo { has_settings: with_settings.contains(&path), path })
+            .collect())
     }
 
     pub fn create_folder(&self, parent_path: &str, name: &str) -> Result<String> {
@@ -596,13 +1243,12 @@ impl Database {
         if from.is_empty() {
             return Ok((0, 0));
         }
-        let from_like = format!("{}/", from);
 
         // Collect report ids to update meta_json as well.
         let mut report_ids: Vec<i64> = Vec::new();
         {
-            let mut stmt = conn.prepare("SELECT id FROM reports WHERE folder_path = ?1 OR folder_path LIKE ?2")?;
-            let iter = stmt.query_map(params![from, format!("{}%", from_like)], |row| Ok(row.get::<_, i64>(0)?))?;
+            let mut stmt = conn.prepare("SELECT id FROM reports WHERE folder_path = ?1 OR folder_path LIKE ?2 ESCAPE '\\'")?;
+            let iter = stmt.query_map(params![from, Self::like_children(&from)], |row| Ok(row.get::<_, i64>(0)?))?;
             for r in iter { report_ids.push(r?); }
         }
 
@@ -627,9 +1273,9 @@ impl Database {
             } else {
                 format!("{}/{}", to, suffix)
             };
-            let mut meta: Value = serde_json::from_str(&meta_str).unwrap_or_else(|_| serde_json::json!({}));
-            Self::set_meta_folder_path(&mut meta, &new_fp);
-            let meta_json = serde_json::to_string(&meta).unwrap_or_else(|_| "{}".to_string());
+            let mut meta = ReportMeta::from_json_str(&meta_str);
+            meta.set_folder_path(&new_fp);
+            let meta_json = meta.to_json_string();
             conn.execute(
                 "UPDATE reports SET folder_path = ?1, meta_json = ?2 WHERE id = ?3",
                 params![new_fp, meta_json, id],
@@ -639,8 +1285,8 @@ impl Database {
         // Move folders under prefix (including the prefix itself if it exists)
         let mut folder_paths: Vec<String> = Vec::new();
         {
-            let mut stmt = conn.prepare("SELECT path FROM folders WHERE path = ?1 OR path LIKE ?2")?;
-            let iter = stmt.query_map(params![from, format!("{}%", from_like)], |row| Ok(row.get::<_, String>(0)?))?;
+            let mut stmt = conn.prepare("SELECT path FROM folders WHERE path = ?1 OR path LIKE ?2 ESCAPE '\\'")?;
+            let iter = stmt.query_map(params![from, Self::like_children(&from)], |row| Ok(row.get::<_, String>(0)?))?;
             for r in iter { folder_paths.push(Self::normalize_folder_path(&r?)); }
         }
         for p in &folder_paths {
@@ -669,33 +1315,276 @@ impl Database {
             )?;
         }
 
+        // Pins follow their folders; a pin already set at the destination wins.
+        let mut pinned: Vec<String> = Vec::new();
+        {
+            let mut stmt = conn.prepare("SELECT folder_path FROM folder_baselines WHERE folder_path = ?1 OR folder_path LIKE ?2 ESCAPE '\\'")?;
+            let iter = stmt.query_map(params![from, Self::like_children(&from)], |row| row.get::<_, String>(0))?;
+            for r in iter { pinned.push(r?); }
+        }
+        for p in &pinned {
+            let suffix = p.strip_prefix(&from).unwrap_or("").trim_start_matches('/');
+            let new_p = match (to.is_empty(), suffix.is_empty()) {
+                (true, _) => suffix.to_string(),
+                (false, true) => to.clone(),
+                (false, false) => format!("{}/{}", to, suffix),
+            };
+            conn.execute("UPDATE OR IGNORE folder_baselines SET folder_path = ?1 WHERE folder_path = ?2", params![new_p, p])?;
+            conn.execute("DELETE FROM folder_baselines WHERE folder_path = ?1 AND ?1 <> ?2", params![p, new_p])?;
+        }
+
+        // Folder defaults move the same way; settings already at the destination win.
+        let mut configured: Vec<String> = Vec::new();
+        {
+            let mut stmt = conn.prepare("SELECT path FROM folder_settings WHERE path = ?1 OR path LIKE ?2 ESCAPE '\\'")?;
+            let iter = stmt.query_map(params![from, Self::like_children(&from)], |row| row.get::<_, String>(0))?;
+            for r in iter { configured.push(r?); }
+        }
+        for p in &configured {
+            let suffix = p.strip_prefix(&from).unwrap_or("").trim_start_matches('/');
+            let new_p = match (to.is_empty(), suffix.is_empty()) {
+                (true, _) => suffix.to_string(),
+                (false, true) => to.clone(),
+                (false, false) => format!("{}/{}", to, suffix),
+            };
+            if new_p.is_empty() {
+                conn.execute("DELETE FROM folder_settings WHERE path = ?1", params![p])?;
+                continue;
+            }
+            conn.execute("UPDATE OR IGNORE folder_settings SET path = ?1 WHERE path = ?2", params![new_p, p])?;
+            conn.execute("DELETE FROM folder_settings WHERE path = ?1 AND ?1 <> ?2", params![p, new_p])?;
+        }
+
         Ok((report_ids.len(), folder_paths.len()))
     }
 
-    pub fn rename_folder(&self, path: &str, new_name: &str) -> Result<String> {
+    // Destination of renaming the folder `from` to the leaf `new_name` (same parent).
+    fn renamed_folder_path(from: &str, new_name: &str) -> Option<String> {
+        let parent = from.rsplit_once('/').map(|(a, _)| a.to_string()).unwrap_or_else(|| "".to_string());
+        let leaf = Self::normalize_folder_path(new_name);
+        if leaf.is_empty() {
+            return None;
+        }
+        Some(if parent.is_empty() { leaf } else { format!("{}/{}", parent, leaf) })
+    }
+
+    // Ids of the rows of `table` filed at `path` or below.
+    fn ids_under_tx(conn: &Connection, table: &str, path: &str) -> Result<Vec<i64>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id FROM {} WHERE folder_path = ?1 OR folder_path LIKE ?2 ESCAPE '\\'",
+            table
+        ))?;
+        let ids = stmt.query_map(params![path, Self::like_children(path)], |row| row.get(0))?;
+        ids.collect()
+    }
+
+    // A merge entry for the meta of an item moved from `from` into the existing folder `to`.
+    fn folder_merge_entry(from: &str, to: &str) -> Value {
+        serde_json::json!({ "from": from, "into": to, "merged_at": chrono::Utc::now().to_rfc3339() })
+    }
+
+    pub fn rename_folder(
+        &self,
+        path: &str,
+        new_name: &str,
+        on_conflict: FolderConflict,
+    ) -> std::result::Result<String, PerfSightError> {
         let mut conn = self.conn.lock().unwrap();
         let from = Self::normalize_folder_path(path);
         if from.is_empty() {
             return Ok(from);
         }
-        let parent = from.rsplit_once('/').map(|(a, _)| a.to_string()).unwrap_or_else(|| "".to_string());
-        let leaf = Self::normalize_folder_path(new_name);
-        if leaf.is_empty() {
-            return Ok(from);
+        let Some(to) = Self::renamed_folder_path(&from, new_name) else { return Ok(from) };
+        if to == from {
+            return Ok(to);
+        }
+        let existing = Self::get_folder_stats_conn(&conn, &to)?;
+        let merging = existing.report_count > 0 || existing.child_folder_count > 0;
+        if merging && on_conflict == FolderConflict::Fail {
+            return Err(PerfSightError::FolderExists {
+                path: to,
+                reports: existing.report_count,
+                folders: existing.child_folder_count,
+            });
         }
-        let to = if parent.is_empty() { leaf } else { format!("{}/{}", parent, leaf) };
 
         let tx = conn.transaction()?;
+        let moved = if merging { Self::ids_under_tx(&tx, "reports", &from)? } else { Vec::new() };
         let _ = Self::rename_folder_prefix_tx(&tx, &from, &to)?;
         tx.execute(
             "INSERT OR IGNORE INTO folders (path, created_at) VALUES (?1, ?2)",
             params![to, chrono::Utc::now().to_rfc3339()],
         )?;
+        let entry = Self::folder_merge_entry(&from, &to);
+        for id in moved {
+            let meta_str: String = tx.query_row("SELECT meta_json FROM reports WHERE id = ?1", params![id], |row| row.get(0))?;
+            let mut meta = ReportMeta::from_json_str(&meta_str);
+            let merges = meta.extra.entry("folder_merges").or_insert_with(|| Value::Array(Vec::new()));
+            if let Some(list) = merges.as_array_mut() {
+                list.push(entry.clone());
+            }
+            tx.execute("UPDATE reports SET meta_json = ?1 WHERE id = ?2", params![meta.to_json_string(), id])?;
+        }
         tx.commit()?;
         Ok(to)
     }
 
-    pub fn delete_folder(&self, path: &str, strategy: Option<&str>) -> Result<(usize, usize)> {
+    fn db_size_bytes(conn: &Connection) -> Result<u64> {
+        let pages: i64 = conn.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+        let page_size: i64 = conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+        Ok((pages.max(0) as u64) * (page_size.max(0) as u64))
+    }
+
+    // Comparisons pointing at deleted reports, folder rows nothing can select, and folder defaults
+    // left behind by folders that no longer exist.
+    fn prune_orphans_tx(conn: &Connection, fixes: &mut Vec<String>) -> Result<()> {
+        let report_ids: std::collections::HashSet<i64> = {
+            let mut stmt = conn.prepare("SELECT id FROM reports")?;
+            let ids = stmt.query_map([], |row| row.get::<_, i64>(0))?;
+            ids.collect::<Result<_>>()?
+        };
+
+        // id, title, report_ids, baseline, cpu selections, mem selections, meta
+        type ComparisonRow = (i64, String, String, Option<i64>, String, String, String);
+        let comparisons: Vec<ComparisonRow> = {
+            let mut stmt = conn.prepare(
+                "SELECT id, title, report_ids_json, baseline_report_id, cpu_selections_json, mem_selections_json, meta_json FROM comparisons",
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get::<_, String>(2).unwrap_or_else(|_| "[]".to_string()),
+                    row.get(3).ok().flatten(),
+                    row.get::<_, String>(4).unwrap_or_else(|_| "{}".to_string()),
+                    row.get::<_, String>(5).unwrap_or_else(|_| "{}".to_string()),
+                    row.get::<_, String>(6).unwrap_or_else(|_| "{}".to_string()),
+                ))
+            })?;
+            rows.collect::<Result<_>>()?
+        };
+        for (id, title, ids_json, baseline, cpu_json, mem_json, meta_json) in comparisons {
+            let ids: Vec<i64> = serde_json::from_str(&ids_json).unwrap_or_default();
+            let (kept, missing): (Vec<i64>, Vec<i64>) = ids.into_iter().partition(|i| report_ids.contains(i));
+            if kept.is_empty() {
+                conn.execute("DELETE FROM comparisons WHERE id = ?1", params![id])?;
+                Self::unindex_tags(conn, TAG_TABLES[1], &[id])?;
+                fixes.push(format!("Deleted comparison {} \"{}\": none of its reports exist", id, title));
+                continue;
+            }
+            // Selections are keyed by report id; drop keys for reports no longer in the comparison.
+            let prune = |raw: &str| -> (Value, usize) {
+                let mut v: Value = serde_json::from_str(raw).unwrap_or_else(|_| serde_json::json!({}));
+                let mut removed = 0;
+                if let Some(obj) = v.as_object_mut() {
+                    let before = obj.len();
+                    obj.retain(|k, _| k.parse::<i64>().map(|k| kept.contains(&k)).unwrap_or(false));
+                    removed = before - obj.len();
+                }
+                (v, removed)
+            };
+            let (cpu, cpu_removed) = prune(&cpu_json);
+            let (mem, mem_removed) = prune(&mem_json);
+            let new_baseline = baseline.filter(|b| kept.contains(b)).or(kept.first().copied());
+            if missing.is_empty() && cpu_removed == 0 && mem_removed == 0 && new_baseline == baseline {
+                continue;
+            }
+            let mut meta: Value = serde_json::from_str(&meta_json).unwrap_or_else(|_| serde_json::json!({}));
+            if meta.is_object() {
+                meta["report_ids"] = serde_json::json!(kept);
+            }
+            conn.execute(
+                "UPDATE comparisons SET report_ids_json = ?1, baseline_report_id = ?2, cpu_selections_json = ?3,
+                 mem_selections_json = ?4, meta_json = ?5 WHERE id = ?6",
+                params![
+                    serde_json::to_string(&kept).unwrap_or_else(|_| "[]".to_string()),
+                    new_baseline,
+                    cpu.to_string(),
+                    mem.to_string(),
+                    meta.to_string(),
+                    id
+                ],
+            )?;
+            let mut what = Vec::new();
+            if !missing.is_empty() {
+                what.push(format!("removed missing reports {:?}", missing));
+            }
+            if cpu_removed + mem_removed > 0 {
+                what.push(format!("dropped {} stale process selections", cpu_removed + mem_removed));
+            }
+            if new_baseline != baseline {
+                what.push(format!("baseline reset to report {}", new_baseline.unwrap_or_default()));
+            }
+            fixes.push(format!("Comparison {} \"{}\": {}", id, title, what.join(", ")));
+        }
+
+        for table in ["folders", "comparison_folders"] {
+            // Rows whose path isn't in normalized form can never be selected or deleted from the UI.
+            let paths: Vec<String> = {
+                let mut stmt = conn.prepare(&format!("SELECT path FROM {}", table))?;
+                let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+                rows.collect::<Result<_>>()?
+            };
+            for path in paths {
+                // Folders holding items show up in the tree from the items' paths alone.
+                if Self::normalize_folder_path(&path) != path {
+                    conn.execute(&format!("DELETE FROM {} WHERE path = ?1", table), params![path])?;
+                    fixes.push(format!("Deleted malformed folder \"{}\" from {}", path, table));
+                }
+            }
+        }
+
+        // Defaults apply to a report folder that is either created or holds reports (or sub-folders).
+        let stale: Vec<String> = {
+            let mut stmt = conn.prepare(
+                "SELECT s.path FROM folder_settings s
+                 WHERE NOT EXISTS (SELECT 1 FROM folders f WHERE f.path = s.path OR substr(f.path, 1, length(s.path) + 1) = s.path || '/')
+                   AND NOT EXISTS (SELECT 1 FROM reports r WHERE r.folder_path = s.path OR substr(r.folder_path, 1, length(s.path) + 1) = s.path || '/')",
+            )?;
+            let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+            rows.collect::<Result<_>>()?
+        };
+        for path in stale {
+            conn.execute("DELETE FROM folder_settings WHERE path = ?1", params![path])?;
+            fixes.push(format!("Deleted collection defaults of missing folder \"{}\"", path));
+        }
+        Ok(())
+    }
+
+    /// Integrity check, orphan cleanup and VACUUM, all under the connection lock so nothing else
+    /// touches the database meanwhile. A database that fails the integrity check is left as is.
+    pub fn maintain(&self) -> Result<MaintenanceReport> {
+        let mut conn = self.conn.lock().unwrap();
+        let size_before_bytes = Self::db_size_bytes(&conn)?;
+        let integrity: Vec<String> = {
+            let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+            let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+            rows.collect::<Result<_>>()?
+        };
+        let healthy = integrity.len() == 1 && integrity[0] == "ok";
+        let mut fixes = Vec::new();
+        if healthy {
+            let tx = conn.transaction()?;
+            Self::prune_orphans_tx(&tx, &mut fixes)?;
+            Self::check_tag_index_tx(&tx, &mut fixes)?;
+            tx.commit()?;
+            conn.execute_batch("VACUUM")?;
+        }
+        Ok(MaintenanceReport {
+            ran_at: chrono::Utc::now().to_rfc3339(),
+            integrity,
+            size_before_bytes,
+            size_after_bytes: Self::db_size_bytes(&conn)?,
+            fixes,
+            vacuumed: healthy,
+        })
+    }
+
+    pub fn delete_folder(
+        &self,
+        path: &str,
+        strategy: Option<&str>,
+    ) -> std::result::Result<(usize, usize), PerfSightError> {
         let mut conn = self.conn.lock().unwrap();
         let p = Self::normalize_folder_path(path);
         if p.is_empty() {
@@ -704,18 +1593,18 @@ impl Database {
         // IMPORTANT: do not call self.get_folder_stats() here (it locks the same mutex again).
         let stats = Self::get_folder_stats_conn(&conn, &p)?;
         if stats.report_count == 0 && stats.child_folder_count == 0 {
-            conn.execute("DELETE FROM folders WHERE path = ?1", params![p])?;
+            let tx = conn.transaction()?;
+            tx.execute("DELETE FROM folder_settings WHERE path = ?1", params![p])?;
+            tx.execute("DELETE FROM folders WHERE path = ?1", params![p])?;
+            tx.commit()?;
             return Ok((0, 0));
         }
         let strat = strategy.unwrap_or("");
         if strat.is_empty() {
-            return Err(rusqlite::Error::ToSqlConversionFailure(Box::new(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!(
-                    "FOLDER_NOT_EMPTY reports={} folders={}",
-                    stats.report_count, stats.child_folder_count
-                ),
-            ))));
+            return Err(PerfSightError::FolderNotEmpty {
+                reports: stats.report_count,
+                folders: stats.child_folder_count,
+            });
         }
         let parent = p.rsplit_once('/').map(|(a, _)| a.to_string()).unwrap_or_else(|| "".to_string());
         let dest = match strat {
@@ -724,27 +1613,19 @@ impl Database {
             _ => "".to_string(),
         };
         let tx = conn.transaction()?;
+        // The deleted folder's own defaults go with it; sub-folders keep theirs as they move.
+        tx.execute("DELETE FROM folder_settings WHERE path = ?1", params![p])?;
         let (moved_reports, moved_folders) = Self::rename_folder_prefix_tx(&tx, &p, &dest)?;
         tx.execute("DELETE FROM folders WHERE path = ?1", params![p])?;
         tx.commit()?;
         Ok((moved_reports, moved_folders))
     }
 
-    /// Return distinct tag strings seen in existing reports, with frequency counts.
-    pub fn get_known_tags(&self) -> Result<Vec<TagStat>> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare("SELECT meta_json FROM reports")?;
-
+    // Case-insensitive tag counts (first spelling wins), most frequent first.
+    fn count_tags<I: IntoIterator<Item = Vec<String>>>(tag_lists: I) -> Vec<TagStat> {
         let mut counts: std::collections::HashMap<String, (String, u64)> = std::collections::HashMap::new();
-        let iter = stmt.query_map([], |row| {
-            let meta_str: String = row.get(0).unwrap_or_else(|_| "{}".to_string());
-            Ok(meta_str)
-        })?;
-
-        for r in iter {
-            let meta_str = r?;
-            let meta: Value = serde_json::from_str(&meta_str).unwrap_or_else(|_| serde_json::json!({}));
-            for tag in Self::extract_tags_from_meta(&meta) {
+        for tags in tag_lists {
+            for tag in tags {
                 let key = tag.trim().to_lowercase();
                 if key.is_empty() {
                     continue;
@@ -753,43 +1634,359 @@ impl Database {
                 entry.1 += 1;
             }
         }
-
         let mut out: Vec<TagStat> = counts
             .into_iter()
             .map(|(_, (tag, count))| TagStat { tag, count })
             .collect();
         out.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.to_lowercase().cmp(&b.tag.to_lowercase())));
-        Ok(out)
+        out
+    }
+
+    // Replace the indexed tags of one report or comparison (`table` is one of TAG_TABLES).
+    fn index_tags(conn: &Connection, table: (&str, &str), id: i64, tags: &[String]) -> Result<()> {
+        let (name, owner) = table;
+        conn.execute(&format!("DELETE FROM {} WHERE {} = ?1", name, owner), params![id])?;
+        for tag in tags {
+            conn.execute(
+                &format!("INSERT OR IGNORE INTO {} ({}, tag, tag_lower) VALUES (?1, ?2, ?3)", name, owner),
+                params![id, tag, tag.to_lowercase()],
+            )?;
+        }
+        Ok(())
+    }
+
+    fn unindex_tags(conn: &Connection, table: (&str, &str), ids: &[i64]) -> Result<()> {
+        let (name, owner) = table;
+        let placeholders = (0..ids.len()).map(|i| format!("?{}", i + 1)).collect::<Vec<_>>().join(", ");
+        conn.execute(
+            &format!("DELETE FROM {} WHERE {} IN ({})", name, owner, placeholders),
+            rusqlite::params_from_iter(ids.iter()),
+        )?;
+        Ok(())
+    }
+
+    // Rows the report and comparison tag tables should hold, read from meta.
+    fn expected_tag_rows(conn: &Connection) -> Result<(BTreeSet<TagRow>, BTreeSet<TagRow>)> {
+        let rows = |sql: &str, tags: &dyn Fn(&str) -> Vec<String>| -> Result<BTreeSet<TagRow>> {
+            let mut stmt = conn.prepare(sql)?;
+            let metas = stmt.query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1).unwrap_or_else(|_| "{}".to_string())))
+            })?;
+            let mut out = BTreeSet::new();
+            for m in metas {
+                let (id, meta) = m?;
+                out.extend(tags(&meta).into_iter().map(|t| (id, t.to_lowercase(), t)));
+            }
+            Ok(out)
+        };
+        let reports = rows("SELECT id, meta_json FROM reports", &|m| ReportMeta::from_json_str(m).tags())?;
+        let comparisons = rows("SELECT id, meta_json FROM comparisons", &|m| {
+            Self::extract_tags_from_comparison_meta(&serde_json::from_str(m).unwrap_or_else(|_| serde_json::json!({})))
+        })?;
+        Ok((reports, comparisons))
+    }
+
+    /// Refill both tag tables from meta.
+    pub(crate) fn rebuild_tag_index(conn: &Connection) -> Result<()> {
+        let (reports, comparisons) = Self::expected_tag_rows(conn)?;
+        Self::write_tag_rows(conn, TAG_TABLES[0], &reports)?;
+        Self::write_tag_rows(conn, TAG_TABLES[1], &comparisons)
+    }
+
+    fn write_tag_rows(conn: &Connection, table: (&str, &str), rows: &BTreeSet<TagRow>) -> Result<()> {
+        let (name, owner) = table;
+        conn.execute(&format!("DELETE FROM {}", name), [])?;
+        let mut stmt = conn.prepare(&format!("INSERT INTO {} ({}, tag_lower, tag) VALUES (?1, ?2, ?3)", name, owner))?;
+        for (id, lower, tag) in rows {
+            stmt.execute(params![id, lower, tag])?;
+        }
+        Ok(())
+    }
+
+    // Rebuild a tag table whose rows no longer match meta.
+    fn check_tag_index_tx(conn: &Connection, fixes: &mut Vec<String>) -> Result<()> {
+        let (reports, comparisons) = Self::expected_tag_rows(conn)?;
+        for (table, expected) in TAG_TABLES.into_iter().zip([reports, comparisons]) {
+            let (name, owner) = table;
+            let actual: BTreeSet<TagRow> = {
+                let mut stmt = conn.prepare(&format!("SELECT {}, tag_lower, tag FROM {}", owner, name))?;
+                let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+                rows.collect::<Result<_>>()?
+            };
+            if actual != expected {
+                Self::write_tag_rows(conn, table, &expected)?;
+                fixes.push(format!("Rebuilt {} ({} rows, was {})", name, expected.len(), actual.len()));
+            }
+        }
+        Ok(())
+    }
+
+    // WHERE clause + params matching `get_folder_stats_conn` (root = only unfiled items).
+    fn folder_filter(path: &str) -> (String, &'static str, Vec<String>) {
+        let p = Self::normalize_folder_path(path);
+        if p.is_empty() {
+            (p, "folder_path = ''", vec![])
+        } else {
+            let like = Self::like_children(&p);
+            (p.clone(), "(folder_path = ?1 OR folder_path LIKE ?2 ESCAPE '\\')", vec![p, like])
+        }
+    }
+
+    /// Aggregate of the reports in `path` and below, sample data left out. Missing sparklines are computed first, on up
+    /// to `workers` threads.
+    pub fn get_folder_aggregate(&self, path: &str, workers: usize) -> Result<FolderAggregate> {
+        if let Err(e) = self.backfill_sparklines_parallel(workers) {
+            log_warn!("Failed to backfill report sparklines: {}", e);
+        }
+        let conn = self.conn.lock().unwrap();
+        let (p, clause, args) = Self::folder_filter(path);
+        let mut stmt = conn.prepare(&format!(
+            "SELECT created_at, meta_json, summary_json FROM reports WHERE {}",
+            clause
+        ))?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(args.iter()), |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1).unwrap_or_else(|_| "{}".to_string()),
+                row.get::<_, Option<String>>(2)?,
+            ))
+        })?;
+
+        let mut report_count = 0u64;
+        let mut summaries: Vec<ReportSparkline> = Vec::new();
+        let mut earliest: Option<String> = None;
+        let mut latest: Option<String> = None;
+        let mut tag_lists = Vec::new();
+        for r in rows {
+            let (created_at, meta_str, summary_str) = r?;
+            let meta = ReportMeta::from_json_str(&meta_str);
+            // Onboarding data would skew the folder's numbers.
+            if meta.is_sample() {
+                continue;
+            }
+            report_count += 1;
+            // RFC 3339 timestamps in one zone sort lexicographically.
+            if earliest.as_ref().is_none_or(|e| created_at < *e) {
+                earliest = Some(created_at.clone());
+            }
+            if latest.as_ref().is_none_or(|l| created_at > *l) {
+                latest = Some(created_at);
+            }
+            tag_lists.push(meta.tags());
+            if let Some(s) = summary_str.and_then(|s| serde_json::from_str(&s).ok()) {
+                summaries.push(s);
+            }
+        }
+
+        let n = summaries.len() as f64;
+        let avg = |f: &dyn Fn(&ReportSparkline) -> f64| {
+            if summaries.is_empty() { None } else { Some(summaries.iter().map(f).sum::<f64>() / n) }
+        };
+        let mut top_tags = Self::count_tags(tag_lists);
+        top_tags.truncate(AGGREGATE_TOP_TAGS);
+        let baseline_report_id = Self::get_folder_baseline_conn(&conn, &p)?;
+        Ok(FolderAggregate {
+            path: p,
+            report_count,
+            avg_score: avg(&|s| s.score as f64),
+            min_score: summaries.iter().map(|s| s.score).min(),
+            max_score: summaries.iter().map(|s| s.score).max(),
+            avg_p95_cpu: avg(&|s| s.p95_cpu as f64),
+            avg_max_mem_mb: avg(&|s| s.max_mem_mb),
+            analyzer_versions: summaries.iter().map(|s| s.analyzer_version).collect::<BTreeSet<_>>().into_iter().collect(),
+            earliest_created_at: earliest,
+            latest_created_at: latest,
+            top_tags,
+            baseline_report_id,
+        })
+    }
+
+    // Case-insensitive tag counts from a tag table, most frequent first. SQLite takes the bare
+    // `tag` from the MIN row, so the oldest spelling wins as in `count_tags`.
+    fn known_tags_conn(conn: &Connection, table: (&str, &str)) -> Result<Vec<TagStat>> {
+        let (name, owner) = table;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT tag, MIN({}), COUNT(*) AS n FROM {} GROUP BY tag_lower ORDER BY n DESC, tag_lower",
+            owner, name
+        ))?;
+        let rows = stmt.query_map([], |row| Ok(TagStat { tag: row.get(0)?, count: row.get::<_, i64>(2)? as u64 }))?;
+        rows.collect()
+    }
+
+    /// Return distinct tag strings seen in existing reports, with frequency counts.
+    pub fn get_known_tags(&self) -> Result<Vec<TagStat>> {
+        let conn = self.conn.lock().unwrap();
+        Self::known_tags_conn(&conn, TAG_TABLES[0])
     }
     
+    /// Meta only (no metrics), for checks that don't need samples.
+    pub fn get_report_meta(&self, id: i64) -> Result<ReportMeta> {
+        let conn = self.conn.lock().unwrap();
+        let meta_str: String =
+            conn.query_row("SELECT meta_json FROM reports WHERE id = ?1", params![id], |row| row.get(0))?;
+        Ok(ReportMeta::from_json_str(&meta_str))
+    }
+
     pub fn get_report_detail(&self, id: i64) -> Result<ReportDetail> {
+        // Only the read holds the connection; parsing and analyzing a large report doesn't keep
+        // other queries waiting.
+        let (id, created_at, title_db, metrics_str, meta_str): (i64, String, String, String, String) = {
+            let conn = self.conn.lock().unwrap();
+            conn.query_row(
+                "SELECT id, created_at, title, metrics_json, meta_json FROM reports WHERE id = ?1",
+                [id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
+            )?
+        };
+        let metrics: Vec<BatchMetric> = serde_json::from_str(&metrics_str).unwrap_or_default();
+        drop(metrics_str);
+        let meta = ReportMeta::from_json_str(&meta_str);
+
+        // On-the-fly analysis
+        let mut analysis = analysis::analyze_report(&metrics, &meta);
+        analysis::apply_aliases(&mut analysis, &meta.aliases());
+
+        Ok(ReportDetail {
+            id,
+            created_at,
+            title: meta.display_title(&title_db),
+            metrics,
+            analysis: Some(analysis),
+            meta,
+        })
+    }
+
+    /// The report's stored columns, with the samples left as raw JSON.
+    pub fn stored_report(&self, id: i64) -> Result<StoredReport> {
         let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare("SELECT id, created_at, title, metrics_json, meta_json FROM reports WHERE id = ?1")?;
-        
-        let report = stmt.query_row([id], |row| {
-            let metrics_str: String = row.get(3)?;
-            let metrics: Vec<BatchMetric> = serde_json::from_str(&metrics_str).unwrap_or_default();
-            let meta_str: String = row.get(4)?;
-            let meta: Value = serde_json::from_str(&meta_str).unwrap_or_else(|_| serde_json::json!({}));
-            
-            // On-the-fly analysis
-            let analysis = analysis::analyze(&metrics);
+        conn.query_row(
+            "SELECT id, created_at, title, metrics_json, meta_json, analysis_json FROM reports WHERE id = ?1",
+            params![id],
+            |row| {
+                let meta = ReportMeta::from_json_str(&row.get::<_, String>(4)?);
+                let title_db: String = row.get(2)?;
+                let analysis: Option<String> = row.get(5)?;
+                Ok(StoredReport {
+                    id: row.get(0)?,
+                    created_at: row.get(1)?,
+                    title: meta.display_title(&title_db),
+                    metrics_json: row.get(3)?,
+                    analysis: analysis.and_then(|s| serde_json::from_str(&s).ok()),
+                    meta,
+                })
+            },
+        )
+    }
 
-            Ok(ReportDetail {
-                id: row.get(0)?,
-                created_at: row.get(1)?,
-                title: row.get(2)?,
-                metrics,
-                analysis: Some(analysis),
-                meta,
-            })
+    /// Store a report's analysis (as `analysis::analyze_report` returns it, before aliases).
+    pub fn cache_analysis(&self, id: i64, analysis: &AnalysisReport) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE reports SET analysis_json = ?1 WHERE id = ?2",
+            params![serde_json::to_string(analysis).unwrap_or_else(|_| "null".to_string()), id],
+        )
+    }
+
+    /// Reports in `scope` whose cached analysis or sparkline came from an analyzer older than
+    /// `ANALYZER_VERSION`, oldest first; every report in scope with `force`. A report whose
+    /// analysis was never cached only needs its sparkline checked, as the analysis is computed
+    /// on first use.
+    pub fn reports_to_reanalyze(&self, scope: &ReanalyzeScope, force: bool) -> Result<Vec<i64>> {
+        #[derive(Deserialize)]
+        struct Stamp {
+            #[serde(default)]
+            analyzer_version: u32,
+        }
+        let stale = |json: Option<String>| {
+            json.and_then(|s| serde_json::from_str::<Stamp>(&s).ok())
+                .is_some_and(|s| s.analyzer_version < analysis::ANALYZER_VERSION)
+        };
+        let conn = self.conn.lock().unwrap();
+        let (clause, args): (String, Vec<rusqlite::types::Value>) = match scope {
+            ReanalyzeScope::Folder { path } => {
+                let (_, clause, args) = Self::folder_filter(path);
+                (clause.to_string(), args.into_iter().map(rusqlite::types::Value::Text).collect())
+            }
+            ReanalyzeScope::Ids { ids } => {
+                let placeholders = (1..=ids.len()).map(|i| format!("?{}", i)).collect::<Vec<_>>().join(", ");
+                (format!("id IN ({})", placeholders), ids.iter().map(|id| rusqlite::types::Value::Integer(*id)).collect())
+            }
+            ReanalyzeScope::All => ("1 = 1".to_string(), Vec::new()),
+        };
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, summary_json, analysis_json FROM reports WHERE {} ORDER BY id",
+            clause
+        ))?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(args.iter()), |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, Option<String>>(1)?, row.get::<_, Option<String>>(2)?))
         })?;
+        let mut out = Vec::new();
+        for row in rows {
+            let (id, summary, analysis) = row?;
+            // A missing sparkline is stale too; `backfill_sparklines_once` would otherwise compute it.
+            if force || summary.is_none() || stale(summary) || stale(analysis) {
+                out.push(id);
+            }
+        }
+        Ok(out)
+    }
+
+    /// A report's analysis and sparkline recomputed with the current analyzer. The connection is
+    /// only held to read the row, so several reports can be computed at once; store the result
+    /// with `store_reanalysis`.
+    pub fn reanalysis(&self, id: i64) -> Result<Reanalysis> {
+        let (metrics_str, meta_str): (String, String) = self.conn.lock().unwrap().query_row(
+            "SELECT metrics_json, meta_json FROM reports WHERE id = ?1",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let metrics: Vec<BatchMetric> = serde_json::from_str(&metrics_str).unwrap_or_default();
+        // One analysis feeds both, so the listing's score matches the report's.
+        let analysis = analysis::analyze_report(&metrics, &ReportMeta::from_json_str(&meta_str));
+        Ok(Reanalysis {
+            id,
+            score: analysis.score,
+            analysis_json: serde_json::to_string(&analysis).unwrap_or_else(|_| "null".to_string()),
+            summary_json: Self::sparkline_json_from(&metrics, &analysis),
+        })
+    }
+
+    /// Replace a report's cached analysis and sparkline. Budget verdicts are cleared and
+    /// recomputed on next use.
+    pub fn store_reanalysis(&self, r: &Reanalysis) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE reports SET analysis_json = ?1, summary_json = ?2, budget_results_json = NULL WHERE id = ?3",
+            params![r.analysis_json, r.summary_json, r.id],
+        )
+    }
+
+    pub fn report_count(&self) -> Result<u64> {
+        let conn = self.conn.lock().unwrap();
+        Self::sum_i64(&conn, "SELECT COUNT(*) FROM reports")
+    }
 
-        Ok(report)
+    /// Ids of the reports and comparisons flagged `sample` in meta (see `sample_data`).
+    pub fn sample_data_ids(&self) -> Result<(Vec<i64>, Vec<i64>)> {
+        let conn = self.conn.lock().unwrap();
+        let mut ids = [Vec::new(), Vec::new()];
+        for (table, out) in ["reports", "comparisons"].into_iter().zip(ids.iter_mut()) {
+            let mut stmt = conn.prepare(&format!("SELECT id, meta_json FROM {} ORDER BY id", table))?;
+            let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+            for row in rows {
+                let (id, meta_str) = row?;
+                if ReportMeta::from_json_str(&meta_str).is_sample() {
+                    out.push(id);
+                }
+            }
+        }
+        let [reports, comparisons] = ids;
+        Ok((reports, comparisons))
     }
 
     pub fn delete_report(&self, id: i64) -> Result<usize> {
         let conn = self.conn.lock().unwrap();
+        Self::unindex_tags(&conn, TAG_TABLES[0], &[id])?;
         conn.execute("DELETE FROM reports WHERE id = ?1", params![id])
     }
 
@@ -798,20 +1995,43 @@ impl Database {
             return Ok(0);
         }
         let conn = self.conn.lock().unwrap();
-        // Build `IN (?1, ?2, ...)` safely with bound params.
+        // Build `IN (?1, ?2, ...)` safely with bound params.
+        let placeholders = (0..ids.len())
+            .map(|i| format!("?{}", i + 1))
+            .collect::<Vec<_>>()
+            .join(", ");
+        Self::unindex_tags(&conn, TAG_TABLES[0], ids)?;
+        let sql = format!("DELETE FROM reports WHERE id IN ({})", placeholders);
+        let mut stmt = conn.prepare(&sql)?;
+        stmt.execute(rusqlite::params_from_iter(ids.iter()))
+    }
+
+    pub fn set_report_locked(&self, id: i64, locked: bool) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("UPDATE reports SET locked = ?1 WHERE id = ?2", params![locked, id])
+    }
+
+    /// The subset of `ids` that is locked, ascending.
+    pub fn locked_report_ids(&self, ids: &[i64]) -> Result<Vec<i64>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let conn = self.conn.lock().unwrap();
         let placeholders = (0..ids.len())
             .map(|i| format!("?{}", i + 1))
             .collect::<Vec<_>>()
             .join(", ");
-        let sql = format!("DELETE FROM reports WHERE id IN ({})", placeholders);
+        let sql = format!("SELECT id FROM reports WHERE locked = 1 AND id IN ({}) ORDER BY id", placeholders);
         let mut stmt = conn.prepare(&sql)?;
-        stmt.execute(rusqlite::params_from_iter(ids.iter()))
+        let rows = stmt.query_map(rusqlite::params_from_iter(ids.iter()), |row| row.get(0))?;
+        rows.collect()
     }
 
     pub fn update_report_title(&self, id: i64, title: &str) -> Result<usize> {
         let conn = self.conn.lock().unwrap();
+        // The hash covers the title; it's recomputed on the next duplicate lookup.
         conn.execute(
-            "UPDATE reports SET title = ?1 WHERE id = ?2",
+            "UPDATE reports SET title = ?1, content_hash = NULL WHERE id = ?2",
             params![title, id],
         )
     }
@@ -824,9 +2044,9 @@ impl Database {
             params![id],
             |row| row.get(0),
         )?;
-        let mut meta: Value = serde_json::from_str(&meta_str).unwrap_or_else(|_| serde_json::json!({}));
-        Self::set_meta_folder_path(&mut meta, &fp);
-        let meta_json = serde_json::to_string(&meta).unwrap_or_else(|_| "{}".to_string());
+        let mut meta = ReportMeta::from_json_str(&meta_str);
+        meta.set_folder_path(&fp);
+        let meta_json = meta.to_json_string();
         conn.execute(
             "UPDATE reports SET folder_path = ?1, meta_json = ?2 WHERE id = ?3",
             params![fp, meta_json, id],
@@ -846,9 +2066,9 @@ impl Database {
                 params![id],
                 |row| row.get(0),
             )?;
-            let mut meta: Value = serde_json::from_str(&meta_str).unwrap_or_else(|_| serde_json::json!({}));
-            Self::set_meta_folder_path(&mut meta, &fp);
-            let meta_json = serde_json::to_string(&meta).unwrap_or_else(|_| "{}".to_string());
+            let mut meta = ReportMeta::from_json_str(&meta_str);
+            meta.set_folder_path(&fp);
+            let meta_json = meta.to_json_string();
             conn.execute(
                 "UPDATE reports SET folder_path = ?1, meta_json = ?2 WHERE id = ?3",
                 params![fp, meta_json, id],
@@ -868,6 +2088,314 @@ impl Database {
         stmt.execute(rusqlite::params_from_iter(params))
     }
 
+    /// Replace a report's process aliases. Every PID must appear in the report (samples,
+    /// target PIDs or process snapshot).
+    pub fn update_report_aliases(
+        &self,
+        id: i64,
+        aliases: &[ProcessAlias],
+    ) -> std::result::Result<usize, PerfSightError> {
+        let conn = self.conn.lock().unwrap();
+        let (metrics_str, meta_str): (String, String) = conn.query_row(
+            "SELECT metrics_json, meta_json FROM reports WHERE id = ?1",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let metrics: Vec<BatchMetric> = serde_json::from_str(&metrics_str).unwrap_or_default();
+        let mut meta = ReportMeta::from_json_str(&meta_str);
+
+        let mut known: std::collections::HashSet<u32> =
+            metrics.iter().flat_map(|b| b.metrics.keys().copied()).collect();
+        if let Some(c) = &meta.collection {
+            known.extend(c.target_pids.iter().copied());
+        }
+        known.extend(
+            meta.process_snapshot
+                .iter()
+                .filter_map(|p| p.get("pid").and_then(|v| v.as_u64()))
+                .map(|pid| pid as u32),
+        );
+        let mut unknown: Vec<u32> = aliases.iter().map(|a| a.pid).filter(|pid| !known.contains(pid)).collect();
+        if !unknown.is_empty() {
+            unknown.sort();
+            unknown.dedup();
+            return Err(PerfSightError::invalid_input(
+                "aliases",
+                format!(
+                    "unknown pid(s) for this report: {}",
+                    unknown.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", ")
+                ),
+            ));
+        }
+
+        meta.set_aliases(aliases);
+        Ok(conn.execute(
+            "UPDATE reports SET meta_json = ?1 WHERE id = ?2",
+            params![meta.to_json_string(), id],
+        )?)
+    }
+
+    /// Replace a report's samples and meta after a post-save enrichment (e.g. a trace import).
+    /// The sparkline is rebuilt and the content hash cleared, since the content changed.
+    pub fn update_report_samples(&self, id: i64, metrics: &[BatchMetric], meta: &ReportMeta) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        let metrics_json = serde_json::to_string(metrics).unwrap_or_else(|_| "[]".to_string());
+        let meta_json = meta.to_json_string();
+        let (size_bytes, storage_json) = Self::storage_json(metrics, &metrics_json, &meta_json, meta);
+        let changed = conn.execute(
+            "UPDATE reports SET metrics_json = ?1, meta_json = ?2, summary_json = ?3, content_hash = NULL,
+             analysis_json = NULL, budget_results_json = NULL, size_bytes = ?4, storage_json = ?5 WHERE id = ?6",
+            params![metrics_json, meta_json, Self::sparkline_json(metrics, meta), size_bytes, storage_json, id],
+        )?;
+        if changed > 0 {
+            Self::index_tags(&conn, TAG_TABLES[0], id, &meta.tags())?;
+        }
+        Ok(changed)
+    }
+
+    /// Shallow-merge `patch` into a report's meta_json (used for post-save annotations).
+    pub fn update_report_meta_patch(&self, id: i64, patch: &Value) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        let meta_str: String = conn.query_row(
+            "SELECT meta_json FROM reports WHERE id = ?1",
+            params![id],
+            |row| row.get(0),
+        )?;
+        let mut meta: Value = serde_json::from_str(&meta_str).unwrap_or_else(|_| serde_json::json!({}));
+        if let (Value::Object(dst), Value::Object(src)) = (&mut meta, patch) {
+            for (k, v) in src.iter() {
+                dst.insert(k.clone(), v.clone());
+            }
+        }
+        let meta_json = serde_json::to_string(&meta).unwrap_or_else(|_| "{}".to_string());
+        // Budgets and events live in meta, so the cached verdicts may be stale now.
+        let changed = conn.execute(
+            "UPDATE reports SET meta_json = ?1, analysis_json = NULL, budget_results_json = NULL WHERE id = ?2",
+            params![meta_json, id],
+        )?;
+        Self::index_tags(&conn, TAG_TABLES[0], id, &ReportMeta::from_json_str(&meta_json).tags())?;
+        Ok(changed)
+    }
+
+    /// Newest `limit` reports directly in `path` (by creation time), optionally only those of one
+    /// scenario (case-insensitive). Returns (id, created_at, display title, meta); samples aren't read.
+    pub fn latest_reports_in_folder(
+        &self,
+        path: &str,
+        scenario: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<(i64, String, String, ReportMeta)>> {
+        let conn = self.conn.lock().unwrap();
+        let p = Self::normalize_folder_path(path);
+        let mut stmt = conn.prepare(
+            "SELECT id, created_at, title, meta_json FROM reports WHERE folder_path = ?1 ORDER BY created_at DESC, id DESC",
+        )?;
+        let rows = stmt.query_map(params![p], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?))
+        })?;
+        let mut out = Vec::new();
+        for row in rows {
+            let (id, created_at, title, meta_str) = row?;
+            let meta = ReportMeta::from_json_str(&meta_str);
+            if let Some(want) = scenario {
+                if !meta.scenario_name().is_some_and(|s| s.eq_ignore_ascii_case(want.trim())) {
+                    continue;
+                }
+            }
+            let title = meta.display_title(&title);
+            out.push((id, created_at, title, meta));
+            if out.len() >= limit {
+                break;
+            }
+        }
+        Ok(out)
+    }
+
+    /// Cached scores (id, score, analyzer version) of the newest `limit` reports directly in `path`
+    /// with the same scenario (case-insensitive; None matches reports without one), excluding
+    /// `exclude_id` and sample data. Newest first; reports not backfilled yet are left out.
+    pub fn recent_scores_in_folder(
+        &self,
+        path: &str,
+        scenario: Option<&str>,
+        exclude_id: i64,
+        limit: usize,
+    ) -> Result<Vec<(i64, u8, u32)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, meta_json, summary_json FROM reports WHERE folder_path = ?1 AND id <> ?2 ORDER BY created_at DESC, id DESC",
+        )?;
+        let rows = stmt.query_map(params![Self::normalize_folder_path(path), exclude_id], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<String>>(2)?))
+        })?;
+        let want = scenario.map(str::trim).filter(|s| !s.is_empty());
+        let mut out = Vec::new();
+        for row in rows {
+            let (id, meta_str, summary_str) = row?;
+            let meta = ReportMeta::from_json_str(&meta_str);
+            if meta.is_sample() {
+                continue;
+            }
+            let same = match (want, meta.scenario_name().map(str::trim).filter(|s| !s.is_empty())) {
+                (Some(w), Some(s)) => w.eq_ignore_ascii_case(s),
+                (None, None) => true,
+                _ => false,
+            };
+            if !same {
+                continue;
+            }
+            if let Some(s) = summary_str.and_then(|s| serde_json::from_str::<ReportSparkline>(&s).ok()) {
+                out.push((id, s.score, s.analyzer_version));
+                if out.len() >= limit {
+                    break;
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    /// Score from the report's cached summary, with the analyzer version that produced it.
+    pub fn cached_score(&self, id: i64) -> Result<Option<(u8, u32)>> {
+        let conn = self.conn.lock().unwrap();
+        let summary: Option<String> =
+            conn.query_row("SELECT summary_json FROM reports WHERE id = ?1", params![id], |row| row.get(0))?;
+        Ok(summary.and_then(|s| serde_json::from_str::<ReportSparkline>(&s).ok()).map(|s| (s.score, s.analyzer_version)))
+    }
+
+    /// Budget verdicts for a report against the budgets in its meta. Served from the cached
+    /// columns when present; otherwise computed (samples are only loaded when the analysis itself
+    /// was never cached) and stored. Empty when the report has no budgets.
+    pub fn budget_results(&self, id: i64) -> Result<Vec<BudgetResult>> {
+        let conn = self.conn.lock().unwrap();
+        let (meta_str, analysis_str, results_str): (String, Option<String>, Option<String>) = conn.query_row(
+            "SELECT meta_json, analysis_json, budget_results_json FROM reports WHERE id = ?1",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        if let Some(results) = results_str.and_then(|s| serde_json::from_str::<Vec<BudgetResult>>(&s).ok()) {
+            return Ok(results);
+        }
+        let meta = ReportMeta::from_json_str(&meta_str);
+        if meta.budgets.is_empty() {
+            return Ok(Vec::new());
+        }
+        let analysis = match analysis_str.and_then(|s| serde_json::from_str::<AnalysisReport>(&s).ok()) {
+            Some(a) => a,
+            None => {
+                let metrics_str: String =
+                    conn.query_row("SELECT metrics_json FROM reports WHERE id = ?1", params![id], |row| row.get(0))?;
+                let metrics: Vec<BatchMetric> = serde_json::from_str(&metrics_str).unwrap_or_default();
+                let a = analysis::analyze_report(&metrics, &meta);
+                conn.execute(
+                    "UPDATE reports SET analysis_json = ?1 WHERE id = ?2",
+                    params![serde_json::to_string(&a).unwrap_or_else(|_| "null".to_string()), id],
+                )?;
+                a
+            }
+        };
+        let results = analysis::evaluate_budgets(&analysis, &meta.budgets);
+        conn.execute(
+            "UPDATE reports SET budget_results_json = ?1 WHERE id = ?2",
+            params![serde_json::to_string(&results).unwrap_or_else(|_| "[]".to_string()), id],
+        )?;
+        Ok(results)
+    }
+
+    /// Pin `report_id` as the baseline of report folder `path`, or clear the pin with None.
+    pub fn set_folder_baseline(&self, path: &str, report_id: Option<i64>) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        let p = Self::normalize_folder_path(path);
+        match report_id {
+            Some(id) => conn.execute(
+                "INSERT INTO folder_baselines (folder_path, report_id, pinned_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(folder_path) DO UPDATE SET report_id = excluded.report_id, pinned_at = excluded.pinned_at",
+                params![p, id, chrono::Utc::now().to_rfc3339()],
+            ),
+            None => conn.execute("DELETE FROM folder_baselines WHERE folder_path = ?1", params![p]),
+        }
+    }
+
+    /// Store the collection defaults of report folder `path`; settings with no field set clear them.
+    pub fn set_folder_settings(&self, path: &str, settings: &FolderCollectionSettings) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        let p = Self::normalize_folder_path(path);
+        if settings.fields().is_empty() {
+            return conn.execute("DELETE FROM folder_settings WHERE path = ?1", params![p]);
+        }
+        conn.execute(
+            "INSERT INTO folder_settings (path, settings_json, updated_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(path) DO UPDATE SET settings_json = excluded.settings_json, updated_at = excluded.updated_at",
+            params![
+                p,
+                serde_json::to_string(settings).unwrap_or_else(|_| "{}".to_string()),
+                chrono::Utc::now().to_rfc3339()
+            ],
+        )
+    }
+
+    /// The defaults set on `path` itself (not inherited ones).
+    pub fn get_folder_settings(&self, path: &str) -> Result<Option<FolderCollectionSettings>> {
+        let conn = self.conn.lock().unwrap();
+        Self::get_folder_settings_conn(&conn, &Self::normalize_folder_path(path))
+    }
+
+    fn get_folder_settings_conn(conn: &Connection, path: &str) -> Result<Option<FolderCollectionSettings>> {
+        match conn.query_row("SELECT settings_json FROM folder_settings WHERE path = ?1", params![path], |row| {
+            row.get::<_, String>(0)
+        }) {
+            Ok(json) => Ok(Some(serde_json::from_str(&json).unwrap_or_default())),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Defaults that apply to a run filed into `path`: the folder's own, then each parent's, nearest
+    /// first. Folders without settings are skipped.
+    pub fn folder_settings_chain(&self, path: &str) -> Result<Vec<(String, FolderCollectionSettings)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut current = Self::normalize_folder_path(path);
+        let mut chain = Vec::new();
+        while !current.is_empty() {
+            if let Some(settings) = Self::get_folder_settings_conn(&conn, &current)? {
+                chain.push((current.clone(), settings));
+            }
+            current = current.rsplit_once('/').map(|(parent, _)| parent.to_string()).unwrap_or_default();
+        }
+        Ok(chain)
+    }
+
+    /// The pinned baseline of report folder `path`. A pin whose report was deleted reads as None.
+    pub fn get_folder_baseline(&self, path: &str) -> Result<Option<i64>> {
+        let conn = self.conn.lock().unwrap();
+        Self::get_folder_baseline_conn(&conn, path)
+    }
+
+    fn get_folder_baseline_conn(conn: &Connection, path: &str) -> Result<Option<i64>> {
+        match conn.query_row(
+            "SELECT b.report_id FROM folder_baselines b JOIN reports r ON r.id = b.report_id WHERE b.folder_path = ?1",
+            params![Self::normalize_folder_path(path)],
+            |row| row.get(0),
+        ) {
+            Ok(id) => Ok(Some(id)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// (created_at, folder_path) of each report, in `ids` order. Missing ids are an error.
+    pub fn report_placements(&self, ids: &[i64]) -> Result<Vec<(String, String)>> {
+        let conn = self.conn.lock().unwrap();
+        ids.iter()
+            .map(|id| {
+                conn.query_row(
+                    "SELECT created_at, folder_path FROM reports WHERE id = ?1",
+                    params![id],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+            })
+            .collect()
+    }
+
     // ============================
     // Comparisons (separate artifact)
     // ============================
@@ -880,6 +2408,7 @@ impl Database {
         baseline_report_id: Option<i64>,
         cpu_selections_by_id: &Value,
         mem_selections_by_id: &Value,
+        metric_selections: Option<&[String]>,
         meta: &Value,
     ) -> Result<i64> {
         let conn = self.conn.lock().unwrap();
@@ -888,22 +2417,36 @@ impl Database {
         let report_ids_json = serde_json::to_string(report_ids).unwrap_or_else(|_| "[]".to_string());
         let cpu_json = serde_json::to_string(cpu_selections_by_id).unwrap_or_else(|_| "{}".to_string());
         let mem_json = serde_json::to_string(mem_selections_by_id).unwrap_or_else(|_| "{}".to_string());
+        let metrics_json = metric_selections.map(|m| serde_json::to_string(m).unwrap_or_else(|_| "[]".to_string()));
         let mut meta_v = meta.clone();
         Self::set_comparison_meta_folder_path(&mut meta_v, &fp);
         let meta_json = serde_json::to_string(&meta_v).unwrap_or_else(|_| "{}".to_string());
 
         conn.execute(
-            "INSERT INTO comparisons (created_at, title, folder_path, report_ids_json, baseline_report_id, cpu_selections_json, mem_selections_json, meta_json)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-            params![created_at, title, fp, report_ids_json, baseline_report_id, cpu_json, mem_json, meta_json],
+            "INSERT INTO comparisons (created_at, title, folder_path, report_ids_json, baseline_report_id, cpu_selections_json, mem_selections_json, metric_selections_json, meta_json)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![created_at, title, fp, report_ids_json, baseline_report_id, cpu_json, mem_json, metrics_json, meta_json],
         )?;
-        Ok(conn.last_insert_rowid())
+        let id = conn.last_insert_rowid();
+        Self::index_tags(&conn, TAG_TABLES[1], id, &Self::extract_tags_from_comparison_meta(&meta_v))?;
+        Ok(id)
     }
 
-    pub fn get_all_comparisons(&self) -> Result<Vec<ComparisonSummary>> {
+    /// All comparisons, newest first. `tag` keeps only comparisons carrying that tag (case-insensitive).
+    pub fn get_all_comparisons(&self, tag: Option<&str>) -> Result<Vec<ComparisonSummary>> {
         let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare("SELECT id, created_at, title, folder_path, report_ids_json, meta_json FROM comparisons ORDER BY id DESC")?;
-        let iter = stmt.query_map([], |row| {
+        let tag = tag.map(str::trim).filter(|t| !t.is_empty());
+        let filter = if tag.is_some() {
+            "WHERE id IN (SELECT comparison_id FROM comparison_tags WHERE tag_lower = ?1)"
+        } else {
+            ""
+        };
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, created_at, title, folder_path, report_ids_json, meta_json FROM comparisons {} ORDER BY id DESC",
+            filter
+        ))?;
+        let args: Vec<String> = tag.map(str::to_lowercase).into_iter().collect();
+        let iter = stmt.query_map(rusqlite::params_from_iter(args.iter()), |row| {
             let id: i64 = row.get(0)?;
             let created_at: String = row.get(1)?;
             let title: String = row.get(2)?;
@@ -923,15 +2466,40 @@ impl Database {
                 report_count: report_ids.len() as u64,
             })
         })?;
-        let mut out = Vec::new();
-        for r in iter { out.push(r?); }
-        Ok(out)
+        iter.collect()
+    }
+
+    /// Replace a comparison's tags (stored as `meta.tags`, normalized like on read). An older
+    /// `meta.test_context.tags` is dropped so it can't re-add removed tags.
+    pub fn update_comparison_tags(&self, id: i64, tags: &[String]) -> Result<Vec<String>> {
+        let conn = self.conn.lock().unwrap();
+        let meta_str: String =
+            conn.query_row("SELECT meta_json FROM comparisons WHERE id = ?1", params![id], |row| row.get(0))?;
+        let mut meta: Value = serde_json::from_str(&meta_str).unwrap_or_else(|_| serde_json::json!({}));
+        if !meta.is_object() {
+            meta = serde_json::json!({});
+        }
+        let normalized = Self::extract_tags_from_comparison_meta(&serde_json::json!({ "tags": tags }));
+        meta["tags"] = serde_json::json!(normalized);
+        if let Some(tc) = meta.get_mut("test_context").and_then(|v| v.as_object_mut()) {
+            tc.remove("tags");
+        }
+        let meta_json = serde_json::to_string(&meta).unwrap_or_else(|_| "{}".to_string());
+        conn.execute("UPDATE comparisons SET meta_json = ?1 WHERE id = ?2", params![meta_json, id])?;
+        Self::index_tags(&conn, TAG_TABLES[1], id, &normalized)?;
+        Ok(normalized)
+    }
+
+    /// Distinct comparison tags with frequency counts (mirrors `get_known_tags`).
+    pub fn get_known_comparison_tags(&self) -> Result<Vec<TagStat>> {
+        let conn = self.conn.lock().unwrap();
+        Self::known_tags_conn(&conn, TAG_TABLES[1])
     }
 
     pub fn get_comparison_detail(&self, id: i64) -> Result<ComparisonDetail> {
         let conn = self.conn.lock().unwrap();
         conn.query_row(
-            "SELECT id, created_at, title, folder_path, report_ids_json, baseline_report_id, cpu_selections_json, mem_selections_json, meta_json
+            "SELECT id, created_at, title, folder_path, report_ids_json, baseline_report_id, cpu_selections_json, mem_selections_json, meta_json, metric_selections_json
              FROM comparisons WHERE id = ?1",
             params![id],
             |row| {
@@ -942,6 +2510,7 @@ impl Database {
                 let mem_str: String = row.get(7).unwrap_or_else(|_| "{}".to_string());
                 let meta_str: String = row.get(8).unwrap_or_else(|_| "{}".to_string());
                 let meta: Value = serde_json::from_str(&meta_str).unwrap_or_else(|_| serde_json::json!({}));
+                let metrics_str: Option<String> = row.get(9).unwrap_or(None);
                 let folder_from_meta = Self::extract_folder_path_from_comparison_meta(&meta);
                 let tags = Self::extract_tags_from_comparison_meta(&meta);
                 Ok(ComparisonDetail {
@@ -954,6 +2523,9 @@ impl Database {
                     baseline_report_id: row.get(5).ok(),
                     cpu_selections_by_id: serde_json::from_str(&cpu_str).unwrap_or(Value::Object(serde_json::Map::new())),
                     mem_selections_by_id: serde_json::from_str(&mem_str).unwrap_or(Value::Object(serde_json::Map::new())),
+                    metric_selections: metrics_str
+                        .and_then(|s| serde_json::from_str(&s).ok())
+                        .unwrap_or_else(default_comparison_metrics),
                     meta,
                 })
             },
@@ -962,6 +2534,7 @@ impl Database {
 
     pub fn delete_comparison(&self, id: i64) -> Result<usize> {
         let conn = self.conn.lock().unwrap();
+        Self::unindex_tags(&conn, TAG_TABLES[1], &[id])?;
         conn.execute("DELETE FROM comparisons WHERE id = ?1", params![id])
     }
 
@@ -969,6 +2542,7 @@ impl Database {
         if ids.is_empty() { return Ok(0); }
         let conn = self.conn.lock().unwrap();
         let placeholders = (0..ids.len()).map(|i| format!("?{}", i + 1)).collect::<Vec<_>>().join(", ");
+        Self::unindex_tags(&conn, TAG_TABLES[1], ids)?;
         let sql = format!("DELETE FROM comparisons WHERE id IN ({})", placeholders);
         let mut stmt = conn.prepare(&sql)?;
         stmt.execute(rusqlite::params_from_iter(ids.iter()))
@@ -985,13 +2559,17 @@ impl Database {
         baseline_report_id: Option<i64>,
         cpu_selections_by_id: &Value,
         mem_selections_by_id: &Value,
+        metric_selections: Option<&[String]>,
     ) -> Result<usize> {
         let conn = self.conn.lock().unwrap();
         let cpu_json = serde_json::to_string(cpu_selections_by_id).unwrap_or_else(|_| "{}".to_string());
         let mem_json = serde_json::to_string(mem_selections_by_id).unwrap_or_else(|_| "{}".to_string());
+        // None leaves the saved metric selection as it is.
+        let metrics_json = metric_selections.map(|m| serde_json::to_string(m).unwrap_or_else(|_| "[]".to_string()));
         conn.execute(
-            "UPDATE comparisons SET baseline_report_id = ?1, cpu_selections_json = ?2, mem_selections_json = ?3 WHERE id = ?4",
-            params![baseline_report_id, cpu_json, mem_json, id],
+            "UPDATE comparisons SET baseline_report_id = ?1, cpu_selections_json = ?2, mem_selections_json = ?3,
+             metric_selections_json = COALESCE(?4, metric_selections_json) WHERE id = ?5",
+            params![baseline_report_id, cpu_json, mem_json, metrics_json, id],
         )
     }
 
@@ -1088,7 +2666,16 @@ impl Database {
 
         let mut v = out.into_iter().collect::<Vec<_>>();
         v.sort();
-        Ok(v.into_iter().map(|path| FolderInfo { path }).collect())
+        Ok(v.into_iter().map(|path| FolderInfo { path, has_settings: false }).collect())
+    }
+
+    /// Create the comparison folder row for `path` and each of its parents.
+    pub fn ensure_comparison_folder_path(&self, path: &str) -> Result<String> {
+        let mut parent = String::new();
+        for part in Self::normalize_folder_path(path).split('/').filter(|p| !p.is_empty()) {
+            parent = self.create_comparison_folder(&parent, part)?;
+        }
+        Ok(parent)
     }
 
     pub fn create_comparison_folder(&self, parent_path: &str, name: &str) -> Result<String> {
@@ -1107,6 +2694,52 @@ impl Database {
         Ok(full)
     }
 
+    pub fn get_comparison_folder_aggregate(&self, path: &str) -> Result<ComparisonFolderAggregate> {
+        let conn = self.conn.lock().unwrap();
+        let (p, clause, args) = Self::folder_filter(path);
+        let mut stmt = conn.prepare(&format!(
+            "SELECT created_at, report_ids_json, meta_json FROM comparisons WHERE {}",
+            clause
+        ))?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(args.iter()), |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1).unwrap_or_else(|_| "[]".to_string()),
+                row.get::<_, String>(2).unwrap_or_else(|_| "{}".to_string()),
+            ))
+        })?;
+
+        let mut comparison_count = 0u64;
+        let mut report_ref_count = 0u64;
+        let mut earliest: Option<String> = None;
+        let mut latest: Option<String> = None;
+        let mut tag_lists = Vec::new();
+        for r in rows {
+            let (created_at, report_ids_str, meta_str) = r?;
+            comparison_count += 1;
+            report_ref_count += serde_json::from_str::<Vec<i64>>(&report_ids_str).map(|v| v.len() as u64).unwrap_or(0);
+            if earliest.as_ref().is_none_or(|e| created_at < *e) {
+                earliest = Some(created_at.clone());
+            }
+            if latest.as_ref().is_none_or(|l| created_at > *l) {
+                latest = Some(created_at);
+            }
+            let meta: Value = serde_json::from_str(&meta_str).unwrap_or_else(|_| serde_json::json!({}));
+            tag_lists.push(Self::extract_tags_from_comparison_meta(&meta));
+        }
+
+        let mut top_tags = Self::count_tags(tag_lists);
+        top_tags.truncate(AGGREGATE_TOP_TAGS);
+        Ok(ComparisonFolderAggregate {
+            path: p,
+            comparison_count,
+            report_ref_count,
+            earliest_created_at: earliest,
+            latest_created_at: latest,
+            top_tags,
+        })
+    }
+
     pub fn get_comparison_folder_stats(&self, path: &str) -> Result<ComparisonFolderStats> {
         let conn = self.conn.lock().unwrap();
         Self::get_comparison_folder_stats_conn(&conn, path)
@@ -1118,13 +2751,12 @@ impl Database {
         if from.is_empty() {
             return Ok((0, 0));
         }
-        let from_like = format!("{}/", from);
 
         // Update comparisons folder_path + meta_json
         let mut comparison_ids: Vec<i64> = Vec::new();
         {
-            let mut stmt = conn.prepare("SELECT id FROM comparisons WHERE folder_path = ?1 OR folder_path LIKE ?2")?;
-            let iter = stmt.query_map(params![from, format!("{}%", from_like)], |row| Ok(row.get::<_, i64>(0)?))?;
+            let mut stmt = conn.prepare("SELECT id FROM comparisons WHERE folder_path = ?1 OR folder_path LIKE ?2 ESCAPE '\\'")?;
+            let iter = stmt.query_map(params![from, Self::like_children(&from)], |row| Ok(row.get::<_, i64>(0)?))?;
             for r in iter { comparison_ids.push(r?); }
         }
         for id in &comparison_ids {
@@ -1160,8 +2792,8 @@ impl Database {
         // Move folders under prefix
         let mut folder_paths: Vec<String> = Vec::new();
         {
-            let mut stmt = conn.prepare("SELECT path FROM comparison_folders WHERE path = ?1 OR path LIKE ?2")?;
-            let iter = stmt.query_map(params![from, format!("{}%", from_like)], |row| Ok(row.get::<_, String>(0)?))?;
+            let mut stmt = conn.prepare("SELECT path FROM comparison_folders WHERE path = ?1 OR path LIKE ?2 ESCAPE '\\'")?;
+            let iter = stmt.query_map(params![from, Self::like_children(&from)], |row| Ok(row.get::<_, String>(0)?))?;
             for r in iter { folder_paths.push(Self::normalize_folder_path(&r?)); }
         }
         for p in &folder_paths {
@@ -1193,30 +2825,61 @@ impl Database {
         Ok((comparison_ids.len(), folder_paths.len()))
     }
 
-    pub fn rename_comparison_folder(&self, path: &str, new_name: &str) -> Result<String> {
+    /// Like `rename_folder`, for comparison folders.
+    pub fn rename_comparison_folder(
+        &self,
+        path: &str,
+        new_name: &str,
+        on_conflict: FolderConflict,
+    ) -> std::result::Result<String, PerfSightError> {
         let mut conn = self.conn.lock().unwrap();
         let from = Self::normalize_folder_path(path);
         if from.is_empty() {
             return Ok(from);
         }
-        let parent = from.rsplit_once('/').map(|(a, _)| a.to_string()).unwrap_or_else(|| "".to_string());
-        let leaf = Self::normalize_folder_path(new_name);
-        if leaf.is_empty() {
-            return Ok(from);
+        let Some(to) = Self::renamed_folder_path(&from, new_name) else { return Ok(from) };
+        if to == from {
+            return Ok(to);
+        }
+        let existing = Self::get_comparison_folder_stats_conn(&conn, &to)?;
+        let merging = existing.comparison_count > 0 || existing.child_folder_count > 0;
+        if merging && on_conflict == FolderConflict::Fail {
+            return Err(PerfSightError::FolderExists {
+                path: to,
+                reports: existing.comparison_count,
+                folders: existing.child_folder_count,
+            });
         }
-        let to = if parent.is_empty() { leaf } else { format!("{}/{}", parent, leaf) };
 
         let tx = conn.transaction()?;
+        let moved = if merging { Self::ids_under_tx(&tx, "comparisons", &from)? } else { Vec::new() };
         let _ = Self::rename_comparison_folder_prefix_tx(&tx, &from, &to)?;
         tx.execute(
             "INSERT OR IGNORE INTO comparison_folders (path, created_at) VALUES (?1, ?2)",
             params![to, chrono::Utc::now().to_rfc3339()],
         )?;
+        let entry = Self::folder_merge_entry(&from, &to);
+        for id in moved {
+            let meta_str: String =
+                tx.query_row("SELECT meta_json FROM comparisons WHERE id = ?1", params![id], |row| row.get(0))?;
+            let mut meta: Value = serde_json::from_str(&meta_str).unwrap_or_else(|_| serde_json::json!({}));
+            let Some(obj) = meta.as_object_mut() else { continue };
+            let merges = obj.entry("folder_merges").or_insert_with(|| Value::Array(Vec::new()));
+            if let Some(list) = merges.as_array_mut() {
+                list.push(entry.clone());
+            }
+            let meta_json = serde_json::to_string(&meta).unwrap_or_else(|_| "{}".to_string());
+            tx.execute("UPDATE comparisons SET meta_json = ?1 WHERE id = ?2", params![meta_json, id])?;
+        }
         tx.commit()?;
         Ok(to)
     }
 
-    pub fn delete_comparison_folder(&self, path: &str, strategy: Option<&str>) -> Result<(usize, usize)> {
+    pub fn delete_comparison_folder(
+        &self,
+        path: &str,
+        strategy: Option<&str>,
+    ) -> std::result::Result<(usize, usize), PerfSightError> {
         let mut conn = self.conn.lock().unwrap();
         let p = Self::normalize_folder_path(path);
         if p.is_empty() {
@@ -1229,13 +2892,10 @@ impl Database {
         }
         let strat = strategy.unwrap_or("");
         if strat.is_empty() {
-            return Err(rusqlite::Error::ToSqlConversionFailure(Box::new(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!(
-                    "FOLDER_NOT_EMPTY comparisons={} folders={}",
-                    stats.comparison_count, stats.child_folder_count
-                ),
-            ))));
+            return Err(PerfSightError::FolderNotEmpty {
+                reports: stats.comparison_count,
+                folders: stats.child_folder_count,
+            });
         }
         let parent = p.rsplit_once('/').map(|(a, _)| a.to_string()).unwrap_or_else(|| "".to_string());
         let dest = match strat {
@@ -1278,9 +2938,423 @@ impl Database {
         Self::set_comparison_meta_folder_path(&mut meta, &folder_path);
 
         let meta_json = serde_json::to_string(&meta).unwrap_or_else(|_| "{}".to_string());
-        conn.execute(
+        let changed = conn.execute(
             "UPDATE comparisons SET folder_path = ?1, meta_json = ?2 WHERE id = ?3",
             params![folder_path, meta_json, id],
-        )
+        )?;
+        Self::index_tags(&conn, TAG_TABLES[1], id, &Self::extract_tags_from_comparison_meta(&meta))?;
+        Ok(changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CollectionMeta;
+
+    fn memory_db() -> Database {
+        Database::new(":memory:").expect("in-memory database")
+    }
+
+    fn meta_in(folder: &str) -> ReportMeta {
+        ReportMeta {
+            collection: Some(CollectionMeta { folder_path: Some(folder.to_string()), ..Default::default() }),
+            ..Default::default()
+        }
+    }
+
+    fn folder_rows(db: &Database, table: &str) -> Vec<String> {
+        let conn = db.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!("SELECT path FROM {} ORDER BY path", table)).unwrap();
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0)).unwrap();
+        rows.collect::<Result<_>>().unwrap()
+    }
+
+    #[test]
+    fn maintenance_deletes_unmatched_folder_rows_without_recreating_any() {
+        let db = memory_db();
+        db.save_report("filed", &Vec::new(), &meta_in("Filed/Deep")).unwrap();
+        db.create_folder("", "Kept").unwrap();
+        {
+            let conn = db.conn.lock().unwrap();
+            for path in ["/Broken//", "Also broken/"] {
+                conn.execute("INSERT INTO folders (path, created_at) VALUES (?1, '')", params![path]).unwrap();
+            }
+            conn.execute("INSERT INTO comparison_folders (path, created_at) VALUES (' x ', '')", []).unwrap();
+            for path in ["Kept", "Filed", "Gone"] {
+                conn.execute(
+                    "INSERT INTO folder_settings (path, settings_json, updated_at) VALUES (?1, '{}', '')",
+                    params![path],
+                )
+                .unwrap();
+            }
+        }
+
+        let report = db.maintain().unwrap();
+
+        assert!(report.vacuumed);
+        // Folders that only exist through their reports get no row of their own.
+        assert_eq!(folder_rows(&db, "folders"), vec!["Kept".to_string()]);
+        assert!(folder_rows(&db, "comparison_folders").is_empty());
+        assert_eq!(folder_rows(&db, "folder_settings"), vec!["Filed".to_string(), "Kept".to_string()]);
+        assert_eq!(report.fixes.len(), 4, "{:?}", report.fixes);
+        assert!(db.list_folder_paths().unwrap().iter().any(|f| f.path == "Filed/Deep"));
+
+        // A second run has nothing left to fix.
+        assert!(db.maintain().unwrap().fixes.is_empty());
+    }
+
+    #[test]
+    fn listing_leaves_missing_sparklines_to_the_background_backfill() {
+        let db = memory_db();
+        let id = db.save_report("old", &Vec::new(), &meta_in("")).unwrap();
+        db.conn.lock().unwrap().execute("UPDATE reports SET summary_json = NULL", []).unwrap();
+
+        assert!(db.get_all_reports().unwrap()[0].sparkline.is_none());
+        assert!(db.cached_score(id).unwrap().is_none());
+
+        // Only one backfill runs at a time.
+        db.sparkline_backfill.store(true, Ordering::SeqCst);
+        assert!(db.backfill_sparklines_once(2).is_none());
+        db.sparkline_backfill.store(false, Ordering::SeqCst);
+
+        assert_eq!(db.backfill_sparklines_once(2).unwrap().unwrap(), 1);
+        assert!(db.get_all_reports().unwrap()[0].sparkline.is_some());
+        assert_eq!(db.backfill_sparklines_once(2).unwrap().unwrap(), 0);
+    }
+
+    // Summaries written before the headline stats existed are recomputed, not averaged in as 0.
+    #[test]
+    fn folder_aggregate_backfills_summaries_without_headline_stats() {
+        let db = memory_db();
+        let ids: Vec<i64> = crate::sample_data::reports(chrono::Utc::now())
+            .into_iter()
+            .map(|r| db.save_report(&r.title, &r.metrics, &meta_in("Runs")).unwrap())
+            .collect();
+        let fresh = db.get_folder_aggregate("Runs", 2).unwrap();
+        assert!(fresh.avg_p95_cpu.unwrap() > 0.0 && fresh.avg_max_mem_mb.unwrap() > 0.0);
+
+        {
+            let conn = db.conn.lock().unwrap();
+            let old: String = conn.query_row("SELECT summary_json FROM reports WHERE id = ?1", [ids[0]], |row| row.get(0)).unwrap();
+            let mut old: Value = serde_json::from_str(&old).unwrap();
+            let fields = old.as_object_mut().unwrap();
+            fields.remove("p95_cpu");
+            fields.remove("max_mem_mb");
+            conn.execute("UPDATE reports SET summary_json = ?1 WHERE id = ?2", params![old.to_string(), ids[0]]).unwrap();
+            conn.execute("UPDATE reports SET summary_json = 'not json' WHERE id = ?1", [ids[1]]).unwrap();
+        }
+
+        let backfilled = db.get_folder_aggregate("Runs", 2).unwrap();
+        // Allow for float rounding between the save and backfill paths.
+        assert!((backfilled.avg_p95_cpu.unwrap() - fresh.avg_p95_cpu.unwrap()).abs() < 1e-3);
+        assert!((backfilled.avg_max_mem_mb.unwrap() - fresh.avg_max_mem_mb.unwrap()).abs() < 1e-3);
+        assert_eq!(backfilled.avg_score, fresh.avg_score);
+        // Nothing is left to recompute.
+        assert_eq!(db.backfill_sparklines_once(2).unwrap().unwrap(), 0);
+    }
+
+    fn stored_meta(db: &Database, id: i64) -> Value {
+        let conn = db.conn.lock().unwrap();
+        let raw: String = conn.query_row("SELECT meta_json FROM reports WHERE id = ?1", params![id], |row| row.get(0)).unwrap();
+        serde_json::from_str(&raw).unwrap()
+    }
+
+    #[test]
+    fn renaming_a_folder_leaves_malformed_meta_fields_alone() {
+        let db = memory_db();
+        let id = db.save_report("r", &Vec::new(), &meta_in("Old")).unwrap();
+        let malformed = serde_json::json!({
+            "collection": { "folder_path": "Old", "interval_ms": "fast", "target_pids": "1,2" },
+            "folder_path": "Old",
+            "budgets": "strict",
+            "process_aliases": { "1": "main" },
+        });
+        db.conn
+            .lock()
+            .unwrap()
+            .execute("UPDATE reports SET meta_json = ?1 WHERE id = ?2", params![malformed.to_string(), id])
+            .unwrap();
+
+        assert_eq!(db.rename_folder("Old", "New", FolderConflict::Fail).unwrap(), "New");
+
+        let mut expected = malformed;
+        expected["collection"]["folder_path"] = Value::from("New");
+        expected["folder_path"] = Value::from("New");
+        assert_eq!(stored_meta(&db, id), expected);
+    }
+
+    // Stamp a report's cached sparkline and analysis as produced by an older analyzer.
+    fn age_analysis(db: &Database, id: i64) {
+        let conn = db.conn.lock().unwrap();
+        for column in ["summary_json", "analysis_json"] {
+            conn.execute(
+                &format!("UPDATE reports SET {0} = json_set({0}, '$.analyzer_version', ?1) WHERE id = ?2", column),
+                params![analysis::ANALYZER_VERSION - 1, id],
+            )
+            .unwrap();
+        }
+    }
+
+    fn summary_of(db: &Database, id: i64) -> String {
+        let conn = db.conn.lock().unwrap();
+        conn.query_row("SELECT summary_json FROM reports WHERE id = ?1", params![id], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn reanalysis_recomputes_only_stale_reports() {
+        let db = memory_db();
+        let mut ids = Vec::new();
+        for _ in 0..3 {
+            for sample in crate::sample_data::reports(chrono::Utc::now()) {
+                let id = db.save_report(&sample.title, &sample.metrics, &sample.meta).unwrap();
+                let detail = db.get_report_detail(id).unwrap();
+                db.cache_analysis(id, &analysis::analyze_report(&sample.metrics, &detail.meta)).unwrap();
+                ids.push(id);
+            }
+        }
+        let stale = vec![ids[1], ids[4]];
+        for id in &stale {
+            age_analysis(&db, *id);
+        }
+        let fresh_before: Vec<String> = ids.iter().filter(|id| !stale.contains(id)).map(|id| summary_of(&db, *id)).collect();
+
+        assert_eq!(db.reports_to_reanalyze(&ReanalyzeScope::All, false).unwrap(), stale);
+        assert_eq!(db.reports_to_reanalyze(&ReanalyzeScope::Ids { ids: vec![ids[0], ids[4]] }, false).unwrap(), vec![ids[4]]);
+        assert_eq!(db.reports_to_reanalyze(&ReanalyzeScope::Ids { ids: vec![ids[0]] }, true).unwrap(), vec![ids[0]]);
+
+        for id in db.reports_to_reanalyze(&ReanalyzeScope::All, false).unwrap() {
+            let r = db.reanalysis(id).unwrap();
+            db.store_reanalysis(&r).unwrap();
+            // The listing's score and the report's analysis come from the same computation.
+            let (score, version) = db.cached_score(id).unwrap().unwrap();
+            assert_eq!((score, version), (r.score, analysis::ANALYZER_VERSION));
+        }
+
+        assert!(db.reports_to_reanalyze(&ReanalyzeScope::All, false).unwrap().is_empty());
+        let fresh_after: Vec<String> = ids.iter().filter(|id| !stale.contains(id)).map(|id| summary_of(&db, *id)).collect();
+        assert_eq!(fresh_before, fresh_after);
+    }
+
+    // (folder_path, merge entries in meta) of every row of `table`, by id.
+    fn placements(db: &Database, table: &str) -> Vec<(String, usize)> {
+        let conn = db.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!("SELECT folder_path, meta_json FROM {} ORDER BY id", table)).unwrap();
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))).unwrap();
+        rows.map(|r| {
+            let (folder, meta) = r.unwrap();
+            let meta: Value = serde_json::from_str(&meta).unwrap();
+            (folder, meta["folder_merges"].as_array().map_or(0, |m| m.len()))
+        })
+        .collect()
+    }
+
+    fn add_comparison(db: &Database, folder: &str) {
+        let none = serde_json::json!({});
+        db.create_comparison("cmp", &[], folder, None, &none, &none, None, &none).unwrap();
+    }
+
+    #[test]
+    fn renaming_a_folder_onto_itself_changes_nothing() {
+        let db = memory_db();
+        db.save_report("a", &Vec::new(), &meta_in("A/Sub")).unwrap();
+        db.create_folder("", "A").unwrap();
+        add_comparison(&db, "A");
+        db.create_comparison_folder("", "A").unwrap();
+        for conflict in [FolderConflict::Fail, FolderConflict::Merge] {
+            assert_eq!(db.rename_folder("A", "A", conflict).unwrap(), "A");
+            assert_eq!(db.rename_comparison_folder("/A/", " A ", conflict).unwrap(), "A");
+        }
+        assert_eq!(placements(&db, "reports"), [("A/Sub".to_string(), 0)]);
+        assert_eq!(placements(&db, "comparisons"), [("A".to_string(), 0)]);
+        assert_eq!(folder_rows(&db, "folders"), ["A"]);
+        assert_eq!(folder_rows(&db, "comparison_folders"), ["A"]);
+    }
+
+    #[test]
+    fn renaming_onto_an_existing_empty_folder_takes_it_over() {
+        let db = memory_db();
+        db.save_report("a", &Vec::new(), &meta_in("A")).unwrap();
+        db.save_report("a2", &Vec::new(), &meta_in("A/Sub")).unwrap();
+        db.create_folder("", "B").unwrap();
+        add_comparison(&db, "A");
+        db.create_comparison_folder("", "B").unwrap();
+
+        // No conflict to report, and nothing recorded as a merge.
+        assert_eq!(db.rename_folder("A", "B", FolderConflict::Fail).unwrap(), "B");
+        assert_eq!(db.rename_comparison_folder("A", "B", FolderConflict::Fail).unwrap(), "B");
+        assert_eq!(placements(&db, "reports"), [("B".to_string(), 0), ("B/Sub".to_string(), 0)]);
+        assert_eq!(placements(&db, "comparisons"), [("B".to_string(), 0)]);
+        assert_eq!(folder_rows(&db, "folders"), ["B"]);
+        assert_eq!(folder_rows(&db, "comparison_folders"), ["B"]);
+    }
+
+    #[test]
+    fn renaming_onto_a_nonempty_folder_fails_whole_or_merges_on_request() {
+        let db = memory_db();
+        db.save_report("a", &Vec::new(), &meta_in("A")).unwrap();
+        db.save_report("b", &Vec::new(), &meta_in("B")).unwrap();
+        db.save_report("b2", &Vec::new(), &meta_in("B/Sub")).unwrap();
+        db.create_folder("", "A").unwrap();
+        db.create_folder("", "B").unwrap();
+        db.create_folder("B", "Sub").unwrap();
+        add_comparison(&db, "A");
+        add_comparison(&db, "B");
+        db.create_comparison_folder("", "A").unwrap();
+        db.create_comparison_folder("", "B").unwrap();
+
+        match db.rename_folder("A", "B", FolderConflict::Fail) {
+            Err(PerfSightError::FolderExists { path, reports, folders }) => {
+                assert_eq!((path.as_str(), reports, folders), ("B", 2, 1));
+            }
+            other => panic!("expected FolderExists, got {:?}", other),
+        }
+        match db.rename_comparison_folder("A", "B", FolderConflict::Fail) {
+            Err(PerfSightError::FolderExists { path, reports, folders }) => {
+                assert_eq!((path.as_str(), reports, folders), ("B", 1, 0));
+            }
+            other => panic!("expected FolderExists, got {:?}", other),
+        }
+        // A refused rename leaves everything where it was.
+        assert_eq!(placements(&db, "reports")[0], ("A".to_string(), 0));
+        assert_eq!(placements(&db, "comparisons")[0], ("A".to_string(), 0));
+        assert_eq!(folder_rows(&db, "folders"), ["A", "B", "B/Sub"]);
+
+        assert_eq!(db.rename_folder("A", "B", FolderConflict::Merge).unwrap(), "B");
+        assert_eq!(db.rename_comparison_folder("A", "B", FolderConflict::Merge).unwrap(), "B");
+        // Only the moved items record the merge; the destination keeps one folder row.
+        assert_eq!(
+            placements(&db, "reports"),
+            [("B".to_string(), 1), ("B".to_string(), 0), ("B/Sub".to_string(), 0)]
+        );
+        assert_eq!(placements(&db, "comparisons"), [("B".to_string(), 1), ("B".to_string(), 0)]);
+        assert_eq!(folder_rows(&db, "folders"), ["B", "B/Sub"]);
+        assert_eq!(folder_rows(&db, "comparison_folders"), ["B"]);
+    }
+
+    fn comparison_with_meta(db: &Database, meta: Value) -> i64 {
+        let none = serde_json::json!({});
+        db.create_comparison("cmp", &[], "", None, &none, &none, None, &meta).unwrap()
+    }
+
+    fn comparison_tags(db: &Database, id: i64) -> Vec<String> {
+        db.get_all_comparisons(None).unwrap().into_iter().find(|c| c.id == id).unwrap().tags
+    }
+
+    // Older comparisons store tags as a CSV string, in `meta.tags` or copied from a report's
+    // `test_context`; they read, count and filter like array tags.
+    #[test]
+    fn comparison_csv_string_tags_are_read_like_arrays() {
+        let db = memory_db();
+        let csv = comparison_with_meta(&db, serde_json::json!({ "tags": " perf, Nightly ,,perf" }));
+        let copied = comparison_with_meta(&db, serde_json::json!({ "test_context": { "tags": "nightly,release" } }));
+        let both = comparison_with_meta(&db, serde_json::json!({ "tags": ["Perf"], "test_context": { "tags": "smoke, PERF" } }));
+
+        assert_eq!(comparison_tags(&db, csv), vec!["perf", "Nightly"]);
+        assert_eq!(comparison_tags(&db, copied), vec!["nightly", "release"]);
+        assert_eq!(comparison_tags(&db, both), vec!["Perf", "smoke"]);
+
+        let known: Vec<(String, u64)> =
+            db.get_known_comparison_tags().unwrap().into_iter().map(|t| (t.tag.to_lowercase(), t.count)).collect();
+        assert_eq!(
+            known,
+            vec![("nightly".to_string(), 2), ("perf".to_string(), 2), ("release".to_string(), 1), ("smoke".to_string(), 1)]
+        );
+        let ids = |tag: &str| db.get_all_comparisons(Some(tag)).unwrap().iter().map(|c| c.id).collect::<Vec<_>>();
+        assert_eq!(ids("NIGHTLY"), vec![copied, csv]);
+        assert_eq!(ids(" perf "), vec![both, csv]);
+        assert_eq!(ids("missing"), Vec::<i64>::new());
+        assert_eq!(db.get_all_comparisons(Some("  ")).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn updating_comparison_tags_normalizes_and_drops_copied_csv_tags() {
+        let db = memory_db();
+        let id = comparison_with_meta(&db, serde_json::json!({ "tags": "old", "test_context": { "tags": "nightly, release", "build": "42" } }));
+        let tags = vec![" Smoke ".to_string(), "smoke".to_string(), String::new(), "nightly".to_string()];
+
+        assert_eq!(db.update_comparison_tags(id, &tags).unwrap(), vec!["Smoke", "nightly"]);
+        assert_eq!(comparison_tags(&db, id), vec!["Smoke", "nightly"]);
+        let detail = db.get_comparison_detail(id).unwrap();
+        assert_eq!(detail.meta["tags"], serde_json::json!(["Smoke", "nightly"]));
+        assert_eq!(detail.meta["test_context"], serde_json::json!({ "build": "42" }));
+        assert!(db.get_all_comparisons(Some("release")).unwrap().is_empty());
+        assert_eq!(db.get_all_comparisons(Some("smoke")).unwrap().len(), 1);
+    }
+
+    fn report_folders(db: &Database) -> Vec<String> {
+        let mut folders: Vec<String> = db.get_all_reports().unwrap().into_iter().map(|r| r.folder_path).collect();
+        folders.sort();
+        folders
+    }
+
+    // LIKE wildcards in folder names match only themselves: "100%" is not a prefix of "1000",
+    // and "a_b" is not "axb".
+    #[test]
+    fn folder_stats_and_listing_treat_wildcards_literally() {
+        let db = memory_db();
+        for folder in ["100%", "100%/Sub", "100_", "1000", "a_b", "axb", r"w\x", "wx", "🚀 launch"] {
+            db.save_report(folder, &Vec::new(), &meta_in(folder)).unwrap();
+            db.ensure_folder_path(folder).unwrap();
+        }
+        let stats = |p: &str| {
+            let s = db.get_folder_stats(p).unwrap();
+            (s.report_count, s.child_folder_count)
+        };
+        assert_eq!(stats("100%"), (2, 1));
+        assert_eq!(stats("100_"), (1, 0));
+        assert_eq!(stats("a_b"), (1, 0));
+        assert_eq!(stats("%"), (0, 0));
+        assert_eq!(stats("_"), (0, 0));
+        // A backslash is a separator, so "w\x" was filed as "w/x".
+        assert_eq!(stats("w"), (1, 1));
+        assert_eq!(stats("🚀 launch"), (1, 0));
+        assert_eq!(db.report_ids_in_folder("100%", true).unwrap().len(), 2);
+        assert_eq!(db.report_ids_in_folder("a_b", true).unwrap().len(), 1);
+        assert_eq!(db.report_ids_in_folder("%", true).unwrap(), Vec::<i64>::new());
+    }
+
+    #[test]
+    fn renaming_a_folder_with_wildcards_moves_only_its_own_tree() {
+        let db = memory_db();
+        for folder in ["100%", "100%/Sub", "100_", "1000", "100%x"] {
+            db.save_report(folder, &Vec::new(), &meta_in(folder)).unwrap();
+            db.ensure_folder_path(folder).unwrap();
+        }
+        assert_eq!(db.rename_folder("100%", "50%_off", FolderConflict::Fail).unwrap(), "50%_off");
+        assert_eq!(report_folders(&db), vec!["100%x", "1000", "100_", "50%_off", "50%_off/Sub"]);
+        assert_eq!(folder_rows(&db, "folders"), vec!["100%x", "1000", "100_", "50%_off", "50%_off/Sub"]);
+        let moved = db.report_ids_in_folder("50%_off", true).unwrap();
+        assert_eq!(moved.len(), 2);
+        for id in moved {
+            assert!(db.get_report_detail(id).unwrap().meta.folder_path().starts_with("50%_off"));
+        }
+
+        // The new name is normalized like any path: no traversal or control characters survive.
+        assert_eq!(db.rename_folder("100_", "../x\u{7}y", FolderConflict::Fail).unwrap(), "xy");
+        assert_eq!(db.get_folder_stats("xy").unwrap().report_count, 1);
+        assert_eq!(db.get_folder_stats("100_").unwrap().report_count, 0);
+    }
+
+    #[test]
+    fn hostile_folder_paths_are_filed_normalized() {
+        let db = memory_db();
+        let long = "x".repeat(300);
+        let deep = vec!["d"; 40].join("/");
+        for folder in ["../../etc", "A/./B/../C", "tab\there/\u{1b}[31mred", long.as_str(), deep.as_str()] {
+            db.save_report(folder, &Vec::new(), &meta_in(folder)).unwrap();
+        }
+        let expected_deep = vec!["d"; crate::models::MAX_FOLDER_DEPTH].join("/");
+        let mut expected = vec![
+            "A/B/C".to_string(),
+            expected_deep.clone(),
+            "etc".to_string(),
+            "tabhere/[31mred".to_string(),
+            "x".repeat(crate::models::MAX_FOLDER_NAME_CHARS),
+        ];
+        expected.sort();
+        assert_eq!(report_folders(&db), expected);
+        assert_eq!(db.get_folder_stats(&deep).unwrap().path, expected_deep);
+        assert_eq!(db.get_folder_stats(&long).unwrap().report_count, 1);
     }
 }
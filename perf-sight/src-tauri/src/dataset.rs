@@ -0,0 +1,326 @@
+use std::collections::BTreeSet;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use crate::database::ReportDetail;
+use crate::error::PerfSightError;
+use crate::models::{DataSourceInfo, MetricPoint, MetricSelection, ReportMeta, RunEvent};
+
+/// Newest dataset layout this build writes and fully reads.
+pub const DATASET_SCHEMA_VERSION: u32 = 2;
+// Oldest layout still read.
+const DATASET_MIN_VERSION: u32 = 1;
+
+// Fields each layout level is known to have; anything else in a newer dataset is reported.
+const KNOWN_TOP_FIELDS: &[&str] =
+    &["schema_version", "exported_at", "filter", "report", "events", "artifacts", "data_sources", "coverage"];
+const KNOWN_REPORT_FIELDS: &[&str] = &["id", "created_at", "title", "metrics", "analysis", "meta"];
+const KNOWN_BATCH_FIELDS: &[&str] = &["timestamp", "metrics"];
+// Batches checked for unknown sample fields; newer fields show up in the first samples.
+const SAMPLE_SCAN_BATCHES: usize = 100;
+
+/// A report exported as JSON. Version 1 is `schema_version`, `exported_at`, `filter` and `report`.
+/// Version 2 adds the `header` fields next to them, copied out of `report.meta` so tools can
+/// read them without knowing the meta layout; version 1 readers ignore them.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReportDataset {
+    pub schema_version: u32,
+    pub exported_at: String,
+    /// Set when only part of the report was exported; the samples are partial.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<MetricSelection>,
+    pub report: ReportDetail,
+    #[serde(flatten)]
+    pub header: DatasetHeaderV2,
+}
+
+/// Top-level fields added in schema_version 2 (all empty in a version 1 dataset).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DatasetHeaderV2 {
+    /// The run's timeline (`meta.events`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<RunEvent>,
+    /// Files exported alongside the dataset.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub artifacts: Vec<DatasetArtifact>,
+    /// Where the samples came from (`collection.data_sources`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub data_sources: Vec<DataSourceInfo>,
+    /// Per-PID sample coverage (`collection.coverage`), keyed by PID.
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub coverage: Map<String, Value>,
+}
+
+/// A file exported with a dataset, e.g. the report PDF in a bundle.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatasetArtifact {
+    /// "pdf", ...
+    pub kind: String,
+    /// Relative to the dataset's directory.
+    pub path: String,
+}
+
+impl DatasetHeaderV2 {
+    /// The header for `meta` in layout `version`; empty below version 2.
+    pub fn for_version(version: u32, meta: &ReportMeta, artifacts: Vec<DatasetArtifact>) -> Self {
+        if version < 2 {
+            return Self::default();
+        }
+        Self {
+            events: meta.events.clone(),
+            artifacts,
+            data_sources: meta.data_sources(),
+            coverage: meta
+                .collection
+                .as_ref()
+                .and_then(|c| c.extra.get("coverage"))
+                .and_then(|c| c.as_object())
+                .cloned()
+                .unwrap_or_default(),
+        }
+    }
+
+    // Fill what the report's meta lacks (e.g. a tool trimmed it); meta wins where both have data.
+    fn merge_into(self, meta: &mut ReportMeta) {
+        if meta.events.is_empty() {
+            meta.events = self.events;
+        }
+        let collection = meta.collection.get_or_insert_with(Default::default);
+        if !self.data_sources.is_empty() && !collection.extra.contains_key("data_sources") {
+            collection.extra.insert("data_sources".to_string(), json!(self.data_sources));
+        }
+        if !self.coverage.is_empty() && !collection.extra.contains_key("coverage") {
+            collection.extra.insert("coverage".to_string(), Value::Object(self.coverage));
+        }
+        if !self.artifacts.is_empty() {
+            meta.extra.insert("dataset_artifacts".to_string(), json!(self.artifacts));
+        }
+    }
+}
+
+/// Layout to write: `target_version` when given (1 or 2), else the latest.
+pub fn target_version(target_version: Option<u32>) -> Result<u32, PerfSightError> {
+    match target_version {
+        None => Ok(DATASET_SCHEMA_VERSION),
+        Some(v) if (DATASET_MIN_VERSION..=DATASET_SCHEMA_VERSION).contains(&v) => Ok(v),
+        Some(v) => Err(PerfSightError::invalid_input(
+            "target_version",
+            format!("must be {}..={}, got {}", DATASET_MIN_VERSION, DATASET_SCHEMA_VERSION, v),
+        )),
+    }
+}
+
+/// What a dataset from a newer build carried that this build could not use.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatasetCompatWarning {
+    pub schema_version: u64,
+    pub supported_version: u32,
+    /// Dotted paths, e.g. "report.baseline" or "report.metrics[].metrics.<pid>.power_watts".
+    pub ignored_fields: Vec<String>,
+}
+
+/// A dataset read by `read_dataset`.
+pub struct ParsedDataset {
+    pub report: ReportDetail,
+    /// Set for datasets newer than `DATASET_SCHEMA_VERSION`.
+    pub warning: Option<DatasetCompatWarning>,
+}
+
+/// Read a dataset of any version from 1 up. Versions 1 and 2 are read fully; a newer one is read
+/// as far as its fields are known, with a warning listing the rest (also kept as
+/// `meta.dataset_compat`). The applied export filter is kept as `meta.dataset_filter`.
+pub fn read_dataset(mut v: Value) -> Result<ParsedDataset, PerfSightError> {
+    let version = v.get("schema_version").and_then(Value::as_u64).unwrap_or(0);
+    if version < DATASET_MIN_VERSION as u64 {
+        return Err(PerfSightError::invalid_input(
+            "schema_version",
+            format!("unsupported dataset schema_version {}", version),
+        ));
+    }
+    let warning = (version > DATASET_SCHEMA_VERSION as u64).then(|| DatasetCompatWarning {
+        schema_version: version,
+        supported_version: DATASET_SCHEMA_VERSION,
+        ignored_fields: ignored_fields(&v),
+    });
+    let report_v = v
+        .get_mut("report")
+        .map(Value::take)
+        .ok_or_else(|| PerfSightError::invalid_input("report", "missing"))?;
+    let mut report: ReportDetail =
+        serde_json::from_value(report_v).map_err(|e| PerfSightError::invalid_input("report", e.to_string()))?;
+    // Keep the partial-export marker with the report; analysis only ever sees the included samples.
+    if let Some(filter) = v.get("filter").filter(|f| !f.is_null()) {
+        report.meta.extra.insert("dataset_filter".to_string(), filter.clone());
+    }
+    if version >= 2 {
+        let header: Map<String, Value> = ["events", "artifacts", "data_sources", "coverage"]
+            .iter()
+            .filter_map(|k| v.get_mut(*k).map(|x| (k.to_string(), x.take())))
+            .collect();
+        let header: DatasetHeaderV2 = serde_json::from_value(Value::Object(header)).unwrap_or_default();
+        header.merge_into(&mut report.meta);
+    }
+    if let Some(w) = &warning {
+        report.meta.extra.insert("dataset_compat".to_string(), json!(w));
+    }
+    Ok(ParsedDataset { report, warning })
+}
+
+// Fields of `v` this build does not read: unknown keys at the top, in the report, in the batches
+// and (non-null) in the samples. Unknown meta keys are kept as-is, so they are not listed.
+fn ignored_fields(v: &Value) -> Vec<String> {
+    let unknown = |obj: Option<&Map<String, Value>>, known: &[&str], prefix: &str, out: &mut BTreeSet<String>| {
+        for k in obj.into_iter().flat_map(|o| o.keys()) {
+            if !known.contains(&k.as_str()) {
+                out.insert(format!("{}{}", prefix, k));
+            }
+        }
+    };
+    let mut out = BTreeSet::new();
+    unknown(v.as_object(), KNOWN_TOP_FIELDS, "", &mut out);
+    let report = v.get("report");
+    unknown(report.and_then(Value::as_object), KNOWN_REPORT_FIELDS, "report.", &mut out);
+    let batches = report.and_then(|r| r.get("metrics")).and_then(Value::as_array);
+    for batch in batches.into_iter().flatten().take(SAMPLE_SCAN_BATCHES) {
+        unknown(batch.as_object(), KNOWN_BATCH_FIELDS, "report.metrics[].", &mut out);
+        for point in batch.get("metrics").and_then(Value::as_object).into_iter().flat_map(|m| m.values()) {
+            // Known sample fields are the ones that survive a round trip through MetricPoint.
+            let Some(obj) = point.as_object() else { continue };
+            let kept = serde_json::from_value::<MetricPoint>(point.clone())
+                .ok()
+                .and_then(|p| serde_json::to_value(p).ok());
+            let kept = kept.as_ref().and_then(Value::as_object);
+            for (k, value) in obj {
+                if !value.is_null() && !kept.is_some_and(|m| m.contains_key(k)) {
+                    out.insert(format!("report.metrics[].metrics.<pid>.{}", k));
+                }
+            }
+        }
+    }
+    out.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn report() -> ReportDetail {
+        let sample = crate::sample_data::reports(Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap()).remove(0);
+        let mut meta = sample.meta;
+        meta.events.push(RunEvent {
+            timestamp: Utc.with_ymd_and_hms(2025, 1, 1, 11, 0, 0).unwrap(),
+            kind: crate::models::RunEventKind::Marker,
+            pid: None,
+            detail: json!({ "label": "login" }),
+        });
+        let collection = meta.collection.as_mut().unwrap();
+        collection.extra.insert(
+            "data_sources".to_string(),
+            json!([{ "source": "native", "backend": "simulate", "samples": 540 }]),
+        );
+        collection.extra.insert("coverage".to_string(), json!({ "80000": { "samples": 180 } }));
+        ReportDetail {
+            id: 7,
+            created_at: "2025-01-01T00:00:00+00:00".to_string(),
+            title: sample.title,
+            metrics: sample.metrics,
+            analysis: None,
+            meta,
+        }
+    }
+
+    fn write(version: u32, report: ReportDetail) -> Value {
+        let header = DatasetHeaderV2::for_version(
+            version,
+            &report.meta,
+            vec![DatasetArtifact { kind: "pdf".to_string(), path: "report_7.pdf".to_string() }],
+        );
+        let dataset = ReportDataset {
+            schema_version: version,
+            exported_at: "2025-01-02T00:00:00+00:00".to_string(),
+            filter: None,
+            report,
+            header,
+        };
+        serde_json::from_str(&serde_json::to_string(&dataset).unwrap()).unwrap()
+    }
+
+    fn assert_same_report(read: &ReportDetail, written: &ReportDetail) {
+        assert_eq!(read.created_at, written.created_at);
+        assert_eq!(read.title, written.title);
+        assert_eq!(serde_json::to_value(&read.metrics).unwrap(), serde_json::to_value(&written.metrics).unwrap());
+        assert_eq!(read.meta.events.len(), written.meta.events.len());
+        assert_eq!(read.meta.data_sources().len(), 1);
+        assert_eq!(read.meta.collection.as_ref().unwrap().extra["coverage"], json!({ "80000": { "samples": 180 } }));
+    }
+
+    #[test]
+    fn version_1_round_trips_without_header_fields() {
+        let v = write(1, report());
+        for field in ["events", "artifacts", "data_sources", "coverage"] {
+            assert!(v.get(field).is_none(), "v1 dataset has {}", field);
+        }
+        let parsed = read_dataset(v).unwrap();
+        assert!(parsed.warning.is_none());
+        assert_same_report(&parsed.report, &report());
+        assert!(!parsed.report.meta.extra.contains_key("dataset_artifacts"));
+    }
+
+    #[test]
+    fn version_2_round_trips_and_refills_trimmed_meta() {
+        let v = write(2, report());
+        assert_eq!(v["events"].as_array().unwrap().len(), 1);
+        assert_eq!(v["data_sources"][0]["backend"], "simulate");
+        assert_eq!(v["artifacts"], json!([{ "kind": "pdf", "path": "report_7.pdf" }]));
+        let parsed = read_dataset(v.clone()).unwrap();
+        assert!(parsed.warning.is_none());
+        assert_same_report(&parsed.report, &report());
+        assert_eq!(parsed.report.meta.extra["dataset_artifacts"], v["artifacts"]);
+
+        // A tool that dropped the run details from meta still gets them from the header.
+        let mut trimmed = v;
+        trimmed["report"]["meta"]["events"] = json!([]);
+        let collection = trimmed["report"]["meta"]["collection"].as_object_mut().unwrap();
+        collection.remove("data_sources");
+        collection.remove("coverage");
+        let parsed = read_dataset(trimmed).unwrap();
+        assert_same_report(&parsed.report, &report());
+    }
+
+    #[test]
+    fn newer_versions_import_known_fields_and_list_the_rest() {
+        let mut v = write(2, report());
+        v["schema_version"] = json!(3);
+        v["signature"] = json!("abc");
+        v["report"]["baseline_id"] = json!(3);
+        v["report"]["metrics"][0]["seq"] = json!(1);
+        let pid = v["report"]["metrics"][0]["metrics"].as_object().unwrap().keys().next().unwrap().clone();
+        v["report"]["metrics"][0]["metrics"][&pid]["power_watts"] = json!(4.5);
+        v["report"]["metrics"][0]["metrics"][&pid]["thermal_state"] = Value::Null;
+
+        let parsed = read_dataset(v).unwrap();
+        let warning = parsed.warning.expect("compat warning");
+        assert_eq!(warning.schema_version, 3);
+        assert_eq!(warning.supported_version, DATASET_SCHEMA_VERSION);
+        assert_eq!(
+            warning.ignored_fields,
+            vec!["report.baseline_id", "report.metrics[].metrics.<pid>.power_watts", "report.metrics[].seq", "signature"]
+        );
+        assert_same_report(&parsed.report, &report());
+        assert_eq!(parsed.report.meta.extra["dataset_compat"]["ignored_fields"].as_array().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn unversioned_or_broken_datasets_are_refused() {
+        let mut v = write(1, report());
+        v.as_object_mut().unwrap().remove("schema_version");
+        assert!(read_dataset(v).is_err());
+        assert!(read_dataset(json!({ "schema_version": 2 })).is_err());
+        assert!(read_dataset(json!({ "schema_version": 1, "report": { "id": "x" } })).is_err());
+
+        assert_eq!(target_version(None).unwrap(), DATASET_SCHEMA_VERSION);
+        assert_eq!(target_version(Some(1)).unwrap(), 1);
+        assert!(target_version(Some(0)).is_err());
+        assert!(target_version(Some(DATASET_SCHEMA_VERSION + 1)).is_err());
+    }
+}
@@ -0,0 +1,344 @@
+use std::fmt;
+use serde::ser::{Serialize, SerializeMap, Serializer};
+
+/// Error returned by every Tauri command. Serialized as an object with a snake_case `kind`,
+/// a human-readable `message`, and any variant fields, e.g.
+/// `{"kind":"folder_not_empty","message":"...","reports":3,"folders":1}`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PerfSightError {
+    NotFound,
+    FolderNotEmpty { reports: u64, folders: u64 },
+    /// A rename's destination already holds `reports` items and `folders` sub-folders; retry
+    /// with `on_conflict: "merge"` to combine them.
+    FolderExists { path: String, reports: u64, folders: u64 },
+    InvalidInput { field: String, reason: String },
+    SidecarMissing,
+    /// The sidecar did not acknowledge a start command within `timeout_ms`.
+    SidecarNotResponding { timeout_ms: u64 },
+    CdpUnreachable { endpoint: String },
+    /// A strict browser-mode start needs the extension for the memory of `pids`, and no extension
+    /// is connected and sending.
+    ExtensionNotConnected { pids: Vec<u32> },
+    /// A file or payload over the configured size limit (`max_artifact_mb`).
+    TooLarge { field: String, limit_bytes: u64 },
+    /// The OS refused access to `path`.
+    PermissionDenied { path: String },
+    /// `path` is missing, not a directory, or not representable as UTF-8.
+    InvalidPath { path: String, reason: String },
+    /// The change would touch locked reports; retry with `force` to override.
+    Locked { report_ids: Vec<i64> },
+    /// The operation was stopped by the user before it finished.
+    Cancelled,
+    Database(String),
+    Io(String),
+    /// Anything without a more specific kind (serialization, HTTP, runtime failures).
+    Internal(String),
+}
+
+impl PerfSightError {
+    pub fn invalid_input(field: impl Into<String>, reason: impl Into<String>) -> Self {
+        PerfSightError::InvalidInput {
+            field: field.into(),
+            reason: reason.into(),
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PerfSightError::NotFound => "not_found",
+            PerfSightError::FolderNotEmpty { .. } => "folder_not_empty",
+            PerfSightError::FolderExists { .. } => "folder_exists",
+            PerfSightError::InvalidInput { .. } => "invalid_input",
+            PerfSightError::SidecarMissing => "sidecar_missing",
+            PerfSightError::SidecarNotResponding { .. } => "sidecar_not_responding",
+            PerfSightError::CdpUnreachable { .. } => "cdp_unreachable",
+            PerfSightError::ExtensionNotConnected { .. } => "extension_not_connected",
+            PerfSightError::TooLarge { .. } => "too_large",
+            PerfSightError::PermissionDenied { .. } => "permission_denied",
+            PerfSightError::InvalidPath { .. } => "invalid_path",
+            PerfSightError::Locked { .. } => "locked",
+            PerfSightError::Cancelled => "cancelled",
+            PerfSightError::Database(_) => "database",
+            PerfSightError::Io(_) => "io",
+            PerfSightError::Internal(_) => "internal",
+        }
+    }
+}
+
+impl fmt::Display for PerfSightError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PerfSightError::NotFound => write!(f, "Not found"),
+            PerfSightError::FolderNotEmpty { reports, folders } => {
+                write!(f, "Folder is not empty ({} items, {} subfolders)", reports, folders)
+            }
+            PerfSightError::FolderExists { path, reports, folders } => {
+                write!(f, "Folder {} already exists ({} items, {} subfolders would be merged)", path, reports, folders)
+            }
+            PerfSightError::InvalidInput { field, reason } => write!(f, "Invalid {}: {}", field, reason),
+            PerfSightError::SidecarMissing => write!(f, "Collector sidecar is missing or failed to start"),
+            PerfSightError::SidecarNotResponding { timeout_ms } => {
+                write!(f, "Collector sidecar did not acknowledge the start command within {} ms", timeout_ms)
+            }
+            PerfSightError::CdpUnreachable { endpoint } => {
+                write!(f, "Chrome DevTools endpoint {} is unreachable", endpoint)
+            }
+            PerfSightError::ExtensionNotConnected { pids } => write!(
+                f,
+                "The PerfSight extension is not connected; Chrome reports no memory for {} target process(es) without it",
+                pids.len()
+            ),
+            PerfSightError::TooLarge { field, limit_bytes } => {
+                write!(f, "{} exceeds the size limit of {} MB", field, limit_bytes / (1024 * 1024))
+            }
+            PerfSightError::PermissionDenied { path } => write!(f, "Permission denied: {}", path),
+            PerfSightError::InvalidPath { path, reason } => write!(f, "Invalid path {}: {}", path, reason),
+            PerfSightError::Locked { report_ids } => {
+                write!(f, "{} locked report(s) would be modified; unlock them or force the change", report_ids.len())
+            }
+            PerfSightError::Cancelled => write!(f, "Cancelled"),
+            PerfSightError::Database(msg) => write!(f, "Database error: {}", msg),
+            PerfSightError::Io(msg) => write!(f, "I/O error: {}", msg),
+            PerfSightError::Internal(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for PerfSightError {}
+
+impl Serialize for PerfSightError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("kind", self.kind())?;
+        map.serialize_entry("message", &self.to_string())?;
+        match self {
+            PerfSightError::FolderNotEmpty { reports, folders } => {
+                map.serialize_entry("reports", reports)?;
+                map.serialize_entry("folders", folders)?;
+            }
+            PerfSightError::FolderExists { path, reports, folders } => {
+                map.serialize_entry("path", path)?;
+                map.serialize_entry("reports", reports)?;
+                map.serialize_entry("folders", folders)?;
+            }
+            PerfSightError::InvalidInput { field, reason } => {
+                map.serialize_entry("field", field)?;
+                map.serialize_entry("reason", reason)?;
+            }
+            PerfSightError::SidecarNotResponding { timeout_ms } => map.serialize_entry("timeout_ms", timeout_ms)?,
+            PerfSightError::CdpUnreachable { endpoint } => map.serialize_entry("endpoint", endpoint)?,
+            PerfSightError::ExtensionNotConnected { pids } => map.serialize_entry("pids", pids)?,
+            PerfSightError::TooLarge { field, limit_bytes } => {
+                map.serialize_entry("field", field)?;
+                map.serialize_entry("limit_bytes", limit_bytes)?;
+            }
+            PerfSightError::PermissionDenied { path } => map.serialize_entry("path", path)?,
+            PerfSightError::InvalidPath { path, reason } => {
+                map.serialize_entry("path", path)?;
+                map.serialize_entry("reason", reason)?;
+            }
+            PerfSightError::Locked { report_ids } => map.serialize_entry("report_ids", report_ids)?,
+            PerfSightError::Database(detail) | PerfSightError::Io(detail) | PerfSightError::Internal(detail) => {
+                map.serialize_entry("detail", detail)?
+            }
+            PerfSightError::NotFound | PerfSightError::SidecarMissing | PerfSightError::Cancelled => {}
+        }
+        map.end()
+    }
+}
+
+impl From<rusqlite::Error> for PerfSightError {
+    fn from(e: rusqlite::Error) -> Self {
+        match e {
+            rusqlite::Error::QueryReturnedNoRows => PerfSightError::NotFound,
+            other => PerfSightError::Database(other.to_string()),
+        }
+    }
+}
+
+impl From<std::io::Error> for PerfSightError {
+    fn from(e: std::io::Error) -> Self {
+        PerfSightError::Io(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for PerfSightError {
+    fn from(e: serde_json::Error) -> Self {
+        PerfSightError::Internal(e.to_string())
+    }
+}
+
+// Helpers shared with headless mode still report plain strings.
+impl From<String> for PerfSightError {
+    fn from(msg: String) -> Self {
+        PerfSightError::Internal(msg)
+    }
+}
+
+impl From<&str> for PerfSightError {
+    fn from(msg: &str) -> Self {
+        PerfSightError::Internal(msg.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    // Every variant with its full serialized form. The match in `every_variant_is_listed` fails to
+    // compile when a variant is added without a case here.
+    fn cases() -> Vec<(PerfSightError, Value)> {
+        vec![
+            (PerfSightError::NotFound, json!({ "kind": "not_found", "message": "Not found" })),
+            (
+                PerfSightError::FolderNotEmpty { reports: 3, folders: 1 },
+                json!({
+                    "kind": "folder_not_empty",
+                    "message": "Folder is not empty (3 items, 1 subfolders)",
+                    "reports": 3,
+                    "folders": 1,
+                }),
+            ),
+            (
+                PerfSightError::FolderExists { path: "A/B".to_string(), reports: 2, folders: 0 },
+                json!({
+                    "kind": "folder_exists",
+                    "message": "Folder A/B already exists (2 items, 0 subfolders would be merged)",
+                    "path": "A/B",
+                    "reports": 2,
+                    "folders": 0,
+                }),
+            ),
+            (
+                PerfSightError::invalid_input("interval_ms", "must be at least 100"),
+                json!({
+                    "kind": "invalid_input",
+                    "message": "Invalid interval_ms: must be at least 100",
+                    "field": "interval_ms",
+                    "reason": "must be at least 100",
+                }),
+            ),
+            (
+                PerfSightError::SidecarMissing,
+                json!({ "kind": "sidecar_missing", "message": "Collector sidecar is missing or failed to start" }),
+            ),
+            (
+                PerfSightError::SidecarNotResponding { timeout_ms: 5000 },
+                json!({
+                    "kind": "sidecar_not_responding",
+                    "message": "Collector sidecar did not acknowledge the start command within 5000 ms",
+                    "timeout_ms": 5000,
+                }),
+            ),
+            (
+                PerfSightError::CdpUnreachable { endpoint: "http://127.0.0.1:9222".to_string() },
+                json!({
+                    "kind": "cdp_unreachable",
+                    "message": "Chrome DevTools endpoint http://127.0.0.1:9222 is unreachable",
+                    "endpoint": "http://127.0.0.1:9222",
+                }),
+            ),
+            (
+                PerfSightError::ExtensionNotConnected { pids: vec![10, 11] },
+                json!({
+                    "kind": "extension_not_connected",
+                    "message": "The PerfSight extension is not connected; Chrome reports no memory for 2 target process(es) without it",
+                    "pids": [10, 11],
+                }),
+            ),
+            (
+                PerfSightError::TooLarge { field: "pdf_base64".to_string(), limit_bytes: 50 * 1024 * 1024 },
+                json!({
+                    "kind": "too_large",
+                    "message": "pdf_base64 exceeds the size limit of 50 MB",
+                    "field": "pdf_base64",
+                    "limit_bytes": 52428800,
+                }),
+            ),
+            (
+                PerfSightError::PermissionDenied { path: "/root/out.zip".to_string() },
+                json!({
+                    "kind": "permission_denied",
+                    "message": "Permission denied: /root/out.zip",
+                    "path": "/root/out.zip",
+                }),
+            ),
+            (
+                PerfSightError::InvalidPath { path: "/nope".to_string(), reason: "not a directory".to_string() },
+                json!({
+                    "kind": "invalid_path",
+                    "message": "Invalid path /nope: not a directory",
+                    "path": "/nope",
+                    "reason": "not a directory",
+                }),
+            ),
+            (
+                PerfSightError::Locked { report_ids: vec![4, 9] },
+                json!({
+                    "kind": "locked",
+                    "message": "2 locked report(s) would be modified; unlock them or force the change",
+                    "report_ids": [4, 9],
+                }),
+            ),
+            (PerfSightError::Cancelled, json!({ "kind": "cancelled", "message": "Cancelled" })),
+            (
+                PerfSightError::Database("disk I/O error".to_string()),
+                json!({ "kind": "database", "message": "Database error: disk I/O error", "detail": "disk I/O error" }),
+            ),
+            (
+                PerfSightError::Io("broken pipe".to_string()),
+                json!({ "kind": "io", "message": "I/O error: broken pipe", "detail": "broken pipe" }),
+            ),
+            (
+                PerfSightError::Internal("bad JSON".to_string()),
+                json!({ "kind": "internal", "message": "bad JSON", "detail": "bad JSON" }),
+            ),
+        ]
+    }
+
+    #[test]
+    fn every_variant_serializes_its_kind_message_and_fields() {
+        for (error, expected) in cases() {
+            assert_eq!(serde_json::to_value(&error).unwrap(), expected, "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn every_variant_is_listed() {
+        let listed: Vec<&str> = cases().iter().map(|(e, _)| e.kind()).collect();
+        for (error, _) in cases() {
+            let kind = match error {
+                PerfSightError::NotFound
+                | PerfSightError::FolderNotEmpty { .. }
+                | PerfSightError::FolderExists { .. }
+                | PerfSightError::InvalidInput { .. }
+                | PerfSightError::SidecarMissing
+                | PerfSightError::SidecarNotResponding { .. }
+                | PerfSightError::CdpUnreachable { .. }
+                | PerfSightError::ExtensionNotConnected { .. }
+                | PerfSightError::TooLarge { .. }
+                | PerfSightError::PermissionDenied { .. }
+                | PerfSightError::InvalidPath { .. }
+                | PerfSightError::Locked { .. }
+                | PerfSightError::Cancelled
+                | PerfSightError::Database(_)
+                | PerfSightError::Io(_)
+                | PerfSightError::Internal(_) => error.kind(),
+            };
+            assert_eq!(listed.iter().filter(|k| **k == kind).count(), 1, "{}", kind);
+        }
+        assert_eq!(listed.len(), 16);
+    }
+
+    #[test]
+    fn conversions_pick_the_matching_kind() {
+        assert_eq!(PerfSightError::from(rusqlite::Error::QueryReturnedNoRows), PerfSightError::NotFound);
+        assert_eq!(PerfSightError::from(rusqlite::Error::InvalidQuery).kind(), "database");
+        let io = std::io::Error::other("gone");
+        assert_eq!(PerfSightError::from(io), PerfSightError::Io("gone".to_string()));
+        let json_err = serde_json::from_str::<Value>("{").unwrap_err();
+        assert_eq!(PerfSightError::from(json_err).kind(), "internal");
+        assert_eq!(PerfSightError::from("plain"), PerfSightError::Internal("plain".to_string()));
+    }
+}
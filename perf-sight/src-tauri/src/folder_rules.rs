@@ -0,0 +1,75 @@
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use crate::database::FolderRule;
+use crate::error::PerfSightError;
+use crate::models::{normalize_folder_path, ReportMeta};
+
+/// Where a report would land, and which rule put it there.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FolderRuleMatch {
+    pub rule_id: i64,
+    pub folder_path: String,
+}
+
+pub fn validate_rule(rule: &FolderRule) -> Result<(), PerfSightError> {
+    let tag = rule.match_tag.as_deref().map(str::trim).unwrap_or("");
+    let scenario = rule.match_scenario.as_deref().map(str::trim).unwrap_or("");
+    if tag.is_empty() && scenario.is_empty() {
+        return Err(PerfSightError::invalid_input("match_tag", "set a tag or a scenario pattern"));
+    }
+    if !scenario.is_empty() {
+        Regex::new(scenario).map_err(|e| PerfSightError::invalid_input("match_scenario", e.to_string()))?;
+    }
+    if rule.folder_template.trim().is_empty() {
+        return Err(PerfSightError::invalid_input("folder_template", "cannot be empty"));
+    }
+    Ok(())
+}
+
+// A rule matches when every condition it sets holds (tag: case-insensitive, scenario: regex).
+fn rule_matches(rule: &FolderRule, meta: &ReportMeta) -> bool {
+    if let Some(tag) = rule.match_tag.as_deref().map(str::trim).filter(|t| !t.is_empty()) {
+        if !meta.tags().iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            return false;
+        }
+    }
+    if let Some(pattern) = rule.match_scenario.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+        let Ok(re) = Regex::new(pattern) else { return false };
+        if !meta.scenario_name().is_some_and(|s| re.is_match(s)) {
+            return false;
+        }
+    }
+    true
+}
+
+// Placeholder values can't add folder levels of their own.
+fn segment(value: Option<&str>) -> String {
+    value
+        .map(|v| v.trim().replace('/', "-"))
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Fill `{build_id}`, `{scenario}`, `{mode}` and `{date}` (YYYY-MM-DD, UTC) in a folder template.
+pub fn render_template(template: &str, meta: &ReportMeta, now: DateTime<Utc>) -> String {
+    let rendered = template
+        .replace("{build_id}", &segment(meta.build_id()))
+        .replace("{scenario}", &segment(meta.scenario_name()))
+        .replace("{mode}", &segment(meta.mode()))
+        .replace("{date}", &now.format("%Y-%m-%d").to_string());
+    normalize_folder_path(&rendered)
+}
+
+/// First enabled matching rule wins. `rules` must already be in evaluation order.
+pub fn evaluate(rules: &[FolderRule], meta: &ReportMeta, now: DateTime<Utc>) -> Option<FolderRuleMatch> {
+    rules
+        .iter()
+        .filter(|r| r.enabled)
+        .find(|r| rule_matches(r, meta))
+        .map(|r| FolderRuleMatch {
+            rule_id: r.id,
+            folder_path: render_template(&r.folder_template, meta, now),
+        })
+        .filter(|m| !m.folder_path.is_empty())
+}
@@ -0,0 +1,302 @@
+use std::io::Read;
+use std::thread;
+use tauri::{AppHandle, Manager, State};
+use tiny_http::{Header, Method, Request, Response, Server};
+use serde_json::{json, Value};
+use crate::logging::{log_error, log_info, log_warn};
+use crate::commands::{CollectionState, DataSource, payload_timestamp, process_metric_payload, push_custom_metric, safe_lock};
+use crate::ws_server::{IngestServerState, process_console_log_payload};
+use crate::prometheus;
+use chrono::Utc;
+use crate::database::Database;
+use crate::settings::{Settings, PORT_FALLBACK_TRIES};
+
+// Request bodies are small JSON documents; anything larger is almost certainly a mistake.
+const MAX_BODY_BYTES: u64 = 4 * 1024 * 1024;
+
+fn bind_http_listener_with_fallback(base: u16) -> Option<(Server, u16)> {
+    // Same strategy as the WebSocket listener, on a separate port range.
+    for i in 0..PORT_FALLBACK_TRIES {
+        let Some(port) = base.checked_add(i) else { break };
+        let addr = format!("127.0.0.1:{}", port);
+        match Server::http(&addr) {
+            Ok(s) => return Some((s, port)),
+            Err(e) => {
+                let in_use = e
+                    .downcast_ref::<std::io::Error>()
+                    .map(|io| io.kind() == std::io::ErrorKind::AddrInUse)
+                    .unwrap_or(false);
+                if in_use {
+                    continue;
+                }
+                log_warn!("Failed to bind HTTP ingest server on {}: {}", addr, e);
+                return None;
+            }
+        }
+    }
+    None
+}
+
+fn json_response(status: u16, body: Value) -> Response<std::io::Cursor<Vec<u8>>> {
+    let header = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap();
+    Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(header)
+}
+
+fn error_response(status: u16, message: &str) -> Response<std::io::Cursor<Vec<u8>>> {
+    json_response(status, json!({ "error": message }))
+}
+
+fn header_value<'a>(req: &'a Request, name: &str) -> Option<&'a str> {
+    req.headers()
+        .iter()
+        .find(|h| h.field.as_str().as_str().eq_ignore_ascii_case(name))
+        .map(|h| h.value.as_str())
+}
+
+fn is_authorized(req: &Request, token: &str) -> bool {
+    if let Some(v) = header_value(req, "X-PerfSight-Token") {
+        return v.trim() == token;
+    }
+    if let Some(v) = header_value(req, "Authorization") {
+        if let Some(bearer) = v.trim().strip_prefix("Bearer ") {
+            return bearer.trim() == token;
+        }
+    }
+    false
+}
+
+fn read_json_body(req: &mut Request) -> Result<Value, String> {
+    let mut body = String::new();
+    req.as_reader()
+        .take(MAX_BODY_BYTES + 1)
+        .read_to_string(&mut body)
+        .map_err(|e| format!("Failed to read body: {e}"))?;
+    if body.len() as u64 > MAX_BODY_BYTES {
+        return Err("Request body too large".to_string());
+    }
+    serde_json::from_str::<Value>(&body).map_err(|e| format!("Invalid JSON: {e}"))
+}
+
+/// POST /ingest/metrics: same schema as the extension's metric payload
+/// (`{"type":"data","timestamp":<ms>,"metrics":{"<pid>":{"cpu":..,"memory":<MB>}}}`), plus an
+/// optional per-PID `memory_basis`. Points go to the session recording their PID, in any mode.
+fn ingest_metrics<R: tauri::Runtime>(app: &AppHandle<R>, mut data: Value) -> Result<usize, String> {
+    check_metrics_payload(&mut data)?;
+    let state: State<CollectionState> = app.state();
+    Ok(process_metric_payload(app, data, state.inner(), DataSource::Http, "http"))
+}
+
+// Validate an /ingest/metrics body (defaulting `type` to "data").
+fn check_metrics_payload(data: &mut Value) -> Result<(), String> {
+    if !data.is_object() {
+        return Err("Body must be a JSON object".to_string());
+    }
+    if data.get("type").is_none() {
+        data["type"] = Value::String("data".to_string());
+    }
+    if data["type"] != "data" {
+        return Err("type must be \"data\"".to_string());
+    }
+    let ts_ms = data["timestamp"]
+        .as_i64()
+        .ok_or_else(|| "timestamp must be an integer (ms since epoch)".to_string())?;
+    payload_timestamp(ts_ms).map_err(|e| e.to_string())?;
+    let metrics = data["metrics"]
+        .as_object()
+        .ok_or_else(|| "metrics must be an object keyed by pid".to_string())?;
+    for (pid_str, val) in metrics {
+        if pid_str.parse::<u32>().is_err() {
+            return Err(format!("Invalid pid key: {}", pid_str));
+        }
+        if !val.is_null() && !val.is_object() {
+            return Err(format!("metrics.{} must be an object", pid_str));
+        }
+    }
+    Ok(())
+}
+
+/// POST /ingest/custom_metric: accepts the extension's `console_log` payload (matched against the
+/// run's log metric regexes) or a direct `{"pid", "name", "value", "timestamp"?}` point.
+fn ingest_custom_metric<R: tauri::Runtime>(app: &AppHandle<R>, data: Value) -> Result<usize, String> {
+    if !data.is_object() {
+        return Err("Body must be a JSON object".to_string());
+    }
+    let state: State<CollectionState> = app.state();
+
+    if data["type"] == "console_log" {
+        if !data["data"]["content"].is_string() {
+            return Err("data.content must be a string".to_string());
+        }
+        if let Some(ts_ms) = data["data"]["timestamp"].as_i64() {
+            payload_timestamp(ts_ms).map_err(|e| e.to_string())?;
+        }
+        return Ok(process_console_log_payload(app, state.inner(), &data));
+    }
+
+    let name = data["name"]
+        .as_str()
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| "name must be a non-empty string".to_string())?;
+    let value = data["value"]
+        .as_f64()
+        .ok_or_else(|| "value must be a number".to_string())?;
+    let pid = data["pid"]
+        .as_u64()
+        .ok_or_else(|| "pid must be an integer".to_string())? as u32;
+    let ts_ms = match data.get("timestamp") {
+        None | Some(Value::Null) => Utc::now().timestamp_millis(),
+        Some(v) => v
+            .as_i64()
+            .ok_or_else(|| "timestamp must be an integer (ms since epoch)".to_string())?,
+    };
+    let timestamp = payload_timestamp(ts_ms).map_err(|e| e.to_string())?;
+
+    push_custom_metric(app, state.inner(), pid, timestamp, name.to_string(), value);
+    Ok(1)
+}
+
+fn handle_request<R: tauri::Runtime>(app: &AppHandle<R>, server: &IngestServerState, mut req: Request) {
+    let path = req.url().split('?').next().unwrap_or("").to_string();
+
+    // Read-only Prometheus scrape endpoint (opt-in via settings; no token so scrapers can use a static config).
+    if path == "/metrics" {
+        if !*safe_lock(&server.prometheus_enabled) {
+            let _ = req.respond(error_response(404, "Prometheus endpoint is disabled"));
+            return;
+        }
+        if *req.method() != Method::Get {
+            let _ = req.respond(error_response(405, "Method not allowed"));
+            return;
+        }
+        let state: State<CollectionState> = app.state();
+        let header = Header::from_bytes(&b"Content-Type"[..], &b"text/plain; version=0.0.4"[..]).unwrap();
+        let body = prometheus::render_exposition(state.inner());
+        let _ = req.respond(Response::from_string(body).with_header(header));
+        return;
+    }
+
+    let handler: fn(&AppHandle<R>, Value) -> Result<usize, String> = match path.as_str() {
+        "/ingest/metrics" => ingest_metrics,
+        "/ingest/custom_metric" => ingest_custom_metric,
+        _ => {
+            let _ = req.respond(error_response(404, "Not found"));
+            return;
+        }
+    };
+    if *req.method() != Method::Post {
+        let _ = req.respond(error_response(405, "Method not allowed"));
+        return;
+    }
+    if !is_authorized(&req, &server.session_token) {
+        let _ = req.respond(error_response(401, "Missing or invalid session token"));
+        return;
+    }
+
+    let response = match read_json_body(&mut req).and_then(|data| handler(app, data)) {
+        Ok(accepted) => json_response(202, json!({ "accepted": accepted })),
+        Err(e) => error_response(400, &e),
+    };
+    let _ = req.respond(response);
+}
+
+pub fn start_server(app_handle: AppHandle) {
+    thread::spawn(move || {
+        // Localhost only, like the WebSocket server.
+        let base = Settings::load(app_handle.state::<Database>().inner()).http_port_base;
+        let (server, port) = match bind_http_listener_with_fallback(base) {
+            Some(x) => x,
+            None => {
+                log_error!("Failed to bind HTTP ingest server on 127.0.0.1:{}..", base);
+                return;
+            }
+        };
+
+        log_info!("HTTP ingest server listening on 127.0.0.1:{}", port);
+        let server_state: State<IngestServerState> = app_handle.state();
+        *safe_lock(&server_state.http_port) = Some(port);
+        let server_state = server_state.inner().clone();
+
+        for req in server.incoming_requests() {
+            handle_request(&app_handle, &server_state, req);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn out_of_range_timestamp_is_rejected() {
+        assert!(payload_timestamp(i64::MAX).is_err());
+        assert!(payload_timestamp(i64::MIN).is_err());
+        assert!(payload_timestamp(1_700_000_000_000).is_ok());
+
+        let mut data = json!({ "timestamp": i64::MAX, "metrics": { "42": { "cpu": 1.0 } } });
+        let err = check_metrics_payload(&mut data).unwrap_err();
+        assert!(err.contains("timestamp"), "{}", err);
+    }
+
+    #[test]
+    fn valid_metrics_payload_defaults_type() {
+        let mut data = json!({ "timestamp": 1_700_000_000_000i64, "metrics": { "42": { "cpu": 1.0 } } });
+        check_metrics_payload(&mut data).unwrap();
+        assert_eq!(data["type"], "data");
+    }
+
+    // A load-test rig posting into a system-mode run: its points are stored, not ignored.
+    #[test]
+    fn posted_metrics_feed_a_system_mode_session() {
+        let app = tauri::test::mock_app();
+        app.manage(CollectionState::new());
+        let ingest = IngestServerState::new();
+        let state = app.state::<CollectionState>();
+        let run = crate::commands::tests::test_run("rig", vec![4242]);
+        assert_eq!(run.mode, "system");
+        assert!(state.begin(run).is_ok());
+
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let port = server.server_addr().to_ip().unwrap().port();
+        let handle = app.handle().clone();
+        let served = {
+            let ingest = ingest.clone();
+            thread::spawn(move || {
+                let req = server.recv().unwrap();
+                handle_request(&handle, &ingest, req);
+            })
+        };
+
+        let ts = Utc::now().timestamp_millis();
+        let body = json!({ "timestamp": ts, "seq": 1, "metrics": { "4242": { "cpu": 12.5, "memory": 100.0 }, "7": { "cpu": 1.0, "memory": 1.0 } } }).to_string();
+        let mut stream = std::net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+        write!(
+            stream,
+            "POST /ingest/metrics HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nX-PerfSight-Token: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            ingest.session_token,
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        served.join().unwrap();
+        assert!(response.starts_with("HTTP/1.1 202"), "{}", response);
+        assert!(response.ends_with(r#"{"accepted":1}"#), "{}", response);
+
+        state
+            .read_session("rig", |run| {
+                assert_eq!(run.buffer.len(), 1);
+                let point = &run.buffer[0].metrics[&4242];
+                assert_eq!(point.cpu_usage, 12.5);
+                assert_eq!(point.memory_rss, 100 * 1024 * 1024);
+                assert_eq!(point.memory_basis, None, "not the extension's private memory");
+                assert_eq!((run.ingest.http_samples, run.ingest.websocket_samples, run.ingest.websocket_ignored), (1, 0, 0));
+                assert_eq!(run.ingest.sequence_stats(DataSource::Http).map(|s| s.received), Some(1));
+            })
+            .unwrap();
+    }
+}
@@ -0,0 +1,272 @@
+use crate::analysis::{AnalysisReport, BudgetResult};
+use crate::models::ReportMeta;
+
+fn escape_xml(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            // Control characters other than tab/newline are not allowed in XML 1.0.
+            c if (c as u32) < 0x20 && c != '\t' && c != '\n' && c != '\r' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn fmt_num(v: f64) -> String {
+    let s = format!("{:.3}", v);
+    s.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// Render budget results as a JUnit XML document: one `<testcase>` per budget check, or a single
+/// informational passing case when the report has no budgets.
+pub fn render_junit(
+    title: &str,
+    created_at: &str,
+    meta: &ReportMeta,
+    analysis: &AnalysisReport,
+    results: &[BudgetResult],
+) -> String {
+    let scenario = meta.scenario_name().unwrap_or(title);
+    let duration = meta.duration_seconds().unwrap_or(0) as f64;
+    let classname = format!("perfsight.{}", scenario);
+
+    let mut cases = String::new();
+    let mut tests = 0;
+    let mut failures = 0;
+    if results.is_empty() {
+        tests = 1;
+        cases.push_str(&format!(
+            "    <testcase name=\"{}\" classname=\"{}\" time=\"{}\">\n      <system-out>{}</system-out>\n    </testcase>\n",
+            escape_xml(&format!("{}: no budgets defined", scenario)),
+            escape_xml(&classname),
+            fmt_num(duration),
+            escape_xml(&format!("score={} (informational)", analysis.score)),
+        ));
+    }
+    for r in results {
+        tests += 1;
+        let op = if r.comparison == "min" { ">=" } else { "<=" };
+        let name = format!("{}: {} {} {}", scenario, r.metric, op, fmt_num(r.threshold));
+        let observed = r.observed.map(fmt_num).unwrap_or_else(|| "n/a".to_string());
+        cases.push_str(&format!(
+            "    <testcase name=\"{}\" classname=\"{}\" time=\"0\">\n",
+            escape_xml(&name),
+            escape_xml(&classname)
+        ));
+        if !r.passed {
+            failures += 1;
+            let message = if r.observed.is_none() {
+                format!("unknown metric '{}'", r.metric)
+            } else {
+                format!("observed {} {} threshold {}", observed, if op == "<=" { ">" } else { "<" }, fmt_num(r.threshold))
+            };
+            let mut body = format!("metric: {}\nobserved: {}\nthreshold: {} {}", r.metric, observed, op, fmt_num(r.threshold));
+            if let Some(insight) = &r.insight {
+                body.push_str(&format!("\ninsight: {}", insight));
+            }
+            cases.push_str(&format!(
+                "      <failure message=\"{}\" type=\"BudgetExceeded\">{}</failure>\n",
+                escape_xml(&message),
+                escape_xml(&body)
+            ));
+        }
+        cases.push_str("    </testcase>\n");
+    }
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str(&format!(
+        "<testsuites name=\"PerfSight\" tests=\"{}\" failures=\"{}\" time=\"{}\">\n",
+        tests,
+        failures,
+        fmt_num(duration)
+    ));
+    out.push_str(&format!(
+        "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"0\" time=\"{}\" timestamp=\"{}\">\n",
+        escape_xml(scenario),
+        tests,
+        failures,
+        fmt_num(duration),
+        escape_xml(created_at)
+    ));
+    out.push_str(&cases);
+    out.push_str("  </testsuite>\n</testsuites>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quick_xml::events::Event;
+    use serde_json::json;
+
+    #[derive(Debug, Default)]
+    struct Element {
+        name: String,
+        attrs: Vec<(String, String)>,
+        text: String,
+        children: Vec<Element>,
+    }
+
+    impl Element {
+        fn attr(&self, name: &str) -> &str {
+            self.attrs.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str()).unwrap_or_else(|| panic!("no {}", name))
+        }
+
+        fn all(&self, name: &str) -> Vec<&Element> {
+            self.children.iter().filter(|c| c.name == name).collect()
+        }
+    }
+
+    fn xml_char(c: char) -> bool {
+        matches!(c, '\t' | '\n' | '\r' | '\u{20}'..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}' | '\u{10000}'..='\u{10FFFF}')
+    }
+
+    fn element(e: &quick_xml::events::BytesStart) -> Element {
+        let attrs = e
+            .attributes()
+            .with_checks(true)
+            .map(|a| {
+                let a = a.expect("well-formed attribute");
+                (String::from_utf8(a.key.as_ref().to_vec()).unwrap(), a.unescape_value().expect("known entities").into_owned())
+            })
+            .collect();
+        Element { name: String::from_utf8(e.name().as_ref().to_vec()).unwrap(), attrs, ..Default::default() }
+    }
+
+    // Parse strictly: matching end tags, known entities only, legal characters, one root element
+    // and no text outside it.
+    fn parse(xml: &str) -> Element {
+        assert!(xml.chars().all(xml_char), "illegal XML character");
+        let mut reader = quick_xml::Reader::from_str(xml);
+        reader.config_mut().check_end_names = true;
+        let mut stack: Vec<Element> = Vec::new();
+        let mut root = None;
+        loop {
+            match reader.read_event().expect("well-formed XML") {
+                Event::Decl(_) => assert!(stack.is_empty() && root.is_none(), "misplaced declaration"),
+                Event::Start(e) => stack.push(element(&e)),
+                Event::Empty(e) => match stack.last_mut() {
+                    Some(parent) => parent.children.push(element(&e)),
+                    None => panic!("empty root element"),
+                },
+                Event::End(_) => {
+                    let done = stack.pop().unwrap();
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(done),
+                        None => {
+                            assert!(root.is_none(), "second root element");
+                            root = Some(done);
+                        }
+                    }
+                }
+                Event::Text(t) => {
+                    let text = t.decode().unwrap();
+                    match stack.last_mut() {
+                        Some(current) => current.text.push_str(&text),
+                        None => assert!(text.trim().is_empty(), "text outside the root: {:?}", text),
+                    }
+                }
+                Event::GeneralRef(r) => {
+                    let resolved = match r.resolve_char_ref().expect("valid character reference") {
+                        Some(c) => c.to_string(),
+                        None => {
+                            let name = r.decode().unwrap();
+                            quick_xml::escape::resolve_predefined_entity(&name)
+                                .unwrap_or_else(|| panic!("undefined entity &{};", name))
+                                .to_string()
+                        }
+                    };
+                    stack.last_mut().expect("reference outside the root").text.push_str(&resolved);
+                }
+                Event::Eof => break,
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+        assert!(stack.is_empty(), "unclosed elements");
+        root.expect("a root element")
+    }
+
+    fn meta(scenario: &str) -> ReportMeta {
+        ReportMeta::from_json_str(
+            &json!({
+                "collection": { "duration_seconds": 95 },
+                "test_context": { "scenario_name": scenario },
+            })
+            .to_string(),
+        )
+    }
+
+    fn budget(metric: &str, comparison: &str, threshold: f64, observed: Option<f64>, passed: bool, insight: Option<&str>) -> BudgetResult {
+        BudgetResult {
+            metric: metric.to_string(),
+            comparison: comparison.to_string(),
+            threshold,
+            observed,
+            passed,
+            insight: insight.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn budgets_round_trip_through_a_strict_parser() {
+        let scenario = "Load <\"A&B\"> 'quoted'\u{1}";
+        let results = [
+            budget("avg_cpu", "max", 50.0, Some(62.25), false, Some("CPU spikes ]]> & <stalls>\u{7}")),
+            budget("score", "min", 80.0, Some(91.0), true, None),
+            budget("no_such_metric", "max", 1.0, None, false, None),
+        ];
+        let analysis = crate::analysis::analyze_report(&[], &ReportMeta::default());
+        let xml = render_junit("Title", "2024-03-01T12:00:00Z", &meta(scenario), &analysis, &results);
+
+        let root = parse(&xml);
+        let clean_scenario = "Load <\"A&B\"> 'quoted'";
+        assert_eq!(root.name, "testsuites");
+        assert_eq!((root.attr("tests"), root.attr("failures"), root.attr("time")), ("3", "2", "95"));
+        let [suite] = root.all("testsuite")[..] else { panic!("one testsuite") };
+        assert_eq!(suite.attr("name"), clean_scenario);
+        assert_eq!(suite.attr("timestamp"), "2024-03-01T12:00:00Z");
+        assert_eq!((suite.attr("tests"), suite.attr("failures"), suite.attr("time")), ("3", "2", "95"));
+
+        let cases = suite.all("testcase");
+        let names: Vec<&str> = cases.iter().map(|c| c.attr("name")).collect();
+        assert_eq!(
+            names,
+            [
+                format!("{}: avg_cpu <= 50", clean_scenario),
+                format!("{}: score >= 80", clean_scenario),
+                format!("{}: no_such_metric <= 1", clean_scenario),
+            ]
+        );
+        assert!(cases.iter().all(|c| c.attr("classname") == format!("perfsight.{}", clean_scenario)));
+
+        let [failure] = cases[0].all("failure")[..] else { panic!("avg_cpu fails") };
+        assert_eq!(failure.attr("message"), "observed 62.25 > threshold 50");
+        assert_eq!(failure.attr("type"), "BudgetExceeded");
+        assert_eq!(
+            failure.text,
+            "metric: avg_cpu\nobserved: 62.25\nthreshold: <= 50\ninsight: CPU spikes ]]> & <stalls>"
+        );
+        assert!(cases[1].children.is_empty());
+        assert_eq!(cases[2].all("failure")[0].attr("message"), "unknown metric 'no_such_metric'");
+    }
+
+    #[test]
+    fn no_budgets_is_one_informational_passing_case() {
+        let analysis = crate::analysis::analyze_report(&[], &ReportMeta::default());
+        let xml = render_junit("Untitled & run", "2024-03-01T12:00:00Z", &ReportMeta::default(), &analysis, &[]);
+        let root = parse(&xml);
+        assert_eq!((root.attr("tests"), root.attr("failures"), root.attr("time")), ("1", "0", "0"));
+        let suite = root.all("testsuite")[0];
+        assert_eq!(suite.attr("name"), "Untitled & run");
+        let [case] = suite.all("testcase")[..] else { panic!("one testcase") };
+        assert_eq!(case.attr("name"), "Untitled & run: no budgets defined");
+        assert!(case.all("failure").is_empty());
+        assert_eq!(case.all("system-out")[0].text, format!("score={} (informational)", analysis.score));
+    }
+}
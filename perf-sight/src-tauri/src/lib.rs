@@ -3,18 +3,56 @@ pub mod models;
 pub mod collector;
 pub mod commands;
 pub mod database;
+pub mod migrations;
 pub mod analysis;
 pub mod ws_server;
+pub mod http_server;
+pub mod prometheus;
+pub mod metric_sink;
+pub mod stream_file;
+pub mod otlp;
+pub mod junit;
+pub mod cli;
+pub mod webhook;
+pub mod csv_export;
+pub mod csv_import;
+pub mod markdown;
+pub mod settings;
+pub mod error;
+pub mod folder_rules;
+pub mod roles;
+pub mod lifecycle;
+pub mod artifacts;
+pub mod dataset;
+pub mod anonymize;
+pub mod timezone;
+pub mod trace_import;
+pub mod scenario;
+pub mod parallel;
+pub mod sample_data;
+pub mod logging;
 
 use commands::CollectionState;
 use database::Database;
+use ws_server::IngestServerState;
 use tauri::Manager;
+use logging::log_info;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // `--headless ...` runs a single collection without a window (CI automation).
+    let args: Vec<String> = std::env::args().collect();
+    let headless = match cli::parse_headless_args(&args) {
+        Ok(h) => h,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(cli::EXIT_ERROR);
+        }
+    };
+
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
-        .setup(|app| {
+        .setup(move |app| {
             // Get platform-specific app data directory
             // Windows: C:\Users\Username\AppData\Local\com.perfsight.dev
             // Mac: ~/Library/Application Support/com.perfsight.dev
@@ -26,45 +64,141 @@ pub fn run() {
                 std::fs::create_dir_all(&app_data_dir).expect("Failed to create app data dir");
             }
             
+            // Diagnostics go to rotating files next to the database (see `logging`).
+            if let Err(e) = logging::init(&app_data_dir.join("logs"), logging::LogLevel::default()) {
+                eprintln!("Failed to open the log file: {}", e);
+            }
+
             let db_path = app_data_dir.join("perfsight.db");
-            println!("Database path: {:?}", db_path);
+            log_info!("Database path: {:?}", db_path);
 
             let db = Database::new(db_path.to_str().unwrap()).expect("Failed to init DB");
             
+            commands::recover_unsaved_runs(&db);
+            if headless.is_none() {
+                commands::seed_sample_data(&db);
+            }
+            let app_settings = settings::Settings::load(&db);
+            logging::set_level(app_settings.log_level);
+            let ingest_state = IngestServerState::new();
+            *commands::safe_lock(&ingest_state.prometheus_enabled) = app_settings.prometheus_enabled;
+            let collection_state = CollectionState::new();
+            *commands::safe_lock(&collection_state.default_interval_ms) = app_settings.default_interval_ms;
+
             app.manage(db);
-            app.manage(CollectionState::new());
+            app.manage(collection_state);
+            app.manage(ingest_state);
+            app.manage(scenario::ScenarioState::new());
+            app.manage(commands::ReportStreams::new());
+            app.manage(commands::BundleExports::new());
+            app.manage(commands::ReanalyzeJobs::new());
+            commands::spawn_auto_maintenance_if_due(app.handle());
             
             // Start WebSocket Server for Chrome Extension
             ws_server::start_server(app.handle().clone());
+            // Start HTTP ingest server for non-browser data sources (load-test rigs, scripts)
+            http_server::start_server(app.handle().clone());
+
+            // The main window is declared with `"create": false` so headless runs never open one.
+            if let Some(args) = headless.clone() {
+                let handle = app.handle().clone();
+                tauri::async_runtime::spawn(async move {
+                    let code = cli::run_headless(handle.clone(), args).await;
+                    handle.exit(code);
+                });
+            } else if let Some(window_config) = app.config().app.windows.first().cloned() {
+                tauri::WebviewWindowBuilder::from_config(app.handle(), &window_config)?.build()?;
+            }
             
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             commands::get_process_list,
             commands::get_collection_status,
+            commands::get_ws_server_status,
+            commands::set_prometheus_enabled,
+            commands::get_app_settings,
+            commands::update_app_settings,
+            commands::get_metric_sink_config,
+            commands::set_metric_sink_config,
+            commands::get_webhook_config,
+            commands::set_webhook_config,
             commands::start_collection,
+            commands::start_collection_from_preset,
+            commands::run_scenario_script,
+            commands::abort_scenario_script,
+            commands::save_collection_preset,
+            commands::list_collection_presets,
+            commands::delete_collection_preset,
+            commands::import_collection_presets,
             commands::stop_collection,
             commands::get_reports,
             commands::get_known_tags,
+            commands::get_known_comparison_tags,
+            commands::update_comparison_tags,
             commands::get_report_detail,
+            commands::get_live_buffer_tail,
+            commands::analyze_report_with_selection,
+            commands::get_report_detail_for_comparison,
+            commands::get_report_detail_streamed,
+            commands::abort_report_stream,
+            commands::reanalyze_reports,
+            commands::cancel_reanalyze_reports,
+            commands::remove_sample_data,
+            commands::get_recent_logs,
+            commands::export_diagnostics_zip,
+            commands::set_report_locked,
             commands::delete_report,
             commands::delete_reports,
             commands::update_report_title,
+            commands::update_report_aliases,
             commands::update_report_folder_path,
             commands::update_reports_folder_path,
             commands::list_folder_paths,
             commands::create_folder,
             commands::get_folder_stats,
+            commands::get_folder_aggregate,
+            commands::resolve_process_selection,
+            commands::preview_role_assignment,
+            commands::list_folder_rules,
+            commands::save_folder_rule,
+            commands::delete_folder_rule,
+            commands::reorder_folder_rules,
+            commands::dry_run_folder_rules,
             commands::rename_folder,
             commands::delete_folder,
             commands::debug_get_macos_rusage,
+            commands::debug_get_cdp_process_info,
             commands::export_report_pdf,
+            commands::export_report_pdf_from_path,
+            commands::maintain_database,
+            commands::get_last_maintenance,
+            commands::get_database_stats,
+            commands::get_storage_breakdown,
+            commands::execute_readonly_query,
             commands::export_report_dataset,
+            commands::export_report_otlp,
+            commands::export_report_junit,
+            commands::export_report_markdown,
+            commands::check_folder_budgets,
             commands::export_reports_bundle_zip,
+            commands::cancel_bundle_export,
+            commands::export_folder_bundle_zip,
+            commands::import_folder_bundle,
             commands::import_report_dataset,
+            commands::import_csv_report,
+            commands::import_jsonl_report,
+            commands::import_trace_summary,
             commands::import_comparison_bundle,
             // Comparisons
             commands::create_comparison,
+            commands::create_comparison_checked,
+            commands::create_comparison_auto,
+            commands::set_folder_baseline,
+            commands::set_folder_settings,
+            commands::get_folder_settings,
+            commands::delete_folder_settings,
+            commands::get_folder_baseline,
             commands::get_comparisons,
             commands::get_comparison_detail,
             commands::delete_comparison,
@@ -76,12 +210,22 @@ pub fn run() {
             commands::list_comparison_folder_paths,
             commands::create_comparison_folder,
             commands::get_comparison_folder_stats,
+            commands::get_comparison_folder_aggregate,
             commands::rename_comparison_folder,
             commands::delete_comparison_folder,
             commands::export_comparison_bundle_json,
+            commands::export_report_csv,
+            commands::export_comparison_csv,
+            commands::export_comparison_overlay_csv,
             commands::update_comparison_meta,
             commands::update_comparison_reports
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Quitting mid-run (window closed or app exit) saves the runs and stops the sidecar.
+            if let tauri::RunEvent::Exit = event {
+                commands::shutdown_on_exit(app);
+            }
+        });
 }
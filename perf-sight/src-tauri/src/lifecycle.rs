@@ -0,0 +1,166 @@
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use sysinfo::{Pid, ProcessStatus, System};
+use crate::models::{ProcessTermination, TerminationReason};
+
+// Crash reporters write their record a moment after the process is gone; look once more after this.
+const CRASH_RECORD_WAIT: Duration = Duration::from_secs(3);
+// Crash records older than this before the exit was noticed belong to an earlier process.
+const CRASH_RECORD_WINDOW_SECS: i64 = 60;
+
+/// Whether `pid` is still running. A zombie (exited, not yet reaped) counts as gone.
+pub fn pid_alive(sys: &mut System, pid: u32) -> bool {
+    let sys_pid = Pid::from_u32(pid);
+    if !sys.refresh_process(sys_pid) {
+        return false;
+    }
+    sys.process(sys_pid).is_some_and(|p| p.status() != ProcessStatus::Zombie)
+}
+
+/// Best-effort reason `pid` ended, noticed at `exited_at`: its wait status when it was our child,
+/// else the platform's crash records (macOS DiagnosticReports, Windows WER events, Linux
+/// coredumpctl). Unknown when none has an answer; a clean exit of an unrelated process leaves no
+/// record, so it is Unknown too. Blocking (may wait a few seconds for a crash record).
+pub fn resolve_termination(pid: u32, exited_at: DateTime<Utc>) -> ProcessTermination {
+    if let Some(t) = child_status(pid) {
+        return t;
+    }
+    crash_record(pid, exited_at)
+        .or_else(|| {
+            std::thread::sleep(CRASH_RECORD_WAIT);
+            crash_record(pid, exited_at)
+        })
+        .unwrap_or_else(ProcessTermination::unknown)
+}
+
+fn termination(reason: TerminationReason, source: &str) -> ProcessTermination {
+    ProcessTermination { reason, source: Some(source.to_string()), ..ProcessTermination::unknown() }
+}
+
+// Reap `pid` if it is our exited child. Other processes' statuses are not ours to read.
+#[cfg(unix)]
+fn child_status(pid: u32) -> Option<ProcessTermination> {
+    let mut status: libc::c_int = 0;
+    let rc = unsafe { libc::waitpid(pid as libc::pid_t, &mut status, libc::WNOHANG) };
+    if rc != pid as libc::pid_t {
+        return None;
+    }
+    if libc::WIFEXITED(status) {
+        let code = libc::WEXITSTATUS(status);
+        let reason = if code == 0 { TerminationReason::CleanExit } else { TerminationReason::ErrorExit };
+        return Some(ProcessTermination { exit_code: Some(code), ..termination(reason, "wait_status") });
+    }
+    if libc::WIFSIGNALED(status) {
+        let signal = libc::WTERMSIG(status);
+        let fault = [libc::SIGSEGV, libc::SIGBUS, libc::SIGILL, libc::SIGFPE, libc::SIGABRT, libc::SIGTRAP];
+        let reason = if fault.contains(&signal) || libc::WCOREDUMP(status) {
+            TerminationReason::Crashed
+        } else {
+            TerminationReason::Signaled
+        };
+        return Some(ProcessTermination { signal: Some(signal), ..termination(reason, "wait_status") });
+    }
+    None
+}
+
+#[cfg(not(unix))]
+fn child_status(_pid: u32) -> Option<ProcessTermination> {
+    None
+}
+
+// A crash report (.ips or .crash) written after the exit window opened that names `pid`.
+#[cfg(target_os = "macos")]
+fn crash_record(pid: u32, exited_at: DateTime<Utc>) -> Option<ProcessTermination> {
+    let since = std::time::SystemTime::from(exited_at - chrono::Duration::seconds(CRASH_RECORD_WINDOW_SECS));
+    let home = std::env::var_os("HOME").map(std::path::PathBuf::from);
+    let dirs = home
+        .map(|h| h.join("Library/Logs/DiagnosticReports"))
+        .into_iter()
+        .chain([std::path::PathBuf::from("/Library/Logs/DiagnosticReports")]);
+    let pid_re = regex::Regex::new(&format!(r#""pid"\s*:\s*{}\s*,|Process:.*\[{}\]"#, pid, pid)).ok()?;
+    let signal_re = regex::Regex::new(r#""signal"\s*:\s*"(SIG[A-Z]+)"|Exception Type:.*\((SIG[A-Z]+)\)"#).ok()?;
+    for dir in dirs {
+        let Ok(entries) = std::fs::read_dir(&dir) else { continue };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !matches!(path.extension().and_then(|e| e.to_str()), Some("ips" | "crash")) {
+                continue;
+            }
+            if entry.metadata().and_then(|m| m.modified()).map_or(true, |m| m < since) {
+                continue;
+            }
+            let Ok(text) = std::fs::read_to_string(&path) else { continue };
+            if !pid_re.is_match(&text) {
+                continue;
+            }
+            let signal = signal_re
+                .captures(&text)
+                .and_then(|c| c.get(1).or_else(|| c.get(2)))
+                .map(|m| m.as_str().to_string());
+            let detail = match signal {
+                Some(sig) => format!("{} ({})", path.display(), sig),
+                None => path.display().to_string(),
+            };
+            return Some(ProcessTermination { detail: Some(detail), ..termination(TerminationReason::Crashed, "crash_report") });
+        }
+    }
+    None
+}
+
+// An Application Error event (WER, event id 1000) of the last minutes naming `pid`.
+#[cfg(target_os = "windows")]
+fn crash_record(pid: u32, exited_at: DateTime<Utc>) -> Option<ProcessTermination> {
+    let window_ms = (Utc::now() - exited_at).num_milliseconds().max(0) + CRASH_RECORD_WINDOW_SECS * 1000;
+    let query = format!("*[System[(EventID=1000) and TimeCreated[timediff(@SystemTime) <= {}]]]", window_ms);
+    let output = std::process::Command::new("wevtutil")
+        .args(["qe", "Application", &format!("/q:{}", query), "/f:text", "/rd:true", "/c:50"])
+        .output()
+        .ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let pid_hex = format!("0x{:x}", pid);
+    // Events are separated by "Event[n]:" headers; the description names the faulting process id.
+    for event in text.split("Event[") {
+        let lower = event.to_lowercase();
+        let names_pid = lower
+            .lines()
+            .any(|l| l.contains("process id") && l.split_whitespace().any(|w| w == pid_hex));
+        if !names_pid {
+            continue;
+        }
+        let code = event
+            .lines()
+            .find(|l| l.to_lowercase().contains("exception code"))
+            .map(|l| l.trim().to_string());
+        return Some(ProcessTermination { detail: code, ..termination(TerminationReason::Crashed, "wer") });
+    }
+    None
+}
+
+// A core dump systemd-coredump recorded for `pid` since the exit window opened.
+#[cfg(target_os = "linux")]
+fn crash_record(pid: u32, exited_at: DateTime<Utc>) -> Option<ProcessTermination> {
+    let since = (exited_at - chrono::Duration::seconds(CRASH_RECORD_WINDOW_SECS)).timestamp();
+    let output = std::process::Command::new("coredumpctl")
+        .args(["--no-pager", "--no-legend", &format!("--since=@{}", since), "list", &pid.to_string()])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let text = String::from_utf8_lossy(&output.stdout);
+    // Columns: TIME (several words) PID UID GID SIG COREFILE EXE [SIZE].
+    let line = text.lines().find(|l| l.split_whitespace().any(|w| w == pid.to_string()))?;
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let sig = words.iter().position(|w| *w == pid.to_string()).and_then(|i| words.get(i + 3)).copied();
+    let signal = sig.and_then(|s| s.parse::<i32>().ok());
+    Some(ProcessTermination {
+        signal,
+        detail: Some(line.trim().to_string()),
+        ..termination(TerminationReason::Crashed, "coredumpctl")
+    })
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+fn crash_record(_pid: u32, _exited_at: DateTime<Utc>) -> Option<ProcessTermination> {
+    None
+}
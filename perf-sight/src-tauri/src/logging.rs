@@ -0,0 +1,180 @@
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Mutex, OnceLock};
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Size at which the current log file is rotated.
+pub const LOG_FILE_MAX_BYTES: u64 = 2 * 1024 * 1024;
+/// Files kept: `perfsight.log` and its rotated predecessors `perfsight.log.1` (newest) and up.
+pub const LOG_FILES_KEPT: usize = 5;
+const LOG_FILE_NAME: &str = "perfsight.log";
+// Most lines `recent` returns, whatever the caller asks for.
+pub const MAX_RECENT_LINES: usize = 5000;
+
+/// Severity of a log line; a level includes every level above it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error = 0,
+    Warn = 1,
+    #[default]
+    Info = 2,
+    Debug = 3,
+}
+
+impl LogLevel {
+    fn from_u8(v: u8) -> Self {
+        match v {
+            0 => LogLevel::Error,
+            1 => LogLevel::Warn,
+            2 => LogLevel::Info,
+            _ => LogLevel::Debug,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            LogLevel::Error => "ERROR",
+            LogLevel::Warn => "WARN",
+            LogLevel::Info => "INFO",
+            LogLevel::Debug => "DEBUG",
+        }
+    }
+}
+
+/// One line of the log file, stored as JSON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogLine {
+    /// RFC 3339, UTC, milliseconds.
+    pub timestamp: String,
+    pub level: LogLevel,
+    /// Module that wrote the line, e.g. "commands" or "collector::simulate".
+    pub module: String,
+    pub message: String,
+}
+
+struct LogFile {
+    dir: PathBuf,
+    file: Option<File>,
+    size: u64,
+}
+
+impl LogFile {
+    // Shift `perfsight.log.<n>` up by one (dropping the oldest) and start an empty current file.
+    fn rotate(&mut self) {
+        self.file = None;
+        for n in (1..LOG_FILES_KEPT).rev() {
+            let from = if n == 1 { self.dir.join(LOG_FILE_NAME) } else { rotated_path(&self.dir, n - 1) };
+            let _ = fs::rename(from, rotated_path(&self.dir, n));
+        }
+        self.file = OpenOptions::new().create(true).append(true).open(self.dir.join(LOG_FILE_NAME)).ok();
+        self.size = 0;
+    }
+}
+
+fn rotated_path(dir: &Path, n: usize) -> PathBuf {
+    dir.join(format!("{}.{}", LOG_FILE_NAME, n))
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Info as u8);
+static LOG_FILE: OnceLock<Mutex<LogFile>> = OnceLock::new();
+
+/// Start writing to `dir/perfsight.log` at `level`. Lines logged before this only go to stderr.
+pub fn init(dir: &Path, level: LogLevel) -> std::io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(LOG_FILE_NAME);
+    let file = OpenOptions::new().create(true).append(true).open(&path)?;
+    let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+    set_level(level);
+    let _ = LOG_FILE.set(Mutex::new(LogFile { dir: dir.to_path_buf(), file: Some(file), size }));
+    Ok(())
+}
+
+/// Change the level of lines written from now on (the `log_level` setting).
+pub fn set_level(level: LogLevel) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn level() -> LogLevel {
+    LogLevel::from_u8(LEVEL.load(Ordering::Relaxed))
+}
+
+/// Log files on disk, newest first; empty before `init`.
+pub fn log_files() -> Vec<PathBuf> {
+    let Some(log) = LOG_FILE.get() else { return Vec::new() };
+    let dir = log.lock().unwrap_or_else(|p| p.into_inner()).dir.clone();
+    std::iter::once(dir.join(LOG_FILE_NAME))
+        .chain((1..LOG_FILES_KEPT).map(|n| rotated_path(&dir, n)))
+        .filter(|p| p.is_file())
+        .collect()
+}
+
+/// Write a line at `level` from `module` (a `module_path!()`), to stderr and the log file. Use
+/// the `log_*!` macros rather than calling this directly.
+pub fn write(level: LogLevel, module: &str, args: fmt::Arguments) {
+    if level > self::level() {
+        return;
+    }
+    // Drop the crate name: "perf_sight_lib::commands" -> "commands".
+    let module = module.split_once("::").map_or(module, |(_, m)| m);
+    let line = LogLine {
+        timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+        level,
+        module: module.to_string(),
+        message: args.to_string(),
+    };
+    eprintln!("{} {:<5} {}: {}", line.timestamp, level.label(), line.module, line.message);
+
+    let Some(log) = LOG_FILE.get() else { return };
+    let Ok(mut json) = serde_json::to_string(&line) else { return };
+    json.push('\n');
+    let mut log = log.lock().unwrap_or_else(|p| p.into_inner());
+    if log.size > 0 && log.size + json.len() as u64 > LOG_FILE_MAX_BYTES {
+        log.rotate();
+    }
+    if let Some(file) = log.file.as_mut() {
+        if file.write_all(json.as_bytes()).is_ok() {
+            log.size += json.len() as u64;
+        }
+    }
+}
+
+/// The newest `limit` lines at `level` or more severe, across the current and rotated files,
+/// oldest first. Lines that aren't valid log JSON are skipped.
+pub fn recent(level: LogLevel, limit: usize) -> Vec<LogLine> {
+    let limit = limit.min(MAX_RECENT_LINES);
+    let mut newest_first: Vec<LogLine> = Vec::new();
+    for path in log_files() {
+        let Ok(file) = File::open(&path) else { continue };
+        let lines: Vec<LogLine> = BufReader::new(file)
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|l| serde_json::from_str::<LogLine>(&l).ok())
+            .filter(|l| l.level <= level)
+            .collect();
+        newest_first.extend(lines.into_iter().rev().take(limit - newest_first.len()));
+        if newest_first.len() >= limit {
+            break;
+        }
+    }
+    newest_first.reverse();
+    newest_first
+}
+
+macro_rules! log_error {
+    ($($arg:tt)*) => { $crate::logging::write($crate::logging::LogLevel::Error, module_path!(), format_args!($($arg)*)) };
+}
+macro_rules! log_warn {
+    ($($arg:tt)*) => { $crate::logging::write($crate::logging::LogLevel::Warn, module_path!(), format_args!($($arg)*)) };
+}
+macro_rules! log_info {
+    ($($arg:tt)*) => { $crate::logging::write($crate::logging::LogLevel::Info, module_path!(), format_args!($($arg)*)) };
+}
+macro_rules! log_debug {
+    ($($arg:tt)*) => { $crate::logging::write($crate::logging::LogLevel::Debug, module_path!(), format_args!($($arg)*)) };
+}
+pub(crate) use {log_debug, log_error, log_info, log_warn};
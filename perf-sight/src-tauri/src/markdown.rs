@@ -0,0 +1,246 @@
+use crate::analysis::{AnalysisReport, BudgetResult};
+use crate::database::ReportDetail;
+use crate::models::RunEventKind;
+use crate::timezone::DisplayZone;
+
+// Timeline rows shown before the list is cut off.
+const MAX_EVENT_ROWS: usize = 20;
+
+// Table cells can't contain pipes or newlines.
+fn cell(s: &str) -> String {
+    s.replace('|', "\\|").replace(['\n', '\r'], " ")
+}
+
+fn fmt_duration(seconds: u64) -> String {
+    let (h, m, s) = (seconds / 3600, (seconds % 3600) / 60, seconds % 60);
+    if h > 0 {
+        format!("{}h {}m {}s", h, m, s)
+    } else if m > 0 {
+        format!("{}m {}s", m, s)
+    } else {
+        format!("{}s", s)
+    }
+}
+
+/// Compact Markdown summary of a report for pasting into chat or PR comments.
+/// `budget_results` adds a verdict table when Some and non-empty. The date is shown in `zone`.
+pub fn render_markdown(
+    report: &ReportDetail,
+    analysis: &AnalysisReport,
+    budget_results: Option<&[BudgetResult]>,
+    zone: DisplayZone,
+) -> String {
+    let meta = &report.meta;
+    let s = &analysis.summary;
+    let mut out = String::new();
+
+    out.push_str(&format!("### {}\n\n", report.title.trim()));
+
+    let date = chrono::DateTime::parse_from_rfc3339(&report.created_at)
+        .map(|d| {
+            let t = d.with_timezone(&chrono::Utc);
+            format!("{} {}", zone.format(t, "%Y-%m-%d %H:%M"), zone.label(t))
+        })
+        .unwrap_or_else(|_| report.created_at.clone());
+    let mut header = vec![format!("**Date:** {}", date)];
+    if let Some(d) = meta.duration_seconds() {
+        header.push(format!("**Duration:** {}", fmt_duration(d)));
+    }
+    if let Some(mode) = meta.mode() {
+        header.push(format!("**Mode:** {}", mode));
+    }
+    if let Some(build) = meta.build_id() {
+        header.push(format!("**Build:** `{}`", build));
+    }
+    let tags = meta.tags();
+    if !tags.is_empty() {
+        header.push(format!(
+            "**Tags:** {}",
+            tags.iter().map(|t| format!("`{}`", t)).collect::<Vec<_>>().join(" ")
+        ));
+    }
+    out.push_str(&header.join(" · "));
+    out.push_str("\n\n");
+
+    out.push_str("| Metric | Value |\n|---|---:|\n");
+    out.push_str(&format!("| Score | {} / 100 (analyzer v{}) |\n", analysis.score, analysis.analyzer_version));
+    out.push_str(&format!("| CPU avg | {:.1}% |\n", s.avg_cpu));
+    out.push_str(&format!("| CPU p95 | {:.1}% |\n", s.p95_cpu));
+    out.push_str(&format!("| CPU max | {:.1}% |\n", s.max_cpu));
+    out.push_str(&format!("| Memory avg | {:.1} MB |\n", s.avg_mem_mb));
+    out.push_str(&format!("| Memory max | {:.1} MB |\n", s.max_mem_mb));
+    out.push_str(&format!("| Memory growth | {:+.3} MB/sample |\n", s.mem_growth_rate));
+    out.push('\n');
+
+    if let Some(results) = budget_results.filter(|r| !r.is_empty()) {
+        let failed = results.iter().filter(|r| !r.passed).count();
+        if failed == 0 {
+            out.push_str(&format!("**Budgets:** ✅ all {} passed\n\n", results.len()));
+        } else {
+            out.push_str(&format!("**Budgets:** ❌ {} of {} failed\n\n", failed, results.len()));
+        }
+        out.push_str("| Budget | Observed | Result |\n|---|---:|:---:|\n");
+        for r in results {
+            let op = if r.comparison == "min" { ">=" } else { "<=" };
+            let observed = r.observed.map(|v| format!("{:.2}", v)).unwrap_or_else(|| "n/a".to_string());
+            out.push_str(&format!(
+                "| {} {} {} | {} | {} |\n",
+                cell(&r.metric),
+                op,
+                r.threshold,
+                observed,
+                if r.passed { "✅" } else { "❌" }
+            ));
+        }
+        out.push('\n');
+    }
+
+    if !analysis.insights.is_empty() {
+        out.push_str("**Insights**\n");
+        for i in &analysis.insights {
+            out.push_str(&format!("- **{}** `{}`: {}\n", i.severity.label(), i.code, i.message));
+        }
+        out.push('\n');
+    }
+
+    if !analysis.top_cpu.is_empty() {
+        let aliases = meta.aliases();
+        out.push_str("**Top contributors**\n\n| Process | CPU avg | CPU share | Mem avg |\n|---|---:|---:|---:|\n");
+        for c in &analysis.top_cpu {
+            let label = match aliases.get(&c.pid) {
+                Some(a) => format!("{} ({})", cell(a), c.pid),
+                None => c.pid.to_string(),
+            };
+            out.push_str(&format!(
+                "| {} | {:.1}% | {:.0}% | {:.1} MB |\n",
+                label,
+                c.avg_cpu,
+                c.cpu_share * 100.0,
+                c.avg_mem_mb
+            ));
+        }
+        out.push('\n');
+    }
+
+    let sources = meta.data_sources();
+    if !sources.is_empty() {
+        out.push_str("**Data sources**\n\n| Source | Backend | Samples | Details |\n|---|---|---:|---|\n");
+        for s in &sources {
+            let mut details = Vec::new();
+            if let Some(v) = &s.version {
+                details.push(format!("version {}", v));
+            }
+            if let Some(port) = s.ws_port {
+                details.push(format!("ws port {}", port));
+            }
+            if let Some(port) = s.http_port {
+                details.push(format!("http port {}", port));
+            }
+            if let Some(seq) = s.sequence.filter(|q| q.lost > 0) {
+                details.push(format!("{} of {} messages lost", seq.lost, seq.expected));
+            }
+            if !s.extension_versions.is_empty() {
+                details.push(format!("extension {}", s.extension_versions.join(", ")));
+            }
+            out.push_str(&format!(
+                "| {} | {} | {} | {} |\n",
+                cell(&s.source),
+                cell(&s.backend),
+                s.samples,
+                cell(&details.join(", "))
+            ));
+        }
+        out.push('\n');
+    }
+
+    let exits = meta.process_lifecycle();
+    if !exits.is_empty() {
+        let aliases = meta.aliases();
+        out.push_str("**Process exits**\n\n| Process | Exited | Reason | Code / signal | Source |\n|---|---|---|---|---|\n");
+        for (pid, exit) in &exits {
+            let label = match aliases.get(pid) {
+                Some(a) => format!("{} ({})", cell(a), pid),
+                None => pid.to_string(),
+            };
+            let t = &exit.termination;
+            let reason = serde_json::to_value(t.reason).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default();
+            let code = match (t.exit_code, t.signal) {
+                (Some(code), _) => format!("exit {}", code),
+                (None, Some(sig)) => format!("signal {}", sig),
+                (None, None) => String::new(),
+            };
+            out.push_str(&format!(
+                "| {} | {} | {} | {} | {} |\n",
+                label,
+                zone.format(exit.exited_at, "%H:%M:%S"),
+                reason,
+                code,
+                cell(t.source.as_deref().unwrap_or(""))
+            ));
+        }
+        out.push('\n');
+    }
+
+    // Start/stop are implied by the header; list only what happened in between.
+    let events: Vec<_> = meta
+        .events
+        .iter()
+        .filter(|e| !matches!(e.kind, RunEventKind::Started | RunEventKind::Stopped))
+        .collect();
+    if !events.is_empty() {
+        out.push_str("**Events**\n\n| Time | Event | PID | Detail |\n|---|---|---:|---|\n");
+        for e in events.iter().take(MAX_EVENT_ROWS) {
+            let kind = serde_json::to_value(e.kind).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default();
+            let detail = if e.detail.is_null() { String::new() } else { e.detail.to_string() };
+            out.push_str(&format!(
+                "| {} | {} | {} | {} |\n",
+                zone.format(e.timestamp, "%H:%M:%S"),
+                kind,
+                e.pid.map(|p| p.to_string()).unwrap_or_default(),
+                cell(&detail)
+            ));
+        }
+        if events.len() > MAX_EVENT_ROWS {
+            out.push_str(&format!("\n_{} more events not shown._\n", events.len() - MAX_EVENT_ROWS));
+        }
+    }
+
+    out.trim_end().to_string() + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn report_created_at(created_at: &str) -> ReportDetail {
+        serde_json::from_value(json!({
+            "id": 1,
+            "created_at": created_at,
+            "title": "Run",
+            "metrics": [],
+            "analysis": null,
+            "meta": {},
+        }))
+        .unwrap()
+    }
+
+    fn date_line(created_at: &str, zone: DisplayZone) -> String {
+        let report = report_created_at(created_at);
+        let md = render_markdown(&report, &crate::analysis::analyze(&report.metrics), None, zone);
+        let line = md.lines().find(|l| l.starts_with("**Date:**")).expect("date line").to_string();
+        line.split(" · ").next().unwrap().to_string()
+    }
+
+    // Timestamps saved with a local offset are converted before the zone's label is attached.
+    #[test]
+    fn date_is_converted_to_the_zone_it_is_labelled_with() {
+        assert_eq!(date_line("2025-01-01T09:30:00+08:00", DisplayZone::Utc), "**Date:** 2025-01-01 01:30 UTC");
+        assert_eq!(date_line("2025-01-01T01:30:00Z", DisplayZone::Utc), "**Date:** 2025-01-01 01:30 UTC");
+        let berlin = DisplayZone::parse(Some("Europe/Berlin")).unwrap();
+        assert_eq!(date_line("2025-01-01T09:30:00+08:00", berlin), "**Date:** 2025-01-01 02:30 CET");
+        assert_eq!(date_line("2025-07-01T09:30:00+08:00", berlin), "**Date:** 2025-07-01 03:30 CEST");
+        // Unparseable dates are shown as stored.
+        assert_eq!(date_line("yesterday", DisplayZone::Utc), "**Date:** yesterday");
+    }
+}
@@ -0,0 +1,416 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use serde::Serialize;
+use crate::logging::log_warn;
+use crate::commands::safe_lock;
+use crate::models::{BatchMetric, MetricSinkConfig};
+
+const DEFAULT_MEASUREMENT: &str = "perfsight";
+const DEFAULT_BATCH_SIZE: usize = 500;
+const DEFAULT_FLUSH_INTERVAL_MS: u64 = 5000;
+// Upper bound on lines waiting for the worker; beyond this new points are dropped instead of buffered.
+const QUEUE_CAPACITY: usize = 20_000;
+const MAX_RETRIES: u32 = 3;
+// How long stop_collection waits for the final flush before reporting stats.
+const SHUTDOWN_WAIT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MetricSinkStats {
+    pub points_sent: u64,
+    pub points_dropped: u64,
+    pub last_error: Option<String>,
+}
+
+#[derive(Default)]
+struct SinkCounters {
+    points_sent: AtomicU64,
+    points_dropped: AtomicU64,
+    last_error: Mutex<Option<String>>,
+}
+
+impl SinkCounters {
+    fn snapshot(&self) -> MetricSinkStats {
+        MetricSinkStats {
+            points_sent: self.points_sent.load(Ordering::Relaxed),
+            points_dropped: self.points_dropped.load(Ordering::Relaxed),
+            last_error: safe_lock(&self.last_error).clone(),
+        }
+    }
+}
+
+/// Streams buffered samples to an InfluxDB-compatible line-protocol endpoint from a background
+/// thread. Sending never blocks the collector: when the queue is full points are counted as dropped.
+pub struct MetricSink {
+    sender: Option<SyncSender<String>>,
+    counters: Arc<SinkCounters>,
+    // Behind a Mutex only so the sink can live in the shared (Sync) run state.
+    done: Mutex<Receiver<()>>,
+    measurement: String,
+    // pid -> (alias, proc_type), fixed for the run.
+    tags: HashMap<u32, (String, String)>,
+    mode: String,
+}
+
+impl MetricSink {
+    pub fn start(
+        config: MetricSinkConfig,
+        mode: &str,
+        tags: HashMap<u32, (String, String)>,
+    ) -> Result<Self, String> {
+        let url = write_url(&config)?;
+        let measurement = config
+            .measurement
+            .as_deref()
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .unwrap_or(DEFAULT_MEASUREMENT)
+            .to_string();
+        let batch_size = config.batch_size.filter(|n| *n > 0).unwrap_or(DEFAULT_BATCH_SIZE);
+        let flush_interval = Duration::from_millis(
+            config.flush_interval_ms.filter(|n| *n > 0).unwrap_or(DEFAULT_FLUSH_INTERVAL_MS),
+        );
+        let token = config.token.clone().filter(|t| !t.trim().is_empty());
+
+        let (sender, receiver) = mpsc::sync_channel::<String>(QUEUE_CAPACITY);
+        let (done_tx, done) = mpsc::channel::<()>();
+        let counters = Arc::new(SinkCounters::default());
+        let worker_counters = counters.clone();
+
+        thread::spawn(move || {
+            let client = match reqwest::blocking::Client::builder()
+                .timeout(Duration::from_secs(5))
+                .build()
+            {
+                Ok(c) => c,
+                Err(e) => {
+                    *safe_lock(&worker_counters.last_error) = Some(e.to_string());
+                    // Drain so the counters still reflect every point we were handed.
+                    for _ in receiver.iter() {
+                        worker_counters.points_dropped.fetch_add(1, Ordering::Relaxed);
+                    }
+                    let _ = done_tx.send(());
+                    return;
+                }
+            };
+
+            let mut pending: Vec<String> = Vec::new();
+            let mut last_flush = Instant::now();
+            loop {
+                let wait = flush_interval.saturating_sub(last_flush.elapsed());
+                let disconnected = match receiver.recv_timeout(wait) {
+                    Ok(line) => {
+                        pending.push(line);
+                        false
+                    }
+                    Err(RecvTimeoutError::Timeout) => false,
+                    Err(RecvTimeoutError::Disconnected) => true,
+                };
+
+                let interval_due = last_flush.elapsed() >= flush_interval;
+                if !pending.is_empty() && (disconnected || interval_due || pending.len() >= batch_size) {
+                    flush(&client, &url, token.as_deref(), &pending, &worker_counters);
+                    pending.clear();
+                    last_flush = Instant::now();
+                } else if interval_due {
+                    last_flush = Instant::now();
+                }
+                if disconnected {
+                    break;
+                }
+            }
+            let _ = done_tx.send(());
+        });
+
+        Ok(Self {
+            sender: Some(sender),
+            counters,
+            done: Mutex::new(done),
+            measurement,
+            tags,
+            mode: mode.to_string(),
+        })
+    }
+
+    /// Queue every point of a freshly buffered batch. Never blocks.
+    pub fn send_batch(&self, batch: &BatchMetric) {
+        let Some(sender) = &self.sender else { return };
+        for point in batch.metrics.values() {
+            let (alias, proc_type) = self
+                .tags
+                .get(&point.pid)
+                .map(|(a, t)| (a.as_str(), t.as_str()))
+                .unwrap_or(("", ""));
+            let Some(line) = to_line_protocol(&self.measurement, &self.mode, alias, proc_type, point) else {
+                continue;
+            };
+            match sender.try_send(line) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => {
+                    self.counters.points_dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    pub fn stats(&self) -> MetricSinkStats {
+        self.counters.snapshot()
+    }
+
+    /// Close the queue, give the worker a short window for its final flush and return the stats.
+    pub fn shutdown(mut self) -> MetricSinkStats {
+        self.sender.take();
+        let _ = safe_lock(&self.done).recv_timeout(SHUTDOWN_WAIT);
+        self.counters.snapshot()
+    }
+}
+
+fn write_url(config: &MetricSinkConfig) -> Result<String, String> {
+    let base = config.url.trim().trim_end_matches('/');
+    if base.is_empty() {
+        return Err("Metric sink URL is empty".to_string());
+    }
+    let mut url = url::Url::parse(base).map_err(|e| format!("Invalid metric sink URL: {e}"))?;
+    // With a bucket we target the InfluxDB v2 write API; otherwise the URL is used as-is
+    // (e.g. a Telegraf http_listener or an InfluxDB v1 /write?db=.. endpoint).
+    if let Some(bucket) = config.bucket.as_deref().map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        if !url.path().ends_with("/api/v2/write") {
+            let path = format!("{}/api/v2/write", url.path().trim_end_matches('/'));
+            url.set_path(&path);
+        }
+        {
+            let mut q = url.query_pairs_mut();
+            q.append_pair("bucket", bucket);
+            if let Some(org) = config.org.as_deref().map(|s| s.trim()).filter(|s| !s.is_empty()) {
+                q.append_pair("org", org);
+            }
+        }
+    }
+    // Timestamps are written in milliseconds.
+    if !url.query_pairs().any(|(k, _)| k == "precision") {
+        url.query_pairs_mut().append_pair("precision", "ms");
+    }
+    Ok(url.to_string())
+}
+
+fn flush(
+    client: &reqwest::blocking::Client,
+    url: &str,
+    token: Option<&str>,
+    lines: &[String],
+    counters: &SinkCounters,
+) {
+    let body = lines.join("\n");
+    let mut last_err = String::new();
+    for attempt in 0..MAX_RETRIES {
+        if attempt > 0 {
+            thread::sleep(Duration::from_millis(250 * (1 << attempt)));
+        }
+        let mut req = client
+            .post(url)
+            .header("Content-Type", "text/plain; charset=utf-8")
+            .body(body.clone());
+        if let Some(t) = token {
+            req = req.header("Authorization", format!("Token {}", t));
+        }
+        match req.send() {
+            Ok(resp) if resp.status().is_success() => {
+                counters.points_sent.fetch_add(lines.len() as u64, Ordering::Relaxed);
+                return;
+            }
+            Ok(resp) => {
+                last_err = format!("HTTP {}", resp.status());
+                // Client errors (bad token, malformed line) won't improve on retry.
+                if resp.status().is_client_error() {
+                    break;
+                }
+            }
+            Err(e) => last_err = e.to_string(),
+        }
+    }
+    log_warn!("Metric sink flush failed ({} points): {}", lines.len(), last_err);
+    counters.points_dropped.fetch_add(lines.len() as u64, Ordering::Relaxed);
+    *safe_lock(&counters.last_error) = Some(last_err);
+}
+
+// Measurement names escape commas and spaces.
+fn escape_measurement(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            ',' | ' ' => {
+                out.push('\\');
+                out.push(ch);
+            }
+            '\n' | '\r' => out.push(' '),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+// Tag keys, tag values and field keys escape commas, equals signs and spaces.
+fn escape_key(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            ',' | '=' | ' ' => {
+                out.push('\\');
+                out.push(ch);
+            }
+            // Line protocol has no newline escape; keep the line intact.
+            '\n' | '\r' => out.push_str("\\ "),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+/// Serialize one sample as an InfluxDB line-protocol record (millisecond precision).
+/// Returns None when the point carries no fields.
+pub fn to_line_protocol(
+    measurement: &str,
+    mode: &str,
+    alias: &str,
+    proc_type: &str,
+    point: &crate::models::MetricPoint,
+) -> Option<String> {
+    let mut tags = vec![("pid".to_string(), point.pid.to_string())];
+    // Empty tag values are invalid in line protocol; omit them.
+    for (k, v) in [("alias", alias), ("proc_type", proc_type), ("mode", mode)] {
+        let v = v.trim();
+        if !v.is_empty() {
+            tags.push((k.to_string(), v.to_string()));
+        }
+    }
+
+    let custom_only = point.custom_metrics.is_some()
+        && point.cpu_usage == 0.0
+        && point.memory_rss == 0
+        && point.memory_private.is_none();
+
+    let mut fields: Vec<(String, String)> = Vec::new();
+    let float = |v: f64| if v.is_finite() { Some(format!("{}", v)) } else { None };
+    if !custom_only {
+        fields.push(("cpu".to_string(), format!("{}", point.cpu_usage)));
+        fields.push(("cpu_os".to_string(), format!("{}", point.cpu_os_usage)));
+        if let Some(v) = point.cpu_chrome_usage {
+            fields.push(("cpu_chrome".to_string(), format!("{}", v)));
+        }
+        fields.push(("memory_rss".to_string(), format!("{}i", point.memory_rss)));
+        if let Some(v) = point.memory_private {
+            fields.push(("memory_private".to_string(), format!("{}i", v)));
+        }
+        if let Some(v) = point.memory_footprint {
+            fields.push(("memory_footprint".to_string(), format!("{}i", v)));
+        }
+        if let Some(v) = point.js_heap_size {
+            fields.push(("js_heap_size".to_string(), format!("{}i", v)));
+        }
+        if let Some(v) = point.js_heap_total_size {
+            fields.push(("js_heap_total_size".to_string(), format!("{}i", v)));
+        }
+        if let Some(v) = point.gpu_usage {
+            fields.push(("gpu".to_string(), format!("{}", v)));
+        }
+    }
+    if let Some(custom) = &point.custom_metrics {
+        let mut names: Vec<_> = custom.keys().collect();
+        names.sort();
+        for name in names {
+            if let Some(v) = float(custom[name]) {
+                fields.push((format!("custom_{}", name), v));
+            }
+        }
+    }
+    if fields.is_empty() {
+        return None;
+    }
+
+    let tag_str: String = tags
+        .iter()
+        .map(|(k, v)| format!(",{}={}", escape_key(k), escape_key(v)))
+        .collect();
+    let field_str = fields
+        .iter()
+        .map(|(k, v)| format!("{}={}", escape_key(k), v))
+        .collect::<Vec<_>>()
+        .join(",");
+    Some(format!(
+        "{}{} {} {}",
+        escape_measurement(measurement),
+        tag_str,
+        field_str,
+        point.timestamp.timestamp_millis()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MetricPoint;
+    use serde_json::json;
+
+    fn point(extra: serde_json::Value) -> MetricPoint {
+        let mut v = json!({
+            "timestamp": "2024-03-01T12:00:00.250Z",
+            "pid": 42,
+            "cpu_usage": 12.5,
+            "cpu_os_usage": 25.0,
+            "memory_rss": 1048576,
+        });
+        v.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+        serde_json::from_value(v).unwrap()
+    }
+
+    #[test]
+    fn measurement_escapes_commas_and_spaces_only() {
+        assert_eq!(escape_measurement("perf sight,app=x"), "perf\\ sight\\,app=x");
+        assert_eq!(escape_measurement("a\nb\rc"), "a b c");
+        assert_eq!(escape_measurement("plain"), "plain");
+    }
+
+    #[test]
+    fn keys_and_tag_values_escape_commas_equals_and_spaces() {
+        assert_eq!(escape_key("Chat tab, main=1"), "Chat\\ tab\\,\\ main\\=1");
+        assert_eq!(escape_key("two\nlines"), "two\\ lines");
+        assert_eq!(escape_key("ünïcode\"quote"), "ünïcode\"quote");
+    }
+
+    #[test]
+    fn line_carries_escaped_tags_typed_fields_and_millisecond_time() {
+        let p = point(json!({ "memory_private": 2048, "gpu_usage": 3.0 }));
+        let line = to_line_protocol("perf sight", "browser", "Chat tab, main=1", "renderer", &p).unwrap();
+        assert_eq!(
+            line,
+            "perf\\ sight,pid=42,alias=Chat\\ tab\\,\\ main\\=1,proc_type=renderer,mode=browser \
+             cpu=12.5,cpu_os=25,memory_rss=1048576i,memory_private=2048i,gpu=3 1709294400250"
+        );
+    }
+
+    #[test]
+    fn blank_tags_are_omitted() {
+        let line = to_line_protocol("m", " ", "", "  ", &point(json!({}))).unwrap();
+        assert!(line.starts_with("m,pid=42 cpu=12.5,"), "{}", line);
+    }
+
+    #[test]
+    fn custom_only_points_write_sorted_finite_custom_fields() {
+        let mut p = point(json!({
+            "cpu_usage": 0.0,
+            "memory_rss": 0,
+            "custom_metrics": { "z score": 1.5, "fps": 60.0 },
+        }));
+        p.custom_metrics.as_mut().unwrap().insert("inf".to_string(), f64::INFINITY);
+        let line = to_line_protocol("m", "system", "", "", &p).unwrap();
+        assert_eq!(line, "m,pid=42,mode=system custom_fps=60,custom_z\\ score=1.5 1709294400250");
+
+        let mut empty = point(json!({ "cpu_usage": 0.0, "memory_rss": 0, "custom_metrics": {} }));
+        assert!(to_line_protocol("m", "system", "", "", &empty).is_none());
+        empty.custom_metrics = Some(HashMap::from([("nan".to_string(), f64::NAN)]));
+        assert!(to_line_protocol("m", "system", "", "", &empty).is_none());
+    }
+}
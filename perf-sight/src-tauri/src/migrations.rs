@@ -0,0 +1,398 @@
+use rusqlite::{ffi, params, Connection, Result};
+use crate::database::Database;
+
+/// One schema step. `apply` must be idempotent: databases created before `schema_migrations`
+/// existed run every step, including ones their ad-hoc column checks already covered.
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    apply: fn(&Connection) -> Result<()>,
+}
+
+/// Every schema step in order; `version` is the 1-based position. Steps are only ever appended:
+/// a database records the number it reached, so renumbering would skip steps it never ran.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, name: "baseline_schema", apply: baseline_schema },
+    Migration { version: 2, name: "reports_meta_and_folder", apply: reports_meta_and_folder },
+    Migration { version: 3, name: "reports_summary_hash_lock", apply: reports_summary_hash_lock },
+    Migration { version: 4, name: "reports_cached_analysis", apply: reports_cached_analysis },
+    Migration { version: 5, name: "reports_storage", apply: reports_storage },
+    Migration { version: 6, name: "comparisons_columns", apply: comparisons_columns },
+    Migration { version: 7, name: "comparisons_metric_selections", apply: comparisons_metric_selections },
+    Migration { version: 8, name: "tag_index", apply: tag_index },
+    Migration { version: 9, name: "settings", apply: settings_table },
+    Migration { version: 10, name: "collection_presets", apply: collection_presets },
+    Migration { version: 11, name: "folder_rules", apply: folder_rules },
+    Migration { version: 12, name: "folder_baselines", apply: folder_baselines },
+    Migration { version: 13, name: "run_chunks", apply: run_chunks },
+    Migration { version: 14, name: "folder_settings", apply: folder_settings },
+];
+
+/// Newest schema version this build creates and reads.
+pub const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// Bring the database at `conn` to `SCHEMA_VERSION`, one transaction per pending migration, and
+/// return the version it was at. A database recorded at a newer version is refused rather than
+/// opened by code that doesn't know its layout.
+pub fn migrate(conn: &mut Connection) -> Result<u32> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    let current = schema_version(conn)?;
+    if current > SCHEMA_VERSION {
+        return Err(rusqlite::Error::SqliteFailure(
+            ffi::Error::new(ffi::SQLITE_CANTOPEN),
+            Some(format!(
+                "database schema version {} is newer than this build supports ({}); open it with a newer PerfSight",
+                current, SCHEMA_VERSION
+            )),
+        ));
+    }
+    for m in MIGRATIONS.iter().filter(|m| m.version > current) {
+        let tx = conn.transaction()?;
+        (m.apply)(&tx)?;
+        tx.execute(
+            "INSERT INTO schema_migrations (version, name, applied_at) VALUES (?1, ?2, ?3)",
+            params![m.version, m.name, chrono::Utc::now().to_rfc3339()],
+        )?;
+        tx.commit()?;
+    }
+    Ok(current)
+}
+
+/// Highest applied migration, 0 for a database that predates `schema_migrations`.
+pub fn schema_version(conn: &Connection) -> Result<u32> {
+    conn.query_row("SELECT COALESCE(MAX(version), 0) FROM schema_migrations", [], |row| row.get(0))
+}
+
+// Add `column` to `table` unless it is already there.
+fn add_column(conn: &Connection, table: &str, column: &str, decl: &str) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>>>()?
+        .iter()
+        .any(|c| c == column);
+    if !exists {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl), [])?;
+    }
+    Ok(())
+}
+
+// Schema as it stood before migrations were tracked: reports, comparisons and their folders.
+// Tables added since have their own steps below.
+fn baseline_schema(conn: &Connection) -> Result<()> {
+    // Storing metrics as a huge JSON blob for simplicity in Phase 1
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS reports (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            created_at TEXT NOT NULL,
+            title TEXT NOT NULL,
+            folder_path TEXT NOT NULL DEFAULT '',
+            metrics_json TEXT NOT NULL,
+            meta_json TEXT NOT NULL DEFAULT '{}'
+        );
+        CREATE TABLE IF NOT EXISTS folders (
+            path TEXT PRIMARY KEY,
+            created_at TEXT NOT NULL
+        );
+        -- Comparisons (separate artifact from reports)
+        CREATE TABLE IF NOT EXISTS comparisons (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            created_at TEXT NOT NULL,
+            title TEXT NOT NULL,
+            folder_path TEXT NOT NULL DEFAULT '',
+            report_ids_json TEXT NOT NULL,
+            baseline_report_id INTEGER,
+            cpu_selections_json TEXT NOT NULL DEFAULT '{}',
+            mem_selections_json TEXT NOT NULL DEFAULT '{}',
+            meta_json TEXT NOT NULL DEFAULT '{}'
+        );
+        CREATE TABLE IF NOT EXISTS comparison_folders (
+            path TEXT PRIMARY KEY,
+            created_at TEXT NOT NULL
+        );",
+    )
+}
+
+fn reports_meta_and_folder(conn: &Connection) -> Result<()> {
+    add_column(conn, "reports", "meta_json", "TEXT NOT NULL DEFAULT '{}'")?;
+    add_column(conn, "reports", "folder_path", "TEXT NOT NULL DEFAULT ''")
+}
+
+fn reports_summary_hash_lock(conn: &Connection) -> Result<()> {
+    // NULL until computed; older reports are backfilled in the background (see `get_reports`).
+    add_column(conn, "reports", "summary_json", "TEXT")?;
+    // Duplicate detection on import; NULL rows are hashed on first lookup.
+    add_column(conn, "reports", "content_hash", "TEXT")?;
+    add_column(conn, "reports", "locked", "INTEGER NOT NULL DEFAULT 0")?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_reports_content_hash ON reports(content_hash)", [])?;
+    Ok(())
+}
+
+// Cached analysis and budget verdicts: NULL until first needed (see `budget_results`), cleared
+// whenever the samples or meta change.
+fn reports_cached_analysis(conn: &Connection) -> Result<()> {
+    add_column(conn, "reports", "analysis_json", "TEXT")?;
+    add_column(conn, "reports", "budget_results_json", "TEXT")
+}
+
+// Storage accounting: NULL until backfilled by `backfill_storage`.
+fn reports_storage(conn: &Connection) -> Result<()> {
+    add_column(conn, "reports", "size_bytes", "INTEGER")?;
+    add_column(conn, "reports", "storage_json", "TEXT")
+}
+
+fn comparisons_columns(conn: &Connection) -> Result<()> {
+    add_column(conn, "comparisons", "folder_path", "TEXT NOT NULL DEFAULT ''")?;
+    add_column(conn, "comparisons", "report_ids_json", "TEXT NOT NULL DEFAULT '[]'")?;
+    add_column(conn, "comparisons", "baseline_report_id", "INTEGER")?;
+    add_column(conn, "comparisons", "cpu_selections_json", "TEXT NOT NULL DEFAULT '{}'")?;
+    add_column(conn, "comparisons", "mem_selections_json", "TEXT NOT NULL DEFAULT '{}'")?;
+    add_column(conn, "comparisons", "meta_json", "TEXT NOT NULL DEFAULT '{}'")
+}
+
+// NULL keeps "never chosen" apart from an explicit selection.
+fn comparisons_metric_selections(conn: &Connection) -> Result<()> {
+    add_column(conn, "comparisons", "metric_selections_json", "TEXT")
+}
+
+// Tag index derived from meta (which stays the source of truth, e.g. for exports). Kept in step
+// on every meta write and rebuilt by `maintain` if it drifts; filled here from the existing meta.
+fn tag_index(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS report_tags (
+            report_id INTEGER NOT NULL,
+            tag TEXT NOT NULL,
+            tag_lower TEXT NOT NULL,
+            PRIMARY KEY (report_id, tag_lower)
+        );
+        CREATE INDEX IF NOT EXISTS idx_report_tags_tag ON report_tags(tag_lower);
+        CREATE TABLE IF NOT EXISTS comparison_tags (
+            comparison_id INTEGER NOT NULL,
+            tag TEXT NOT NULL,
+            tag_lower TEXT NOT NULL,
+            PRIMARY KEY (comparison_id, tag_lower)
+        );
+        CREATE INDEX IF NOT EXISTS idx_comparison_tags_tag ON comparison_tags(tag_lower);",
+    )?;
+    Database::rebuild_tag_index(conn)
+}
+
+// App settings (key -> JSON value)
+fn settings_table(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS settings (
+            key TEXT PRIMARY KEY,
+            value_json TEXT NOT NULL
+        );",
+    )
+}
+
+// Named collection presets (CollectionConfig JSON without target_pids)
+fn collection_presets(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS collection_presets (
+            name TEXT PRIMARY KEY,
+            config_json TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );",
+    )
+}
+
+// Auto-foldering rules, evaluated in `position` order at save time
+fn folder_rules(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS folder_rules (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            position INTEGER NOT NULL,
+            match_tag TEXT,
+            match_scenario TEXT,
+            folder_template TEXT NOT NULL,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL
+        );",
+    )
+}
+
+// Pinned baseline report per report folder (see `set_folder_baseline`)
+fn folder_baselines(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS folder_baselines (
+            folder_path TEXT PRIMARY KEY,
+            report_id INTEGER NOT NULL,
+            pinned_at TEXT NOT NULL
+        );",
+    )
+}
+
+// Samples written while a run is still going, one JSON array per chunk. Rows are removed once
+// the run's report is saved; leftovers are recovered at startup.
+fn run_chunks(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS run_chunks (
+            session_id TEXT NOT NULL,
+            seq INTEGER NOT NULL,
+            started_at TEXT NOT NULL,
+            mode TEXT NOT NULL,
+            title TEXT NOT NULL,
+            batches_json TEXT NOT NULL,
+            PRIMARY KEY (session_id, seq)
+        );",
+    )
+}
+
+// Collection defaults per report folder (see `set_folder_settings`)
+fn folder_settings(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS folder_settings (
+            path TEXT PRIMARY KEY,
+            settings_json TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    // Tables with their columns, and indexes, as `migrate` leaves them; column order differs
+    // between created and altered tables, so columns are sorted.
+    fn layout(conn: &Connection) -> BTreeMap<String, Vec<String>> {
+        let mut stmt = conn
+            .prepare("SELECT type, name FROM sqlite_master WHERE name NOT LIKE 'sqlite_%' ORDER BY name")
+            .unwrap();
+        let objects: Vec<(String, String)> =
+            stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?))).unwrap().map(|r| r.unwrap()).collect();
+        objects
+            .into_iter()
+            .map(|(kind, name)| {
+                let mut columns = Vec::new();
+                if kind == "table" {
+                    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", name)).unwrap();
+                    columns = stmt.query_map([], |row| row.get(1)).unwrap().map(|r| r.unwrap()).collect();
+                    columns.sort();
+                }
+                (format!("{} {}", kind, name), columns)
+            })
+            .collect()
+    }
+
+    fn latest_layout() -> BTreeMap<String, Vec<String>> {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        layout(&conn)
+    }
+
+    // A database as a build that had applied the first `version` steps left it.
+    fn fixture_at(version: u32) -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE schema_migrations (version INTEGER PRIMARY KEY, name TEXT NOT NULL, applied_at TEXT NOT NULL);")
+            .unwrap();
+        for m in &MIGRATIONS[..version as usize] {
+            let tx = conn.transaction().unwrap();
+            (m.apply)(&tx).unwrap();
+            tx.execute(
+                "INSERT INTO schema_migrations (version, name, applied_at) VALUES (?1, ?2, '2025-01-01T00:00:00Z')",
+                params![m.version, m.name],
+            )
+            .unwrap();
+            tx.commit().unwrap();
+        }
+        conn
+    }
+
+    fn add_report(conn: &Connection) {
+        conn.execute(
+            "INSERT INTO reports (created_at, title, metrics_json) VALUES ('2025-01-01T00:00:00Z', 'kept', '[]')",
+            [],
+        )
+        .unwrap();
+    }
+
+    fn assert_migrated(mut conn: Connection, from: u32) {
+        assert_eq!(migrate(&mut conn).unwrap(), from);
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        assert_eq!(layout(&conn), latest_layout(), "layout after migrating from version {}", from);
+        let (title, meta, folder): (String, String, String) = conn
+            .query_row("SELECT title, meta_json, folder_path FROM reports", [], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .unwrap();
+        assert_eq!((title.as_str(), meta.as_str(), folder.as_str()), ("kept", "{}", ""));
+    }
+
+    #[test]
+    fn baseline_creates_only_the_original_tables() {
+        let conn = fixture_at(1);
+        let tables: Vec<String> =
+            layout(&conn).into_keys().filter_map(|k| k.strip_prefix("table ").map(str::to_string)).collect();
+        assert_eq!(
+            tables,
+            ["comparison_folders", "comparisons", "folders", "reports", "schema_migrations"]
+        );
+    }
+
+    #[test]
+    fn every_recorded_version_migrates_to_the_latest_layout() {
+        for version in 1..=SCHEMA_VERSION {
+            let conn = fixture_at(version);
+            add_report(&conn);
+            assert_migrated(conn, version);
+        }
+    }
+
+    #[test]
+    fn unversioned_databases_migrate_to_the_latest_layout() {
+        // The earliest layout: no meta or folder on reports, comparisons without their columns.
+        let oldest = Connection::open_in_memory().unwrap();
+        oldest
+            .execute_batch(
+                "CREATE TABLE reports (id INTEGER PRIMARY KEY AUTOINCREMENT, created_at TEXT NOT NULL, title TEXT NOT NULL, metrics_json TEXT NOT NULL);
+                CREATE TABLE comparisons (id INTEGER PRIMARY KEY AUTOINCREMENT, created_at TEXT NOT NULL, title TEXT NOT NULL);",
+            )
+            .unwrap();
+        add_report(&oldest);
+        assert_migrated(oldest, 0);
+
+        // The layout just before versions were recorded: every table, no `schema_migrations`.
+        let untracked = Connection::open_in_memory().unwrap();
+        for m in MIGRATIONS.iter().filter(|m| m.name != "tag_index") {
+            (m.apply)(&untracked).unwrap();
+        }
+        add_report(&untracked);
+        assert_migrated(untracked, 0);
+    }
+
+    #[test]
+    fn databases_where_step_one_created_every_table_still_migrate() {
+        // Builds that recorded versions 1-8 created the later tables in step 1.
+        let conn = fixture_at(8);
+        for m in &MIGRATIONS[8..] {
+            (m.apply)(&conn).unwrap();
+        }
+        add_report(&conn);
+        assert_migrated(conn, 8);
+    }
+
+    #[test]
+    fn newer_databases_are_refused() {
+        let mut conn = fixture_at(SCHEMA_VERSION);
+        conn.execute(
+            "INSERT INTO schema_migrations (version, name, applied_at) VALUES (?1, 'future', '2030-01-01T00:00:00Z')",
+            params![SCHEMA_VERSION + 1],
+        )
+        .unwrap();
+        let err = migrate(&mut conn).unwrap_err().to_string();
+        assert!(err.contains("newer than this build supports"), "{}", err);
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION + 1);
+    }
+}
@@ -1,8 +1,16 @@
 
-use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
 use chrono::{DateTime, Utc};
+use crate::collector::adaptive::AdaptiveSampling;
+use crate::collector::simulate::SimulationConfig;
+use crate::error::PerfSightError;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+/// `proc_type` of PerfSight's own processes (the app and its collector sidecar).
+pub const SELF_PROC_TYPE: &str = "PerfSight";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessInfo {
@@ -16,6 +24,24 @@ pub struct ProcessInfo {
     pub proc_type: String, // Browser, GPU, Renderer, Utility, Other
     pub title: Option<String>,
     pub url: Option<String>,
+    /// Browser tabs backed by this PID (several when tabs share a renderer). With several,
+    /// `title` lists them all and `url` is the first tab's; the list is kept in report snapshots.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<TabTarget>,
+    /// macOS only: bundle identifier of the app the executable belongs to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bundle_id: Option<String>,
+    /// macOS only: title of the process's frontmost on-screen window.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window_title: Option<String>,
+}
+
+/// A CDP page target, as listed under `ProcessInfo.targets`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TabTarget {
+    pub id: String,
+    pub title: String,
+    pub url: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,8 +52,11 @@ pub struct MetricPoint {
     /// - system mode: OS CPU%
     /// - browser mode: Chrome Task Manager-aligned CPU% when available, else OS CPU%
     pub cpu_usage: f32,
-    /// OS-level CPU% for this PID (sysinfo).
+    /// OS-level CPU% for this PID (sysinfo), scaled per the run's `CpuNormalization`.
     pub cpu_os_usage: f32,
+    /// OS-level CPU% before normalization (per-core summed, can exceed 100%), when known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_os_usage_raw: Option<f32>,
     /// Chrome Task Manager-aligned CPU% derived from CDP cpuTime deltas (when available).
     pub cpu_chrome_usage: Option<f32>,
     pub memory_rss: u64,
@@ -35,11 +64,153 @@ pub struct MetricPoint {
     pub memory_footprint: Option<u64>,
     pub gpu_usage: Option<f32>, 
     pub js_heap_size: Option<u64>, // Browser Metric
+    /// Total (allocated) JS heap alongside `js_heap_size` (used), from Runtime.getHeapUsage.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub js_heap_total_size: Option<u64>,
     // Browser Task Manager-aligned metric (when available via CDP SystemInfo.getProcessInfo)
     // Typically reported as "Memory footprint" / private memory.
     pub memory_private: Option<u64>,
+    /// GPU memory attributed to the process (Chrome's GPU process), when CDP reports it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gpu_memory_bytes: Option<u64>,
+    /// CPU% of the process's busiest thread (100 = one full core, never normalized), when the
+    /// collector can read per-thread times.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_thread_cpu: Option<f32>,
     // Dynamic metrics extracted from Console Logs or Custom Events (e.g. "Inference Time", "FPS")
     pub custom_metrics: Option<HashMap<String, f64>>,
+    /// What the primary memory value (`memory_private`, else `memory_rss`) measures, set by the
+    /// code path that filled it. None for imported samples and reports saved before it existed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_basis: Option<MemoryBasis>,
+}
+
+/// Source of a sample's primary memory value. Different bases of one process can differ by
+/// hundreds of MB, so series are only comparable when their bases match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryBasis {
+    /// OS resident set size (sysinfo, or the sidecar where psutil has no private figure).
+    Rss,
+    /// Chrome's privateMemorySize from CDP `SystemInfo.getProcessInfo`.
+    PrivateCdp,
+    /// macOS phys_footprint, used when CDP has no private memory.
+    PhysFootprint,
+    /// Private memory reported by the browser extension.
+    ExtensionPrivate,
+    /// Windows private commit charge, from the sidecar.
+    PrivateCommit,
+    /// Windows private working set (Task Manager "Memory"), from the sidecar.
+    PrivateWorkingSet,
+    /// Linux proportional set size (`/proc/<pid>/smaps_rollup`), from the sidecar.
+    Pss,
+}
+
+impl MemoryBasis {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MemoryBasis::Rss => "rss",
+            MemoryBasis::PrivateCdp => "private_cdp",
+            MemoryBasis::PhysFootprint => "phys_footprint",
+            MemoryBasis::ExtensionPrivate => "extension_private",
+            MemoryBasis::PrivateCommit => "private_commit",
+            MemoryBasis::PrivateWorkingSet => "private_working_set",
+            MemoryBasis::Pss => "pss",
+        }
+    }
+}
+
+/// Which memory figure System API runs (and the picker) report.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryStandard {
+    /// Resident set size / working set, as sysinfo and psutil report it.
+    #[default]
+    Rss,
+    /// What the OS task manager shows: phys_footprint on macOS, private working set on Windows,
+    /// PSS on Linux. Falls back to RSS where the figure can't be read.
+    TaskManager,
+}
+
+impl MemoryStandard {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Rss => "rss",
+            Self::TaskManager => "task_manager",
+        }
+    }
+}
+
+/// How OS CPU% is scaled before it is stored as `cpu_os_usage`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CpuNormalization {
+    /// 0-100% of total machine capacity, like Windows Task Manager.
+    TotalCapacity,
+    /// Per-core usage summed (can exceed 100%), like Activity Monitor, top or Process Explorer.
+    PerCoreSum,
+}
+
+impl CpuNormalization {
+    /// What each platform used before the setting existed: per-core on macOS, total capacity elsewhere.
+    pub fn platform_default() -> Self {
+        Self::default_for_os(std::env::consts::OS)
+    }
+
+    /// Default for an OS name as recorded in `meta.env.os` (older reports carry no definition).
+    pub fn default_for_os(os: &str) -> Self {
+        if os.eq_ignore_ascii_case("macos") {
+            Self::PerCoreSum
+        } else {
+            Self::TotalCapacity
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::TotalCapacity => "total_capacity",
+            Self::PerCoreSum => "per_core_sum",
+        }
+    }
+
+    /// Scale a per-core summed CPU% (sysinfo/psutil raw value).
+    pub fn apply(self, raw_pct: f32) -> f32 {
+        match self {
+            Self::PerCoreSum => raw_pct,
+            Self::TotalCapacity => {
+                let cpu_count = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1) as f32;
+                raw_pct / cpu_count
+            }
+        }
+    }
+}
+
+impl MetricPoint {
+    /// A point that only carries custom metrics (no CPU/memory sample for the PID).
+    pub fn is_custom_only(&self) -> bool {
+        self.custom_metrics.is_some()
+            && self.cpu_usage == 0.0
+            && self.memory_rss == 0
+            && self.memory_private.is_none()
+    }
+}
+
+/// How many batches of a run carried a CPU/memory sample for one PID, and when the first and
+/// last of them were taken.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PidCoverage {
+    pub samples: u64,
+    pub first_sample_at: DateTime<Utc>,
+    pub last_sample_at: DateTime<Utc>,
+}
+
+impl PidCoverage {
+    pub fn record(coverage: &mut HashMap<u32, PidCoverage>, pid: u32, at: DateTime<Utc>) {
+        let c = coverage.entry(pid).or_insert(PidCoverage { samples: 0, first_sample_at: at, last_sample_at: at });
+        c.samples += 1;
+        c.first_sample_at = c.first_sample_at.min(at);
+        c.last_sample_at = c.last_sample_at.max(at);
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -48,11 +219,76 @@ pub struct ProcessAlias {
     pub alias: String,
 }
 
+/// Process selection by rule instead of a fixed PID list. Explicit `pids` are always selected;
+/// `proc_types` and `name_contains` (both optional, combined with AND) add every scanned process
+/// that matches.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProcessSelector {
+    #[serde(default)]
+    pub pids: Vec<u32>,
+    /// Case-insensitive `proc_type` values, e.g. ["Renderer", "GPU"].
+    #[serde(default)]
+    pub proc_types: Vec<String>,
+    /// Case-insensitive substring of the process name or title.
+    #[serde(default)]
+    pub name_contains: Option<String>,
+    /// How often a running collection re-resolves the selector (default 5s, 0 = only at start).
+    #[serde(default)]
+    pub refresh_seconds: Option<u64>,
+}
+
+impl ProcessSelector {
+    pub const DEFAULT_REFRESH_SECONDS: u64 = 5;
+
+    fn has_rules(&self) -> bool {
+        !self.proc_types.is_empty() || self.name_contains.as_deref().is_some_and(|s| !s.trim().is_empty())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pids.is_empty() && !self.has_rules()
+    }
+
+    pub fn matches(&self, p: &ProcessInfo) -> bool {
+        if self.pids.contains(&p.pid) {
+            return true;
+        }
+        if !self.has_rules() {
+            return false;
+        }
+        let type_ok = self.proc_types.is_empty()
+            || self.proc_types.iter().any(|t| t.trim().eq_ignore_ascii_case(&p.proc_type));
+        let name_ok = match self.name_contains.as_deref().map(|s| s.trim().to_lowercase()) {
+            Some(needle) if !needle.is_empty() => {
+                p.name.to_lowercase().contains(&needle)
+                    || p.title.as_deref().is_some_and(|t| t.to_lowercase().contains(&needle))
+            }
+            _ => true,
+        };
+        type_ok && name_ok
+    }
+
+    /// Matching processes from a scan, in scan order.
+    pub fn resolve(&self, processes: Vec<ProcessInfo>) -> Vec<ProcessInfo> {
+        processes.into_iter().filter(|p| self.matches(p)).collect()
+    }
+
+    pub fn refresh_interval(&self) -> Option<u64> {
+        match self.refresh_seconds.unwrap_or(Self::DEFAULT_REFRESH_SECONDS) {
+            0 => None,
+            s => Some(s),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CollectionConfig {
+    /// Required for a run; omitted in presets (see `validate`).
+    #[serde(default)]
     pub target_pids: Vec<u32>,
+    /// 0 (or omitted) takes the folder default, then the `default_interval_ms` setting.
+    #[serde(default)]
     pub interval_ms: u64,
-    pub mode: String, // "system" | "browser"
+    pub mode: String, // "system" | "browser" | "simulate"
     /// Optional folder path (e.g. "Release/Scenario") for organizing reports.
     pub folder_path: Option<String>,
     pub test_context: Option<TestContext>,
@@ -60,6 +296,297 @@ pub struct CollectionConfig {
     /// Optional: auto-stop after N seconds and generate report.
     pub stop_after_seconds: Option<u64>,
     pub log_metric_configs: Option<Vec<LogMetricConfig>>,
+    /// Optional: stream samples to an InfluxDB line-protocol endpoint while collecting.
+    /// Falls back to the `metric_sink` setting when omitted.
+    pub metric_sink: Option<MetricSinkConfig>,
+    /// Optional: performance budgets evaluated against the report analysis.
+    pub budgets: Option<Vec<PerformanceBudget>>,
+    /// Reject an out-of-range `interval_ms` instead of clamping it, and refuse a browser-mode
+    /// start that needs the extension while none is connected.
+    #[serde(default)]
+    pub strict: bool,
+    /// Series shape for mode "simulate"; ignored by the other modes.
+    #[serde(default)]
+    pub simulation: Option<SimulationConfig>,
+    /// Optional: select processes by rule. Resolved into `target_pids` when the run starts and
+    /// re-resolved while it runs so new matching processes are picked up.
+    #[serde(default)]
+    pub target_selector: Option<ProcessSelector>,
+    /// Optional: custom metric that tells the active phase (non-zero) from idle (zero). Each
+    /// switch is recorded as a phase marker, so the report is analyzed per phase.
+    #[serde(default)]
+    pub phase_metric: Option<String>,
+    /// Browser mode: save the raw CDP process info and its interpretation, captured once at
+    /// start, as `meta.env.cdp_process_info`. For debugging memory unit mismatches.
+    #[serde(default)]
+    pub snapshot_cdp_process_info: bool,
+    /// Alias the selected processes from `Settings::role_templates` once the start snapshot is
+    /// taken. Aliases given in `process_aliases` win.
+    #[serde(default)]
+    pub apply_role_templates: bool,
+    /// Optional: also append every buffered batch to this JSON Lines file while the run goes
+    /// ("auto" picks a file under the app data `streams` directory). Re-importable with
+    /// `import_jsonl_report`.
+    #[serde(default)]
+    pub stream_to_file: Option<String>,
+    /// Also record PerfSight itself (the app and, when it collects, the sidecar). Its PIDs are
+    /// kept out of the scenario totals and summarized as `meta.overhead`.
+    #[serde(default)]
+    pub include_self: bool,
+    /// Optional: sample less often while every PID is stable, back at `interval_ms` as soon as
+    /// one changes. Native and sidecar modes; the policy is saved as
+    /// `meta.collection.adaptive_sampling`.
+    #[serde(default)]
+    pub adaptive_sampling: Option<AdaptiveSampling>,
+    /// Memory figure of System API samples: "rss" (default) or "task_manager". Saved as
+    /// `meta.definitions.memory_standard`; browser mode keeps Chrome's own figures.
+    #[serde(default)]
+    pub memory_standard: MemoryStandard,
+}
+
+pub const MIN_INTERVAL_MS: u64 = 100;
+pub const MAX_INTERVAL_MS: u64 = 60_000;
+
+impl CollectionConfig {
+    /// Check and normalize the config before a run starts. Fixable issues (duplicate PIDs,
+    /// interval out of range when not `strict`) are corrected and returned as warnings.
+    pub fn validate(&mut self) -> Result<Vec<String>, PerfSightError> {
+        let mut warnings = Vec::new();
+
+        let mut seen = std::collections::HashSet::new();
+        let before = self.target_pids.len();
+        self.target_pids.retain(|pid| seen.insert(*pid));
+        if self.target_pids.is_empty() {
+            return Err(match &self.target_selector {
+                Some(sel) if !sel.is_empty() => {
+                    PerfSightError::invalid_input("target_selector", "matched no running processes")
+                }
+                _ => PerfSightError::invalid_input("target_pids", "select at least one process"),
+            });
+        }
+        if self.target_pids.len() != before {
+            warnings.push(format!("Removed {} duplicate PID(s)", before - self.target_pids.len()));
+        }
+
+        if !(MIN_INTERVAL_MS..=MAX_INTERVAL_MS).contains(&self.interval_ms) {
+            if self.strict {
+                return Err(PerfSightError::invalid_input(
+                    "interval_ms",
+                    format!("must be between {} and {}", MIN_INTERVAL_MS, MAX_INTERVAL_MS),
+                ));
+            }
+            let clamped = self.interval_ms.clamp(MIN_INTERVAL_MS, MAX_INTERVAL_MS);
+            warnings.push(format!("interval_ms {} clamped to {}", self.interval_ms, clamped));
+            self.interval_ms = clamped;
+        }
+
+        if let Some(policy) = &self.adaptive_sampling {
+            policy.validate(self.interval_ms)?;
+            if self.mode == "browser" {
+                warnings.push("adaptive_sampling is ignored in browser mode".to_string());
+            }
+        }
+
+        if self.stop_after_seconds == Some(0) {
+            return Err(PerfSightError::invalid_input("stop_after_seconds", "must be greater than 0"));
+        }
+
+        if let Some(aliases) = &self.process_aliases {
+            if let Some(a) = aliases.iter().find(|a| !self.target_pids.contains(&a.pid)) {
+                return Err(PerfSightError::invalid_input(
+                    "process_aliases",
+                    format!("pid {} is not being monitored", a.pid),
+                ));
+            }
+        }
+
+        // Metric name -> the config producing it; two producers would interleave in one series.
+        let mut produced: HashMap<String, usize> = HashMap::new();
+        for (i, cfg) in self.log_metric_configs.iter().flatten().enumerate() {
+            for target in cfg.targets() {
+                let name = target.name.trim();
+                if name.is_empty() {
+                    continue;
+                }
+                let field = match target.group {
+                    Some(group) => format!("log_metric_configs[{}].captures.{}", i, group),
+                    None => format!("log_metric_configs[{}].name", i),
+                };
+                if let Some(other) = produced.insert(name.to_string(), i) {
+                    return Err(PerfSightError::invalid_input(
+                        field,
+                        format!("metric '{}' is already produced by log_metric_configs[{}]", name, other),
+                    ));
+                }
+            }
+            if let Some(format) = &cfg.value_format {
+                format.validate(&format!("log_metric_configs[{}].value_format", i))?;
+            }
+            let field = format!("log_metric_configs[{}].pattern", i);
+            if cfg.pattern.len() > MAX_LOG_PATTERN_LEN {
+                return Err(PerfSightError::invalid_input(field, format!("must be at most {} bytes", MAX_LOG_PATTERN_LEN)));
+            }
+            let re = compile_log_pattern(&cfg.pattern)
+                .map_err(|e| PerfSightError::invalid_input(field.clone(), e.to_string()))?;
+            if !cfg.captures.is_empty() {
+                let groups: Vec<&str> = re.capture_names().flatten().collect();
+                for target in cfg.targets() {
+                    let group = target.group.unwrap_or_default();
+                    if !groups.contains(&group) {
+                        return Err(PerfSightError::invalid_input(field, format!("has no capture group named '{}'", group)));
+                    }
+                    if target.name.trim().is_empty() {
+                        return Err(PerfSightError::invalid_input(
+                            format!("log_metric_configs[{}].captures.{}", i, group),
+                            "metric name must not be empty",
+                        ));
+                    }
+                }
+                continue;
+            }
+            // captures_len includes the implicit whole-match group.
+            if re.captures_len() != 2 {
+                return Err(PerfSightError::invalid_input(
+                    field,
+                    format!("must contain exactly one capture group (found {})", re.captures_len() - 1),
+                ));
+            }
+        }
+
+        if let Some(folder) = &self.folder_path {
+            check_folder_path("folder_path", folder)?;
+        }
+        self.folder_path = self
+            .folder_path
+            .as_deref()
+            .map(normalize_folder_path)
+            .filter(|s| !s.is_empty());
+        self.stream_to_file = self
+            .stream_to_file
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string);
+
+        Ok(warnings)
+    }
+
+    /// Fill the fields the caller left unset from `defaults`. Returns the names of the fields
+    /// that were filled.
+    pub fn apply_folder_defaults(&mut self, defaults: &FolderCollectionSettings) -> Vec<&'static str> {
+        let mut applied = Vec::new();
+        if self.interval_ms == 0 {
+            if let Some(v) = defaults.interval_ms {
+                self.interval_ms = v;
+                applied.push("interval_ms");
+            }
+        }
+        if self.stop_after_seconds.is_none() && defaults.stop_after_seconds.is_some() {
+            self.stop_after_seconds = defaults.stop_after_seconds;
+            applied.push("stop_after_seconds");
+        }
+        if self.budgets.is_none() && defaults.budgets.is_some() {
+            self.budgets = defaults.budgets.clone();
+            applied.push("budgets");
+        }
+        if self.log_metric_configs.is_none() && defaults.log_metric_configs.is_some() {
+            self.log_metric_configs = defaults.log_metric_configs.clone();
+            applied.push("log_metric_configs");
+        }
+        if self.metric_sink.is_none() && defaults.metric_sink.is_some() {
+            self.metric_sink = defaults.metric_sink.clone();
+            applied.push("metric_sink");
+        }
+        if self.phase_metric.is_none() && defaults.phase_metric.is_some() {
+            self.phase_metric = defaults.phase_metric.clone();
+            applied.push("phase_metric");
+        }
+        if self.stream_to_file.is_none() && defaults.stream_to_file.is_some() {
+            self.stream_to_file = defaults.stream_to_file.clone();
+            applied.push("stream_to_file");
+        }
+        applied
+    }
+}
+
+/// Collection defaults of a report folder (`set_folder_settings`). A run filed into the folder
+/// or one of its sub-folders takes every field its config leaves unset; a sub-folder's value
+/// wins over its parent's.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FolderCollectionSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_after_seconds: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budgets: Option<Vec<PerformanceBudget>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_metric_configs: Option<Vec<LogMetricConfig>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metric_sink: Option<MetricSinkConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phase_metric: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_to_file: Option<String>,
+}
+
+impl FolderCollectionSettings {
+    pub fn validate(&self) -> Result<(), PerfSightError> {
+        if let Some(v) = self.interval_ms {
+            if !(MIN_INTERVAL_MS..=MAX_INTERVAL_MS).contains(&v) {
+                return Err(PerfSightError::invalid_input(
+                    "interval_ms",
+                    format!("must be between {} and {}", MIN_INTERVAL_MS, MAX_INTERVAL_MS),
+                ));
+            }
+        }
+        if self.stop_after_seconds == Some(0) {
+            return Err(PerfSightError::invalid_input("stop_after_seconds", "must be greater than 0"));
+        }
+        Ok(())
+    }
+
+    /// Names of the fields that are set, in declaration order.
+    pub fn fields(&self) -> Vec<&'static str> {
+        [
+            ("interval_ms", self.interval_ms.is_some()),
+            ("stop_after_seconds", self.stop_after_seconds.is_some()),
+            ("budgets", self.budgets.is_some()),
+            ("log_metric_configs", self.log_metric_configs.is_some()),
+            ("metric_sink", self.metric_sink.is_some()),
+            ("phase_metric", self.phase_metric.is_some()),
+            ("stream_to_file", self.stream_to_file.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(name, set)| set.then_some(name))
+        .collect()
+    }
+}
+
+/// A pass/fail threshold on an analysis metric (`avg_cpu`, `p95_cpu`, `max_mem_mb`, `score`, ...).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PerformanceBudget {
+    pub metric: String,
+    /// Fail when the observed value is above this.
+    pub max: Option<f64>,
+    /// Fail when the observed value is below this (e.g. for `score`).
+    pub min: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricSinkConfig {
+    /// InfluxDB base URL (with `bucket`) or a full line-protocol write URL.
+    pub url: String,
+    pub token: Option<String>,
+    /// InfluxDB v2 bucket/org; when set, `/api/v2/write` is used.
+    pub bucket: Option<String>,
+    pub org: Option<String>,
+    /// Measurement name (default "perfsight").
+    pub measurement: Option<String>,
+    /// Flush after this many points (default 500)...
+    pub batch_size: Option<usize>,
+    /// ...or after this many milliseconds (default 5000), whichever comes first.
+    pub flush_interval_ms: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -68,14 +595,391 @@ pub struct LogMetricConfig {
     pub pattern: String,
     pub unit: Option<String>,
     pub target_pid: Option<u32>,
+    /// How the captured text is read as a number; omitted uses `LogValueFormat::default()`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value_format: Option<LogValueFormat>,
+    /// Named capture group -> metric, to read several values from one line. When set, `name`
+    /// only labels the config; without it the pattern's single group feeds `name`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub captures: BTreeMap<String, LogCapture>,
+}
+
+/// Metric fed by one named capture group: a metric name, or a name with its own unit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum LogCapture {
+    Name(String),
+    Metric {
+        name: String,
+        #[serde(default)]
+        unit: Option<String>,
+    },
+}
+
+/// One metric a `LogMetricConfig` produces.
+pub struct LogMetricTarget<'a> {
+    /// Named group to read; None for the single unnamed group.
+    pub group: Option<&'a str>,
+    pub name: &'a str,
+    pub unit: Option<&'a str>,
+}
+
+impl LogMetricConfig {
+    /// Every metric the config produces. A capture without its own unit takes the config's.
+    pub fn targets(&self) -> Vec<LogMetricTarget<'_>> {
+        if self.captures.is_empty() {
+            return vec![LogMetricTarget { group: None, name: &self.name, unit: self.unit.as_deref() }];
+        }
+        self.captures
+            .iter()
+            .map(|(group, capture)| match capture {
+                LogCapture::Name(name) => LogMetricTarget { group: Some(group), name, unit: self.unit.as_deref() },
+                LogCapture::Metric { name, unit } => LogMetricTarget {
+                    group: Some(group),
+                    name,
+                    unit: unit.as_deref().or(self.unit.as_deref()),
+                },
+            })
+            .collect()
+    }
+}
+
+/// Longest accepted log metric pattern, in bytes.
+pub const MAX_LOG_PATTERN_LEN: usize = 1024;
+// Compiled program and lazy-DFA cache bounds for log metric regexes (regex's defaults are 10 MB / 2 MB).
+const LOG_REGEX_SIZE_LIMIT: usize = 1 << 20;
+const LOG_REGEX_DFA_SIZE_LIMIT: usize = 1 << 20;
+/// Log lines are matched up to this many bytes; the rest is cut off and counted.
+pub const MAX_LOG_LINE_BYTES: usize = 16 * 1024;
+
+/// Compile a log metric pattern within the size limits. Matching stays linear in the line length
+/// (regex does not backtrack), so together with `MAX_LOG_LINE_BYTES` this bounds the cost per line.
+pub fn compile_log_pattern(pattern: &str) -> Result<regex::Regex, regex::Error> {
+    regex::RegexBuilder::new(pattern)
+        .size_limit(LOG_REGEX_SIZE_LIMIT)
+        .dfa_size_limit(LOG_REGEX_DFA_SIZE_LIMIT)
+        .build()
+}
+
+/// Log lines the ingest path did not match in full, saved as `meta.collection.log_ingest`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LogIngestStats {
+    /// Lines longer than `MAX_LOG_LINE_BYTES`, matched on their start only.
+    pub truncated_lines: u64,
+    /// Lines dropped because the matching queue was full.
+    pub dropped_lines: u64,
+}
+
+/// Captures of one log metric that could not be read as a number, saved per metric name as
+/// `meta.collection.log_metric_parse_failures`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LogParseFailures {
+    pub count: u64,
+    /// The latest text that failed, for fixing the pattern or `value_format`.
+    pub last_text: String,
 }
 
+/// Number format of a log metric's captured text. Every field is optional in JSON.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogValueFormat {
+    /// '.' or ','. Unset guesses per value: with both present the last one is the decimal
+    /// separator; a single ',' before exactly three digits ("1,234") is a thousands separator.
+    pub decimal_separator: Option<char>,
+    /// Unset accepts spaces, apostrophes and whichever of ',' / '.' is not the decimal separator.
+    pub thousands_separator: Option<char>,
+    /// Drop a trailing unit ("12.5 ms", "3%"); when false, anything after the number fails.
+    pub strip_unit: bool,
+    /// Factor applied to the parsed value, e.g. 0.001 for ms to s.
+    pub multiplier: f64,
+}
+
+impl Default for LogValueFormat {
+    fn default() -> Self {
+        Self { decimal_separator: None, thousands_separator: None, strip_unit: true, multiplier: 1.0 }
+    }
+}
+
+// Thousands separators accepted besides ',' / '.': space, no-break space, narrow no-break space, apostrophe.
+const GROUP_CHARS: [char; 4] = [' ', '\u{a0}', '\u{202f}', '\''];
+
+impl LogValueFormat {
+    fn validate(&self, field: &str) -> Result<(), PerfSightError> {
+        if self.decimal_separator.is_some_and(|c| c != '.' && c != ',') {
+            return Err(PerfSightError::invalid_input(field, "decimal_separator must be '.' or ','"));
+        }
+        if self.thousands_separator.is_some() && self.thousands_separator == self.decimal_separator {
+            return Err(PerfSightError::invalid_input(field, "thousands_separator must differ from decimal_separator"));
+        }
+        if !self.multiplier.is_finite() || self.multiplier == 0.0 {
+            return Err(PerfSightError::invalid_input(field, "multiplier must be a non-zero number"));
+        }
+        Ok(())
+    }
+
+    /// Read `text` ("12,5 ms", " 1.234,5 ", "-3e2") as a number, times `multiplier`.
+    pub fn parse(&self, text: &str) -> Option<f64> {
+        let text = text.trim();
+        let is_sep = |c: char| c == '.' || c == ',' || GROUP_CHARS.contains(&c) || Some(c) == self.thousands_separator;
+        // The number: optional sign, digits and separators, optional exponent. The rest is the unit.
+        let mut end = usize::from(text.starts_with(['+', '-']));
+        end += text[end..].find(|c: char| !(c.is_ascii_digit() || is_sep(c))).unwrap_or(text.len() - end);
+        let mut mantissa = &text[..end];
+        while let Some(c) = mantissa.chars().last().filter(|c| is_sep(*c)) {
+            mantissa = &mantissa[..mantissa.len() - c.len_utf8()];
+        }
+        let mut rest = &text[mantissa.len()..];
+        let mut exponent = "";
+        if let Some(exp) = rest.strip_prefix(['e', 'E']) {
+            let digits = exp.strip_prefix(['+', '-']).unwrap_or(exp);
+            let len = digits.find(|c: char| !c.is_ascii_digit()).unwrap_or(digits.len());
+            if len > 0 {
+                let taken = 1 + exp.len() - digits.len() + len;
+                exponent = &rest[..taken];
+                rest = &rest[taken..];
+            }
+        }
+        if !rest.trim().is_empty() && !self.strip_unit {
+            return None;
+        }
+        if !mantissa.chars().any(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        let decimal = self.decimal_separator.or_else(|| {
+            let marks: Vec<(usize, char)> = mantissa
+                .char_indices()
+                .filter(|(_, c)| (*c == '.' || *c == ',') && Some(*c) != self.thousands_separator)
+                .collect();
+            let &(at, last) = marks.last()?;
+            if marks.iter().any(|(_, c)| *c != last) {
+                return Some(last);
+            }
+            if marks.len() > 1 {
+                return None;
+            }
+            // A lone ',' before exactly three digits reads as grouping ("1,234"), unless the
+            // integer part is 0 ("0,125").
+            let int = mantissa[..at].trim_start_matches(['+', '-']);
+            let grouped = last == ',' && mantissa.len() - at - 1 == 3 && !int.is_empty() && int != "0";
+            (!grouped).then_some(last)
+        });
+        let mut normalized = String::with_capacity(mantissa.len() + exponent.len() + 1);
+        let mut seen_decimal = false;
+        for c in mantissa.chars() {
+            if Some(c) == decimal {
+                if seen_decimal {
+                    return None;
+                }
+                seen_decimal = true;
+                normalized.push('.');
+            } else if c.is_ascii_digit() || c == '+' || c == '-' {
+                normalized.push(c);
+            } else {
+                let grouping = match self.thousands_separator {
+                    Some(t) => c == t,
+                    None => is_sep(c),
+                };
+                // Grouping after the decimal separator is malformed.
+                if seen_decimal || !grouping {
+                    return None;
+                }
+            }
+        }
+        normalized.push_str(exponent);
+        let value = normalized.parse::<f64>().ok()? * self.multiplier;
+        value.is_finite().then_some(value)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(from = "RawTestContext")]
 pub struct TestContext {
     pub scenario_name: Option<String>,
     pub build_id: Option<String>,
+    /// Older reports stored tags as a comma-separated string.
     pub tags: Option<Vec<String>>,
     pub notes: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+    /// Entries of a `tags` array that were not strings, as (index, JSON type). They are left out
+    /// of `tags`; `sanitize` refuses them for a new run.
+    #[serde(skip)]
+    pub non_string_tags: Vec<(usize, &'static str)>,
+}
+
+#[derive(Deserialize)]
+struct RawTestContext {
+    #[serde(default, deserialize_with = "lenient")]
+    scenario_name: Option<String>,
+    #[serde(default, deserialize_with = "lenient")]
+    build_id: Option<String>,
+    #[serde(default)]
+    tags: Value,
+    #[serde(default, deserialize_with = "lenient")]
+    notes: Option<String>,
+    #[serde(flatten)]
+    extra: Map<String, Value>,
+}
+
+impl From<RawTestContext> for TestContext {
+    fn from(raw: RawTestContext) -> Self {
+        let mut non_string_tags = Vec::new();
+        let tags = match raw.tags {
+            Value::Array(arr) => Some(
+                arr.into_iter()
+                    .enumerate()
+                    .filter_map(|(i, t)| match t {
+                        Value::String(s) => Some(s),
+                        other => {
+                            non_string_tags.push((i, json_type(&other)));
+                            None
+                        }
+                    })
+                    .collect(),
+            ),
+            Value::String(s) => Some(
+                s.split(',')
+                    .map(|x| x.trim())
+                    .filter(|x| !x.is_empty())
+                    .map(|x| x.to_string())
+                    .collect(),
+            ),
+            _ => None,
+        };
+        Self {
+            scenario_name: raw.scenario_name,
+            build_id: raw.build_id,
+            tags,
+            notes: raw.notes,
+            extra: raw.extra,
+            non_string_tags,
+        }
+    }
+}
+
+fn json_type(v: &Value) -> &'static str {
+    match v {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Caps applied to a run's `test_context` when it starts (`Settings::test_context_limits`).
+/// Every field is optional in JSON; unset fields use the defaults.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TestContextLimits {
+    /// Characters kept of `scenario_name` and `build_id`.
+    pub max_text_chars: usize,
+    /// Characters kept of `notes`.
+    pub max_notes_chars: usize,
+    /// Tags kept; later ones are dropped.
+    pub max_tags: usize,
+    /// Characters kept of each tag.
+    pub max_tag_chars: usize,
+    /// Largest serialized size of the other (free-form) fields. Larger is refused, not cut.
+    pub max_extra_bytes: usize,
+}
+
+impl Default for TestContextLimits {
+    fn default() -> Self {
+        Self {
+            max_text_chars: 256,
+            max_notes_chars: 16_384,
+            max_tags: 50,
+            max_tag_chars: 64,
+            max_extra_bytes: 64 * 1024,
+        }
+    }
+}
+
+impl TestContextLimits {
+    pub fn validate(&self) -> Result<(), PerfSightError> {
+        let fields = [
+            ("max_text_chars", self.max_text_chars, 1_024),
+            ("max_notes_chars", self.max_notes_chars, 262_144),
+            ("max_tags", self.max_tags, 1_000),
+            ("max_tag_chars", self.max_tag_chars, 1_024),
+            ("max_extra_bytes", self.max_extra_bytes, 4 * 1024 * 1024),
+        ];
+        for (name, value, max) in fields {
+            if !(1..=max).contains(&value) {
+                return Err(PerfSightError::invalid_input(
+                    format!("test_context_limits.{}", name),
+                    format!("must be between 1 and {}", max),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A `test_context` field shortened by `TestContext::sanitize`. Counts are characters, except
+/// for `tags` itself where they are entries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestContextCut {
+    /// "scenario_name", "build_id", "notes", "tags" or "tags[<index>]".
+    pub field: String,
+    pub original: usize,
+    pub kept: usize,
+}
+
+impl TestContext {
+    /// Apply `limits` before a run starts. Non-string tags and oversized free-form fields are
+    /// refused; long text is cut at a character boundary and tags past the cap are dropped.
+    /// Returns the cuts made, empty when everything fit.
+    pub fn sanitize(&mut self, limits: &TestContextLimits) -> Result<Vec<TestContextCut>, PerfSightError> {
+        if let Some((i, ty)) = self.non_string_tags.first() {
+            return Err(PerfSightError::invalid_input(
+                format!("test_context.tags[{}]", i),
+                format!("must be a string, got {}", ty),
+            ));
+        }
+        let extra_bytes = serde_json::to_vec(&self.extra).map_or(0, |b| b.len());
+        if extra_bytes > limits.max_extra_bytes {
+            return Err(PerfSightError::invalid_input(
+                "test_context",
+                format!(
+                    "custom fields are {} bytes, at most {} allowed",
+                    extra_bytes, limits.max_extra_bytes
+                ),
+            ));
+        }
+
+        let mut cuts = Vec::new();
+        let mut cut = |field: String, s: &mut String, max: usize| {
+            let original = s.chars().count();
+            if original > max {
+                *s = s.chars().take(max).collect();
+                cuts.push(TestContextCut { field, original, kept: max });
+            }
+        };
+        let text_fields = [
+            ("scenario_name", &mut self.scenario_name, limits.max_text_chars),
+            ("build_id", &mut self.build_id, limits.max_text_chars),
+            ("notes", &mut self.notes, limits.max_notes_chars),
+        ];
+        for (name, value, max) in text_fields {
+            if let Some(s) = value {
+                cut(name.to_string(), s, max);
+            }
+        }
+        if let Some(tags) = &mut self.tags {
+            for (i, tag) in tags.iter_mut().enumerate().take(limits.max_tags) {
+                cut(format!("tags[{}]", i), tag, limits.max_tag_chars);
+            }
+            if tags.len() > limits.max_tags {
+                cuts.push(TestContextCut { field: "tags".to_string(), original: tags.len(), kept: limits.max_tags });
+                tags.truncate(limits.max_tags);
+            }
+        }
+        Ok(cuts)
+    }
 }
 
 // New Batch Metric for broadcasting
@@ -85,6 +989,127 @@ pub struct BatchMetric {
     pub metrics: HashMap<u32, MetricPoint>, // Map<PID, Metric>
 }
 
+// Metric families a `MetricSelection` understands, besides "custom:<name>" / "custom:*".
+const SELECTABLE_METRICS: &[&str] = &["cpu", "memory", "gpu", "js_heap"];
+
+/// Check metric identifiers ("cpu", "memory", "gpu", "js_heap", "custom:<name>", "custom:*"), as
+/// used by `MetricSelection::metrics` and comparison metric selections.
+pub fn check_metric_ids(field: &str, ids: &[String]) -> Result<(), PerfSightError> {
+    for m in ids {
+        let known = SELECTABLE_METRICS.contains(&m.as_str())
+            || m.strip_prefix("custom:").is_some_and(|name| !name.trim().is_empty());
+        if !known {
+            return Err(PerfSightError::invalid_input(
+                field,
+                format!("unknown metric '{}' (cpu, memory, gpu, js_heap, custom:<name>)", m),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// A subset of a report's samples: some PIDs, some metric families, a time range (inclusive).
+/// Every part is optional; an empty selection keeps everything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MetricSelection {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pids: Vec<u32>,
+    /// "cpu", "memory", "gpu", "js_heap", "custom:<name>" or "custom:*".
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub metrics: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl MetricSelection {
+    pub fn is_empty(&self) -> bool {
+        self.pids.is_empty() && self.metrics.is_empty() && self.from.is_none() && self.to.is_none()
+    }
+
+    pub fn validate(&self) -> Result<(), PerfSightError> {
+        check_metric_ids("metrics", &self.metrics)?;
+        if let (Some(from), Some(to)) = (self.from, self.to) {
+            if from > to {
+                return Err(PerfSightError::invalid_input("from", "must not be after `to`"));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn wants(&self, family: &str) -> bool {
+        self.metrics.is_empty() || self.metrics.iter().any(|m| m == family)
+    }
+
+    pub fn wants_custom(&self, name: &str) -> bool {
+        self.metrics.is_empty()
+            || self
+                .metrics
+                .iter()
+                .filter_map(|m| m.strip_prefix("custom:"))
+                .any(|n| n == "*" || n == name)
+    }
+
+    pub fn in_range(&self, t: DateTime<Utc>) -> bool {
+        self.from.is_none_or(|from| t >= from) && self.to.is_none_or(|to| t <= to)
+    }
+
+    /// Copy of `metrics` restricted to the selection. Unselected optional fields become None and
+    /// unselected CPU/memory become 0; points left with no selected value and empty batches are dropped.
+    pub fn apply(&self, metrics: &[BatchMetric]) -> Vec<BatchMetric> {
+        let (cpu, memory) = (self.wants("cpu"), self.wants("memory"));
+        let (gpu, js_heap) = (self.wants("gpu"), self.wants("js_heap"));
+        metrics
+            .iter()
+            .filter(|b| self.in_range(b.timestamp))
+            .filter_map(|b| {
+                let points: HashMap<u32, MetricPoint> = b
+                    .metrics
+                    .iter()
+                    .filter(|(pid, _)| self.pids.is_empty() || self.pids.contains(pid))
+                    .filter_map(|(pid, p)| {
+                        let mut p = p.clone();
+                        if !cpu {
+                            p.cpu_usage = 0.0;
+                            p.cpu_os_usage = 0.0;
+                            p.cpu_chrome_usage = None;
+                        }
+                        if !memory {
+                            p.memory_rss = 0;
+                            p.memory_footprint = None;
+                            p.memory_private = None;
+                            p.memory_basis = None;
+                        }
+                        if !gpu {
+                            p.gpu_usage = None;
+                        }
+                        if !js_heap {
+                            p.js_heap_size = None;
+                            p.js_heap_total_size = None;
+                        }
+                        p.custom_metrics = p
+                            .custom_metrics
+                            .map(|m| m.into_iter().filter(|(k, _)| self.wants_custom(k)).collect::<HashMap<_, _>>())
+                            .filter(|m| !m.is_empty());
+                        let has_data = cpu
+                            || memory
+                            || p.gpu_usage.is_some()
+                            || p.js_heap_size.is_some()
+                            || p.custom_metrics.is_some();
+                        has_data.then_some((*pid, p))
+                    })
+                    .collect();
+                if points.is_empty() {
+                    None
+                } else {
+                    Some(BatchMetric { timestamp: b.timestamp, metrics: points })
+                }
+            })
+            .collect()
+    }
+}
+
 // CDP JSON Structures (http://localhost:9222/json/list)
 #[derive(Debug, Deserialize, Clone)]
 pub struct CdpTarget {
@@ -95,3 +1120,878 @@ pub struct CdpTarget {
     #[serde(rename = "webSocketDebuggerUrl")]
     pub web_socket_debugger_url: Option<String>,
 }
+
+// ---- Report meta ----------------------------------------------------------------------------
+//
+// `reports.meta_json` has grown over several releases, so every typed field below is read
+// leniently: a value of the wrong shape becomes the field's default instead of failing the whole
+// report, and keys we don't model are kept in `extra` so they are written back unchanged.
+
+/// Deserialize `T`, falling back to `T::default()` when the value has an unexpected shape.
+pub fn lenient<'de, D, T>(d: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned + Default,
+{
+    let v = Value::deserialize(d)?;
+    Ok(serde_json::from_value(v).unwrap_or_default())
+}
+
+/// Non-negative integer that may have been stored as a float or a numeric string.
+pub fn lenient_u64<'de, D: Deserializer<'de>>(d: D) -> Result<Option<u64>, D::Error> {
+    let v = Value::deserialize(d)?;
+    Ok(match v {
+        Value::Number(n) => n.as_u64().or_else(|| n.as_f64().filter(|f| *f >= 0.0).map(|f| f.round() as u64)),
+        Value::String(s) => s.trim().parse::<f64>().ok().filter(|f| *f >= 0.0).map(|f| f.round() as u64),
+        _ => None,
+    })
+}
+
+/// Array whose malformed elements are skipped rather than failing the whole list.
+pub fn lenient_vec<'de, D, T>(d: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    let v = Value::deserialize(d)?;
+    Ok(match v {
+        Value::Array(arr) => arr.into_iter().filter_map(|x| serde_json::from_value(x).ok()).collect(),
+        _ => Vec::new(),
+    })
+}
+
+pub fn normalize_folder_path(raw: &str) -> String {
+    raw.split(['/', '\\'])
+        .map(|p| {
+            let name: String = p.chars().filter(|c| !c.is_control()).take(MAX_FOLDER_NAME_CHARS).collect();
+            name.trim().to_string()
+        })
+        .filter(|p| !p.is_empty() && p != "." && p != "..")
+        .take(MAX_FOLDER_DEPTH)
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Longest folder name (one path segment), in characters.
+pub const MAX_FOLDER_NAME_CHARS: usize = 100;
+/// Deepest folder nesting.
+pub const MAX_FOLDER_DEPTH: usize = 16;
+
+/// Reject a folder path given by the user that `normalize_folder_path` would otherwise have to
+/// rewrite: control characters, backslashes, "..", names over `MAX_FOLDER_NAME_CHARS` or more
+/// than `MAX_FOLDER_DEPTH` levels.
+pub fn check_folder_path(field: &str, raw: &str) -> Result<(), PerfSightError> {
+    if raw.chars().any(char::is_control) {
+        return Err(PerfSightError::invalid_input(field, "folder names cannot contain control characters"));
+    }
+    if raw.contains('\\') {
+        return Err(PerfSightError::invalid_input(field, "use '/' to separate folders"));
+    }
+    let names: Vec<&str> = raw.split('/').map(str::trim).filter(|p| !p.is_empty() && *p != ".").collect();
+    if names.contains(&"..") {
+        return Err(PerfSightError::invalid_input(field, "'..' is not a valid folder name"));
+    }
+    if let Some(name) = names.iter().find(|p| p.chars().count() > MAX_FOLDER_NAME_CHARS) {
+        return Err(PerfSightError::invalid_input(
+            field,
+            format!("folder name '{}…' is longer than {} characters", name.chars().take(20).collect::<String>(), MAX_FOLDER_NAME_CHARS),
+        ));
+    }
+    if names.len() > MAX_FOLDER_DEPTH {
+        return Err(PerfSightError::invalid_input(field, format!("folders can be nested at most {} levels deep", MAX_FOLDER_DEPTH)));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AppMeta {
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "lenient")]
+    pub version: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Host the report was recorded on.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EnvMeta {
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "lenient")]
+    pub os: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "lenient")]
+    pub arch: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "lenient")]
+    pub device_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "lenient_u64")]
+    pub cpu_logical_cores: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "lenient_u64")]
+    pub cpu_physical_cores: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "lenient")]
+    pub cpu_brand: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "lenient")]
+    pub cpu_vendor: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "lenient_u64")]
+    pub cpu_frequency_mhz: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "lenient_u64")]
+    pub total_memory_bytes: Option<u64>,
+    /// IANA zone of the capturing machine, e.g. "Europe/Berlin".
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "lenient")]
+    pub timezone: Option<String>,
+    /// UTC offset at save time, e.g. "+02:00".
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "lenient")]
+    pub utc_offset: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// What a `RunEvent` records. Unknown kinds from newer versions read back as `Other`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunEventKind {
+    Started,
+    Stopped,
+    /// A data source (detail.source) connected or went away.
+    SourceConnected,
+    SourceDisconnected,
+    /// A target selector changed the PID set (detail.added / detail.removed).
+    MembershipChanged,
+    /// The spike guard replaced a memory value (detail.prev_mb / detail.current_mb).
+    SampleClamped,
+    /// The collector reported a failure or exited (detail.message / detail.code).
+    CollectorError,
+    /// Wall clock jumped past the progress ticker, usually system sleep (detail.gap_ms).
+    MachineSlept,
+    /// A labelled point in the run, e.g. from a scenario script (detail.label). With detail.phase
+    /// it starts that phase, or ends it when detail.boundary is "end" (see `analysis::phase_windows`).
+    Marker,
+    /// The sidecar samples at another interval or PID set than requested
+    /// (detail.requested_interval_ms / detail.effective_interval_ms, detail.dropped_pids).
+    SamplingAdjusted,
+    /// A data source went silent mid-run (detail.source, detail.last_seen_at, detail.threshold_ms).
+    SourceStalled,
+    /// A stalled source delivered again (detail.source, detail.resumed_at, detail.gap_ms).
+    SourceRecovered,
+    /// macOS moved a monitored process between foreground and background scheduling, e.g. App
+    /// Nap or an occluded window (pid, detail.from / detail.to).
+    ProcessRoleChanged,
+    /// The first batch of a browser-mode run (detail.batch_timestamp). Its Chrome CPU comes from
+    /// a pre-warm sample taken just before, so analysis may leave it out.
+    Warmup,
+    /// A monitored tab's title changed, unread counters aside (pid, detail.from / detail.to).
+    TabTitleChanged,
+    /// A monitored process went away mid-run (pid; detail is its `ProcessTermination`).
+    ProcessExited,
+    #[serde(other)]
+    Other,
+}
+
+/// How the OS schedules a monitored process (macOS: the QoS classes its CPU time ran at).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessRole {
+    Foreground,
+    Background,
+}
+
+/// Time one PID was seen in each role, saved per PID as `meta.collection.process_roles`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProcessRoleSummary {
+    /// Time between the first and last classified tick.
+    pub observed_ms: i64,
+    pub background_ms: i64,
+    /// `background_ms / observed_ms` (0 when nothing was observed).
+    pub background_fraction: f64,
+    pub transitions: u32,
+}
+
+/// How a monitored process ended, as far as PerfSight could tell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TerminationReason {
+    /// Exit code 0.
+    CleanExit,
+    /// A non-zero exit code.
+    ErrorExit,
+    /// Killed by a signal that doesn't indicate a fault (SIGTERM, SIGKILL, ...).
+    Signaled,
+    /// A fault: a crash signal, a crash report, a WER event or a core dump.
+    Crashed,
+    Unknown,
+}
+
+/// Why a monitored process ended, with whatever the source could tell.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProcessTermination {
+    pub reason: TerminationReason,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signal: Option<i32>,
+    /// Where the reason came from: "wait_status", "crash_report", "wer" or "coredumpctl".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Source specifics, e.g. the crash report path or the exception code.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl ProcessTermination {
+    pub fn unknown() -> Self {
+        Self { reason: TerminationReason::Unknown, exit_code: None, signal: None, source: None, detail: None }
+    }
+}
+
+/// A monitored process that exited during the run, saved per PID as
+/// `meta.collection.process_lifecycle`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessLifecycle {
+    /// Last CPU/memory sample before the exit, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_sample_at: Option<DateTime<Utc>>,
+    /// When the exit was noticed (within a second of it).
+    pub exited_at: DateTime<Utc>,
+    pub termination: ProcessTermination,
+}
+
+/// Sampling the sidecar acknowledged for a run, saved as `meta.collection.effective_*`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EffectiveSampling {
+    pub interval_ms: u64,
+    /// Target PIDs the sidecar samples.
+    pub pids: Vec<u32>,
+    /// Target PIDs it dropped (already gone when the command arrived).
+    pub dropped_pids: Vec<u32>,
+}
+
+/// "Something happened at time T" during a run, stored in report meta under `events`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunEvent {
+    pub timestamp: DateTime<Utc>,
+    pub kind: RunEventKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub detail: Value,
+}
+
+impl RunEvent {
+    pub fn now(kind: RunEventKind, pid: Option<u32>, detail: Value) -> Self {
+        Self { timestamp: Utc::now(), kind, pid, detail }
+    }
+}
+
+/// One source that delivered samples during a run, saved under `collection.data_sources`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DataSourceInfo {
+    /// "sidecar" | "websocket" | "http" | "native"
+    pub source: String,
+    /// What measured the numbers: "psutil", "sysinfo", "simulate", "extension" or "http".
+    pub backend: String,
+    /// Per-PID points received from this source.
+    pub samples: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_sample_at: Option<String>,
+    /// Collector sidecar version.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ws_port: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_port: Option<u16>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extension_versions: Vec<String>,
+    /// Messages the source's `seq` numbers say were sent vs received; None when it sent none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<SequenceStats>,
+    /// Sidecar stdout lines that could not be decoded; None when there were none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol_errors: Option<u64>,
+}
+
+/// Delivery accounting from a source's `seq` numbers. `lost` = `expected` - `received`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SequenceStats {
+    pub expected: u64,
+    pub received: u64,
+    pub lost: u64,
+}
+
+impl SequenceStats {
+    pub fn loss_ratio(&self) -> f64 {
+        if self.expected == 0 {
+            0.0
+        } else {
+            self.lost as f64 / self.expected as f64
+        }
+    }
+}
+
+/// PerfSight's own usage during a self-monitored run, saved as `meta.overhead`. CPU and memory
+/// are the per-batch totals over `pids`, memory in MB.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SelfOverhead {
+    pub pids: Vec<u32>,
+    pub samples: u64,
+    pub avg_cpu: f64,
+    pub max_cpu: f64,
+    pub avg_mem_mb: f64,
+    pub max_mem_mb: f64,
+}
+
+/// How the run was collected.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CollectionMeta {
+    /// "system" | "browser" | "imported"
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "lenient")]
+    pub mode: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "lenient")]
+    pub metric_standard: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "lenient_u64")]
+    pub interval_ms: Option<u64>,
+    #[serde(default, deserialize_with = "lenient_vec")]
+    pub target_pids: Vec<u32>,
+    /// Canonical folder location (see `ReportMeta::folder_path`).
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "lenient")]
+    pub folder_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "lenient")]
+    pub started_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "lenient")]
+    pub ended_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "lenient_u64")]
+    pub duration_seconds: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "lenient_u64")]
+    pub stop_after_seconds: Option<u64>,
+    /// Older UI builds nested the test context here.
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "lenient")]
+    pub test_context: Option<TestContext>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Prefix of the title given to runs saved without a scenario name.
+pub const AUTO_TITLE_PREFIX: &str = "Test Run - ";
+
+/// Typed view of `reports.meta_json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReportMeta {
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "lenient_u64")]
+    pub schema_version: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "lenient")]
+    pub app: Option<AppMeta>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "lenient")]
+    pub env: Option<EnvMeta>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "lenient")]
+    pub collection: Option<CollectionMeta>,
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "lenient")]
+    pub test_context: Option<TestContext>,
+    #[serde(default, deserialize_with = "lenient_vec")]
+    pub process_aliases: Vec<ProcessAlias>,
+    /// Raw entries: the process info shape changed across versions.
+    #[serde(default, deserialize_with = "lenient")]
+    pub process_snapshot: Vec<Value>,
+    /// Legacy copy of `collection.folder_path`, still written for older readers.
+    #[serde(default, skip_serializing_if = "Option::is_none", deserialize_with = "lenient")]
+    pub folder_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty", deserialize_with = "lenient_vec")]
+    pub budgets: Vec<PerformanceBudget>,
+    /// Timeline of things that happened during the run, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty", deserialize_with = "lenient_vec")]
+    pub events: Vec<RunEvent>,
+    /// Everything else (definitions, versions, import, webhook, ...), preserved as-is.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+    /// The JSON this meta was read from (see `to_json_string`); None for a meta built in code.
+    #[serde(skip)]
+    pub source: Option<std::sync::Arc<MetaSource>>,
+}
+
+/// Stored meta JSON and what the typed fields made of it.
+#[derive(Debug)]
+pub struct MetaSource {
+    stored: Value,
+    parsed: Value,
+}
+
+// Put back the stored form of every value of `current` that is still what reading `stored` gave
+// (`parsed`), so values the lenient fields rejected or normalized are written back verbatim.
+// Keys dropped while reading come back unless code has set them since; defaults reading added
+// are left out again.
+fn restore_unchanged(current: &mut Value, parsed: &Value, stored: &Value) {
+    if current == parsed {
+        *current = stored.clone();
+        return;
+    }
+    let (Value::Object(current), Value::Object(parsed), Value::Object(stored)) = (current, parsed, stored) else {
+        return;
+    };
+    for (key, stored_value) in stored {
+        match (current.get_mut(key), parsed.get(key)) {
+            (None, None) => {
+                current.insert(key.clone(), stored_value.clone());
+            }
+            (Some(value), Some(parsed_value)) => restore_unchanged(value, parsed_value, stored_value),
+            // Removed, or set where the stored value was unreadable: the new value wins.
+            _ => {}
+        }
+    }
+    current.retain(|key, value| stored.contains_key(key) || parsed.get(key) != Some(value));
+}
+
+impl ReportMeta {
+    /// Parse stored meta JSON; unreadable input yields an empty meta.
+    pub fn from_json_str(s: &str) -> Self {
+        serde_json::from_str(s).map(Self::from_value).unwrap_or_default()
+    }
+
+    pub fn from_value(v: Value) -> Self {
+        if !v.is_object() {
+            return Self::default();
+        }
+        let mut meta: ReportMeta = serde_json::from_value(v.clone()).unwrap_or_default();
+        if let Ok(parsed) = serde_json::to_value(&meta) {
+            meta.source = Some(std::sync::Arc::new(MetaSource { stored: v, parsed }));
+        }
+        meta
+    }
+
+    /// Serialize for storage. Whatever wasn't changed since `from_json_str` is written exactly as
+    /// it was read, malformed values included, so moves and renames don't rewrite them.
+    pub fn to_json_string(&self) -> String {
+        let Ok(mut value) = serde_json::to_value(self) else { return "{}".to_string() };
+        if let Some(source) = &self.source {
+            restore_unchanged(&mut value, &source.parsed, &source.stored);
+        }
+        value.to_string()
+    }
+
+    /// Normalized folder path. Canonical location is `collection.folder_path`; `folder_path`
+    /// at the top level is the back-compat fallback.
+    pub fn folder_path(&self) -> String {
+        let raw = self
+            .collection
+            .as_ref()
+            .and_then(|c| c.folder_path.as_deref())
+            .or(self.folder_path.as_deref())
+            .unwrap_or("");
+        normalize_folder_path(raw)
+    }
+
+    /// Write the folder path to both the canonical and the legacy location.
+    pub fn set_folder_path(&mut self, folder_path: &str) {
+        self.folder_path = Some(folder_path.to_string());
+        self.collection.get_or_insert_with(Default::default).folder_path = Some(folder_path.to_string());
+    }
+
+    /// Tags from `test_context.tags` and the older `collection.test_context.tags`,
+    /// trimmed and de-duplicated case-insensitively (first-seen casing wins).
+    pub fn tags(&self) -> Vec<String> {
+        let sources = [
+            self.test_context.as_ref(),
+            self.collection.as_ref().and_then(|c| c.test_context.as_ref()),
+        ];
+        let mut seen = std::collections::HashSet::<String>::new();
+        let mut out: Vec<String> = Vec::new();
+        for t in sources.iter().flatten().filter_map(|tc| tc.tags.as_ref()).flatten() {
+            let trimmed = t.trim();
+            if !trimmed.is_empty() && seen.insert(trimmed.to_lowercase()) {
+                out.push(trimmed.to_string());
+            }
+        }
+        out
+    }
+
+    /// Generated onboarding data (see `sample_data`), kept out of folder trends.
+    pub fn is_sample(&self) -> bool {
+        self.extra.get("sample").and_then(Value::as_bool) == Some(true)
+    }
+
+    pub fn scenario_name(&self) -> Option<&str> {
+        self.test_context
+            .as_ref()
+            .and_then(|tc| tc.scenario_name.as_deref())
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+    }
+
+    /// Title to show for a report. The stored title wins (it's what renames update); the scenario
+    /// name is only used when the stored title is empty or still the auto-generated one.
+    pub fn display_title(&self, stored: &str) -> String {
+        let stored = stored.trim();
+        match self.scenario_name() {
+            Some(scenario) if stored.is_empty() || stored.starts_with(AUTO_TITLE_PREFIX) => scenario.to_string(),
+            _ => stored.to_string(),
+        }
+    }
+
+    pub fn build_id(&self) -> Option<&str> {
+        self.test_context
+            .as_ref()
+            .and_then(|tc| tc.build_id.as_deref())
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+    }
+
+    pub fn duration_seconds(&self) -> Option<u64> {
+        self.collection.as_ref().and_then(|c| c.duration_seconds)
+    }
+
+    /// PerfSight's own PIDs when the run monitored itself (`collection.self_pids`).
+    pub fn self_pids(&self) -> Vec<u32> {
+        self.collection
+            .as_ref()
+            .and_then(|c| c.extra.get("self_pids"))
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Foreground/background time per PID (`collection.process_roles`, macOS runs only).
+    pub fn process_roles(&self) -> HashMap<u32, ProcessRoleSummary> {
+        self.collection
+            .as_ref()
+            .and_then(|c| c.extra.get("process_roles"))
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Sources recorded under `collection.data_sources` (empty for older reports).
+    pub fn data_sources(&self) -> Vec<DataSourceInfo> {
+        self.collection
+            .as_ref()
+            .and_then(|c| c.extra.get("data_sources"))
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// Processes that exited mid-run (`collection.process_lifecycle`), by PID.
+    pub fn process_lifecycle(&self) -> BTreeMap<u32, ProcessLifecycle> {
+        self.collection
+            .as_ref()
+            .and_then(|c| c.extra.get("process_lifecycle"))
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn mode(&self) -> Option<&str> {
+        self.collection
+            .as_ref()
+            .and_then(|c| c.mode.as_deref())
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+    }
+
+    /// CPU normalization the report was collected with; reports from before the setting existed
+    /// used their platform's default.
+    pub fn cpu_normalization(&self) -> Option<CpuNormalization> {
+        self.extra
+            .get("definitions")
+            .and_then(|d| d.get("cpu_normalization"))
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .or_else(|| {
+                self.env
+                    .as_ref()
+                    .and_then(|e| e.os.as_deref())
+                    .map(CpuNormalization::default_for_os)
+            })
+    }
+
+    pub fn app_version(&self) -> Option<&str> {
+        self.app.as_ref().and_then(|a| a.version.as_deref())
+    }
+
+    /// Replace the alias list and mirror it into the `alias` field of `process_snapshot` entries.
+    /// Blank aliases clear the PID's alias.
+    pub fn set_aliases(&mut self, aliases: &[ProcessAlias]) {
+        self.process_aliases = aliases
+            .iter()
+            .map(|a| ProcessAlias { pid: a.pid, alias: a.alias.trim().to_string() })
+            .filter(|a| !a.alias.is_empty())
+            .collect();
+        let by_pid: HashMap<u32, String> = self.process_aliases.iter().map(|a| (a.pid, a.alias.clone())).collect();
+        for p in self.process_snapshot.iter_mut() {
+            let Some(obj) = p.as_object_mut() else { continue };
+            let Some(pid) = obj.get("pid").and_then(|v| v.as_u64()) else { continue };
+            let alias = by_pid.get(&(pid as u32)).cloned().map(Value::String).unwrap_or(Value::Null);
+            obj.insert("alias".to_string(), alias);
+        }
+    }
+
+    /// PID -> alias, skipping blank aliases. Aliases only recorded in `process_snapshot` entries
+    /// (older and imported reports) fill in the PIDs `process_aliases` lacks.
+    pub fn aliases(&self) -> HashMap<u32, String> {
+        let mut by_pid: HashMap<u32, String> = self
+            .process_snapshot
+            .iter()
+            .filter_map(|p| {
+                let pid = p.get("pid")?.as_u64()? as u32;
+                let alias = p.get("alias")?.as_str()?.trim();
+                (!alias.is_empty()).then(|| (pid, alias.to_string()))
+            })
+            .collect();
+        by_pid.extend(
+            self.process_aliases
+                .iter()
+                .filter(|a| !a.alias.trim().is_empty())
+                .map(|a| (a.pid, a.alias.trim().to_string())),
+        );
+        by_pid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // One malformed value per typed field, top level and nested.
+    fn malformed_fields() -> Vec<(&'static str, Value)> {
+        vec![
+            ("schema_version", json!("one")),
+            ("app", json!("PerfSight 1.0")),
+            ("env", json!(["macos"])),
+            ("collection", json!(42)),
+            ("test_context", json!(true)),
+            ("process_aliases", json!({ "1": "main" })),
+            ("process_snapshot", json!("n/a")),
+            ("folder_path", json!(["A", "B"])),
+            ("budgets", json!("strict")),
+            ("events", json!({ "kind": "marker" })),
+        ]
+    }
+
+    #[test]
+    fn malformed_fields_round_trip_verbatim() {
+        for (field, value) in malformed_fields() {
+            let stored = json!({ "schema_version": 1, field: value.clone(), "custom": { "kept": [1, 2.5] } });
+            let meta = ReportMeta::from_json_str(&stored.to_string());
+            let written: Value = serde_json::from_str(&meta.to_json_string()).unwrap();
+            assert_eq!(written, stored, "field {}", field);
+        }
+    }
+
+    #[test]
+    fn malformed_nested_fields_round_trip_verbatim() {
+        for (field, value) in [
+            ("interval_ms", json!("fast")),
+            ("target_pids", json!("1,2")),
+            ("mode", json!(7)),
+            ("started_at", json!({ "s": 1 })),
+            ("duration_seconds", json!(-3)),
+        ] {
+            let stored = json!({ "collection": { "mode": "system", field: value.clone() } });
+            let meta = ReportMeta::from_json_str(&stored.to_string());
+            let written: Value = serde_json::from_str(&meta.to_json_string()).unwrap();
+            assert_eq!(written, stored, "collection.{}", field);
+        }
+    }
+
+    #[test]
+    fn moving_a_report_keeps_its_malformed_fields() {
+        for (field, value) in malformed_fields() {
+            if field == "collection" || field == "folder_path" {
+                continue;
+            }
+            let stored = json!({ "collection": { "folder_path": "Old", "interval_ms": "x" }, field: value.clone() });
+            let mut meta = ReportMeta::from_json_str(&stored.to_string());
+            meta.set_folder_path("New/Place");
+            let written: Value = serde_json::from_str(&meta.to_json_string()).unwrap();
+            assert_eq!(written[field], value, "field {}", field);
+            assert_eq!(written["collection"]["interval_ms"], json!("x"));
+            assert_eq!(written["collection"]["folder_path"], json!("New/Place"));
+            assert_eq!(written["folder_path"], json!("New/Place"));
+        }
+    }
+
+    #[test]
+    fn fields_set_in_code_replace_unreadable_ones() {
+        let mut meta = ReportMeta::from_json_str(r#"{"collection": 42, "folder_path": ["A"]}"#);
+        meta.set_folder_path("Fixed");
+        let written: Value = serde_json::from_str(&meta.to_json_string()).unwrap();
+        assert_eq!(written["collection"], json!({ "folder_path": "Fixed", "target_pids": [] }));
+        assert_eq!(written["folder_path"], json!("Fixed"));
+
+        let mut meta = ReportMeta::from_json_str(r#"{"budgets": "strict", "events": []}"#);
+        meta.extra.insert("note".to_string(), json!("added"));
+        meta.events.clear();
+        let written: Value = serde_json::from_str(&meta.to_json_string()).unwrap();
+        assert_eq!(written, json!({ "budgets": "strict", "events": [], "note": "added" }));
+    }
+
+    fn collection_config(overrides: Value) -> CollectionConfig {
+        let mut v = json!({ "target_pids": [1, 2], "interval_ms": 1000, "mode": "system" });
+        v.as_object_mut().unwrap().extend(overrides.as_object().unwrap().clone());
+        serde_json::from_value(v).unwrap()
+    }
+
+    fn log_metric(pattern: &str, captures: Value) -> Value {
+        json!([{ "name": "latency", "pattern": pattern, "unit": null, "target_pid": null, "captures": captures }])
+    }
+
+    fn invalid_field(err: PerfSightError) -> String {
+        match err {
+            PerfSightError::InvalidInput { field, .. } => field,
+            other => panic!("expected InvalidInput, got {:?}", other),
+        }
+    }
+
+    // One case per rule: the config overrides and the field the rejection must name.
+    #[test]
+    fn collection_config_validate_rejects_each_rule_naming_its_field() {
+        let cases: Vec<(&str, Value, &str)> = vec![
+            ("no pids", json!({ "target_pids": [] }), "target_pids"),
+            (
+                "selector matched nothing",
+                json!({ "target_pids": [], "target_selector": { "name_contains": "chrome" } }),
+                "target_selector",
+            ),
+            ("interval below range, strict", json!({ "interval_ms": 50, "strict": true }), "interval_ms"),
+            ("interval above range, strict", json!({ "interval_ms": 60_001, "strict": true }), "interval_ms"),
+            ("adaptive window", json!({ "adaptive_sampling": { "window": 1 } }), "adaptive_sampling.window"),
+            ("zero stop_after_seconds", json!({ "stop_after_seconds": 0 }), "stop_after_seconds"),
+            (
+                "alias of an unmonitored pid",
+                json!({ "process_aliases": [{ "pid": 9, "alias": "ghost" }] }),
+                "process_aliases",
+            ),
+            (
+                "bad value format",
+                json!({ "log_metric_configs": [{ "name": "x", "pattern": r"(\d+)", "unit": null, "target_pid": null, "value_format": { "multiplier": 0.0 } }] }),
+                "log_metric_configs[0].value_format",
+            ),
+            ("pattern too long", json!({ "log_metric_configs": log_metric(&"a".repeat(MAX_LOG_PATTERN_LEN + 1), json!({})) }), "log_metric_configs[0].pattern"),
+            ("invalid regex", json!({ "log_metric_configs": log_metric(r"(\d+", json!({})) }), "log_metric_configs[0].pattern"),
+            ("no capture group", json!({ "log_metric_configs": log_metric(r"\d+ ms", json!({})) }), "log_metric_configs[0].pattern"),
+            ("two capture groups", json!({ "log_metric_configs": log_metric(r"(\d+) (\d+)", json!({})) }), "log_metric_configs[0].pattern"),
+            (
+                "capture names a missing group",
+                json!({ "log_metric_configs": log_metric(r"(?P<fps>\d+)", json!({ "ms": "frame_ms" })) }),
+                "log_metric_configs[0].pattern",
+            ),
+            (
+                "capture with an empty metric name",
+                json!({ "log_metric_configs": log_metric(r"(?P<fps>\d+)", json!({ "fps": " " })) }),
+                "log_metric_configs[0].captures.fps",
+            ),
+            (
+                "two captures feeding one metric",
+                json!({ "log_metric_configs": log_metric(r"(?P<a>\d+) (?P<b>\d+)", json!({ "a": "fps", "b": "fps" })) }),
+                "log_metric_configs[0].captures.b",
+            ),
+            (
+                "capture duplicating another config's metric",
+                json!({ "log_metric_configs": [
+                    { "name": "fps", "pattern": r"fps (\d+)", "unit": null, "target_pid": null },
+                    { "name": "stats", "pattern": r"fps=(?P<fps>\d+)", "unit": null, "target_pid": null, "captures": { "fps": { "name": "fps", "unit": "Hz" } } },
+                ] }),
+                "log_metric_configs[1].captures.fps",
+            ),
+            ("folder escapes with ..", json!({ "folder_path": "A/../B" }), "folder_path"),
+            ("folder with a backslash", json!({ "folder_path": r"A\B" }), "folder_path"),
+        ];
+        for (name, overrides, field) in cases {
+            let err = collection_config(overrides).validate().expect_err(name);
+            assert_eq!(invalid_field(err), field, "{}", name);
+        }
+    }
+
+    #[test]
+    fn collection_config_validate_fixes_what_it_can() {
+        let mut config = collection_config(json!({
+            "target_pids": [1, 2, 1, 2, 3],
+            "interval_ms": 0,
+            "folder_path": "  Release / ./ Scenario/ ",
+            "stream_to_file": "  ",
+            "process_aliases": [{ "pid": 3, "alias": "main" }],
+        }));
+        let warnings = config.validate().unwrap();
+        assert_eq!(config.target_pids, vec![1, 2, 3]);
+        assert_eq!(config.interval_ms, MIN_INTERVAL_MS);
+        assert_eq!(config.folder_path.as_deref(), Some("Release/Scenario"));
+        assert_eq!(config.stream_to_file, None);
+        assert_eq!(warnings, vec!["Removed 2 duplicate PID(s)".to_string(), "interval_ms 0 clamped to 100".to_string()]);
+
+        let mut config = collection_config(json!({ "interval_ms": 120_000 }));
+        assert_eq!(config.validate().unwrap(), vec!["interval_ms 120000 clamped to 60000".to_string()]);
+        assert_eq!(config.interval_ms, MAX_INTERVAL_MS);
+
+        let mut config = collection_config(json!({
+            "mode": "browser",
+            "adaptive_sampling": {},
+            "log_metric_configs": log_metric(r"(?P<fps>\d+) fps (?P<ms>\d+)", json!({ "fps": "fps", "ms": { "name": "frame_ms", "unit": "ms" } })),
+        }));
+        assert_eq!(config.validate().unwrap(), vec!["adaptive_sampling is ignored in browser mode".to_string()]);
+
+        let mut config = collection_config(json!({ "folder_path": " / " }));
+        assert!(config.validate().is_ok());
+        assert_eq!(config.folder_path, None);
+    }
+
+    #[test]
+    fn hostile_folder_paths_are_rejected_or_normalized() {
+        let long = "x".repeat(MAX_FOLDER_NAME_CHARS + 1);
+        let deep = vec!["d"; MAX_FOLDER_DEPTH + 1].join("/");
+        for raw in ["A/../B", r"A\B", "A/\u{0}B", "tab\there", long.as_str(), deep.as_str()] {
+            assert!(check_folder_path("folder_path", raw).is_err(), "{:?} accepted", raw);
+        }
+        for raw in ["100%/a_b", "🚀 launch", " A / ./ B ", "x".repeat(MAX_FOLDER_NAME_CHARS).as_str()] {
+            assert!(check_folder_path("folder_path", raw).is_ok(), "{:?} rejected", raw);
+        }
+        assert_eq!(normalize_folder_path(" A / ./ B /"), "A/B");
+        assert_eq!(normalize_folder_path(r"..\..\etc"), "etc");
+        assert_eq!(normalize_folder_path("100%/a_b"), "100%/a_b");
+        assert_eq!(normalize_folder_path("A/\u{1b}B\u{7f}"), "A/B");
+        assert_eq!(normalize_folder_path(&long).chars().count(), MAX_FOLDER_NAME_CHARS);
+        assert_eq!(normalize_folder_path(&deep).split('/').count(), MAX_FOLDER_DEPTH);
+    }
+
+    // (locale, captured text as that locale's app logs it, decimal separator configured or None to guess,
+    // expected value).
+    const LOCALE_NUMBERS: [(&str, &str, Option<char>, f64); 14] = [
+        ("en-US", "1,234.56 ms", None, 1234.56),
+        ("en-GB", "0.125s", None, 0.125),
+        ("de-DE", "12,5 ms", Some(','), 12.5),
+        ("de-DE", "1.234,56 ms", None, 1234.56),
+        ("de-DE", "0,125", None, 0.125),
+        ("fr-FR", "1\u{202f}234,5 ms", None, 1234.5),
+        ("fr-CA", "1\u{a0}234,5", None, 1234.5),
+        ("sv-SE", " 1 234,5 ms ", None, 1234.5),
+        ("de-CH", "1'234.5 ms", None, 1234.5),
+        ("en-IN", "12,34,567.8", None, 1234567.8),
+        ("it-IT", "1.234", Some(','), 1234.0),
+        ("es-ES", "-3,75 %", Some(','), -3.75),
+        ("ja-JP", "1,234", None, 1234.0),
+        ("C", "-3.5e2us", None, -350.0),
+    ];
+
+    #[test]
+    fn log_values_parse_in_a_dozen_locale_formats() {
+        for (locale, text, decimal, expected) in LOCALE_NUMBERS {
+            let format = LogValueFormat { decimal_separator: decimal, ..Default::default() };
+            let parsed = format.parse(text);
+            assert!(parsed.is_some_and(|v| (v - expected).abs() < 1e-9), "{} {:?}: {:?} != {}", locale, text, parsed, expected);
+        }
+    }
+
+    #[test]
+    fn log_value_format_options_change_the_reading() {
+        let de = |thousands: Option<char>| LogValueFormat {
+            decimal_separator: Some(','),
+            thousands_separator: thousands,
+            ..Default::default()
+        };
+        // "1,234" is grouping when guessed, a decimal when the locale says so.
+        assert_eq!(LogValueFormat::default().parse("1,234"), Some(1234.0));
+        assert_eq!(de(None).parse("1,234"), Some(1.234));
+        assert_eq!(de(Some('.')).parse("1.234.567,5"), Some(1234567.5));
+        // A second decimal separator is not a number.
+        assert_eq!(de(None).parse("1,2,3"), None);
+
+        let strict = LogValueFormat { strip_unit: false, ..Default::default() };
+        assert_eq!(strict.parse(" 12.5 "), Some(12.5));
+        assert_eq!(strict.parse("12.5 ms"), None);
+
+        let ms_to_s = LogValueFormat { multiplier: 0.001, ..Default::default() };
+        assert!(ms_to_s.parse("1.234,5 ms").is_some_and(|v| (v - 1.2345).abs() < 1e-12));
+
+        for text in ["", "ms", "n/a", ",", "e5"] {
+            assert_eq!(LogValueFormat::default().parse(text), None, "{:?}", text);
+        }
+    }
+}
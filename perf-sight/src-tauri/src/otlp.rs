@@ -0,0 +1,514 @@
+use std::collections::HashMap;
+use prost::Message;
+use serde::Serialize;
+use crate::models::{BatchMetric, ReportMeta};
+
+// Minimal subset of the OTLP metrics protobuf schema
+// (opentelemetry/proto/collector/metrics/v1/metrics_service.proto and friends).
+// Only gauges with double values are produced, so the rest of the schema is omitted.
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ExportMetricsServiceRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub resource_metrics: Vec<ResourceMetrics>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ResourceMetrics {
+    #[prost(message, optional, tag = "1")]
+    pub resource: Option<Resource>,
+    #[prost(message, repeated, tag = "2")]
+    pub scope_metrics: Vec<ScopeMetrics>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Resource {
+    #[prost(message, repeated, tag = "1")]
+    pub attributes: Vec<KeyValue>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ScopeMetrics {
+    #[prost(message, optional, tag = "1")]
+    pub scope: Option<InstrumentationScope>,
+    #[prost(message, repeated, tag = "2")]
+    pub metrics: Vec<Metric>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct InstrumentationScope {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(string, tag = "2")]
+    pub version: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Metric {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(string, tag = "2")]
+    pub description: String,
+    #[prost(string, tag = "3")]
+    pub unit: String,
+    #[prost(message, optional, tag = "5")]
+    pub gauge: Option<Gauge>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Gauge {
+    #[prost(message, repeated, tag = "1")]
+    pub data_points: Vec<NumberDataPoint>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct NumberDataPoint {
+    #[prost(message, repeated, tag = "7")]
+    pub attributes: Vec<KeyValue>,
+    #[prost(fixed64, tag = "3")]
+    pub time_unix_nano: u64,
+    // A oneof so 0.0 is still encoded (plain proto3 scalars skip default values).
+    #[prost(oneof = "number_data_point::Value", tags = "4")]
+    pub value: Option<number_data_point::Value>,
+}
+
+pub mod number_data_point {
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Value {
+        #[prost(double, tag = "4")]
+        AsDouble(f64),
+    }
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct KeyValue {
+    #[prost(string, tag = "1")]
+    pub key: String,
+    #[prost(message, optional, tag = "2")]
+    pub value: Option<AnyValue>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct AnyValue {
+    #[prost(oneof = "any_value::Value", tags = "1, 3")]
+    pub value: Option<any_value::Value>,
+}
+
+pub mod any_value {
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Value {
+        #[prost(string, tag = "1")]
+        StringValue(String),
+        #[prost(int64, tag = "3")]
+        IntValue(i64),
+    }
+}
+
+// Keep each request well below the common 4 MiB collector receive limit.
+const MAX_POINTS_PER_REQUEST: usize = 10_000;
+
+#[derive(Debug, Serialize)]
+pub struct OtlpExportResult {
+    pub data_points: usize,
+    pub requests: usize,
+}
+
+fn kv_str(key: &str, value: &str) -> KeyValue {
+    KeyValue {
+        key: key.to_string(),
+        value: Some(AnyValue { value: Some(any_value::Value::StringValue(value.to_string())) }),
+    }
+}
+
+fn kv_int(key: &str, value: i64) -> KeyValue {
+    KeyValue {
+        key: key.to_string(),
+        value: Some(AnyValue { value: Some(any_value::Value::IntValue(value)) }),
+    }
+}
+
+// (metric name, unit, description) of the built-in series.
+const CPU: (&str, &str, &str) = ("perfsight.cpu.usage", "%", "Primary CPU% of the process");
+const MEM_RSS: (&str, &str, &str) = ("perfsight.memory.rss", "By", "Resident set size");
+const MEM_PRIVATE: (&str, &str, &str) = ("perfsight.memory.private", "By", "Chrome private memory footprint");
+const MEM_FOOTPRINT: (&str, &str, &str) = ("perfsight.memory.footprint", "By", "OS memory footprint");
+const JS_HEAP: (&str, &str, &str) = ("perfsight.js_heap.size", "By", "JS heap size");
+const JS_HEAP_TOTAL: (&str, &str, &str) = ("perfsight.js_heap.total", "By", "JS heap total size");
+const GPU: (&str, &str, &str) = ("perfsight.gpu.usage", "%", "GPU usage");
+
+/// Per-PID attributes (alias, proc_type) plus run-level ones (scenario_name, build_id) from report meta.
+struct AttributeSource {
+    aliases: HashMap<u32, String>,
+    proc_types: HashMap<u32, String>,
+    run: Vec<KeyValue>,
+}
+
+impl AttributeSource {
+    fn from_meta(meta: &ReportMeta) -> Self {
+        let aliases = meta.aliases();
+        let mut proc_types = HashMap::new();
+        for p in &meta.process_snapshot {
+            let pid = p.get("pid").and_then(|v| v.as_u64());
+            let proc_type = p.get("proc_type").and_then(|v| v.as_str());
+            if let (Some(pid), Some(t)) = (pid, proc_type) {
+                proc_types.insert(pid as u32, t.to_string());
+            }
+        }
+        let mut run = Vec::new();
+        for (key, value) in [("scenario_name", meta.scenario_name()), ("build_id", meta.build_id())] {
+            if let Some(s) = value {
+                run.push(kv_str(key, s));
+            }
+        }
+        Self { aliases, proc_types, run }
+    }
+
+    fn for_pid(&self, pid: u32) -> Vec<KeyValue> {
+        let mut attrs = vec![kv_int("pid", pid as i64)];
+        if let Some(a) = self.aliases.get(&pid) {
+            attrs.push(kv_str("alias", a));
+        }
+        if let Some(t) = self.proc_types.get(&pid) {
+            attrs.push(kv_str("proc_type", t));
+        }
+        attrs.extend(self.run.iter().cloned());
+        attrs
+    }
+}
+
+/// Gauge points grouped by metric name, kept in first-seen order.
+#[derive(Default)]
+struct PointGroups {
+    order: Vec<(String, String, String)>,
+    points: HashMap<String, Vec<NumberDataPoint>>,
+    count: usize,
+}
+
+impl PointGroups {
+    fn push(&mut self, def: (&str, &str, &str), attributes: &[KeyValue], time_unix_nano: u64, value: f64) {
+        if !value.is_finite() {
+            return;
+        }
+        let (name, unit, description) = def;
+        if !self.points.contains_key(name) {
+            self.order.push((name.to_string(), unit.to_string(), description.to_string()));
+        }
+        self.points.entry(name.to_string()).or_default().push(NumberDataPoint {
+            attributes: attributes.to_vec(),
+            time_unix_nano,
+            value: Some(number_data_point::Value::AsDouble(value)),
+        });
+        self.count += 1;
+    }
+
+    fn into_request(mut self, resource: &Resource, app_version: &str) -> ExportMetricsServiceRequest {
+        let metrics = self
+            .order
+            .into_iter()
+            .map(|(name, unit, description)| Metric {
+                gauge: Some(Gauge { data_points: self.points.remove(&name).unwrap_or_default() }),
+                name,
+                description,
+                unit,
+            })
+            .collect();
+        ExportMetricsServiceRequest {
+            resource_metrics: vec![ResourceMetrics {
+                resource: Some(resource.clone()),
+                scope_metrics: vec![ScopeMetrics {
+                    scope: Some(InstrumentationScope {
+                        name: "perfsight".to_string(),
+                        version: app_version.to_string(),
+                    }),
+                    metrics,
+                }],
+            }],
+        }
+    }
+}
+
+/// Convert a saved report into OTLP export requests, each holding at most `MAX_POINTS_PER_REQUEST`
+/// data points. Sample timestamps are preserved.
+pub fn build_requests(
+    report_id: i64,
+    title: &str,
+    metrics: &[BatchMetric],
+    meta: &ReportMeta,
+) -> Vec<ExportMetricsServiceRequest> {
+    let attrs = AttributeSource::from_meta(meta);
+    let app_version = meta.app_version().unwrap_or("unknown").to_string();
+    let resource = Resource {
+        attributes: vec![
+            kv_str("service.name", "perfsight"),
+            kv_int("perfsight.report_id", report_id),
+            kv_str("perfsight.report_title", title),
+        ],
+    };
+
+    let mut requests = Vec::new();
+    let mut groups = PointGroups::default();
+    for batch in metrics {
+        let mut pids: Vec<_> = batch.metrics.keys().copied().collect();
+        pids.sort();
+        for pid in pids {
+            let p = &batch.metrics[&pid];
+            let attributes = attrs.for_pid(pid);
+            let ts = p.timestamp.timestamp_nanos_opt().unwrap_or(0).max(0) as u64;
+
+            let custom_only = p.custom_metrics.is_some()
+                && p.cpu_usage == 0.0
+                && p.memory_rss == 0
+                && p.memory_private.is_none();
+            if !custom_only {
+                groups.push(CPU, &attributes, ts, p.cpu_usage as f64);
+                groups.push(MEM_RSS, &attributes, ts, p.memory_rss as f64);
+                if let Some(v) = p.memory_private {
+                    groups.push(MEM_PRIVATE, &attributes, ts, v as f64);
+                }
+                if let Some(v) = p.memory_footprint {
+                    groups.push(MEM_FOOTPRINT, &attributes, ts, v as f64);
+                }
+                if let Some(v) = p.js_heap_size {
+                    groups.push(JS_HEAP, &attributes, ts, v as f64);
+                }
+                if let Some(v) = p.js_heap_total_size {
+                    groups.push(JS_HEAP_TOTAL, &attributes, ts, v as f64);
+                }
+                if let Some(v) = p.gpu_usage {
+                    groups.push(GPU, &attributes, ts, v as f64);
+                }
+            }
+            if let Some(custom) = &p.custom_metrics {
+                let mut names: Vec<_> = custom.keys().collect();
+                names.sort();
+                for name in names {
+                    let metric_name = format!("perfsight.custom.{}", name);
+                    groups.push((&metric_name, "", "Custom metric"), &attributes, ts, custom[name]);
+                }
+            }
+        }
+        // Split on sample boundaries so a request never exceeds the point budget by more than one batch.
+        if groups.count >= MAX_POINTS_PER_REQUEST {
+            requests.push(std::mem::take(&mut groups).into_request(&resource, &app_version));
+        }
+    }
+    if groups.count > 0 {
+        requests.push(groups.into_request(&resource, &app_version));
+    }
+    requests
+}
+
+pub fn count_data_points(req: &ExportMetricsServiceRequest) -> usize {
+    req.resource_metrics
+        .iter()
+        .flat_map(|rm| rm.scope_metrics.iter())
+        .flat_map(|sm| sm.metrics.iter())
+        .map(|m| m.gauge.as_ref().map(|g| g.data_points.len()).unwrap_or(0))
+        .sum()
+}
+
+/// Accepts either a collector base URL (`http://host:4318`) or the full `/v1/metrics` URL.
+pub fn metrics_endpoint(endpoint: &str) -> Result<String, String> {
+    let trimmed = endpoint.trim().trim_end_matches('/');
+    if trimmed.is_empty() {
+        return Err("OTLP endpoint is empty".to_string());
+    }
+    url::Url::parse(trimmed).map_err(|e| format!("Invalid OTLP endpoint: {e}"))?;
+    if trimmed.ends_with("/v1/metrics") {
+        Ok(trimmed.to_string())
+    } else {
+        Ok(format!("{}/v1/metrics", trimmed))
+    }
+}
+
+/// POST each request as `application/x-protobuf`. Stops at the first failure.
+pub async fn push_requests(
+    endpoint: &str,
+    headers: &HashMap<String, String>,
+    requests: Vec<ExportMetricsServiceRequest>,
+) -> Result<OtlpExportResult, String> {
+    let url = metrics_endpoint(endpoint)?;
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let mut result = OtlpExportResult { data_points: 0, requests: 0 };
+    for req in requests {
+        let points = count_data_points(&req);
+        let mut http = client
+            .post(&url)
+            .header("Content-Type", "application/x-protobuf")
+            .body(req.encode_to_vec());
+        for (k, v) in headers {
+            http = http.header(k.as_str(), v.as_str());
+        }
+        let resp = http.send().await.map_err(|e| {
+            format!("OTLP export failed after {} data points: {}", result.data_points, e)
+        })?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(format!(
+                "OTLP export failed after {} data points: HTTP {} {}",
+                result.data_points,
+                status,
+                body.chars().take(500).collect::<String>()
+            ));
+        }
+        result.data_points += points;
+        result.requests += 1;
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+    use std::sync::mpsc;
+
+    fn meta() -> ReportMeta {
+        ReportMeta::from_json_str(
+            &json!({
+                "app": { "version": "9.9.9" },
+                "test_context": { "scenario_name": "Idle", "build_id": "b-42" },
+                "process_aliases": [{ "pid": 1, "alias": "Browser" }],
+                "process_snapshot": [{ "pid": 1, "proc_type": "browser" }, { "pid": 2, "proc_type": "renderer" }],
+            })
+            .to_string(),
+        )
+    }
+
+    // `count` one-second batches of pids 1 and 2, each point with CPU, RSS and one custom metric.
+    fn batches(count: i64) -> Vec<BatchMetric> {
+        let start = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+        (0..count)
+            .map(|i| {
+                let timestamp = start + chrono::Duration::milliseconds(i * 1000 + 250);
+                let metrics = [1u32, 2]
+                    .into_iter()
+                    .map(|pid| {
+                        let point = json!({
+                            "timestamp": timestamp,
+                            "pid": pid,
+                            "cpu_usage": i as f32 % 100.0,
+                            "cpu_os_usage": 0.0,
+                            "memory_rss": 1000 + i as u64,
+                            "custom_metrics": { "fps": 60.0 },
+                        });
+                        (pid, serde_json::from_value(point).unwrap())
+                    })
+                    .collect();
+                BatchMetric { timestamp, metrics }
+            })
+            .collect()
+    }
+
+    struct Received {
+        content_type: String,
+        auth: String,
+        body: ExportMetricsServiceRequest,
+    }
+
+    // A local OTLP/HTTP receiver answering `status` to each of the first `requests` requests.
+    fn mock_receiver(status: u16, requests: usize) -> (String, mpsc::Receiver<Received>) {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for mut req in server.incoming_requests().take(requests) {
+                let header = |req: &tiny_http::Request, name: &'static str| {
+                    req.headers()
+                        .iter()
+                        .find(|h| h.field.equiv(name))
+                        .map(|h| h.value.to_string())
+                        .unwrap_or_default()
+                };
+                assert_eq!(req.url(), "/v1/metrics");
+                let (content_type, auth) = (header(&req, "Content-Type"), header(&req, "Authorization"));
+                let mut bytes = Vec::new();
+                req.as_reader().read_to_end(&mut bytes).unwrap();
+                let body = ExportMetricsServiceRequest::decode(bytes.as_slice()).expect("valid OTLP protobuf");
+                let _ = req.respond(tiny_http::Response::empty(status));
+                let _ = tx.send(Received { content_type, auth, body });
+            }
+        });
+        (url, rx)
+    }
+
+    fn attr<'a>(attrs: &'a [KeyValue], key: &str) -> Option<&'a any_value::Value> {
+        attrs.iter().find(|kv| kv.key == key).and_then(|kv| kv.value.as_ref()?.value.as_ref())
+    }
+
+    fn string(s: &str) -> any_value::Value {
+        any_value::Value::StringValue(s.to_string())
+    }
+
+    #[test]
+    fn pushes_chunked_protobuf_the_receiver_can_decode() {
+        // 3 points per PID and sample: 6 per batch, so 2000 batches need two requests.
+        let metrics = batches(2000);
+        let requests = build_requests(7, "Idle run", &metrics, &meta());
+        assert_eq!(requests.len(), 2);
+        let (url, received) = mock_receiver(200, requests.len());
+        let headers = HashMap::from([("Authorization".to_string(), "Bearer t0ken".to_string())]);
+
+        let result = tauri::async_runtime::block_on(push_requests(&url, &headers, requests)).unwrap();
+        assert_eq!((result.requests, result.data_points), (2, 12_000));
+
+        let bodies: Vec<Received> = received.iter().take(2).collect();
+        let mut timestamps = Vec::new();
+        for r in &bodies {
+            assert_eq!(r.content_type, "application/x-protobuf");
+            assert_eq!(r.auth, "Bearer t0ken");
+            let [rm] = r.body.resource_metrics.as_slice() else { panic!("one resource") };
+            let resource = &rm.resource.as_ref().unwrap().attributes;
+            assert_eq!(attr(resource, "service.name"), Some(&string("perfsight")));
+            assert_eq!(attr(resource, "perfsight.report_id"), Some(&any_value::Value::IntValue(7)));
+            let [sm] = rm.scope_metrics.as_slice() else { panic!("one scope") };
+            assert_eq!(sm.scope.as_ref().unwrap().version, "9.9.9");
+            let names: Vec<&str> = sm.metrics.iter().map(|m| m.name.as_str()).collect();
+            assert_eq!(names, ["perfsight.cpu.usage", "perfsight.memory.rss", "perfsight.custom.fps"]);
+
+            for point in &sm.metrics[0].gauge.as_ref().unwrap().data_points {
+                let a = &point.attributes;
+                assert_eq!(attr(a, "scenario_name"), Some(&string("Idle")));
+                assert_eq!(attr(a, "build_id"), Some(&string("b-42")));
+                match attr(a, "pid") {
+                    Some(any_value::Value::IntValue(1)) => {
+                        assert_eq!(attr(a, "alias"), Some(&string("Browser")));
+                        assert_eq!(attr(a, "proc_type"), Some(&string("browser")));
+                        timestamps.push(point.time_unix_nano);
+                    }
+                    Some(any_value::Value::IntValue(2)) => {
+                        assert_eq!(attr(a, "alias"), None);
+                        assert_eq!(attr(a, "proc_type"), Some(&string("renderer")));
+                    }
+                    other => panic!("unexpected pid {:?}", other),
+                }
+            }
+        }
+        // Sample times, not export time, in order across both requests.
+        let expected: Vec<u64> =
+            metrics.iter().map(|b| b.timestamp.timestamp_nanos_opt().unwrap() as u64).collect();
+        assert_eq!(timestamps, expected);
+    }
+
+    #[test]
+    fn a_rejected_request_stops_the_export_and_says_how_far_it_got() {
+        let requests = build_requests(7, "Idle run", &batches(10), &meta());
+        let (url, _received) = mock_receiver(500, 1);
+        let err = tauri::async_runtime::block_on(push_requests(&url, &HashMap::new(), requests)).unwrap_err();
+        assert!(err.contains("after 0 data points") && err.contains("500"), "{}", err);
+    }
+
+    #[test]
+    fn endpoint_gets_the_metrics_path_once() {
+        assert_eq!(metrics_endpoint("http://localhost:4318/").unwrap(), "http://localhost:4318/v1/metrics");
+        assert_eq!(metrics_endpoint(" http://c:4318/v1/metrics ").unwrap(), "http://c:4318/v1/metrics");
+        assert!(metrics_endpoint("  ").is_err());
+        assert!(metrics_endpoint("not a url").is_err());
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Condvar, Mutex, OnceLock};
+
+/// Workers used for per-report work when the `max_parallel_reports` setting is unset: half the
+/// physical cores, at least one, so the UI stays responsive. Logical CPUs stand in when the
+/// physical count is unknown.
+pub fn default_workers() -> usize {
+    static PHYSICAL_CORES: OnceLock<Option<usize>> = OnceLock::new();
+    let cores = PHYSICAL_CORES.get_or_init(|| sysinfo::System::new().physical_core_count());
+    cores
+        .or_else(|| std::thread::available_parallelism().ok().map(|n| n.get()))
+        .map_or(1, |n| n / 2)
+        .max(1)
+}
+
+// Held by a worker while it runs: if `work` panics, stop the other workers and wake any waiting
+// on the window, which would otherwise wait for the lost result forever. The panic itself
+// reaches the caller when the scope joins.
+struct StopOnPanic<'a> {
+    stop: &'a AtomicBool,
+    consumed: &'a (Mutex<usize>, Condvar),
+}
+
+impl Drop for StopOnPanic<'_> {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            let (lock, cvar) = self.consumed;
+            let _done = lock.lock().unwrap_or_else(|p| p.into_inner());
+            self.stop.store(true, Ordering::SeqCst);
+            cvar.notify_all();
+        }
+    }
+}
+
+/// Run `work` over `items` on up to `workers` threads and hand each result to `sink` on the
+/// calling thread, in input order (so e.g. zip entries come out as they would sequentially).
+/// Workers stay at most `2 * workers` items ahead of `sink`, which bounds the results held in
+/// memory. `sink` returns false to stop: workers finish their current item and take no more.
+/// A panic in `work` stops the pool the same way and is then re-raised here.
+pub fn for_each_ordered<T, R, W, S>(items: &[T], workers: usize, work: W, mut sink: S)
+where
+    T: Sync,
+    R: Send,
+    W: Fn(&T) -> R + Sync,
+    S: FnMut(usize, R) -> bool,
+{
+    let workers = workers.clamp(1, items.len().max(1));
+    if workers == 1 {
+        for (i, item) in items.iter().enumerate() {
+            if !sink(i, work(item)) {
+                return;
+            }
+        }
+        return;
+    }
+
+    let window = workers * 2;
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    // Results handed to `sink` so far; workers wait on it to stay within the window.
+    let consumed = (Mutex::new(0usize), Condvar::new());
+    let (tx, rx) = mpsc::sync_channel::<(usize, R)>(window);
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            let tx = tx.clone();
+            let (next, stop, consumed, work) = (&next, &stop, &consumed, &work);
+            scope.spawn(move || {
+                let _guard = StopOnPanic { stop, consumed };
+                loop {
+                    let i = next.fetch_add(1, Ordering::SeqCst);
+                    if i >= items.len() {
+                        break;
+                    }
+                    let (lock, cvar) = consumed;
+                    let mut done = lock.lock().unwrap_or_else(|p| p.into_inner());
+                    while i >= *done + window && !stop.load(Ordering::SeqCst) {
+                        done = cvar.wait(done).unwrap_or_else(|p| p.into_inner());
+                    }
+                    drop(done);
+                    if stop.load(Ordering::SeqCst) || tx.send((i, work(&items[i]))).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(tx);
+
+        let mut pending: BTreeMap<usize, R> = BTreeMap::new();
+        let mut wanted = 0;
+        'recv: for (i, r) in rx.iter() {
+            pending.insert(i, r);
+            while let Some(r) = pending.remove(&wanted) {
+                let more = sink(wanted, r);
+                wanted += 1;
+                let (lock, cvar) = &consumed;
+                *lock.lock().unwrap_or_else(|p| p.into_inner()) = wanted;
+                if !more {
+                    stop.store(true, Ordering::SeqCst);
+                    cvar.notify_all();
+                    break 'recv;
+                }
+                cvar.notify_all();
+            }
+        }
+        // Unblocks workers still sending once `sink` has stopped.
+        drop(rx);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn collect(items: &[u32], workers: usize) -> Vec<(usize, u32)> {
+        let mut out = Vec::new();
+        for_each_ordered(items, workers, |x| x * 2, |i, r| {
+            out.push((i, r));
+            true
+        });
+        out
+    }
+
+    #[test]
+    fn results_reach_the_sink_in_input_order() {
+        let items: Vec<u32> = (0..200).collect();
+        let expected: Vec<(usize, u32)> = items.iter().enumerate().map(|(i, x)| (i, x * 2)).collect();
+        for workers in [1, 2, 3, 8, 64] {
+            assert_eq!(collect(&items, workers), expected, "{} workers", workers);
+        }
+        assert!(collect(&[], 4).is_empty());
+    }
+
+    #[test]
+    fn a_false_sink_takes_no_more_items() {
+        let items: Vec<u32> = (0..1000).collect();
+        let started = AtomicUsize::new(0);
+        let mut seen = Vec::new();
+        for_each_ordered(
+            &items,
+            4,
+            |x| {
+                started.fetch_add(1, Ordering::SeqCst);
+                *x
+            },
+            |i, _| {
+                seen.push(i);
+                i < 9
+            },
+        );
+        assert_eq!(seen, (0..10).collect::<Vec<_>>());
+        // At most the window (2 * workers) plus the item each worker was on past the stop.
+        assert!(started.load(Ordering::SeqCst) <= 10 + 8 + 4);
+    }
+
+    #[test]
+    fn a_panicking_item_stops_the_pool_and_reaches_the_caller() {
+        let items: Vec<u32> = (0..500).collect();
+        let (done_tx, done_rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let result = std::panic::catch_unwind(|| {
+                for_each_ordered(
+                    &items,
+                    4,
+                    |x| {
+                        if *x == 3 {
+                            panic!("bad report");
+                        }
+                        std::thread::sleep(Duration::from_millis(1));
+                        *x
+                    },
+                    |_, _| true,
+                );
+            });
+            let _ = done_tx.send(result.is_err());
+        });
+        let panicked = done_rx.recv_timeout(Duration::from_secs(30)).expect("pool deadlocked after a panic");
+        assert!(panicked);
+    }
+
+    // Eight synthetic reports, each simulated, analyzed and serialized like a bundle dataset:
+    // every worker count hands back the same datasets, in report order.
+    #[test]
+    fn eight_reports_give_the_same_output_with_any_worker_count() {
+        use crate::collector::simulate::{SimulatedCollector, SimulationConfig};
+        use crate::collector::ResourceCollector;
+        use chrono::TimeZone;
+
+        let reports: Vec<u64> = (0..8).collect();
+        let started = chrono::Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let work = |seed: &u64| {
+            let config = SimulationConfig { baseline_cpu: 5.0 + *seed as f32, ..Default::default() };
+            let mut collector = SimulatedCollector::new(config);
+            let pids: Vec<u32> = collector.scan_processes("simulate").iter().map(|p| p.pid).collect();
+            let metrics = collector.series(&pids, 300, started, 1000);
+            let analysis = crate::analysis::analyze_report(&metrics, &Default::default());
+            // As a JSON value, so per-PID maps compare regardless of their hash order.
+            (*seed, serde_json::to_value((&metrics, &analysis)).unwrap())
+        };
+        let run = |workers: usize| {
+            let mut out = Vec::new();
+            for_each_ordered(&reports, workers, work, |i, r| {
+                out.push((i, r));
+                true
+            });
+            out
+        };
+
+        let sequential = run(1);
+        assert_eq!(sequential.iter().map(|(i, (seed, _))| (*i, *seed)).collect::<Vec<_>>(), (0..8).map(|i| (i, i as u64)).collect::<Vec<_>>());
+        assert!(sequential.iter().all(|(_, (_, dataset))| dataset[0].as_array().is_some_and(|m| m.len() == 300)));
+        for workers in [2, 3, 8] {
+            assert!(run(workers) == sequential, "{} workers changed the output", workers);
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use crate::commands::CollectionState;
+
+/// Escape a label value per the Prometheus text exposition format.
+fn escape_label_value(v: &str) -> String {
+    let mut out = String::with_capacity(v.len());
+    for ch in v.chars() {
+        match ch {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+fn labels(pairs: &[(&str, &str)]) -> String {
+    let inner = pairs
+        .iter()
+        .map(|(k, v)| format!("{}=\"{}\"", k, escape_label_value(v)))
+        .collect::<Vec<_>>()
+        .join(",");
+    format!("{{{}}}", inner)
+}
+
+fn header(out: &mut String, name: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+}
+
+/// Render the latest sample per monitored PID as Prometheus text format gauges.
+/// When no run is active only the collection status gauges are emitted.
+pub fn render_exposition(state: &CollectionState) -> String {
+    // One read per session so counts, samples and labels are consistent with each other; the
+    // sessions are then merged (each PID belongs to one session).
+    let snapshots = state.read_each(|run| {
+        let aliases: HashMap<u32, String> = run
+            .process_aliases
+            .iter()
+            .map(|a| (a.pid, a.alias.trim().to_string()))
+            .collect();
+        let proc_types: HashMap<u32, String> = run
+            .process_snapshot
+            .iter()
+            .map(|p| (p.pid, p.proc_type.clone()))
+            .collect();
+        (run.buffer.len(), run.latest_samples.values().cloned().collect::<Vec<_>>(), aliases, proc_types)
+    });
+    let is_running = !snapshots.is_empty();
+    let mut sample_count = 0;
+    let mut latest = Vec::new();
+    let mut aliases = HashMap::new();
+    let mut proc_types = HashMap::new();
+    for (count, samples, run_aliases, run_types) in snapshots {
+        sample_count += count;
+        latest.extend(samples);
+        aliases.extend(run_aliases);
+        proc_types.extend(run_types);
+    }
+    latest.sort_by_key(|p| p.pid);
+
+    let mut out = String::new();
+
+    header(&mut out, "perfsight_collection_running", "1 while a collection run is active.");
+    let _ = writeln!(out, "perfsight_collection_running {}", if is_running { 1 } else { 0 });
+    header(&mut out, "perfsight_collection_sample_count", "Number of buffered batches across active runs.");
+    let _ = writeln!(out, "perfsight_collection_sample_count {}", sample_count);
+
+    if latest.is_empty() {
+        return out;
+    }
+
+    let pid_labels = |pid: u32, extra: &[(&str, &str)]| {
+        let pid_s = pid.to_string();
+        let alias = aliases.get(&pid).cloned().unwrap_or_default();
+        let proc_type = proc_types.get(&pid).cloned().unwrap_or_default();
+        let mut pairs: Vec<(&str, &str)> = extra.to_vec();
+        pairs.push(("pid", &pid_s));
+        pairs.push(("alias", &alias));
+        pairs.push(("proc_type", &proc_type));
+        labels(&pairs)
+    };
+
+    header(&mut out, "perfsight_cpu_percent", "Primary CPU% of the process (latest sample).");
+    for p in &latest {
+        let _ = writeln!(out, "perfsight_cpu_percent{} {}", pid_labels(p.pid, &[]), p.cpu_usage);
+    }
+
+    header(&mut out, "perfsight_memory_bytes", "Process memory in bytes by kind (latest sample).");
+    for p in &latest {
+        let _ = writeln!(out, "perfsight_memory_bytes{} {}", pid_labels(p.pid, &[("kind", "rss")]), p.memory_rss);
+        if let Some(v) = p.memory_private {
+            let _ = writeln!(out, "perfsight_memory_bytes{} {}", pid_labels(p.pid, &[("kind", "private")]), v);
+        }
+        if let Some(v) = p.memory_footprint {
+            let _ = writeln!(out, "perfsight_memory_bytes{} {}", pid_labels(p.pid, &[("kind", "footprint")]), v);
+        }
+    }
+
+    let has_custom = latest.iter().any(|p| p.custom_metrics.as_ref().is_some_and(|m| !m.is_empty()));
+    if has_custom {
+        header(&mut out, "perfsight_custom", "Custom metric value (latest sample).");
+        for p in &latest {
+            if let Some(custom) = &p.custom_metrics {
+                let mut names: Vec<_> = custom.keys().collect();
+                names.sort();
+                for name in names {
+                    let _ = writeln!(
+                        out,
+                        "perfsight_custom{} {}",
+                        pid_labels(p.pid, &[("name", name.as_str())]),
+                        custom[name]
+                    );
+                }
+            }
+        }
+    }
+
+    out
+}
@@ -0,0 +1,108 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use crate::error::PerfSightError;
+use crate::models::ProcessInfo;
+
+/// A recurring process role ("Sender tab", "GPU process") and how to recognize it. Stored in
+/// `Settings::role_templates`; the conditions it sets are combined with AND.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoleTemplate {
+    pub role: String,
+    /// Regex on the page URL (any tab backed by the process).
+    #[serde(default)]
+    pub url_regex: Option<String>,
+    /// Regex on the tab or window title.
+    #[serde(default)]
+    pub title_regex: Option<String>,
+    /// Case-insensitive `proc_type`, e.g. "GPU".
+    #[serde(default)]
+    pub proc_type: Option<String>,
+}
+
+/// A role given to a PID, saved in report meta under `collection.role_assignments`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoleAssignment {
+    pub pid: u32,
+    /// The alias given: the role name, numbered ("GPU process 2") when several PIDs match it.
+    pub alias: String,
+    pub role: String,
+    /// Position of the matching template in the list.
+    pub template_index: usize,
+    /// Conditions that matched: "url", "title" and/or "proc_type".
+    pub matched_on: Vec<String>,
+}
+
+fn set(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+pub fn validate_templates(templates: &[RoleTemplate]) -> Result<(), PerfSightError> {
+    for (i, t) in templates.iter().enumerate() {
+        let field = |name: &str| format!("role_templates[{}].{}", i, name);
+        if t.role.trim().is_empty() {
+            return Err(PerfSightError::invalid_input(field("role"), "cannot be empty"));
+        }
+        if set(&t.url_regex).is_none() && set(&t.title_regex).is_none() && set(&t.proc_type).is_none() {
+            return Err(PerfSightError::invalid_input(field("url_regex"), "set a URL, title or proc_type rule"));
+        }
+        for (name, pattern) in [("url_regex", set(&t.url_regex)), ("title_regex", set(&t.title_regex))] {
+            if let Some(p) = pattern {
+                Regex::new(p).map_err(|e| PerfSightError::invalid_input(field(name), e.to_string()))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// The conditions of `t` that `p` satisfies, or None when one of them fails.
+fn template_matches(t: &RoleTemplate, p: &ProcessInfo) -> Option<Vec<String>> {
+    let mut matched = Vec::new();
+    if let Some(pattern) = set(&t.url_regex) {
+        let re = Regex::new(pattern).ok()?;
+        let mut urls = p.url.iter().map(String::as_str).chain(p.targets.iter().map(|t| t.url.as_str()));
+        if !urls.any(|u| re.is_match(u)) {
+            return None;
+        }
+        matched.push("url".to_string());
+    }
+    if let Some(pattern) = set(&t.title_regex) {
+        let re = Regex::new(pattern).ok()?;
+        let mut titles = p
+            .title
+            .iter()
+            .chain(p.window_title.iter())
+            .map(String::as_str)
+            .chain(p.targets.iter().map(|t| t.title.as_str()));
+        if !titles.any(|t| re.is_match(t)) {
+            return None;
+        }
+        matched.push("title".to_string());
+    }
+    if let Some(kind) = set(&t.proc_type) {
+        if !kind.eq_ignore_ascii_case(&p.proc_type) {
+            return None;
+        }
+        matched.push("proc_type".to_string());
+    }
+    Some(matched)
+}
+
+/// Give each process the first template it matches, in `processes` order. A role matched by
+/// several processes is numbered from the second one on.
+pub fn assign(templates: &[RoleTemplate], processes: &[ProcessInfo]) -> Vec<RoleAssignment> {
+    let mut out: Vec<RoleAssignment> = Vec::new();
+    for p in processes {
+        let Some((template_index, t, matched_on)) = templates
+            .iter()
+            .enumerate()
+            .find_map(|(i, t)| template_matches(t, p).map(|m| (i, t, m)))
+        else {
+            continue;
+        };
+        let role = t.role.trim().to_string();
+        let n = out.iter().filter(|a| a.template_index == template_index).count();
+        let alias = if n == 0 { role.clone() } else { format!("{} {}", role, n + 1) };
+        out.push(RoleAssignment { pid: p.pid, alias, role, template_index, matched_on });
+    }
+    out
+}
@@ -0,0 +1,104 @@
+use chrono::{DateTime, Duration, Utc};
+use serde_json::{json, Map};
+use crate::collector::ResourceCollector;
+use crate::collector::simulate::{SimulatedCollector, SimulationConfig, SIMULATED_PID_BASE};
+use crate::models::{AppMeta, BatchMetric, CollectionMeta, EnvMeta, ProcessAlias, ReportMeta, TestContext};
+
+/// Folder the onboarding reports and comparison are filed under.
+pub const SAMPLE_FOLDER: &str = "Samples";
+pub const SAMPLE_TAG: &str = "sample";
+const SAMPLE_SCENARIO: &str = "Sample: dashboard idle";
+const SAMPLE_INTERVAL_MS: u64 = 1000;
+// Three minutes of samples per report.
+const SAMPLE_COUNT: u64 = 180;
+const SAMPLE_ALIASES: [&str; 3] = ["Browser", "Dashboard tab", "Chat tab"];
+
+/// A generated report, saved like a finished run.
+pub struct SampleReport {
+    pub title: String,
+    pub metrics: Vec<BatchMetric>,
+    pub meta: ReportMeta,
+}
+
+/// Two simulated runs of one scenario: a baseline build, and a candidate with a CPU spike and a
+/// mild memory leak in the browser process. Both are flagged `sample: true` in meta and tagged
+/// "sample".
+pub fn reports(now: DateTime<Utc>) -> Vec<SampleReport> {
+    let baseline = SimulationConfig {
+        baseline_cpu: 8.0,
+        spike_every: 0,
+        cpu_jitter: 3.0,
+        base_mem_mb: 240.0,
+        leak_mb_per_sample: 0.02,
+        custom_metric: "fps".to_string(),
+        ..Default::default()
+    };
+    let candidate = SimulationConfig {
+        baseline_cpu: 10.0,
+        spike_every: 90,
+        spike_cpu: 85.0,
+        leak_mb_per_sample: 0.4,
+        ..baseline.clone()
+    };
+    vec![
+        report("Sample: baseline build", "sample-1.0.0", baseline, now - Duration::days(1) - Duration::hours(2)),
+        report("Sample: candidate build", "sample-1.1.0", candidate, now - Duration::hours(2)),
+    ]
+}
+
+fn report(title: &str, build_id: &str, config: SimulationConfig, started: DateTime<Utc>) -> SampleReport {
+    let mut collector = SimulatedCollector::new(config.clone());
+    let processes = collector.scan_processes("simulate");
+    let pids: Vec<u32> = processes.iter().map(|p| p.pid).collect();
+    let metrics = collector.series(&pids, SAMPLE_COUNT, started, SAMPLE_INTERVAL_MS);
+    let ended = metrics.last().map_or(started, |b| b.timestamp);
+
+    let mut collection_extra = Map::new();
+    collection_extra.insert("simulated".to_string(), json!(true));
+    collection_extra.insert("simulation".to_string(), json!(config));
+    let mut meta = ReportMeta {
+        schema_version: Some(1),
+        app: Some(AppMeta { version: Some(env!("CARGO_PKG_VERSION").to_string()), extra: Default::default() }),
+        env: Some(EnvMeta {
+            os: Some(std::env::consts::OS.to_string()),
+            arch: Some(std::env::consts::ARCH.to_string()),
+            ..Default::default()
+        }),
+        collection: Some(CollectionMeta {
+            mode: Some("simulate".to_string()),
+            metric_standard: Some("simulated".to_string()),
+            interval_ms: Some(SAMPLE_INTERVAL_MS),
+            target_pids: pids.clone(),
+            folder_path: Some(SAMPLE_FOLDER.to_string()),
+            started_at: Some(started.to_rfc3339()),
+            ended_at: Some(ended.to_rfc3339()),
+            duration_seconds: Some((ended - started).num_seconds().max(0) as u64),
+            extra: collection_extra,
+            ..Default::default()
+        }),
+        test_context: Some(TestContext {
+            scenario_name: Some(SAMPLE_SCENARIO.to_string()),
+            build_id: Some(build_id.to_string()),
+            tags: Some(vec![SAMPLE_TAG.to_string()]),
+            notes: Some("Generated sample data; `remove_sample_data` deletes it.".to_string()),
+            ..Default::default()
+        }),
+        process_aliases: pids
+            .iter()
+            .zip(SAMPLE_ALIASES)
+            .map(|(&pid, alias)| ProcessAlias { pid, alias: alias.to_string() })
+            .collect(),
+        process_snapshot: processes.iter().filter_map(|p| serde_json::to_value(p).ok()).collect(),
+        ..Default::default()
+    };
+    meta.extra.insert("sample".to_string(), json!(true));
+    SampleReport { title: title.to_string(), metrics, meta }
+}
+
+/// Process selection for the sample comparison: the browser process, whose memory leaks in the
+/// candidate. PIDs are the same in both reports, so one selection serves both.
+pub fn comparison_selection(report_ids: &[i64]) -> serde_json::Value {
+    let selection: Map<String, serde_json::Value> =
+        report_ids.iter().map(|id| (id.to_string(), json!([SIMULATED_PID_BASE]))).collect();
+    serde_json::Value::Object(selection)
+}
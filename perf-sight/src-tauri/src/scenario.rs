@@ -0,0 +1,442 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_shell::ShellExt;
+use crate::logging::log_warn;
+use crate::analysis::{analyze, evaluate_budgets};
+use crate::commands::{append_session_event, safe_lock, start_collection_with, stop_collection_and_save, CollectionState, SessionId};
+use crate::database::Database;
+use crate::error::PerfSightError;
+use crate::models::{CollectionConfig, PerformanceBudget, RunEventKind};
+
+// Sleeps are cut into slices this long so an abort takes effect quickly.
+const ABORT_POLL: Duration = Duration::from_millis(200);
+const DEFAULT_SHELL_TIMEOUT_SECONDS: u64 = 300;
+const MAX_SHELL_OUTPUT_CHARS: usize = 2000;
+
+/// A scripted collection: steps run one after another on a backend task.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScenarioScript {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub steps: Vec<ScenarioStep>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ScenarioStep {
+    /// Start a run from an inline `config` (a CollectionConfig) or a saved `preset` (with `pids`
+    /// as target PIDs).
+    Start {
+        #[serde(default)]
+        config: Option<Value>,
+        #[serde(default)]
+        preset: Option<String>,
+        #[serde(default)]
+        pids: Option<Vec<u32>>,
+    },
+    Sleep { seconds: f64 },
+    /// Add a `marker` event to the active run's timeline. With `phase` it starts that phase, or
+    /// ends it when `boundary` is "end".
+    AddMarker {
+        label: String,
+        #[serde(default)]
+        phase: Option<String>,
+        #[serde(default)]
+        boundary: Option<String>,
+    },
+    /// Run a program (no shell interpolation); a non-zero exit fails the step unless `allow_failure`.
+    Shell {
+        program: String,
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(default)]
+        timeout_seconds: Option<u64>,
+        #[serde(default)]
+        allow_failure: bool,
+    },
+    StopAndSave,
+    /// Check budgets against the last saved report, or the live buffer while collecting.
+    AssertBudget { budgets: Vec<PerformanceBudget> },
+}
+
+impl ScenarioStep {
+    pub fn action(&self) -> &'static str {
+        match self {
+            ScenarioStep::Start { .. } => "start",
+            ScenarioStep::Sleep { .. } => "sleep",
+            ScenarioStep::AddMarker { .. } => "add_marker",
+            ScenarioStep::Shell { .. } => "shell",
+            ScenarioStep::StopAndSave => "stop_and_save",
+            ScenarioStep::AssertBudget { .. } => "assert_budget",
+        }
+    }
+}
+
+impl ScenarioScript {
+    pub fn parse(script: Value) -> Result<Self, PerfSightError> {
+        let script: ScenarioScript =
+            serde_json::from_value(script).map_err(|e| PerfSightError::invalid_input("script", e.to_string()))?;
+        script.validate()?;
+        Ok(script)
+    }
+
+    fn validate(&self) -> Result<(), PerfSightError> {
+        if self.steps.is_empty() {
+            return Err(PerfSightError::invalid_input("script", "steps cannot be empty"));
+        }
+        for (i, step) in self.steps.iter().enumerate() {
+            let field = format!("steps[{}]", i);
+            match step {
+                ScenarioStep::Start { config, preset, .. } => {
+                    let has_preset = preset.as_deref().is_some_and(|p| !p.trim().is_empty());
+                    if config.is_some() == has_preset {
+                        return Err(PerfSightError::invalid_input(field, "start needs exactly one of config or preset"));
+                    }
+                    if let Some(config) = config {
+                        parse_config(config.clone())?;
+                    }
+                }
+                ScenarioStep::Sleep { seconds } if !seconds.is_finite() || *seconds < 0.0 => {
+                    return Err(PerfSightError::invalid_input(field, "sleep seconds must be a non-negative number"));
+                }
+                ScenarioStep::AddMarker { label, .. } if label.trim().is_empty() => {
+                    return Err(PerfSightError::invalid_input(field, "marker label cannot be empty"));
+                }
+                ScenarioStep::AddMarker { boundary: Some(b), .. } if b != "start" && b != "end" => {
+                    return Err(PerfSightError::invalid_input(field, "marker boundary must be \"start\" or \"end\""));
+                }
+                ScenarioStep::AddMarker { phase: None, boundary: Some(_), .. } => {
+                    return Err(PerfSightError::invalid_input(field, "marker boundary needs a phase"));
+                }
+                ScenarioStep::Shell { program, .. } if program.trim().is_empty() => {
+                    return Err(PerfSightError::invalid_input(field, "shell program cannot be empty"));
+                }
+                ScenarioStep::AssertBudget { budgets } if budgets.is_empty() => {
+                    return Err(PerfSightError::invalid_input(field, "assert_budget needs at least one budget"));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Timing and outcome of one step, stored in report meta under `scenario.steps`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepRecord {
+    pub index: usize,
+    pub action: String,
+    pub started_at: String,
+    pub duration_ms: u64,
+    /// "ok" | "failed" | "aborted"
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub detail: Value,
+}
+
+/// Payload of the "scenario-progress" event, sent when a step starts and when it ends.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScenarioProgress {
+    pub scenario_id: String,
+    pub step_index: usize,
+    pub total_steps: usize,
+    pub action: String,
+    /// "running" | "ok" | "failed" | "aborted"
+    pub status: String,
+    pub error: Option<String>,
+}
+
+/// Payload of the "scenario-finished" event.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScenarioOutcome {
+    pub scenario_id: String,
+    /// "completed" | "failed" | "aborted"
+    pub status: String,
+    pub report_ids: Vec<i64>,
+    pub steps: Vec<StepRecord>,
+    pub error: Option<String>,
+}
+
+struct ActiveScenario {
+    id: String,
+    abort: Arc<AtomicBool>,
+}
+
+/// At most one script runs at a time.
+#[derive(Clone, Default)]
+pub struct ScenarioState {
+    active: Arc<Mutex<Option<ActiveScenario>>>,
+}
+
+impl ScenarioState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the running script to stop after (or during) its current step. Returns its id.
+    pub fn abort(&self) -> Option<String> {
+        let active = safe_lock(&self.active);
+        active.as_ref().map(|a| {
+            a.abort.store(true, Ordering::SeqCst);
+            a.id.clone()
+        })
+    }
+}
+
+/// Validate `script` and run it in the background. Returns the scenario id used in events.
+pub fn spawn_scenario(app: AppHandle, script: ScenarioScript) -> Result<String, PerfSightError> {
+    let scenario_state: State<ScenarioState> = app.state();
+    let abort = Arc::new(AtomicBool::new(false));
+    let id = format!("scenario-{}", Utc::now().timestamp_millis());
+    {
+        let mut active = safe_lock(&scenario_state.active);
+        if let Some(running) = active.as_ref() {
+            return Err(PerfSightError::invalid_input("script", format!("scenario {} is still running", running.id)));
+        }
+        *active = Some(ActiveScenario { id: id.clone(), abort: abort.clone() });
+    }
+
+    let scenario_id = id.clone();
+    tauri::async_runtime::spawn(async move {
+        let outcome = Runner { app: app.clone(), id: scenario_id, abort, report_ids: Vec::new(), session: None }
+            .run(&script)
+            .await;
+        *safe_lock(&app.state::<ScenarioState>().active) = None;
+        let _ = app.emit("scenario-finished", &outcome);
+    });
+    Ok(id)
+}
+
+struct Runner {
+    app: AppHandle,
+    id: String,
+    abort: Arc<AtomicBool>,
+    report_ids: Vec<i64>,
+    // Session of the run this script started, while it is active.
+    session: Option<SessionId>,
+}
+
+enum StepError {
+    Failed(String),
+    Aborted,
+}
+
+impl From<PerfSightError> for StepError {
+    fn from(e: PerfSightError) -> Self {
+        StepError::Failed(e.to_string())
+    }
+}
+
+impl Runner {
+    async fn run(mut self, script: &ScenarioScript) -> ScenarioOutcome {
+        let total = script.steps.len();
+        let mut steps = Vec::with_capacity(total);
+        let mut failure: Option<(String, Option<String>)> = None;
+
+        for (index, step) in script.steps.iter().enumerate() {
+            let action = step.action().to_string();
+            self.progress(index, total, &action, "running", None);
+            let started_at = Utc::now().to_rfc3339();
+            let clock = Instant::now();
+            let result = if self.abort.load(Ordering::SeqCst) { Err(StepError::Aborted) } else { self.step(step).await };
+            let (status, error, detail) = match result {
+                Ok(detail) => ("ok", None, detail),
+                Err(StepError::Failed(e)) => ("failed", Some(e), Value::Null),
+                Err(StepError::Aborted) => ("aborted", Some("aborted".to_string()), Value::Null),
+            };
+            self.progress(index, total, &action, status, error.clone());
+            steps.push(StepRecord {
+                index,
+                action,
+                started_at,
+                duration_ms: clock.elapsed().as_millis() as u64,
+                status: status.to_string(),
+                error: error.clone(),
+                detail,
+            });
+            if status != "ok" {
+                failure = Some((if status == "aborted" { "aborted" } else { "failed" }.to_string(), error));
+                break;
+            }
+        }
+
+        // A script that never stopped its run (or broke off mid-run) still saves what it has.
+        let incomplete_report = if self.session.is_some() { self.stop_and_save().ok().flatten() } else { None };
+        let (status, error) = failure.unwrap_or_else(|| ("completed".to_string(), None));
+        let incomplete = status != "completed";
+        let db: State<Database> = self.app.state();
+        for id in &self.report_ids {
+            let patch = json!({
+                "scenario": {
+                    "id": self.id,
+                    "name": script.name,
+                    "script": script,
+                    "steps": steps,
+                    "status": status,
+                    "incomplete": incomplete && incomplete_report == Some(*id),
+                }
+            });
+            if let Err(e) = db.update_report_meta_patch(*id, &patch) {
+                log_warn!("Failed to record scenario on report {}: {}", id, e);
+            }
+        }
+        ScenarioOutcome { scenario_id: self.id, status, report_ids: self.report_ids, steps, error }
+    }
+
+    fn progress(&self, step_index: usize, total_steps: usize, action: &str, status: &str, error: Option<String>) {
+        let _ = self.app.emit(
+            "scenario-progress",
+            &ScenarioProgress {
+                scenario_id: self.id.clone(),
+                step_index,
+                total_steps,
+                action: action.to_string(),
+                status: status.to_string(),
+                error,
+            },
+        );
+    }
+
+    fn stop_and_save(&mut self) -> Result<Option<i64>, PerfSightError> {
+        let state: State<CollectionState> = self.app.state();
+        let db: State<Database> = self.app.state();
+        let Some(session_id) = self.session.take() else {
+            return Ok(None);
+        };
+        let saved = stop_collection_and_save(&self.app, state.inner(), db.inner(), &session_id)
+            .map_err(PerfSightError::Internal)?
+            .report_id;
+        self.report_ids.extend(saved);
+        Ok(saved)
+    }
+
+    async fn step(&mut self, step: &ScenarioStep) -> Result<Value, StepError> {
+        let state: State<CollectionState> = self.app.state();
+        let db: State<Database> = self.app.state();
+        match step {
+            ScenarioStep::Start { config, preset, pids } => {
+                if self.session.is_some() {
+                    return Err(StepError::Failed("start: this script's collection is already running".to_string()));
+                }
+                let mut config = match (config, preset) {
+                    (Some(c), _) => parse_config(c.clone())?,
+                    (None, Some(name)) => {
+                        parse_config(db.get_collection_preset(name.trim()).map_err(PerfSightError::from)?.config)?
+                    }
+                    (None, None) => unreachable!("validated"),
+                };
+                if let Some(pids) = pids {
+                    config.target_pids = pids.clone();
+                    if let Some(aliases) = config.process_aliases.as_mut() {
+                        aliases.retain(|a| pids.contains(&a.pid));
+                    }
+                }
+                // The script decides when to stop.
+                config.stop_after_seconds = None;
+                let session_id = start_collection_with(self.app.clone(), state.inner(), db.inner(), config).await?;
+                let started_at = state.read_session(&session_id, |r| r.started_at.clone());
+                self.session = Some(session_id.clone());
+                Ok(json!({ "session_id": session_id, "started_at": started_at }))
+            }
+            ScenarioStep::Sleep { seconds } => {
+                let deadline = Instant::now() + Duration::from_secs_f64(*seconds);
+                while Instant::now() < deadline {
+                    if self.abort.load(Ordering::SeqCst) {
+                        return Err(StepError::Aborted);
+                    }
+                    tokio::time::sleep(ABORT_POLL.min(deadline.saturating_duration_since(Instant::now()))).await;
+                }
+                Ok(Value::Null)
+            }
+            ScenarioStep::AddMarker { label, phase, boundary } => {
+                let Some(session_id) = self.session.as_deref().filter(|id| state.has_session(id)) else {
+                    return Err(StepError::Failed("add_marker needs an active collection".to_string()));
+                };
+                let mut detail = json!({ "label": label.trim() });
+                if let Some(phase) = phase.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+                    detail["phase"] = json!(phase);
+                    detail["boundary"] = json!(boundary.as_deref().unwrap_or("start"));
+                }
+                append_session_event(state.inner(), session_id, RunEventKind::Marker, detail);
+                Ok(Value::Null)
+            }
+            ScenarioStep::Shell { program, args, timeout_seconds, allow_failure } => {
+                let timeout = Duration::from_secs(timeout_seconds.unwrap_or(DEFAULT_SHELL_TIMEOUT_SECONDS));
+                let output = self.app.shell().command(program.trim()).args(args).output();
+                let output = tokio::select! {
+                    out = tokio::time::timeout(timeout, output) => match out {
+                        Ok(out) => out.map_err(|e| StepError::Failed(format!("{}: {}", program, e)))?,
+                        Err(_) => return Err(StepError::Failed(format!("{} timed out after {}s", program, timeout.as_secs()))),
+                    },
+                    _ = self.wait_for_abort() => return Err(StepError::Aborted),
+                };
+                let code = output.status.code();
+                let detail = json!({
+                    "exit_code": code,
+                    "stdout": tail(&output.stdout),
+                    "stderr": tail(&output.stderr),
+                });
+                if code != Some(0) && !allow_failure {
+                    return Err(StepError::Failed(format!("{} exited with {:?}: {}", program, code, tail(&output.stderr))));
+                }
+                Ok(detail)
+            }
+            ScenarioStep::StopAndSave => {
+                if !self.session.as_deref().is_some_and(|id| state.has_session(id)) {
+                    return Err(StepError::Failed("stop_and_save needs an active collection".to_string()));
+                }
+                let report_id = self.stop_and_save()?;
+                Ok(json!({ "report_id": report_id }))
+            }
+            ScenarioStep::AssertBudget { budgets } => {
+                let live = self.session.as_deref().and_then(|id| state.read_session(id, |r| r.buffer.clone()));
+                let (source, analysis) = match live {
+                    Some(buffer) => ("live".to_string(), analyze(&buffer)),
+                    None => {
+                        let Some(id) = self.report_ids.last().copied() else {
+                            return Err(StepError::Failed("assert_budget needs a running or saved collection".to_string()));
+                        };
+                        let report = db.get_report_detail(id).map_err(PerfSightError::from)?;
+                        let analysis = report.analysis.unwrap_or_else(|| analyze(&report.metrics));
+                        (format!("report {}", id), analysis)
+                    }
+                };
+                let results = evaluate_budgets(&analysis, budgets);
+                let failed: Vec<String> = results
+                    .iter()
+                    .filter(|r| !r.passed)
+                    .map(|r| format!("{} {} {} (observed {:?})", r.metric, r.comparison, r.threshold, r.observed))
+                    .collect();
+                if !failed.is_empty() {
+                    return Err(StepError::Failed(format!("budget failed on {}: {}", source, failed.join("; "))));
+                }
+                Ok(json!({ "source": source, "results": results }))
+            }
+        }
+    }
+
+    async fn wait_for_abort(&self) {
+        while !self.abort.load(Ordering::SeqCst) {
+            tokio::time::sleep(ABORT_POLL).await;
+        }
+    }
+}
+
+fn parse_config(config: Value) -> Result<CollectionConfig, PerfSightError> {
+    serde_json::from_value(config).map_err(|e| PerfSightError::invalid_input("config", e.to_string()))
+}
+
+// Last part of a process's output, enough to explain a failure.
+fn tail(bytes: &[u8]) -> String {
+    let s = String::from_utf8_lossy(bytes);
+    let s = s.trim();
+    let start = s.char_indices().rev().nth(MAX_SHELL_OUTPUT_CHARS - 1).map(|(i, _)| i).unwrap_or(0);
+    s[start..].to_string()
+}
@@ -0,0 +1,291 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::database::Database;
+use crate::error::PerfSightError;
+use crate::analysis::AnalysisSettings;
+use crate::artifacts::{mb_to_bytes, DEFAULT_MAX_ARTIFACT_MB};
+use crate::logging::{log_warn, LogLevel};
+use crate::models::{CpuNormalization, MetricSinkConfig, TestContextLimits, MAX_INTERVAL_MS, MIN_INTERVAL_MS};
+use crate::parallel;
+use crate::roles::{self, RoleTemplate};
+use crate::webhook::WebhookConfig;
+
+// Listeners try `base..base+PORT_FALLBACK_TRIES` when the base port is busy.
+pub const PORT_FALLBACK_TRIES: u16 = 30;
+// Range of the port base settings; the whole fallback range stays below u16::MAX.
+pub const MIN_PORT_BASE: u16 = 1024;
+pub const MAX_PORT_BASE: u16 = u16::MAX - PORT_FALLBACK_TRIES;
+
+fn default_ws_port_base() -> u16 {
+    23333
+}
+
+fn default_http_port_base() -> u16 {
+    23433
+}
+
+fn default_interval_ms() -> u64 {
+    1000
+}
+
+fn default_max_artifact_mb() -> u64 {
+    DEFAULT_MAX_ARTIFACT_MB
+}
+
+/// Persisted app settings. Each top-level field is stored as its own row in the `settings` table
+/// (key = field name), so single-purpose commands like `set_prometheus_enabled` stay compatible.
+/// Missing keys fall back to the serde defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Settings {
+    /// First port tried by the extension WebSocket listener (applied on restart).
+    #[serde(default = "default_ws_port_base")]
+    pub ws_port_base: u16,
+    /// First port tried by the HTTP ingest listener (applied on restart).
+    #[serde(default = "default_http_port_base")]
+    pub http_port_base: u16,
+    /// Sampling interval used when nothing else is configured.
+    #[serde(default = "default_interval_ms")]
+    pub default_interval_ms: u64,
+    #[serde(default)]
+    pub prometheus_enabled: bool,
+    #[serde(default)]
+    pub metric_sink: Option<MetricSinkConfig>,
+    #[serde(default)]
+    pub webhook: WebhookConfig,
+    /// Largest PDF, bundle or dataset accepted for export/import, in MB.
+    #[serde(default = "default_max_artifact_mb")]
+    pub max_artifact_mb: u64,
+    /// Run `maintain_database` at startup when the last run is over a month old.
+    #[serde(default)]
+    pub auto_maintenance: bool,
+    /// How OS CPU% is scaled; unset keeps the platform default (see `CpuNormalization::platform_default`).
+    #[serde(default)]
+    pub cpu_normalization: Option<CpuNormalization>,
+    /// Roles assigned as aliases when a run starts with `apply_role_templates`, first match wins.
+    #[serde(default)]
+    pub role_templates: Vec<RoleTemplate>,
+    #[serde(default)]
+    pub analysis: AnalysisSettings,
+    /// Caps applied to a run's test context (scenario, build, notes, tags) when it starts.
+    #[serde(default)]
+    pub test_context_limits: TestContextLimits,
+    /// Reports processed at once by bundle exports, folder aggregates and reanalysis; unset uses
+    /// `parallel::default_workers`.
+    #[serde(default)]
+    pub max_parallel_reports: Option<usize>,
+    /// Least severe diagnostic lines written to the log (applied immediately).
+    #[serde(default)]
+    pub log_level: LogLevel,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            ws_port_base: default_ws_port_base(),
+            http_port_base: default_http_port_base(),
+            default_interval_ms: default_interval_ms(),
+            prometheus_enabled: false,
+            metric_sink: None,
+            webhook: WebhookConfig::default(),
+            max_artifact_mb: default_max_artifact_mb(),
+            auto_maintenance: false,
+            cpu_normalization: None,
+            role_templates: Vec::new(),
+            analysis: AnalysisSettings::default(),
+            test_context_limits: TestContextLimits::default(),
+            max_parallel_reports: None,
+            log_level: LogLevel::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn load(db: &Database) -> Settings {
+        let rows = match db.get_all_settings() {
+            Ok(rows) => rows,
+            Err(e) => {
+                log_warn!("Failed to read settings, using defaults: {}", e);
+                return Settings::default();
+            }
+        };
+        // Deserialize field by field so one bad value doesn't reset everything else.
+        let mut merged = serde_json::to_value(Settings::default()).unwrap_or(Value::Null);
+        if let Value::Object(obj) = &mut merged {
+            for (key, value) in rows {
+                if !obj.contains_key(&key) {
+                    continue;
+                }
+                let mut candidate = obj.clone();
+                candidate.insert(key.clone(), value);
+                if serde_json::from_value::<Settings>(Value::Object(candidate.clone())).is_ok() {
+                    *obj = candidate;
+                } else {
+                    log_warn!("Ignoring invalid setting '{}'", key);
+                }
+            }
+        }
+        let mut settings: Settings = serde_json::from_value(merged).unwrap_or_default();
+        settings.reset_out_of_range();
+        settings
+    }
+
+    // Stored values from before the current bounds (or edited by hand) fall back to the defaults,
+    // so the listeners and collectors never see them.
+    fn reset_out_of_range(&mut self) {
+        let defaults = Settings::default();
+        if !(MIN_PORT_BASE..=MAX_PORT_BASE).contains(&self.ws_port_base) {
+            log_warn!("Ignoring out-of-range setting 'ws_port_base' ({})", self.ws_port_base);
+            self.ws_port_base = defaults.ws_port_base;
+        }
+        if !(MIN_PORT_BASE..=MAX_PORT_BASE).contains(&self.http_port_base) {
+            log_warn!("Ignoring out-of-range setting 'http_port_base' ({})", self.http_port_base);
+            self.http_port_base = defaults.http_port_base;
+        }
+        if !(MIN_INTERVAL_MS..=MAX_INTERVAL_MS).contains(&self.default_interval_ms) {
+            log_warn!("Ignoring out-of-range setting 'default_interval_ms' ({})", self.default_interval_ms);
+            self.default_interval_ms = defaults.default_interval_ms;
+        }
+    }
+
+    pub fn max_artifact_bytes(&self) -> u64 {
+        mb_to_bytes(self.max_artifact_mb)
+    }
+
+    pub fn report_workers(&self) -> usize {
+        self.max_parallel_reports.unwrap_or_else(parallel::default_workers)
+    }
+
+    pub fn cpu_normalization(&self) -> CpuNormalization {
+        self.cpu_normalization.unwrap_or_else(CpuNormalization::platform_default)
+    }
+
+    pub fn save(&self, db: &Database) -> Result<(), String> {
+        let v = serde_json::to_value(self).map_err(|e| e.to_string())?;
+        if let Value::Object(obj) = v {
+            for (key, value) in obj {
+                db.set_setting(&key, &value).map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), PerfSightError> {
+        for (name, port) in [("ws_port_base", self.ws_port_base), ("http_port_base", self.http_port_base)] {
+            if !(MIN_PORT_BASE..=MAX_PORT_BASE).contains(&port) {
+                return Err(PerfSightError::invalid_input(
+                    name,
+                    format!("must be between {} and {}", MIN_PORT_BASE, MAX_PORT_BASE),
+                ));
+            }
+        }
+        if self.ws_port_base.abs_diff(self.http_port_base) < PORT_FALLBACK_TRIES {
+            return Err(PerfSightError::invalid_input(
+                "http_port_base",
+                format!("must be at least {} apart from ws_port_base", PORT_FALLBACK_TRIES),
+            ));
+        }
+        // Same bounds as a run's interval (see `CollectionConfig::validate`).
+        if !(MIN_INTERVAL_MS..=MAX_INTERVAL_MS).contains(&self.default_interval_ms) {
+            return Err(PerfSightError::invalid_input(
+                "default_interval_ms",
+                format!("must be between {} and {}", MIN_INTERVAL_MS, MAX_INTERVAL_MS),
+            ));
+        }
+        if !(1..=16_384).contains(&self.max_artifact_mb) {
+            return Err(PerfSightError::invalid_input("max_artifact_mb", "must be between 1 and 16384"));
+        }
+        if let Some(sink) = &self.metric_sink {
+            url::Url::parse(sink.url.trim())
+                .map_err(|e| PerfSightError::invalid_input("metric_sink.url", e.to_string()))?;
+            if sink.batch_size == Some(0) {
+                return Err(PerfSightError::invalid_input("metric_sink.batch_size", "must be positive"));
+            }
+            if sink.flush_interval_ms == Some(0) {
+                return Err(PerfSightError::invalid_input("metric_sink.flush_interval_ms", "must be positive"));
+            }
+        }
+        roles::validate_templates(&self.role_templates)?;
+        if !(1..=100).contains(&self.analysis.regression_lookback) {
+            return Err(PerfSightError::invalid_input("analysis.regression_lookback", "must be between 1 and 100"));
+        }
+        if !(self.analysis.regression_threshold > 0.0 && self.analysis.regression_threshold <= 100.0) {
+            return Err(PerfSightError::invalid_input("analysis.regression_threshold", "must be between 0 and 100"));
+        }
+        self.test_context_limits.validate()?;
+        if self.max_parallel_reports.is_some_and(|n| !(1..=64).contains(&n)) {
+            return Err(PerfSightError::invalid_input("max_parallel_reports", "must be between 1 and 64"));
+        }
+        if self.webhook.enabled {
+            let url = url::Url::parse(self.webhook.url.trim())
+                .map_err(|e| PerfSightError::invalid_input("webhook.url", e.to_string()))?;
+            if url.scheme() != "http" && url.scheme() != "https" {
+                return Err(PerfSightError::invalid_input("webhook.url", "must be http(s)"));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn field_of(settings: &Settings) -> Option<String> {
+        match settings.validate() {
+            Ok(()) => None,
+            Err(PerfSightError::InvalidInput { field, .. }) => Some(field),
+            Err(e) => panic!("unexpected error {:?}", e),
+        }
+    }
+
+    #[test]
+    fn default_interval_uses_the_run_interval_bounds() {
+        for (interval, ok) in [
+            (MIN_INTERVAL_MS - 1, false),
+            (MIN_INTERVAL_MS, true),
+            (MAX_INTERVAL_MS, true),
+            (MAX_INTERVAL_MS + 1, false),
+            (3_600_000, false),
+        ] {
+            let settings = Settings { default_interval_ms: interval, ..Default::default() };
+            assert_eq!(field_of(&settings).is_none(), ok, "interval {}", interval);
+        }
+    }
+
+    #[test]
+    fn port_bases_leave_room_for_the_fallback_range() {
+        for (port, ok) in [
+            (MIN_PORT_BASE - 1, false),
+            (MIN_PORT_BASE, true),
+            (MAX_PORT_BASE, true),
+            (MAX_PORT_BASE + 1, false),
+            (u16::MAX, false),
+        ] {
+            let ws = Settings { ws_port_base: port, http_port_base: 2000, ..Default::default() };
+            assert_eq!(field_of(&ws).is_none(), ok, "ws_port_base {}", port);
+            let http = Settings { http_port_base: port, ws_port_base: 2000, ..Default::default() };
+            assert_eq!(field_of(&http).is_none(), ok, "http_port_base {}", port);
+        }
+        assert!(MAX_PORT_BASE.checked_add(PORT_FALLBACK_TRIES - 1).is_some());
+        let close = Settings { ws_port_base: 5000, http_port_base: 5000 + PORT_FALLBACK_TRIES - 1, ..Default::default() };
+        assert_eq!(field_of(&close).as_deref(), Some("http_port_base"));
+    }
+
+    #[test]
+    fn stored_out_of_range_values_load_as_defaults() {
+        let db = Database::new(":memory:").unwrap();
+        db.set_setting("ws_port_base", &json!(u16::MAX)).unwrap();
+        db.set_setting("http_port_base", &json!(80)).unwrap();
+        db.set_setting("default_interval_ms", &json!(3_600_000)).unwrap();
+        db.set_setting("max_artifact_mb", &json!(7)).unwrap();
+
+        let loaded = Settings::load(&db);
+        let defaults = Settings::default();
+        assert_eq!(loaded.ws_port_base, defaults.ws_port_base);
+        assert_eq!(loaded.http_port_base, defaults.http_port_base);
+        assert_eq!(loaded.default_interval_ms, defaults.default_interval_ms);
+        assert_eq!(loaded.max_artifact_mb, 7);
+        assert!(loaded.validate().is_ok());
+    }
+}
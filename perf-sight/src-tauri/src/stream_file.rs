@@ -0,0 +1,136 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use serde::Serialize;
+use crate::logging::log_warn;
+use crate::commands::safe_lock;
+use crate::models::BatchMetric;
+
+// Batches waiting for the writer; beyond this new ones are dropped instead of stalling ingest.
+const QUEUE_CAPACITY: usize = 10_000;
+// How often written lines are flushed and synced to disk.
+const FSYNC_INTERVAL: Duration = Duration::from_secs(2);
+// How long stop_collection waits for the final sync before reporting stats.
+const SHUTDOWN_WAIT: Duration = Duration::from_secs(3);
+
+/// Saved as `meta.collection.stream_file`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StreamFileStats {
+    pub path: String,
+    pub lines_written: u64,
+    pub lines_dropped: u64,
+    pub last_error: Option<String>,
+}
+
+#[derive(Default)]
+struct StreamCounters {
+    lines_written: AtomicU64,
+    lines_dropped: AtomicU64,
+    last_error: Mutex<Option<String>>,
+}
+
+/// Appends every buffered batch of a run to a JSON Lines file (one `BatchMetric` per line) from a
+/// background thread, so a long run can be tailed and a crashed one re-imported.
+pub struct StreamFile {
+    sender: Option<SyncSender<BatchMetric>>,
+    path: PathBuf,
+    counters: Arc<StreamCounters>,
+    // Behind a Mutex only so the writer can live in the shared (Sync) run state.
+    done: Mutex<Receiver<()>>,
+}
+
+impl StreamFile {
+    /// Open `path` for appending (creating parent directories) and start the writer thread.
+    pub fn start(path: &Path) -> Result<Self, String> {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("Cannot open {}: {}", path.display(), e))?;
+
+        let (sender, receiver) = mpsc::sync_channel::<BatchMetric>(QUEUE_CAPACITY);
+        let (done_tx, done) = mpsc::channel::<()>();
+        let counters = Arc::new(StreamCounters::default());
+        let worker_counters = counters.clone();
+        thread::spawn(move || {
+            write_loop(file, receiver, &worker_counters);
+            let _ = done_tx.send(());
+        });
+
+        Ok(Self { sender: Some(sender), path: path.to_path_buf(), counters, done: Mutex::new(done) })
+    }
+
+    /// Queue a freshly buffered batch. Never blocks.
+    pub fn send_batch(&self, batch: &BatchMetric) {
+        let Some(sender) = &self.sender else { return };
+        if sender.try_send(batch.clone()).is_err() {
+            self.counters.lines_dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn stats(&self) -> StreamFileStats {
+        StreamFileStats {
+            path: self.path.to_string_lossy().to_string(),
+            lines_written: self.counters.lines_written.load(Ordering::Relaxed),
+            lines_dropped: self.counters.lines_dropped.load(Ordering::Relaxed),
+            last_error: safe_lock(&self.counters.last_error).clone(),
+        }
+    }
+
+    /// Close the queue, give the writer a short window for its final sync and return the stats.
+    pub fn shutdown(mut self) -> StreamFileStats {
+        self.sender.take();
+        let _ = safe_lock(&self.done).recv_timeout(SHUTDOWN_WAIT);
+        self.stats()
+    }
+}
+
+fn write_loop(file: File, receiver: Receiver<BatchMetric>, counters: &StreamCounters) {
+    let mut out = BufWriter::new(file);
+    let mut last_sync = Instant::now();
+    let mut dirty = false;
+    loop {
+        let wait = FSYNC_INTERVAL.saturating_sub(last_sync.elapsed());
+        let disconnected = match receiver.recv_timeout(wait) {
+            Ok(batch) => {
+                let written = serde_json::to_string(&batch)
+                    .map_err(|e| e.to_string())
+                    .and_then(|line| writeln!(out, "{}", line).map_err(|e| e.to_string()));
+                match written {
+                    Ok(()) => {
+                        counters.lines_written.fetch_add(1, Ordering::Relaxed);
+                        dirty = true;
+                    }
+                    Err(e) => {
+                        counters.lines_dropped.fetch_add(1, Ordering::Relaxed);
+                        *safe_lock(&counters.last_error) = Some(e);
+                    }
+                }
+                false
+            }
+            Err(RecvTimeoutError::Timeout) => false,
+            Err(RecvTimeoutError::Disconnected) => true,
+        };
+        if dirty && (disconnected || last_sync.elapsed() >= FSYNC_INTERVAL) {
+            if let Err(e) = out.flush().and_then(|_| out.get_ref().sync_data()) {
+                log_warn!("Stream file sync failed: {}", e);
+                *safe_lock(&counters.last_error) = Some(e.to_string());
+            }
+            dirty = false;
+            last_sync = Instant::now();
+        } else if last_sync.elapsed() >= FSYNC_INTERVAL {
+            last_sync = Instant::now();
+        }
+        if disconnected {
+            break;
+        }
+    }
+}
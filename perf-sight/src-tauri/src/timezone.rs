@@ -0,0 +1,159 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use crate::error::PerfSightError;
+
+/// Zone timestamps are rendered in by exporters and summaries. Storage is always UTC.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DisplayZone {
+    Utc,
+    Named(Tz),
+    /// The machine's zone when it has no usable IANA name.
+    Local,
+}
+
+impl DisplayZone {
+    /// None / "" -> UTC, "local" -> this machine's zone, anything else must be an IANA name.
+    pub fn parse(raw: Option<&str>) -> Result<Self, PerfSightError> {
+        let s = raw.map(str::trim).unwrap_or("");
+        if s.is_empty() {
+            return Ok(DisplayZone::Utc);
+        }
+        if s.eq_ignore_ascii_case("local") {
+            return Ok(local_iana_name()
+                .and_then(|name| name.parse::<Tz>().ok())
+                .map(DisplayZone::Named)
+                .unwrap_or(DisplayZone::Local));
+        }
+        s.parse::<Tz>()
+            .map(DisplayZone::Named)
+            .map_err(|_| PerfSightError::invalid_input("timezone", format!("unknown IANA time zone '{}'", s)))
+    }
+
+    pub fn format(&self, t: DateTime<Utc>, fmt: &str) -> String {
+        match self {
+            DisplayZone::Utc => t.format(fmt).to_string(),
+            DisplayZone::Named(tz) => t.with_timezone(tz).format(fmt).to_string(),
+            DisplayZone::Local => t.with_timezone(&chrono::Local).format(fmt).to_string(),
+        }
+    }
+
+    /// RFC3339 with the zone's offset at `t` (DST-aware), e.g. 2024-03-31T03:30:00+02:00.
+    pub fn rfc3339(&self, t: DateTime<Utc>) -> String {
+        match self {
+            DisplayZone::Utc => t.to_rfc3339(),
+            DisplayZone::Named(tz) => t.with_timezone(tz).to_rfc3339(),
+            DisplayZone::Local => t.with_timezone(&chrono::Local).to_rfc3339(),
+        }
+    }
+
+    /// Short label for `t`: "UTC", the zone abbreviation ("CEST", "PDT") or "UTC+05:30".
+    pub fn label(&self, t: DateTime<Utc>) -> String {
+        match self {
+            DisplayZone::Utc => "UTC".to_string(),
+            DisplayZone::Named(tz) => t.with_timezone(tz).format("%Z").to_string(),
+            DisplayZone::Local => t.with_timezone(&chrono::Local).format("UTC%:z").to_string(),
+        }
+    }
+
+    /// Re-render a stored RFC3339 string; unparseable input is returned unchanged.
+    pub fn rfc3339_str(&self, stored: &str) -> String {
+        match DateTime::parse_from_rfc3339(stored) {
+            Ok(t) => self.rfc3339(t.with_timezone(&Utc)),
+            Err(_) => stored.to_string(),
+        }
+    }
+}
+
+/// IANA name of this machine's zone (e.g. "Europe/Berlin"), when the OS exposes one.
+pub fn local_iana_name() -> Option<String> {
+    iana_time_zone::get_timezone().ok().filter(|s| !s.trim().is_empty())
+}
+
+/// This machine's current UTC offset, e.g. "+02:00".
+pub fn local_utc_offset() -> String {
+    chrono::Local::now().format("%:z").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn zone(name: &str) -> DisplayZone {
+        DisplayZone::parse(Some(name)).unwrap()
+    }
+
+    #[test]
+    fn parse_accepts_utc_local_and_iana_names_only() {
+        assert_eq!(DisplayZone::parse(None).unwrap(), DisplayZone::Utc);
+        assert_eq!(DisplayZone::parse(Some("  ")).unwrap(), DisplayZone::Utc);
+        assert_eq!(zone("Europe/Berlin"), DisplayZone::Named(chrono_tz::Europe::Berlin));
+        assert!(matches!(zone("LOCAL"), DisplayZone::Named(_) | DisplayZone::Local));
+        let err = DisplayZone::parse(Some("Mars/Olympus")).unwrap_err().to_string();
+        assert!(err.contains("Mars/Olympus"), "{}", err);
+    }
+
+    // Europe/Berlin skips 02:00-03:00 on 2024-03-31 (01:00 UTC).
+    #[test]
+    fn spring_forward_jumps_the_wall_clock_and_the_offset() {
+        let berlin = zone("Europe/Berlin");
+        assert_eq!(berlin.rfc3339(utc("2024-03-31T00:59:59Z")), "2024-03-31T01:59:59+01:00");
+        assert_eq!(berlin.rfc3339(utc("2024-03-31T01:00:00Z")), "2024-03-31T03:00:00+02:00");
+        assert_eq!(berlin.label(utc("2024-03-31T00:59:59Z")), "CET");
+        assert_eq!(berlin.label(utc("2024-03-31T01:00:00Z")), "CEST");
+        assert_eq!(berlin.format(utc("2024-03-31T01:30:00Z"), "%H:%M"), "03:30");
+
+        // America/New_York skips 02:00-03:00 on 2024-03-10 (07:00 UTC).
+        let new_york = zone("America/New_York");
+        assert_eq!(new_york.rfc3339(utc("2024-03-10T06:59:00Z")), "2024-03-10T01:59:00-05:00");
+        assert_eq!(new_york.rfc3339(utc("2024-03-10T07:00:00Z")), "2024-03-10T03:00:00-04:00");
+        assert_eq!(new_york.label(utc("2024-03-10T07:00:00Z")), "EDT");
+    }
+
+    // Europe/Berlin repeats 02:00-03:00 on 2024-10-27: the two 02:30s differ only by offset.
+    #[test]
+    fn fall_back_repeats_the_wall_clock_with_distinct_offsets() {
+        let berlin = zone("Europe/Berlin");
+        let first = berlin.rfc3339(utc("2024-10-27T00:30:00Z"));
+        let second = berlin.rfc3339(utc("2024-10-27T01:30:00Z"));
+        assert_eq!(first, "2024-10-27T02:30:00+02:00");
+        assert_eq!(second, "2024-10-27T02:30:00+01:00");
+        assert_eq!(berlin.label(utc("2024-10-27T00:30:00Z")), "CEST");
+        assert_eq!(berlin.label(utc("2024-10-27T01:30:00Z")), "CET");
+        // Rendered strings still name the instant: they parse back to distinct, ordered UTC times.
+        assert!(utc(&first) < utc(&second));
+        assert_eq!(berlin.rfc3339_str("2024-10-27T01:30:00Z"), second);
+
+        let new_york = zone("America/New_York");
+        assert_eq!(new_york.rfc3339(utc("2024-11-03T05:30:00Z")), "2024-11-03T01:30:00-04:00");
+        assert_eq!(new_york.rfc3339(utc("2024-11-03T06:30:00Z")), "2024-11-03T01:30:00-05:00");
+    }
+
+    // Every minute across both 2024 transitions round-trips through the rendered string.
+    #[test]
+    fn rendering_round_trips_every_minute_across_transitions() {
+        let berlin = zone("Europe/Berlin");
+        for start in [Utc.with_ymd_and_hms(2024, 3, 30, 23, 0, 0).unwrap(), Utc.with_ymd_and_hms(2024, 10, 26, 23, 0, 0).unwrap()] {
+            let mut previous: Option<DateTime<Utc>> = None;
+            for minute in 0..240 {
+                let t = start + chrono::Duration::minutes(minute);
+                let back = utc(&berlin.rfc3339(t));
+                assert_eq!(back, t);
+                assert!(previous.is_none_or(|p| p < back));
+                previous = Some(back);
+            }
+        }
+    }
+
+    #[test]
+    fn utc_and_unparseable_input_are_left_alone() {
+        let t = utc("2024-10-27T01:30:00Z");
+        assert_eq!(DisplayZone::Utc.rfc3339(t), "2024-10-27T01:30:00+00:00");
+        assert_eq!(DisplayZone::Utc.label(t), "UTC");
+        assert_eq!(zone("Europe/Berlin").rfc3339_str("yesterday"), "yesterday");
+    }
+}
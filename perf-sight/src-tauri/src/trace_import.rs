@@ -0,0 +1,368 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io::Read;
+use chrono::{DateTime, Utc};
+use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::error::PerfSightError;
+use crate::models::{lenient, lenient_u64, BatchMetric, MetricPoint};
+
+/// Prefix of the custom metrics written by a trace import (replaced on re-import).
+pub const TRACE_METRIC_PREFIX: &str = "trace:";
+
+// Main-thread tasks longer than this count as long tasks (same threshold as DevTools).
+const LONG_TASK_MS: f64 = 50.0;
+
+const SCRIPTING: &[&str] = &[
+    "EvaluateScript", "v8.compile", "v8.compileModule", "v8.evaluateModule", "v8.run", "V8.Execute",
+    "FunctionCall", "TimerFire", "FireIdleCallback", "FireAnimationFrame", "RunMicrotasks",
+    "EventDispatch", "XHRReadyStateChange", "XHRLoad",
+];
+const LAYOUT: &[&str] = &["Layout", "UpdateLayoutTree", "RecalculateStyles", "UpdateLayerTree", "HitTest", "PrePaint", "Layerize"];
+const PAINT: &[&str] = &["Paint", "PaintImage", "CompositeLayers", "RasterTask", "Decode Image", "ImageDecodeTask"];
+const TOP_LEVEL_TASKS: &[&str] = &["RunTask", "ThreadControllerImpl::RunTask"];
+
+// Indexes into the per-bucket totals.
+const SCRIPTING_MS: usize = 0;
+const LAYOUT_MS: usize = 1;
+const PAINT_MS: usize = 2;
+const LONG_TASK_TOTAL_MS: usize = 3;
+const LONG_TASK_COUNT: usize = 4;
+
+/// Derived numbers stored in report meta under `trace_summary`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceSummary {
+    pub source_file: String,
+    pub imported_at: String,
+    pub trace_started_at: String,
+    /// Report window the totals were restricted to.
+    pub window_from: String,
+    pub window_to: String,
+    /// "pid:tid" of the threads counted (renderer main threads when the trace names them).
+    pub threads: Vec<String>,
+    pub events_parsed: u64,
+    pub long_task_count: u64,
+    pub long_task_total_ms: f64,
+    pub scripting_ms: f64,
+    pub layout_ms: f64,
+    pub paint_ms: f64,
+    /// Custom-metric points added to the report (0 unless requested).
+    pub points_added: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct TraceEvent {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    ph: String,
+    #[serde(default, deserialize_with = "lenient")]
+    ts: Option<f64>,
+    #[serde(default, deserialize_with = "lenient")]
+    dur: Option<f64>,
+    #[serde(default, deserialize_with = "lenient_u64")]
+    pid: Option<u64>,
+    #[serde(default, deserialize_with = "lenient_u64")]
+    tid: Option<u64>,
+    #[serde(default, deserialize_with = "lenient")]
+    args: Value,
+}
+
+#[derive(Default)]
+struct ThreadAgg {
+    name: Option<String>,
+    // Trace second (ts / 1e6) -> totals indexed by the *_MS / LONG_TASK_COUNT constants.
+    buckets: BTreeMap<i64, [f64; 5]>,
+    // Per category, the end of the time already counted, so nested events aren't counted twice.
+    covered_until: [f64; 3],
+    // Open "B" events.
+    open: Vec<(String, f64)>,
+}
+
+impl ThreadAgg {
+    // Count the part of [start, end) (µs) not already covered for `category`.
+    fn add_span(&mut self, category: usize, start: f64, end: f64) {
+        let from = start.max(self.covered_until[category]);
+        if end > from {
+            self.bucket(from)[category] += (end - from) / 1000.0;
+            self.covered_until[category] = end;
+        }
+    }
+
+    fn bucket(&mut self, ts: f64) -> &mut [f64; 5] {
+        self.buckets.entry((ts / 1_000_000.0).floor() as i64).or_insert([0.0; 5])
+    }
+
+    fn complete(&mut self, name: &str, start: f64, dur: f64) {
+        let end = start + dur.max(0.0);
+        if SCRIPTING.contains(&name) {
+            self.add_span(SCRIPTING_MS, start, end);
+        } else if LAYOUT.contains(&name) {
+            self.add_span(LAYOUT_MS, start, end);
+        } else if PAINT.contains(&name) {
+            self.add_span(PAINT_MS, start, end);
+        } else if TOP_LEVEL_TASKS.contains(&name) && dur / 1000.0 > LONG_TASK_MS {
+            let b = self.bucket(start);
+            b[LONG_TASK_TOTAL_MS] += dur / 1000.0;
+            b[LONG_TASK_COUNT] += 1.0;
+        }
+    }
+}
+
+#[derive(Default)]
+struct TraceAggregate {
+    threads: HashMap<(u64, u64), ThreadAgg>,
+    events: u64,
+    min_ts: Option<f64>,
+    metadata: Value,
+}
+
+impl TraceAggregate {
+    fn add(&mut self, e: TraceEvent) {
+        self.events += 1;
+        let key = (e.pid.unwrap_or(0), e.tid.unwrap_or(0));
+        if e.ph == "M" {
+            if e.name == "thread_name" {
+                let name = e.args.get("name").and_then(|n| n.as_str()).map(str::to_string);
+                self.threads.entry(key).or_default().name = name;
+            }
+            return;
+        }
+        let Some(ts) = e.ts.filter(|t| t.is_finite() && *t > 0.0) else { return };
+        self.min_ts = Some(self.min_ts.map_or(ts, |m| m.min(ts)));
+        let thread = self.threads.entry(key).or_default();
+        match e.ph.as_str() {
+            "X" => thread.complete(&e.name, ts, e.dur.unwrap_or(0.0)),
+            "B" => thread.open.push((e.name, ts)),
+            "E" => {
+                if let Some((name, start)) = thread.open.pop() {
+                    thread.complete(&name, start, ts - start);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// Feeds every element of `traceEvents` (or of a bare array) into the aggregate without
+// materializing the array.
+struct EventsSeed<'a>(&'a mut TraceAggregate);
+
+impl<'de> DeserializeSeed<'de> for EventsSeed<'_> {
+    type Value = ();
+    fn deserialize<D: Deserializer<'de>>(self, d: D) -> Result<(), D::Error> {
+        d.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for EventsSeed<'_> {
+    type Value = ();
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an array of trace events")
+    }
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(event) = seq.next_element::<Option<TraceEvent>>()? {
+            if let Some(event) = event {
+                self.0.add(event);
+            }
+        }
+        Ok(())
+    }
+}
+
+// Top level: either the JSON Array Format or the JSON Object Format (`traceEvents` + `metadata`).
+struct TraceFileSeed<'a>(&'a mut TraceAggregate);
+
+impl<'de> DeserializeSeed<'de> for TraceFileSeed<'_> {
+    type Value = bool;
+    fn deserialize<D: Deserializer<'de>>(self, d: D) -> Result<bool, D::Error> {
+        d.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for TraceFileSeed<'_> {
+    // Whether any trace events were found.
+    type Value = bool;
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a Chrome trace (array of events or object with traceEvents)")
+    }
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<bool, A::Error> {
+        EventsSeed(self.0).visit_seq(seq)?;
+        Ok(true)
+    }
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<bool, A::Error> {
+        let mut found = false;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "traceEvents" => {
+                    map.next_value_seed(EventsSeed(self.0))?;
+                    found = true;
+                }
+                "metadata" => self.0.metadata = map.next_value()?,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(found)
+    }
+}
+
+fn parse_error(e: serde_json::Error) -> PerfSightError {
+    let reason = if e.is_eof() {
+        format!("trace file is truncated ({})", e)
+    } else {
+        format!("not a valid Chrome trace: {}", e)
+    };
+    PerfSightError::invalid_input("trace_path", reason)
+}
+
+/// Per-second totals of a trace, mapped to wall-clock time.
+pub struct ParsedTrace {
+    pub started_at: DateTime<Utc>,
+    pub threads: Vec<(u64, u64)>,
+    pub events: u64,
+    /// (second start, totals) ordered by time.
+    pub buckets: Vec<(DateTime<Utc>, [f64; 5])>,
+}
+
+/// Stream-parse a trace. `started_at` anchors the trace clock to wall time and overrides the
+/// trace's own `metadata.startTime`; one of the two is required.
+pub fn parse_trace<R: Read>(reader: R, started_at: Option<DateTime<Utc>>) -> Result<ParsedTrace, PerfSightError> {
+    let mut agg = TraceAggregate::default();
+    let mut de = serde_json::Deserializer::from_reader(reader);
+    let found = TraceFileSeed(&mut agg).deserialize(&mut de).map_err(parse_error)?;
+    de.end().map_err(parse_error)?;
+    if !found {
+        return Err(PerfSightError::invalid_input("trace_path", "unsupported trace format: no traceEvents"));
+    }
+    let Some(min_ts) = agg.min_ts else {
+        return Err(PerfSightError::invalid_input("trace_path", "trace contains no timed events"));
+    };
+    let started_at = started_at
+        .or_else(|| {
+            agg.metadata
+                .get("startTime")
+                .and_then(|s| s.as_str())
+                .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+                .map(|d| d.with_timezone(&Utc))
+        })
+        .ok_or_else(|| {
+            PerfSightError::invalid_input("trace_started_at", "trace has no metadata.startTime; pass trace_started_at")
+        })?;
+
+    // Renderer main threads when the trace names them, otherwise every thread.
+    let main: Vec<(u64, u64)> = agg
+        .threads
+        .iter()
+        .filter(|(_, t)| t.name.as_deref() == Some("CrRendererMain"))
+        .map(|(k, _)| *k)
+        .collect();
+    let mut threads: Vec<(u64, u64)> = if main.is_empty() { agg.threads.keys().copied().collect() } else { main };
+    threads.sort_unstable();
+
+    let base_second = (min_ts / 1_000_000.0).floor() as i64;
+    let mut merged: BTreeMap<i64, [f64; 5]> = BTreeMap::new();
+    for key in &threads {
+        for (second, totals) in &agg.threads[key].buckets {
+            let m = merged.entry(*second).or_insert([0.0; 5]);
+            for (dst, src) in m.iter_mut().zip(totals) {
+                *dst += src;
+            }
+        }
+    }
+    let buckets = merged
+        .into_iter()
+        .map(|(second, totals)| (started_at + chrono::Duration::seconds(second - base_second), totals))
+        .collect();
+    Ok(ParsedTrace { started_at, threads, events: agg.events, buckets })
+}
+
+impl ParsedTrace {
+    /// Totals for buckets inside [from, to].
+    pub fn summarize(&self, source_file: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> TraceSummary {
+        let mut totals = [0.0; 5];
+        for (_, b) in self.buckets.iter().filter(|(t, _)| *t >= from && *t <= to) {
+            for (dst, src) in totals.iter_mut().zip(b) {
+                *dst += src;
+            }
+        }
+        let round = |v: f64| (v * 10.0).round() / 10.0;
+        TraceSummary {
+            source_file: source_file.to_string(),
+            imported_at: Utc::now().to_rfc3339(),
+            trace_started_at: self.started_at.to_rfc3339(),
+            window_from: from.to_rfc3339(),
+            window_to: to.to_rfc3339(),
+            threads: self.threads.iter().map(|(pid, tid)| format!("{}:{}", pid, tid)).collect(),
+            events_parsed: self.events,
+            long_task_count: totals[LONG_TASK_COUNT] as u64,
+            long_task_total_ms: round(totals[LONG_TASK_TOTAL_MS]),
+            scripting_ms: round(totals[SCRIPTING_MS]),
+            layout_ms: round(totals[LAYOUT_MS]),
+            paint_ms: round(totals[PAINT_MS]),
+            points_added: 0,
+        }
+    }
+
+    /// One custom-metric-only batch per non-empty second in [from, to], attributed to `pid`.
+    pub fn custom_batches(&self, pid: u32, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<BatchMetric> {
+        self.buckets
+            .iter()
+            .filter(|(t, b)| *t >= from && *t <= to && b.iter().any(|v| *v > 0.0))
+            .map(|(t, b)| {
+                let custom: HashMap<String, f64> = [
+                    ("scripting_ms", b[SCRIPTING_MS]),
+                    ("layout_ms", b[LAYOUT_MS]),
+                    ("paint_ms", b[PAINT_MS]),
+                    ("long_task_ms", b[LONG_TASK_TOTAL_MS]),
+                ]
+                .into_iter()
+                .map(|(k, v)| (format!("{}{}", TRACE_METRIC_PREFIX, k), v))
+                .collect();
+                let point = MetricPoint {
+                    timestamp: *t,
+                    pid,
+                    cpu_usage: 0.0,
+                    cpu_os_usage: 0.0,
+                    cpu_os_usage_raw: None,
+                    cpu_chrome_usage: None,
+                    memory_rss: 0,
+                    memory_footprint: None,
+                    gpu_usage: None,
+                    js_heap_size: None,
+                    js_heap_total_size: None,
+                    memory_private: None,
+                    gpu_memory_bytes: None,
+                    max_thread_cpu: None,
+                    custom_metrics: Some(custom),
+                    memory_basis: None,
+                };
+                BatchMetric { timestamp: *t, metrics: HashMap::from([(pid, point)]) }
+            })
+            .collect()
+    }
+}
+
+/// Drop custom metrics from an earlier trace import, and points/batches left empty by that.
+pub fn strip_trace_metrics(metrics: &mut Vec<BatchMetric>) {
+    for batch in metrics.iter_mut() {
+        batch.metrics.retain(|_, p| {
+            let Some(custom) = p.custom_metrics.as_mut() else { return true };
+            let before = custom.len();
+            custom.retain(|k, _| !k.starts_with(TRACE_METRIC_PREFIX));
+            if custom.is_empty() {
+                p.custom_metrics = None;
+            }
+            // Points that only ever carried trace metrics go away entirely.
+            let trace_only = before > 0
+                && p.custom_metrics.is_none()
+                && p.cpu_usage == 0.0
+                && p.memory_rss == 0
+                && p.memory_private.is_none();
+            !trace_only
+        });
+    }
+    metrics.retain(|b| !b.metrics.is_empty());
+}
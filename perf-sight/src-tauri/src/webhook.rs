@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+use std::thread;
+use std::time::Duration;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager, State};
+use crate::logging::log_warn;
+use crate::analysis::{AnalysisReport, ScoreRegression};
+use crate::database::Database;
+use crate::models::ReportMeta;
+
+pub const SETTING_WEBHOOK: &str = "webhook";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+// One initial attempt plus two retries.
+const MAX_ATTEMPTS: u32 = 3;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+pub fn load_config(db: &Database) -> WebhookConfig {
+    db.get_setting_as(SETTING_WEBHOOK).ok().flatten().unwrap_or_default()
+}
+
+/// JSON body sent when a report is saved.
+pub fn build_payload(
+    report_id: i64,
+    title: &str,
+    meta: &ReportMeta,
+    analysis: &AnalysisReport,
+    score_regression: Option<&ScoreRegression>,
+) -> Value {
+    let folder_path = meta.folder_path();
+    json!({
+        "event": "report_saved",
+        "report_id": report_id,
+        "title": title,
+        "folder_path": if folder_path.is_empty() { None } else { Some(folder_path) },
+        "tags": meta.tags(),
+        "scenario_name": meta.scenario_name(),
+        "build_id": meta.build_id(),
+        "duration_seconds": meta.duration_seconds(),
+        "score": analysis.score,
+        "insights": analysis.insights.iter().take(3).map(|i| &i.message).collect::<Vec<_>>(),
+        "score_regression": score_regression
+    })
+}
+
+fn deliver(config: &WebhookConfig, payload: &Value) -> Value {
+    let client = match reqwest::blocking::Client::builder().timeout(REQUEST_TIMEOUT).build() {
+        Ok(c) => c,
+        Err(e) => return json!({ "status": "failed", "attempts": 0, "error": e.to_string() }),
+    };
+    let mut last_error = String::new();
+    let mut last_status: Option<u16> = None;
+    for attempt in 1..=MAX_ATTEMPTS {
+        if attempt > 1 {
+            thread::sleep(Duration::from_millis(500 * attempt as u64));
+        }
+        let mut req = client.post(config.url.trim()).json(payload);
+        for (k, v) in &config.headers {
+            req = req.header(k.as_str(), v.as_str());
+        }
+        match req.send() {
+            Ok(resp) if resp.status().is_success() => {
+                return json!({
+                    "status": "delivered",
+                    "attempts": attempt,
+                    "http_status": resp.status().as_u16(),
+                    "delivered_at": Utc::now().to_rfc3339()
+                });
+            }
+            Ok(resp) => {
+                last_status = Some(resp.status().as_u16());
+                last_error = format!("HTTP {}", resp.status());
+            }
+            Err(e) => last_error = e.to_string(),
+        }
+    }
+    json!({
+        "status": "failed",
+        "attempts": MAX_ATTEMPTS,
+        "http_status": last_status,
+        "error": last_error
+    })
+}
+
+/// Fire-and-forget delivery on a background thread; the outcome is recorded in the report's
+/// meta under `webhook`. Never affects the save itself.
+pub fn notify_report_saved<R: tauri::Runtime>(app: AppHandle<R>, config: WebhookConfig, report_id: i64, payload: Value) {
+    thread::spawn(move || {
+        let status = deliver(&config, &payload);
+        if status["status"] != "delivered" {
+            log_warn!("Webhook delivery failed for report {}: {}", report_id, status["error"]);
+        }
+        let db: State<Database> = app.state();
+        if let Err(e) = db.update_report_meta_patch(report_id, &json!({ "webhook": status })) {
+            log_warn!("Failed to record webhook status for report {}: {}", report_id, e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    // Headers (lowercased names) and JSON body of one request.
+    type Received = (HashMap<String, String>, Value);
+
+    // A local receiver answering `statuses` in turn, handing each request to the test.
+    fn mock_server(statuses: Vec<u16>) -> (String, mpsc::Receiver<Received>) {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", server.server_addr().to_ip().unwrap());
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for (mut req, status) in server.incoming_requests().zip(statuses) {
+                let headers = req.headers().iter().map(|h| (h.field.to_string().to_lowercase(), h.value.to_string())).collect();
+                let body: Value = serde_json::from_reader(req.as_reader()).expect("JSON body");
+                let _ = req.respond(tiny_http::Response::empty(status));
+                let _ = tx.send((headers, body));
+            }
+        });
+        (url, rx)
+    }
+
+    fn saved_report(db: &Database) -> (i64, ReportMeta, AnalysisReport) {
+        let meta = ReportMeta::from_json_str(
+            &json!({
+                "collection": { "folder_path": "Nightly/Chat", "duration_seconds": 3600 },
+                "test_context": { "scenario_name": "Chat idle", "build_id": "1.2.3", "tags": ["nightly", "ci"] },
+            })
+            .to_string(),
+        );
+        let metrics = crate::sample_data::reports(Utc::now()).remove(1).metrics;
+        let id = db.save_report("Chat idle", &metrics, &meta).unwrap();
+        let analysis = crate::analysis::analyze_report(&metrics, &meta);
+        (id, meta, analysis)
+    }
+
+    fn wait_for_status(db: &Database, id: i64) -> Value {
+        for _ in 0..100 {
+            if let Some(status) = db.get_report_meta(id).unwrap().extra.get("webhook") {
+                return status.clone();
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        panic!("webhook status never recorded");
+    }
+
+    #[test]
+    fn saved_report_is_posted_and_the_delivery_recorded() {
+        let app = tauri::test::mock_app();
+        app.manage(Database::new(":memory:").unwrap());
+        let db = app.state::<Database>();
+        let (id, meta, analysis) = saved_report(db.inner());
+        let (url, received) = mock_server(vec![200]);
+        let config = WebhookConfig {
+            enabled: true,
+            url,
+            headers: HashMap::from([("X-Team".to_string(), "perf".to_string())]),
+        };
+
+        notify_report_saved(app.handle().clone(), config, id, build_payload(id, "Chat idle", &meta, &analysis, None));
+
+        let (headers, body) = received.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(headers["x-team"], "perf");
+        assert_eq!(headers["content-type"], "application/json");
+        let insights: Vec<&String> = analysis.insights.iter().take(3).map(|i| &i.message).collect();
+        assert_eq!(
+            body,
+            json!({
+                "event": "report_saved",
+                "report_id": id,
+                "title": "Chat idle",
+                "folder_path": "Nightly/Chat",
+                "tags": ["nightly", "ci"],
+                "scenario_name": "Chat idle",
+                "build_id": "1.2.3",
+                "duration_seconds": 3600,
+                "score": analysis.score,
+                "insights": insights,
+                "score_regression": null,
+            })
+        );
+        assert!(!analysis.insights.is_empty());
+
+        let status = wait_for_status(db.inner(), id);
+        assert_eq!((status["status"].as_str(), status["attempts"].as_u64()), (Some("delivered"), Some(1)));
+    }
+
+    #[test]
+    fn failed_attempts_are_retried_and_the_last_error_kept() {
+        let (url, received) = mock_server(vec![503, 200]);
+        let config = WebhookConfig { enabled: true, url: url.clone(), headers: HashMap::new() };
+        let status = deliver(&config, &json!({ "event": "report_saved" }));
+        assert_eq!((status["status"].as_str(), status["attempts"].as_u64()), (Some("delivered"), Some(2)));
+        assert_eq!(received.iter().take(2).count(), 2);
+
+        let (url, _received) = mock_server(vec![500; MAX_ATTEMPTS as usize]);
+        let status = deliver(&WebhookConfig { url, ..config }, &json!({}));
+        assert_eq!(status["status"], "failed");
+        assert_eq!(status["attempts"], MAX_ATTEMPTS);
+        assert_eq!(status["http_status"], 500);
+    }
+}
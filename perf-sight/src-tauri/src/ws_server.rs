@@ -1,18 +1,205 @@
-use std::net::TcpListener;
+use std::net::{TcpListener, TcpStream};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
 use std::thread;
-use tungstenite::accept;
+use std::time::Duration;
+use tungstenite::protocol::frame::coding::CloseCode;
+use tungstenite::protocol::CloseFrame;
+use tungstenite::{accept, Message, WebSocket};
 use tauri::{AppHandle, Manager, State, Emitter};
-use crate::commands::{CollectionState, process_websocket_metric_payload, push_custom_metric, safe_lock};
-use serde_json::Value;
-use chrono::{Utc, TimeZone};
+use crate::logging::{log_error, log_info, log_warn};
+use crate::commands::{ActiveRun, CollectionState, append_run_event, payload_timestamp, process_websocket_metric_payload, push_custom_metric, safe_lock};
+use crate::models::{LogValueFormat, RunEventKind, MAX_LOG_LINE_BYTES};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use chrono::{DateTime, Utc};
+use crate::database::Database;
+use crate::settings::{Settings, PORT_FALLBACK_TRIES};
 
-fn bind_ws_listener_with_fallback() -> Option<(TcpListener, u16)> {
-    // Prefer 23333, but if busy, try a small range (dev-friendly).
+/// Local ingest endpoints (WebSocket for the extension, HTTP for other data sources).
+/// Ports are filled in once the listeners are bound.
+#[derive(Clone)]
+pub struct IngestServerState {
+    pub ws_port: Arc<Mutex<Option<u16>>>,
+    pub http_port: Arc<Mutex<Option<u16>>>,
+    /// Random per-launch token HTTP clients must present (X-PerfSight-Token or Bearer).
+    pub session_token: String,
+    /// Serve GET /metrics (Prometheus text format) on the HTTP listener. Off by default.
+    pub prometheus_enabled: Arc<Mutex<bool>>,
+    /// Extension connections that completed the hello handshake.
+    pub ws_clients: Arc<Mutex<Vec<WsClientInfo>>>,
+    /// Their sockets by client id, so `close_connections` can end them from another thread.
+    pub ws_streams: Arc<Mutex<HashMap<String, TcpStream>>>,
+    /// When each of them last sent a message (the hello included), by client id.
+    pub ws_last_message: Arc<Mutex<HashMap<String, DateTime<Utc>>>>,
+    /// Set on app exit; the listener stops accepting.
+    pub closing: Arc<AtomicBool>,
+    /// Feeds the log matching thread once the server has started.
+    pub log_queue: Arc<Mutex<Option<SyncSender<Value>>>>,
+}
+
+impl IngestServerState {
+    pub fn new() -> Self {
+        Self {
+            ws_port: Arc::new(Mutex::new(None)),
+            http_port: Arc::new(Mutex::new(None)),
+            session_token: uuid::Uuid::new_v4().simple().to_string(),
+            prometheus_enabled: Arc::new(Mutex::new(false)),
+            ws_clients: Arc::new(Mutex::new(Vec::new())),
+            ws_streams: Arc::new(Mutex::new(HashMap::new())),
+            ws_last_message: Arc::new(Mutex::new(HashMap::new())),
+            closing: Arc::new(AtomicBool::new(false)),
+            log_queue: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+impl IngestServerState {
+    /// Extension connections heard from within `EXTENSION_ACTIVE_WINDOW`.
+    pub fn active_extensions(&self) -> Vec<WsClientInfo> {
+        let cutoff = Utc::now() - EXTENSION_ACTIVE_WINDOW;
+        let last = safe_lock(&self.ws_last_message);
+        safe_lock(&self.ws_clients)
+            .iter()
+            .filter(|c| last.get(&c.id).is_some_and(|t| *t >= cutoff))
+            .cloned()
+            .collect()
+    }
+
+    pub fn extension_status(&self) -> ExtensionStatus {
+        // One lock at a time: `active_extensions` takes both.
+        let connected = safe_lock(&self.ws_clients).len();
+        let active = self.active_extensions().len();
+        let last_message_at = safe_lock(&self.ws_last_message).values().max().map(|t| t.to_rfc3339());
+        ExtensionStatus { connected, active, last_message_at }
+    }
+}
+
+/// Extension connections as seen by `get_collection_status`. Browser-mode starts that need the
+/// extension's memory figures check `active` (see `start_collection`).
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtensionStatus {
+    /// Connections that completed the hello.
+    pub connected: usize,
+    /// Of those, the ones that sent something recently (the extension reports every second or so).
+    pub active: usize,
+    pub last_message_at: Option<String>,
+}
+
+impl Default for IngestServerState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Extension protocol versions this build understands (the `hello` message's `protocol_version`).
+pub const WS_PROTOCOL_MIN: u32 = 1;
+pub const WS_PROTOCOL_MAX: u32 = 1;
+
+// An extension silent for longer no longer counts as active.
+const EXTENSION_ACTIVE_WINDOW: chrono::TimeDelta = chrono::TimeDelta::seconds(10);
+// A client that hasn't said hello by then is dropped.
+const HELLO_TIMEOUT: Duration = Duration::from_secs(5);
+// Console logs waiting to be matched; once full, further lines are dropped (and counted).
+const LOG_QUEUE_CAPACITY: usize = 1024;
+
+/// First message every extension connection must send.
+#[derive(Debug, Deserialize)]
+struct Hello {
+    protocol_version: u32,
+    #[serde(default)]
+    extension_version: Option<String>,
+    #[serde(default)]
+    capabilities: Vec<String>,
+}
+
+/// A connected extension and what it negotiated (shown in `get_ws_server_status`, saved in
+/// report meta for runs that used its data).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WsClientInfo {
+    pub id: String,
+    pub connected_at: String,
+    pub protocol_version: u32,
+    pub extension_version: Option<String>,
+    pub capabilities: Vec<String>,
+}
+
+fn close_with(websocket: &mut WebSocket<TcpStream>, code: CloseCode, reason: String) {
+    log_warn!("Rejecting extension connection: {}", reason);
+    let _ = websocket.close(Some(CloseFrame { code, reason: reason.into() }));
+    // Let the close handshake go out before the socket is dropped.
+    let _ = websocket.flush();
+}
+
+// Wait for the hello, check the version and answer it. None when the client was rejected.
+fn handshake(app: &AppHandle, websocket: &mut WebSocket<TcpStream>) -> Option<WsClientInfo> {
+    let _ = websocket.get_ref().set_read_timeout(Some(HELLO_TIMEOUT));
+    let text = match websocket.read() {
+        Ok(Message::Text(text)) => text.to_string(),
+        Ok(_) | Err(_) => {
+            close_with(websocket, CloseCode::Policy, "expected a hello message first".to_string());
+            return None;
+        }
+    };
+    let _ = websocket.get_ref().set_read_timeout(None);
+
+    let value: Value = serde_json::from_str(&text).unwrap_or(Value::Null);
+    if value["type"] != "hello" {
+        close_with(websocket, CloseCode::Policy, "expected a hello message first".to_string());
+        return None;
+    }
+    let hello: Hello = match serde_json::from_value(value) {
+        Ok(h) => h,
+        Err(e) => {
+            close_with(websocket, CloseCode::Protocol, format!("invalid hello: {}", e));
+            return None;
+        }
+    };
+    if !(WS_PROTOCOL_MIN..=WS_PROTOCOL_MAX).contains(&hello.protocol_version) {
+        close_with(
+            websocket,
+            CloseCode::Protocol,
+            format!(
+                "protocol_version {} is not supported (PerfSight {} supports {}-{}); update the extension or the app",
+                hello.protocol_version,
+                app.package_info().version,
+                WS_PROTOCOL_MIN,
+                WS_PROTOCOL_MAX
+            ),
+        );
+        return None;
+    }
+
+    let state: State<CollectionState> = app.state();
+    // With several sessions, report browser mode when any session uses it.
+    let modes = state.read_each(|run| run.mode.clone());
+    let collection_mode = modes.iter().find(|m| *m == "browser").or(modes.first()).cloned();
+    let ack = json!({
+        "type": "hello_ack",
+        "protocol_version": hello.protocol_version,
+        "supported": { "min": WS_PROTOCOL_MIN, "max": WS_PROTOCOL_MAX },
+        "app_version": app.package_info().version.to_string(),
+        "collection_mode": collection_mode,
+    });
+    if websocket.send(Message::Text(ack.to_string().into())).is_err() {
+        return None;
+    }
+    Some(WsClientInfo {
+        id: uuid::Uuid::new_v4().simple().to_string(),
+        connected_at: Utc::now().to_rfc3339(),
+        protocol_version: hello.protocol_version,
+        extension_version: hello.extension_version,
+        capabilities: hello.capabilities,
+    })
+}
+
+fn bind_ws_listener_with_fallback(base: u16) -> Option<(TcpListener, u16)> {
+    // Prefer the configured base (23333 by default), but if busy, try a small range (dev-friendly).
     // This avoids flaky `tauri dev` on Windows when a previous instance still holds the port.
-    let base: u16 = 23333;
-    let max_tries: u16 = 30;
-    for i in 0..max_tries {
-        let port = base + i;
+    for i in 0..PORT_FALLBACK_TRIES {
+        let Some(port) = base.checked_add(i) else { break };
         let addr = format!("127.0.0.1:{}", port);
         match TcpListener::bind(&addr) {
             Ok(l) => return Some((l, port)),
@@ -20,7 +207,7 @@ fn bind_ws_listener_with_fallback() -> Option<(TcpListener, u16)> {
                 if e.kind() == std::io::ErrorKind::AddrInUse {
                     continue;
                 }
-                eprintln!("Failed to bind WebSocket server on {}: {}", addr, e);
+                log_warn!("Failed to bind WebSocket server on {}: {}", addr, e);
                 return None;
             }
         }
@@ -28,70 +215,247 @@ fn bind_ws_listener_with_fallback() -> Option<(TcpListener, u16)> {
     None
 }
 
+// The `target_id` (CDP page target) or `tab_id` (extension tab) a log came from, as map keys.
+fn log_tab_keys(log_data: &Value) -> Vec<String> {
+    ["target_id", "tab_id"]
+        .iter()
+        .filter_map(|field| match &log_data[*field] {
+            Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+            Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+// PID of the tab a log came from, per the sessions' tab maps. A tab seen with a real PID that
+// no map knows yet is remembered, so later logs from it resolve even without one.
+fn resolve_tab_pid(state: &CollectionState, keys: &[String], log_pid: u32) -> Option<u32> {
+    let known = keys
+        .iter()
+        .find_map(|key| state.read_each(|run| run.tab_pids.get(key).copied()).into_iter().flatten().next());
+    if known.is_none() && log_pid != 0 {
+        if let Some(key) = keys.first() {
+            state.write_each(|run| {
+                if run.mode == "browser" {
+                    run.tab_pids.insert(key.clone(), log_pid);
+                }
+            });
+        }
+    }
+    known
+}
+
+/// Handle a `console_log` payload: run the configured log metric regexes over the content
+/// and push every captured value as a custom metric. Returns the number of pushed points.
+///
+/// The PID is the one mapped to the log's `target_id`/`tab_id`, else the config's
+/// `target_pid`, else the `pid` the extension attached.
+pub fn process_console_log_payload<R: tauri::Runtime>(app: &AppHandle<R>, state: &CollectionState, data: &Value) -> usize {
+    let log_data = &data["data"];
+    let content = log_data["content"].as_str().unwrap_or("");
+    let pid = log_data["pid"].as_u64().unwrap_or(0) as u32;
+    let tab_pid = resolve_tab_pid(state, &log_tab_keys(log_data), pid);
+    let ts_ms = log_data["timestamp"].as_i64().unwrap_or(Utc::now().timestamp_millis());
+    let timestamp = match payload_timestamp(ts_ms) {
+        Ok(t) => t,
+        Err(e) => {
+            log_warn!("Dropping console_log payload: {}", e);
+            return 0;
+        }
+    };
+
+    let configs = state.read_each(|run| run.log_metrics.clone()).concat();
+    if configs.is_empty() {
+        return 0;
+    }
+    // Match only the start of very long lines, keeping the cost per line bounded.
+    let content = if content.len() > MAX_LOG_LINE_BYTES {
+        let mut end = MAX_LOG_LINE_BYTES;
+        while !content.is_char_boundary(end) {
+            end -= 1;
+        }
+        state.write_each(|run| {
+            if !run.log_metrics.is_empty() {
+                run.log_ingest.truncated_lines += 1;
+            }
+        });
+        &content[..end]
+    } else {
+        content
+    };
+    let default_format = LogValueFormat::default();
+    let mut pushed = 0;
+    let mut failed = Vec::new();
+
+    for (cfg, re) in configs.iter() {
+        let Some(caps) = re.captures(content) else { continue };
+        let effective_pid = tab_pid.or(cfg.target_pid).unwrap_or(pid);
+        let format = cfg.value_format.as_ref().unwrap_or(&default_format);
+        // Legacy configs read the single group; named captures each feed their own metric, all
+        // sharing the line's timestamp and PID. Groups that did not participate are skipped.
+        for target in cfg.targets() {
+            let val_match = match target.group {
+                Some(group) => caps.name(group),
+                None => caps.get(1),
+            };
+            let Some(val_match) = val_match else { continue };
+            match format.parse(val_match.as_str()) {
+                Some(val) => {
+                    push_custom_metric(app, state, effective_pid, timestamp, target.name.to_string(), val);
+                    pushed += 1;
+                }
+                None => failed.push((target.name.to_string(), val_match.as_str().to_string())),
+            }
+        }
+    }
+    if !failed.is_empty() {
+        // Sessions sharing a config each ran it; count the capture once per session.
+        failed.sort();
+        failed.dedup();
+        state.write_each(|run| {
+            for (name, text) in &failed {
+                if run.log_metrics.iter().any(|(c, _)| c.targets().iter().any(|t| t.name == name)) {
+                    let f = run.log_parse_failures.entry(name.clone()).or_default();
+                    f.count += 1;
+                    f.last_text = text.clone();
+                }
+            }
+        });
+    }
+    pushed
+}
+
+/// Start the thread that matches `console_log` payloads from the extension sockets against the
+/// log metric patterns, so a slow pattern delays only custom metrics, never metric payloads.
+pub(crate) fn spawn_log_worker<R: tauri::Runtime>(app: AppHandle<R>) -> SyncSender<Value> {
+    let (tx, rx) = mpsc::sync_channel::<Value>(LOG_QUEUE_CAPACITY);
+    thread::spawn(move || {
+        for data in rx {
+            let state: State<CollectionState> = app.state();
+            process_console_log_payload(&app, state.inner(), &data);
+        }
+    });
+    tx
+}
+
+// Hand a `console_log` payload to the log worker; matched inline only if the worker is not running.
+pub(crate) fn queue_console_log<R: tauri::Runtime>(app: &AppHandle<R>, data: Value) {
+    let server_state: State<IngestServerState> = app.state();
+    let queue = safe_lock(&server_state.log_queue).clone();
+    let state: State<CollectionState> = app.state();
+    let data = match queue {
+        Some(tx) => match tx.try_send(data) {
+            Ok(()) => return,
+            Err(TrySendError::Full(_)) => {
+                state.write_each(|run| {
+                    if !run.log_metrics.is_empty() {
+                        run.log_ingest.dropped_lines += 1;
+                    }
+                });
+                return;
+            }
+            Err(TrySendError::Disconnected(data)) => data,
+        },
+        None => data,
+    };
+    process_console_log_payload(app, state.inner(), &data);
+}
+
+// Remember which extension fed the browser-mode sessions (saved as `collection.websocket_clients`).
+fn note_client(state: &CollectionState, client: &WsClientInfo) {
+    let known = |run: &ActiveRun| run.mode != "browser" || run.ws_clients.iter().any(|c| c.id == client.id);
+    if state.read_each(known).contains(&false) {
+        state.write_each(|run| {
+            if !known(run) {
+                run.ws_clients.push(client.clone());
+            }
+        });
+    }
+}
+
+/// Stop accepting and shut down every extension socket (app exit). Their reader threads see the
+/// closed socket and exit.
+pub fn close_connections<R: tauri::Runtime>(app_handle: &AppHandle<R>) {
+    let server_state: State<IngestServerState> = app_handle.state();
+    server_state.closing.store(true, Ordering::SeqCst);
+    for (_, socket) in safe_lock(&server_state.ws_streams).drain() {
+        let _ = socket.shutdown(std::net::Shutdown::Both);
+    }
+}
+
 pub fn start_server(app_handle: AppHandle) {
     thread::spawn(move || {
         // Listen on localhost only for security.
-        // Dev-friendly: if 23333 is busy, fall back to 23334/23335... and print the actual port.
-        let (listener, port) = match bind_ws_listener_with_fallback() {
+        // Dev-friendly: if the base port is busy, fall back to base+1, base+2... and print the actual port.
+        let base = Settings::load(app_handle.state::<Database>().inner()).ws_port_base;
+        let (listener, port) = match bind_ws_listener_with_fallback(base) {
             Some(x) => x,
             None => {
-                eprintln!("Failed to bind WebSocket server on 127.0.0.1:23333..");
+                log_error!("Failed to bind WebSocket server on 127.0.0.1:{}..", base);
                 return;
             }
         };
 
-        println!("WebSocket Server listening on 127.0.0.1:{}", port);
+        log_info!("WebSocket Server listening on 127.0.0.1:{}", port);
+        let server_state: State<IngestServerState> = app_handle.state();
+        *safe_lock(&server_state.ws_port) = Some(port);
+        *safe_lock(&server_state.log_queue) = Some(spawn_log_worker(app_handle.clone()));
         let _ = app_handle.emit("ws-server-port", port);
-        
+
         for stream in listener.incoming() {
+            if server_state.closing.load(Ordering::SeqCst) {
+                break;
+            }
             if let Ok(stream) = stream {
                 let app = app_handle.clone();
-                
+
                 thread::spawn(move || {
                     if let Ok(mut websocket) = accept(stream) {
-                        println!("New Extension Connection!");
-                        
+                        let Some(client) = handshake(&app, &mut websocket) else { return };
+                        log_info!(
+                            "New Extension Connection (protocol {}, extension {})",
+                            client.protocol_version,
+                            client.extension_version.as_deref().unwrap_or("unknown")
+                        );
+                        let server_state: State<IngestServerState> = app.state();
+                        safe_lock(&server_state.ws_clients).push(client.clone());
+                        safe_lock(&server_state.ws_last_message).insert(client.id.clone(), Utc::now());
+                        if let Ok(socket) = websocket.get_ref().try_clone() {
+                            safe_lock(&server_state.ws_streams).insert(client.id.clone(), socket);
+                        }
+                        let state: State<CollectionState> = app.state();
+                        append_run_event(
+                            state.inner(),
+                            RunEventKind::SourceConnected,
+                            None,
+                            json!({ "source": "websocket", "extension_version": client.extension_version }),
+                        );
+
                         loop {
                             match websocket.read() {
                                 Ok(msg) => {
+                                    safe_lock(&server_state.ws_last_message).insert(client.id.clone(), Utc::now());
                                     if msg.is_text() || msg.is_binary() {
                                         if let Ok(text) = msg.to_text() {
                                             if let Ok(data) = serde_json::from_str::<Value>(text) {
+                                                let state: State<CollectionState> = app.state();
                                                 if data["type"] == "console_log" {
-                                                    // Log parsing logic
-                                                    let log_data = &data["data"];
-                                                    let content = log_data["content"].as_str().unwrap_or("");
-                                                    let pid = log_data["pid"].as_u64().unwrap_or(0) as u32;
-                                                    let ts_ms = log_data["timestamp"].as_i64().unwrap_or(Utc::now().timestamp_millis());
-                                                    let timestamp = Utc.timestamp_millis_opt(ts_ms).unwrap();
-
-                                                    let state: State<CollectionState> = app.state();
-                                                    let configs = safe_lock(&state.inner().log_metrics);
-                                                    
-                                                    for (cfg, re) in configs.iter() {
-                                                        if let Some(caps) = re.captures(content) {
-                                                            // Assume the first capture group is the value
-                                                            if let Some(val_match) = caps.get(1) {
-                                                                if let Ok(val) = val_match.as_str().parse::<f64>() {
-                                                                    // Use configured PID if present, otherwise use log PID
-                                                                    let effective_pid = cfg.target_pid.unwrap_or(pid);
-                                                                    
-                                                                    push_custom_metric(&app, state.inner(), effective_pid, timestamp, cfg.name.clone(), val);
-                                                                    // println!("Captured Custom Metric: {} = {} (PID {})", cfg.name, val, effective_pid);
-                                                                }
-                                                            }
-                                                        }
-                                                    }
+                                                    queue_console_log(&app, data);
                                                 } else {
-                                                    let state: State<CollectionState> = app.state();
-                                                    process_websocket_metric_payload(&app, data, state.inner());
+                                                    process_websocket_metric_payload(&app, data, state.inner(), &client.id);
                                                 }
+                                                note_client(state.inner(), &client);
                                             }
                                         }
                                     }
                                 }
                                 Err(_) => {
-                                    println!("Extension Disconnected");
+                                    log_info!("Extension Disconnected");
+                                    safe_lock(&server_state.ws_clients).retain(|c| c.id != client.id);
+                                    safe_lock(&server_state.ws_streams).remove(&client.id);
+                                    safe_lock(&server_state.ws_last_message).remove(&client.id);
+                                    let state: State<CollectionState> = app.state();
+                                    append_run_event(state.inner(), RunEventKind::SourceDisconnected, None, json!({ "source": "websocket" }));
                                     break;
                                 }
                             }
@@ -103,3 +467,34 @@ pub fn start_server(app_handle: AppHandle) {
     });
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(id: &str) -> WsClientInfo {
+        WsClientInfo {
+            id: id.to_string(),
+            connected_at: Utc::now().to_rfc3339(),
+            protocol_version: 1,
+            extension_version: None,
+            capabilities: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn extension_status_counts_connected_and_recently_heard_clients() {
+        let state = IngestServerState::new();
+        let empty = state.extension_status();
+        assert_eq!((empty.connected, empty.active, empty.last_message_at), (0, 0, None));
+
+        let heard = Utc::now();
+        safe_lock(&state.ws_clients).extend([client("a"), client("b"), client("c")]);
+        safe_lock(&state.ws_last_message).extend([
+            ("a".to_string(), heard),
+            ("b".to_string(), heard - EXTENSION_ACTIVE_WINDOW - chrono::TimeDelta::seconds(1)),
+        ]);
+        let status = state.extension_status();
+        assert_eq!((status.connected, status.active), (3, 1));
+        assert_eq!(status.last_message_at, Some(heard.to_rfc3339()));
+    }
+}
@@ -1,6 +1,6 @@
 import { useState } from "react";
 import { Routes, Route, Link, useLocation } from 'react-router-dom';
-import { LayoutDashboard, FileText, GitCompare, Sun, Moon, Info, X, Github, ExternalLink } from "lucide-react";
+import { LayoutDashboard, FileText, GitCompare, Settings as SettingsIcon, Sun, Moon, Info, X, Github, ExternalLink } from "lucide-react";
 import { Dashboard } from "./pages/Dashboard";
 import { Reports } from "./pages/Reports";
 import { ReportDetail } from "./pages/ReportDetail";
@@ -9,6 +9,7 @@ import { Comparisons } from "./pages/Comparisons";
 import { ComparisonDetail } from "./pages/ComparisonDetail";
 import { RetestPreview } from "./pages/RetestPreview";
 import { FloatingWidget } from "./pages/FloatingWidget";
+import { Settings } from "./pages/Settings";
 import { useTheme } from "./theme";
 
 const APP_VERSION = "0.1.0";
@@ -76,6 +77,7 @@ export default function App() {
           <NavLink to="/" icon={LayoutDashboard} label="Dashboard" />
           <NavLink to="/reports" icon={FileText} label="Reports" />
           <NavLink to="/comparisons" icon={GitCompare} label="Comparisons" />
+          <NavLink to="/settings" icon={SettingsIcon} label="Settings" />
         </nav>
 
         {/* About button at bottom */}
@@ -179,6 +181,7 @@ export default function App() {
           <Route path="/compare" element={<ReportCompare />} />
           <Route path="/comparisons" element={<Comparisons />} />
           <Route path="/comparison/:id" element={<ComparisonDetail />} />
+          <Route path="/settings" element={<Settings />} />
           <Route path="/widget" element={<FloatingWidget />} />
         </Routes>
       </div>
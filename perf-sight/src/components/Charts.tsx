@@ -24,6 +24,9 @@ export interface ProcessInfo {
   proc_type: string;
   title?: string;
   url?: string;
+  // macOS only.
+  bundle_id?: string;
+  window_title?: string;
 }
 
 interface ChartsProps {
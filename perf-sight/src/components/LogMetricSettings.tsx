@@ -6,6 +6,8 @@ export interface LogMetricConfig {
   pattern: string;
   unit?: string;
   target_pid?: number;
+  // Named capture group -> metric name (or { name, unit }); several metrics per line.
+  captures?: Record<string, string | { name: string; unit?: string }>;
 }
 
 interface LogMetricSettingsProps {
@@ -51,6 +51,8 @@ export const ProcessList: React.FC<ProcessListProps> = ({
   const filteredProcesses = processes.filter(p => 
     p.name.toLowerCase().includes(filterText.toLowerCase()) || 
     (p.title && p.title.toLowerCase().includes(filterText.toLowerCase())) ||
+    (p.window_title && p.window_title.toLowerCase().includes(filterText.toLowerCase())) ||
+    (p.bundle_id && p.bundle_id.toLowerCase().includes(filterText.toLowerCase())) ||
     getAlias(p.pid).toLowerCase().includes(filterText.toLowerCase()) ||
     p.pid.toString().includes(filterText)
   );
@@ -99,11 +101,13 @@ export const ProcessList: React.FC<ProcessListProps> = ({
                 <div className="min-w-0 flex-1">
                 <div className="font-medium truncate">
                     {(getAlias(p.pid).trim() ? getAlias(p.pid).trim() : (p.title || p.name))}
+                    {p.window_title && p.window_title !== p.title && <span className="opacity-70"> — {p.window_title}</span>}
                 </div>
                 <div className="text-xs opacity-60 truncate flex gap-2 items-center">
                     <span>{p.pid}</span>
                     {p.url && <span className="max-w-[200px] truncate" title={p.url}>• {p.url}</span>}
                     {!p.url && p.proc_type !== 'Browser' && <span>• {p.proc_type}</span>}
+                    {p.bundle_id && <span className="truncate" title={p.bundle_id}>• {p.bundle_id}</span>}
                 </div>
                 {isSelected && onRenameProcess ? (
                   <div className="mt-2">
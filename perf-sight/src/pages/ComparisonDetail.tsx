@@ -21,6 +21,8 @@ interface ComparisonDetailData {
   baseline_report_id?: number | null;
   cpu_selections_by_id: Record<string, number[]>;
   mem_selections_by_id: Record<string, number[]>;
+  // "cpu", "memory", "custom:<name>"...; legacy comparisons read as cpu + memory.
+  metric_selections: string[];
   meta?: any;
 }
 
@@ -667,10 +667,23 @@ export const Comparisons: React.FC = () => {
                     onClick={async () => {
                       try {
                         setIsFolderOp(true);
-                        const newPath = (await invoke("rename_comparison_folder", {
-                          path: folderModal.currentPath,
-                          newName: folderNameDraft.trim(),
-                        } as any)) as string;
+                        const rename = (onConflict?: "merge") =>
+                          invoke("rename_comparison_folder", {
+                            path: folderModal.currentPath,
+                            newName: folderNameDraft.trim(),
+                            onConflict,
+                          } as any) as Promise<string>;
+                        let newPath: string;
+                        try {
+                          newPath = await rename();
+                        } catch (e: any) {
+                          if (e?.kind !== "folder_exists") throw e;
+                          const ok = confirm(
+                            `"${e.path}" already exists (${e.reports} items, ${e.folders} subfolders). Merge this folder into it?`
+                          );
+                          if (!ok) return;
+                          newPath = await rename("merge");
+                        }
                         setSelectedFolder(normFolder(newPath));
                         await loadComparisons();
                         await loadFolders();
@@ -395,7 +395,10 @@ export const Dashboard: React.FC = () => {
   const handleStop = async () => {
     try {
       if (!isMocking) {
-        await invoke("stop_collection");
+        const result: any = await invoke("stop_collection");
+        if (result && !result.saved) {
+          console.warn("No report saved:", result.reasons);
+        }
       }
       setIsCollecting(false);
       setIsMocking(false);
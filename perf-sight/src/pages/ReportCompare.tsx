@@ -24,7 +24,7 @@ interface AnalysisSummary {
 interface AnalysisReport {
   score: number;
   summary: AnalysisSummary;
-  insights: string[];
+  insights: Array<{ code: string; severity: string; message: string }>;
 }
 
 interface ReportDetailData {
@@ -644,10 +644,33 @@ export const ReportCompare: React.FC = () => {
   const baseline = baselineId != null ? reportById.get(baselineId) : null;
   const targets = reports.filter((r) => baselineId == null || r.id !== baselineId);
 
+  // Largest share of the run any monitored process spent backgrounded (macOS runs only).
+  const backgroundFraction = (r: any): number | null => {
+    const roles = r.meta?.collection?.process_roles;
+    if (!roles || typeof roles !== "object") return null;
+    const fractions = Object.values(roles)
+      .map((v: any) => v?.background_fraction)
+      .filter((v): v is number => typeof v === "number");
+    return fractions.length ? Math.max(...fractions) : null;
+  };
+
+  // Memory bases recorded in the report's samples, e.g. "rss" or "private_cdp".
+  const memoryBases = (r: any): string => {
+    const bases = new Set<string>();
+    (r.metrics ?? []).forEach((b: any) =>
+      Object.values(b?.metrics ?? {}).forEach((m: any) => {
+        if (typeof m?.memory_basis === "string") bases.add(m.memory_basis);
+      })
+    );
+    return Array.from(bases).sort().join("+");
+  };
+
   const warnings: string[] = [];
   if (baseline) {
     const baseMode = baseline.meta?.collection?.mode;
     const baseInterval = baseline.meta?.collection?.interval_ms;
+    const baseBackground = backgroundFraction(baseline);
+    const baseBasis = memoryBases(baseline);
     for (const r of targets) {
       const m = r.meta?.collection?.mode;
       const it = r.meta?.collection?.interval_ms;
@@ -665,6 +688,20 @@ export const ReportCompare: React.FC = () => {
           `Interval mismatch: baseline is ${baseInterval}ms but report #${r.id} is ${it}ms.`
         );
       }
+      const basis = memoryBases(r);
+      if (baseBasis && basis && baseBasis !== basis) {
+        warnings.push(
+          `Memory basis mismatch: baseline memory is ${baseBasis} but report #${r.id} is ${basis}. Memory numbers are not comparable.`
+        );
+      }
+      const bg = backgroundFraction(r);
+      if (baseBackground != null && bg != null && Math.abs(baseBackground - bg) >= 0.2) {
+        warnings.push(
+          `Foreground/background mismatch: processes were backgrounded for up to ${Math.round(
+            baseBackground * 100
+          )}% of the baseline run but ${Math.round(bg * 100)}% of report #${r.id}. CPU numbers may not be comparable.`
+        );
+      }
     }
   }
 
@@ -1237,7 +1274,9 @@ export const ReportCompare: React.FC = () => {
           const selCpu = cpuSelById[r.id] ?? [];
           const selMem = memSelById[r.id] ?? [];
           const perPid = perPidSummariesById[r.id] ?? [];
-          const insights: string[] = Array.isArray(r.analysis?.insights) ? r.analysis!.insights : [];
+          const insights: string[] = Array.isArray(r.analysis?.insights)
+            ? r.analysis!.insights.map((i) => i.message)
+            : [];
           return (
             <div
               key={`detail_${r.id}`}
@@ -21,6 +21,25 @@ import jsPDF from "jspdf";
 import html2canvas from "html2canvas";
 import { buildReportPdfDataUri } from "../utils/bulkExport";
 
+type InsightSeverity = "info" | "warning" | "critical";
+
+interface Insight {
+  code: string;
+  severity: InsightSeverity;
+  message: string;
+  params?: Record<string, unknown>;
+}
+
+const severityLabel = (s: InsightSeverity) =>
+  s === "critical" ? "Critical" : s === "warning" ? "Warning" : "Info";
+
+const severityClass = (s: InsightSeverity) =>
+  s === "critical"
+    ? "text-rose-700 dark:text-rose-300"
+    : s === "warning"
+    ? "text-amber-700 dark:text-amber-300"
+    : "text-slate-600 dark:text-slate-300";
+
 interface AnalysisReport {
   score: number;
   summary: {
@@ -50,6 +69,8 @@ interface AnalysisReport {
     cpu_share: number;
     avg_mem_mb: number;
     mem_share: number;
+    samples?: number;
+    coverage_pct?: number;
   }>;
   top_mem?: Array<{
     pid: number;
@@ -57,8 +78,16 @@ interface AnalysisReport {
     cpu_share: number;
     avg_mem_mb: number;
     mem_share: number;
+    samples?: number;
+    coverage_pct?: number;
+  }>;
+  insights: Insight[];
+  phases?: Array<{
+    name: string;
+    samples: number;
+    duration_ms: number;
+    summary: { avg_cpu: number; p95_cpu: number; avg_mem_mb: number; max_mem_mb: number };
   }>;
-  insights: string[];
 }
 
 interface ReportDetailData {
@@ -760,8 +789,8 @@ export const ReportDetail: React.FC = () => {
       }
 
       addSectionTitle("Insights");
-      const insights: string[] = Array.isArray(report.analysis?.insights)
-        ? (report.analysis?.insights as any)
+      const insights: Insight[] = Array.isArray(report.analysis?.insights)
+        ? report.analysis!.insights
         : [];
       if (insights.length === 0) {
         addKV("Result", "No issues detected.");
@@ -770,7 +799,10 @@ export const ReportDetail: React.FC = () => {
         pdf.setFontSize(10);
         pdf.setTextColor(226, 232, 240);
         insights.slice(0, 12).forEach((t) => {
-          const lines = pdf.splitTextToSize(`• ${t}`, W - marginX * 2);
+          const lines = pdf.splitTextToSize(
+            `• [${severityLabel(t.severity)}] ${t.message}`,
+            W - marginX * 2
+          );
           pdf.text(lines, marginX, y);
           y += lines.length * 5;
           if (y > H - 18) {
@@ -1223,6 +1255,33 @@ export const ReportDetail: React.FC = () => {
               </div>
             </div>
 
+            {(report.meta?.collection?.data_sources?.length ?? 0) > 0 && (
+              <div className="mt-4 bg-slate-50 border border-slate-200 rounded-lg p-3 dark:bg-slate-950/50 dark:border-slate-800">
+                <div className="text-xs text-slate-500 mb-2">Data Sources</div>
+                <div className="space-y-1 text-sm">
+                  {report.meta.collection.data_sources.map((s: any) => (
+                    <div key={s.source} className="flex justify-between gap-3">
+                      <span className="text-slate-400">
+                        {s.source} ({s.backend})
+                      </span>
+                      <span className="tabular-nums text-slate-900 dark:text-slate-200">
+                        {[
+                          `${s.samples} samples`,
+                          s.version && `v${s.version}`,
+                          s.ws_port != null && `ws :${s.ws_port}`,
+                          s.http_port != null && `http :${s.http_port}`,
+                          s.extension_versions?.length &&
+                            `extension ${s.extension_versions.join(", ")}`,
+                        ]
+                          .filter(Boolean)
+                          .join(" · ")}
+                      </span>
+                    </div>
+                  ))}
+                </div>
+              </div>
+            )}
+
             {report.meta?.test_context && (
               <div className="mt-4 bg-slate-50 border border-slate-200 rounded-lg p-3 dark:bg-slate-950/50 dark:border-slate-800">
                 <div className="text-xs text-slate-500 mb-2">Test Context</div>
@@ -1538,10 +1597,11 @@ export const ReportDetail: React.FC = () => {
                   {report.analysis.insights.map((insight, i) => (
                     <li
                       key={i}
-                      className="text-sm text-rose-700 flex gap-2 items-start dark:text-rose-300"
+                      className={`text-sm flex gap-2 items-start ${severityClass(insight.severity)}`}
+                      title={insight.code}
                     >
                       <AlertTriangle className="w-4 h-4 shrink-0 mt-0.5" />
-                      <span>{insight}</span>
+                      <span>{insight.message}</span>
                     </li>
                   ))}
                 </ul>
@@ -1550,6 +1610,40 @@ export const ReportDetail: React.FC = () => {
           </div>
         )}
 
+        {report.analysis?.phases && report.analysis.phases.length > 0 && (
+          <div className="bg-white border border-slate-200 p-5 rounded-xl dark:bg-slate-900 dark:border-slate-800">
+            <div className="text-sm text-slate-500 uppercase font-bold mb-3">
+              Phases
+            </div>
+            <table className="w-full text-sm">
+              <thead>
+                <tr className="text-left text-slate-500">
+                  <th className="py-1">Phase</th>
+                  <th className="py-1">Duration</th>
+                  <th className="py-1">Samples</th>
+                  <th className="py-1">Avg CPU</th>
+                  <th className="py-1">P95 CPU</th>
+                  <th className="py-1">Avg Mem</th>
+                  <th className="py-1">Max Mem</th>
+                </tr>
+              </thead>
+              <tbody>
+                {report.analysis.phases.map((p) => (
+                  <tr key={p.name} className="border-t border-slate-100 dark:border-slate-800">
+                    <td className="py-1 font-medium">{p.name}</td>
+                    <td className="py-1">{(p.duration_ms / 1000).toFixed(1)}s</td>
+                    <td className="py-1">{p.samples}</td>
+                    <td className="py-1">{p.summary.avg_cpu.toFixed(1)}%</td>
+                    <td className="py-1">{p.summary.p95_cpu.toFixed(1)}%</td>
+                    <td className="py-1">{p.summary.avg_mem_mb.toFixed(0)} MB</td>
+                    <td className="py-1">{p.summary.max_mem_mb.toFixed(0)} MB</td>
+                  </tr>
+                ))}
+              </tbody>
+            </table>
+          </div>
+        )}
+
         {report.analysis &&
           ((report.analysis.top_cpu && report.analysis.top_cpu.length > 0) ||
             (report.analysis.top_mem &&
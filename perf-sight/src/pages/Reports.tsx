@@ -1,5 +1,6 @@
 import React, { useEffect, useMemo, useState } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import { useLocation, useNavigate } from "react-router-dom";
 import { buildReportPdfDataUri } from "../utils/bulkExport";
 import {
@@ -78,6 +79,7 @@ export const Reports: React.FC = () => {
 
   const [isImporting, setIsImporting] = useState(false);
   const [isExportingBundle, setIsExportingBundle] = useState(false);
+  const [bundleProgress, setBundleProgress] = useState<{ index: number; total: number } | null>(null);
 
   const [knownTags, setKnownTags] = useState<TagStat[]>([]);
   const [tagQuery, setTagQuery] = useState("");
@@ -272,6 +274,13 @@ export const Reports: React.FC = () => {
         // ignore
       }
     })();
+    // Sparklines of older reports are computed in the background after the first listing.
+    const unlisten = listen("report-sparklines-updated", () => {
+      loadReports().catch(console.error);
+    });
+    return () => {
+      unlisten.then((f) => f());
+    };
   }, []);
 
   // If reports list changes (e.g., delete/import), drop any selected IDs that no longer exist.
@@ -334,16 +343,26 @@ export const Reports: React.FC = () => {
         });
         items.push({ report_id: id, pdf_base64: pdf });
       }
-      const outPath = (await invoke("export_reports_bundle_zip", {
-        items,
-        filename: null,
-      })) as string;
-      alert(`Exported ZIP:\n${outPath}`);
-    } catch (e) {
+      const unlisten = await listen<{ index: number; total: number }>(
+        "bundle-export-progress",
+        (event) => setBundleProgress({ index: event.payload.index, total: event.payload.total })
+      );
+      try {
+        const outPath = (await invoke("export_reports_bundle_zip", {
+          items,
+          filename: null,
+        })) as string;
+        alert(`Exported ZIP:\n${outPath}`);
+      } finally {
+        unlisten();
+      }
+    } catch (e: any) {
+      if (e?.kind === "cancelled") return;
       console.error("Export ZIP failed", e);
       alert("Failed to export ZIP");
     } finally {
       setIsExportingBundle(false);
+      setBundleProgress(null);
     }
   };
 
@@ -801,9 +820,10 @@ export const Reports: React.FC = () => {
                           }
                         } else if (data.schema_version === 1 && data.report) {
                           // Single dataset import
-                          const newId = (await invoke("import_report_dataset", {
+                          const item = (await invoke("import_report_dataset", {
                             datasetJson: text,
-                          })) as number;
+                          })) as { status: string; report_id: number };
+                          const newId = item.report_id;
                           await loadReports();
                           await loadFolders();
                           navigate(`/report/${newId}`, { state: { fromReports: true } });
@@ -941,8 +961,21 @@ export const Reports: React.FC = () => {
                   className="bg-slate-900 hover:bg-slate-800 disabled:opacity-60 text-white px-3 py-1.5 rounded-md text-sm font-medium transition-colors dark:bg-slate-800 dark:hover:bg-slate-700"
                   title="Export selected reports as a single ZIP (dataset + PDF for each report)"
                 >
-                  {isExportingBundle ? "Exporting…" : "Export…"}
+                  {isExportingBundle
+                    ? bundleProgress
+                      ? `Exporting ${bundleProgress.index}/${bundleProgress.total}…`
+                      : "Exporting…"
+                    : "Export…"}
                 </button>
+                {bundleProgress && (
+                  <button
+                    onClick={() => invoke("cancel_bundle_export")}
+                    className="bg-slate-200 hover:bg-slate-300 text-slate-800 px-3 py-1.5 rounded-md text-sm font-medium transition-colors dark:bg-slate-700 dark:hover:bg-slate-600 dark:text-slate-100"
+                    title="Stop the export after the current report"
+                  >
+                    Cancel
+                  </button>
+                )}
                 <button
                   onClick={handleCreateComparison}
                   disabled={selectedIds.size < 2}
@@ -1281,10 +1314,23 @@ export const Reports: React.FC = () => {
                     onClick={async () => {
                       try {
                         setIsFolderOp(true);
-                        const newPath = (await invoke("rename_folder", {
-                          path: folderModal.currentPath,
-                          newName: folderNameDraft.trim(),
-                        })) as string;
+                        const rename = (onConflict?: "merge") =>
+                          invoke("rename_folder", {
+                            path: folderModal.currentPath,
+                            newName: folderNameDraft.trim(),
+                            onConflict,
+                          }) as Promise<string>;
+                        let newPath: string;
+                        try {
+                          newPath = await rename();
+                        } catch (e: any) {
+                          if (e?.kind !== "folder_exists") throw e;
+                          const ok = confirm(
+                            `"${e.path}" already exists (${e.reports} items, ${e.folders} subfolders). Merge this folder into it?`
+                          );
+                          if (!ok) return;
+                          newPath = await rename("merge");
+                        }
                         setSelectedFolder(normFolder(newPath));
                         await loadReports();
                         await loadFolders();
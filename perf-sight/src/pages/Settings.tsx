@@ -0,0 +1,180 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { Database, RefreshCw, Wrench, CheckCircle2, AlertTriangle } from "lucide-react";
+
+interface DatabaseStats {
+  db_bytes: number;
+  free_bytes: number;
+  reports: number;
+  comparisons: number;
+  reports_bytes: number;
+  run_chunks_bytes: number;
+}
+
+interface MaintenanceReport {
+  ran_at: string;
+  integrity: string[];
+  size_before_bytes: number;
+  size_after_bytes: number;
+  fixes: string[];
+  vacuumed: boolean;
+}
+
+const formatBytes = (bytes: number) => {
+  if (bytes >= 1024 * 1024 * 1024) return `${(bytes / 1024 / 1024 / 1024).toFixed(2)} GB`;
+  if (bytes >= 1024 * 1024) return `${(bytes / 1024 / 1024).toFixed(1)} MB`;
+  if (bytes >= 1024) return `${(bytes / 1024).toFixed(1)} KB`;
+  return `${bytes} B`;
+};
+
+const Stat = ({ label, value }: { label: string; value: string }) => (
+  <div className="bg-slate-50 dark:bg-slate-800/50 rounded-xl p-4">
+    <div className="text-xs text-slate-500 mb-1">{label}</div>
+    <div className="font-mono font-bold text-slate-700 dark:text-slate-300">{value}</div>
+  </div>
+);
+
+export const Settings = () => {
+  const [stats, setStats] = useState<DatabaseStats | null>(null);
+  const [last, setLast] = useState<MaintenanceReport | null>(null);
+  const [autoMaintenance, setAutoMaintenance] = useState(false);
+  const [running, setRunning] = useState(false);
+  const [error, setError] = useState<string | null>(null);
+
+  const load = async () => {
+    try {
+      const [s, l, settings] = await Promise.all([
+        invoke("get_database_stats") as Promise<DatabaseStats>,
+        invoke("get_last_maintenance") as Promise<MaintenanceReport | null>,
+        invoke("get_app_settings") as Promise<any>,
+      ]);
+      setStats(s);
+      setLast(l);
+      setAutoMaintenance(!!settings?.auto_maintenance);
+    } catch (e: any) {
+      setError(String(e?.message ?? e));
+    }
+  };
+
+  useEffect(() => {
+    load();
+    // Startup maintenance runs in the background; refresh when it is done.
+    const unlisten = listen("database-maintenance-finished", () => load());
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, []);
+
+  const runMaintenance = async () => {
+    setRunning(true);
+    setError(null);
+    try {
+      const report = (await invoke("maintain_database")) as MaintenanceReport;
+      setLast(report);
+      setStats((await invoke("get_database_stats")) as DatabaseStats);
+    } catch (e: any) {
+      setError(String(e?.message ?? e));
+    } finally {
+      setRunning(false);
+    }
+  };
+
+  const toggleAutoMaintenance = async (enabled: boolean) => {
+    setError(null);
+    try {
+      const settings = (await invoke("get_app_settings")) as any;
+      const saved = (await invoke("update_app_settings", {
+        settings: { ...settings, auto_maintenance: enabled },
+      })) as any;
+      setAutoMaintenance(!!saved?.auto_maintenance);
+    } catch (e: any) {
+      setError(String(e?.message ?? e));
+    }
+  };
+
+  const healthy = last && last.integrity.length === 1 && last.integrity[0] === "ok";
+
+  return (
+    <div className="h-full overflow-y-auto p-6">
+      <h1 className="text-2xl font-bold mb-6">Settings</h1>
+
+      <section className="bg-white dark:bg-slate-900 border border-slate-200 dark:border-slate-800 rounded-2xl p-6 max-w-3xl space-y-4">
+        <div className="flex items-center justify-between">
+          <div className="flex items-center gap-2">
+            <Database className="w-5 h-5 text-indigo-600 dark:text-indigo-400" />
+            <h2 className="text-lg font-semibold">Database</h2>
+          </div>
+          <button
+            type="button"
+            onClick={load}
+            className="p-2 rounded-lg hover:bg-slate-100 dark:hover:bg-slate-800 transition-colors"
+            title="Refresh"
+          >
+            <RefreshCw className="w-4 h-4" />
+          </button>
+        </div>
+
+        {stats && (
+          <div className="grid grid-cols-2 lg:grid-cols-4 gap-4">
+            <Stat label="File size" value={formatBytes(stats.db_bytes)} />
+            <Stat label="Reclaimable" value={formatBytes(stats.free_bytes)} />
+            <Stat label="Reports" value={String(stats.reports)} />
+            <Stat label="Comparisons" value={String(stats.comparisons)} />
+          </div>
+        )}
+
+        <div className="flex items-center justify-between gap-4">
+          <label className="flex items-center gap-2 text-sm text-slate-700 dark:text-slate-300">
+            <input
+              type="checkbox"
+              checked={autoMaintenance}
+              onChange={(e) => toggleAutoMaintenance(e.target.checked)}
+            />
+            Run maintenance at startup once a month
+          </label>
+          <button
+            type="button"
+            onClick={runMaintenance}
+            disabled={running}
+            className="flex items-center gap-2 px-4 py-2 rounded-lg bg-indigo-600 hover:bg-indigo-500 disabled:opacity-50 text-white text-sm font-medium transition-colors"
+            title="Integrity check, orphan cleanup and VACUUM (not while collecting)"
+          >
+            <Wrench className="w-4 h-4" />
+            {running ? "Maintaining…" : "Run maintenance"}
+          </button>
+        </div>
+
+        {error && <div className="text-sm text-red-600 dark:text-red-400">{error}</div>}
+
+        {last && (
+          <div className="bg-slate-50 dark:bg-slate-800/50 rounded-xl p-4 text-sm space-y-2">
+            <div className="flex items-center gap-2">
+              {healthy ? (
+                <CheckCircle2 className="w-4 h-4 text-emerald-600" />
+              ) : (
+                <AlertTriangle className="w-4 h-4 text-amber-600" />
+              )}
+              <span className="font-medium">
+                Last run {new Date(last.ran_at).toLocaleString()}:{" "}
+                {healthy ? "integrity ok" : `${last.integrity.length} integrity problem(s), nothing changed`}
+              </span>
+            </div>
+            {last.vacuumed && (
+              <div className="text-slate-600 dark:text-slate-400">
+                {formatBytes(last.size_before_bytes)} → {formatBytes(last.size_after_bytes)}
+              </div>
+            )}
+            {(healthy ? last.fixes : last.integrity).length > 0 && (
+              <ul className="text-slate-600 dark:text-slate-400 space-y-1">
+                {(healthy ? last.fixes : last.integrity).map((line, i) => (
+                  <li key={i}>• {line}</li>
+                ))}
+              </ul>
+            )}
+          </div>
+        )}
+      </section>
+    </div>
+  );
+};
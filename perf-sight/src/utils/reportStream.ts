@@ -0,0 +1,101 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen, UnlistenFn } from "@tauri-apps/api/event";
+
+type StreamedReportDetail = {
+  id: number;
+  created_at: string;
+  title: string;
+  metrics: any[];
+  analysis?: any;
+  meta?: any;
+};
+
+type ChunkEvent = {
+  stream_id: string;
+  report_id: number;
+  chunk_index: number;
+  total_chunks: number;
+  metrics: any[];
+};
+
+type DoneEvent = {
+  stream_id: string;
+  report_id: number;
+  status: "completed" | "cancelled" | "failed";
+  created_at?: string;
+  title?: string;
+  meta?: any;
+  analysis?: any;
+  error?: string;
+};
+
+/**
+ * Load a report through get_report_detail_streamed, so very large reports don't arrive as one
+ * IPC response. `onChunk` sees the metrics assembled so far after every chunk.
+ * Returns the full report plus a `cancel` that aborts the stream.
+ */
+export function streamReportDetail(
+  id: number,
+  onChunk?: (metricsSoFar: any[], chunkIndex: number, totalChunks: number) => void,
+  chunkSize?: number
+): { done: Promise<StreamedReportDetail>; cancel: () => void } {
+  let streamId: string | null = null;
+  let cancelled = false;
+  const unlisten: UnlistenFn[] = [];
+  const cleanup = () => unlisten.splice(0).forEach((u) => u());
+
+  const done = new Promise<StreamedReportDetail>(async (resolve, reject) => {
+    const metrics: any[] = [];
+    // Events can arrive before invoke resolves; hold them until the stream id is known.
+    const early: ChunkEvent[] = [];
+    const onChunkEvent = (c: ChunkEvent) => {
+      metrics.push(...c.metrics);
+      onChunk?.(metrics, c.chunk_index, c.total_chunks);
+    };
+    let earlyDone: DoneEvent | null = null;
+    const onDone = (d: DoneEvent) => {
+      cleanup();
+      if (d.status === "completed") {
+        resolve({
+          id: d.report_id,
+          created_at: d.created_at ?? "",
+          title: d.title ?? "",
+          metrics,
+          analysis: d.analysis,
+          meta: d.meta,
+        });
+      } else {
+        reject(new Error(d.error ?? `Report stream ${d.status}`));
+      }
+    };
+
+    try {
+      unlisten.push(
+        await listen<ChunkEvent>("report-metrics-chunk", (e) => {
+          if (streamId === null) early.push(e.payload);
+          else if (e.payload.stream_id === streamId) onChunkEvent(e.payload);
+        })
+      );
+      unlisten.push(
+        await listen<DoneEvent>("report-detail-streamed", (e) => {
+          if (streamId === null) earlyDone = e.payload;
+          else if (e.payload.stream_id === streamId) onDone(e.payload);
+        })
+      );
+      streamId = (await invoke("get_report_detail_streamed", { id, chunkSize })) as string;
+      early.filter((c) => c.stream_id === streamId).forEach(onChunkEvent);
+      const finished = earlyDone as DoneEvent | null;
+      if (finished && finished.stream_id === streamId) onDone(finished);
+      if (cancelled) await invoke("abort_report_stream", { streamId });
+    } catch (e) {
+      cleanup();
+      reject(e);
+    }
+  });
+
+  const cancel = () => {
+    cancelled = true;
+    if (streamId !== null) invoke("abort_report_stream", { streamId }).catch(() => {});
+  };
+  return { done, cancel };
+}